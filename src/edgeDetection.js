@@ -9,7 +9,10 @@ import init, {
   calculate_gradients as wasmGradients,
   dilate as wasmDilate,
   non_maximum_suppression as wasmMaximumSuppression,
+  canny_edge_detector as wasmCanny,
   canny_edge_detector_full as wasmFullCanny,
+  canny_edge_detector_debug as wasmCannyDebug,
+  CannyOptions,
   hysteresis_thresholding as wasmHysteresis
 } from '../wasm_blur/pkg/wasm_blur.js';
 import wasmBase64 from '../wasm_blur/pkg/wasm_blur_bg_base64.js';
@@ -598,15 +601,32 @@ export async function cannyEdgeDetector(input, options = {}) {
     try {
       await initializeWasm();
       const t0w = performance.now();
-      const finalEdges = new Uint8ClampedArray(
-        wasmFullCanny(
-          grayscale, width, height,
-          lowThreshold, highThreshold,
-          kernelSize, sigma,
-          L2gradient,
-          applyDilation, dilationKernelSize
-        )
-      );
+      const cannyOptions = new CannyOptions();
+      let finalEdges;
+      try {
+        cannyOptions.set_thresholds(lowThreshold, highThreshold);
+        cannyOptions.set_blur(kernelSize, sigma);
+        cannyOptions.set_l2_gradient(L2gradient);
+        cannyOptions.set_dilation(applyDilation ? dilationKernelSize : 0);
+        if (storeFullDebug) {
+          // Keeps every intermediate plane, slower than the fused pipeline
+          const stages = wasmCannyDebug(grayscale, width, height, cannyOptions);
+          try {
+            options.debug.blurred = stages.blurred();
+            options.debug.magnitude = stages.magnitude();
+            options.debug.suppressed = stages.suppressed();
+            options.debug.edgeMap = stages.edge_map();
+            options.debug.cannyEdges = new Uint8ClampedArray(stages.canny_edges());
+            finalEdges = new Uint8ClampedArray(stages.edges());
+          } finally {
+            stages.free();
+          }
+        } else {
+          finalEdges = new Uint8ClampedArray(wasmCanny(grayscale, width, height, cannyOptions));
+        }
+      } finally {
+        cannyOptions.free();
+      }
       const t1w = performance.now();
 
       // Record a single combined timing entry. Individual phases ran entirely
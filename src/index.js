@@ -604,7 +604,21 @@ async function detectDocumentInternal(grayscaleData, width, height, scaleFactor,
     };
   }
   
-  const passProfiles = buildDetectionPassProfiles(options);
+  // With `relativeUnits`, sizes are fractions of the image diagonal and are
+  // resolved against the processing frame here.
+  const diagonal = Math.hypot(width, height);
+  const relative = options.relativeUnits === true;
+  // Kernels stay odd so they keep a centre pixel.
+  const toKernelSize = (fraction) => Math.max(3, Math.round(fraction * diagonal) | 1);
+  const passProfiles = buildDetectionPassProfiles(relative && options.dilationKernelSize !== undefined
+    ? { ...options, dilationKernelSize: toKernelSize(options.dilationKernelSize) }
+    : options);
+  const gaussianBlurKernelSize = relative && options.gaussianBlurKernelSize !== undefined
+    ? toKernelSize(options.gaussianBlurKernelSize)
+    : options.gaussianBlurKernelSize;
+  const minArea = relative && options.minArea !== undefined
+    ? (options.minArea * diagonal) ** 2
+    : (options.minArea || 1000) / (scaleFactor * scaleFactor);
   const passResults = [];
 
   const runDetectionPass = async (profile, passIndex) => {
@@ -617,6 +631,7 @@ async function detectDocumentInternal(grayscaleData, width, height, scaleFactor,
       height,
       lowThreshold: profile.lowThreshold,
       highThreshold: profile.highThreshold,
+      kernelSize: gaussianBlurKernelSize,
       dilationKernelSize: profile.dilationKernelSize,
       dilationIterations: profile.dilationIterations,
       applyDilation: profile.applyDilation,
@@ -636,9 +651,10 @@ async function detectDocumentInternal(grayscaleData, width, height, scaleFactor,
 
     let t0 = performance.now();
     const contours = detectDocumentContour(edges, {
-      minArea: (options.minArea || 1000) / (scaleFactor * scaleFactor),
+      minArea,
       width,
-      height
+      height,
+      debug: passDebug._timingsOnly ? undefined : passDebug
     });
     timings.push({ step: `Find Contours${passSuffix}`, ms: (performance.now() - t0).toFixed(2) });
 
@@ -657,7 +673,8 @@ async function detectDocumentInternal(grayscaleData, width, height, scaleFactor,
       },
       contours,
       best,
-      candidates
+      candidates,
+      stages: passDebug._timingsOnly ? null : passDebug
     };
   };
 
@@ -699,6 +716,9 @@ async function detectDocumentInternal(grayscaleData, width, height, scaleFactor,
     debugInfo.passes = passResults.map((pass) => ({
       name: pass.name,
       params: pass.params,
+      // Intermediate planes (blurred, magnitude, suppressed, edgeMap, cannyEdges,
+      // finalEdges) and contours of this pass
+      stages: pass.stages,
       contourCount: pass.contours.length,
       bestCandidate: pass.best
         ? {
@@ -165,12 +165,21 @@ export interface DetectionOptions {
   maxProcessingDimension?: number;
   lowThreshold?: number;
   highThreshold?: number;
+  /** Gaussian blur kernel size before edge detection (odd, default 5). */
+  gaussianBlurKernelSize?: number;
   applyDilation?: boolean;
   dilationKernelSize?: number;
   dilationIterations?: number;
   useWasmHysteresis?: boolean;
   useWasmFullCanny?: boolean;
   minArea?: number;
+  /**
+   * Treat `gaussianBlurKernelSize` and `dilationKernelSize` as fractions of
+   * the image diagonal (rounded to an odd size of at least 3) and `minArea` as
+   * the side of a square in that unit, so settings tuned at one capture
+   * resolution carry over to another.
+   */
+  relativeUnits?: boolean;
   epsilon?: number;
   minDetectionConfidence?: number;
   maxCandidateContours?: number;
@@ -0,0 +1,224 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String};
+
+use crate::clock::now_ms;
+
+/// Timed stages of the `ScanContext` edge pipeline.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineStage {
+    /// Conversion of the input frame to grayscale, pixel formats included
+    Grayscale = 0,
+    /// Blur, gradient, non-maximum suppression and hysteresis
+    Canny = 1,
+    /// Dilation of the edge map; skipped when disabled and in low-power mode
+    Dilation = 2,
+}
+
+const STAGES: usize = 3;
+
+// Totals a `ScanContext` accumulates for `session_report`.
+#[derive(Clone, Default)]
+pub(crate) struct SessionStats {
+    frames_seen: usize,
+    frames_processed: usize,
+    stage_ms: [f64; STAGES],
+    stage_runs: [usize; STAGES],
+    // Clock reading at the end of the previous stage of the current frame
+    stage_start: Option<f64>,
+    // First and latest clock readings of the session
+    first_ms: Option<f64>,
+    last_ms: Option<f64>,
+    detection_attempts: usize,
+    detections: usize,
+    retakes: usize,
+}
+
+impl SessionStats {
+    pub(crate) fn begin_frame(&mut self, processed: bool) {
+        let now = now_ms();
+        self.frames_seen += 1;
+        self.first_ms = self.first_ms.or(now);
+        self.last_ms = now.or(self.last_ms);
+        if processed {
+            self.frames_processed += 1;
+            self.stage_start = now;
+        }
+    }
+
+    // Adds the time since the previous stage ended (or the frame began) to
+    // `stage`.
+    pub(crate) fn end_stage(&mut self, stage: PipelineStage) {
+        let now = now_ms();
+        if let (Some(start), Some(end)) = (self.stage_start, now) {
+            self.stage_ms[stage as usize] += end - start;
+            self.stage_runs[stage as usize] += 1;
+        }
+        self.stage_start = now;
+        self.last_ms = now.or(self.last_ms);
+    }
+
+    pub(crate) fn record_detection(&mut self, found: bool) {
+        self.detection_attempts += 1;
+        self.detections += found as usize;
+    }
+
+    pub(crate) fn record_retake(&mut self) {
+        self.retakes += 1;
+    }
+
+    pub(crate) fn report(&self) -> SessionReport {
+        let average_ms = core::array::from_fn(|i| {
+            if self.stage_runs[i] > 0 {
+                self.stage_ms[i] / self.stage_runs[i] as f64
+            } else {
+                0.0
+            }
+        });
+        let duration_ms = match (self.first_ms, self.last_ms) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0.0,
+        };
+        SessionReport {
+            frames_seen: self.frames_seen,
+            frames_processed: self.frames_processed,
+            average_ms,
+            detection_attempts: self.detection_attempts,
+            detections: self.detections,
+            retakes: self.retakes,
+            duration_ms,
+        }
+    }
+}
+
+/// Aggregate metrics of a capture session, from `ScanContext::session_report`.
+/// Latencies are 0 where no clock is available (`no_std` builds outside the
+/// browser).
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionReport {
+    frames_seen: usize,
+    frames_processed: usize,
+    average_ms: [f64; STAGES],
+    detection_attempts: usize,
+    detections: usize,
+    retakes: usize,
+    duration_ms: f64,
+}
+
+#[wasm_bindgen]
+impl SessionReport {
+    /// Frames handed to the context, including those skipped in low-power mode.
+    #[wasm_bindgen(getter)]
+    pub fn frames_seen(&self) -> usize {
+        self.frames_seen
+    }
+
+    /// Frames that ran the edge pipeline.
+    #[wasm_bindgen(getter)]
+    pub fn frames_processed(&self) -> usize {
+        self.frames_processed
+    }
+
+    /// Mean time of one run of `stage` in milliseconds, 0 if it never ran.
+    pub fn average_latency_ms(&self, stage: PipelineStage) -> f64 {
+        self.average_ms[stage as usize]
+    }
+
+    /// Results recorded with `record_detection`.
+    #[wasm_bindgen(getter)]
+    pub fn detection_attempts(&self) -> usize {
+        self.detection_attempts
+    }
+
+    /// Recorded results that found a quad.
+    #[wasm_bindgen(getter)]
+    pub fn detections(&self) -> usize {
+        self.detections
+    }
+
+    /// Share of the recorded results that found a quad, 0 without any.
+    #[wasm_bindgen(getter)]
+    pub fn detection_success_rate(&self) -> f64 {
+        if self.detection_attempts > 0 {
+            self.detections as f64 / self.detection_attempts as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Retakes reported with `record_retake`.
+    #[wasm_bindgen(getter)]
+    pub fn retakes(&self) -> usize {
+        self.retakes
+    }
+
+    /// Time from the first to the latest frame in milliseconds.
+    #[wasm_bindgen(getter)]
+    pub fn duration_ms(&self) -> f64 {
+        self.duration_ms
+    }
+
+    /// The report as a JSON object with the getter names as keys and the stage
+    /// latencies under `average_latency_ms`, ready to send to an analytics
+    /// backend.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"frames_seen\":{},\"frames_processed\":{},\"average_latency_ms\":{{\"grayscale\":{},\"canny\":{},\
+             \"dilation\":{}}},\"detection_attempts\":{},\"detections\":{},\"detection_success_rate\":{},\
+             \"retakes\":{},\"duration_ms\":{}}}",
+            self.frames_seen,
+            self.frames_processed,
+            self.average_ms[0],
+            self.average_ms[1],
+            self.average_ms[2],
+            self.detection_attempts,
+            self.detections,
+            self.detection_success_rate(),
+            self.retakes,
+            self.duration_ms,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_session_report() {
+        let mut context = ScanContext::new(32, 24);
+        context.set_low_power(true);
+        for _ in 0..6 {
+            context.process_gray_frame(&vec![128u8; 32 * 24]).unwrap();
+        }
+        let quad = [4.0, 4.0, 28.0, 4.0, 28.0, 20.0, 4.0, 20.0];
+        for corners in [&quad[..], &[], &quad[..], &quad[..]] {
+            context.record_detection(corners, 0.8, 0.0).unwrap();
+        }
+        context.record_retake();
+
+        let report = context.session_report();
+        // Low-power mode runs every third frame
+        assert_eq!((report.frames_seen(), report.frames_processed()), (6, 2));
+        assert_eq!((report.detection_attempts(), report.detections(), report.retakes()), (4, 3, 1));
+        assert_eq!(report.detection_success_rate(), 0.75);
+        assert!(report.average_latency_ms(PipelineStage::Canny) > 0.0);
+        // No dilation in low-power mode
+        assert_eq!(report.average_latency_ms(PipelineStage::Dilation), 0.0);
+        assert!(report.duration_ms() >= 0.0);
+        let json = report.to_json();
+        assert!(json.starts_with("{\"frames_seen\":6,\"frames_processed\":2,\"average_latency_ms\":{\"grayscale\":"));
+        let tail = format!("\"detection_success_rate\":0.75,\"retakes\":1,\"duration_ms\":{}}}", report.duration_ms());
+        assert!(json.ends_with(&tail));
+
+        // Clearing the detection history keeps the session totals
+        context.clear_history();
+        assert_eq!(context.session_report().detections(), 3);
+        context.reset_session_stats();
+        assert_eq!(context.session_report().frames_seen(), 0);
+    }
+}
@@ -0,0 +1,155 @@
+// Bump arena for per-frame scratch memory (blur temporaries, gradient rows,
+// hysteresis stack, dilation temp). Stages allocate from it freely during a frame
+// and the whole arena is reset before the next one. After the first frame the
+// arena holds a single chunk sized to the high-water mark, so steady-state frames
+// never touch the allocator and WASM memory stops growing.
+
+use core::cell::{Cell, UnsafeCell};
+use core::mem::{align_of, size_of};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// Plain-old-data element types that can live in the arena (any bit pattern valid,
+/// no drop glue, alignment at most 8).
+pub(crate) trait ArenaPod: Copy + 'static {}
+
+impl ArenaPod for u8 {}
+impl ArenaPod for u16 {}
+impl ArenaPod for u32 {}
+impl ArenaPod for i16 {}
+impl ArenaPod for i32 {}
+impl ArenaPod for f32 {}
+impl ArenaPod for usize {}
+
+const WORD: usize = size_of::<u64>();
+
+// A heap chunk owned through a raw pointer (not a Box) so the arena can keep
+// handing out slices into it while the chunk list itself is being updated.
+struct Chunk {
+    ptr: *mut u64,
+    words: usize,
+}
+
+impl Chunk {
+    fn new(words: usize) -> Chunk {
+        let boxed = vec![0u64; words].into_boxed_slice();
+        Chunk { ptr: Box::into_raw(boxed) as *mut u64, words }
+    }
+
+    fn bytes(&self) -> usize {
+        self.words * WORD
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // Safety: ptr/words come from the boxed slice created in Chunk::new
+        unsafe {
+            drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(self.ptr, self.words)));
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct FrameArena {
+    // Chunk memory never moves once allocated; only the last chunk is bumped into.
+    chunks: UnsafeCell<Vec<Chunk>>,
+    used: Cell<usize>,      // bytes used in the last chunk
+    allocated: Cell<usize>, // bytes handed out since the last reset
+    high_water: Cell<usize>,
+}
+
+impl FrameArena {
+    /// Makes sure the next frame can allocate `bytes` without growing.
+    pub(crate) fn reserve(&mut self, bytes: usize) {
+        let chunks = self.chunks.get_mut();
+        let capacity: usize = chunks.iter().map(Chunk::bytes).sum();
+        if capacity < bytes || chunks.len() > 1 {
+            let words = bytes.max(capacity).div_ceil(WORD);
+            chunks.clear();
+            chunks.push(Chunk::new(words));
+        }
+        self.used.set(0);
+        self.allocated.set(0);
+    }
+
+    /// Releases all allocations. Requires `&mut self`, so no slice handed out
+    /// earlier can still be alive.
+    pub(crate) fn reset(&mut self) {
+        let high_water = self.high_water.get().max(self.allocated.get());
+        self.high_water.set(high_water);
+        self.reserve(high_water);
+    }
+
+    /// Frees every chunk and forgets the high-water mark, so the next frame starts
+    /// from an empty arena.
+    pub(crate) fn release(&mut self) {
+        self.chunks.get_mut().clear();
+        self.used.set(0);
+        self.allocated.set(0);
+        self.high_water.set(0);
+    }
+
+    /// Bytes currently held by the arena's chunks.
+    pub(crate) fn capacity(&mut self) -> usize {
+        self.chunks.get_mut().iter().map(Chunk::bytes).sum()
+    }
+
+    /// Allocates a zeroed slice of `len` elements valid until the next reset.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) fn alloc<T: ArenaPod>(&self, len: usize) -> &mut [T] {
+        debug_assert!(align_of::<T>() <= WORD);
+        let bytes = len * size_of::<T>();
+        // Every allocation starts on a word boundary, so the frame's total is
+        // exactly the size of the single chunk the next reset consolidates to.
+        let span = bytes.next_multiple_of(WORD);
+
+        // Safety: the chunk list only holds raw pointers, chunk memory is never
+        // moved or freed while `&self` is borrowed (reset/release need `&mut self`),
+        // and every allocation covers a disjoint byte range, so slices never alias.
+        unsafe {
+            let chunks = &mut *self.chunks.get();
+            let mut offset = self.used.get();
+            let fits = chunks.last().is_some_and(|c| offset + span <= c.bytes());
+            if !fits {
+                let capacity: usize = chunks.iter().map(Chunk::bytes).sum();
+                chunks.push(Chunk::new((span / WORD).max(capacity / WORD).max(1)));
+                offset = 0;
+            }
+
+            let chunk = chunks.last().unwrap();
+            let ptr = (chunk.ptr as *mut u8).add(offset);
+            core::ptr::write_bytes(ptr, 0, bytes);
+            self.used.set(offset + span);
+            self.allocated.set(self.allocated.get() + span);
+            core::slice::from_raw_parts_mut(ptr as *mut T, len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena_allocations_are_disjoint_and_consolidate() {
+        let mut arena = FrameArena::default();
+        {
+            let a = arena.alloc::<u8>(13);
+            let b = arena.alloc::<f32>(100);
+            a.fill(7);
+            b.fill(1.5);
+            assert!(a.iter().all(|&v| v == 7));
+            assert!(b.iter().all(|&v| v == 1.5));
+        }
+        arena.reset();
+        assert_eq!(arena.chunks.get_mut().len(), 1);
+
+        // The same frame shape now fits in the single consolidated chunk
+        let _a = arena.alloc::<u8>(13);
+        let b = arena.alloc::<f32>(100);
+        assert!(b.iter().all(|&v| v == 0.0));
+        assert_eq!(unsafe { (*arena.chunks.get()).len() }, 1);
+    }
+}
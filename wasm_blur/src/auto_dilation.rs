@@ -0,0 +1,200 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// Sizes the suggestion is chosen from: 3 keeps fine detail, 15 closes gaps of
+// up to 14 pixels.
+const MIN_SIZE: usize = 3;
+const MAX_SIZE: usize = 15;
+// Edge maps covering more than this fraction of the frame are texture or
+// noise; closing would merge them into blobs, so they get the smallest size.
+const DENSE_EDGES: f32 = 0.15;
+// Broken joins per edge pixel below which a map counts as unbroken: a handful
+// of gaps along a page border doesn't need a larger closing.
+const MIN_FRAGMENTATION: f32 = 0.002;
+// Percentile of the measured gaps the suggested size closes.
+const GAP_PERCENTILE: usize = 75;
+
+// Root of pixel `i` in the union-find forest of `labels`, where a non-zero
+// entry is the parent index plus one. Halves the path on the way up.
+fn find(labels: &mut [u32], mut i: usize) -> usize {
+    loop {
+        let parent = labels[i] as usize - 1;
+        if parent == i {
+            return i;
+        }
+        let grandparent = labels[parent] as usize - 1;
+        labels[i] = grandparent as u32 + 1;
+        i = parent;
+    }
+}
+
+// Labels the 8-connected components of `edges`: afterwards every edge pixel
+// holds its component's root index plus one, background pixels 0.
+fn label_components(edges: &[u8], width: usize, labels: &mut [u32]) {
+    for i in 0..edges.len() {
+        labels[i] = if edges[i] > 0 { i as u32 + 1 } else { 0 };
+        if edges[i] == 0 {
+            continue;
+        }
+        let x = i % width;
+        let mut neighbors = [None; 4];
+        if x > 0 {
+            neighbors[0] = Some(i - 1);
+        }
+        if i >= width {
+            neighbors[1] = Some(i - width);
+            if x > 0 {
+                neighbors[2] = Some(i - width - 1);
+            }
+            if x + 1 < width {
+                neighbors[3] = Some(i - width + 1);
+            }
+        }
+        for j in neighbors.into_iter().flatten().filter(|&j| edges[j] > 0) {
+            let (a, b) = (find(labels, i), find(labels, j));
+            labels[a.max(b)] = a.min(b) as u32 + 1;
+        }
+    }
+    for i in 0..edges.len() {
+        if labels[i] > 0 {
+            labels[i] = find(labels, i) as u32 + 1;
+        }
+    }
+}
+
+// Number of edge pixels among the 8 neighbours of (x, y).
+fn neighbor_count(edges: &[u8], width: usize, height: usize, x: usize, y: usize) -> usize {
+    let (x0, x1) = (x.saturating_sub(1), (x + 1).min(width - 1));
+    let (y0, y1) = (y.saturating_sub(1), (y + 1).min(height - 1));
+    (y0..=y1)
+        .flat_map(|ny| (x0..=x1).map(move |nx| (nx, ny)))
+        .filter(|&p| p != (x, y) && edges[p.1 * width + p.0] > 0)
+        .count()
+}
+
+// Chebyshev distance from (x, y) to the nearest edge pixel of another
+// component, searched ring by ring up to `MAX_SIZE`.
+fn gap_at(labels: &[u32], width: usize, height: usize, x: usize, y: usize) -> Option<usize> {
+    let own = labels[y * width + x];
+    (2..=MAX_SIZE).find(|&d| {
+        let (x0, x1) = (x as isize - d as isize, x as isize + d as isize);
+        let (y0, y1) = (y as isize - d as isize, y as isize + d as isize);
+        let other = |nx: isize, ny: isize| {
+            (0..width as isize).contains(&nx) && (0..height as isize).contains(&ny) && {
+                let label = labels[ny as usize * width + nx as usize];
+                label > 0 && label != own
+            }
+        };
+        (x0..=x1).any(|nx| other(nx, y0) || other(nx, y1)) || (y0 + 1..y1).any(|ny| other(x0, ny) || other(x1, ny))
+    })
+}
+
+// `suggest_dilation_size` with the component labels in caller-provided scratch
+// of `width * height` entries.
+pub(crate) fn suggest_dilation_into(edges: &[u8], width: usize, height: usize, labels: &mut [u32]) -> usize {
+    let edge_pixels = edges.iter().filter(|&&e| e > 0).count();
+    if edge_pixels == 0 || edge_pixels as f32 > DENSE_EDGES * edges.len() as f32 {
+        return MIN_SIZE;
+    }
+    label_components(edges, width, labels);
+
+    // Histogram of the gap lengths at the loose ends of edge segments
+    let mut gaps = [0usize; MAX_SIZE + 1];
+    for (i, _) in edges.iter().enumerate().filter(|&(_, &e)| e > 0) {
+        let (x, y) = (i % width, i / width);
+        if neighbor_count(edges, width, height, x, y) <= 1 {
+            if let Some(gap) = gap_at(labels, width, height, x, y) {
+                gaps[gap] += 1;
+            }
+        }
+    }
+    let broken: usize = gaps.iter().sum();
+    if (broken as f32) < MIN_FRAGMENTATION * edge_pixels as f32 {
+        return MIN_SIZE;
+    }
+
+    // Pixels at Chebyshev distance d join under a kernel of size d
+    let target = (broken * GAP_PERCENTILE).div_ceil(100);
+    let mut seen = 0;
+    let gap = (0..=MAX_SIZE)
+        .find(|&d| {
+            seen += gaps[d];
+            seen >= target
+        })
+        .unwrap_or(MAX_SIZE);
+    (gap | 1).clamp(MIN_SIZE, MAX_SIZE)
+}
+
+/// Suggests a dilation kernel size for a Canny edge map, in place of a fixed
+/// size that only suits one resolution. Segment ends whose nearest other
+/// segment lies within 14 pixels count as broken joins; once they are more
+/// than a few per thousand edge pixels, the size closes three in four of
+/// those gaps. Unbroken maps, and dense maps of texture or noise that closing
+/// would merge into blobs, get the smallest size.
+///
+/// # Arguments
+/// * `edges` - Undilated edge map (non-zero for edges), e.g. from
+///   `canny_edge_detector` with dilation 0
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Odd dilation kernel size between 3 and 15
+#[wasm_bindgen]
+pub fn suggest_dilation_size(edges: &[u8], width: usize, height: usize) -> Result<usize, ScanError> {
+    check_gray(edges, width, height)?;
+    let mut labels = vec![0u32; edges.len()];
+    Ok(suggest_dilation_into(edges, width, height, &mut labels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rectangle outline with a gap of `gap` pixels every `period` pixels
+    // along its horizontal sides.
+    fn broken_outline(width: usize, height: usize, gap: usize, period: usize) -> Vec<u8> {
+        let mut edges = vec![0u8; width * height];
+        for x in 10..width - 10 {
+            if (x - 10) % period >= gap {
+                edges[10 * width + x] = 255;
+                edges[(height - 11) * width + x] = 255;
+            }
+        }
+        for y in 10..height - 10 {
+            edges[y * width + 10] = 255;
+            edges[y * width + width - 11] = 255;
+        }
+        edges
+    }
+
+    #[test]
+    fn test_suggest_dilation_size() {
+        let (width, height) = (200, 120);
+        assert_eq!(suggest_dilation_size(&broken_outline(width, height, 0, 20), width, height), Ok(MIN_SIZE));
+        assert_eq!(suggest_dilation_size(&broken_outline(width, height, 4, 20), width, height), Ok(5));
+        assert_eq!(suggest_dilation_size(&broken_outline(width, height, 8, 30), width, height), Ok(9));
+
+        // Dense noise and an empty map keep the smallest size
+        let noise: Vec<u8> = (0..width * height).map(|i| if (i * 7919) % 5 == 0 { 255 } else { 0 }).collect();
+        assert_eq!(suggest_dilation_size(&noise, width, height), Ok(MIN_SIZE));
+        assert_eq!(suggest_dilation_size(&vec![0; width * height], width, height), Ok(MIN_SIZE));
+        assert!(suggest_dilation_size(&[0; 10], 4, 2).is_err());
+
+        // Canny traces both sides of a drawn line and around its ends, so the
+        // context sees closed contours and drops from its default size of 5
+        let mut context = crate::context::ScanContext::new(width, height);
+        context.set_auto_dilation(true);
+        assert_eq!((context.auto_dilation(), context.dilation_size_used()), (true, 5));
+        let frame: Vec<u8> =
+            broken_outline(width, height, 6, 20).iter().map(|&e| if e > 0 { 20 } else { 230 }).collect();
+        assert_eq!(context.process_gray_frame(&frame), Ok(()));
+        assert_eq!(context.dilation_size_used(), MIN_SIZE);
+        context.set_auto_dilation(false);
+        assert_eq!(context.dilation_size_used(), 5);
+    }
+}
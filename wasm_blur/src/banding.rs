@@ -0,0 +1,264 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::histogram::{gray_histogram, percentile_levels};
+
+use core::f32::consts::PI;
+
+// Share of each row brighter than its background level, so text and lines
+// don't pull the level down.
+const BACKGROUND_PCT: f32 = 20.0;
+// Shortest band period searched, in rows; shorter ripple is sensor noise.
+const MIN_PERIOD: f32 = 8.0;
+// At least this many periods have to fit in the frame.
+const MIN_CYCLES: f32 = 1.5;
+// Frequency step of the search, as a share of one cycle per frame.
+const FREQUENCY_STEP: f32 = 0.25;
+// Banding needs a relative brightness swing of this amplitude, explaining at
+// least this share of the row-to-row variation around the lighting trend.
+const MIN_AMPLITUDE: f32 = 0.015;
+const MIN_STRENGTH: f32 = 0.4;
+// Background levels below this are too dark to measure a gain on.
+const MIN_LEVEL: f32 = 16.0;
+
+/// Periodic horizontal banding of a frame, from `detect_banding`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BandingReport {
+    period: f32,
+    amplitude: f32,
+    strength: f32,
+}
+
+#[wasm_bindgen]
+impl BandingReport {
+    /// Whether the frame shows flicker bands: a periodic swing of at least
+    /// 1.5 % that explains at least 40 % of the row brightness variation.
+    pub fn banded(&self) -> bool {
+        self.amplitude >= MIN_AMPLITUDE && self.strength >= MIN_STRENGTH
+    }
+
+    /// Distance between bands in rows, 0 if no period was found.
+    #[wasm_bindgen(getter)]
+    pub fn period(&self) -> f32 {
+        self.period
+    }
+
+    /// Relative brightness swing of the bands, e.g. 0.1 for ±10 %.
+    #[wasm_bindgen(getter)]
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
+    }
+
+    /// Share of the row brightness variation the bands explain, 0..1.
+    #[wasm_bindgen(getter)]
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+}
+
+// Relative deviation of each row's background level from the smooth lighting
+// trend (a quadratic in the row), 0 on rows too dark to measure.
+fn row_gains(gray: &[u8], width: usize, height: usize) -> Vec<f32> {
+    let levels: Vec<f32> = gray
+        .chunks_exact(width)
+        .map(|row| percentile_levels(&gray_histogram(row), 0.0, BACKGROUND_PCT).1 as f32)
+        .collect();
+
+    // 1, x and x² - mean(x²) are orthogonal over rows symmetric around 0
+    let xs: Vec<f32> = (0..height).map(|y| 2.0 * y as f32 / (height - 1).max(1) as f32 - 1.0).collect();
+    let mean_square = xs.iter().map(|x| x * x).sum::<f32>() / height as f32;
+    let basis = |x: f32| [1.0, x, x * x - mean_square];
+    let (mut dots, mut norms) = ([0f32; 3], [0f32; 3]);
+    for (&x, &level) in xs.iter().zip(&levels) {
+        for (k, v) in basis(x).into_iter().enumerate() {
+            dots[k] += v * level;
+            norms[k] += v * v;
+        }
+    }
+    let coefficients: [f32; 3] = core::array::from_fn(|k| if norms[k] > 0.0 { dots[k] / norms[k] } else { 0.0 });
+    xs.iter()
+        .zip(&levels)
+        .map(|(&x, &level)| {
+            let trend: f32 = basis(x).iter().zip(coefficients).map(|(v, c)| c * v).sum();
+            if trend >= MIN_LEVEL && level >= MIN_LEVEL {
+                level / trend - 1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// Cosine and sine amplitude of `gains` at `frequency` cycles per row.
+fn project(gains: &[f32], frequency: f32) -> (f32, f32) {
+    let (c, s) = gains.iter().enumerate().fold((0.0, 0.0), |(c, s), (y, &g)| {
+        let phase = 2.0 * PI * frequency * y as f32;
+        (c + g * phase.cos(), s + g * phase.sin())
+    });
+    let scale = 2.0 / gains.len() as f32;
+    (c * scale, s * scale)
+}
+
+// Strongest periodic component of the row gains: (frequency, amplitude,
+// share of the variance it explains).
+fn dominant_frequency(gains: &[f32]) -> (f32, f32, f32) {
+    let n = gains.len() as f32;
+    let variance = gains.iter().map(|g| g * g).sum::<f32>() / n;
+    let (low, high) = (MIN_CYCLES / n, 1.0 / MIN_PERIOD);
+    let steps = ((high - low) * n / FREQUENCY_STEP).max(0.0) as usize;
+    let (frequency, amplitude) = (0..=steps)
+        .map(|i| {
+            let frequency = low + i as f32 * FREQUENCY_STEP / n;
+            let (c, s) = project(gains, frequency);
+            (frequency, c.hypot(s))
+        })
+        .fold((0.0, 0.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+    let strength = if variance > 0.0 { (amplitude * amplitude / 2.0 / variance).min(1.0) } else { 0.0 };
+    (frequency, amplitude, strength)
+}
+
+fn report(gains: &[f32]) -> BandingReport {
+    let (frequency, amplitude, strength) = dominant_frequency(gains);
+    let period = if frequency > 0.0 { 1.0 / frequency } else { 0.0 };
+    BandingReport { period, amplitude, strength }
+}
+
+// Frequency (cycles per row) and phase (radians) of the flicker bands of a
+// grayscale frame, None if it isn't banded. The bands follow
+// `cos(2π (frequency * y) - phase)`.
+#[cfg_attr(not(feature = "flicker"), allow(dead_code))]
+pub(crate) fn band_phase(gray: &[u8], width: usize, height: usize) -> Option<(f32, f32)> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let gains = row_gains(gray, width, height);
+    let banding = report(&gains);
+    if !banding.banded() {
+        return None;
+    }
+    let frequency = 1.0 / banding.period;
+    let (c, s) = project(&gains, frequency);
+    Some((frequency, s.atan2(c)))
+}
+
+/// Detects periodic horizontal banding from lights flickering at the mains
+/// frequency while the sensor reads out row by row: bright and dark stripes
+/// across the whole frame that break up edges and make detection noisy. The
+/// background brightness of every row is compared with the smooth lighting
+/// trend and the strongest periodic swing of the difference is reported.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// `BandingReport` with the band period, amplitude and strength
+#[wasm_bindgen]
+pub fn detect_banding(gray: &[u8], width: usize, height: usize) -> Result<BandingReport, ScanError> {
+    check_gray(gray, width, height)?;
+    if width == 0 || height == 0 {
+        return Ok(BandingReport { period: 0.0, amplitude: 0.0, strength: 0.0 });
+    }
+    Ok(report(&row_gains(gray, width, height)))
+}
+
+/// Removes flicker banding (see `detect_banding`) from an RGBA frame. The
+/// band pattern is fitted as a sinusoid at the detected period plus its first
+/// harmonic, and each row is divided by it, so page content and the overall
+/// lighting are left alone. Frames without banding are returned unchanged.
+///
+/// # Arguments
+/// * `rgba` - RGBA frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// RGBA frame as Vec<u8> with the bands evened out
+#[wasm_bindgen]
+pub fn remove_banding(rgba: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut out = rgba.to_vec();
+    if width == 0 || height == 0 {
+        return Ok(out);
+    }
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, GrayscaleWeights::Bt601);
+    let gains = row_gains(&gray, width, height);
+    let banding = report(&gains);
+    if !banding.banded() {
+        return Ok(out);
+    }
+
+    let frequency = 1.0 / banding.period;
+    let harmonics = [frequency, 2.0 * frequency].map(|f| (f, project(&gains, f)));
+    for (y, row) in out.chunks_exact_mut(width * 4).enumerate() {
+        let band: f32 = harmonics
+            .iter()
+            .map(|&(f, (c, s))| {
+                let phase = 2.0 * PI * f * y as f32;
+                c * phase.cos() + s * phase.sin()
+            })
+            .sum();
+        let scale = 1.0 / (1.0 + band).max(0.5);
+        for px in row.chunks_exact_mut(4) {
+            for v in &mut px[..3] {
+                *v = (*v as f32 * scale + 0.5).min(255.0) as u8;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banding() {
+        // A page with lines of text every 20 rows, lit brighter towards the
+        // bottom, under ±10 % bands every 70 rows
+        let (width, height) = (160, 300);
+        let page = |x: usize, y: usize| {
+            let paper = 170.0 + 40.0 * y as f32 / height as f32;
+            if y % 20 < 4 && (20..100).contains(&x) {
+                40.0
+            } else {
+                paper
+            }
+        };
+        let band = |y: usize| 1.0 + 0.1 * (2.0 * PI * y as f32 / 70.0).sin();
+        let gray = |banded: bool| -> Vec<u8> {
+            (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    (page(x, y) * if banded { band(y) } else { 1.0 } + 0.5) as u8
+                })
+                .collect()
+        };
+
+        let banded = detect_banding(&gray(true), width, height).unwrap();
+        assert!(banded.banded(), "{banded:?}");
+        assert!((banded.period() - 70.0).abs() < 2.0 && (banded.amplitude() - 0.1).abs() < 0.02, "{banded:?}");
+        let clean = detect_banding(&gray(false), width, height).unwrap();
+        assert!(!clean.banded(), "{clean:?}");
+
+        // Correcting the bands brings the paper back to the unbanded level
+        let rgba: Vec<u8> = gray(true).iter().flat_map(|&v| [v, v, v, 255]).collect();
+        let fixed = remove_banding(&rgba, width, height).unwrap();
+        for y in 0..height {
+            let (v, expected) = (fixed[(y * width + 150) * 4], page(150, y));
+            assert!((v as f32 - expected).abs() <= 5.0, "row {y}: {v} vs {expected}");
+        }
+        assert!(!detect_banding(&fixed.iter().step_by(4).copied().collect::<Vec<u8>>(), width, height)
+            .unwrap()
+            .banded());
+        let unbanded: Vec<u8> = gray(false).iter().flat_map(|&v| [v, v, v, 255]).collect();
+        assert_eq!(remove_banding(&unbanded, width, height).unwrap(), unbanded);
+    }
+}
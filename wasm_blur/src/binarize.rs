@@ -0,0 +1,249 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// Dynamic range of the standard deviation in Sauvola's formula (8-bit images).
+const SAUVOLA_RANGE: f64 = 128.0;
+// Side of the square blocks the halftone classifier looks at.
+pub(crate) const BLOCK: usize = 16;
+// Grey levels strictly between these count as mid-tones. Text blocks are paper
+// and ink with a thin anti-aliased rim; photos are mostly mid-tones.
+const MID_LOW: u8 = 48;
+const MID_HIGH: u8 = 208;
+// Fraction of mid-tone pixels from which a block counts as continuous tone.
+const MID_FRACTION: f32 = 0.5;
+// Fraction of horizontal neighbours differing by more than `DOT_CONTRAST` from
+// which a block counts as a resolved halftone dot pattern. Text strokes change
+// far less often per pixel.
+const DOT_TRANSITIONS: f32 = 0.35;
+const DOT_CONTRAST: u8 = 64;
+// Photo blocks need this many photo blocks among their 8 neighbours, so stray
+// blocks on dense text don't get dithered.
+const MIN_NEIGHBOURS: usize = 2;
+
+// Sauvola thresholding into `out` (255 paper, 0 ink). Local mean and standard
+// deviation come from integral images, so the cost is independent of `window`.
+pub(crate) fn sauvola_into(gray: &[u8], width: usize, height: usize, window: usize, k: f32, out: &mut [u8]) {
+    let stride = width + 1;
+    let mut sum = vec![0u64; stride * (height + 1)];
+    let mut sum_sq = vec![0u64; stride * (height + 1)];
+    for y in 0..height {
+        let (mut row, mut row_sq) = (0u64, 0u64);
+        for x in 0..width {
+            let v = gray[y * width + x] as u64;
+            row += v;
+            row_sq += v * v;
+            sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+            sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+        }
+    }
+
+    let radius = window / 2;
+    for y in 0..height {
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+        for x in 0..width {
+            let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+            let area = |t: &[u64]| t[y1 * stride + x1] + t[y0 * stride + x0] - t[y0 * stride + x1] - t[y1 * stride + x0];
+            let n = ((x1 - x0) * (y1 - y0)) as f64;
+            let mean = area(&sum) as f64 / n;
+            let variance = (area(&sum_sq) as f64 / n - mean * mean).max(0.0);
+            let threshold = mean * (1.0 + k as f64 * (variance.sqrt() / SAUVOLA_RANGE - 1.0));
+            out[y * width + x] = if gray[y * width + x] as f64 > threshold { 255 } else { 0 };
+        }
+    }
+}
+
+// Per-block halftone / continuous-tone classification, as a block grid.
+pub(crate) fn halftone_blocks(gray: &[u8], width: usize, height: usize) -> (Vec<bool>, usize, usize) {
+    let (bw, bh) = (width.div_ceil(BLOCK), height.div_ceil(BLOCK));
+    let mut photo = vec![false; bw * bh];
+    for by in 0..bh {
+        for bx in 0..bw {
+            let (x0, x1) = (bx * BLOCK, ((bx + 1) * BLOCK).min(width));
+            let (y0, y1) = (by * BLOCK, ((by + 1) * BLOCK).min(height));
+            let (mut mid, mut dots, mut pairs) = (0usize, 0usize, 0usize);
+            for row in gray[y0 * width..y1 * width].chunks_exact(width) {
+                let row = &row[x0..x1];
+                mid += row.iter().filter(|&&v| v > MID_LOW && v < MID_HIGH).count();
+                dots += row.windows(2).filter(|w| w[0].abs_diff(w[1]) > DOT_CONTRAST).count();
+                pairs += row.len() - 1;
+            }
+            let pixels = ((x1 - x0) * (y1 - y0)) as f32;
+            photo[by * bw + bx] =
+                mid as f32 >= MID_FRACTION * pixels || (pairs > 0 && dots as f32 >= DOT_TRANSITIONS * pairs as f32);
+        }
+    }
+
+    // Drop blocks without enough photo neighbours
+    let supported: Vec<bool> = (0..bw * bh)
+        .map(|i| {
+            let (bx, by) = ((i % bw) as isize, (i / bw) as isize);
+            let neighbours = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (bx + dx, by + dy)))
+                .filter(|&(x, y)| (x, y) != (bx, by) && x >= 0 && y >= 0 && x < bw as isize && y < bh as isize)
+                .filter(|&(x, y)| photo[y as usize * bw + x as usize])
+                .count();
+            photo[i] && neighbours >= MIN_NEIGHBOURS
+        })
+        .collect();
+    (supported, bw, bh)
+}
+
+// Floyd-Steinberg error diffusion of the pixels where `mask` is set; error is
+// only passed on to other masked pixels, so text next to a photo is untouched.
+fn diffuse_into(gray: &[u8], width: usize, height: usize, mask: &[bool], out: &mut [u8]) {
+    let mut error = vec![0f32; width * 2];
+    for y in 0..height {
+        let (current, next) = error.split_at_mut(width);
+        for x in 0..width {
+            let i = y * width + x;
+            if !mask[i] {
+                continue;
+            }
+            let value = gray[i] as f32 + current[x];
+            let level = if value >= 128.0 { 255.0 } else { 0.0 };
+            out[i] = level as u8;
+            let e = value - level;
+            if x + 1 < width && mask[i + 1] {
+                current[x + 1] += e * 7.0 / 16.0;
+            }
+            if y + 1 < height {
+                if x > 0 && mask[i + width - 1] {
+                    next[x - 1] += e * 3.0 / 16.0;
+                }
+                if mask[i + width] {
+                    next[x] += e * 5.0 / 16.0;
+                }
+                if x + 1 < width && mask[i + width + 1] {
+                    next[x + 1] += e / 16.0;
+                }
+            }
+        }
+        // The next row's error becomes current, and the row after starts clean
+        error.copy_within(width.., 0);
+        error[width..].fill(0.0);
+    }
+}
+
+// Sauvola thresholding with error diffusion inside the halftone regions.
+pub(crate) fn binarize_document_into(gray: &[u8], width: usize, height: usize, window: usize, k: f32, out: &mut [u8]) {
+    sauvola_into(gray, width, height, window, k, out);
+    let (blocks, bw, _) = halftone_blocks(gray, width, height);
+    if blocks.iter().any(|&b| b) {
+        let mask: Vec<bool> = (0..gray.len()).map(|i| blocks[(i / width / BLOCK) * bw + (i % width) / BLOCK]).collect();
+        diffuse_into(gray, width, height, &mask, out);
+    }
+}
+
+/// Binarizes a grayscale page with Sauvola's local threshold, which copes with
+/// shading and uneven light far better than a global threshold.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `window_size` - Side of the local window in pixels, e.g. 25 for text pages
+/// * `k` - Sensitivity, typically 0.2-0.5; higher values give thinner strokes
+///
+/// # Returns
+/// Binary image as Vec<u8>, 0 for ink and 255 for paper
+#[wasm_bindgen]
+pub fn sauvola_binarize(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    window_size: usize,
+    k: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let mut out = vec![0u8; gray.len()];
+    sauvola_into(gray, width, height, window_size.max(1), k, &mut out);
+    Ok(out)
+}
+
+/// Finds halftone and continuous-tone regions (printed photos) on a grayscale
+/// page, in blocks of 16x16 pixels.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Mask as Vec<u8>, 255 for pixels in photo regions and 0 elsewhere
+#[wasm_bindgen]
+pub fn halftone_mask(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let (blocks, bw, _) = halftone_blocks(gray, width, height);
+    Ok((0..gray.len()).map(|i| if blocks[(i / width / BLOCK) * bw + (i % width) / BLOCK] { 255 } else { 0 }).collect())
+}
+
+/// Binarizes a page with Sauvola thresholding for text and Floyd-Steinberg error
+/// diffusion inside the regions `halftone_mask` finds, so embedded photos stay
+/// recognizable in black-and-white output instead of turning into black blobs.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `window_size` - Sauvola window side in pixels
+/// * `k` - Sauvola sensitivity
+///
+/// # Returns
+/// Binary image as Vec<u8>, 0 for ink and 255 for paper
+#[wasm_bindgen]
+pub fn binarize_document(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    window_size: usize,
+    k: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let mut out = vec![0u8; gray.len()];
+    binarize_document_into(gray, width, height, window_size.max(1), k, &mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_and_photo_binarization() {
+        // Text-like strokes on the left half, a smooth photo gradient on the right
+        let (width, height) = (128usize, 64usize);
+        let mut gray = vec![220u8; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                if x < 64 && y % 8 < 2 && x % 12 < 9 {
+                    gray[y * width + x] = 20;
+                } else if x >= 64 {
+                    gray[y * width + x] = (60 + (x - 64) * 2) as u8;
+                }
+            }
+        }
+
+        let mask = halftone_mask(&gray, width, height).unwrap();
+        assert!(mask.chunks_exact(width).all(|row| row[..64].iter().all(|&m| m == 0)));
+        assert!(mask.chunks_exact(width).all(|row| row[80..].iter().all(|&m| m == 255)));
+
+        // Sauvola alone turns the gradient into a flat area; error diffusion
+        // keeps its brightness as dot density
+        let plain = sauvola_binarize(&gray, width, height, 25, 0.3).unwrap();
+        let mixed = binarize_document(&gray, width, height, 25, 0.3).unwrap();
+        assert_eq!(plain[width + 3], 0);
+        let text = |out: &[u8]| out.chunks_exact(width).flat_map(|r| r[..64].to_vec()).collect::<Vec<_>>();
+        assert_eq!(text(&plain), text(&mixed));
+        let white = |out: &[u8], x0: usize| {
+            let count = out.chunks_exact(width).map(|r| r[x0..x0 + 16].iter().filter(|&&v| v == 255).count()).sum::<usize>();
+            count as f32 / (16 * height) as f32
+        };
+        // Gradient columns 80..96 are average 107 (42%), 112..128 average 171 (67%)
+        assert!((white(&mixed, 80) - 0.42).abs() < 0.03);
+        assert!((white(&mixed, 112) - 0.67).abs() < 0.03);
+    }
+}
@@ -0,0 +1,96 @@
+use wasm_bindgen::prelude::*;
+
+/// How convolution passes (blur, gradients) read pixels beyond the frame
+/// border, following OpenCV's border types. Shown for a row `abcdefgh` with
+/// three pixels of padding on either side.
+///
+/// Clamping to the edge pixel stretches whatever lies at the border across the
+/// whole kernel, e.g. a bright strip of desk at the frame edge; the reflecting
+/// modes keep the local structure instead. Dilation takes no border mode: its
+/// max filter gives the same result for all of them, since replicated and
+/// mirrored pixels are already inside the window and the constant border is 0.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BorderMode {
+    /// `aaa|abcdefgh|hhh`, clamp to the edge pixel (the default)
+    #[default]
+    Replicate = 0,
+    /// `cba|abcdefgh|hgf`, mirror including the edge pixel
+    Reflect = 1,
+    /// `dcb|abcdefgh|gfe`, mirror around the edge pixel
+    Reflect101 = 2,
+    /// `000|abcdefgh|000`, zeros outside the frame
+    Constant = 3,
+}
+
+impl BorderMode {
+    // Pixel read for coordinate `i` of a row or column of `len` pixels, None
+    // where the constant border applies. Kernels wider than the frame reflect
+    // as often as needed.
+    #[inline]
+    pub(crate) fn index(self, i: isize, len: usize) -> Option<usize> {
+        let last = len as isize - 1;
+        if (0..=last).contains(&i) {
+            return Some(i as usize);
+        }
+        match self {
+            BorderMode::Replicate => Some(i.clamp(0, last) as usize),
+            BorderMode::Constant => None,
+            BorderMode::Reflect | BorderMode::Reflect101 if len == 1 => Some(0),
+            BorderMode::Reflect | BorderMode::Reflect101 => {
+                // Mirroring repeats with this period; fold into one period first
+                let shift = (self == BorderMode::Reflect101) as isize;
+                let period = 2 * len as isize - 2 * shift;
+                let i = i.rem_euclid(period);
+                Some(if i <= last { i } else { period - i - 1 + shift } as usize)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_border_index() {
+        let padded = |mode: BorderMode| -> Vec<Option<usize>> { (-3..11).map(|i| mode.index(i, 8)).collect() };
+        let some = |v: &[usize]| -> Vec<Option<usize>> { v.iter().map(|&i| Some(i)).collect() };
+        assert_eq!(padded(BorderMode::Replicate), some(&[0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 7, 7, 7]));
+        assert_eq!(padded(BorderMode::Reflect), some(&[2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 7, 6, 5]));
+        assert_eq!(padded(BorderMode::Reflect101), some(&[3, 2, 1, 0, 1, 2, 3, 4, 5, 6, 7, 6, 5, 4]));
+        assert_eq!(padded(BorderMode::Constant)[..4], [None, None, None, Some(0)]);
+        // Kernels wider than the frame keep reflecting
+        assert_eq!(BorderMode::Reflect101.index(-5, 3), Some(1));
+        assert_eq!(BorderMode::Reflect.index(7, 2), Some(0));
+    }
+
+    #[test]
+    fn test_blur_and_gradients_follow_border_mode() {
+        use crate::gaussian_blur::blur_with_border;
+        use crate::gradient_calculation::{calculate_gradients, calculate_gradients_with_border};
+
+        // A flat frame stays flat, except where the constant border reads zeros
+        let flat = [100u8; 8 * 6];
+        for kernel_size in [3, 5, 7] {
+            for mode in [BorderMode::Replicate, BorderMode::Reflect, BorderMode::Reflect101] {
+                let blurred = blur_with_border(&flat, 8, 6, kernel_size, 0.0, mode).unwrap();
+                assert!(blurred.iter().all(|&v| v.abs_diff(100) <= 1), "{kernel_size} {mode:?} {blurred:?}");
+            }
+            let constant = blur_with_border(&flat, 8, 6, kernel_size, 0.0, BorderMode::Constant).unwrap();
+            assert!(constant[0] < 80, "{kernel_size} {constant:?}");
+        }
+        assert_eq!(crate::blur(&flat, 8, 6, 5, 0.0), blur_with_border(&flat, 8, 6, 5, 0.0, BorderMode::Replicate));
+
+        // Border gradients are 0 on a flat frame unless zeros are read in, and
+        // interior gradients match `calculate_gradients`
+        assert!(calculate_gradients_with_border(&flat, 8, 6, BorderMode::Reflect).unwrap().iter().all(|&g| g == 0));
+        let constant = calculate_gradients_with_border(&flat, 8, 6, BorderMode::Constant).unwrap();
+        assert_eq!(constant[..2], [300, 300]);
+        let ramp: Vec<u8> = (0..8 * 6).map(|i| (i % 8 * 20 + i / 8 * 5) as u8).collect();
+        let interior = calculate_gradients(&ramp, 8, 6).unwrap();
+        let bordered = calculate_gradients_with_border(&ramp, 8, 6, BorderMode::Replicate).unwrap();
+        assert_eq!(bordered[2 * 9..2 * 15], interior[2 * 9..2 * 15]);
+        assert_eq!(bordered[2 * 8..2 * 9], [80, 40]);
+    }
+}
@@ -1,63 +1,534 @@
 use wasm_bindgen::prelude::*;
 
-// Hysteresis thresholding implementation, a key part of the Canny algorithm.
-fn hysteresis_thresholding(
-    suppressed: &[f32],
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+use crate::arena::FrameArena;
+use crate::border::BorderMode;
+use crate::error::{check_gray, check_kernel_size, ScanError};
+use crate::scheduler::ROWS_PER_TASK;
+
+// Edge classes written into the output buffer before the final binary pass.
+const NOT_EDGE: u8 = 0;
+const WEAK_EDGE: u8 = 1;
+const STRONG_EDGE: u8 = 2;
+
+// Three-row ring buffers for gradients and magnitude, indexed by row % 3.
+struct GradientRows<'a> {
+    dx: &'a mut [i16],
+    dy: &'a mut [i16],
+    mag: &'a mut [f32],
+}
+
+// Rows per NMS band. Each band streams its rows through its own gradient ring,
+// so with the `threads` feature bands run in parallel; otherwise one band covers
+// the whole frame.
+fn nms_band_rows(height: usize) -> usize {
+    if cfg!(feature = "threads") { ROWS_PER_TASK } else { height.max(1) }
+}
+
+// Arena bytes one `canny_into` call allocates for a width x height frame.
+pub(crate) fn canny_arena_bytes(width: usize, height: usize) -> usize {
+    let size = width * height;
+    let ring = 3 * width * height.div_ceil(nms_band_rows(height));
+    let round = |b: usize| b.next_multiple_of(8);
+    let stack = if cfg!(target_pointer_width = "32") { 0 } else { round(size * size_of::<usize>()) };
+    round(size) + round(size * 4) + 2 * round(ring * 2) + round(ring * 4) + stack
+}
+
+// 3x3 Sobel (gx, gy) of an interior pixel.
+#[inline]
+fn sobel(plane: &[u8], prev_row: usize, curr_row: usize, next_row: usize, x: usize) -> (i16, i16) {
+    let p0 = plane[prev_row + x - 1] as i16;
+    let p1 = plane[prev_row + x] as i16;
+    let p2 = plane[prev_row + x + 1] as i16;
+    let p3 = plane[curr_row + x - 1] as i16;
+    let p5 = plane[curr_row + x + 1] as i16;
+    let p6 = plane[next_row + x - 1] as i16;
+    let p7 = plane[next_row + x] as i16;
+    let p8 = plane[next_row + x + 1] as i16;
+
+    let gx = (p2 - p0) + 2 * (p5 - p3) + (p8 - p6);
+    let gy = (p6 + 2 * p7 + p8) - (p0 + 2 * p1 + p2);
+    (gx, gy)
+}
+
+#[inline]
+fn magnitude(gx: i16, gy: i16, l2_gradient: bool) -> f32 {
+    let (fx, fy) = (gx as f32, gy as f32);
+    if l2_gradient { (fx * fx + fy * fy).sqrt() } else { fx.abs() + fy.abs() }
+}
+
+// Sobel gradients and magnitude for one row of the blurred planes into ring slot
+// `row % 3`. With several planes each pixel takes the gradient of the plane with
+// the strongest response. Border rows and columns get zero gradients, matching
+// `calculate_gradients`.
+fn gradient_row(
+    blurred: &[&[u8]],
+    rows: &mut GradientRows,
+    width: usize,
+    height: usize,
+    row: usize,
+    l2_gradient: bool,
+) {
+    let slot = (row % 3) * width;
+    let dx = &mut rows.dx[slot..slot + width];
+    let dy = &mut rows.dy[slot..slot + width];
+    let mag = &mut rows.mag[slot..slot + width];
+    dx.fill(0);
+    dy.fill(0);
+    mag.fill(0.0);
+    if row == 0 || row + 1 >= height {
+        return;
+    }
+
+    let prev_row = (row - 1) * width;
+    let curr_row = row * width;
+    let next_row = (row + 1) * width;
+    for x in 1..width - 1 {
+        let (mut gx, mut gy) = sobel(blurred[0], prev_row, curr_row, next_row, x);
+        let mut m = magnitude(gx, gy, l2_gradient);
+        for plane in &blurred[1..] {
+            let (px, py) = sobel(plane, prev_row, curr_row, next_row, x);
+            let pm = magnitude(px, py, l2_gradient);
+            if pm > m {
+                (gx, gy, m) = (px, py, pm);
+            }
+        }
+        dx[x] = gx;
+        dy[x] = gy;
+        mag[x] = m;
+    }
+}
+
+// Gradients and NMS with double thresholding for the rows held by `edges`, which
+// start at row `y_start`. Each pixel is classified as NOT_EDGE, WEAK_EDGE or
+// STRONG_EDGE; border rows and columns stay NOT_EDGE.
+#[allow(clippy::too_many_arguments)]
+fn classify_band(
+    blurred: &[&[u8]],
+    rows: &mut GradientRows,
     width: usize,
     height: usize,
+    y_start: usize,
     low_threshold: f32,
     high_threshold: f32,
-) -> Vec<u8> {
-    let mut edge_map = vec![0u8; width * height];
-    let mut stack = Vec::new();
+    l2_gradient: bool,
+    edges: &mut [u8],
+) {
+    let first = y_start.max(1);
+    let last = (y_start + edges.len() / width).min(height - 1);
+    if first >= last {
+        return;
+    }
+
+    gradient_row(blurred, rows, width, height, first - 1, l2_gradient);
+    gradient_row(blurred, rows, width, height, first, l2_gradient);
+    for y in first..last {
+        gradient_row(blurred, rows, width, height, y + 1, l2_gradient);
+
+        let above = ((y - 1) % 3) * width;
+        let curr = (y % 3) * width;
+        let below = ((y + 1) % 3) * width;
+        let mag = &*rows.mag;
+        let edge_row = &mut edges[(y - y_start) * width..(y - y_start + 1) * width];
 
-    // Apply double thresholding to identify strong and weak edges.
-    for y in 1..(height - 1) {
         for x in 1..(width - 1) {
-            let idx = y * width + x;
-            let mag = suppressed[idx];
-            if mag >= high_threshold {
-                edge_map[idx] = 2; // Strong edge
-                stack.push((x, y));
-            } else if mag >= low_threshold {
-                edge_map[idx] = 1; // Weak edge
+            let m = mag[curr + x];
+            if m == 0.0 {
+                continue;
+            }
+
+            let gx = rows.dx[curr + x] as f32;
+            let gy = rows.dy[curr + x] as f32;
+            let abs_gx = gx.abs();
+            let abs_gy = gy.abs();
+
+            // Same 45-degree sector partition as `non_maximum_suppression` (tan(67.5) = 2.4142)
+            let (neighbor1, neighbor2) = if abs_gy > abs_gx * 2.4142 {
+                (mag[above + x], mag[below + x])
+            } else if abs_gx > abs_gy * 2.4142 {
+                (mag[curr + x - 1], mag[curr + x + 1])
+            } else if (gx > 0.0 && gy > 0.0) || (gx < 0.0 && gy < 0.0) {
+                (mag[above + x + 1], mag[below + x - 1])
+            } else {
+                (mag[above + x - 1], mag[below + x + 1])
+            };
+
+            if m >= neighbor1 && m >= neighbor2 {
+                if m >= high_threshold {
+                    edge_row[x] = STRONG_EDGE;
+                } else if m >= low_threshold {
+                    edge_row[x] = WEAK_EDGE;
+                }
             }
         }
     }
+}
 
-    // Perform edge tracking by hysteresis.
-    while let Some((x, y)) = stack.pop() {
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-                let nx = (x as isize + dx) as usize;
-                let ny = (y as isize + dy) as usize;
-
-                if nx > 0 && nx < width && ny > 0 && ny < height {
-                    let neighbor_idx = ny * width + nx;
-                    if edge_map[neighbor_idx] == 1 { // Connect weak edges
-                        edge_map[neighbor_idx] = 2; // Promote to strong edge
-                        stack.push((nx, ny));
-                    }
-                }
+// Fused blur -> gradients -> NMS -> hysteresis. Magnitude is computed row by row
+// inside the NMS sweep and classified straight into `edges`, so no full-frame
+// gradient, magnitude or suppressed planes are ever allocated. All temporaries
+// come from `arena`; the blur's u32 temp plane is reused as the hysteresis stack.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn canny_into(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+    l2_gradient: bool,
+    arena: &FrameArena,
+    edges: &mut [u8],
+) {
+    canny_planes_into(
+        &[grayscale],
+        width,
+        height,
+        low_threshold,
+        high_threshold,
+        kernel_size,
+        sigma,
+        border,
+        l2_gradient,
+        arena,
+        edges,
+    );
+}
+
+// `canny_into` on up to three planes of the same frame (e.g. colour channels),
+// using the strongest gradient of any plane at each pixel.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn canny_planes_into(
+    planes: &[&[u8]],
+    width: usize,
+    height: usize,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+    l2_gradient: bool,
+    arena: &FrameArena,
+    edges: &mut [u8],
+) {
+    let size = width * height;
+    let blur_temp = arena.alloc::<u32>(size);
+    edges.fill(NOT_EDGE);
+
+    // Step 1: Apply Gaussian Blur.
+    let mut blurred: [&[u8]; 3] = [&[]; 3];
+    for (plane, out) in planes.iter().zip(blurred.iter_mut()) {
+        let target = arena.alloc::<u8>(size);
+        crate::gaussian_blur::blur_into(plane, width, height, kernel_size, sigma, border, blur_temp, target);
+        *out = target;
+    }
+    let blurred = &blurred[..planes.len().min(3)];
+    if width < 3 || height < 3 {
+        return;
+    }
+
+    let final_low_threshold = if l2_gradient { low_threshold * low_threshold } else { low_threshold };
+    let final_high_threshold = if l2_gradient { high_threshold * high_threshold } else { high_threshold };
+
+    // Steps 2 and 3: Gradients and Non-Maximum Suppression with double thresholding,
+    // one gradient ring per band of rows.
+    let band_rows = nms_band_rows(height);
+    let ring = 3 * width * height.div_ceil(band_rows);
+    let ring_dx = arena.alloc::<i16>(ring);
+    let ring_dy = arena.alloc::<i16>(ring);
+    let ring_mag = arena.alloc::<f32>(ring);
+
+    #[cfg(feature = "threads")]
+    let bands = edges
+        .par_chunks_mut(band_rows * width)
+        .zip(ring_dx.par_chunks_mut(3 * width))
+        .zip(ring_dy.par_chunks_mut(3 * width))
+        .zip(ring_mag.par_chunks_mut(3 * width));
+    #[cfg(not(feature = "threads"))]
+    let bands = edges
+        .chunks_mut(band_rows * width)
+        .zip(ring_dx.chunks_mut(3 * width))
+        .zip(ring_dy.chunks_mut(3 * width))
+        .zip(ring_mag.chunks_mut(3 * width));
+
+    bands.enumerate().for_each(|(band, (((edges, dx), dy), mag))| {
+        let mut rows = GradientRows { dx, dy, mag };
+        classify_band(
+            blurred,
+            &mut rows,
+            width,
+            height,
+            band * band_rows,
+            final_low_threshold,
+            final_high_threshold,
+            l2_gradient,
+            edges,
+        );
+    });
+
+    // Step 4: Edge tracking by hysteresis, seeded with every strong pixel. Each pixel
+    // is pushed at most once, so a width * height stack is enough. Weak edges only
+    // exist on interior pixels, so the 8 neighbours of a strong pixel are always in
+    // bounds. On 32-bit targets every pixel index fits in the blur's u32 temp plane,
+    // which is reused; memory64 builds can address frames beyond u32::MAX pixels and
+    // get a usize stack instead.
+    #[cfg(target_pointer_width = "32")]
+    let stack = blur_temp;
+    #[cfg(not(target_pointer_width = "32"))]
+    let stack = arena.alloc::<usize>(size);
+
+    let mut stack_len = 0;
+    for (idx, &e) in edges.iter().enumerate() {
+        if e == STRONG_EDGE {
+            stack[stack_len] = idx as _;
+            stack_len += 1;
+        }
+    }
+
+    let w = width as isize;
+    let neighbor_offsets: [isize; 8] = [-w - 1, -w, -w + 1, -1, 1, w - 1, w, w + 1];
+    while stack_len > 0 {
+        stack_len -= 1;
+        let idx = stack[stack_len] as isize;
+        for &offset in &neighbor_offsets {
+            let neighbor_idx = (idx + offset) as usize;
+            if edges[neighbor_idx] == WEAK_EDGE {
+                edges[neighbor_idx] = STRONG_EDGE; // Promote to strong edge
+                stack[stack_len] = neighbor_idx as _;
+                stack_len += 1;
             }
         }
     }
 
     // Create the final binary edge image (255 for edges, 0 for non-edges).
-    let mut final_edges = vec![0u8; width * height];
-    for i in 0..(width * height) {
-        if edge_map[i] == 2 {
-            final_edges[i] = 255;
+    for e in edges.iter_mut() {
+        *e = if *e == STRONG_EDGE { 255 } else { 0 };
+    }
+}
+
+/// Settings for `canny_edge_detector`, so JS can pass named options instead of a
+/// long positional argument list, and new settings don't break existing callers.
+/// Defaults match `ScanContext`: 75/200 thresholds, 5x5 blur with the sigma
+/// derived from the kernel size, L1 gradient and 5x5 dilation. Blur and
+/// dilation sizes can instead be given as fractions of the image diagonal, so
+/// settings tuned at one capture resolution carry over to another.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CannyOptions {
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+    l2_gradient: bool,
+    dilation_kernel_size: usize,
+    // Fractions of the image diagonal that replace the pixel sizes above when
+    // non-zero.
+    blur_fraction: f32,
+    dilation_fraction: f32,
+}
+
+impl Default for CannyOptions {
+    fn default() -> Self {
+        CannyOptions {
+            low_threshold: 75.0,
+            high_threshold: 200.0,
+            kernel_size: 5,
+            sigma: 0.0,
+            border: BorderMode::Replicate,
+            l2_gradient: false,
+            dilation_kernel_size: 5,
+            blur_fraction: 0.0,
+            dilation_fraction: 0.0,
         }
     }
+}
 
-    final_edges
+/// Odd kernel size covering `fraction` of the image diagonal, at least 3. Used
+/// for the resolution-independent blur and dilation settings, and by callers
+/// scaling their own pixel-based parameters.
+///
+/// # Arguments
+/// * `fraction` - Size as a fraction of the diagonal, e.g. 0.005
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Kernel size in pixels
+#[wasm_bindgen]
+pub fn relative_kernel_size(fraction: f32, width: usize, height: usize) -> usize {
+    let diagonal = ((width * width + height * height) as f32).sqrt();
+    ((fraction.max(0.0) * diagonal).round() as usize | 1).max(3)
 }
 
 #[wasm_bindgen]
+impl CannyOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CannyOptions {
+        CannyOptions::default()
+    }
+
+    /// Hysteresis thresholds, compared against L1 or L2 gradient magnitudes.
+    pub fn set_thresholds(&mut self, low_threshold: f32, high_threshold: f32) {
+        self.low_threshold = low_threshold;
+        self.high_threshold = high_threshold;
+    }
+
+    /// Gaussian blur before the gradient; `kernel_size` must be odd and sigma 0
+    /// derives it from the kernel size.
+    pub fn set_blur(&mut self, kernel_size: usize, sigma: f32) {
+        self.kernel_size = kernel_size;
+        self.sigma = sigma;
+        self.blur_fraction = 0.0;
+    }
+
+    /// Gaussian blur sized as a fraction of the image diagonal (see
+    /// `relative_kernel_size`), resolved for each image; 0 returns to the pixel
+    /// size from `set_blur`.
+    pub fn set_blur_relative(&mut self, fraction: f32, sigma: f32) {
+        self.blur_fraction = fraction.max(0.0);
+        self.sigma = sigma;
+    }
+
+    /// How the blur reads pixels beyond the frame border (see `BorderMode`).
+    pub fn set_border_mode(&mut self, border: BorderMode) {
+        self.border = border;
+    }
+
+    pub fn set_l2_gradient(&mut self, l2_gradient: bool) {
+        self.l2_gradient = l2_gradient;
+    }
+
+    /// Dilation kernel size applied after Canny; 0 disables dilation.
+    pub fn set_dilation(&mut self, kernel_size: usize) {
+        self.dilation_kernel_size = kernel_size;
+        self.dilation_fraction = 0.0;
+    }
+
+    /// Dilation sized as a fraction of the image diagonal, resolved for each
+    /// image; 0 returns to the pixel size from `set_dilation`.
+    pub fn set_dilation_relative(&mut self, fraction: f32) {
+        self.dilation_fraction = fraction.max(0.0);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn low_threshold(&self) -> f32 {
+        self.low_threshold
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn high_threshold(&self) -> f32 {
+        self.high_threshold
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kernel_size(&self) -> usize {
+        self.kernel_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn sigma(&self) -> f32 {
+        self.sigma
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn border_mode(&self) -> BorderMode {
+        self.border
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn l2_gradient(&self) -> bool {
+        self.l2_gradient
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dilation_kernel_size(&self) -> usize {
+        self.dilation_kernel_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn blur_fraction(&self) -> f32 {
+        self.blur_fraction
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dilation_fraction(&self) -> f32 {
+        self.dilation_fraction
+    }
+}
+
+impl CannyOptions {
+    // The options with any diagonal fractions resolved to pixel sizes for a
+    // `width` x `height` image.
+    pub(crate) fn for_size(&self, width: usize, height: usize) -> CannyOptions {
+        let mut options = *self;
+        if self.blur_fraction > 0.0 {
+            options.set_blur(relative_kernel_size(self.blur_fraction, width, height), self.sigma);
+        }
+        if self.dilation_fraction > 0.0 {
+            options.set_dilation(relative_kernel_size(self.dilation_fraction, width, height));
+        }
+        options
+    }
+}
+
+/// Runs the full Canny pipeline (blur, gradients, non-maximum suppression,
+/// hysteresis and optional dilation) in a single call.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `options` - Thresholds, blur, gradient and dilation settings
+///
+/// # Returns
+/// Binary edge map (0 or 255) as Vec<u8>
+#[wasm_bindgen]
+pub fn canny_edge_detector(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    options: &CannyOptions,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(grayscale, width, height)?;
+    let options = &options.for_size(width, height);
+    check_kernel_size(options.kernel_size)?;
+    let arena = FrameArena::default();
+    let mut canny_edges = vec![0u8; width * height];
+    canny_into(
+        grayscale,
+        width,
+        height,
+        options.low_threshold,
+        options.high_threshold,
+        options.kernel_size,
+        options.sigma,
+        options.border,
+        options.l2_gradient,
+        &arena,
+        &mut canny_edges,
+    );
+    // Release the scratch before dilation allocates its own buffers
+    drop(arena);
+
+    // Step 5: Apply Dilation if requested.
+    if options.dilation_kernel_size > 0 {
+        canny_edges = crate::dilation::dilate(&canny_edges, width, height, options.dilation_kernel_size)?;
+    }
+
+    Ok(canny_edges)
+}
+
+/// Positional form of `canny_edge_detector`, kept for existing callers.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn canny_edge_detector_full(
     grayscale: &[u8],
     width: usize,
@@ -69,12 +540,37 @@ pub fn canny_edge_detector_full(
     l2_gradient: bool,
     apply_dilation: bool,
     dilation_kernel_size: usize,
-) -> Vec<u8> {
-    // Step 1: Apply Gaussian Blur.
-    let blurred = crate::blur(grayscale, width, height, kernel_size, sigma);
+) -> Result<Vec<u8>, ScanError> {
+    let options = CannyOptions {
+        low_threshold,
+        high_threshold,
+        kernel_size,
+        sigma,
+        border: BorderMode::Replicate,
+        l2_gradient,
+        dilation_kernel_size: if apply_dilation { dilation_kernel_size } else { 0 },
+        ..CannyOptions::default()
+    };
+    canny_edge_detector(grayscale, width, height, &options)
+}
 
-    // Step 2: Calculate Gradients.
-    let gradients = crate::gradient_calculation::calculate_gradients(&blurred, width, height);
+/// Runs the Canny pipeline but returns an anti-aliased (0-255) edge visualization
+/// instead of the hard binary map, intended for UI overlays on the camera preview.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn canny_edge_detector_soft(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    l2_gradient: bool,
+) -> Result<Vec<u8>, ScanError> {
+    let blurred = crate::blur(grayscale, width, height, kernel_size, sigma)?;
+
+    let gradients = crate::gradient_calculation::calculate_gradients(&blurred, width, height)?;
     let mut dx_i16 = Vec::with_capacity(width * height);
     let mut dy_i16 = Vec::with_capacity(width * height);
     for i in 0..(width * height) {
@@ -82,31 +578,119 @@ pub fn canny_edge_detector_full(
         dy_i16.push(gradients[2 * i + 1]);
     }
 
-    // Step 3: Apply Non-Maximum Suppression.
     let suppressed = crate::non_maximum_suppression::non_maximum_suppression(
         &dx_i16,
         &dy_i16,
         width,
         height,
         l2_gradient,
-    );
+    )?;
 
-    // Step 4: Perform Hysteresis Thresholding.
     let final_low_threshold = if l2_gradient { low_threshold * low_threshold } else { low_threshold };
     let final_high_threshold = if l2_gradient { high_threshold * high_threshold } else { high_threshold };
 
-    let mut canny_edges = hysteresis_thresholding(
+    let edge_map = crate::hysteresis::hysteresis_thresholding(
         &suppressed,
         width,
         height,
         final_low_threshold,
         final_high_threshold,
-    );
+    )?;
 
-    // Step 5: Apply Dilation if requested.
-    if apply_dilation {
-        canny_edges = crate::dilation::dilate(&canny_edges, width, height, dilation_kernel_size);
+    crate::hysteresis::edge_map_to_soft(&edge_map, &suppressed, width, height, final_high_threshold)
+}
+
+/// Runs the full Canny pipeline directly on an RGBA buffer (e.g. `ImageData.data`),
+/// converting to grayscale inside WASM instead of in JS.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn canny_from_rgba(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    weights: crate::grayscale::GrayscaleWeights,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    l2_gradient: bool,
+    apply_dilation: bool,
+    dilation_kernel_size: usize,
+) -> Result<Vec<u8>, ScanError> {
+    let grayscale = crate::grayscale::rgba_to_grayscale(rgba, width, height, weights)?;
+    canny_edge_detector_full(
+        &grayscale,
+        width,
+        height,
+        low_threshold,
+        high_threshold,
+        kernel_size,
+        sigma,
+        l2_gradient,
+        apply_dilation,
+        dilation_kernel_size,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic test image: a bright rotated-ish rectangle on noisy background
+    fn test_image(width: usize, height: usize) -> Vec<u8> {
+        let mut seed = 12345u32;
+        let mut img = vec![0u8; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let noise = (seed >> 24) as u8 / 8;
+                let inside = x + y / 3 > 12 && x < 50 + y / 4 && y > 8 && y < 52;
+                img[y * width + x] = if inside { 200 } else { 40 } + noise;
+            }
+        }
+        img
     }
 
-    canny_edges
+    #[test]
+    fn test_fused_canny_matches_staged_pipeline() {
+        let (width, height) = (64, 60);
+        let gray = test_image(width, height);
+        for &l2 in &[false, true] {
+            let (low, high) = if l2 { (10.0, 20.0) } else { (50.0, 150.0) };
+            let fused = canny_edge_detector_full(&gray, width, height, low, high, 5, 0.0, l2, false, 3).unwrap();
+            let mut options = CannyOptions::new();
+            options.set_thresholds(low, high);
+            options.set_l2_gradient(l2);
+            options.set_dilation(0);
+            assert_eq!(canny_edge_detector(&gray, width, height, &options).unwrap(), fused);
+
+            let blurred = crate::blur(&gray, width, height, 5, 0.0).unwrap();
+            let gradients = crate::gradient_calculation::calculate_gradients(&blurred, width, height).unwrap();
+            let dx: Vec<i16> = gradients.iter().step_by(2).copied().collect();
+            let dy: Vec<i16> = gradients.iter().skip(1).step_by(2).copied().collect();
+            let suppressed =
+                crate::non_maximum_suppression::non_maximum_suppression(&dx, &dy, width, height, l2).unwrap();
+            let (low, high) = if l2 { (low * low, high * high) } else { (low, high) };
+            let staged =
+                crate::hysteresis::hysteresis_thresholding_binary(&suppressed, width, height, low, high).unwrap();
+
+            assert_eq!(fused, staged);
+            assert!(fused.contains(&255));
+        }
+
+        // Diagonal fractions resolve to the same odd pixel sizes at any resolution
+        assert_eq!(relative_kernel_size(0.01, 1280, 720), 15);
+        assert_eq!(relative_kernel_size(0.01, 1920, 1080), 23);
+        assert_eq!(relative_kernel_size(0.0, 1920, 1080), 3);
+        let mut relative = CannyOptions::new();
+        relative.set_blur_relative(0.05, 0.0);
+        relative.set_dilation_relative(0.035);
+        let mut pixels = CannyOptions::new();
+        pixels.set_dilation(3);
+        assert_eq!(relative.for_size(width, height), pixels);
+        let edges = canny_edge_detector(&gray, width, height, &relative);
+        assert_eq!(edges, canny_edge_detector(&gray, width, height, &pixels));
+        relative.set_dilation(5);
+        assert_eq!((relative.dilation_fraction(), relative.blur_fraction()), (0.0, 0.05));
+    }
 }
@@ -0,0 +1,190 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::canny::{canny_edge_detector, CannyOptions};
+use crate::detection::{edge_support, label_enclosed_regions, quad_area, region_corners};
+use crate::error::{check_index, check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::warp::{quad_output_size, rect_to_quad_matrix, warp_with_matrix, OutOfBoundsPolicy};
+
+// Share of the frame one card may cover: several have to fit next to each
+// other, and anything smaller is a logo or a printed box on a card.
+const MIN_CARD_FRACTION: f32 = 0.01;
+const MAX_CARD_FRACTION: f32 = 0.4;
+// Long to short side of the rectified card. Covers ID-1 (1.59), EU (1.55) and
+// US (1.75) business cards with room for perspective.
+const MIN_ASPECT: f32 = 1.3;
+const MAX_ASPECT: f32 = 2.0;
+// Least share of the quad the enclosed region has to fill; rounded corners and
+// the diagonal corner fit lose a little.
+const MIN_FILL: f32 = 0.85;
+// Least share of the quad's sides backed by edges.
+const MIN_SUPPORT: f32 = 0.7;
+
+/// Cards found by `scan_cards`, in reading order (rows top to bottom, left to
+/// right within a row), each with its quad in the frame and the rectified
+/// image.
+#[wasm_bindgen]
+pub struct CardScan {
+    cards: Vec<Card>,
+}
+
+struct Card {
+    corners: [f32; 8],
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl CardScan {
+    pub fn count(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Corners of one card in the frame (TL, TR, BR, BL as 8 values).
+    pub fn corners(&self, index: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(index)?.corners.to_vec())
+    }
+
+    pub fn width(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.width)
+    }
+
+    pub fn height(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.height)
+    }
+
+    /// Rectified RGBA pixels of one card.
+    pub fn image(&self, index: usize) -> Result<Vec<u8>, ScanError> {
+        Ok(self.get(index)?.rgba.clone())
+    }
+}
+
+impl CardScan {
+    fn get(&self, index: usize) -> Result<&Card, ScanError> {
+        check_index(index, self.cards.len())?;
+        Ok(&self.cards[index])
+    }
+}
+
+// Quads of the card-shaped regions enclosed by `edges`, in reading order.
+pub(crate) fn find_cards(edges: &[u8], width: usize, height: usize, margin: f32) -> Vec<[f32; 8]> {
+    let (labels, pixels) = label_enclosed_regions(edges, width, height);
+    let frame = (width * height) as f32;
+    let mut quads: Vec<[f32; 8]> = Vec::new();
+    for (index, &count) in pixels.iter().enumerate() {
+        let share = count as f32 / frame;
+        if !(MIN_CARD_FRACTION..=MAX_CARD_FRACTION).contains(&share) {
+            continue;
+        }
+        let corners = region_corners(&labels, index as u32 + 1, width, height, margin);
+        let (w, h) = quad_output_size(&corners);
+        let aspect = w.max(h) as f32 / w.min(h) as f32;
+        let area = quad_area(&corners);
+        let fill = if area > 0.0 { count as f32 / area } else { 0.0 };
+        if (MIN_ASPECT..=MAX_ASPECT).contains(&aspect)
+            && fill >= MIN_FILL
+            && edge_support(edges, width, height, &corners) >= MIN_SUPPORT
+        {
+            quads.push(corners);
+        }
+    }
+
+    // Cards whose centres lie within half a card height of each other share a
+    // row; rows run top to bottom, cards left to right
+    let centre = |q: &[f32; 8]| ((q[0] + q[2] + q[4] + q[6]) / 4.0, (q[1] + q[3] + q[5] + q[7]) / 4.0);
+    quads.sort_by(|a, b| centre(a).1.total_cmp(&centre(b).1));
+    let mut rows: Vec<Vec<[f32; 8]>> = Vec::new();
+    for quad in quads {
+        let (_, cy) = centre(&quad);
+        let half_height = quad_output_size(&quad).1 as f32 / 2.0;
+        match rows.last_mut() {
+            Some(row) if (cy - centre(&row[0]).1).abs() < half_height => row.push(quad),
+            _ => rows.push(vec![quad]),
+        }
+    }
+    rows.into_iter()
+        .flat_map(|mut row| {
+            row.sort_by(|a, b| centre(a).0.total_cmp(&centre(b).0));
+            row
+        })
+        .collect()
+}
+
+/// Business-card mode: finds every card-sized quad in one shot of several
+/// cards laid out on a contrasting surface, e.g. a grid of business cards or
+/// ID cards, and rectifies each one separately. Cards have to cover 1-40 % of
+/// the frame each, with a side ratio of 1.3-2.0, and must not touch each other.
+///
+/// # Arguments
+/// * `rgba` - RGBA frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `options` - Canny settings; dilation closes small gaps in the card borders
+///
+/// # Returns
+/// `CardScan` with the corners and rectified image of each card
+#[wasm_bindgen]
+pub fn scan_cards(rgba: &[u8], width: usize, height: usize, options: &CannyOptions) -> Result<CardScan, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, GrayscaleWeights::Bt601);
+    let options = options.for_size(width, height);
+    let edges = canny_edge_detector(&gray, width, height, &options)?;
+    let margin = (options.dilation_kernel_size() / 2) as f32;
+
+    let cards = find_cards(&edges, width, height, margin)
+        .into_iter()
+        .filter_map(|corners| {
+            let (card_width, card_height) = quad_output_size(&corners);
+            let matrix = rect_to_quad_matrix(&corners, card_width, card_height)?;
+            let policy = OutOfBoundsPolicy::Replicate;
+            let rgba = warp_with_matrix(rgba, width, height, &matrix, card_width, card_height, policy, 0);
+            Some(Card { corners, width: card_width, height: card_height, rgba })
+        })
+        .collect();
+    Ok(CardScan { cards })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_card_grid() {
+        // Two rows of cards on a dark table, slightly out of line, plus a
+        // square coaster and a tiny sticker that aren't cards. Each card is
+        // filled with its number.
+        let (width, height) = (240, 180);
+        let cards = [(20, 20, 1), (130, 26, 2), (24, 100, 3), (128, 96, 4)];
+        let mut rgba = vec![0u8; width * height * 4];
+        for (i, px) in rgba.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let card = cards.iter().find(|&&(cx, cy, _)| (cx..cx + 80).contains(&x) && (cy..cy + 48).contains(&y));
+            let value = match card {
+                Some(&(_, _, n)) => 150 + 20 * n,
+                None if (212..236).contains(&x) && (148..172).contains(&y) => 200,
+                None if (4..10).contains(&x) && (160..166).contains(&y) => 200,
+                None => 40,
+            };
+            px.copy_from_slice(&[value as u8, value as u8, value as u8, 255]);
+        }
+
+        let scan = scan_cards(&rgba, width, height, &CannyOptions::new()).unwrap();
+        assert_eq!(scan.count(), 4);
+        for (i, &(x, y, n)) in cards.iter().enumerate() {
+            let corners = scan.corners(i).unwrap();
+            let expected = [x, y, x + 79, y, x + 79, y + 47, x, y + 47].map(|v| v as f32);
+            assert!(corners.iter().zip(expected).all(|(c, e)| (c - e).abs() <= 2.0), "{i} {corners:?}");
+            let (w, h) = (scan.width(i).unwrap(), scan.height(i).unwrap());
+            assert!(w.abs_diff(80) <= 3 && h.abs_diff(48) <= 3, "{w}x{h}");
+            // The rectified card is its own fill inside a thin border
+            let image = scan.image(i).unwrap();
+            assert_eq!(image[((h / 2) * w + w / 2) * 4], (150 + 20 * n) as u8);
+        }
+        assert!(scan.image(4).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+
+// Levels below this are black, as in `PngMode::Bilevel`.
+pub(crate) const BILEVEL_THRESHOLD: u8 = 128;
+// Mode codes (T.4 table 4) as (code, length).
+const PASS: (u16, u8) = (0b0001, 4);
+const HORIZONTAL: (u16, u8) = (0b001, 3);
+// Vertical mode codes by the offset a1 - b1, from -3 to 3.
+const VERTICAL: [(u16, u8); 7] =
+    [(0b0000010, 7), (0b000010, 6), (0b010, 3), (0b1, 1), (0b011, 3), (0b000011, 6), (0b0000011, 7)];
+// End of line; two of them end the image (EOFB).
+const EOL: (u16, u8) = (0b000000000001, 12);
+// Longest run a single makeup code covers.
+const MAX_MAKEUP: usize = 2560;
+
+// Bits written most significant first, as fax and TIFF LZW coders expect.
+pub(crate) struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn with_capacity(bytes: usize) -> BitWriter {
+        BitWriter { out: Vec::with_capacity(bytes), acc: 0, bits: 0 }
+    }
+
+    pub(crate) fn put(&mut self, (code, len): (u16, u8)) {
+        self.acc = self.acc << len | code as u32;
+        self.bits += len as u32;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.out.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1 << self.bits) - 1;
+    }
+
+    // Pads the last byte with zeros.
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push((self.acc << (8 - self.bits)) as u8);
+        }
+        self.out
+    }
+}
+
+// Writes a run as makeup codes for its multiples of 64 and a terminating code.
+fn put_run(w: &mut BitWriter, mut run: usize, codes: &[(u16, u8); 104]) {
+    while run > MAX_MAKEUP {
+        w.put(codes[103]);
+        run -= MAX_MAKEUP;
+    }
+    if run >= 64 {
+        w.put(codes[63 + run / 64]);
+        run %= 64;
+    }
+    w.put(codes[run]);
+}
+
+// Positions where a row changes colour, starting from an imaginary white
+// pixel, so even entries turn black and odd ones white; padded with the
+// width, where the decoder sees a change of either colour.
+fn changes(row: &[bool], into: &mut Vec<usize>) {
+    into.clear();
+    let mut previous = false;
+    for (x, &black) in row.iter().enumerate() {
+        if black != previous {
+            into.push(x);
+            previous = black;
+        }
+    }
+    into.extend_from_slice(&[row.len(); 3]);
+}
+
+// Codes one row against the row above with the 2D modes of T.6 section 2.2.
+fn encode_row(w: &mut BitWriter, coding: &[usize], reference: &[usize], width: usize) {
+    // a0 starts on an imaginary white pixel left of the row
+    let (mut a0, mut black, mut started) = (0, false, false);
+    let (mut i, mut j) = (0, 0);
+    loop {
+        // Changes right of a0: a1 on this row, b1 of the opposite colour above
+        while started && coding[i] <= a0 {
+            i += 1;
+        }
+        while started && reference[j] <= a0 {
+            j += 1;
+        }
+        let b = j + usize::from((j % 2 == 1) != black);
+        let (a1, b1, b2) = (coding[i], reference[b], reference[b + 1]);
+        if b2 < a1 {
+            w.put(PASS);
+            a0 = b2;
+        } else if a1.abs_diff(b1) <= 3 {
+            w.put(VERTICAL[(a1 as isize - b1 as isize + 3) as usize]);
+            a0 = a1;
+            black = !black;
+        } else {
+            let a2 = coding[i + 1];
+            w.put(HORIZONTAL);
+            let (first, second) = if black { (&BLACK_CODES, &WHITE_CODES) } else { (&WHITE_CODES, &BLACK_CODES) };
+            put_run(w, a1 - a0, first);
+            put_run(w, a2 - a1, second);
+            a0 = a2;
+        }
+        started = true;
+        if a0 >= width {
+            break;
+        }
+    }
+}
+
+/// Encodes a black-and-white page as CCITT Group 4 (T.6), the standard
+/// compression of fax and archival scans: a binarized text page comes out
+/// far smaller than as JPEG or PNG. The output can go straight into a PDF
+/// (`PdfDocument::add_ccitt_page`) or a TIFF with compression 4.
+///
+/// # Arguments
+/// * `image` - Grayscale or RGBA pixels, black below 128; the layout is
+///   taken from the length, and RGBA is reduced to its luma
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Group 4 coded rows without a header, ended by EOFB and padded to a byte
+#[wasm_bindgen]
+pub fn encode_ccitt_g4(image: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    let pixels = image_len(width, height, 1)?;
+    let mut converted = Vec::new();
+    let gray = if image.len() == pixels {
+        image
+    } else {
+        check_len(image.len(), image_len(width, height, 4)?, "width * height or width * height * 4")?;
+        converted.resize(pixels, 0);
+        rgba_to_grayscale_into(image, &mut converted, GrayscaleWeights::Bt601);
+        &converted
+    };
+    if width == 0 || height == 0 {
+        return Err(ScanError::InvalidParameter("Image width and height must be at least 1"));
+    }
+
+    let mut w = BitWriter::with_capacity(pixels / 64);
+    let (mut row, mut coding, mut reference) = (vec![false; width], Vec::new(), Vec::new());
+    // The row above the first is white
+    changes(&row, &mut reference);
+    for line in gray.chunks_exact(width) {
+        for (black, &v) in row.iter_mut().zip(line) {
+            *black = v < BILEVEL_THRESHOLD;
+        }
+        changes(&row, &mut coding);
+        encode_row(&mut w, &coding, &reference, width);
+        core::mem::swap(&mut coding, &mut reference);
+    }
+    w.put(EOL);
+    w.put(EOL);
+    Ok(w.finish())
+}
+
+// Modified Huffman codes (T.4 tables 2 and 3) as (code, length): the
+// terminating codes of runs 0 to 63, then the makeup codes of 64 to 2560.
+#[rustfmt::skip]
+const WHITE_CODES: [(u16, u8); 104] = [
+    (0b00110101, 8), (0b000111, 6), (0b0111, 4), (0b1000, 4), (0b1011, 4), (0b1100, 4), (0b1110, 4), (0b1111, 4),
+    (0b10011, 5), (0b10100, 5), (0b00111, 5), (0b01000, 5), (0b001000, 6), (0b000011, 6), (0b110100, 6),
+    (0b110101, 6), (0b101010, 6), (0b101011, 6), (0b0100111, 7), (0b0001100, 7), (0b0001000, 7), (0b0010111, 7),
+    (0b0000011, 7), (0b0000100, 7), (0b0101000, 7), (0b0101011, 7), (0b0010011, 7), (0b0100100, 7), (0b0011000, 7),
+    (0b00000010, 8), (0b00000011, 8), (0b00011010, 8), (0b00011011, 8), (0b00010010, 8), (0b00010011, 8),
+    (0b00010100, 8), (0b00010101, 8), (0b00010110, 8), (0b00010111, 8), (0b00101000, 8), (0b00101001, 8),
+    (0b00101010, 8), (0b00101011, 8), (0b00101100, 8), (0b00101101, 8), (0b00000100, 8), (0b00000101, 8),
+    (0b00001010, 8), (0b00001011, 8), (0b01010010, 8), (0b01010011, 8), (0b01010100, 8), (0b01010101, 8),
+    (0b00100100, 8), (0b00100101, 8), (0b01011000, 8), (0b01011001, 8), (0b01011010, 8), (0b01011011, 8),
+    (0b01001010, 8), (0b01001011, 8), (0b00110010, 8), (0b00110011, 8), (0b00110100, 8), (0b11011, 5), (0b10010, 5),
+    (0b010111, 6), (0b0110111, 7), (0b00110110, 8), (0b00110111, 8), (0b01100100, 8), (0b01100101, 8),
+    (0b01101000, 8), (0b01100111, 8), (0b011001100, 9), (0b011001101, 9), (0b011010010, 9), (0b011010011, 9),
+    (0b011010100, 9), (0b011010101, 9), (0b011010110, 9), (0b011010111, 9), (0b011011000, 9), (0b011011001, 9),
+    (0b011011010, 9), (0b011011011, 9), (0b010011000, 9), (0b010011001, 9), (0b010011010, 9), (0b011000, 6),
+    (0b010011011, 9), (0b00000001000, 11), (0b00000001100, 11), (0b00000001101, 11), (0b000000010010, 12),
+    (0b000000010011, 12), (0b000000010100, 12), (0b000000010101, 12), (0b000000010110, 12), (0b000000010111, 12),
+    (0b000000011100, 12), (0b000000011101, 12), (0b000000011110, 12), (0b000000011111, 12),
+];
+#[rustfmt::skip]
+const BLACK_CODES: [(u16, u8); 104] = [
+    (0b0000110111, 10), (0b010, 3), (0b11, 2), (0b10, 2), (0b011, 3), (0b0011, 4), (0b0010, 4), (0b00011, 5),
+    (0b000101, 6), (0b000100, 6), (0b0000100, 7), (0b0000101, 7), (0b0000111, 7), (0b00000100, 8), (0b00000111, 8),
+    (0b000011000, 9), (0b0000010111, 10), (0b0000011000, 10), (0b0000001000, 10), (0b00001100111, 11),
+    (0b00001101000, 11), (0b00001101100, 11), (0b00000110111, 11), (0b00000101000, 11), (0b00000010111, 11),
+    (0b00000011000, 11), (0b000011001010, 12), (0b000011001011, 12), (0b000011001100, 12), (0b000011001101, 12),
+    (0b000001101000, 12), (0b000001101001, 12), (0b000001101010, 12), (0b000001101011, 12), (0b000011010010, 12),
+    (0b000011010011, 12), (0b000011010100, 12), (0b000011010101, 12), (0b000011010110, 12), (0b000011010111, 12),
+    (0b000001101100, 12), (0b000001101101, 12), (0b000011011010, 12), (0b000011011011, 12), (0b000001010100, 12),
+    (0b000001010101, 12), (0b000001010110, 12), (0b000001010111, 12), (0b000001100100, 12), (0b000001100101, 12),
+    (0b000001010010, 12), (0b000001010011, 12), (0b000000100100, 12), (0b000000110111, 12), (0b000000111000, 12),
+    (0b000000100111, 12), (0b000000101000, 12), (0b000001011000, 12), (0b000001011001, 12), (0b000000101011, 12),
+    (0b000000101100, 12), (0b000001011010, 12), (0b000001100110, 12), (0b000001100111, 12), (0b0000001111, 10),
+    (0b000011001000, 12), (0b000011001001, 12), (0b000001011011, 12), (0b000000110011, 12), (0b000000110100, 12),
+    (0b000000110101, 12), (0b0000001101100, 13), (0b0000001101101, 13), (0b0000001001010, 13), (0b0000001001011, 13),
+    (0b0000001001100, 13), (0b0000001001101, 13), (0b0000001110010, 13), (0b0000001110011, 13), (0b0000001110100, 13),
+    (0b0000001110101, 13), (0b0000001110110, 13), (0b0000001110111, 13), (0b0000001010010, 13), (0b0000001010011, 13),
+    (0b0000001010100, 13), (0b0000001010101, 13), (0b0000001011010, 13), (0b0000001011011, 13), (0b0000001100100, 13),
+    (0b0000001100101, 13), (0b00000001000, 11), (0b00000001100, 11), (0b00000001101, 11), (0b000000010010, 12),
+    (0b000000010011, 12), (0b000000010100, 12), (0b000000010101, 12), (0b000000010110, 12), (0b000000010111, 12),
+    (0b000000011100, 12), (0b000000011101, 12), (0b000000011110, 12), (0b000000011111, 12),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sum of the makeup codes and the terminating code of a run.
+    fn read_run(read: &mut impl FnMut(&[(u16, u8)]) -> usize, codes: &[(u16, u8); 104]) -> usize {
+        let mut run = 0;
+        loop {
+            let k = read(codes);
+            if k < 64 {
+                return run + k;
+            }
+            run += (k - 63) * 64;
+        }
+    }
+
+    // Decodes Group 4 data as a fax reader does, into rows of black flags.
+    fn decode(data: &[u8], width: usize, height: usize) -> Vec<bool> {
+        let mut pos = 0;
+        // Index in `codes` of the code the next bits start with
+        let mut read = |codes: &[(u16, u8)]| -> usize {
+            let mut code = 0;
+            for len in 1..=13u8 {
+                let n = pos + len as usize - 1;
+                code = code << 1 | (data[n / 8] >> (7 - n % 8) & 1) as u16;
+                if let Some(k) = codes.iter().position(|&c| c == (code, len)) {
+                    pos += len as usize;
+                    return k;
+                }
+            }
+            panic!("no code at bit {pos}");
+        };
+        let modes: Vec<(u16, u8)> = [PASS, HORIZONTAL].into_iter().chain(VERTICAL).chain([EOL]).collect();
+        let (mut image, mut reference, mut row) = (Vec::new(), vec![width; 3], vec![false; width]);
+        for _ in 0..height {
+            let (mut a0, mut black, mut started) = (0, false, false);
+            loop {
+                let j = reference.iter().position(|&c| !started || c > a0).unwrap();
+                let b = j + usize::from((j % 2 == 1) != black);
+                let (b1, b2) = (reference[b], reference[b + 1]);
+                let mut fill = |from: usize, to: usize, black: bool| row[from..to.min(width)].fill(black);
+                match read(&modes) {
+                    0 => {
+                        fill(a0, b2, black);
+                        a0 = b2;
+                    }
+                    1 => {
+                        let (codes, other) =
+                            if black { (&BLACK_CODES, &WHITE_CODES) } else { (&WHITE_CODES, &BLACK_CODES) };
+                        let first = read_run(&mut read, codes);
+                        let second = read_run(&mut read, other);
+                        fill(a0, a0 + first, black);
+                        fill(a0 + first, a0 + first + second, !black);
+                        a0 += first + second;
+                    }
+                    k @ 2..=8 => {
+                        let a1 = (b1 + k - 2).checked_sub(3).unwrap();
+                        fill(a0, a1, black);
+                        a0 = a1;
+                        black = !black;
+                    }
+                    _ => panic!("EOL inside the image"),
+                }
+                started = true;
+                if a0 >= width {
+                    break;
+                }
+            }
+            changes(&row, &mut reference);
+            image.extend_from_slice(&row);
+        }
+        assert_eq!((read(&modes), read(&modes)), (9, 9));
+        image
+    }
+
+    #[test]
+    fn test_encode_ccitt_g4() {
+        // Text-like strokes and a frame on a page 101 px wide, so rows end
+        // inside a byte and runs need makeup codes
+        let (width, height) = (101, 40);
+        let page: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let ink = (y % 10 < 6 && (10..90).contains(&x) && (x / 3 + y / 4) % 4 == 0) || x == 0 || y == 39;
+                if ink {
+                    20
+                } else {
+                    235
+                }
+            })
+            .collect();
+        let g4 = encode_ccitt_g4(&page, width, height).unwrap();
+        let black: Vec<bool> = page.iter().map(|&v| v < BILEVEL_THRESHOLD).collect();
+        assert_eq!(decode(&g4, width, height), black);
+        assert!(g4.len() < width * height / 8 / 2, "{}", g4.len());
+
+        // Runs longer than the largest makeup code, and black from the first pixel
+        let (width, height) = (6000, 3);
+        let wide: Vec<u8> =
+            (0..width * height).map(|i| if i % width < 3 || i % width > 5900 { 0 } else { 255 }).collect();
+        let black: Vec<bool> = wide.iter().map(|&v| v < BILEVEL_THRESHOLD).collect();
+        assert_eq!(decode(&encode_ccitt_g4(&wide, width, height).unwrap(), width, height), black);
+
+        let rgba: Vec<u8> = page.iter().flat_map(|&v| [v, v, v, 255]).collect();
+        assert_eq!(encode_ccitt_g4(&rgba, 101, 40).unwrap(), g4);
+        assert!(encode_ccitt_g4(&page[1..], 101, 40).is_err());
+        assert!(encode_ccitt_g4(&[], 0, 0).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_rgba, ScanError};
+
+// BT.601 full-range (JPEG) luma weights in Q16, summing to exactly 1 << 16.
+const LUMA_R: i32 = 19595;
+const LUMA_G: i32 = 38470;
+const LUMA_B: i32 = 7471;
+
+#[inline]
+fn luma_q16(px: &[u8]) -> i32 {
+    LUMA_R * px[0] as i32 + LUMA_G * px[1] as i32 + LUMA_B * px[2] as i32
+}
+
+// Luma plane (Y of full-range YCbCr) of an RGBA image.
+pub(crate) fn luma_into(rgba: &[u8], luma: &mut [u8]) {
+    for (y, px) in luma.iter_mut().zip(rgba.chunks_exact(4)) {
+        *y = ((luma_q16(px) + (1 << 15)) >> 16) as u8;
+    }
+}
+
+// Replaces the luma of every pixel while keeping its chroma (Cb, Cr) and alpha.
+// With Cb and Cr fixed, R, G and B each move by exactly the luma change, so
+// this is the YCbCr split/merge without rounding the chroma planes to bytes.
+pub(crate) fn merge_luma_into(rgba: &[u8], luma: &[u8], out: &mut [u8]) {
+    for ((dst, src), &y) in out.chunks_exact_mut(4).zip(rgba.chunks_exact(4)).zip(luma) {
+        let delta = ((y as i32) << 16) - luma_q16(src);
+        let shift = (delta + (1 << 15)) >> 16;
+        for (d, &s) in dst[..3].iter_mut().zip(&src[..3]) {
+            *d = (s as i32 + shift).clamp(0, 255) as u8;
+        }
+        dst[3] = src[3];
+    }
+}
+
+// Runs `filter` on the luma plane of an RGBA image and merges the result back.
+fn filter_luma(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    filter: impl FnOnce(&[u8]) -> Result<Vec<u8>, ScanError>,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut luma = vec![0u8; width * height];
+    luma_into(rgba, &mut luma);
+    let filtered = filter(&luma)?;
+    let mut out = vec![0u8; rgba.len()];
+    merge_luma_into(rgba, &filtered, &mut out);
+    Ok(out)
+}
+
+// Number of histogram bins (one per grey level).
+const BINS: usize = 256;
+
+// Histogram-equalization LUT of one tile, with the histogram clipped at
+// `clip_limit` times its mean bin height and the excess spread over all bins.
+fn clahe_lut(gray: &[u8], width: usize, x0: usize, x1: usize, y0: usize, y1: usize, clip_limit: f32) -> [u8; BINS] {
+    let mut histogram = [0u32; BINS];
+    for row in gray[y0 * width..y1 * width].chunks_exact(width) {
+        for &v in &row[x0..x1] {
+            histogram[v as usize] += 1;
+        }
+    }
+    let pixels = ((x1 - x0) * (y1 - y0)) as u32;
+
+    if clip_limit > 0.0 {
+        let limit = ((clip_limit * pixels as f32 / BINS as f32) as u32).max(1);
+        let mut excess = 0;
+        for bin in histogram.iter_mut() {
+            if *bin > limit {
+                excess += *bin - limit;
+                *bin = limit;
+            }
+        }
+        let (spread, remainder) = (excess / BINS as u32, (excess % BINS as u32) as usize);
+        for (i, bin) in histogram.iter_mut().enumerate() {
+            *bin += spread + (i < remainder) as u32;
+        }
+    }
+
+    let mut lut = [0u8; BINS];
+    let mut cdf = 0u32;
+    for (entry, &count) in lut.iter_mut().zip(&histogram) {
+        cdf += count;
+        *entry = ((cdf as u64 * 255 + pixels as u64 / 2) / pixels as u64) as u8;
+    }
+    lut
+}
+
+// Contrast-limited adaptive histogram equalization into a caller-owned buffer.
+pub(crate) fn clahe_into(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    tiles_x: usize,
+    tiles_y: usize,
+    clip_limit: f32,
+    out: &mut [u8],
+) {
+    let tiles_x = tiles_x.clamp(1, width.max(1));
+    let tiles_y = tiles_y.clamp(1, height.max(1));
+    let bound = |i: usize, tiles: usize, len: usize| i * len / tiles;
+
+    let mut luts = Vec::with_capacity(tiles_x * tiles_y);
+    for ty in 0..tiles_y {
+        for tx in 0..tiles_x {
+            let (x0, x1) = (bound(tx, tiles_x, width), bound(tx + 1, tiles_x, width));
+            let (y0, y1) = (bound(ty, tiles_y, height), bound(ty + 1, tiles_y, height));
+            luts.push(clahe_lut(gray, width, x0, x1, y0, y1, clip_limit));
+        }
+    }
+
+    // Each pixel blends the LUTs of the four nearest tile centres bilinearly, so
+    // there are no visible tile seams
+    let tile_w = width as f32 / tiles_x as f32;
+    let tile_h = height as f32 / tiles_y as f32;
+    let neighbours = |pos: f32, tile: f32, tiles: usize| {
+        let t = (pos + 0.5) / tile - 0.5;
+        let i0 = t.max(0.0) as usize;
+        let i0 = i0.min(tiles - 1);
+        let i1 = (i0 + 1).min(tiles - 1);
+        (i0, i1, (t - i0 as f32).clamp(0.0, 1.0))
+    };
+
+    for (y, row) in out.chunks_exact_mut(width).enumerate() {
+        let (ty0, ty1, fy) = neighbours(y as f32, tile_h, tiles_y);
+        for (x, value) in row.iter_mut().enumerate() {
+            let (tx0, tx1, fx) = neighbours(x as f32, tile_w, tiles_x);
+            let v = gray[y * width + x] as usize;
+            let top = luts[ty0 * tiles_x + tx0][v] as f32 * (1.0 - fx) + luts[ty0 * tiles_x + tx1][v] as f32 * fx;
+            let bottom = luts[ty1 * tiles_x + tx0][v] as f32 * (1.0 - fx) + luts[ty1 * tiles_x + tx1][v] as f32 * fx;
+            *value = (top * (1.0 - fy) + bottom * fy + 0.5) as u8;
+        }
+    }
+}
+
+/// Contrast-limited adaptive histogram equalization (CLAHE) of a grayscale image.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `tiles_x` - Number of tile columns (8 is typical)
+/// * `tiles_y` - Number of tile rows
+/// * `clip_limit` - Histogram clip as a multiple of the mean bin height (2..4 is
+///   typical); 0 disables clipping (plain adaptive equalization)
+///
+/// # Returns
+/// Equalized image as Vec<u8>
+#[wasm_bindgen]
+pub fn clahe(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    tiles_x: usize,
+    tiles_y: usize,
+    clip_limit: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let mut out = vec![0u8; width * height];
+    if width > 0 && height > 0 {
+        clahe_into(gray, width, height, tiles_x, tiles_y, clip_limit, &mut out);
+    }
+    Ok(out)
+}
+
+/// Luma (Y of full-range BT.601 YCbCr) of an RGBA image. Filter it with any
+/// grayscale stage and put it back with `merge_luma`.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Luma plane as Vec<u8>
+#[wasm_bindgen]
+pub fn luma_plane(rgba: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut luma = vec![0u8; width * height];
+    luma_into(rgba, &mut luma);
+    Ok(luma)
+}
+
+/// Replaces the luma of an RGBA image, keeping each pixel's chroma and alpha.
+///
+/// # Arguments
+/// * `rgba` - Original RGBA pixels (supplies chroma and alpha)
+/// * `luma` - New luma plane, width * height bytes
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn merge_luma(rgba: &[u8], luma: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    check_gray(luma, width, height)?;
+    let mut out = vec![0u8; rgba.len()];
+    merge_luma_into(rgba, luma, &mut out);
+    Ok(out)
+}
+
+/// Gaussian blur of the luma channel only, leaving colours untouched.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Blur kernel size (odd)
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn blur_luma(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    sigma: f32,
+) -> Result<Vec<u8>, ScanError> {
+    filter_luma(rgba, width, height, |luma| crate::blur(luma, width, height, kernel_size, sigma))
+}
+
+/// Unsharp-mask sharpening of the luma channel only, so chroma noise is not
+/// amplified and hues do not shift.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Blur kernel size of the mask (odd)
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+/// * `amount` - Strength; 1 adds the full difference to the blurred image
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn sharpen_luma(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    sigma: f32,
+    amount: f32,
+) -> Result<Vec<u8>, ScanError> {
+    filter_luma(rgba, width, height, |luma| {
+        let blurred = crate::blur(luma, width, height, kernel_size, sigma)?;
+        Ok(luma
+            .iter()
+            .zip(&blurred)
+            .map(|(&y, &b)| (y as f32 + amount * (y as f32 - b as f32)).round().clamp(0.0, 255.0) as u8)
+            .collect())
+    })
+}
+
+/// CLAHE on the luma channel only (see `clahe`).
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `tiles_x` - Number of tile columns
+/// * `tiles_y` - Number of tile rows
+/// * `clip_limit` - Histogram clip as a multiple of the mean bin height
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn clahe_luma(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    tiles_x: usize,
+    tiles_y: usize,
+    clip_limit: f32,
+) -> Result<Vec<u8>, ScanError> {
+    filter_luma(rgba, width, height, |luma| clahe(luma, width, height, tiles_x, tiles_y, clip_limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_luma_filters_preserve_color() {
+        let (width, height) = (32usize, 24usize);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let v = (80 + (i % width) * 2 + (i / width)) as u8;
+                [v, v.saturating_sub(40), v / 2, 200]
+            })
+            .collect();
+
+        // Splitting and merging unchanged luma is lossless
+        let luma = luma_plane(&rgba, width, height).unwrap();
+        assert_eq!(merge_luma(&rgba, &luma, width, height).unwrap(), rgba);
+
+        // CLAHE stretches the narrow luma range; chroma differences and alpha stay
+        let enhanced = clahe_luma(&rgba, width, height, 2, 2, 3.0).unwrap();
+        let range = |img: &[u8]| {
+            let l = luma_plane(img, width, height).unwrap();
+            l.iter().max().unwrap() - l.iter().min().unwrap()
+        };
+        assert!(range(&enhanced) > range(&rgba));
+        for (a, b) in enhanced.chunks_exact(4).zip(rgba.chunks_exact(4)).filter(|(a, _)| a[..3].iter().all(|&c| c > 0 && c < 255)) {
+            assert_eq!(a[0] as i32 - a[1] as i32, b[0] as i32 - b[1] as i32);
+            assert_eq!(a[3], 200);
+        }
+
+        // Gray pixels stay gray through luma-only blur
+        let gray_rgba: Vec<u8> = luma.iter().flat_map(|&v| [v, v, v, 255]).collect();
+        let blurred = blur_luma(&gray_rgba, width, height, 5, 0.0).unwrap();
+        assert!(blurred.chunks_exact(4).all(|p| p[0] == p[1] && p[1] == p[2]));
+    }
+}
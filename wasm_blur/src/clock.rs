@@ -0,0 +1,29 @@
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+extern "C" {
+    // `performance` exists on both the window and worker globals
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+// Milliseconds from an arbitrary origin, or None without a clock.
+#[cfg(target_family = "wasm")]
+pub(crate) fn now_ms() -> Option<f64> {
+    Some(performance_now())
+}
+
+#[cfg(all(not(target_family = "wasm"), feature = "std"))]
+pub(crate) fn now_ms() -> Option<f64> {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    Some(ORIGIN.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0)
+}
+
+#[cfg(all(not(target_family = "wasm"), not(feature = "std")))]
+pub(crate) fn now_ms() -> Option<f64> {
+    None
+}
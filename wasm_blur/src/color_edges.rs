@@ -0,0 +1,154 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::arena::FrameArena;
+use crate::border::BorderMode;
+use crate::colorspace::saturation_into;
+use crate::error::{check_kernel_size, check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+
+/// Image data the Canny gradient is computed from, see
+/// `ScanContext::set_edge_source`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeSource {
+    /// Brightness; finds white paper on darker backgrounds
+    Luma = 0,
+    /// Sobel on the red, green and blue channels, keeping the strongest response
+    /// per pixel, so borders between colours of similar brightness are found too
+    ColorGradient = 1,
+    /// HSV saturation; separates coloured flyers and ID cards from grey or white
+    /// surroundings
+    Saturation = 2,
+}
+
+// Fills `gray` (width * height bytes) from an RGBA frame according to `source`:
+// luma, or the saturation for `Saturation`. For `ColorGradient` the red, green
+// and blue planes also go into `planes`, and true is returned.
+pub(crate) fn load_rgba_into(
+    rgba: &[u8],
+    gray: &mut [u8],
+    planes: &mut Vec<u8>,
+    weights: GrayscaleWeights,
+    source: EdgeSource,
+) -> bool {
+    match source {
+        EdgeSource::Luma => rgba_to_grayscale_into(rgba, gray, weights),
+        EdgeSource::Saturation => saturation_into(rgba, gray),
+        EdgeSource::ColorGradient => {
+            rgba_to_grayscale_into(rgba, gray, weights);
+            let size = gray.len();
+            planes.resize(size * 3, 0);
+            for (i, px) in rgba.chunks_exact(4).enumerate() {
+                planes[i] = px[0];
+                planes[size + i] = px[1];
+                planes[2 * size + i] = px[2];
+            }
+        }
+    }
+    source == EdgeSource::ColorGradient
+}
+
+// The three colour planes filled by `load_rgba_into`.
+pub(crate) fn split_planes(planes: &[u8], size: usize) -> [&[u8]; 3] {
+    let (r, rest) = planes.split_at(size);
+    let (g, b) = rest.split_at(size);
+    [r, g, b]
+}
+
+/// Canny edge detection on an RGBA image with a selectable gradient source, for
+/// documents that differ from their background in colour rather than
+/// brightness.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `source` - Luma, per-channel colour gradient or saturation
+/// * `low_threshold` - Lower hysteresis threshold
+/// * `high_threshold` - Upper hysteresis threshold
+/// * `kernel_size` - Gaussian blur kernel size
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+/// * `l2_gradient` - Exact (L2) instead of L1 gradient magnitude
+///
+/// # Returns
+/// Binary edge map (0 or 255) as Vec<u8>
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn canny_color(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    source: EdgeSource,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    l2_gradient: bool,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    check_kernel_size(kernel_size)?;
+    let size = width * height;
+    let mut gray = vec![0u8; size];
+    let mut planes = Vec::new();
+    let color = load_rgba_into(rgba, &mut gray, &mut planes, GrayscaleWeights::Bt601, source);
+    let luma = [&gray[..]];
+    let rgb = split_planes(&planes, if color { size } else { 0 });
+    let sources: &[&[u8]] = if color { &rgb } else { &luma };
+
+    let arena = FrameArena::default();
+    let mut edges = vec![0u8; size];
+    crate::canny::canny_planes_into(
+        sources,
+        width,
+        height,
+        low_threshold,
+        high_threshold,
+        kernel_size,
+        sigma,
+        BorderMode::Replicate,
+        l2_gradient,
+        &arena,
+        &mut edges,
+    );
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_colour_edges_find_isoluminant_card() {
+        // A red card on a grey desk of the same brightness
+        let (width, height) = (32, 24);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                if (8..24).contains(&x) && (6..18).contains(&y) { [220, 60, 60, 255] } else { [108, 108, 108, 255] }
+            })
+            .collect();
+        let edges = |source| canny_color(&rgba, width, height, source, 50.0, 150.0, 3, 0.0, false).unwrap();
+        // The card's left border and its inside
+        let border = |edges: &[u8]| (edges[12 * width + 7] | edges[12 * width + 8], edges[12 * width + 16]);
+        assert_eq!(border(&edges(EdgeSource::Luma)), (0, 0));
+        for source in [EdgeSource::ColorGradient, EdgeSource::Saturation] {
+            assert_eq!(border(&edges(source)), (255, 0), "{source:?}");
+        }
+
+        // The context uses the selected source for RGBA frames only
+        let mut context = ScanContext::new(width, height);
+        context.set_dilation(0);
+        context.set_blur(3, 0.0);
+        context.set_thresholds(50.0, 150.0);
+        context.set_edge_source(EdgeSource::ColorGradient);
+        context.process_frame(&rgba).unwrap();
+        assert_eq!(context.edges(), edges(EdgeSource::ColorGradient));
+        let luma = crate::grayscale::rgba_to_grayscale(&rgba, width, height, GrayscaleWeights::Bt601).unwrap();
+        context.process_gray_frame(&luma).unwrap();
+        assert_eq!(context.edges(), edges(EdgeSource::Luma));
+    }
+}
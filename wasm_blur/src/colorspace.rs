@@ -0,0 +1,359 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+/// Colour space for `rgba_to_color_space` and `color_space_to_rgba`. Converted
+/// images stay 4 bytes per pixel with the alpha channel kept, using the 8-bit
+/// encodings of OpenCV.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Hue over the full byte range (256 steps per turn), saturation and value
+    /// 0..255
+    Hsv = 0,
+    /// CIE L*a*b* for sRGB with a D65 white point: L* scaled to 0..255, a* and
+    /// b* offset by 128
+    Lab = 1,
+    /// Full-range BT.601 YCbCr as used by JPEG
+    YCbCr = 2,
+}
+
+// Rows of `r * m[0] + g * m[1] + b * m[2] + m[3]` for the JPEG YCbCr transform
+// and its inverse.
+const RGB_TO_YCBCR: [[f32; 4]; 3] =
+    [[0.299, 0.587, 0.114, 0.0], [-0.168736, -0.331264, 0.5, 128.0], [0.5, -0.418688, -0.081312, 128.0]];
+const YCBCR_TO_RGB: [[f32; 4]; 3] =
+    [[1.0, 0.0, 1.402, -179.456], [1.0, -0.344136, -0.714136, 135.45882], [1.0, 1.772, 0.0, -226.816]];
+
+// 8-bit hue steps per degree.
+const HUE_SCALE: f32 = 256.0 / 360.0;
+
+// Linear sRGB to XYZ and back (D65), and the D65 white point.
+const RGB_TO_XYZ: [[f32; 3]; 3] =
+    [[0.4124564, 0.3575761, 0.1804375], [0.2126729, 0.7151522, 0.072175], [0.0193339, 0.119192, 0.9503041]];
+const XYZ_TO_RGB: [[f32; 3]; 3] =
+    [[3.2404542, -1.5371385, -0.4985314], [-0.969266, 1.8760108, 0.041556], [0.0556434, -0.2040259, 1.0572252]];
+const WHITE_XYZ: [f32; 3] = [0.95047, 1.0, 1.08883];
+// Break point of the L*a*b* companding function.
+const LAB_EPSILON: f32 = 6.0 / 29.0;
+
+// Rounds to the nearest byte, clamping to 0..255. The SIMD kernels round the
+// same way (add a half, truncate) so both paths give identical bytes.
+#[inline]
+fn to_u8(v: f32) -> u8 {
+    (v + 0.5).clamp(0.0, 255.0) as u8
+}
+
+#[inline]
+fn affine(m: &[[f32; 4]; 3], [r, g, b]: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * r + row[1] * g + row[2] * b + row[3])
+}
+
+// Hue in degrees (0..360), saturation 0..1 and value in the input range.
+pub(crate) fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let v = r.max(g).max(b);
+    let delta = v - r.min(g).min(b);
+    if delta == 0.0 {
+        return [0.0, 0.0, v];
+    }
+    let h = if v == r {
+        let h = 60.0 * (g - b) / delta;
+        if h < 0.0 {
+            h + 360.0
+        } else {
+            h
+        }
+    } else if v == g {
+        60.0 * (b - r) / delta + 120.0
+    } else {
+        60.0 * (r - g) / delta + 240.0
+    };
+    [h, delta / v, v]
+}
+
+pub(crate) fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let chroma = v * s;
+    let sector = h / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let m = v - chroma;
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + m, g + m, b + m]
+}
+
+// sRGB transfer function, 0..255 to linear 0..1 and back.
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    let c = c / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    c * 255.0
+}
+
+// L* (0..100), a* and b* of a linear RGB colour.
+pub(crate) fn linear_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let f = |t: f32| {
+        if t > LAB_EPSILON * LAB_EPSILON * LAB_EPSILON {
+            t.cbrt()
+        } else {
+            t / (3.0 * LAB_EPSILON * LAB_EPSILON) + 4.0 / 29.0
+        }
+    };
+    let [x, y, z] = core::array::from_fn(|i| {
+        let row = RGB_TO_XYZ[i];
+        f((row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]) / WHITE_XYZ[i])
+    });
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+pub(crate) fn lab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let f_inv = |t: f32| {
+        if t > LAB_EPSILON {
+            t * t * t
+        } else {
+            3.0 * LAB_EPSILON * LAB_EPSILON * (t - 4.0 / 29.0)
+        }
+    };
+    let fy = (l + 16.0) / 116.0;
+    let xyz = [f_inv(fy + a / 500.0), f_inv(fy), f_inv(fy - b / 200.0)];
+    let xyz: [f32; 3] = core::array::from_fn(|i| xyz[i] * WHITE_XYZ[i]);
+    XYZ_TO_RGB.map(|row| row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2])
+}
+
+// HSV saturation of every pixel of an RGBA image, the S channel of
+// `rgba_to_color_space` with `ColorSpace::Hsv`.
+#[cfg_attr(not(feature = "color_edges"), allow(dead_code))]
+pub(crate) fn saturation_into(rgba: &[u8], out: &mut [u8]) {
+    for (s, px) in out.iter_mut().zip(rgba.chunks_exact(4)) {
+        *s = to_u8(rgb_to_hsv(rgb(px))[1] * 255.0);
+    }
+}
+
+// Channel `c` of 4 packed pixels as f32 lanes.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn channel(px: v128, c: u32) -> v128 {
+    f32x4_convert_u32x4(v128_and(u32x4_shr(px, 8 * c), u32x4_splat(0xff)))
+}
+
+// Same rounding as `to_u8`, as i32 lanes.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn round_u8(v: v128) -> v128 {
+    let clamped = f32x4_pmin(f32x4_pmax(f32x4_add(v, f32x4_splat(0.5)), f32x4_splat(0.0)), f32x4_splat(255.0));
+    i32x4_trunc_sat_f32x4(clamped)
+}
+
+// Packs three channels of i32 lanes with the alpha of `px`.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn pack(px: v128, channels: [v128; 3]) -> v128 {
+    let mut out = v128_and(px, u32x4_splat(0xff00_0000));
+    for (c, v) in channels.into_iter().enumerate() {
+        out = v128_or(out, u32x4_shl(v, 8 * c as u32));
+    }
+    out
+}
+
+// 4 pixels per iteration, in the operation order of `affine`.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn affine_simd(src: &[u8], dst: &mut [u8], m: &[[f32; 4]; 3]) -> usize {
+    let pixels = src.len() / 4;
+    let mut x = 0;
+    while x + 4 <= pixels {
+        let px = v128_load(src.as_ptr().add(x * 4) as *const v128);
+        let (r, g, b) = (channel(px, 0), channel(px, 1), channel(px, 2));
+        let out = m.map(|row| {
+            let sum = f32x4_add(f32x4_mul(f32x4_splat(row[0]), r), f32x4_mul(f32x4_splat(row[1]), g));
+            let sum = f32x4_add(f32x4_add(sum, f32x4_mul(f32x4_splat(row[2]), b)), f32x4_splat(row[3]));
+            round_u8(sum)
+        });
+        v128_store(dst.as_mut_ptr().add(x * 4) as *mut v128, pack(px, out));
+        x += 4;
+    }
+    x
+}
+
+// 4 pixels per iteration. All three hue formulas are evaluated and the one for
+// the maximum channel selected, like the branches of `rgb_to_hsv`; grey pixels
+// (where they divide by zero) get hue and saturation 0.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn rgb_to_hsv_simd(src: &[u8], dst: &mut [u8]) -> usize {
+    let pixels = src.len() / 4;
+    let zero = f32x4_splat(0.0);
+    let sixty = f32x4_splat(60.0);
+    let mut x = 0;
+    while x + 4 <= pixels {
+        let px = v128_load(src.as_ptr().add(x * 4) as *const v128);
+        let (r, g, b) = (channel(px, 0), channel(px, 1), channel(px, 2));
+        let v = f32x4_max(f32x4_max(r, g), b);
+        let delta = f32x4_sub(v, f32x4_min(f32x4_min(r, g), b));
+
+        let hr = f32x4_div(f32x4_mul(sixty, f32x4_sub(g, b)), delta);
+        let hr = v128_bitselect(f32x4_add(hr, f32x4_splat(360.0)), hr, f32x4_lt(hr, zero));
+        let hg = f32x4_add(f32x4_div(f32x4_mul(sixty, f32x4_sub(b, r)), delta), f32x4_splat(120.0));
+        let hb = f32x4_add(f32x4_div(f32x4_mul(sixty, f32x4_sub(r, g)), delta), f32x4_splat(240.0));
+        let h = v128_bitselect(hr, v128_bitselect(hg, hb, f32x4_eq(v, g)), f32x4_eq(v, r));
+        let grey = f32x4_eq(delta, zero);
+        let h = v128_bitselect(zero, h, grey);
+        let s = v128_bitselect(zero, f32x4_div(delta, v), grey);
+
+        // Hue wraps around instead of clamping
+        let h = i32x4_trunc_sat_f32x4(f32x4_add(f32x4_mul(h, f32x4_splat(HUE_SCALE)), f32x4_splat(0.5)));
+        let h = v128_and(h, u32x4_splat(0xff));
+        let s = round_u8(f32x4_mul(s, f32x4_splat(255.0)));
+        v128_store(dst.as_mut_ptr().add(x * 4) as *mut v128, pack(px, [h, s, round_u8(v)]));
+        x += 4;
+    }
+    x
+}
+
+#[inline]
+fn rgb(px: &[u8]) -> [f32; 3] {
+    [px[0] as f32, px[1] as f32, px[2] as f32]
+}
+
+pub(crate) fn rgba_to_color_space_into(rgba: &[u8], out: &mut [u8], space: ColorSpace) {
+    #[cfg(target_family = "wasm")]
+    let done = match space {
+        _ if !simd_enabled() => 0,
+        ColorSpace::Hsv => unsafe { rgb_to_hsv_simd(rgba, out) },
+        ColorSpace::Lab => 0,
+        ColorSpace::YCbCr => unsafe { affine_simd(rgba, out, &RGB_TO_YCBCR) },
+    };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    // Lab goes through the transfer curve and cube roots, so it stays scalar
+    // with the curve tabulated
+    let linear: [f32; 256] = if space == ColorSpace::Lab {
+        core::array::from_fn(|i| srgb_to_linear(i as f32))
+    } else {
+        [0.0; 256]
+    };
+    for (src, dst) in rgba.chunks_exact(4).zip(out.chunks_exact_mut(4)).skip(done) {
+        let converted = match space {
+            ColorSpace::Hsv => {
+                let [h, s, v] = rgb_to_hsv(rgb(src));
+                [((h * HUE_SCALE + 0.5) as u32 & 0xff) as u8, to_u8(s * 255.0), to_u8(v)]
+            }
+            ColorSpace::Lab => {
+                let [l, a, b] = linear_to_lab([0, 1, 2].map(|c| linear[src[c] as usize]));
+                [to_u8(l * 2.55), to_u8(a + 128.0), to_u8(b + 128.0)]
+            }
+            ColorSpace::YCbCr => affine(&RGB_TO_YCBCR, rgb(src)).map(to_u8),
+        };
+        dst[..3].copy_from_slice(&converted);
+        dst[3] = src[3];
+    }
+}
+
+pub(crate) fn color_space_to_rgba_into(image: &[u8], out: &mut [u8], space: ColorSpace) {
+    #[cfg(target_family = "wasm")]
+    let done = match space {
+        ColorSpace::YCbCr if simd_enabled() => unsafe { affine_simd(image, out, &YCBCR_TO_RGB) },
+        _ => 0,
+    };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (src, dst) in image.chunks_exact(4).zip(out.chunks_exact_mut(4)).skip(done) {
+        let [c0, c1, c2] = rgb(src);
+        let converted = match space {
+            ColorSpace::Hsv => hsv_to_rgb([c0 / HUE_SCALE, c1 / 255.0, c2]),
+            ColorSpace::Lab => lab_to_linear([c0 / 2.55, c1 - 128.0, c2 - 128.0]).map(linear_to_srgb),
+            ColorSpace::YCbCr => affine(&YCBCR_TO_RGB, [c0, c1, c2]),
+        };
+        dst[..3].copy_from_slice(&converted.map(to_u8));
+        dst[3] = src[3];
+    }
+}
+
+/// Converts an RGBA image to HSV, L*a*b* or YCbCr, e.g. for chroma-based
+/// background detection or skin-tone rejection.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `space` - Target colour space
+///
+/// # Returns
+/// Converted image as Vec<u8>, 4 bytes per pixel in the order of the space's
+/// name, with alpha kept
+#[wasm_bindgen]
+pub fn rgba_to_color_space(rgba: &[u8], width: usize, height: usize, space: ColorSpace) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut out = vec![0u8; rgba.len()];
+    rgba_to_color_space_into(rgba, &mut out, space);
+    Ok(out)
+}
+
+/// Converts an image from `rgba_to_color_space` back to RGBA. Out-of-gamut
+/// colours are clipped.
+///
+/// # Arguments
+/// * `image` - Converted pixels, 4 bytes each
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `space` - Colour space of `image`
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn color_space_to_rgba(image: &[u8], width: usize, height: usize, space: ColorSpace) -> Result<Vec<u8>, ScanError> {
+    check_rgba(image, width, height)?;
+    let mut out = vec![0u8; image.len()];
+    color_space_to_rgba_into(image, &mut out, space);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_space_round_trips() {
+        let colors: [[u8; 4]; 4] = [[255, 0, 0, 255], [0, 255, 0, 9], [255, 255, 255, 255], [128, 128, 128, 0]];
+        let rgba = colors.concat();
+        let hsv = rgba_to_color_space(&rgba, 2, 2, ColorSpace::Hsv).unwrap();
+        assert_eq!(hsv, [[0, 255, 255, 255], [85, 255, 255, 9], [0, 0, 255, 255], [0, 0, 128, 0]].concat());
+        let lab = rgba_to_color_space(&rgba, 2, 2, ColorSpace::Lab).unwrap();
+        assert_eq!(&lab[..4], &[136, 208, 195, 255]);
+        assert_eq!(&lab[8..12], &[255, 128, 128, 255]);
+        let ycbcr = rgba_to_color_space(&rgba, 2, 2, ColorSpace::YCbCr).unwrap();
+        assert_eq!(&ycbcr[..4], &[76, 85, 255, 255]);
+        assert_eq!(&ycbcr[12..], &[128, 128, 128, 0]);
+
+        // Every space converts back to within a few levels on a colour ramp. Whole
+        // a*/b* steps move saturated colours near the gamut edge the most
+        let ramp: Vec<u8> =
+            (0..64u32).flat_map(|i| [(i * 4) as u8, (255 - i * 3) as u8, (i * i % 256) as u8, 255]).collect();
+        for (space, tolerance) in [(ColorSpace::Hsv, 3), (ColorSpace::Lab, 8), (ColorSpace::YCbCr, 1)] {
+            let back = color_space_to_rgba(&rgba_to_color_space(&ramp, 8, 8, space).unwrap(), 8, 8, space).unwrap();
+            assert!(back.iter().zip(&ramp).all(|(&a, &b)| a.abs_diff(b) <= tolerance), "{space:?}");
+        }
+    }
+}
@@ -0,0 +1,976 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+#[cfg(feature = "analytics")]
+use crate::analytics::{PipelineStage, SessionReport, SessionStats};
+use crate::arena::FrameArena;
+#[cfg(feature = "auto_dilation")]
+use crate::auto_dilation;
+use crate::border::BorderMode;
+use crate::canny::{self, CannyOptions};
+use crate::error::{check_gray, check_len, check_rgba, image_len, ScanError};
+#[cfg(feature = "history")]
+use crate::error::{check_index, to_quad};
+#[cfg(feature = "detection")]
+use crate::detection::{self, DetectionFailure, DetectionResult};
+#[cfg(feature = "color_edges")]
+use crate::color_edges::{self, EdgeSource};
+use crate::grayscale::GrayscaleWeights;
+#[cfg(feature = "history")]
+use crate::history::DetectionHistory;
+#[cfg(feature = "low_light")]
+use crate::low_light::{self, LowLight, LowLightMode};
+#[cfg(feature = "occlusion")]
+use crate::occlusion::{self, BorderGaps};
+#[cfg(feature = "formats")]
+use crate::pixel_format::{self, PixelFormat};
+#[cfg(feature = "preset")]
+use crate::preset::{DeviceProfile, QualityPreset};
+#[cfg(feature = "readiness")]
+use crate::readiness::{self, CaptureReadiness};
+#[cfg(feature = "screen")]
+use crate::screen;
+#[cfg(feature = "shake")]
+use crate::shake::ShakeEstimator;
+use crate::warnings::{ScanWarning, Warnings};
+
+// In low-power mode only every this many frames runs the edge pipeline.
+const LOW_POWER_FRAME_INTERVAL: usize = 3;
+// Blur kernel used in low-power mode.
+const LOW_POWER_KERNEL_SIZE: usize = 3;
+
+/// Long-lived processing context for a video stream. Owns every working buffer
+/// the edge pipeline needs for one frame size, so processing a frame does not
+/// allocate and WASM memory stays flat over long camera sessions.
+#[wasm_bindgen]
+pub struct ScanContext {
+    width: usize,
+    height: usize,
+
+    // Pipeline parameters
+    weights: GrayscaleWeights,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+    l2_gradient: bool,
+    dilation_kernel_size: usize,
+    // Fractions of the frame diagonal that replace the pixel sizes when non-zero
+    blur_fraction: f32,
+    dilation_fraction: f32,
+    // Dilation size picked for the last frame while automatic sizing is on
+    #[cfg(feature = "auto_dilation")]
+    auto_dilation: Option<usize>,
+    #[cfg(feature = "color_edges")]
+    edge_source: EdgeSource,
+    // Adjustments made to the requested parameters
+    warnings: Warnings,
+
+    // Low-power mode and frame skipping state
+    low_power: bool,
+    frames_seen: usize,
+    frame_processed: bool,
+
+    // Input frame written directly by JS through `frame_buffer_ptr`
+    frame_buffer: Vec<u8>,
+
+    // Working buffers, sized for width * height
+    gray: Vec<u8>,
+    // Red, green and blue planes of the frame for `EdgeSource::ColorGradient`
+    #[cfg(feature = "color_edges")]
+    color_planes: Vec<u8>,
+    edges: Vec<u8>,
+    dilated: Vec<u8>,
+    // Whether the last processed frame was dilated, i.e. its edge map is in
+    // `dilated` rather than `edges`
+    edges_dilated: bool,
+    // Per-frame temporaries for every stage, reset at the start of each frame
+    arena: FrameArena,
+    // Thumbnail gradients of the previous frame for `shake_score`
+    #[cfg(feature = "shake")]
+    shake: ShakeEstimator,
+    // Low-light mode, exposure metric and temporal average of binned frames
+    #[cfg(feature = "low_light")]
+    low_light: LowLight,
+    // Screen-capture mode: banding removal, moiré blur and bezel suppression
+    #[cfg(feature = "screen")]
+    screen_capture: bool,
+
+    // Result of `init_profile`
+    #[cfg(feature = "preset")]
+    profile: Option<DeviceProfile>,
+    // Latest detection results recorded by the integrator
+    #[cfg(feature = "history")]
+    history: DetectionHistory,
+    // Totals for `session_report`
+    #[cfg(feature = "analytics")]
+    stats: SessionStats,
+}
+
+#[wasm_bindgen]
+impl ScanContext {
+    /// Creates a context for `width` x `height` frames. Defaults follow the JS
+    /// Canny stage: 5x5 blur, L1 gradient and 5x5 dilation, with 75/200 as fixed
+    /// thresholds in place of the adaptive ones.
+    ///
+    /// Construction does not allocate, so it can run at page load without delaying
+    /// anything. Each stage sizes its buffers the first time it runs, and optional
+    /// stages (dilation) never allocate if they stay disabled.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize) -> ScanContext {
+        ScanContext {
+            width,
+            height,
+            weights: GrayscaleWeights::Bt601,
+            low_threshold: 75.0,
+            high_threshold: 200.0,
+            kernel_size: 5,
+            sigma: 0.0,
+            border: BorderMode::Replicate,
+            l2_gradient: false,
+            dilation_kernel_size: 5,
+            blur_fraction: 0.0,
+            dilation_fraction: 0.0,
+            #[cfg(feature = "auto_dilation")]
+            auto_dilation: None,
+            #[cfg(feature = "color_edges")]
+            edge_source: EdgeSource::Luma,
+            warnings: Warnings::default(),
+            low_power: false,
+            frames_seen: 0,
+            frame_processed: false,
+            frame_buffer: Vec::new(),
+            gray: Vec::new(),
+            #[cfg(feature = "color_edges")]
+            color_planes: Vec::new(),
+            edges: Vec::new(),
+            dilated: Vec::new(),
+            edges_dilated: false,
+            arena: FrameArena::default(),
+            #[cfg(feature = "shake")]
+            shake: ShakeEstimator::default(),
+            #[cfg(feature = "low_light")]
+            low_light: LowLight::default(),
+            #[cfg(feature = "screen")]
+            screen_capture: false,
+            #[cfg(feature = "preset")]
+            profile: None,
+            #[cfg(feature = "history")]
+            history: DetectionHistory::default(),
+            #[cfg(feature = "analytics")]
+            stats: SessionStats::default(),
+        }
+    }
+
+    /// Changes the frame size. Buffers follow on the next frame and only
+    /// reallocate when the frame grows.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Frees every working buffer, the input frame buffer and the scratch arena,
+    /// e.g. between scanning sessions. The context stays usable: the next frame
+    /// re-allocates what it needs. Pointers from `edges_ptr`, `gray_ptr` and
+    /// `frame_buffer_ptr` are invalid afterwards.
+    ///
+    /// Freed memory goes back to the WASM allocator, so later allocations (another
+    /// context, an export) reuse it instead of growing the heap further. WASM linear
+    /// memory itself can only grow: `memory.buffer.byteLength` does not go down, and
+    /// the only way to hand those pages back to the browser is dropping the module
+    /// instance.
+    pub fn release_buffers(&mut self) {
+        self.frame_buffer = Vec::new();
+        self.gray = Vec::new();
+        #[cfg(feature = "color_edges")]
+        {
+            self.color_planes = Vec::new();
+        }
+        self.edges = Vec::new();
+        self.dilated = Vec::new();
+        self.edges_dilated = false;
+        self.arena.release();
+        #[cfg(feature = "shake")]
+        self.shake.release();
+        #[cfg(feature = "low_light")]
+        self.low_light.release();
+    }
+
+    /// Bytes currently held by the context's buffers and scratch arena.
+    pub fn retained_bytes(&mut self) -> usize {
+        let bytes = self.frame_buffer.capacity()
+            + self.gray.capacity()
+            + self.edges.capacity()
+            + self.dilated.capacity()
+            + self.arena.capacity();
+        #[cfg(feature = "color_edges")]
+        let bytes = bytes + self.color_planes.capacity();
+        #[cfg(feature = "shake")]
+        let bytes = bytes + self.shake.capacity();
+        #[cfg(feature = "low_light")]
+        let bytes = bytes + self.low_light.capacity();
+        bytes
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set_grayscale_weights(&mut self, weights: GrayscaleWeights) {
+        self.weights = weights;
+    }
+
+    /// Hysteresis thresholds. A low threshold above the high one is swapped and
+    /// reported as `ScanWarning::ThresholdsSwapped`.
+    pub fn set_thresholds(&mut self, low_threshold: f32, high_threshold: f32) {
+        let swapped = low_threshold > high_threshold;
+        self.warnings.set(ScanWarning::ThresholdsSwapped, swapped);
+        self.low_threshold = low_threshold.min(high_threshold);
+        self.high_threshold = high_threshold.max(low_threshold);
+    }
+
+    /// Gaussian blur before the gradient. An even `kernel_size` is rounded up to
+    /// the next odd size and reported as `ScanWarning::KernelSizeRoundedUp`.
+    pub fn set_blur(&mut self, kernel_size: usize, sigma: f32) {
+        self.warnings.set(ScanWarning::KernelSizeRoundedUp, kernel_size.is_multiple_of(2));
+        self.kernel_size = kernel_size | 1;
+        self.sigma = sigma;
+        self.blur_fraction = 0.0;
+    }
+
+    /// Gaussian blur sized as a fraction of the frame diagonal (see
+    /// `relative_kernel_size`), so it follows `resize` to another capture
+    /// resolution; 0 returns to the pixel size from `set_blur`.
+    pub fn set_blur_relative(&mut self, fraction: f32, sigma: f32) {
+        self.warnings.set(ScanWarning::KernelSizeRoundedUp, false);
+        self.blur_fraction = fraction.max(0.0);
+        self.sigma = sigma;
+    }
+
+    /// How the blur reads pixels beyond the frame border (see `BorderMode`).
+    pub fn set_border_mode(&mut self, border: BorderMode) {
+        self.border = border;
+    }
+
+    pub fn set_l2_gradient(&mut self, l2_gradient: bool) {
+        self.l2_gradient = l2_gradient;
+    }
+
+    /// Dilation kernel size applied after Canny; 0 disables dilation.
+    pub fn set_dilation(&mut self, kernel_size: usize) {
+        self.dilation_kernel_size = kernel_size;
+        self.dilation_fraction = 0.0;
+    }
+
+    /// Dilation sized as a fraction of the frame diagonal, so it follows
+    /// `resize`; 0 returns to the pixel size from `set_dilation`.
+    pub fn set_dilation_relative(&mut self, fraction: f32) {
+        self.dilation_fraction = fraction.max(0.0);
+    }
+
+    /// Applies thresholds, blur, gradient and dilation from `options` in one call,
+    /// with the same adjustments and warnings as the individual setters.
+    pub fn set_canny_options(&mut self, options: &CannyOptions) {
+        self.set_thresholds(options.low_threshold(), options.high_threshold());
+        self.set_blur(options.kernel_size(), options.sigma());
+        if options.blur_fraction() > 0.0 {
+            self.set_blur_relative(options.blur_fraction(), options.sigma());
+        }
+        self.set_border_mode(options.border_mode());
+        self.set_l2_gradient(options.l2_gradient());
+        self.set_dilation(options.dilation_kernel_size());
+        if options.dilation_fraction() > 0.0 {
+            self.set_dilation_relative(options.dilation_fraction());
+        }
+    }
+
+    /// Low-power mode for long sessions on battery, e.g. driven by the Battery
+    /// Status API. While enabled only every third frame is processed (the others
+    /// keep the previous edge map, see `frame_processed`), the pipeline runs on
+    /// integers only (3x3 fixed-point blur, L1 gradient) and dilation is skipped.
+    /// The regular settings are kept and apply again once disabled.
+    pub fn set_low_power(&mut self, enabled: bool) {
+        self.low_power = enabled;
+        self.frames_seen = 0;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn low_power(&self) -> bool {
+        self.low_power
+    }
+
+    /// Whether the last frame passed to a `process_*` method ran the pipeline.
+    /// Always true outside low-power mode.
+    pub fn frame_processed(&self) -> bool {
+        self.frame_processed
+    }
+
+    /// Adjustments made to the current settings (see `ScanWarning`), empty when
+    /// everything is used as requested.
+    pub fn warnings(&self) -> Vec<ScanWarning> {
+        self.warnings.to_vec()
+    }
+
+    /// Runs the edge pipeline on an RGBA frame. The result stays inside the
+    /// context; read it with `edges()` or view it through `edges_ptr()`.
+    pub fn process_frame(&mut self, rgba: &[u8]) -> Result<(), ScanError> {
+        check_rgba(rgba, self.width, self.height)?;
+        if !self.begin_frame() {
+            return Ok(());
+        }
+        self.ensure_buffers();
+        let color = self.load_rgba(rgba);
+        self.run_edges(color);
+        Ok(())
+    }
+
+    /// Allocates (or grows) the in-WASM input buffer to `len` bytes and returns
+    /// its address. JS can wrap it in a `Uint8Array` over `memory.buffer` and write
+    /// camera pixels straight into it, skipping the argument copy wasm-bindgen makes
+    /// for `&[u8]`. Views must be re-created whenever WASM memory grows.
+    pub fn alloc_frame_buffer(&mut self, len: usize) -> *mut u8 {
+        self.frame_buffer.resize(len, 0);
+        self.frame_buffer.as_mut_ptr()
+    }
+
+    /// Address of the input buffer (see `alloc_frame_buffer`).
+    pub fn frame_buffer_ptr(&mut self) -> *mut u8 {
+        self.frame_buffer.as_mut_ptr()
+    }
+
+    pub fn frame_buffer_len(&self) -> usize {
+        self.frame_buffer.len()
+    }
+
+    /// Runs the edge pipeline on the frame JS wrote into the input buffer. The
+    /// buffer is treated as RGBA when it holds width * height * 4 bytes and as
+    /// grayscale when it holds width * height bytes.
+    pub fn process_frame_buffer(&mut self) -> Result<(), ScanError> {
+        let size = image_len(self.width, self.height, 1)?;
+        if self.frame_buffer.len() != size {
+            let rgba_len = image_len(self.width, self.height, 4)?;
+            check_len(self.frame_buffer.len(), rgba_len, "width * height or width * height * 4")?;
+        }
+        if !self.begin_frame() {
+            return Ok(());
+        }
+        self.ensure_buffers();
+        let color = if self.frame_buffer.len() == size * 4 {
+            let frame = core::mem::take(&mut self.frame_buffer);
+            let color = self.load_rgba(&frame);
+            self.frame_buffer = frame;
+            color
+        } else {
+            self.gray.copy_from_slice(&self.frame_buffer);
+            false
+        };
+        self.run_edges(color);
+        Ok(())
+    }
+
+    /// Runs the edge pipeline on an already converted grayscale frame.
+    pub fn process_gray_frame(&mut self, gray: &[u8]) -> Result<(), ScanError> {
+        check_gray(gray, self.width, self.height)?;
+        if !self.begin_frame() {
+            return Ok(());
+        }
+        self.ensure_buffers();
+        self.gray.copy_from_slice(gray);
+        self.run_edges(false);
+        Ok(())
+    }
+
+    /// Copy of the edge map from the last processed frame.
+    pub fn edges(&self) -> Vec<u8> {
+        self.edge_buffer().to_vec()
+    }
+
+    /// Pointer to the edge map inside WASM memory (width * height bytes), valid
+    /// until the next frame after a `resize`.
+    pub fn edges_ptr(&self) -> *const u8 {
+        self.edge_buffer().as_ptr()
+    }
+
+    /// Pointer to the grayscale frame inside WASM memory (width * height bytes).
+    pub fn gray_ptr(&self) -> *const u8 {
+        self.gray.as_ptr()
+    }
+}
+
+#[cfg(feature = "formats")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Runs the edge pipeline on a frame in any supported pixel format, e.g. NV12
+    /// from a `VideoFrame` or BGRA from a native camera, without converting in JS.
+    pub fn process_frame_with_format(&mut self, data: &[u8], format: PixelFormat) -> Result<(), ScanError> {
+        let expected = pixel_format::checked_frame_len(format, self.width, self.height)?;
+        check_len(data.len(), expected, "the frame size for this pixel format")?;
+        if !self.begin_frame() {
+            return Ok(());
+        }
+        self.ensure_buffers();
+        pixel_format::to_gray_into(data, format, self.width, self.height, self.weights, &mut self.gray);
+        self.run_edges(false);
+        Ok(())
+    }
+
+    /// Like `process_frame_buffer`, with the input buffer laid out as `format`.
+    pub fn process_frame_buffer_with_format(&mut self, format: PixelFormat) -> Result<(), ScanError> {
+        let expected = pixel_format::checked_frame_len(format, self.width, self.height)?;
+        check_len(self.frame_buffer.len(), expected, "the frame size for this pixel format")?;
+        if !self.begin_frame() {
+            return Ok(());
+        }
+        self.ensure_buffers();
+        pixel_format::to_gray_into(&self.frame_buffer, format, self.width, self.height, self.weights, &mut self.gray);
+        self.run_edges(false);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "preset")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Applies a preset's blur, gradient and dilation settings. Thresholds and
+    /// grayscale weights are left as they are. Frames are still processed at the
+    /// size they are given: downscale them by the preset's `decimation` first.
+    pub fn set_quality_preset(&mut self, preset: QualityPreset) {
+        let settings = preset.settings();
+        self.set_blur(settings.kernel_size(), 0.0);
+        self.set_l2_gradient(settings.l2_gradient());
+        self.set_dilation(settings.dilation_kernel_size());
+    }
+
+    /// Benchmarks the edge pipeline for about 50 ms on a synthetic frame, stores
+    /// the resulting profile and applies its preset. Call it once at startup, so
+    /// low-end devices start with a cheaper preset and smaller frames instead of
+    /// dropping frames with the flagship defaults. The context's own buffers are
+    /// not touched.
+    pub fn init_profile(&mut self) -> DeviceProfile {
+        let profile = crate::preset::benchmark();
+        self.set_quality_preset(profile.preset());
+        self.profile = Some(profile);
+        profile
+    }
+
+    /// Profile from the last `init_profile`, if it ran.
+    pub fn profile(&self) -> Option<DeviceProfile> {
+        self.profile
+    }
+}
+
+#[cfg(feature = "color_edges")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Image data RGBA frames are edge-detected on (luma by default). Colour
+    /// gradients or saturation find coloured flyers and ID cards that luma
+    /// misses; grayscale frames always use luma. With `Saturation` the buffer
+    /// behind `gray_ptr` holds the saturation.
+    pub fn set_edge_source(&mut self, source: EdgeSource) {
+        self.edge_source = source;
+    }
+}
+
+#[cfg(feature = "auto_dilation")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Sizes the dilation of every frame from its own edge map (see
+    /// `suggest_dilation_size`) instead of the `set_dilation` size, which
+    /// applies again once disabled. Off by default; low-power mode still skips
+    /// dilation.
+    pub fn set_auto_dilation(&mut self, enabled: bool) {
+        self.auto_dilation = enabled.then_some(self.auto_dilation.unwrap_or(self.dilation_size()));
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn auto_dilation(&self) -> bool {
+        self.auto_dilation.is_some()
+    }
+
+    /// Dilation size picked for the last processed frame while automatic sizing
+    /// is on, the `set_dilation` size otherwise.
+    pub fn dilation_size_used(&self) -> usize {
+        self.auto_dilation.unwrap_or(self.dilation_size())
+    }
+}
+
+#[cfg(feature = "shake")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Camera shake between the last two processed frames (see `shake_score`),
+    /// `undefined` until two frames of the current size have been processed. An
+    /// auto-capture input next to `stability_variance` and `detection_rate`:
+    /// hold the capture while the score is high (e.g. above 0.3).
+    pub fn shake_score(&self) -> Option<f32> {
+        self.shake.score()
+    }
+}
+
+#[cfg(feature = "low_light")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Low-light pipeline variant (off by default). `Auto` switches it on when
+    /// the mean luma of the frames drops below about 40 and off again above 56.
+    /// In low light the frame is binned 2x2 and averaged over the last few
+    /// frames, then Canny runs with a 7x7 blur and a quarter of the thresholds;
+    /// the edge map is scaled back to the frame size. RGBA frames use luma even
+    /// with a colour `EdgeSource`, and `gray_ptr` keeps the full-resolution frame.
+    pub fn set_low_light_mode(&mut self, mode: LowLightMode) {
+        self.low_light.set_mode(mode);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn low_light_mode(&self) -> LowLightMode {
+        self.low_light.mode()
+    }
+
+    /// Whether the last processed frame ran the low-light pipeline.
+    pub fn low_light_active(&self) -> bool {
+        self.low_light.active()
+    }
+
+    /// Mean luma (0-255) of the last processed frame, the exposure metric `Auto`
+    /// switches on.
+    pub fn mean_luma(&self) -> f32 {
+        self.low_light.mean_luma()
+    }
+}
+
+#[cfg(feature = "screen")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Preset for scanning a document shown on another screen (off by default).
+    /// Refresh banding is flattened (see `remove_refresh_banding`), Canny blurs
+    /// with at least a 9x9 kernel against moiré, and edges along a dark monitor
+    /// bezel running off the frame are dropped (see `suppress_bezel_edges`).
+    /// RGBA frames use luma even with a colour `EdgeSource`; the low-light
+    /// pipeline takes precedence when it is active.
+    pub fn set_screen_capture(&mut self, enabled: bool) {
+        self.screen_capture = enabled;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn screen_capture(&self) -> bool {
+        self.screen_capture
+    }
+}
+
+#[cfg(feature = "detection")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Finds the page on the edge map of the last processed frame (see
+    /// `detect_document`). Fails with `NoEdges` until a frame of the current
+    /// size has been processed.
+    pub fn detect_document(&self) -> DetectionResult {
+        if self.width == 0 || self.height == 0 || self.gray.len() != self.width * self.height {
+            return DetectionResult::failed(DetectionFailure::NoEdges);
+        }
+        let margin = (self.effective_dilation() / 2) as f32;
+        detection::detect_from_edges(&self.gray, self.edge_buffer(), self.width, self.height, margin)
+    }
+}
+
+#[cfg(feature = "history")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Number of detection results kept by `record_detection` (30 by default).
+    /// Shrinking keeps the newest ones.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
+    }
+
+    /// Records the detection result of a frame, e.g. for auto-capture once the
+    /// quad has been stable for a while. `corners` holds 8 values (TL, TR, BR,
+    /// BL) or is empty when nothing was found.
+    pub fn record_detection(&mut self, corners: &[f32], confidence: f32, timestamp_ms: f64) -> Result<(), ScanError> {
+        let quad = if corners.is_empty() { None } else { Some(to_quad(corners)?) };
+        self.history.push(quad, confidence, timestamp_ms);
+        #[cfg(feature = "analytics")]
+        self.stats.record_detection(quad.is_some());
+        Ok(())
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Corners of the detection `age` frames back (0 is the newest), empty if
+    /// that frame found no quad.
+    pub fn history_corners(&self, age: usize) -> Result<Vec<f32>, ScanError> {
+        check_index(age, self.history.len())?;
+        Ok(self.history.corners(age).map_or_else(Vec::new, |q| q.to_vec()))
+    }
+
+    pub fn history_confidence(&self, age: usize) -> Result<f32, ScanError> {
+        check_index(age, self.history.len())?;
+        Ok(self.history.confidence(age))
+    }
+
+    pub fn history_timestamp(&self, age: usize) -> Result<f64, ScanError> {
+        check_index(age, self.history.len())?;
+        Ok(self.history.timestamp_ms(age))
+    }
+
+    /// Per-coordinate median of the quads among the newest `window` results (0
+    /// for all), robust to single-frame outliers. Empty without any quad.
+    pub fn median_quad(&self, window: usize) -> Vec<f32> {
+        self.history.median_quad(window).map_or_else(Vec::new, |q| q.to_vec())
+    }
+
+    /// Mean variance of the corner coordinates over the newest `window` results
+    /// (0 for all), in px². Infinite with fewer than two quads.
+    pub fn stability_variance(&self, window: usize) -> f32 {
+        self.history.stability_variance(window)
+    }
+
+    /// Fraction of the newest `window` results (0 for all) that found a quad.
+    pub fn detection_rate(&self, window: usize) -> f32 {
+        self.history.detection_rate(window)
+    }
+}
+
+#[cfg(feature = "readiness")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Auto-capture check (see `capture_readiness`) on the last processed frame
+    /// and the stability of the newest 10 recorded detections. Not ready until
+    /// a frame of the current size has been processed.
+    pub fn capture_readiness(&self) -> CaptureReadiness {
+        let variance = self.history.stability_variance(readiness::STABILITY_WINDOW);
+        if self.gray.len() != self.width * self.height {
+            return readiness::measure(&[], 0, 0, variance);
+        }
+        readiness::measure(&self.gray, self.width, self.height, variance)
+    }
+}
+
+#[cfg(feature = "occlusion")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Stretches of the sides of `corners` without edges (see `border_gaps`) on
+    /// the edge map of the last processed frame. Fails until a frame of the
+    /// current size has been processed.
+    pub fn border_gaps(&self, corners: &[f32]) -> Result<BorderGaps, ScanError> {
+        occlusion::border_gaps(self.edge_buffer(), self.width, self.height, corners)
+    }
+}
+
+#[cfg(feature = "analytics")]
+#[wasm_bindgen]
+impl ScanContext {
+    /// Counts a retake of the current page, e.g. when the user discards a
+    /// capture, for the session report.
+    pub fn record_retake(&mut self) {
+        self.stats.record_retake();
+    }
+
+    /// Aggregate metrics since the context was created or the last
+    /// `reset_session_stats`: frames seen and processed, average latency per
+    /// pipeline stage, detection success rate (over `record_detection`) and
+    /// retakes.
+    pub fn session_report(&self) -> SessionReport {
+        self.stats.report()
+    }
+
+    /// Starts a new session for `session_report`. The detection history is
+    /// left as it is.
+    pub fn reset_session_stats(&mut self) {
+        self.stats = SessionStats::default();
+    }
+}
+
+impl ScanContext {
+    // Counts the frame and decides whether it runs the pipeline.
+    fn begin_frame(&mut self) -> bool {
+        self.frame_processed = !self.low_power || self.frames_seen.is_multiple_of(LOW_POWER_FRAME_INTERVAL);
+        self.frames_seen = self.frames_seen.wrapping_add(1);
+        #[cfg(feature = "analytics")]
+        self.stats.begin_frame(self.frame_processed);
+        self.frame_processed
+    }
+
+    // Settings in effect for the next frame, with low-power overrides applied.
+    fn effective_kernel_size(&self) -> usize {
+        if self.low_power { LOW_POWER_KERNEL_SIZE } else { self.blur_size() }
+    }
+
+    // Blur and dilation sizes at the current frame size, before low-power
+    // overrides.
+    fn blur_size(&self) -> usize {
+        if self.blur_fraction > 0.0 {
+            canny::relative_kernel_size(self.blur_fraction, self.width, self.height)
+        } else {
+            self.kernel_size
+        }
+    }
+
+    fn dilation_size(&self) -> usize {
+        if self.dilation_fraction > 0.0 {
+            canny::relative_kernel_size(self.dilation_fraction, self.width, self.height)
+        } else {
+            self.dilation_kernel_size
+        }
+    }
+
+    // Converts an RGBA frame into `gray` for the edge pipeline. Returns whether
+    // the colour planes were filled for a colour gradient.
+    #[cfg(feature = "color_edges")]
+    fn load_rgba(&mut self, rgba: &[u8]) -> bool {
+        color_edges::load_rgba_into(rgba, &mut self.gray, &mut self.color_planes, self.weights, self.edge_source)
+    }
+
+    #[cfg(not(feature = "color_edges"))]
+    fn load_rgba(&mut self, rgba: &[u8]) -> bool {
+        crate::grayscale::rgba_to_grayscale_into(rgba, &mut self.gray, self.weights);
+        false
+    }
+
+    fn effective_dilation(&self) -> usize {
+        #[cfg(feature = "auto_dilation")]
+        if let (Some(size), false) = (self.auto_dilation, self.low_power) {
+            return size;
+        }
+        if self.low_power { 0 } else { self.dilation_size() }
+    }
+
+    // Sizes the edge pipeline buffers for the current frame size on first use,
+    // after `resize` and after `release_buffers`.
+    fn ensure_buffers(&mut self) {
+        let size = self.width * self.height;
+        if self.gray.len() != size {
+            self.gray.resize(size, 0);
+            self.edges.resize(size, 0);
+            self.arena.reserve(canny::canny_arena_bytes(self.width, self.height).max(size));
+        }
+    }
+
+    // Runs after the frame was converted to `gray`, which ends its grayscale stage.
+    // `color` runs Canny on the colour planes instead.
+    #[cfg_attr(not(feature = "color_edges"), allow(unused_variables))]
+    fn run_edges(&mut self, color: bool) {
+        #[cfg(feature = "shake")]
+        self.shake.push_frame(&self.gray, self.width, self.height);
+        #[cfg(feature = "analytics")]
+        self.stats.end_stage(PipelineStage::Grayscale);
+        // Preview frames take priority over background export on the shared pool
+        let _preview = crate::scheduler::preview_scope();
+        self.arena.reset();
+        let gray = [&self.gray[..]];
+        #[cfg(feature = "color_edges")]
+        let rgb = color_edges::split_planes(&self.color_planes, if color { self.gray.len() } else { 0 });
+        #[cfg(feature = "color_edges")]
+        let planes: &[&[u8]] = if color { &rgb } else { &gray };
+        #[cfg(not(feature = "color_edges"))]
+        let planes: &[&[u8]] = &gray;
+        #[cfg(feature = "low_light")]
+        let low_light = self.low_light.update(&self.gray);
+        #[cfg(not(feature = "low_light"))]
+        let low_light = false;
+        #[cfg(feature = "screen")]
+        let screen_capture = self.screen_capture;
+        #[cfg(not(feature = "screen"))]
+        let screen_capture = false;
+        if low_light {
+            #[cfg(feature = "low_light")]
+            self.run_low_light_canny();
+        } else if screen_capture {
+            #[cfg(feature = "screen")]
+            self.run_screen_canny();
+        } else {
+            canny::canny_planes_into(
+                planes,
+                self.width,
+                self.height,
+                self.low_threshold,
+                self.high_threshold,
+                self.effective_kernel_size(),
+                if self.low_power { 0.0 } else { self.sigma },
+                self.border,
+                self.l2_gradient && !self.low_power,
+                &self.arena,
+                &mut self.edges,
+            );
+        }
+        #[cfg(feature = "analytics")]
+        self.stats.end_stage(PipelineStage::Canny);
+        #[cfg(feature = "auto_dilation")]
+        if self.auto_dilation.is_some() && !self.low_power {
+            self.arena.reset();
+            let labels = self.arena.alloc::<u32>(self.width * self.height);
+            let size = auto_dilation::suggest_dilation_into(&self.edges, self.width, self.height, labels);
+            self.auto_dilation = Some(size);
+        }
+        let dilation = self.effective_dilation();
+        self.edges_dilated = dilation > 0;
+        if dilation > 0 {
+            self.dilated.resize(self.width * self.height, 0);
+            self.arena.reset();
+            crate::dilation::dilate_into(
+                &self.edges,
+                self.width,
+                self.height,
+                dilation,
+                self.arena.alloc::<u8>(self.width * self.height),
+                &mut self.dilated,
+            );
+            #[cfg(feature = "analytics")]
+            self.stats.end_stage(PipelineStage::Dilation);
+        }
+    }
+
+    // Canny on the 2x2-binned, temporally averaged frame with the low-light
+    // blur and thresholds, upsampled into `edges`.
+    #[cfg(feature = "low_light")]
+    fn run_low_light_canny(&mut self) {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let binned = self.arena.alloc::<u8>(width * height);
+        self.low_light.accumulate_into(&self.gray, self.width, self.height, binned);
+        let small_edges = self.arena.alloc::<u8>(width * height);
+        canny::canny_into(
+            binned,
+            width,
+            height,
+            self.low_threshold * low_light::THRESHOLD_SCALE,
+            self.high_threshold * low_light::THRESHOLD_SCALE,
+            low_light::KERNEL_SIZE,
+            0.0,
+            self.border,
+            self.l2_gradient,
+            &self.arena,
+            small_edges,
+        );
+        low_light::upsample_into(small_edges, self.width, self.height, &mut self.edges);
+    }
+
+    // Canny on the frame with refresh banding removed and the moiré blur, then
+    // the bezel edges cleared.
+    #[cfg(feature = "screen")]
+    fn run_screen_canny(&mut self) {
+        let size = self.width * self.height;
+        let flattened = self.arena.alloc::<u8>(size);
+        screen::remove_banding_into(&self.gray, self.width, self.height, flattened);
+        canny::canny_into(
+            flattened,
+            self.width,
+            self.height,
+            self.low_threshold,
+            self.high_threshold,
+            self.effective_kernel_size().max(screen::KERNEL_SIZE),
+            0.0,
+            self.border,
+            self.l2_gradient && !self.low_power,
+            &self.arena,
+            &mut self.edges,
+        );
+        screen::suppress_bezel_into(
+            &self.gray,
+            self.width,
+            self.height,
+            self.arena.alloc::<usize>(size),
+            self.arena.alloc::<u8>(size),
+            self.arena.alloc::<u8>(size),
+            self.arena.alloc::<u8>(size),
+            &mut self.edges,
+        );
+    }
+
+    // Edge map of the last processed frame, whatever the settings are now.
+    fn edge_buffer(&self) -> &[u8] {
+        if self.edges_dilated {
+            &self.dilated
+        } else {
+            &self.edges
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_allocated_on_first_use() {
+        let mut context = ScanContext::new(64, 48);
+        assert_eq!(context.retained_bytes(), 0);
+
+        context.set_dilation(0);
+        context.process_gray_frame(&vec![0u8; 64 * 48]).unwrap();
+        assert!(context.retained_bytes() > 0);
+        assert_eq!(context.dilated.capacity(), 0);
+        assert_eq!(context.edges().len(), 64 * 48);
+    }
+
+    #[test]
+    fn test_low_power_skips_frames() {
+        let (width, height) = (32, 24);
+        let mut square = vec![0u8; width * height];
+        for y in 6..18 {
+            square[y * width + 8..y * width + 24].fill(255);
+        }
+        let mut context = ScanContext::new(width, height);
+        context.set_low_power(true);
+        context.process_gray_frame(&square).unwrap();
+        assert!(context.frame_processed());
+        let edges = context.edges();
+        assert!(edges.iter().any(|&e| e != 0));
+        assert_eq!(context.dilated.capacity(), 0);
+
+        // The next two frames keep the previous result
+        for _ in 0..2 {
+            context.process_gray_frame(&vec![0u8; width * height]).unwrap();
+            assert!(!context.frame_processed());
+            assert_eq!(context.edges(), edges);
+        }
+        context.process_gray_frame(&vec![0u8; width * height]).unwrap();
+        assert!(context.frame_processed());
+        assert!(context.edges().iter().all(|&e| e == 0));
+
+        context.set_low_power(false);
+        context.process_gray_frame(&square).unwrap();
+        assert!(context.frame_processed() && context.dilated.len() == width * height);
+    }
+
+    #[test]
+    fn test_edges_follow_last_processed_frame() {
+        let (width, height) = (32, 24);
+        let mut square = vec![0u8; width * height];
+        for y in 6..18 {
+            square[y * width + 8..y * width + 24].fill(255);
+        }
+        let mut context = ScanContext::new(width, height);
+        context.set_dilation(0);
+        context.process_gray_frame(&square).unwrap();
+        let raw = context.edges();
+
+        // Changing the settings doesn't change the map until the next frame
+        context.set_dilation(5);
+        assert_eq!(context.edges(), raw);
+        assert_eq!(context.edges_ptr(), context.edges.as_ptr());
+        context.process_gray_frame(&square).unwrap();
+        let dilated = context.edges();
+        assert!(dilated.iter().filter(|&&e| e != 0).count() > raw.iter().filter(|&&e| e != 0).count());
+
+        context.set_low_power(true);
+        assert_eq!(context.edges(), dilated);
+        context.process_gray_frame(&square).unwrap();
+        let low_power = context.edges();
+        assert_eq!(low_power.len(), width * height);
+        assert_ne!(low_power, dilated);
+        context.set_low_power(false);
+        assert_eq!(context.edges(), low_power);
+        assert_eq!(context.edges_ptr(), context.edges.as_ptr());
+    }
+}
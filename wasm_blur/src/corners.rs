@@ -0,0 +1,388 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// Bilinear sample at (x, y), clamped to the image. Pixel centres sit on integer
+// coordinates.
+#[inline]
+fn sample(gray: &[u8], width: usize, height: usize, x: f64, y: f64) -> f64 {
+    let x = x.clamp(0.0, (width - 1) as f64);
+    let y = y.clamp(0.0, (height - 1) as f64);
+    let x0 = x as usize;
+    let y0 = y as usize;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = x - x0 as f64;
+    let fy = y - y0 as f64;
+
+    let top = gray[y0 * width + x0] as f64 * (1.0 - fx) + gray[y0 * width + x1] as f64 * fx;
+    let bottom = gray[y1 * width + x0] as f64 * (1.0 - fx) + gray[y1 * width + x1] as f64 * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+// One corner, OpenCV `cornerSubPix` style. Every point p in the window around the
+// estimate q sees a gradient g(p) orthogonal to q - p when q is the true corner
+// (p lies on an edge through q, or in a flat area where g = 0). Minimising
+// sum(w * (g . (q - p))^2) gives q = G^-1 * b with G = sum(w * g g^T) and
+// b = sum(w * g g^T p), solved repeatedly around the updated estimate.
+fn refine_corner(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    (x, y): (f64, f64),
+    window: usize,
+    max_iterations: usize,
+    epsilon: f64,
+) -> (f64, f64) {
+    let win = window as isize;
+    let sigma2 = (window * window) as f64;
+    let (mut qx, mut qy) = (x, y);
+
+    for _ in 0..max_iterations {
+        let (mut gxx, mut gxy, mut gyy, mut bx, mut by) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for dy in -win..=win {
+            for dx in -win..=win {
+                let (px, py) = (qx + dx as f64, qy + dy as f64);
+                // Central differences on the interpolated image
+                let gx = (sample(gray, width, height, px + 1.0, py) - sample(gray, width, height, px - 1.0, py)) / 2.0;
+                let gy = (sample(gray, width, height, px, py + 1.0) - sample(gray, width, height, px, py - 1.0)) / 2.0;
+                // Gaussian window weight
+                let w = (-((dx * dx + dy * dy) as f64) / sigma2).exp();
+
+                let (wxx, wxy, wyy) = (w * gx * gx, w * gx * gy, w * gy * gy);
+                gxx += wxx;
+                gxy += wxy;
+                gyy += wyy;
+                bx += wxx * px + wxy * py;
+                by += wxy * px + wyy * py;
+            }
+        }
+
+        let det = gxx * gyy - gxy * gxy;
+        // Flat window or a single straight edge: the position is not constrained
+        if det.abs() <= f64::EPSILON * (gxx * gyy).max(1.0) {
+            break;
+        }
+        let nx = (gyy * bx - gxy * by) / det;
+        let ny = (gxx * by - gxy * bx) / det;
+        let step = (nx - qx).hypot(ny - qy);
+        qx = nx;
+        qy = ny;
+        if step < epsilon {
+            break;
+        }
+    }
+
+    // Estimates that drift out of the search window latched onto another feature
+    if (qx - x).abs() > window as f64 || (qy - y).abs() > window as f64 {
+        return (x, y);
+    }
+    (qx, qy)
+}
+
+/// Refines corner positions to sub-pixel accuracy (like OpenCV `cornerSubPix`).
+/// Corners detected on a downscaled frame are quantized to its grid; scale them
+/// to full resolution first and refine them on the full-resolution image so the
+/// warp is not skewed by the quantization.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `corners` - Flat list of x, y pairs
+/// * `window` - Half size of the search window (window 5 searches 11x11 pixels)
+/// * `max_iterations` - Upper bound on refinement steps per corner
+/// * `epsilon` - Stop once a step moves the corner less than this many pixels
+///
+/// # Returns
+/// Refined x, y pairs in the same order. Corners that cannot be refined (flat
+/// area, straight edge, or drifting out of the window) are returned unchanged.
+/// On L-shaped corners, such as a page against a darker background, blur rounds
+/// the tip and the result sits a fraction of the blur width inside the page.
+#[wasm_bindgen]
+pub fn refine_corners(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    corners: &[f32],
+    window: usize,
+    max_iterations: usize,
+    epsilon: f32,
+) -> Result<Vec<f32>, ScanError> {
+    check_gray(gray, width, height)?;
+    if !corners.len().is_multiple_of(2) {
+        return Err(ScanError::InvalidPoints("Corners must be x, y pairs"));
+    }
+    if width == 0 || height == 0 || window == 0 {
+        return Ok(corners.to_vec());
+    }
+
+    Ok(corners
+        .chunks_exact(2)
+        .flat_map(|c| {
+            let (x, y) = (c[0] as f64, c[1] as f64);
+            if !x.is_finite() || !y.is_finite() {
+                return [c[0], c[1]];
+            }
+            let (rx, ry) = refine_corner(gray, width, height, (x, y), window, max_iterations, epsilon as f64);
+            [rx as f32, ry as f32]
+        })
+        .collect())
+}
+
+/// Corner response used by `corner_response` and `detect_corners`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CornerMeasure {
+    /// det(M) - k * trace(M)^2 of the structure tensor M
+    Harris = 0,
+    /// Smaller eigenvalue of M (Shi-Tomasi, "good features to track")
+    ShiTomasi = 1,
+}
+
+// Sum of `values` over a (2r+1) x (2r+1) box around every pixel, clamped at the
+// borders. Separable running sums, so the cost does not depend on the box size.
+fn box_sum(values: &[f32], width: usize, height: usize, r: usize) -> Vec<f32> {
+    let mut rows = vec![0f32; width * height];
+    let mut prefix = vec![0f32; width.max(height) + 1];
+    for (src, dst) in values.chunks_exact(width).zip(rows.chunks_exact_mut(width)) {
+        for (x, &v) in src.iter().enumerate() {
+            prefix[x + 1] = prefix[x] + v;
+        }
+        for (x, out) in dst.iter_mut().enumerate() {
+            *out = prefix[(x + r + 1).min(width)] - prefix[x.saturating_sub(r)];
+        }
+    }
+
+    let mut result = vec![0f32; width * height];
+    for x in 0..width {
+        for y in 0..height {
+            prefix[y + 1] = prefix[y] + rows[y * width + x];
+        }
+        for y in 0..height {
+            result[y * width + x] = prefix[(y + r + 1).min(height)] - prefix[y.saturating_sub(r)];
+        }
+    }
+    result
+}
+
+// Per-pixel corner response from Sobel gradients summed over a block.
+fn response_map(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    block_size: usize,
+    measure: CornerMeasure,
+    k: f32,
+) -> Vec<f32> {
+    let size = width * height;
+    let mut gradients = vec![0i16; 2 * size];
+    crate::gradient_calculation::gradients_into(gray, width, height, &mut gradients);
+    let (mut xx, mut xy, mut yy) = (vec![0f32; size], vec![0f32; size], vec![0f32; size]);
+    for (i, g) in gradients.chunks_exact(2).enumerate() {
+        let (gx, gy) = (g[0] as f32, g[1] as f32);
+        xx[i] = gx * gx;
+        xy[i] = gx * gy;
+        yy[i] = gy * gy;
+    }
+
+    let r = block_size / 2;
+    let (xx, xy, yy) = (box_sum(&xx, width, height, r), box_sum(&xy, width, height, r), box_sum(&yy, width, height, r));
+    (0..size)
+        .map(|i| {
+            let (a, b, c) = (xx[i], xy[i], yy[i]);
+            match measure {
+                CornerMeasure::Harris => a * c - b * b - k * (a + c) * (a + c),
+                CornerMeasure::ShiTomasi => (a + c) / 2.0 - ((a - c) / 2.0).hypot(b),
+            }
+        })
+        .collect()
+}
+
+/// Harris or Shi-Tomasi corner response for every pixel.
+///
+/// # Arguments
+/// * `gray` - Grayscale image (blur it first on noisy input)
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `block_size` - Neighbourhood the gradients are summed over (e.g. 3 or 5)
+/// * `measure` - Harris or Shi-Tomasi
+/// * `k` - Harris sensitivity (typically 0.04..0.06), ignored for Shi-Tomasi
+///
+/// # Returns
+/// Response map of width * height values; larger means more corner-like
+#[wasm_bindgen]
+pub fn corner_response(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    block_size: usize,
+    measure: CornerMeasure,
+    k: f32,
+) -> Result<Vec<f32>, ScanError> {
+    check_gray(gray, width, height)?;
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+    Ok(response_map(gray, width, height, block_size, measure, k))
+}
+
+/// Detects corners (like OpenCV `goodFeaturesToTrack`): local maxima of the
+/// response above `quality_level` times the strongest response, strongest first,
+/// at least `min_distance` pixels apart.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `block_size` - Neighbourhood the gradients are summed over
+/// * `measure` - Harris or Shi-Tomasi
+/// * `k` - Harris sensitivity, ignored for Shi-Tomasi
+/// * `quality_level` - Fraction (0..1) of the strongest response a corner needs
+/// * `min_distance` - Minimum distance between returned corners
+/// * `max_corners` - Maximum number of corners returned
+///
+/// # Returns
+/// Flat `[x, y, response]` triples, strongest first
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn detect_corners(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    block_size: usize,
+    measure: CornerMeasure,
+    k: f32,
+    quality_level: f32,
+    min_distance: f32,
+    max_corners: usize,
+) -> Result<Vec<f32>, ScanError> {
+    let response = corner_response(gray, width, height, block_size, measure, k)?;
+    let strongest = response.iter().copied().fold(0.0, f32::max);
+    if strongest <= 0.0 {
+        return Ok(Vec::new());
+    }
+    let threshold = strongest * quality_level;
+
+    // 3x3 non-maximum suppression; ties go to the first pixel in scan order
+    let mut candidates: Vec<(usize, usize, f32)> = Vec::new();
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width - 1 {
+            let v = response[y * width + x];
+            if v <= threshold {
+                continue;
+            }
+            let is_max = (0..9).filter(|&n| n != 4).all(|n| {
+                let neighbour = response[(y + n / 3 - 1) * width + x + n % 3 - 1];
+                if n < 4 { v > neighbour } else { v >= neighbour }
+            });
+            if is_max {
+                candidates.push((x, y, v));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    // Greedy minimum distance, strongest first
+    let min_distance2 = min_distance * min_distance;
+    let mut corners: Vec<(usize, usize, f32)> = Vec::new();
+    for (x, y, v) in candidates {
+        if corners.len() >= max_corners {
+            break;
+        }
+        let far_enough = corners.iter().all(|&(cx, cy, _)| {
+            let (dx, dy) = (cx as f32 - x as f32, cy as f32 - y as f32);
+            dx * dx + dy * dy >= min_distance2
+        });
+        if far_enough {
+            corners.push((x, y, v));
+        }
+    }
+    Ok(corners.iter().flat_map(|&(x, y, v)| [x as f32, y as f32, v]).collect())
+}
+
+/// Builds a quadrilateral from detected corners, for when no closed contour is
+/// found: the corners extremal along the two diagonals (smallest and largest
+/// x + y and x - y) become TL, TR, BR and BL.
+///
+/// # Arguments
+/// * `corners` - Flat `[x, y, response]` triples from `detect_corners`
+///
+/// # Returns
+/// 8 values (TL, TR, BR, BL), or an empty Vec when fewer than four distinct
+/// corners are available
+#[wasm_bindgen]
+pub fn quad_from_corners(corners: &[f32]) -> Vec<f32> {
+    let points: Vec<(f32, f32)> = corners.chunks_exact(3).map(|c| (c[0], c[1])).collect();
+    if points.len() < 4 {
+        return Vec::new();
+    }
+    let pick = |key: &dyn Fn(&(f32, f32)) -> f32| {
+        *points.iter().max_by(|a, b| key(a).total_cmp(&key(b))).unwrap()
+    };
+    let quad = [
+        pick(&|p| -(p.0 + p.1)),
+        pick(&|p| p.0 - p.1),
+        pick(&|p| p.0 + p.1),
+        pick(&|p| p.1 - p.0),
+    ];
+    for i in 0..4 {
+        if quad[i + 1..].contains(&quad[i]) {
+            return Vec::new();
+        }
+    }
+    quad.iter().flat_map(|&(x, y)| [x, y]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refine_corners_converges_to_subpixel_corner() {
+        // Soft (camera-like) edges crossing at (20.3, 15.7)
+        let (width, height) = (40usize, 32usize);
+        let (cx, cy) = (20.3f64, 15.7f64);
+        let step = |i: usize, c: f64| 1.0 / (1.0 + (-(i as f64 - c) / 0.8).exp());
+        let render = |corner: fn(f64, f64) -> f64| -> Vec<u8> {
+            (0..width * height)
+                .map(|i| (40.0 + 200.0 * corner(step(i % width, cx), step(i / width, cy))).round() as u8)
+                .collect()
+        };
+        let error = |r: &[f32]| (r[0] as f64 - cx).hypot(r[1] as f64 - cy);
+
+        // Checkerboard-style X corner: exact up to interpolation error
+        let checker = render(|a, b| a * b + (1.0 - a) * (1.0 - b));
+        let refined = refine_corners(&checker, width, height, &[19.0, 17.0, 5.0, 5.0], 5, 40, 0.001).unwrap();
+        assert!(error(&refined) < 0.05);
+        // Flat area: nothing to lock on to
+        assert_eq!(&refined[2..], &[5.0, 5.0]);
+
+        // Document-style L corner: the rounded tip leaves a small offset inwards
+        let page = render(|a, b| a * b);
+        let refined = refine_corners(&page, width, height, &[19.0, 17.0], 5, 40, 0.001).unwrap();
+        assert!(error(&refined) < 0.4);
+    }
+
+    #[test]
+    fn test_detect_corners_finds_rectangle() {
+        // Bright rectangle on a dark background: four corners, nothing on the edges
+        let (width, height) = (60usize, 50usize);
+        let gray: Vec<u8> = (0..width * height)
+            .map(|i| if (15..45).contains(&(i % width)) && (10..38).contains(&(i / width)) { 200 } else { 30 })
+            .collect();
+
+        for measure in [CornerMeasure::Harris, CornerMeasure::ShiTomasi] {
+            let corners = detect_corners(&gray, width, height, 3, measure, 0.04, 0.1, 5.0, 10).unwrap();
+            assert_eq!(corners.len(), 4 * 3);
+            let quad = quad_from_corners(&corners);
+            let expected = [(15.0, 10.0), (44.0, 10.0), (44.0, 37.0), (15.0, 37.0)];
+            for (point, &(ex, ey)) in quad.chunks_exact(2).zip(&expected) {
+                assert!((point[0] - ex).abs() <= 1.0 && (point[1] - ey).abs() <= 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "detection")]
+use crate::canny::CannyOptions;
+#[cfg(feature = "detection")]
+use crate::detection::{detect_document, DetectionResult};
+use crate::error::ScanError;
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::orientation::apply_exif_orientation;
+
+use jpeg_decoder::{Decoder, PixelFormat};
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+// Largest image decoded, in pixels (a 64 MP photo), so a small malicious file
+// can't claim gigabytes of WASM memory.
+const MAX_PIXELS: usize = 1 << 26;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+// EXIF tag of the orientation in the first image directory.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// A JPEG or PNG decoded to RGBA and turned upright per its EXIF orientation.
+#[wasm_bindgen]
+pub struct DecodedImage {
+    rgba: Vec<u8>,
+    width: usize,
+    height: usize,
+    orientation: u16,
+}
+
+#[wasm_bindgen]
+impl DecodedImage {
+    /// Width of the upright image.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the upright image.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// EXIF orientation (1 to 8) the image was stored with and has been
+    /// turned from; 1 without EXIF data.
+    #[wasm_bindgen(getter)]
+    pub fn orientation(&self) -> u16 {
+        self.orientation
+    }
+
+    /// RGBA pixels, e.g. for `ImageData`.
+    pub fn rgba(&self) -> Vec<u8> {
+        self.rgba.clone()
+    }
+
+    /// BT.601 luma of the pixels, the input of the edge pipeline.
+    pub fn gray(&self) -> Vec<u8> {
+        let mut gray = vec![0u8; self.width * self.height];
+        rgba_to_grayscale_into(&self.rgba, &mut gray, GrayscaleWeights::Bt601);
+        gray
+    }
+}
+
+// Orientation tag of an EXIF block starting at its TIFF header; 1 if there is
+// none or the block is malformed.
+fn exif_orientation(exif: &[u8]) -> u16 {
+    let big_endian = match exif.get(..4) {
+        Some(b"MM\0*") => true,
+        Some(b"II*\0") => false,
+        _ => return 1,
+    };
+    let u16_at = |at: usize| {
+        exif.get(at..at + 2).map(|b| {
+            if big_endian {
+                u16::from_be_bytes([b[0], b[1]])
+            } else {
+                u16::from_le_bytes([b[0], b[1]])
+            }
+        })
+    };
+    let u32_at = |at: usize| {
+        exif.get(at..at + 4).map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if big_endian {
+                u32::from_be_bytes(b)
+            } else {
+                u32::from_le_bytes(b)
+            }
+        })
+    };
+    let Some(directory) = u32_at(4).map(|offset| offset as usize) else {
+        return 1;
+    };
+    let entries = u16_at(directory).unwrap_or(0) as usize;
+    (0..entries)
+        .map(|e| directory + 2 + 12 * e)
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+        .unwrap_or(1)
+}
+
+fn check_pixels(width: usize, height: usize) -> Result<(), ScanError> {
+    if width == 0 || height == 0 {
+        return Err(ScanError::InvalidData("Image has no size"));
+    }
+    if width.saturating_mul(height) > MAX_PIXELS {
+        return Err(ScanError::InvalidData("Image too large to decode"));
+    }
+    Ok(())
+}
+
+// RGBA pixels, width, height and EXIF orientation of a JPEG.
+fn decode_jpeg(bytes: &[u8]) -> Result<(Vec<u8>, usize, usize, u16), ScanError> {
+    let mut decoder = Decoder::new(bytes);
+    decoder.read_info().map_err(|_| ScanError::InvalidData("Malformed JPEG file"))?;
+    let info = decoder.info().ok_or(ScanError::InvalidData("Malformed JPEG file"))?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    check_pixels(width, height)?;
+    let pixels = decoder.decode().map_err(|_| ScanError::InvalidData("Malformed JPEG file"))?;
+    let orientation = decoder.exif_data().map_or(1, exif_orientation);
+    let rgba = match info.pixel_format {
+        PixelFormat::L8 => pixels.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        PixelFormat::RGB24 => pixels.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255]).collect(),
+        PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|px| {
+                let ink = |c: u8| ((255 - c as u32) * (255 - px[3] as u32) / 255) as u8;
+                [ink(px[0]), ink(px[1]), ink(px[2]), 255]
+            })
+            .collect(),
+        PixelFormat::L16 => return Err(ScanError::InvalidData("16-bit JPEGs are not supported")),
+    };
+    Ok((rgba, width, height, orientation))
+}
+
+// Sample `i` of a PNG row at `depth` bits; samples below 8 bits are packed
+// most significant first and 16-bit ones are big-endian.
+fn png_sample(row: &[u8], i: usize, depth: u8) -> u16 {
+    match depth {
+        16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+        8 => row[i] as u16,
+        _ => {
+            let bit = i * depth as usize;
+            (row[bit / 8] >> (8 - depth as usize - bit % 8)) as u16 & ((1 << depth) - 1)
+        }
+    }
+}
+
+// A PNG sample scaled to 8 bits.
+fn png_level(value: u16, depth: u8) -> u8 {
+    match depth {
+        16 => (value >> 8) as u8,
+        8 => value as u8,
+        _ => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+    }
+}
+
+// Undoes the per-row filters of PNG data in place, leaving `stride` bytes per
+// row after each filter type byte.
+fn png_unfilter(data: &mut [u8], stride: usize, bpp: usize) -> Result<(), ScanError> {
+    for y in 0..data.len() / (stride + 1) {
+        let (above, rest) = data.split_at_mut(y * (stride + 1));
+        let previous = if y > 0 { &above[above.len() - stride..] } else { &[][..] };
+        let (filter, row) = rest[..stride + 1].split_first_mut().unwrap();
+        for x in 0..stride {
+            let a = if x >= bpp { row[x - bpp] } else { 0 };
+            let b = previous.get(x).copied().unwrap_or(0);
+            let c = if x >= bpp { previous.get(x - bpp).copied().unwrap_or(0) } else { 0 };
+            let predicted = match *filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => {
+                    let p = a as i16 + b as i16 - c as i16;
+                    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+                    if pa <= pb && pa <= pc {
+                        a
+                    } else if pb <= pc {
+                        b
+                    } else {
+                        c
+                    }
+                }
+                _ => return Err(ScanError::InvalidData("Unknown PNG filter")),
+            };
+            row[x] = row[x].wrapping_add(predicted);
+        }
+    }
+    Ok(())
+}
+
+// RGBA pixels, width, height and EXIF orientation of a non-interlaced PNG of
+// any colour type and bit depth.
+fn decode_png(bytes: &[u8]) -> Result<(Vec<u8>, usize, usize, u16), ScanError> {
+    let truncated = ScanError::InvalidData("Truncated PNG file");
+    let (mut header, mut palette, mut transparency) = (None, &[][..], &[][..]);
+    let (mut compressed, mut orientation) = (Vec::new(), 1);
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let kind = &bytes[pos + 4..pos + 8];
+        let data = bytes.get(pos + 8..pos + 8 + len).ok_or(truncated)?;
+        match kind {
+            b"IHDR" if len >= 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"eXIf" => orientation = exif_orientation(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Chunk length, type, data and CRC
+        pos += 12 + len;
+    }
+    let header = header.ok_or(ScanError::InvalidData("PNG has no header"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (depth, color) = (header[8], header[9]);
+    check_pixels(width, height)?;
+    if header[12] != 0 {
+        return Err(ScanError::InvalidData("Interlaced PNGs are not supported"));
+    }
+    let channels = match (color, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => return Err(ScanError::InvalidData("Invalid PNG colour type or bit depth")),
+    };
+
+    let stride = (width * channels * depth as usize).div_ceil(8);
+    let expected = (stride + 1) * height;
+    let mut data = decompress_to_vec_zlib_with_limit(&compressed, expected)
+        .map_err(|_| ScanError::InvalidData("Malformed PNG image data"))?;
+    if data.len() < expected {
+        return Err(truncated);
+    }
+    png_unfilter(&mut data, stride, (channels * depth as usize).div_ceil(8))?;
+
+    // Single transparent colour of gray and RGB images
+    let key: Option<[u16; 3]> = match (color, transparency.len()) {
+        (0, 2..) => Some([u16::from_be_bytes([transparency[0], transparency[1]]); 3]),
+        (2, 6..) => Some(core::array::from_fn(|c| u16::from_be_bytes([transparency[2 * c], transparency[2 * c + 1]]))),
+        _ => None,
+    };
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in data.chunks_exact(stride + 1).map(|row| &row[1..]) {
+        for x in 0..width {
+            let sample = |c: usize| png_sample(row, x * channels + c, depth);
+            let level = |c: usize| png_level(sample(c), depth);
+            let pixel = match color {
+                0 => [level(0), level(0), level(0), if key == Some([sample(0); 3]) { 0 } else { 255 }],
+                2 => {
+                    let alpha = if key == Some([sample(0), sample(1), sample(2)]) { 0 } else { 255 };
+                    [level(0), level(1), level(2), alpha]
+                }
+                3 => {
+                    let index = sample(0) as usize;
+                    let rgb = palette.get(3 * index..3 * index + 3).unwrap_or(&[0; 3]);
+                    [rgb[0], rgb[1], rgb[2], transparency.get(index).copied().unwrap_or(255)]
+                }
+                4 => [level(0), level(0), level(0), level(1)],
+                _ => [level(0), level(1), level(2), level(3)],
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+    Ok((rgba, width, height, orientation))
+}
+
+/// Decodes a JPEG or PNG file, e.g. a captured `Blob` or `File` read into
+/// bytes, to RGBA, turned upright per its EXIF orientation as browsers show
+/// it. Baseline and progressive JPEGs (gray, RGB or CMYK) and non-interlaced
+/// PNGs of any colour type are supported, up to 64 megapixels.
+///
+/// # Arguments
+/// * `bytes` - JPEG or PNG file bytes
+///
+/// # Returns
+/// The upright image with its size and original orientation
+#[wasm_bindgen]
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, ScanError> {
+    let (rgba, width, height, orientation) = if bytes.starts_with(&PNG_SIGNATURE) {
+        decode_png(bytes)?
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        decode_jpeg(bytes)?
+    } else {
+        return Err(ScanError::InvalidData("Not a JPEG or PNG file"));
+    };
+    let (rgba, width, height) = apply_exif_orientation(rgba, width, height, orientation);
+    Ok(DecodedImage { rgba, width, height, orientation })
+}
+
+/// `detect_document` on a JPEG or PNG file, decoded and turned upright with
+/// `decode_image`. The corners refer to the upright image.
+///
+/// # Arguments
+/// * `bytes` - JPEG or PNG file bytes
+/// * `options` - Canny settings; dilation closes small gaps in the page border
+///
+/// # Returns
+/// `DetectionResult` with the quad, confidence and failure reason
+#[cfg(feature = "detection")]
+#[wasm_bindgen]
+pub fn detect_document_encoded(bytes: &[u8], options: &CannyOptions) -> Result<DetectionResult, ScanError> {
+    let image = decode_image(bytes)?;
+    detect_document(&image.gray(), image.width, image.height, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+
+    // EXIF block holding only an orientation, big-endian like most cameras.
+    fn exif(orientation: u16) -> Vec<u8> {
+        let mut exif = b"MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&[(orientation >> 8) as u8, orientation as u8, 0, 0, 0, 0, 0, 0]);
+        exif
+    }
+
+    // PNG from filtered rows and extra chunks placed before the image data.
+    // CRCs are left zero; the decoder doesn't check them.
+    fn png(width: u32, height: u32, depth: u8, color: u8, rows: &[u8], chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut header = [width.to_be_bytes(), height.to_be_bytes()].concat();
+        header.extend_from_slice(&[depth, color, 0, 0, 0]);
+        let data = compress_to_vec_zlib(rows, 6);
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (kind, body) in [(b"IHDR", &header[..])]
+            .into_iter()
+            .chain(chunks.iter().copied())
+            .chain([(b"IDAT", &data[..]), (b"IEND", &[][..])])
+        {
+            png.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(body);
+            png.extend_from_slice(&[0; 4]);
+        }
+        png
+    }
+
+    #[test]
+    fn test_decode_image() {
+        // RGBA rows with the Sub, Up, Average and Paeth filters
+        let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| (i * 37 % 256) as u8).collect();
+        let mut rows = vec![];
+        for (y, row) in pixels.chunks_exact(12).enumerate() {
+            let above = if y > 0 { &pixels[(y - 1) * 12..y * 12] } else { &[0; 12][..] };
+            rows.push(y as u8 + 1);
+            for x in 0..12 {
+                let (a, b, c) = if x >= 4 { (row[x - 4], above[x], above[x - 4]) } else { (0, above[x], 0) };
+                let predicted = match y + 1 {
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => {
+                        let p = a as i16 + b as i16 - c as i16;
+                        let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+                        if pa <= pb && pa <= pc {
+                            a
+                        } else if pb <= pc {
+                            b
+                        } else {
+                            c
+                        }
+                    }
+                };
+                rows.push(row[x].wrapping_sub(predicted));
+            }
+        }
+        let image = decode_image(&png(3, 4, 8, 6, &rows, &[])).unwrap();
+        assert_eq!((image.width(), image.height(), image.orientation(), image.rgba()), (3, 4, 1, pixels));
+
+        // 2-bit palette with a transparent entry, and 16-bit gray with a
+        // transparent level
+        let palette = png(
+            5,
+            1,
+            2,
+            3,
+            &[0, 0b00_01_10_00, 0b01_000000],
+            &[(b"PLTE", &[9, 8, 7, 1, 2, 3, 4, 5, 6]), (b"tRNS", &[255, 0])],
+        );
+        assert_eq!(
+            decode_image(&palette).unwrap().rgba(),
+            [9, 8, 7, 255, 1, 2, 3, 0, 4, 5, 6, 255, 9, 8, 7, 255, 1, 2, 3, 0]
+        );
+        let deep = png(2, 1, 16, 0, &[0, 0x12, 0x34, 0xab, 0xcd], &[(b"tRNS", &[0x12, 0x34])]);
+        assert_eq!(decode_image(&deep).unwrap().rgba(), [0x12, 0x12, 0x12, 0, 0xab, 0xab, 0xab, 255]);
+
+        // Orientation 6: stored rotated a quarter turn counter-clockwise
+        let gray = png(3, 2, 8, 0, &[0, 1, 2, 3, 0, 4, 5, 6], &[(b"eXIf", &exif(6))]);
+        let upright = decode_image(&gray).unwrap();
+        assert_eq!((upright.width(), upright.height(), upright.orientation()), (2, 3, 6));
+        assert_eq!(upright.gray(), [4, 1, 5, 2, 6, 3]);
+
+        #[cfg(feature = "jpeg")]
+        {
+            let jpeg = crate::jpeg::encode_jpeg_gray(&[200; 16 * 8], 16, 8, 90).unwrap();
+            let app1 = [&[0xff, 0xe1, 0, 8 + 26][..], b"Exif\0\0", &exif(8)].concat();
+            let jpeg = [&jpeg[..2], &app1, &jpeg[2..]].concat();
+            let image = decode_image(&jpeg).unwrap();
+            assert_eq!((image.width(), image.height(), image.orientation()), (8, 16, 8));
+            assert!(image.gray().iter().all(|&v| v.abs_diff(200) <= 2));
+        }
+        #[cfg(all(feature = "png", feature = "detection"))]
+        {
+            let (width, height) = (120, 90);
+            let frame: Vec<u8> = (0..width * height)
+                .map(|i| if (20..100).contains(&(i % width)) && (15..75).contains(&(i / width)) { 220 } else { 60 })
+                .collect();
+            let file = crate::png::encode_png(&frame, width, height, crate::png::PngMode::Gray8).unwrap();
+            assert!(detect_document_encoded(&file, &CannyOptions::new()).unwrap().found());
+        }
+
+        assert!(decode_image(b"GIF89a").is_err());
+        assert!(decode_image(&png(2, 2, 8, 0, &[0; 5], &[])).is_err());
+        let mut interlaced = png(1, 1, 8, 0, &[0, 0], &[]);
+        interlaced[8 + 8 + 12] = 1;
+        assert!(decode_image(&interlaced).is_err());
+    }
+}
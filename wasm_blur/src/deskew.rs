@@ -0,0 +1,174 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::warp::{warp_with_matrix, OutOfBoundsPolicy};
+
+// Ink pixels are sampled on a grid coarse enough to keep about this many pixels.
+const MAX_SAMPLES: usize = 1 << 18;
+// Coarse search step in degrees; the best coarse angle is refined around it.
+const COARSE_STEP: f32 = 0.5;
+const FINE_STEP: f32 = 0.05;
+
+// Positions of ink pixels on a grid with spacing `step`.
+fn ink_points(gray: &[u8], width: usize, height: usize, step: usize) -> Vec<(f32, f32)> {
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in (0..height).step_by(step) {
+        for x in (0..width).step_by(step) {
+            sum += gray[y * width + x] as u64;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Vec::new();
+    }
+    // Pixels darker than three quarters of the mean brightness count as ink
+    let threshold = (sum / count) as u32 * 3 / 4;
+
+    let mut points = Vec::new();
+    for y in (0..height).step_by(step) {
+        for x in (0..width).step_by(step) {
+            if (gray[y * width + x] as u32) < threshold {
+                points.push((x as f32, y as f32));
+            }
+        }
+    }
+    points
+}
+
+// Sum of squared row counts of the ink projected along lines tilted by `angle`
+// degrees. Text lines aligned with the projection pile up in few rows, which
+// maximises the sum.
+fn profile_score(points: &[(f32, f32)], bins: &mut [u32], width: usize, step: usize, angle: f32) -> u64 {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    bins.iter_mut().for_each(|b| *b = 0);
+    for &(x, y) in points {
+        // Distance across the tilted lines, shifted so it is never negative
+        let across = y * cos - x * sin + width as f32;
+        let bin = (across / step as f32) as usize;
+        if let Some(b) = bins.get_mut(bin) {
+            *b += 1;
+        }
+    }
+    bins.iter().map(|&b| b as u64 * b as u64).sum()
+}
+
+fn search(points: &[(f32, f32)], bins: &mut [u32], width: usize, step: usize, from: f32, to: f32, by: f32) -> f32 {
+    let steps = ((to - from) / by).round().max(0.0) as usize;
+    let mut best = (0, 0f32);
+    for i in 0..=steps {
+        let angle = from + i as f32 * by;
+        let score = profile_score(points, bins, width, step, angle);
+        // Prefer the smaller correction among equal scores
+        if score > best.0 || (score == best.0 && angle.abs() < best.1.abs()) {
+            best = (score, angle);
+        }
+    }
+    best.1
+}
+
+/// Estimates the skew of text lines and other dark horizontal structure in an
+/// RGBA image by projection profiles: the ink is projected along candidate
+/// angles and the angle giving the sharpest row profile wins.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels, e.g. a rectified page
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `max_angle` - Largest skew searched, in degrees either way
+///
+/// # Returns
+/// Skew in degrees, positive when the content is turned clockwise on screen;
+/// 0 for images without ink
+#[wasm_bindgen]
+pub fn estimate_skew(rgba: &[u8], width: usize, height: usize, max_angle: f32) -> Result<f32, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let max_angle = max_angle.clamp(0.0, 45.0);
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, GrayscaleWeights::Bt601);
+
+    let step = ((width * height) as f32 / MAX_SAMPLES as f32).sqrt().ceil().max(1.0) as usize;
+    let points = ink_points(&gray, width, height, step);
+    if points.is_empty() {
+        return Ok(0.0);
+    }
+    let mut bins = vec![0u32; (width + height) / step + 2];
+
+    let coarse = search(&points, &mut bins, width, step, -max_angle, max_angle, COARSE_STEP);
+    let from = (coarse - COARSE_STEP).max(-max_angle);
+    let to = (coarse + COARSE_STEP).min(max_angle);
+    Ok(search(&points, &mut bins, width, step, from, to, FINE_STEP))
+}
+
+// Rotates the image content by `-angle` degrees about its centre, keeping the
+// size; the uncovered corners are filled with the paper colour.
+pub(crate) fn rotate(rgba: &[u8], width: usize, height: usize, angle: f32) -> Vec<u8> {
+    let (sin, cos) = (angle as f64).to_radians().sin_cos();
+    let (cx, cy) = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+    // Output to source: rotate by `angle` about the centre
+    let m = [
+        cos,
+        -sin,
+        cx - cos * cx + sin * cy,
+        sin,
+        cos,
+        cy - sin * cx - cos * cy,
+        0.0,
+        0.0,
+        1.0,
+    ];
+    warp_with_matrix(rgba, width, height, &m, width, height, OutOfBoundsPolicy::PaperFill, 0)
+}
+
+/// Straightens a slightly tilted scan: estimates the skew with `estimate_skew`
+/// and rotates the image back with bilinear resampling. The size is kept and
+/// the uncovered corners are filled with the paper colour.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels, e.g. a rectified page
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `max_angle` - Largest skew corrected, in degrees either way
+///
+/// # Returns
+/// Deskewed RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn deskew(rgba: &[u8], width: usize, height: usize, max_angle: f32) -> Result<Vec<u8>, ScanError> {
+    let angle = estimate_skew(rgba, width, height, max_angle)?;
+    if angle == 0.0 {
+        return Ok(rgba.to_vec());
+    }
+    Ok(rotate(rgba, width, height, angle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_skew_and_deskew() {
+        // White page with dark text lines tilted 3 degrees clockwise
+        let (width, height) = (200usize, 160usize);
+        let tilt = 3f32.to_radians().tan();
+        let mut rgba = vec![255u8; width * height * 4];
+        for line in 0..6 {
+            for x in 20..180 {
+                let y = (25 + line * 20) as f32 + (x as f32 - 100.0) * tilt;
+                let i = (y.round() as usize * width + x) * 4;
+                rgba[i..i + 3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+
+        let skew = estimate_skew(&rgba, width, height, 10.0).unwrap();
+        assert!((skew - 3.0).abs() <= 0.1, "skew {skew}");
+
+        // After deskewing the lines are level again
+        let straight = deskew(&rgba, width, height, 10.0).unwrap();
+        assert!(estimate_skew(&straight, width, height, 10.0).unwrap().abs() <= 0.1);
+        assert_eq!(estimate_skew(&[255u8; 64], 4, 4, 10.0).unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,335 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+
+use crate::canny::{canny_edge_detector, CannyOptions};
+use crate::error::{check_gray, ScanError};
+
+// Frames whose 1st to 99th percentile luma spread is below this show no page
+// against its background.
+const MIN_CONTRAST: usize = 24;
+// Edge maps with fewer edge pixels than this share of the frame have nothing
+// to trace.
+const MIN_EDGE_FRACTION: f32 = 0.002;
+// Regions enclosed by edges smaller than this are texture, not a page.
+const MIN_REGION_PIXELS: usize = 64;
+// Quads covering less than this share of the frame are too small to scan.
+const MIN_AREA_FRACTION: f32 = 0.05;
+// Samples per side and search radius (px) for the edge support of a quad.
+const SIDE_SAMPLES: usize = 32;
+const SUPPORT_RADIUS: isize = 2;
+
+// Pixel labels while tracing the enclosed regions.
+const OUTSIDE: u32 = u32::MAX;
+const EDGE: u32 = u32::MAX - 1;
+const UNVISITED: u32 = 0;
+
+/// Why `detect_document` found no usable page.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetectionFailure {
+    /// A page was found
+    None = 0,
+    /// The frame is too flat to tell a page from its background
+    LowContrast = 1,
+    /// Canny found (almost) no edges
+    NoEdges = 2,
+    /// There are edges, but none of them enclose a region, e.g. the page runs
+    /// off the frame
+    NoClosedContour = 3,
+    /// The best quad covers less than 5 % of the frame
+    QuadTooSmall = 4,
+}
+
+impl DetectionFailure {
+    fn code(self) -> &'static str {
+        match self {
+            DetectionFailure::None => "",
+            DetectionFailure::LowContrast => "low_contrast",
+            DetectionFailure::NoEdges => "no_edges",
+            DetectionFailure::NoClosedContour => "no_closed_contour",
+            DetectionFailure::QuadTooSmall => "quad_too_small",
+        }
+    }
+}
+
+/// Outcome of `detect_document`: the page quad if one was found, a confidence
+/// and the reason detection failed otherwise.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetectionResult {
+    corners: Option<[f32; 8]>,
+    confidence: f32,
+    failure: DetectionFailure,
+}
+
+impl DetectionResult {
+    pub(crate) fn failed(failure: DetectionFailure) -> DetectionResult {
+        DetectionResult { corners: None, confidence: 0.0, failure }
+    }
+}
+
+#[wasm_bindgen]
+impl DetectionResult {
+    /// Whether a page was found.
+    pub fn found(&self) -> bool {
+        self.failure == DetectionFailure::None
+    }
+
+    /// Page corners (TL, TR, BR, BL as 8 values). Also set for
+    /// `QuadTooSmall`, so the rejected quad can be shown; empty otherwise.
+    pub fn corners(&self) -> Vec<f32> {
+        self.corners.map_or_else(Vec::new, |c| c.to_vec())
+    }
+
+    /// How well the quad fits the page, 0..1: the share of the quad filled by
+    /// the enclosed region times the share of its sides backed by edges.
+    #[wasm_bindgen(getter)]
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn failure(&self) -> DetectionFailure {
+        self.failure
+    }
+
+    /// Machine-readable failure reason ("low_contrast", "no_edges",
+    /// "no_closed_contour", "quad_too_small"), empty when a page was found.
+    pub fn reason(&self) -> String {
+        self.failure.code().to_string()
+    }
+}
+
+// Spread between the 1st and 99th percentile of the luma histogram.
+fn contrast(gray: &[u8]) -> usize {
+    let mut histogram = [0usize; 256];
+    for &v in gray {
+        histogram[v as usize] += 1;
+    }
+    let percentile = |p: usize| {
+        let target = gray.len() * p / 100;
+        let mut seen = 0;
+        histogram.iter().position(|&n| {
+            seen += n;
+            seen > target
+        })
+    };
+    match (percentile(1), percentile(99)) {
+        (Some(low), Some(high)) => high - low,
+        _ => 0,
+    }
+}
+
+// Labels the pixels reachable from `seeds` through 4-connected pixels that
+// still hold UNVISITED, returning how many were labelled.
+fn fill(labels: &mut [u32], width: usize, height: usize, seeds: &[usize], label: u32, stack: &mut Vec<usize>) -> usize {
+    stack.clear();
+    for &s in seeds {
+        if labels[s] == UNVISITED {
+            labels[s] = label;
+            stack.push(s);
+        }
+    }
+    let mut count = stack.len();
+    while let Some(i) = stack.pop() {
+        let (x, y) = (i % width, i / width);
+        let neighbours = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+        for n in neighbours.into_iter().flatten() {
+            if labels[n] == UNVISITED {
+                labels[n] = label;
+                stack.push(n);
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+// Shoelace area of a quad.
+pub(crate) fn quad_area(q: &[f32; 8]) -> f32 {
+    (0..4).map(|i| q[2 * i] * q[(2 * i + 3) % 8] - q[(2 * i + 2) % 8] * q[2 * i + 1]).sum::<f32>().abs() / 2.0
+}
+
+// Share of points along the quad's sides with an edge pixel nearby.
+pub(crate) fn edge_support(edges: &[u8], width: usize, height: usize, q: &[f32; 8]) -> f32 {
+    let mut supported = 0;
+    for side in 0..4 {
+        let (ax, ay, bx, by) = (q[2 * side], q[2 * side + 1], q[(2 * side + 2) % 8], q[(2 * side + 3) % 8]);
+        for s in 0..SIDE_SAMPLES {
+            let t = (s as f32 + 0.5) / SIDE_SAMPLES as f32;
+            let (x, y) = ((ax + (bx - ax) * t).round() as isize, (ay + (by - ay) * t).round() as isize);
+            let near = (-SUPPORT_RADIUS..=SUPPORT_RADIUS).any(|dy| {
+                (-SUPPORT_RADIUS..=SUPPORT_RADIUS).any(|dx| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    nx >= 0
+                        && ny >= 0
+                        && (nx as usize) < width
+                        && (ny as usize) < height
+                        && edges[ny as usize * width + nx as usize] != 0
+                })
+            });
+            supported += near as usize;
+        }
+    }
+    supported as f32 / (4 * SIDE_SAMPLES) as f32
+}
+
+// Labels the regions enclosed by edges with 1, 2, ... in scan order. Everything
+// reachable from the frame border without crossing an edge lies outside any
+// contour and is labelled OUTSIDE. Returns the labels and the pixel count of
+// each region, `pixels[label - 1]`.
+pub(crate) fn label_enclosed_regions(edges: &[u8], width: usize, height: usize) -> (Vec<u32>, Vec<usize>) {
+    let mut labels: Vec<u32> = edges.iter().map(|&e| if e != 0 { EDGE } else { UNVISITED }).collect();
+    let border: Vec<usize> = (0..width)
+        .flat_map(|x| [x, (height - 1) * width + x])
+        .chain((0..height).flat_map(|y| [y * width, y * width + width - 1]))
+        .collect();
+    let mut stack = Vec::new();
+    fill(&mut labels, width, height, &border, OUTSIDE, &mut stack);
+    let mut pixels = Vec::new();
+    for i in 0..labels.len() {
+        if labels[i] == UNVISITED {
+            pixels.push(fill(&mut labels, width, height, &[i], pixels.len() as u32 + 1, &mut stack));
+        }
+    }
+    (labels, pixels)
+}
+
+// Corners (TL, TR, BR, BL) of the region with `label`: its pixels extremal
+// along the two diagonals, pushed out by `margin` px onto the middle of the
+// edge band.
+pub(crate) fn region_corners(labels: &[u32], label: u32, width: usize, height: usize, margin: f32) -> [f32; 8] {
+    // Extremes of x + y and x - y: TL, TR, BR, BL
+    let mut extremes = [(f32::INFINITY, 0usize); 4];
+    for (i, _) in labels.iter().enumerate().filter(|(_, &l)| l == label) {
+        let (x, y) = ((i % width) as f32, (i / width) as f32);
+        for (extreme, key) in extremes.iter_mut().zip([x + y, y - x, -(x + y), x - y]) {
+            if key < extreme.0 {
+                *extreme = (key, i);
+            }
+        }
+    }
+    let mut corners = [0f32; 8];
+    for (corner, (&(_, i), (dx, dy))) in
+        corners.chunks_exact_mut(2).zip(extremes.iter().zip([(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]))
+    {
+        let (x, y) = ((i % width) as f32 + dx * margin, (i / width) as f32 + dy * margin);
+        corner[0] = x.clamp(0.0, (width - 1) as f32);
+        corner[1] = y.clamp(0.0, (height - 1) as f32);
+    }
+    corners
+}
+
+// Detection on a frame and its edge map. The page is the largest region
+// enclosed by edges, fitted with `region_corners`.
+pub(crate) fn detect_from_edges(
+    gray: &[u8],
+    edges: &[u8],
+    width: usize,
+    height: usize,
+    margin: f32,
+) -> DetectionResult {
+    if contrast(gray) < MIN_CONTRAST {
+        return DetectionResult::failed(DetectionFailure::LowContrast);
+    }
+    let size = width * height;
+    let edge_pixels = edges.iter().filter(|&&e| e != 0).count();
+    if edge_pixels == 0 || (edge_pixels as f32) < MIN_EDGE_FRACTION * size as f32 {
+        return DetectionResult::failed(DetectionFailure::NoEdges);
+    }
+
+    let (labels, pixels) = label_enclosed_regions(edges, width, height);
+    let Some((best, &best_pixels)) = pixels.iter().enumerate().rev().max_by_key(|&(_, &n)| n) else {
+        return DetectionResult::failed(DetectionFailure::NoClosedContour);
+    };
+    if best_pixels < MIN_REGION_PIXELS {
+        return DetectionResult::failed(DetectionFailure::NoClosedContour);
+    }
+    let corners = region_corners(&labels, best as u32 + 1, width, height, margin);
+
+    let area = quad_area(&corners);
+    let fill_ratio = if area > 0.0 { (best_pixels as f32 / area).min(1.0) } else { 0.0 };
+    let confidence = fill_ratio * edge_support(edges, width, height, &corners);
+    let failure = if area < MIN_AREA_FRACTION * size as f32 {
+        DetectionFailure::QuadTooSmall
+    } else {
+        DetectionFailure::None
+    };
+    DetectionResult { corners: Some(corners), confidence, failure }
+}
+
+/// Finds the document page in a grayscale frame: Canny with `options`, then the
+/// largest region enclosed by edges, fitted with a quad. Instead of returning
+/// nothing on failure, the result says why: a flat frame, no edges, no closed
+/// contour or a quad too small to scan.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `options` - Canny settings; dilation closes small gaps in the page border
+///
+/// # Returns
+/// `DetectionResult` with the quad, confidence and failure reason
+#[wasm_bindgen]
+pub fn detect_document(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    options: &CannyOptions,
+) -> Result<DetectionResult, ScanError> {
+    check_gray(gray, width, height)?;
+    if width == 0 || height == 0 {
+        return Ok(DetectionResult::failed(DetectionFailure::LowContrast));
+    }
+    let options = options.for_size(width, height);
+    let edges = canny_edge_detector(gray, width, height, &options)?;
+    Ok(detect_from_edges(gray, &edges, width, height, (options.dilation_kernel_size() / 2) as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_reports_failure_reasons() {
+        let (width, height) = (120, 90);
+        let frame = |page: &dyn Fn(usize, usize) -> bool| -> Vec<u8> {
+            (0..width * height).map(|i| if page(i % width, i / width) { 220 } else { 60 }).collect()
+        };
+        let options = CannyOptions::new();
+        let detect = |gray: &[u8]| detect_document(gray, width, height, &options).unwrap();
+
+        let page = detect(&frame(&|x, y| (20..100).contains(&x) && (15..75).contains(&y)));
+        assert!(page.found() && page.reason().is_empty() && page.confidence() > 0.8, "{page:?}");
+        let expected = [20.0, 15.0, 99.0, 15.0, 99.0, 74.0, 20.0, 74.0];
+        assert!(page.corners().iter().zip(expected).all(|(c, e)| (c - e).abs() <= 2.0), "{page:?}");
+
+        assert_eq!(detect(&vec![128u8; width * height]).failure(), DetectionFailure::LowContrast);
+        // A smooth ramp has contrast but no edges strong enough for Canny
+        let ramp: Vec<u8> = (0..width * height).map(|i| (i % width * 2) as u8).collect();
+        assert_eq!(detect(&ramp).reason(), "no_edges");
+        // A page running off the frame at the right
+        let open = detect(&frame(&|x, y| x >= 40 && (15..75).contains(&y)));
+        assert_eq!(open.failure(), DetectionFailure::NoClosedContour);
+        assert!(open.corners().is_empty());
+        let small = detect(&frame(&|x, y| (50..68).contains(&x) && (40..55).contains(&y)));
+        assert_eq!((small.failure(), small.corners().len()), (DetectionFailure::QuadTooSmall, 8));
+
+        // ScanContext detects on the edge map of its last frame
+        let mut context = crate::context::ScanContext::new(width, height);
+        assert_eq!(context.detect_document().failure(), DetectionFailure::NoEdges);
+        let gray = frame(&|x, y| (20..100).contains(&x) && (15..75).contains(&y));
+        context.process_gray_frame(&gray).unwrap();
+        assert_eq!(context.detect_document(), page);
+    }
+}
@@ -0,0 +1,176 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+use crate::warp::sample_bilinear;
+
+type Point = (f32, f32);
+
+// Points sampled on each Catmull-Rom span between two control points.
+const SAMPLES_PER_SPAN: usize = 16;
+
+fn to_curve(points: &[f32]) -> Result<Vec<Point>, ScanError> {
+    if !points.len().is_multiple_of(2) || points.len() < 4 {
+        return Err(ScanError::InvalidPoints("Page boundary curves need at least 2 x, y points"));
+    }
+    Ok(points.chunks_exact(2).map(|p| (p[0], p[1])).collect())
+}
+
+// Catmull-Rom spline through the control points, as a dense polyline. The end
+// points are repeated so the curve starts and ends on them.
+fn smooth_curve(points: &[Point]) -> Vec<Point> {
+    let n = points.len();
+    let at = |i: isize| points[i.clamp(0, n as isize - 1) as usize];
+    let mut out = Vec::with_capacity((n - 1) * SAMPLES_PER_SPAN + 1);
+    for i in 0..n as isize - 1 {
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        for s in 0..SAMPLES_PER_SPAN {
+            let t = s as f32 / SAMPLES_PER_SPAN as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let blend = |a: f32, b: f32, c: f32, d: f32| {
+                0.5 * (2.0 * b + (c - a) * t + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2 + (3.0 * b - a - 3.0 * c + d) * t3)
+            };
+            out.push((blend(p0.0, p1.0, p2.0, p3.0), blend(p0.1, p1.1, p2.1, p3.1)));
+        }
+    }
+    out.push(points[n - 1]);
+    out
+}
+
+fn curve_length(curve: &[Point]) -> f32 {
+    curve.windows(2).map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1)).sum()
+}
+
+// `count` points at equal arc-length steps along a polyline, both ends included.
+fn resample(curve: &[Point], count: usize) -> Vec<Point> {
+    let total = curve_length(curve);
+    let mut out = Vec::with_capacity(count);
+    let mut segment = 0;
+    let mut walked = 0.0;
+    for i in 0..count {
+        let target = if count > 1 { total * i as f32 / (count - 1) as f32 } else { 0.0 };
+        // Advance to the segment containing `target`
+        while segment + 2 < curve.len() {
+            let (a, b) = (curve[segment], curve[segment + 1]);
+            let length = (b.0 - a.0).hypot(b.1 - a.1);
+            if walked + length >= target {
+                break;
+            }
+            walked += length;
+            segment += 1;
+        }
+        let (a, b) = (curve[segment], curve[segment + 1]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        let t = if length > 0.0 { ((target - walked) / length).clamp(0.0, 1.0) } else { 0.0 };
+        out.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+    }
+    out
+}
+
+/// Flattens a curved page, e.g. a book page or a curled receipt, where a plain
+/// homography leaves the text lines bent. The top and bottom page boundaries are
+/// modelled as smooth curves through the given points; every output column is
+/// the straight line between the matching points of the two curves (equal arc
+/// length along each), which follows the cylinder-like bend of the page.
+///
+/// # Arguments
+/// * `rgba` - Source RGBA pixels
+/// * `width` - Source width
+/// * `height` - Source height
+/// * `top` - Points along the top page boundary, left to right, as x, y pairs
+/// * `bottom` - Points along the bottom page boundary, left to right
+/// * `out_width` - Output width; 0 uses the longer boundary's length
+/// * `out_height` - Output height; 0 uses the mean length of the page sides
+///
+/// # Returns
+/// Flattened RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn dewarp_page(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    top: &[f32],
+    bottom: &[f32],
+    out_width: usize,
+    out_height: usize,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let top = smooth_curve(&to_curve(top)?);
+    let bottom = smooth_curve(&to_curve(bottom)?);
+
+    let side = |a: Point, b: Point| (b.0 - a.0).hypot(b.1 - a.1);
+    let out_width = if out_width > 0 {
+        out_width
+    } else {
+        (curve_length(&top).max(curve_length(&bottom)).round() as usize).max(1)
+    };
+    let out_height = if out_height > 0 {
+        out_height
+    } else {
+        let left = side(top[0], bottom[0]);
+        let right = side(top[top.len() - 1], bottom[bottom.len() - 1]);
+        (((left + right) / 2.0).round() as usize).max(1)
+    };
+
+    let mut out = vec![0u8; out_width * out_height * 4];
+    if width == 0 || height == 0 {
+        return Ok(out);
+    }
+    let top = resample(&top, out_width);
+    let bottom = resample(&bottom, out_width);
+    let rows = (out_height.max(2) - 1) as f32;
+
+    for oy in 0..out_height {
+        if oy % crate::scheduler::YIELD_INTERVAL_ROWS == 0 {
+            crate::scheduler::yield_point();
+        }
+        let v = oy as f32 / rows;
+        let row = &mut out[oy * out_width * 4..(oy + 1) * out_width * 4];
+        for ((px, t), b) in row.chunks_exact_mut(4).zip(&top).zip(&bottom) {
+            let sx = t.0 + (b.0 - t.0) * v;
+            let sy = t.1 + (b.1 - t.1) * v;
+            px.copy_from_slice(&sample_bilinear(rgba, width, height, sx as f64, sy as f64));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dewarp_flattens_bent_rows() {
+        // Horizontal stripes bent into an arc, like text lines on a book page
+        let (width, height) = (120usize, 90usize);
+        let bend = |x: f32| 20.0 - 0.004 * (x - 60.0) * (x - 60.0);
+        let stripe = |y: f32| if (y as usize / 6).is_multiple_of(2) { 255 } else { 0 };
+        let mut rgba = vec![128u8; width * height * 4];
+        for y in 0..height {
+            for x in 0..width {
+                let v = stripe(y as f32 - bend(x as f32) + 30.0);
+                rgba[(y * width + x) * 4..][..4].copy_from_slice(&[v, v, v, 255]);
+            }
+        }
+
+        let top: Vec<f32> = (0..=6).flat_map(|i| [i as f32 * 18.0 + 6.0, bend(i as f32 * 18.0 + 6.0) + 10.0]).collect();
+        let bottom: Vec<f32> = top.chunks_exact(2).flat_map(|p| [p[0], p[1] + 48.0]).collect();
+        let flat = dewarp_page(&rgba, width, height, &top, &bottom, 100, 49).unwrap();
+        assert_eq!(flat.len(), 100 * 49 * 4);
+
+        // Each output row away from the stripe borders has a single colour
+        for oy in (0..49).filter(|y| y % 6 != 2 && y % 6 != 3) {
+            let row = &flat[oy * 400..(oy + 1) * 400];
+            let (min, max) = row.chunks_exact(4).fold((255, 0), |(lo, hi), p| (p[0].min(lo), p[0].max(hi)));
+            assert!(max - min < 64, "row {oy}: {min}..{max}");
+        }
+
+        // Straight boundaries along the image edges reproduce the image
+        let top = [0.0, 0.0, 60.0, 0.0, 119.0, 0.0];
+        let bottom = [0.0, 89.0, 119.0, 89.0];
+        assert_eq!(dewarp_page(&rgba, width, height, &top, &bottom, width, height).unwrap(), rgba);
+        assert_eq!(dewarp_page(&rgba, width, height, &top, &bottom, 0, 0).unwrap().len(), 119 * 89 * 4);
+    }
+}
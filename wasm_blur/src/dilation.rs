@@ -1,104 +1,108 @@
 use wasm_bindgen::prelude::*;
 
-#[cfg(target_arch = "wasm32")]
-use std::arch::wasm32::*;
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
-#[cfg(target_arch = "wasm32")]
-#[target_feature(enable = "simd128")]
-unsafe fn dilate_fast(
-    edges: &[u8],
-    width: usize,
-    height: usize,
-    kernel_size: usize,
-    dilated: &mut [u8],
-) {
-    let half_kernel = kernel_size / 2;
-    let mut temp = vec![0u8; width * height];
+use crate::error::{check_gray, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
 
-    // Horizontal pass (scalar for simplicity and because it's cache-friendly)
-    for y in 0..height {
-        for x in 0..width {
-            let mut max_val = 0;
-            for k in 0..kernel_size {
-                let dx = k as isize - half_kernel as isize;
-                let nx = (x as isize + dx).clamp(0, (width - 1) as isize) as usize;
-                let val = edges[y * width + nx];
-                if val > max_val {
-                    max_val = val;
-                }
+#[cfg(feature = "threads")]
+use crate::scheduler::ROWS_PER_TASK;
+
+// Horizontal pass for one row (scalar for simplicity and because it's cache-friendly)
+fn dilate_row_horizontal(edges_row: &[u8], temp_row: &mut [u8], kernel_size: usize) {
+    let width = edges_row.len();
+    let half_kernel = kernel_size / 2;
+    for (x, out) in temp_row.iter_mut().enumerate() {
+        let mut max_val = 0;
+        for k in 0..kernel_size {
+            let dx = k as isize - half_kernel as isize;
+            let nx = (x as isize + dx).clamp(0, (width - 1) as isize) as usize;
+            let val = edges_row[nx];
+            if val > max_val {
+                max_val = val;
             }
-            temp[y * width + x] = max_val;
         }
+        *out = max_val;
     }
+}
 
-    // Vertical pass (SIMD optimized)
-    let x_chunks = width / 16;
-    let y_safe_start = half_kernel;
-    let y_safe_end = height.saturating_sub(half_kernel);
-
-    // Process top edge rows with scalar code
-    for y in 0..y_safe_start {
-        for x in 0..width {
-            let mut max_val = 0;
-            for k in 0..kernel_size {
-                let dy = k as isize - half_kernel as isize;
-                let ny = (y as isize + dy).clamp(0, (height - 1) as isize) as usize;
-                let val = temp[ny * width + x];
-                if val > max_val {
-                    max_val = val;
-                }
+// Vertical pass for output row `y`, reading the horizontally dilated `temp` plane
+fn dilate_row_vertical(temp: &[u8], dilated_row: &mut [u8], width: usize, height: usize, y: usize, kernel_size: usize) {
+    let half_kernel = kernel_size / 2;
+
+    // Rows whose whole kernel window is inside the image take the SIMD path
+    #[cfg(target_family = "wasm")]
+    {
+        if simd_enabled() && y >= half_kernel && y + half_kernel < height {
+            unsafe {
+                dilate_row_vertical_simd(temp, dilated_row, width, y, kernel_size);
             }
-            dilated[y * width + x] = max_val;
+            return;
         }
     }
 
-    // Process middle rows with SIMD
-    for y in y_safe_start..y_safe_end {
-        // SIMD part for full chunks
-        for chunk_idx in 0..x_chunks {
-            let x = chunk_idx * 16;
-            // Since we are in the safe y-zone, we don't need to clamp ny.
-            // The first load can be the initial max_vec
-            let mut max_vec = v128_load(temp.as_ptr().add((y as isize - half_kernel as isize) as usize * width + x) as *const v128);
-
-            for k in 1..kernel_size {
-                let dy = k as isize - half_kernel as isize;
-                let ny = (y as isize + dy) as usize;
-                let current_vec = v128_load(temp.as_ptr().add(ny * width + x) as *const v128);
-                max_vec = u8x16_max(max_vec, current_vec);
+    // Top and bottom edge rows (and non-wasm or scalar-only runs) use clamped
+    // scalar code
+    for x in 0..width {
+        let mut max_val = 0;
+        for k in 0..kernel_size {
+            let dy = k as isize - half_kernel as isize;
+            let ny = (y as isize + dy).clamp(0, (height - 1) as isize) as usize;
+            let val = temp[ny * width + x];
+            if val > max_val {
+                max_val = val;
             }
-            v128_store(dilated.as_mut_ptr().add(y * width + x) as *mut v128, max_vec);
         }
+        dilated_row[x] = max_val;
+    }
+}
 
-        // Scalar part for the remainder of the row
-        for x in (x_chunks * 16)..width {
-            let mut max_val = 0;
-            for k in 0..kernel_size {
-                let dy = k as isize - half_kernel as isize;
-                let ny = (y as isize + dy) as usize; // No clamping needed here
-                let val = temp[ny * width + x];
-                if val > max_val {
-                    max_val = val;
-                }
-            }
-            dilated[y * width + x] = max_val;
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn dilate_row_vertical_simd(
+    temp: &[u8],
+    dilated_row: &mut [u8],
+    width: usize,
+    y: usize,
+    kernel_size: usize,
+) {
+    let half_kernel = kernel_size / 2;
+    let x_chunks = width / 16;
+
+    // SIMD part for full chunks
+    for chunk_idx in 0..x_chunks {
+        let x = chunk_idx * 16;
+        // Since we are in the safe y-zone, we don't need to clamp ny.
+        // The first load can be the initial max_vec
+        let mut max_vec = v128_load(temp.as_ptr().add((y - half_kernel) * width + x) as *const v128);
+
+        for k in 1..kernel_size {
+            let ny = y + k - half_kernel;
+            let current_vec = v128_load(temp.as_ptr().add(ny * width + x) as *const v128);
+            max_vec = u8x16_max(max_vec, current_vec);
         }
+        v128_store(dilated_row.as_mut_ptr().add(x) as *mut v128, max_vec);
     }
 
-    // Process bottom edge rows with scalar code
-    for y in y_safe_end..height {
-        for x in 0..width {
-            let mut max_val = 0;
-            for k in 0..kernel_size {
-                let dy = k as isize - half_kernel as isize;
-                let ny = (y as isize + dy).clamp(0, (height - 1) as isize) as usize;
-                let val = temp[ny * width + x];
-                if val > max_val {
-                    max_val = val;
-                }
+    // Scalar part for the remainder of the row
+    for x in (x_chunks * 16)..width {
+        let mut max_val = 0;
+        for k in 0..kernel_size {
+            let ny = y + k - half_kernel; // No clamping needed here
+            let val = temp[ny * width + x];
+            if val > max_val {
+                max_val = val;
             }
-            dilated[y * width + x] = max_val;
         }
+        dilated_row[x] = max_val;
     }
 }
 
@@ -108,50 +112,50 @@ pub fn dilate(
     width: usize,
     height: usize,
     kernel_size: usize,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(edges, width, height)?;
+    let mut temp = vec![0u8; width * height];
     let mut dilated = vec![0u8; width * height];
+    dilate_into(edges, width, height, kernel_size, &mut temp, &mut dilated);
+    Ok(dilated)
+}
+
+// Dilation into caller-owned buffers; `temp` and `dilated` must hold width * height bytes.
+// With the `threads` feature both passes are spread over the rayon pool by rows.
+pub(crate) fn dilate_into(
+    edges: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    temp: &mut [u8],
+    dilated: &mut [u8],
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
 
-    #[cfg(target_arch = "wasm32")]
-    unsafe {
-        dilate_fast(edges, width, height, kernel_size, &mut dilated);
+    #[cfg(feature = "threads")]
+    {
+        edges
+            .par_chunks(width)
+            .zip(temp.par_chunks_mut(width))
+            .with_min_len(ROWS_PER_TASK)
+            .for_each(|(edges_row, temp_row)| dilate_row_horizontal(edges_row, temp_row, kernel_size));
+        let temp = &*temp;
+        dilated
+            .par_chunks_mut(width)
+            .with_min_len(ROWS_PER_TASK)
+            .enumerate()
+            .for_each(|(y, row)| dilate_row_vertical(temp, row, width, height, y, kernel_size));
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(not(feature = "threads"))]
     {
-        let half_kernel = kernel_size / 2;
-        let mut temp = vec![0u8; width * height];
-        // Horizontal pass
-        for y in 0..height {
-            for x in 0..width {
-                let mut max_val = 0;
-                for k in 0..kernel_size {
-                    let dx = k as isize - half_kernel as isize;
-                    let nx = (x as isize + dx).clamp(0, (width - 1) as isize) as usize;
-                    let val = edges[y * width + nx];
-                    if val > max_val {
-                        max_val = val;
-                    }
-                }
-                temp[y * width + x] = max_val;
-            }
+        for (edges_row, temp_row) in edges.chunks(width).zip(temp.chunks_mut(width)) {
+            dilate_row_horizontal(edges_row, temp_row, kernel_size);
         }
-
-        // Vertical pass
-        for y in 0..height {
-            for x in 0..width {
-                let mut max_val = 0;
-                for k in 0..kernel_size {
-                    let dy = k as isize - half_kernel as isize;
-                    let ny = (y as isize + dy).clamp(0, (height - 1) as isize) as usize;
-                    let val = temp[ny * width + x];
-                    if val > max_val {
-                        max_val = val;
-                    }
-                }
-                dilated[y * width + x] = max_val;
-            }
+        for (y, row) in dilated.chunks_mut(width).enumerate() {
+            dilate_row_vertical(temp, row, width, height, y, kernel_size);
         }
     }
-
-    dilated
 }
@@ -0,0 +1,164 @@
+// Runtime choice between the SIMD passes and their scalar references. The scalar
+// code is what non-wasm builds run; switching a wasm build over to it lets users
+// bisect a SIMD-specific problem on their own images without a custom build.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+static SCALAR_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Switches the SIMD passes (blur, dilation, gradient magnitude, grayscale,
+/// pixel format and colour space conversion, resizing, tone curves) to their
+/// scalar reference implementations, or back. Both compute the same result, up
+/// to float rounding in the colour space and gradient magnitude passes, so an
+/// image that comes out differently points at a SIMD bug. The setting is global
+/// to the module instance.
+///
+/// # Arguments
+/// * `enabled` - Whether to use SIMD where the build supports it
+#[wasm_bindgen]
+pub fn set_simd_enabled(enabled: bool) {
+    SCALAR_ONLY.store(!enabled, Ordering::Relaxed);
+}
+
+/// Whether SIMD passes are used; `true` unless switched off with
+/// `set_simd_enabled`. Non-wasm builds have no SIMD passes and run the scalar
+/// code whatever this returns.
+#[wasm_bindgen]
+pub fn simd_enabled() -> bool {
+    !SCALAR_ONLY.load(Ordering::Relaxed)
+}
+
+// Tests run in parallel, so the ones flipping the global switch take turns;
+// otherwise a "SIMD" run could land while another test has it switched off.
+#[cfg(test)]
+static SWITCH: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+// Results of `run` with SIMD on and off, leaving it on.
+#[cfg(test)]
+pub(crate) fn simd_and_scalar<T>(run: impl Fn() -> T) -> (T, T) {
+    let _switch = SWITCH.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    set_simd_enabled(true);
+    let simd = run();
+    set_simd_enabled(false);
+    let scalar = run();
+    set_simd_enabled(true);
+    (simd, scalar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::border::BorderMode;
+    use crate::gaussian_blur::blur_with_border;
+
+    #[test]
+    fn test_scalar_blur_matches_simd() {
+        let borders = [BorderMode::Replicate, BorderMode::Reflect, BorderMode::Reflect101, BorderMode::Constant];
+        for (width, height) in [(37, 23), (16, 16), (5, 3), (1, 9)] {
+            let gray: Vec<u8> = (0..width * height).map(|i| ((i * 7919) % 251) as u8).collect();
+            for kernel_size in [1, 3, 5, 7, 15] {
+                for border in borders {
+                    let blurred = || blur_with_border(&gray, width, height, kernel_size, 0.0, border).unwrap();
+                    let (simd, scalar) = simd_and_scalar(blurred);
+                    assert_eq!(simd, scalar, "{width}x{height} kernel {kernel_size} {border:?}");
+                }
+            }
+        }
+        assert!(simd_enabled());
+    }
+
+    #[test]
+    fn test_scalar_passes_match_simd() {
+        use crate::dilation::dilate;
+        use crate::grayscale::{rgba_to_grayscale, GrayscaleWeights};
+        use crate::non_maximum_suppression::non_maximum_suppression;
+
+        let bytes = |len: usize, seed: usize| -> Vec<u8> { (0..len).map(|i| (i * 7919 + seed) as u8).collect() };
+        for (width, height) in [(37, 23), (16, 16), (5, 3), (1, 9)] {
+            let size = width * height;
+            let (gray, rgba) = (bytes(size, 3), bytes(4 * size, 0));
+
+            let edges: Vec<u8> = gray.iter().map(|&v| if v > 230 { 255 } else { 0 }).collect();
+            for kernel_size in [3, 5, 9] {
+                let (simd, scalar) = simd_and_scalar(|| dilate(&edges, width, height, kernel_size).unwrap());
+                assert_eq!(simd, scalar, "dilate {width}x{height} kernel {kernel_size}");
+            }
+
+            let dx: Vec<i16> = gray.iter().map(|&v| v as i16 * 3 - 380).collect();
+            let dy: Vec<i16> = rgba.iter().step_by(4).map(|&v| 400 - v as i16 * 2).collect();
+            for l2 in [false, true] {
+                let nms = || non_maximum_suppression(&dx, &dy, width, height, l2).unwrap();
+                let (simd, scalar) = simd_and_scalar(nms);
+                assert_eq!(simd, scalar, "non_maximum_suppression {width}x{height} l2 {l2}");
+            }
+
+            for weights in [GrayscaleWeights::Bt601, GrayscaleWeights::Bt709] {
+                let (simd, scalar) = simd_and_scalar(|| rgba_to_grayscale(&rgba, width, height, weights).unwrap());
+                assert_eq!(simd, scalar, "rgba_to_grayscale {width}x{height} {weights:?}");
+            }
+
+            #[cfg(feature = "formats")]
+            {
+                use crate::pixel_format::{convert_to_grayscale, convert_to_rgba, pixel_format_frame_len, PixelFormat};
+                for format in [
+                    PixelFormat::Rgba8,
+                    PixelFormat::Bgra8,
+                    PixelFormat::Rgb8,
+                    PixelFormat::Gray8,
+                    PixelFormat::Nv12,
+                    PixelFormat::I420,
+                    PixelFormat::Rgb565,
+                ] {
+                    let src = bytes(pixel_format_frame_len(format, width, height).unwrap(), 5);
+                    let (simd, scalar) = simd_and_scalar(|| convert_to_rgba(&src, width, height, format).unwrap());
+                    assert_eq!(simd, scalar, "convert_to_rgba {width}x{height} {format:?}");
+                    let weights = GrayscaleWeights::Bt709;
+                    let to_gray = || convert_to_grayscale(&src, width, height, format, weights).unwrap();
+                    let (simd, scalar) = simd_and_scalar(to_gray);
+                    assert_eq!(simd, scalar, "convert_to_grayscale {width}x{height} {format:?}");
+                }
+            }
+
+            // Float rounding may move a channel by one step, or across the hue wrap
+            #[cfg(feature = "colorspace")]
+            {
+                use crate::colorspace::{color_space_to_rgba, rgba_to_color_space, ColorSpace};
+                let close = |a: &[u8], b: &[u8]| {
+                    a.iter().zip(b).all(|(&a, &b)| matches!(a.wrapping_sub(b), 0 | 1 | 255))
+                };
+                for space in [ColorSpace::Hsv, ColorSpace::Lab, ColorSpace::YCbCr] {
+                    let (simd, scalar) = simd_and_scalar(|| rgba_to_color_space(&rgba, width, height, space).unwrap());
+                    assert!(close(&simd, &scalar), "rgba_to_color_space {width}x{height} {space:?}");
+                    let (simd, scalar) = simd_and_scalar(|| color_space_to_rgba(&rgba, width, height, space).unwrap());
+                    assert!(close(&simd, &scalar), "color_space_to_rgba {width}x{height} {space:?}");
+                }
+            }
+
+            #[cfg(feature = "resize")]
+            {
+                use crate::resize::{resize, Interpolation};
+                for (channels, src) in [(1, &gray), (4, &rgba)] {
+                    for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Area] {
+                        for (dst_width, dst_height) in [(2 * width + 1, height.div_ceil(2)), (3, 7)] {
+                            let (simd, scalar) = simd_and_scalar(|| {
+                                resize(src, width, height, channels, dst_width, dst_height, interpolation).unwrap()
+                            });
+                            assert_eq!(simd, scalar, "resize {width}x{height}x{channels} {interpolation:?}");
+                        }
+                    }
+                }
+            }
+
+            #[cfg(feature = "tone")]
+            for image in [&gray, &rgba] {
+                let (simd, scalar) = simd_and_scalar(|| crate::tone::adjust_gamma(image, width, height, 0.6).unwrap());
+                assert_eq!(simd, scalar, "adjust_gamma {width}x{height}");
+                let adjust = || crate::tone::adjust_brightness_contrast(image, width, height, 0.2, 1.4).unwrap();
+                let (simd, scalar) = simd_and_scalar(adjust);
+                assert_eq!(simd, scalar, "adjust_brightness_contrast {width}x{height}");
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::colorspace::{linear_to_lab, rgb_to_hsv, srgb_to_linear, ColorSpace};
+use crate::error::{check_rgba, ScanError};
+
+// Pixels up to this share of the tolerance beyond it are blended towards the
+// paper instead of dropped, so anti-aliased print edges don't leave halos.
+const FEATHER: f32 = 0.5;
+
+// Colour to drop, in the space its tolerance is given in.
+enum Matcher {
+    // Target hue in degrees and the least saturation (0..1)
+    Hsv { hue: f32, min_chroma: f32 },
+    // Target a*, b*, the least C* and the sRGB to linear table
+    Lab { a: f32, b: f32, min_chroma: f32, linear: Vec<f32> },
+}
+
+impl Matcher {
+    // Distance of a pixel from the colour in the units of the tolerance, or
+    // None if it is too grey to belong to any colour family.
+    fn distance(&self, px: &[u8]) -> Option<f32> {
+        let rgb = [px[0] as f32, px[1] as f32, px[2] as f32];
+        match self {
+            Matcher::Hsv { hue, min_chroma } => {
+                let [h, s, _] = rgb_to_hsv(rgb);
+                let d = (h - hue).abs();
+                (s >= *min_chroma).then(|| d.min(360.0 - d))
+            }
+            Matcher::Lab { a, b, min_chroma, linear } => {
+                let [_, pa, pb] = linear_to_lab([0, 1, 2].map(|c| linear[px[c] as usize]));
+                (pa.hypot(pb) >= *min_chroma).then(|| (pa - a).hypot(pb - b))
+            }
+        }
+    }
+}
+
+// Per-channel median of the pixels not dropped, the paper colour dropped
+// pixels are filled with; white if everything is dropped.
+fn paper_color(rgba: &[u8], weights: &[f32]) -> [u8; 3] {
+    let mut histograms = [[0usize; 256]; 3];
+    for (px, _) in rgba.chunks_exact(4).zip(weights).filter(|(_, &w)| w == 0.0) {
+        for (histogram, &v) in histograms.iter_mut().zip(px) {
+            histogram[v as usize] += 1;
+        }
+    }
+    histograms.map(|histogram| {
+        let total: usize = histogram.iter().sum();
+        let mut seen = 0;
+        histogram
+            .iter()
+            .position(|&n| {
+                seen += n;
+                total > 0 && 2 * seen > total
+            })
+            .map_or(255, |level| level as u8)
+    })
+}
+
+/// Removes one ink colour from a scan, like the colour drop-out of document
+/// scanners: red or green pre-print of a form disappears into the paper while
+/// black or blue handwriting stays. Pixels matching the colour are replaced by
+/// the paper colour, with a soft transition at the tolerance.
+///
+/// In `Hsv` the whole colour family is matched by hue, from pale to dark; in
+/// `Lab` only colours close to the given one on the a*/b* plane, e.g. to drop
+/// a red print but keep a red pen of a different shade. Lightness is ignored in
+/// both, so shading across the page doesn't matter.
+///
+/// # Arguments
+/// * `rgba` - RGBA scan
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `red`, `green`, `blue` - Colour to drop, must not be grey
+/// * `space` - `Hsv` or `Lab`
+/// * `tolerance` - Largest hue difference in degrees (`Hsv`) or a*/b* distance
+///   (`Lab`) still dropped, e.g. 20
+/// * `min_chroma` - Least colourfulness of a dropped pixel, HSV saturation
+///   0..1 (`Hsv`) or C* (`Lab`); greyer pixels such as black ink are kept
+///
+/// # Returns
+/// RGBA image as Vec<u8> with the colour removed
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn drop_out_color(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    red: u8,
+    green: u8,
+    blue: u8,
+    space: ColorSpace,
+    tolerance: f32,
+    min_chroma: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    if !(tolerance > 0.0 && tolerance.is_finite() && min_chroma >= 0.0) {
+        return Err(ScanError::InvalidParameter("Tolerance must be positive and minimum chroma non-negative"));
+    }
+    let target = [red, green, blue];
+    let [hue, saturation, _] = rgb_to_hsv(target.map(|c| c as f32));
+    if saturation == 0.0 {
+        return Err(ScanError::InvalidParameter("Drop-out colour must not be grey"));
+    }
+    let matcher = match space {
+        ColorSpace::Hsv => Matcher::Hsv { hue, min_chroma },
+        ColorSpace::Lab => {
+            let linear: Vec<f32> = (0..256).map(|i| srgb_to_linear(i as f32)).collect();
+            let [_, a, b] = linear_to_lab(target.map(|c| linear[c as usize]));
+            Matcher::Lab { a, b, min_chroma, linear }
+        }
+        ColorSpace::YCbCr => return Err(ScanError::InvalidParameter("Drop-out colour is matched in HSV or Lab")),
+    };
+
+    // How much of each pixel is replaced: 1 within the tolerance, fading to 0
+    // over the feather
+    let weights: Vec<f32> = rgba
+        .chunks_exact(4)
+        .map(|px| matcher.distance(px).map_or(0.0, |d| (1.0 - (d - tolerance) / (FEATHER * tolerance)).clamp(0.0, 1.0)))
+        .collect();
+    let paper = paper_color(rgba, &weights);
+    let mut out = rgba.to_vec();
+    for (px, &w) in out.chunks_exact_mut(4).zip(&weights).filter(|(_, &w)| w > 0.0) {
+        for (v, &p) in px.iter_mut().zip(&paper) {
+            *v = (*v as f32 + (p as f32 - *v as f32) * w + 0.5) as u8;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_out_red() {
+        // A form on cream paper: red field boxes in a bright and a dark shade,
+        // a black handwritten stroke across a box, and a blue pen mark
+        let paper = [240, 235, 220, 255];
+        let (width, height) = (40, 10);
+        let mut rgba: Vec<u8> = (0..width * height).flat_map(|_| paper).collect();
+        let mut paint = |x: usize, y: usize, color: [u8; 4]| rgba[(y * width + x) * 4..][..4].copy_from_slice(&color);
+        for x in 0..30 {
+            paint(x, 2, [220, 40, 30, 255]);
+            paint(x, 7, [150, 20, 40, 255]);
+        }
+        for y in 0..10 {
+            paint(10, y, [30, 25, 25, 255]);
+            paint(35, y, [30, 60, 200, 255]);
+        }
+
+        let out = drop_out_color(&rgba, width, height, 255, 0, 0, ColorSpace::Hsv, 20.0, 0.3).unwrap();
+        let at = |x: usize, y: usize| &out[(y * width + x) * 4..][..4];
+        assert_eq!((at(5, 2), at(20, 7)), (&paper[..], &paper[..]));
+        assert_eq!((at(10, 2), at(35, 7)), (&[30, 25, 25, 255][..], &[30, 60, 200, 255][..]));
+
+        // In Lab the darker red is far enough from the target to stay
+        let out = drop_out_color(&rgba, width, height, 220, 40, 30, ColorSpace::Lab, 10.0, 20.0).unwrap();
+        assert_eq!(&out[(2 * width + 5) * 4..][..4], &paper);
+        assert_eq!(&out[(7 * width + 5) * 4..][..4], &[150, 20, 40, 255]);
+
+        assert!(drop_out_color(&rgba, width, height, 90, 90, 90, ColorSpace::Hsv, 20.0, 0.3).is_err());
+        assert!(drop_out_color(&rgba, width, height, 255, 0, 0, ColorSpace::YCbCr, 20.0, 0.3).is_err());
+    }
+}
@@ -0,0 +1,718 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::border::BorderMode;
+use crate::error::{check_len, check_rgba, image_len, ScanError};
+use crate::grayscale::{fixed_weights, luma_scalar, GrayscaleWeights};
+use crate::histogram::{luma_histogram, percentile_levels, stretch_lut, BINS};
+use crate::tone::apply_luts;
+
+// Scales HSV saturation of one pixel by `saturation`, with `vibrance` adding a
+// boost that fades out as the pixel's own saturation approaches 1. Every channel
+// keeps its distance to the maximum channel in proportion, so hue and value (the
+// maximum channel) are unchanged.
+#[inline]
+fn saturate_pixel(px: &mut [u8], saturation: f32, vibrance: f32) {
+    let max = px[0].max(px[1]).max(px[2]) as f32;
+    let min = px[0].min(px[1]).min(px[2]) as f32;
+    if max == min {
+        return;
+    }
+    let current = (max - min) / max;
+    // Past max / (max - min) the smallest channel would go below zero
+    let factor = (saturation * (1.0 + vibrance * (1.0 - current))).clamp(0.0, max / (max - min));
+    for c in &mut px[..3] {
+        *c = (max - (max - *c as f32) * factor).round() as u8;
+    }
+}
+
+/// Adjusts saturation and vibrance of an RGBA image in HSV space, keeping hue
+/// and brightness. Vibrance only lifts muted colours and leaves already vivid
+/// ones (stamps, highlighter) mostly alone, which is gentler on documents than a
+/// plain saturation boost.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `saturation` - Saturation multiplier; 1 keeps it, 0 removes all colour
+/// * `vibrance` - Extra boost for muted colours, -1..1; 0 disables it
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn adjust_saturation(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    saturation: f32,
+    vibrance: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let saturation = saturation.max(0.0);
+    let vibrance = vibrance.clamp(-1.0, 1.0);
+    let mut out = rgba.to_vec();
+    for px in out.chunks_exact_mut(4) {
+        saturate_pixel(px, saturation, vibrance);
+    }
+    Ok(out)
+}
+
+// Tone curve lifting shadows and compressing highlights, sampled into a LUT.
+// The lift is `shadows * x * (1 - x)^2` and the compression
+// `highlights * x^2 * (1 - x)`, both scaled to move tones by at most a quarter of
+// the range. Black and white stay fixed and the curve stays increasing for
+// amounts in 0..1, so tones never swap order and nothing is clipped.
+fn shadow_highlight_lut(shadows: f32, highlights: f32) -> [u8; 256] {
+    const SCALE: f32 = 27.0 / 16.0;
+    let mut lut = [0u8; 256];
+    for (i, v) in lut.iter_mut().enumerate() {
+        let x = i as f32 / 255.0;
+        let lift = shadows * SCALE * x * (1.0 - x) * (1.0 - x);
+        let compress = highlights * SCALE * x * x * (1.0 - x);
+        *v = ((x + lift - compress) * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    lut
+}
+
+fn check_curve(curve: &[u8]) -> Result<(), ScanError> {
+    if curve.len() != 256 {
+        return Err(ScanError::InvalidParameter("Tone curve must have 256 entries"));
+    }
+    Ok(())
+}
+
+/// Applies a tone curve to the red, green and blue channels of an RGBA image,
+/// so integrators can ship their own looks without a new crate release.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `curve` - 256-entry lookup table, output level for every input level
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn apply_tone_curve(rgba: &[u8], width: usize, height: usize, curve: &[u8]) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    check_curve(curve)?;
+    let mut out = rgba.to_vec();
+    apply_luts(&mut out, [curve, curve, curve]);
+    Ok(out)
+}
+
+/// Applies a separate tone curve to each of the red, green and blue channels of
+/// an RGBA image, e.g. for colour grading or white balance looks.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `red` - 256-entry lookup table for the red channel
+/// * `green` - 256-entry lookup table for the green channel
+/// * `blue` - 256-entry lookup table for the blue channel
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn apply_tone_curves(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    red: &[u8],
+    green: &[u8],
+    blue: &[u8],
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    for curve in [red, green, blue] {
+        check_curve(curve)?;
+    }
+    let mut out = rgba.to_vec();
+    apply_luts(&mut out, [red, green, blue]);
+    Ok(out)
+}
+
+/// Lifts shadows and compresses highlights of an RGBA image independently, e.g.
+/// to recover faint pencil writing while keeping the paper from washing out to
+/// pure white.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `shadows` - Shadow lift, 0..1; 0 leaves dark tones unchanged
+/// * `highlights` - Highlight compression, 0..1; 0 leaves bright tones unchanged
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn adjust_shadows_highlights(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    shadows: f32,
+    highlights: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let lut = shadow_highlight_lut(shadows.clamp(0.0, 1.0), highlights.clamp(0.0, 1.0));
+    let mut out = rgba.to_vec();
+    apply_luts(&mut out, [&lut, &lut, &lut]);
+    Ok(out)
+}
+
+/// Stretches the brightness range of an RGBA image so the darkest and brightest
+/// pixels, minus the clipped percentiles, span the full range. All channels get
+/// the same curve, so colours keep their balance; a gentler default than full
+/// histogram equalization for photos embedded in documents.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `clip_low_pct` - Percentage of the darkest pixels allowed to clip to black
+/// * `clip_high_pct` - Percentage of the brightest pixels allowed to clip to white
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn auto_contrast(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    clip_low_pct: f32,
+    clip_high_pct: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let (low, high) = percentile_levels(&luma_histogram(rgba), clip_low_pct, clip_high_pct);
+    let lut = stretch_lut(low, high);
+    let mut out = rgba.to_vec();
+    apply_luts(&mut out, [&lut, &lut, &lut]);
+    Ok(out)
+}
+
+// Smallest black-to-white distance `paper_levels` leaves, so near-blank pages
+// don't get their paper texture stretched into noise.
+const MIN_LEVEL_RANGE: u8 = 64;
+// Percentage of the darkest pixels taken as ink when estimating the black point.
+const INK_PERCENTILE: f32 = 1.0;
+
+// Black and white points of a page from its luma histogram. The white point is
+// the paper itself: the most common level (smoothed over 5 bins) in the brighter
+// half, so specular highlights and bright margins above it don't count. The
+// black point is a low percentile, i.e. the ink.
+pub(crate) fn paper_levels(histogram: &[u32; BINS]) -> (u8, u8) {
+    if histogram.iter().all(|&c| c == 0) {
+        return (0, 255);
+    }
+    let (median, _) = percentile_levels(histogram, 50.0, 0.0);
+    let smoothed = |level: usize| histogram[level.saturating_sub(2)..(level + 3).min(BINS)].iter().sum::<u32>();
+    // Ties go to the more common level, then to the brighter one
+    let white = (median as usize..BINS).max_by_key(|&l| (smoothed(l), histogram[l], l)).unwrap() as u8;
+    let (ink, _) = percentile_levels(histogram, INK_PERCENTILE, 0.0);
+    (ink.min(white.saturating_sub(MIN_LEVEL_RANGE)), white)
+}
+
+// Maps the page's estimated black and white points to 0 and 255 in place.
+// Only white paper is whitened: coloured paper keeps its colour and just gets
+// the ink deepened, dark paper is left alone.
+#[cfg_attr(not(feature = "session"), allow(dead_code))]
+pub(crate) fn normalize_paper_into(rgba: &mut [u8]) {
+    let (black, white) = paper_levels(&luma_histogram(rgba));
+    let white = match classify_paper_color(paper_color_at(rgba, white)) {
+        PaperClass::White => white,
+        PaperClass::Colored => 255,
+        PaperClass::Dark => return,
+    };
+    let lut = stretch_lut(black, white);
+    apply_luts(rgba, [&lut, &lut, &lut]);
+}
+
+// Levels below the paper white point still counted as paper by `paper_color`.
+const PAPER_TOLERANCE: u8 = 8;
+// Paper darker than this luma is classified as dark.
+const DARK_PAPER_LUMA: f32 = 96.0;
+// Paper with a higher HSV saturation is classified as coloured. Well above the
+// cast of white paper under warm indoor light, below yellow legal pads.
+const COLORED_PAPER_SATURATION: f32 = 0.25;
+
+/// Background of a page, see `classify_paper`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaperClass {
+    /// White or grey paper, safe to whiten
+    White = 0,
+    /// Clearly coloured paper, e.g. yellow legal pads
+    Colored = 1,
+    /// Dark background, e.g. posters or slides
+    Dark = 2,
+}
+
+pub(crate) fn classify_paper_color([r, g, b]: [f32; 3]) -> PaperClass {
+    let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if luma < DARK_PAPER_LUMA {
+        PaperClass::Dark
+    } else if (max - min) / max > COLORED_PAPER_SATURATION {
+        PaperClass::Colored
+    } else {
+        PaperClass::White
+    }
+}
+
+/// Classifies the paper of a page as white, coloured or dark from its estimated
+/// paper colour, so enhancement can skip whitening where it would damage the
+/// original. The session's level normalization and page harmonization already
+/// do this.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels of the page
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// The paper class
+#[wasm_bindgen]
+pub fn classify_paper(rgba: &[u8], width: usize, height: usize) -> Result<PaperClass, ScanError> {
+    check_rgba(rgba, width, height)?;
+    Ok(classify_paper_color(paper_color(rgba)))
+}
+
+// Average colour of the paper: the pixels whose luma lies within
+// `PAPER_TOLERANCE` of the paper white point or above it. White for an empty
+// image.
+pub(crate) fn paper_color(rgba: &[u8]) -> [f32; 3] {
+    let (_, white) = paper_levels(&luma_histogram(rgba));
+    paper_color_at(rgba, white)
+}
+
+fn paper_color_at(rgba: &[u8], white: u8) -> [f32; 3] {
+    let floor = white.saturating_sub(PAPER_TOLERANCE);
+    let weights = fixed_weights(GrayscaleWeights::Bt601);
+    let mut sums = [0u64; 3];
+    let mut count = 0u64;
+    for px in rgba.chunks_exact(4) {
+        if luma_scalar(px[0], px[1], px[2], weights) >= floor {
+            for (sum, &c) in sums.iter_mut().zip(&px[..3]) {
+                *sum += c as u64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return [255.0; 3];
+    }
+    sums.map(|sum| sum as f32 / count as f32)
+}
+
+// Multiplies each colour channel by its gain, clamping at 255.
+pub(crate) fn scale_channels_into(rgba: &mut [u8], gains: [f32; 3]) {
+    let luts = gains.map(|gain| {
+        let mut lut = [0u8; BINS];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = (i as f32 * gain).round().clamp(0.0, 255.0) as u8;
+        }
+        lut
+    });
+    apply_luts(rgba, [&luts[0], &luts[1], &luts[2]]);
+}
+
+/// Colour cast estimate used by `white_balance`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WhiteBalanceMethod {
+    /// Assumes the image averages to grey; robust on pages that are mostly
+    /// paper.
+    GrayWorld = 0,
+    /// Assumes the brightest pixels are white; better when coloured content
+    /// covers much of the page.
+    WhitePatch = 1,
+}
+
+// Percentage of the brightest pixels per channel ignored by white patch, so
+// specular highlights don't set the white point.
+const WHITE_PATCH_PERCENTILE: f32 = 1.0;
+// Largest gain either method applies, so a nearly empty channel (a page lit in
+// pure red) isn't blown up into noise.
+const MAX_WB_GAIN: f32 = 4.0;
+
+// Per-channel gains that remove the colour cast estimated by `method`.
+fn white_balance_gains(rgba: &[u8], method: WhiteBalanceMethod) -> [f32; 3] {
+    let gain = |target: f32, reference: f32| if reference > 0.0 { (target / reference).min(MAX_WB_GAIN) } else { 1.0 };
+    match method {
+        WhiteBalanceMethod::GrayWorld => {
+            let mut sums = [0u64; 3];
+            for px in rgba.chunks_exact(4) {
+                for (sum, &c) in sums.iter_mut().zip(&px[..3]) {
+                    *sum += c as u64;
+                }
+            }
+            let count = (rgba.len() / 4).max(1) as f32;
+            let means = sums.map(|sum| sum as f32 / count);
+            // The grey target keeps the overall brightness
+            let grey = (means[0] + means[1] + means[2]) / 3.0;
+            means.map(|m| gain(grey, m))
+        }
+        WhiteBalanceMethod::WhitePatch => {
+            let mut histograms = [[0u32; BINS]; 3];
+            for px in rgba.chunks_exact(4) {
+                for (histogram, &c) in histograms.iter_mut().zip(&px[..3]) {
+                    histogram[c as usize] += 1;
+                }
+            }
+            histograms.map(|histogram| gain(255.0, percentile_levels(&histogram, 0.0, WHITE_PATCH_PERCENTILE).1 as f32))
+        }
+    }
+}
+
+// Removes the colour cast estimated by `method` in place.
+pub(crate) fn white_balance_into(rgba: &mut [u8], method: WhiteBalanceMethod) {
+    let gains = white_balance_gains(rgba, method);
+    scale_channels_into(rgba, gains);
+}
+
+/// Removes a colour cast, e.g. the yellow of warm indoor lighting, from an RGBA
+/// image by scaling each colour channel. Apply it to the rectified page before
+/// other enhancements.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `method` - Gray world keeps the brightness and neutralizes the average
+///   colour; white patch maps the brightest pixels (minus the top 1%) to white
+///
+/// # Returns
+/// Balanced RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn white_balance(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    method: WhiteBalanceMethod,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut out = rgba.to_vec();
+    white_balance_into(&mut out, method);
+    Ok(out)
+}
+
+/// Estimates the ink black point and paper white point of a (flattened) page
+/// from its brightness histogram. Normalizing every page of a document to its
+/// own levels gives all pages the same background brightness.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels of the page
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// `[black, white]` levels
+#[wasm_bindgen]
+pub fn estimate_paper_levels(rgba: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let (black, white) = paper_levels(&luma_histogram(rgba));
+    Ok(vec![black, white])
+}
+
+/// Maps `black` to 0 and `white` to 255 on all colour channels of an RGBA image,
+/// e.g. with the levels from `estimate_paper_levels`.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `black` - Level mapped to black
+/// * `white` - Level mapped to white
+///
+/// # Returns
+/// Adjusted RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn normalize_levels(rgba: &[u8], width: usize, height: usize, black: u8, white: u8) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let lut = stretch_lut(black, white);
+    let mut out = rgba.to_vec();
+    apply_luts(&mut out, [&lut, &lut, &lut]);
+    Ok(out)
+}
+
+// HSV saturation range over which `whiten_preserving_ink` blends from the
+// whitened grey path to the colour-preserving ink path.
+const INK_SATURATION_LOW: f32 = 0.15;
+const INK_SATURATION_HIGH: f32 = 0.35;
+
+/// Pushes the paper to white and dark ink to black while keeping chromatic ink
+/// (stamps, blue signatures) in colour. Plain level normalization flattens such
+/// ink towards grey or washes it out; here saturated pixels are only white
+/// balanced and brightened along with the paper, so they keep their hue and
+/// saturation.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels of the page
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Enhanced RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn whiten_preserving_ink(rgba: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let (black, white) = paper_levels(&luma_histogram(rgba));
+    let lut = stretch_lut(black, white);
+    // White balance that makes the paper neutral at its own brightness
+    let gains = paper_color_at(rgba, white).map(|c| if c > 0.0 { white as f32 / c } else { 1.0 });
+    let lift = 255.0 / white.max(1) as f32;
+
+    let mut out = rgba.to_vec();
+    for px in out.chunks_exact_mut(4) {
+        let max = px[0].max(px[1]).max(px[2]) as f32;
+        let min = px[0].min(px[1]).min(px[2]) as f32;
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        let ink = ((saturation - INK_SATURATION_LOW) / (INK_SATURATION_HIGH - INK_SATURATION_LOW)).clamp(0.0, 1.0);
+        for (c, gain) in px[..3].iter_mut().zip(gains) {
+            let balanced = (*c as f32 * gain).min(255.0);
+            // Grey pixels get the levels stretch, ink only the lift to white paper
+            let gray = lut[balanced.round() as usize] as f32;
+            let colored = (balanced * lift).min(255.0);
+            *c = (gray + (colored - gray) * ink).round() as u8;
+        }
+    }
+    Ok(out)
+}
+
+// Gaussian kernel size covering +-3 sigma of a blur with the given radius.
+fn kernel_for_radius(radius: f32) -> usize {
+    2 * (3.0 * radius).ceil().max(1.0) as usize + 1
+}
+
+/// Sharpens a grayscale or RGBA image with an unsharp mask: the difference to a
+/// Gaussian-blurred copy is added back, scaled by `amount`. Restores crispness
+/// to rectified scans from soft phone cameras. The blur is the fixed-point one
+/// `blur` uses; RGBA images are sharpened per colour channel, alpha is kept.
+///
+/// # Arguments
+/// * `image` - Grayscale or RGBA pixels; the layout is taken from the length
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `amount` - Strength; 1 adds the full difference, typically 0.5-1.5
+/// * `radius` - Gaussian sigma of the mask in pixels, typically 1-2
+/// * `threshold` - Minimum difference to the blurred value before a pixel is
+///   sharpened, so flat paper and noise stay smooth; 0 sharpens everything
+///
+/// # Returns
+/// Sharpened image as Vec<u8>, in the input layout
+#[wasm_bindgen]
+pub fn unsharp_mask(
+    image: &[u8],
+    width: usize,
+    height: usize,
+    amount: f32,
+    radius: f32,
+    threshold: u8,
+) -> Result<Vec<u8>, ScanError> {
+    let pixels = image_len(width, height, 1)?;
+    let channels = if image.len() == pixels { 1 } else { 4 };
+    check_len(image.len(), image_len(width, height, channels)?, "width * height or width * height * 4")?;
+    let mut out = image.to_vec();
+    if pixels == 0 || radius <= 0.0 || amount == 0.0 {
+        return Ok(out);
+    }
+
+    let kernel_size = kernel_for_radius(radius);
+    let mut plane = vec![0u8; pixels];
+    let mut temp = vec![0u32; pixels];
+    let mut blurred = vec![0u8; pixels];
+    for c in 0..channels.min(3) {
+        for (p, px) in plane.iter_mut().zip(image.chunks_exact(channels)) {
+            *p = px[c];
+        }
+        crate::gaussian_blur::blur_into(
+            &plane,
+            width,
+            height,
+            kernel_size,
+            radius,
+            BorderMode::Replicate,
+            &mut temp,
+            &mut blurred,
+        );
+        for ((px, &v), &b) in out.chunks_exact_mut(channels).zip(&plane).zip(&blurred) {
+            let diff = v as f32 - b as f32;
+            if v.abs_diff(b) >= threshold {
+                px[c] = (v as f32 + amount * diff).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saturation_and_vibrance() {
+        // Gray, muted blue, vivid red
+        let rgba = [128, 128, 128, 255, 100, 110, 140, 255, 220, 40, 20, 255];
+
+        assert_eq!(adjust_saturation(&rgba, 3, 1, 1.0, 0.0).unwrap(), rgba.to_vec());
+        let gray = adjust_saturation(&rgba, 3, 1, 0.0, 0.0).unwrap();
+        assert!(gray.chunks_exact(4).all(|p| p[0] == p[1] && p[1] == p[2]));
+        // Doubling saturation keeps value and hue; the vivid red saturates fully
+        let boosted = adjust_saturation(&rgba, 3, 1, 2.0, 0.0).unwrap();
+        assert_eq!(&boosted[4..], &[60, 80, 140, 255, 220, 22, 0, 255]);
+
+        // Vibrance lifts the muted colour (x1.71) much more than the vivid one (x1.09)
+        let vibrant = adjust_saturation(&rgba, 3, 1, 1.0, 1.0).unwrap();
+        assert_eq!(vibrant, vec![128, 128, 128, 255, 71, 89, 140, 255, 220, 24, 2, 255]);
+    }
+
+    #[test]
+    fn test_shadows_highlights() {
+        let lut = shadow_highlight_lut(1.0, 1.0);
+        assert_eq!((lut[0], lut[255]), (0, 255));
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+
+        // Dark tones lift, bright tones drop, alpha is untouched
+        let rgba = [40, 40, 40, 200, 220, 220, 220, 255];
+        let out = adjust_shadows_highlights(&rgba, 2, 1, 0.5, 0.5).unwrap();
+        assert!(out[0] > 40 && out[4] < 220);
+        assert_eq!((out[3], out[7]), (200, 255));
+        assert_eq!(adjust_shadows_highlights(&rgba, 2, 1, 0.0, 0.0).unwrap(), rgba.to_vec());
+    }
+
+    #[test]
+    fn test_tone_curves() {
+        let rgba: Vec<u8> = (0..=255).flat_map(|v: u8| [v, v / 2, 255 - v, v]).collect();
+        let invert: Vec<u8> = (0..=255).map(|v: u8| 255 - v).collect();
+        let identity: Vec<u8> = (0..=255).collect();
+
+        let inverted = apply_tone_curve(&rgba, 16, 16, &invert).unwrap();
+        for (out, px) in inverted.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+            assert_eq!(out, &[255 - px[0], 255 - px[1], 255 - px[2], px[3]]);
+        }
+        // Only the green curve changes anything
+        let green = apply_tone_curves(&rgba, 16, 16, &identity, &invert, &identity).unwrap();
+        for (out, px) in green.chunks_exact(4).zip(rgba.chunks_exact(4)) {
+            assert_eq!(out, &[px[0], 255 - px[1], px[2], px[3]]);
+        }
+    }
+
+    #[test]
+    fn test_auto_contrast() {
+        // Gray levels 60..124 with one dark and one bright outlier
+        let mut rgba: Vec<u8> = (60..124).flat_map(|v| [v, v, v, 255]).collect();
+        rgba[..4].copy_from_slice(&[0, 0, 0, 255]);
+        rgba[252..].copy_from_slice(&[255, 255, 255, 255]);
+
+        // Without clipping the outliers already span the range
+        assert_eq!(auto_contrast(&rgba, 8, 8, 0.0, 0.0).unwrap(), rgba);
+        let out = auto_contrast(&rgba, 8, 8, 2.0, 2.0).unwrap();
+        assert_eq!((out[4], out[248]), (0, 255));
+        assert_eq!(out[3], 255);
+    }
+
+    #[test]
+    fn test_paper_levels() {
+        // Paper around 200 with 10% ink at 40 and a few specular highlights
+        let rgba: Vec<u8> = (0..400)
+            .flat_map(|i| {
+                let v = match i % 40 {
+                    0..=3 => 40,
+                    4 => 255,
+                    k => 198 + (k % 5) as u8,
+                };
+                [v, v, v, 255]
+            })
+            .collect();
+        assert_eq!(estimate_paper_levels(&rgba, 20, 20).unwrap(), vec![40, 200]);
+
+        let mut page = rgba.clone();
+        normalize_paper_into(&mut page);
+        assert_eq!(page, normalize_levels(&rgba, 20, 20, 40, 200).unwrap());
+        assert_eq!((page[0], page[16], page[36]), (0, 255, 255));
+
+        // A blank page keeps a minimum range instead of amplifying its texture
+        let blank: Vec<u8> = (0..64).flat_map(|i| [200 + (i % 3) as u8; 3].into_iter().chain([255])).collect();
+        assert_eq!(estimate_paper_levels(&blank, 8, 8).unwrap(), vec![138, 202]);
+    }
+
+    #[test]
+    fn test_classify_paper() {
+        let page = |paper: [u8; 3]| -> Vec<u8> {
+            let mut rgba: Vec<u8> = (0..64).flat_map(|_| [paper[0], paper[1], paper[2], 255]).collect();
+            rgba[..8].copy_from_slice(&[30, 30, 30, 255, 30, 30, 30, 255]);
+            rgba
+        };
+        assert_eq!(classify_paper(&page([235, 228, 210]), 8, 8).unwrap(), PaperClass::White);
+        assert_eq!(classify_paper(&page([250, 235, 150]), 8, 8).unwrap(), PaperClass::Colored);
+        assert_eq!(classify_paper(&page([40, 45, 60]), 8, 8).unwrap(), PaperClass::Dark);
+
+        // Level normalization whitens white paper only
+        let mut white = page([200, 200, 200]);
+        normalize_paper_into(&mut white);
+        assert_eq!(&white[8..12], &[255, 255, 255, 255]);
+        let mut yellow = page([250, 235, 150]);
+        normalize_paper_into(&mut yellow);
+        assert_eq!(&yellow[..4], &[0, 0, 0, 255]);
+        assert!(yellow[10] < 200);
+        let mut dark = page([40, 45, 60]);
+        normalize_paper_into(&mut dark);
+        assert_eq!(dark, page([40, 45, 60]));
+    }
+
+    #[test]
+    fn test_white_balance() {
+        // Paper under warm light with dark text
+        let rgba: Vec<u8> =
+            (0..100).flat_map(|i| if i % 10 == 0 { [60, 50, 30, 255] } else { [230, 210, 160, 255] }).collect();
+        let patch = white_balance(&rgba, 10, 10, WhiteBalanceMethod::WhitePatch).unwrap();
+        assert_eq!(&patch[4..8], &[255, 255, 255, 255]);
+        assert_eq!(&patch[..4], &[67, 61, 48, 255]);
+
+        // Gray world keeps the brightness and leaves the paper nearly neutral
+        let grey = white_balance(&rgba, 10, 10, WhiteBalanceMethod::GrayWorld).unwrap();
+        let paper = &grey[4..7];
+        assert!(paper.iter().max().unwrap() - paper.iter().min().unwrap() <= 4);
+        assert!(paper.iter().all(|&c| c.abs_diff(200) <= 4));
+        assert_eq!(white_balance(&grey, 10, 10, WhiteBalanceMethod::GrayWorld).unwrap(), grey);
+    }
+
+    #[test]
+    fn test_whiten_preserving_ink() {
+        // Warm paper with grey text and a red stamp
+        let rgba: Vec<u8> = (0..100)
+            .flat_map(|i| match i % 10 {
+                0 => [60, 60, 60, 255],
+                1 => [180, 40, 40, 255],
+                _ => [200, 195, 185, 255],
+            })
+            .collect();
+        let out = whiten_preserving_ink(&rgba, 10, 10).unwrap();
+        assert_eq!(&out[8..12], &[255, 255, 255, 255]);
+        assert!(out[0] < 60 && out[0] == out[1]);
+        // The stamp stays as saturated as before, only white balanced
+        let stamp = &out[4..7];
+        assert_eq!(stamp, &[230, 52, 55]);
+    }
+
+    #[test]
+    fn test_unsharp_mask() {
+        // A soft step from 100 to 160 gets overshoot on both sides
+        let row: Vec<u8> = (0..16).map(|x| [100, 100, 100, 100, 100, 110, 130, 150, 160, 160][x.min(9)]).collect();
+        let gray: Vec<u8> = row.iter().cycle().take(16 * 8).copied().collect();
+        let sharp = unsharp_mask(&gray, 16, 8, 1.0, 1.0, 0).unwrap();
+        assert!(sharp[4] < 100 && sharp[9] > 160);
+        assert_eq!((sharp[0], sharp[15]), (100, 160));
+
+        // A threshold above the edge contrast leaves the image alone
+        assert_eq!(unsharp_mask(&gray, 16, 8, 1.0, 1.0, 40).unwrap(), gray);
+
+        // RGBA channels are sharpened alike; alpha is kept
+        let rgba: Vec<u8> = gray.iter().flat_map(|&v| [v, v, v, 200]).collect();
+        let sharp_rgba = unsharp_mask(&rgba, 16, 8, 1.0, 1.0, 0).unwrap();
+        assert!(sharp_rgba.chunks_exact(4).zip(&sharp).all(|(p, &v)| p == [v, v, v, 200]));
+    }
+}
@@ -0,0 +1,194 @@
+use core::fmt;
+
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use alloc::string::ToString;
+
+/// Invalid input rejected by an export. Exports return it instead of panicking,
+/// which would abort the whole WASM instance: JS gets a thrown `Error` with the
+/// message below and the module stays usable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanError {
+    /// A buffer's length doesn't match the dimensions passed with it. `layout`
+    /// names the expected size, e.g. "width * height * 4".
+    SizeMismatch { layout: &'static str, len: usize },
+    /// Blur kernel size that is 0 or even
+    InvalidKernelSize(usize),
+    /// Malformed point, corner or quad list
+    InvalidPoints(&'static str),
+    /// Parameter outside its valid range
+    InvalidParameter(&'static str),
+    /// Index past the end of a list of `len` items
+    IndexOutOfRange { index: usize, len: usize },
+    /// Malformed or unsupported file contents
+    InvalidData(&'static str),
+    /// A byte source could not deliver `len` bytes at `offset`
+    ReadFailed { offset: u64, len: usize },
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::SizeMismatch { layout, len } => {
+                write!(f, "Input array size doesn't match {layout} (got {len})")
+            }
+            ScanError::InvalidKernelSize(size) => write!(f, "Kernel size must be odd and greater than 0 (got {size})"),
+            ScanError::InvalidPoints(message) | ScanError::InvalidParameter(message) | ScanError::InvalidData(message) => {
+                f.write_str(message)
+            }
+            ScanError::IndexOutOfRange { index, len } => write!(f, "Index {index} out of range for {len} items"),
+            ScanError::ReadFailed { offset, len } => write!(f, "Could not read {len} bytes at offset {offset}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ScanError {}
+
+impl From<ScanError> for JsValue {
+    fn from(error: ScanError) -> JsValue {
+        JsError::new(&error.to_string()).into()
+    }
+}
+
+// Length checks shared by the exports.
+pub(crate) fn check_len(len: usize, expected: usize, layout: &'static str) -> Result<(), ScanError> {
+    if len != expected {
+        return Err(ScanError::SizeMismatch { layout, len });
+    }
+    Ok(())
+}
+
+// Bytes of a `width` x `height` image with `channels` bytes per pixel. Sizes
+// past `usize::MAX` are an error instead of a wrapped product that a short
+// buffer would match.
+pub(crate) fn image_len(width: usize, height: usize, channels: usize) -> Result<usize, ScanError> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or(ScanError::InvalidParameter("Image dimensions are too large"))
+}
+
+// Outputs whose size the caller picks (warps, rectified pages) are capped at
+// 2^28 pixels, 1 GB of RGBA: anything larger is a stray size rather than a
+// page, and fails here instead of aborting on the allocation.
+const MAX_OUTPUT_PIXELS: usize = 1 << 28;
+
+// Bytes of a `width` x `height` output, as `image_len` but within `MAX_OUTPUT_PIXELS`.
+#[cfg_attr(not(feature = "warp"), allow(dead_code))]
+pub(crate) fn output_len(width: usize, height: usize, channels: usize) -> Result<usize, ScanError> {
+    let len = image_len(width, height, channels)?;
+    if width * height > MAX_OUTPUT_PIXELS {
+        return Err(ScanError::InvalidParameter("Output dimensions are too large"));
+    }
+    Ok(len)
+}
+
+pub(crate) fn check_gray(gray: &[u8], width: usize, height: usize) -> Result<(), ScanError> {
+    check_len(gray.len(), image_len(width, height, 1)?, "width * height")
+}
+
+pub(crate) fn check_rgba(rgba: &[u8], width: usize, height: usize) -> Result<(), ScanError> {
+    check_len(rgba.len(), image_len(width, height, 4)?, "width * height * 4")
+}
+
+#[cfg_attr(not(any(feature = "orientation", feature = "resize")), allow(dead_code))]
+pub(crate) fn check_channels(channels: usize) -> Result<(), ScanError> {
+    if channels != 1 && channels != 4 {
+        return Err(ScanError::InvalidParameter("Channels must be 1 (grayscale) or 4 (RGBA)"));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_kernel_size(kernel_size: usize) -> Result<(), ScanError> {
+    if kernel_size.is_multiple_of(2) {
+        return Err(ScanError::InvalidKernelSize(kernel_size));
+    }
+    Ok(())
+}
+
+#[cfg_attr(
+    not(any(
+        feature = "quad_scoring",
+        feature = "session",
+        feature = "history",
+        feature = "cards",
+        feature = "forms",
+        feature = "occlusion",
+        feature = "tables",
+        feature = "photos",
+        feature = "pdf",
+        feature = "tiff"
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn check_index(index: usize, len: usize) -> Result<(), ScanError> {
+    if index >= len {
+        return Err(ScanError::IndexOutOfRange { index, len });
+    }
+    Ok(())
+}
+
+// Validates an 8-value quad (TL, TR, BR, BL) and copies it into an array.
+#[cfg_attr(
+    not(any(feature = "session", feature = "tracker", feature = "history", feature = "occlusion", feature = "paper")),
+    allow(dead_code)
+)]
+pub(crate) fn to_quad(corners: &[f32]) -> Result<[f32; 8], ScanError> {
+    corners.try_into().map_err(|_| ScanError::InvalidPoints("Quad must have 8 coordinates (4 corners)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exports_return_errors() {
+        let gray = [0u8; 16];
+        assert_eq!(crate::blur(&gray, 4, 4, 4, 0.0), Err(ScanError::InvalidKernelSize(4)));
+        assert_eq!(
+            crate::blur(&gray, 5, 4, 3, 0.0),
+            Err(ScanError::SizeMismatch { layout: "width * height", len: 16 })
+        );
+        assert_eq!(crate::blur(&gray, 4, 4, 3, 0.0).map(|b| b.len()), Ok(16));
+
+        let mut context = crate::context::ScanContext::new(4, 4);
+        assert!(matches!(context.process_frame(&gray), Err(ScanError::SizeMismatch { .. })));
+        assert_eq!(context.process_gray_frame(&gray), Ok(()));
+
+        // Dimensions whose product overflows don't wrap round to a short buffer
+        let too_large = Err(ScanError::InvalidParameter("Image dimensions are too large"));
+        assert_eq!(crate::blur(&[], usize::MAX / 2 + 1, 2, 3, 0.0), too_large);
+        let weights = crate::grayscale::GrayscaleWeights::Bt601;
+        assert_eq!(crate::grayscale::rgba_to_grayscale(&[], usize::MAX / 4 + 1, 1, weights), too_large);
+        assert_eq!(image_len(usize::MAX / 4, 1, 4).map(|len| len % 4), Ok(0));
+
+        // Empty frames give empty results, in either dimension
+        for (width, height) in [(0, 0), (0, 3), (3, 0)] {
+            let empty = crate::gradient_calculation::calculate_gradients(&[], width, height);
+            assert_eq!(empty, Ok(Vec::new()));
+            let soft = crate::canny::canny_edge_detector_soft(&[], width, height, 50.0, 150.0, 3, 0.0, false);
+            assert_eq!(soft, Ok(Vec::new()));
+            #[cfg(feature = "pyramid")]
+            {
+                assert_eq!(crate::pyramid::pyr_down(&[], width, height), Ok(Vec::new()));
+                assert_eq!(crate::pyramid::pyr_up(&[], width, height), Ok(Vec::new()));
+            }
+            #[cfg(feature = "pipeline_debug")]
+            {
+                let options = crate::canny::CannyOptions::new();
+                let debug = crate::pipeline_debug::canny_edge_detector_debug(&[], width, height, &options).unwrap();
+                assert!(debug.edges().is_empty());
+            }
+            #[cfg(feature = "sharpness")]
+            {
+                let method = crate::sharpness::SharpnessMethod::Tenengrad;
+                assert_eq!(crate::sharpness::sharpness_score(&[], width, height, method), Ok(0.0));
+            }
+        }
+
+        assert!(matches!(to_quad(&[0.0; 6]), Err(ScanError::InvalidPoints(_))));
+        assert_eq!(ScanError::IndexOutOfRange { index: 3, len: 2 }.to_string(), "Index 3 out of range for 2 items");
+    }
+}
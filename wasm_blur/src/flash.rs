@@ -0,0 +1,159 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::border::BorderMode;
+use crate::error::{check_rgba, ScanError};
+use crate::gaussian_blur::blur_into;
+use crate::grayscale::{fixed_weights, luma_scalar, GrayscaleWeights};
+
+// Blur separating the smooth base (lighting, colour) from the detail layer.
+const KERNEL_SIZE: usize = 15;
+// Flash luma from which a pixel may be glare, and from which it surely is:
+// clipped flash pixels carry no page detail.
+const GLARE_LUMA: f32 = 200.0;
+const SATURATED_LUMA: f32 = 250.0;
+// Flash luma this many times above what the ambient frame predicts is glare;
+// the weight ramps up over GLARE_RAMP above that ratio.
+const GLARE_RATIO: f32 = 1.3;
+const GLARE_RAMP: f32 = 0.4;
+// Bounds of the exposure gain between the two frames.
+const MAX_GAIN: f32 = 8.0;
+
+// Exposure gain from the ambient to the flash frame, measured on pixels the
+// flash didn't clip.
+fn exposure_gain(ambient: &[u8], flash: &[u8]) -> f32 {
+    let (mut ambient_sum, mut flash_sum) = (0u64, 0u64);
+    for (&a, &f) in ambient.iter().zip(flash) {
+        if (f as f32) < SATURATED_LUMA {
+            ambient_sum += a as u64;
+            flash_sum += f as u64;
+        }
+    }
+    if ambient_sum == 0 {
+        return MAX_GAIN;
+    }
+    (flash_sum as f32 / ambient_sum as f32).clamp(1.0 / MAX_GAIN, MAX_GAIN)
+}
+
+// How surely a flash pixel is glare, 0..=1, given the ambient luma scaled by the
+// exposure gain.
+fn glare_weight(flash: f32, expected: f32) -> f32 {
+    let bright = ((flash - GLARE_LUMA) / (SATURATED_LUMA - GLARE_LUMA)).clamp(0.0, 1.0);
+    let excess = ((flash / expected.max(1.0) - GLARE_RATIO) / GLARE_RAMP).clamp(0.0, 1.0);
+    if flash >= SATURATED_LUMA { 1.0 } else { bright * excess }
+}
+
+/// Fuses a no-flash/flash frame pair into one glare-free RGBA page, the way
+/// native scanner apps handle glossy paper. The flash frame is sharp and clean
+/// but has a hotspot and specular glare; the ambient frame has the true
+/// colours, but is dark and noisy. The result takes its smooth base (colour and
+/// lighting) from the ambient frame, scaled to the flash exposure, and its
+/// detail from the flash luma. Where the flash glares, the ambient luma stands
+/// in for it.
+///
+/// The frames must be aligned, e.g. taken back to back with the phone held
+/// still (see `shake_score`).
+///
+/// # Arguments
+/// * `ambient` - RGBA frame taken without flash
+/// * `flash` - RGBA frame taken with flash or torch, same size
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// Fused RGBA frame as Vec<u8>, with the ambient frame's alpha
+#[wasm_bindgen]
+pub fn fuse_flash_pair(ambient: &[u8], flash: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_rgba(ambient, width, height)?;
+    check_rgba(flash, width, height)?;
+    let size = width * height;
+    let weights = fixed_weights(GrayscaleWeights::Bt601);
+    let luma = |rgba: &[u8]| -> Vec<u8> {
+        rgba.chunks_exact(4).map(|p| luma_scalar(p[0], p[1], p[2], weights)).collect()
+    };
+    let ambient_luma = luma(ambient);
+    let mut detail = luma(flash);
+    let gain = exposure_gain(&ambient_luma, &detail);
+
+    // Replace glare with the ambient luma before the blur, so it neither shows
+    // in the detail nor spreads into the base around it
+    for (f, &a) in detail.iter_mut().zip(&ambient_luma) {
+        let expected = a as f32 * gain;
+        let w = glare_weight(*f as f32, expected);
+        if w > 0.0 {
+            *f = (*f as f32 * (1.0 - w) + expected.min(255.0) * w + 0.5) as u8;
+        }
+    }
+
+    let mut temp = vec![0u32; size];
+    let mut detail_base = vec![0u8; size];
+    blur_into(&detail, width, height, KERNEL_SIZE, 0.0, BorderMode::Reflect101, &mut temp, &mut detail_base);
+    let mut plane = vec![0u8; size];
+    let mut bases = [vec![0u8; size], vec![0u8; size], vec![0u8; size]];
+    for (c, base) in bases.iter_mut().enumerate() {
+        for (value, p) in plane.iter_mut().zip(ambient.chunks_exact(4)) {
+            *value = p[c];
+        }
+        blur_into(&plane, width, height, KERNEL_SIZE, 0.0, BorderMode::Reflect101, &mut temp, base);
+    }
+
+    let mut fused = vec![0u8; size * 4];
+    for (i, (out, src)) in fused.chunks_exact_mut(4).zip(ambient.chunks_exact(4)).enumerate() {
+        let scale = gain * (detail[i] as f32 + 0.5) / (detail_base[i] as f32 + 0.5);
+        for (value, base) in out.iter_mut().zip(&bases) {
+            *value = (base[i] as f32 * scale + 0.5).min(255.0) as u8;
+        }
+        out[3] = src[3];
+    }
+    Ok(fused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_flash_pair() {
+        let (width, height) = (96, 64);
+        // Black text lines on white paper. The ambient frame is dim, warm and
+        // noisy; the flash frame is bright and neutral with a glare disc on the
+        // right that hides the text under it.
+        let reflectance = |x: usize, y: usize| if y % 8 < 2 && (8..88).contains(&x) { 0.15 } else { 0.85 };
+        let glare = |x: usize, y: usize| (x as f32 - 70.0).hypot(y as f32 - 32.0) < 9.0;
+        let mut seed = 3u32;
+        let (mut ambient, mut flash) = (Vec::new(), Vec::new());
+        for i in 0..width * height {
+            let (x, y) = (i % width, i / width);
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let noise = ((seed >> 16) % 21) as f32 - 10.0;
+            let r = reflectance(x, y);
+            for tint in [1.0, 0.8, 0.5] {
+                ambient.push((70.0 * tint * r + noise).clamp(0.0, 255.0) as u8);
+            }
+            ambient.push(255);
+            let f = if glare(x, y) { 255 } else { (230.0 * r) as u8 };
+            flash.extend([f, f, f, 255]);
+        }
+
+        let fused = fuse_flash_pair(&ambient, &flash, width, height).unwrap();
+        let at = |x: usize, y: usize| &fused[(y * width + x) * 4..][..4];
+        let mean = |pixels: &mut dyn Iterator<Item = (usize, usize)>, c: usize| {
+            let values: Vec<f32> = pixels.map(|(x, y)| at(x, y)[c] as f32).collect();
+            values.iter().sum::<f32>() / values.len() as f32
+        };
+        // Paper keeps the ambient tint at the flash brightness, without the noise
+        let paper = (20..40).map(|x| (x, 4));
+        let (red, blue) = (mean(&mut paper.clone(), 0), mean(&mut paper.clone(), 2));
+        assert!(red > 150.0 && (blue / red - 0.5).abs() < 0.06, "{red} {blue}");
+        assert!(paper.clone().all(|(x, y)| at(x, y)[0].abs_diff(red as u8) < 8));
+        // Text stays sharp, also under the glare, which is gone
+        let text = mean(&mut (20..40).map(|x| (x, 8)), 0);
+        assert!(text < 0.4 * red, "{text} {red}");
+        let glared_paper = mean(&mut (66..75).map(|x| (x, 36)), 0);
+        let glared_text = mean(&mut (66..75).map(|x| (x, 33)), 0);
+        assert!(glared_paper < 1.2 * red && glared_text < 0.6 * glared_paper, "{glared_paper} {glared_text}");
+        assert!(fuse_flash_pair(&ambient, &flash[4..], width, height).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+use crate::banding::band_phase;
+use crate::error::{check_gray, ScanError};
+
+use core::f32::consts::PI;
+
+// Light flicker of 50 and 60 Hz mains: lamps brighten on both half-waves.
+const FLICKER_HZ: [f32; 2] = [100.0, 120.0];
+// Frame pairs further apart than this (ms) are not compared; the phase drift
+// gets too sensitive to timestamp jitter.
+const MAX_FRAME_GAP_MS: f64 = 200.0;
+// Band frequencies of two frames differing by more than this share belong to
+// different scenes or camera settings.
+const MAX_PERIOD_CHANGE: f32 = 0.05;
+// A pair votes only when the measured drift is at least this much closer (in
+// cycles) to one mains frequency than to the other.
+const MIN_VOTE_MARGIN: f32 = 0.1;
+// Votes needed before the mains frequency is reported, and the share the
+// winner needs.
+const MIN_VOTES: u32 = 3;
+const MIN_VOTE_SHARE: f32 = 0.75;
+// Share of banded frames from which the stream counts as flickering.
+const MIN_BANDED_SHARE: f32 = 0.5;
+
+// Distance of `a` from `b` on a circle of one cycle.
+fn cycle_distance(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(1.0);
+    d.min(1.0 - d)
+}
+
+/// Detects mains flicker from consecutive preview frames. Under lights on
+/// 50 Hz mains (100 Hz flicker) the bands of a rolling-shutter camera move by
+/// a different amount from frame to frame than under 60 Hz (120 Hz flicker);
+/// the measured band movement over the frame time tells the two apart. Apps
+/// can then set the exposure to a multiple of the flicker period, or tell the
+/// user to change the light.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct FlickerDetector {
+    frames: u32,
+    banded: u32,
+    // Votes for 50 and 60 Hz
+    votes: [u32; 2],
+    // Timestamp, band frequency and phase of the previous banded frame
+    previous: Option<(f64, f32, f32)>,
+}
+
+#[wasm_bindgen]
+impl FlickerDetector {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> FlickerDetector {
+        FlickerDetector::default()
+    }
+
+    /// Adds a grayscale preview frame. Pass the capture time of the frame, e.g.
+    /// `mediaTime` from `requestVideoFrameCallback` in milliseconds; render
+    /// timestamps jitter too much to measure the band movement.
+    pub fn push_frame(&mut self, gray: &[u8], width: usize, height: usize, timestamp_ms: f64) -> Result<(), ScanError> {
+        check_gray(gray, width, height)?;
+        self.frames += 1;
+        let Some((frequency, phase)) = band_phase(gray, width, height) else {
+            self.previous = None;
+            return Ok(());
+        };
+        self.banded += 1;
+
+        if let Some((previous_ms, previous_frequency, previous_phase)) = self.previous {
+            let gap_ms = timestamp_ms - previous_ms;
+            let similar = (frequency / previous_frequency - 1.0).abs() <= MAX_PERIOD_CHANGE;
+            if gap_ms > 0.0 && gap_ms <= MAX_FRAME_GAP_MS && similar {
+                // The bands' phase falls by 2π per flicker cycle; the sensor may
+                // read out bottom-up, which flips the direction
+                let drift = (previous_phase - phase) / (2.0 * PI);
+                let distances = FLICKER_HZ.map(|hz| {
+                    let expected = hz * (gap_ms / 1000.0) as f32;
+                    cycle_distance(drift, expected).min(cycle_distance(-drift, expected))
+                });
+                if (distances[0] - distances[1]).abs() >= MIN_VOTE_MARGIN {
+                    self.votes[usize::from(distances[1] < distances[0])] += 1;
+                }
+            }
+        }
+        self.previous = Some((timestamp_ms, frequency, phase));
+        Ok(())
+    }
+
+    /// Whether most frames so far show flicker bands.
+    pub fn flicker(&self) -> bool {
+        self.frames > 0 && self.banded as f32 >= MIN_BANDED_SHARE * self.frames as f32
+    }
+
+    /// Share of the frames so far with flicker bands.
+    pub fn banded_fraction(&self) -> f32 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.banded as f32 / self.frames as f32
+        }
+    }
+
+    /// Mains frequency of the flickering light, 50 or 60; 0 while the frames
+    /// don't tell yet or there is no flicker.
+    pub fn mains_frequency(&self) -> u32 {
+        let total = self.votes[0] + self.votes[1];
+        if !self.flicker() || total < MIN_VOTES {
+            return 0;
+        }
+        match self.votes {
+            [fifty, _] if fifty as f32 >= MIN_VOTE_SHARE * total as f32 => 50,
+            [_, sixty] if sixty as f32 >= MIN_VOTE_SHARE * total as f32 => 60,
+            _ => 0,
+        }
+    }
+
+    /// Flicker period in ms, 10 at 50 Hz and 8.33 at 60 Hz. Exposures of a
+    /// whole number of periods average the flicker out, so no bands show. 0
+    /// while the mains frequency is unknown.
+    pub fn flicker_period_ms(&self) -> f32 {
+        match self.mains_frequency() {
+            50 => 1000.0 / FLICKER_HZ[0],
+            60 => 1000.0 / FLICKER_HZ[1],
+            _ => 0.0,
+        }
+    }
+
+    /// Forgets all frames, e.g. after the light or camera settings changed.
+    pub fn reset(&mut self) {
+        *self = FlickerDetector::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flicker_mains_frequency() {
+        // A rolling-shutter camera reading one row every 1/6000 s, so 100 Hz
+        // flicker gives bands every 60 rows and 120 Hz every 50
+        let (width, height) = (64, 240);
+        let frame = |flicker_hz: f32, time_ms: f64| -> Vec<u8> {
+            (0..width * height)
+                .map(|i| {
+                    let t = time_ms as f32 / 1000.0 + (i / width) as f32 / 6000.0;
+                    (180.0 * (1.0 + 0.1 * (2.0 * PI * flicker_hz * t).cos())) as u8
+                })
+                .collect()
+        };
+        // 50 Hz mains at 30 fps, 60 Hz mains at 25 fps, and steady light
+        for (flicker_hz, frame_ms, mains) in [(100.0, 1000.0 / 30.0, 50), (120.0, 40.0, 60), (0.0, 40.0, 0)] {
+            let mut detector = FlickerDetector::new();
+            for n in 0..6 {
+                let time_ms = 1234.5 + n as f64 * frame_ms;
+                detector.push_frame(&frame(flicker_hz, time_ms), width, height, time_ms).unwrap();
+            }
+            assert_eq!(detector.mains_frequency(), mains, "{flicker_hz} Hz");
+            assert_eq!(detector.flicker(), mains != 0);
+        }
+
+        let mut detector = FlickerDetector::new();
+        for n in 0..4 {
+            detector.push_frame(&frame(100.0, n as f64 * 20.0), width, height, n as f64 * 20.0).unwrap();
+        }
+        assert_eq!(detector.banded_fraction(), 1.0);
+        assert_eq!((detector.mains_frequency(), detector.flicker_period_ms()), (50, 10.0));
+        detector.reset();
+        assert!(!detector.flicker());
+        assert!(detector.push_frame(&[0; 10], width, height, 0.0).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_index, ScanError};
+
+// Binary pixels at or below this are ink.
+const INK: u8 = 127;
+// Smallest side of a box in pixels; anything smaller is a glyph or noise.
+const MIN_SIDE: usize = 8;
+// Largest area of a box as a share of the page, so page borders and big frames
+// around whole sections don't count as fields.
+const MAX_AREA_FRACTION: f32 = 0.25;
+// Depth of the band along each side of the bounding box that has to hold the
+// box outline, absorbing a residual tilt of a pixel or two after deskewing.
+const SIDE_BAND: usize = 3;
+// Share of the positions along every side where the band holds ink.
+const MIN_SIDE_COVERAGE: f32 = 0.9;
+// Long to short side up to which a small box is a checkbox.
+const MAX_CHECKBOX_ASPECT: f32 = 1.5;
+// Largest checkbox side as a share of the longer page side.
+const MAX_CHECKBOX_FRACTION: f32 = 0.1;
+// Interior ink share from which a box counts as filled in: a tick or cross
+// in a checkbox, any writing in a text field.
+const CHECKBOX_FILLED: f32 = 0.06;
+const FIELD_FILLED: f32 = 0.01;
+
+/// Kind of a box found by `detect_form_fields`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Small, roughly square box
+    Checkbox = 0,
+    /// Any other box, e.g. a field for a name or a date
+    TextField = 1,
+}
+
+struct Field {
+    kind: FieldKind,
+    bounds: [usize; 4],
+    ink_fraction: f32,
+}
+
+/// Boxes found by `detect_form_fields`, top to bottom and left to right.
+#[wasm_bindgen]
+pub struct FormFields {
+    fields: Vec<Field>,
+}
+
+#[wasm_bindgen]
+impl FormFields {
+    pub fn count(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn kind(&self, index: usize) -> Result<FieldKind, ScanError> {
+        Ok(self.get(index)?.kind)
+    }
+
+    /// Bounding box of the outline as `[x, y, width, height]`.
+    pub fn bounds(&self, index: usize) -> Result<Vec<usize>, ScanError> {
+        Ok(self.get(index)?.bounds.to_vec())
+    }
+
+    /// Share of ink inside the outline, 0..1.
+    pub fn ink_fraction(&self, index: usize) -> Result<f32, ScanError> {
+        Ok(self.get(index)?.ink_fraction)
+    }
+
+    /// Whether the box is filled in: at least 6 % ink inside a checkbox, or
+    /// 1 % inside a text field.
+    pub fn filled(&self, index: usize) -> Result<bool, ScanError> {
+        let field = self.get(index)?;
+        let threshold = match field.kind {
+            FieldKind::Checkbox => CHECKBOX_FILLED,
+            FieldKind::TextField => FIELD_FILLED,
+        };
+        Ok(field.ink_fraction >= threshold)
+    }
+}
+
+impl FormFields {
+    fn get(&self, index: usize) -> Result<&Field, ScanError> {
+        check_index(index, self.fields.len())?;
+        Ok(&self.fields[index])
+    }
+}
+
+// Bounding boxes `[x0, y0, x1, y1]` (inclusive) of the 8-connected ink
+// components.
+pub(crate) fn ink_components(binary: &[u8], width: usize, height: usize) -> Vec<[usize; 4]> {
+    let mut visited: Vec<bool> = binary.iter().map(|&v| v > INK).collect();
+    let mut stack = Vec::new();
+    let mut boxes = Vec::new();
+    for start in 0..binary.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (x, y) = (start % width, start / width);
+        let mut bounds = [x, y, x, y];
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            bounds = [bounds[0].min(x), bounds[1].min(y), bounds[2].max(x), bounds[3].max(y)];
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let n = ny * width + nx;
+                    if !visited[n] {
+                        visited[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+        boxes.push(bounds);
+    }
+    boxes
+}
+
+// Whether the bounding box of a component is traced by an outline on all four
+// sides, and how deep the outline goes (its stroke width).
+fn outline_depth(binary: &[u8], width: usize, bounds: [usize; 4]) -> Option<usize> {
+    let [x0, y0, x1, y1] = bounds;
+    let ink = |x: usize, y: usize| binary[y * width + x] <= INK;
+    let band = SIDE_BAND.min((x1 - x0).min(y1 - y0) / 2);
+    let mut depth = 0;
+    for side in 0..4 {
+        // Ink at position `t` along the side, `d` steps inwards from it
+        let at = |t: usize, d: usize| match side {
+            0 => ink(x0 + t, y0 + d),
+            1 => ink(x0 + t, y1 - d),
+            2 => ink(x0 + d, y0 + t),
+            _ => ink(x1 - d, y0 + t),
+        };
+        let length = if side < 2 { x1 - x0 + 1 } else { y1 - y0 + 1 };
+        let covered = (0..length).filter(|&t| (0..band).any(|d| at(t, d))).count();
+        if (covered as f32) < MIN_SIDE_COVERAGE * length as f32 {
+            return None;
+        }
+        // Stroke width in the middle of the side, where a tick can't touch it
+        let first = (0..band).find(|&d| at(length / 2, d)).unwrap_or(0);
+        depth = depth.max((first..length / 2).take_while(|&d| at(length / 2, d)).count() + first);
+    }
+    Some(depth)
+}
+
+/// Finds checkboxes and rectangular text fields on a binarized, deskewed form
+/// (e.g. from `binarize_document` after `deskew`) and tells whether each one is
+/// filled in. Boxes are axis-aligned rectangular outlines at least 8 px on a
+/// side; small, roughly square ones are checkboxes. Writing that runs over the
+/// outline joins it and still counts as the box's content.
+///
+/// # Arguments
+/// * `binary` - Binary page, 0 for ink and 255 for paper
+/// * `width` - Page width
+/// * `height` - Page height
+///
+/// # Returns
+/// `FormFields` with the kind, bounds and fill state of each box
+#[wasm_bindgen]
+pub fn detect_form_fields(binary: &[u8], width: usize, height: usize) -> Result<FormFields, ScanError> {
+    check_gray(binary, width, height)?;
+    let max_area = MAX_AREA_FRACTION * (width * height) as f32;
+    let max_checkbox = MAX_CHECKBOX_FRACTION * width.max(height) as f32;
+
+    let mut fields = Vec::new();
+    for bounds in ink_components(binary, width, height) {
+        let [x0, y0, x1, y1] = bounds;
+        let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+        if w.min(h) < MIN_SIDE || (w * h) as f32 > max_area {
+            continue;
+        }
+        let Some(depth) = outline_depth(binary, width, bounds) else {
+            continue;
+        };
+        // Interior: inside the outline plus a pixel of its anti-aliased rim
+        let inset = depth + 1;
+        if 2 * inset >= w.min(h) {
+            continue;
+        }
+        let mut ink = 0;
+        for y in y0 + inset..=y1 - inset {
+            ink += binary[y * width + x0 + inset..=y * width + x1 - inset].iter().filter(|&&v| v <= INK).count();
+        }
+        let interior = (w - 2 * inset) * (h - 2 * inset);
+        let aspect = w.max(h) as f32 / w.min(h) as f32;
+        let kind = if aspect <= MAX_CHECKBOX_ASPECT && w.max(h) as f32 <= max_checkbox {
+            FieldKind::Checkbox
+        } else {
+            FieldKind::TextField
+        };
+        fields.push(Field { kind, bounds: [x0, y0, w, h], ink_fraction: ink as f32 / interior as f32 });
+    }
+    Ok(FormFields { fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_fields() {
+        // A form with an empty and a ticked checkbox, a name field with writing
+        // in it, an empty date field, and a line of text
+        let (width, height) = (200, 120);
+        let mut page = vec![255u8; width * height];
+        let mut rect = |x0: usize, y0: usize, w: usize, h: usize, stroke: usize| {
+            for y in y0..y0 + h {
+                for x in x0..x0 + w {
+                    let (dx, dy) = ((x - x0).min(x0 + w - 1 - x), (y - y0).min(y0 + h - 1 - y));
+                    if dx.min(dy) < stroke {
+                        page[y * width + x] = 0;
+                    }
+                }
+            }
+        };
+        rect(10, 10, 14, 14, 2);
+        rect(10, 40, 14, 14, 2);
+        rect(60, 10, 120, 24, 1);
+        rect(60, 50, 80, 20, 1);
+        for i in 0..8 {
+            // Cross in the second checkbox
+            page[(43 + i) * width + 13 + i] = 0;
+            page[(43 + i) * width + 20 - i] = 0;
+        }
+        for x in (66..150).filter(|x| x % 7 < 4) {
+            // Handwriting in the name field, and a text line below the form
+            page[18 * width + x] = 0;
+            page[26 * width + x] = 0;
+            page[100 * width + x] = 0;
+            page[101 * width + x] = 0;
+        }
+
+        let fields = detect_form_fields(&page, width, height).unwrap();
+        assert_eq!(fields.count(), 4);
+        let expected = [
+            ([10, 10, 14, 14], FieldKind::Checkbox, false),
+            ([60, 10, 120, 24], FieldKind::TextField, true),
+            ([10, 40, 14, 14], FieldKind::Checkbox, true),
+            ([60, 50, 80, 20], FieldKind::TextField, false),
+        ];
+        for (i, (bounds, kind, filled)) in expected.into_iter().enumerate() {
+            assert_eq!(fields.bounds(i).unwrap(), bounds);
+            assert_eq!(fields.kind(i).unwrap(), kind);
+            assert_eq!(fields.filled(i).unwrap(), filled, "{i} {}", fields.ink_fraction(i).unwrap());
+        }
+        assert!(fields.kind(4).is_err());
+    }
+}
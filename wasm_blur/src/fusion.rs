@@ -0,0 +1,134 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::ScanError;
+
+type Quad = [(f32, f32); 4];
+
+fn to_quad(c: &[f32]) -> Quad {
+    [(c[0], c[1]), (c[2], c[3]), (c[4], c[5]), (c[6], c[7])]
+}
+
+// Mean distance between corresponding corners.
+fn corner_distance(a: &Quad, b: &Quad) -> f32 {
+    a.iter().zip(b).map(|(p, q)| (p.0 - q.0).hypot(p.1 - q.1)).sum::<f32>() / 4.0
+}
+
+// Mean of the two diagonals, the scale agreement is measured against.
+fn diagonal(q: &Quad) -> f32 {
+    ((q[0].0 - q[2].0).hypot(q[0].1 - q[2].1) + (q[1].0 - q[3].0).hypot(q[1].1 - q[3].1)) / 2.0
+}
+
+/// Quad agreed on by detections at several scales, from `fuse_quads`.
+#[wasm_bindgen]
+pub struct FusedQuad {
+    corners: Vec<f32>,
+    confidence: f32,
+    support: usize,
+}
+
+#[wasm_bindgen]
+impl FusedQuad {
+    /// Fused corners (TL, TR, BR, BL as 8 values), empty when there was nothing
+    /// to fuse.
+    pub fn corners(&self) -> Vec<f32> {
+        self.corners.clone()
+    }
+
+    /// Share of the total confidence held by the agreeing detections, 0..1.
+    #[wasm_bindgen(getter)]
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    /// Number of detections that agreed on the fused quad.
+    #[wasm_bindgen(getter)]
+    pub fn support(&self) -> usize {
+        self.support
+    }
+}
+
+/// Fuses quads detected on the same frame at different scales. Thin borders may
+/// only be found at full resolution and low-contrast ones only after
+/// downscaling, so a single scale fails on some documents. The detections are
+/// grouped by agreement and the group with the highest total confidence wins;
+/// its corners are averaged, weighted by confidence.
+///
+/// # Arguments
+/// * `quads` - Flat list of quads, 8 values each (TL, TR, BR, BL), all in
+///   full-resolution coordinates (see `ScaleMapping::to_full`)
+/// * `confidences` - One non-negative confidence per quad
+/// * `tolerance` - Largest mean corner distance, as a fraction of the quad's
+///   diagonal, at which two detections count as the same document, e.g. 0.05
+///
+/// # Returns
+/// The fused quad. Detections with non-finite corners or no confidence are
+/// ignored.
+#[wasm_bindgen]
+pub fn fuse_quads(quads: &[f32], confidences: &[f32], tolerance: f32) -> Result<FusedQuad, ScanError> {
+    if !quads.len().is_multiple_of(8) {
+        return Err(ScanError::InvalidPoints("Quads must have 8 coordinates (4 corners) each"));
+    }
+    if confidences.len() != quads.len() / 8 {
+        return Err(ScanError::InvalidParameter("Expected one confidence per quad"));
+    }
+    let detections: Vec<(Quad, f32)> = quads
+        .chunks_exact(8)
+        .zip(confidences)
+        .filter(|(c, &w)| w > 0.0 && w.is_finite() && c.iter().all(|v| v.is_finite()))
+        .map(|(c, &w)| (to_quad(c), w))
+        .collect();
+    let total: f32 = detections.iter().map(|d| d.1).sum();
+
+    // Each detection proposes the group of detections close to it; the
+    // heaviest group wins, the earliest one on ties
+    let mut best: Option<(f32, Vec<usize>)> = None;
+    for (quad, _) in &detections {
+        let limit = tolerance.max(0.0) * diagonal(quad);
+        let members: Vec<usize> =
+            (0..detections.len()).filter(|&j| corner_distance(quad, &detections[j].0) <= limit).collect();
+        let weight: f32 = members.iter().map(|&j| detections[j].1).sum();
+        if best.as_ref().is_none_or(|(w, _)| weight > *w) {
+            best = Some((weight, members));
+        }
+    }
+    let Some((weight, members)) = best else {
+        return Ok(FusedQuad { corners: Vec::new(), confidence: 0.0, support: 0 });
+    };
+
+    let mut corners = vec![0f32; 8];
+    for &j in &members {
+        let (quad, w) = &detections[j];
+        for (k, p) in quad.iter().enumerate() {
+            corners[k * 2] += p.0 * w / weight;
+            corners[k * 2 + 1] += p.1 * w / weight;
+        }
+    }
+    Ok(FusedQuad { corners, confidence: weight / total, support: members.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_quads() {
+        // Two scales agree within a pixel, a third latched onto the desk edge
+        let full = [100.0, 100.0, 500.0, 100.0, 500.0, 700.0, 100.0, 700.0];
+        let half = [101.0, 99.0, 501.0, 101.0, 499.0, 701.0, 101.0, 699.0];
+        let desk = [0.0, 300.0, 640.0, 300.0, 640.0, 800.0, 0.0, 800.0];
+        let quads = [full, half, desk].concat();
+
+        let fused = fuse_quads(&quads, &[1.0, 1.0, 1.5], 0.05).unwrap();
+        assert_eq!(fused.support(), 2);
+        assert!((fused.confidence() - 2.0 / 3.5).abs() < 1e-6);
+        assert_eq!(fused.corners(), vec![100.5, 99.5, 500.5, 100.5, 499.5, 700.5, 100.5, 699.5]);
+
+        // A confident outlier alone outweighs a weak pair; weights shift the mean
+        assert_eq!(fuse_quads(&quads, &[0.5, 0.5, 1.5], 0.05).unwrap().corners(), desk.to_vec());
+        assert_eq!(fuse_quads(&quads[..16], &[3.0, 1.0], 0.05).unwrap().corners()[0], 100.25);
+        assert!(fuse_quads(&[], &[], 0.05).unwrap().corners().is_empty());
+    }
+}
@@ -1,7 +1,24 @@
 use wasm_bindgen::prelude::*;
-use std::arch::wasm32::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::border::BorderMode;
+use crate::error::{check_gray, check_kernel_size, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+#[cfg(feature = "threads")]
+use crate::scheduler::ROWS_PER_TASK;
 
 // Constants for optimization
+#[cfg(target_family = "wasm")]
 const SIMD_WIDTH: usize = 4;
 const FIXED_POINT_SHIFT: u32 = 16;
 const FIXED_POINT_SCALE: f32 = 65536.0; // 2^16
@@ -49,6 +66,7 @@ pub fn create_gaussian_kernel_fixed(size: usize, sigma: f32) -> Vec<FixedPoint>
 }
 
 // Optimized horizontal pass with fixed-point arithmetic
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 #[inline]
 unsafe fn horizontal_pass_fixed(
@@ -80,10 +98,9 @@ unsafe fn horizontal_pass_fixed(
         let dst_row = &mut dst[row_offset..row_offset + width];
         
         // Process pixels with SIMD when possible
-        let simd_end = width.saturating_sub(SIMD_WIDTH);
         let mut x = 0;
         
-        while x <= simd_end {
+        while x + SIMD_WIDTH <= width {
             let mut sum_vec = u32x4_splat(0);
             
             for k_idx in 0..kernel_len {
@@ -125,6 +142,7 @@ unsafe fn horizontal_pass_fixed(
 }
 
 // Specialized 3x3 horizontal pass (most common case)
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 #[inline]
 unsafe fn horizontal_pass_3x3_fixed(
@@ -143,10 +161,10 @@ unsafe fn horizontal_pass_3x3_fixed(
         let src_row = &src[row_offset..row_offset + width];
         let dst_row = &mut dst[row_offset..row_offset + width];
         
-        // First pixel (special case)
-        dst_row[0] = kernel[0] * src_row[0] as u32 + 
-                     kernel[1] * src_row[0] as u32 + 
-                     kernel[2] * src_row[1] as u32;
+        // First pixel (special case), scaled to Q8 like the others
+        dst_row[0] = (kernel[0] * src_row[0] as u32 +
+                      kernel[1] * src_row[0] as u32 +
+                      kernel[2] * src_row[(width > 1) as usize] as u32) >> 8;
         
         // SIMD processing for middle pixels
         let mut x = 1;
@@ -202,6 +220,7 @@ unsafe fn horizontal_pass_3x3_fixed(
 }
 
 // Specialized 5x5 horizontal pass
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 #[inline]
 unsafe fn horizontal_pass_5x5_fixed(
@@ -228,7 +247,9 @@ unsafe fn horizontal_pass_5x5_fixed(
     }
 }
 
-// Optimized vertical pass with fixed-point arithmetic
+// Optimized vertical pass with fixed-point arithmetic. `dst` holds the output
+// rows starting at `y_start`, so the pass can run on independent row bands.
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 #[inline]
 unsafe fn vertical_pass_fixed(
@@ -236,6 +257,7 @@ unsafe fn vertical_pass_fixed(
     dst: &mut [u8],
     width: usize,
     height: usize,
+    y_start: usize,
     kernel: &[FixedPoint],
 ) {
     let half_kernel = kernel.len() / 2;
@@ -243,18 +265,17 @@ unsafe fn vertical_pass_fixed(
     
     // Special optimization for kernel size 3 (most common)
     if kernel_len == 3 {
-        vertical_pass_3x3_fixed(src, dst, width, height, kernel);
+        vertical_pass_3x3_fixed(src, dst, width, height, y_start, kernel);
         return;
     }
     
-    for y in 0..height {
-        let dst_row = &mut dst[y * width..(y + 1) * width];
+    for (row, dst_row) in dst.chunks_exact_mut(width).enumerate() {
+        let y = y_start + row;
         
         // Process with SIMD
         let mut x = 0;
-        let simd_end = width.saturating_sub(SIMD_WIDTH);
         
-        while x <= simd_end {
+        while x + SIMD_WIDTH <= width {
             let mut sum_vec_lo = u64x2_splat(0);
             let mut sum_vec_hi = u64x2_splat(0);
             
@@ -306,7 +327,9 @@ unsafe fn vertical_pass_fixed(
     }
 }
 
-// Specialized 3x3 vertical pass (most common case)
+// Specialized 3x3 vertical pass (most common case). The first and last rows
+// replicate the border, so every row can take the SIMD path.
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 #[inline]
 unsafe fn vertical_pass_3x3_fixed(
@@ -314,33 +337,25 @@ unsafe fn vertical_pass_3x3_fixed(
     dst: &mut [u8],
     width: usize,
     height: usize,
+    y_start: usize,
     kernel: &[FixedPoint],
 ) {
     let k0 = kernel[0] as u64;
     let k1 = kernel[1] as u64;
     let k2 = kernel[2] as u64;
     
-    // First row
-    {
-        let dst_row = &mut dst[0..width];
-        for x in 0..width {
-            let sum = k0 * (src[x] as u64) + 
-                     k1 * (src[x] as u64) + 
-                     k2 * (src[width + x] as u64);
-            dst_row[x] = ((sum >> 24).min(255)) as u8;
-        }
-    }
-    
-    // Middle rows with SIMD
-    for y in 1..height.saturating_sub(1) {
-        let dst_row = &mut dst[y * width..(y + 1) * width];
+    for (row, dst_row) in dst.chunks_exact_mut(width).enumerate() {
+        let y = y_start + row;
+        let above = y.saturating_sub(1) * width;
+        let center = y * width;
+        let below = (y + 1).min(height - 1) * width;
         let mut x = 0;
         
         // SIMD processing
         while x + 4 <= width {
-            let above_q16 = v128_load(src.as_ptr().add((y - 1) * width + x) as *const v128);
-            let center_q16 = v128_load(src.as_ptr().add(y * width + x) as *const v128);
-            let below_q16 = v128_load(src.as_ptr().add((y + 1) * width + x) as *const v128);
+            let above_q16 = v128_load(src.as_ptr().add(above + x) as *const v128);
+            let center_q16 = v128_load(src.as_ptr().add(center + x) as *const v128);
+            let below_q16 = v128_load(src.as_ptr().add(below + x) as *const v128);
 
             // Widen and multiply
             let k0_vec = u64x2_splat(k0);
@@ -367,22 +382,132 @@ unsafe fn vertical_pass_3x3_fixed(
         
         // Handle remaining pixels
         for x in x..width {
-            let sum = k0 * (src[(y - 1) * width + x] as u64) + 
-                     k1 * (src[y * width + x] as u64) + 
-                     k2 * (src[(y + 1) * width + x] as u64);
+            let sum = k0 * (src[above + x] as u64) + 
+                     k1 * (src[center + x] as u64) + 
+                     k2 * (src[below + x] as u64);
             dst_row[x] = ((sum >> 24).min(255)) as u8;
         }
     }
-    
-    // Last row
-    if height > 1 {
-        let y = height - 1;
-        let dst_row = &mut dst[y * width..(y + 1) * width];
-        for x in 0..width {
-            let sum = k0 * (src[(y - 1) * width + x] as u64) + 
-                     k1 * (src[y * width + x] as u64) + 
-                     k2 * (src[y * width + x] as u64);
-            dst_row[x] = ((sum >> 24).min(255)) as u8;
+}
+
+// One Q8 output of the horizontal pass, reading beyond the row ends per `border`.
+fn horizontal_at(src_row: &[u8], x: usize, kernel: &[FixedPoint], border: BorderMode) -> u32 {
+    let half_kernel = kernel.len() / 2;
+    let mut sum = 0u64;
+    for (k_idx, &weight) in kernel.iter().enumerate() {
+        if let Some(px) = border.index((x + k_idx) as isize - half_kernel as isize, src_row.len()) {
+            sum += (src_row[px] as u64) * (weight as u64);
+        }
+    }
+    (sum >> 8) as u32
+}
+
+// One output pixel of the vertical pass, reading beyond the top and bottom per
+// `border`.
+fn vertical_at(
+    src: &[u32],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    kernel: &[FixedPoint],
+    border: BorderMode,
+) -> u8 {
+    let half_kernel = kernel.len() / 2;
+    let mut sum = 0u64;
+    for (k_idx, &weight) in kernel.iter().enumerate() {
+        if let Some(ny) = border.index((y + k_idx) as isize - half_kernel as isize, height) {
+            sum += (src[ny * width + x] as u64) * (weight as u64);
+        }
+    }
+    (sum >> 24).min(255) as u8
+}
+
+// The passes above clamp to the edge pixel. For other border modes the columns
+// within half a kernel of the left and right border are recomputed here, with
+// the same Q8 arithmetic.
+#[cfg(target_family = "wasm")]
+fn horizontal_border_fixed(src: &[u8], dst: &mut [u32], width: usize, kernel: &[FixedPoint], border: BorderMode) {
+    let half_kernel = kernel.len() / 2;
+    let right = width.saturating_sub(half_kernel).max(half_kernel.min(width));
+    for (src_row, dst_row) in src.chunks_exact(width).zip(dst.chunks_exact_mut(width)) {
+        for x in (0..half_kernel.min(width)).chain(right..width) {
+            dst_row[x] = horizontal_at(src_row, x, kernel, border);
+        }
+    }
+}
+
+// Vertical counterpart of `horizontal_border_fixed` for the output rows in `dst`
+// (starting at `y_start`) that lie within half a kernel of the top or bottom.
+#[cfg(target_family = "wasm")]
+fn vertical_border_fixed(
+    src: &[u32],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    y_start: usize,
+    kernel: &[FixedPoint],
+    border: BorderMode,
+) {
+    let half_kernel = kernel.len() / 2;
+    for (row, dst_row) in dst.chunks_exact_mut(width).enumerate() {
+        let y = y_start + row;
+        if y >= half_kernel && y + half_kernel < height {
+            continue;
+        }
+        for (x, out) in dst_row.iter_mut().enumerate() {
+            *out = vertical_at(src, width, height, x, y, kernel, border);
+        }
+    }
+}
+
+// Horizontal pass over a band of whole rows: the SIMD pass plus the border
+// fix-up, or the scalar reference for every column when SIMD is switched off
+// (and on non-wasm targets).
+#[cfg_attr(not(target_family = "wasm"), allow(unused_variables))]
+fn horizontal_band(src: &[u8], dst: &mut [u32], width: usize, kernel: &[FixedPoint], border: BorderMode, simd: bool) {
+    #[cfg(target_family = "wasm")]
+    if simd {
+        unsafe { horizontal_pass_fixed(src, dst, width, src.len() / width, kernel) };
+        if border != BorderMode::Replicate {
+            horizontal_border_fixed(src, dst, width, kernel, border);
+        }
+        return;
+    }
+
+    for (src_row, dst_row) in src.chunks_exact(width).zip(dst.chunks_exact_mut(width)) {
+        for (x, out) in dst_row.iter_mut().enumerate() {
+            *out = horizontal_at(src_row, x, kernel, border);
+        }
+    }
+}
+
+// Vertical counterpart of `horizontal_band` for the output rows in `dst`,
+// starting at `y_start`.
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(target_family = "wasm"), allow(unused_variables))]
+fn vertical_band(
+    src: &[u32],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    y_start: usize,
+    kernel: &[FixedPoint],
+    border: BorderMode,
+    simd: bool,
+) {
+    #[cfg(target_family = "wasm")]
+    if simd {
+        unsafe { vertical_pass_fixed(src, dst, width, height, y_start, kernel) };
+        if border != BorderMode::Replicate {
+            vertical_border_fixed(src, dst, width, height, y_start, kernel, border);
+        }
+        return;
+    }
+
+    for (row, dst_row) in dst.chunks_exact_mut(width).enumerate() {
+        for (x, out) in dst_row.iter_mut().enumerate() {
+            *out = vertical_at(src, width, height, x, y_start + row, kernel, border);
         }
     }
 }
@@ -394,34 +519,188 @@ pub fn blur(
     width: usize,
     height: usize,
     kernel_size: usize,
-    mut sigma: f32,
-) -> Vec<u8> {
+    sigma: f32,
+) -> Result<Vec<u8>, ScanError> {
+    blur_with_border(grayscale, width, height, kernel_size, sigma, BorderMode::Replicate)
+}
+
+/// Gaussian blur with a selectable border mode; `blur` replicates the edge
+/// pixels.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Odd Gaussian kernel size
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+/// * `border` - How pixels beyond the image border are read
+///
+/// # Returns
+/// Blurred image as Vec<u8>
+#[wasm_bindgen]
+pub fn blur_with_border(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(grayscale, width, height)?;
+    check_kernel_size(kernel_size)?;
+
+    // Pre-allocate buffers with exact capacity
+    let pixel_count = width * height;
+    let mut temp_buffer = vec![0u32; pixel_count];
+    let mut result = vec![0u8; pixel_count];
+
+    blur_into(grayscale, width, height, kernel_size, sigma, border, &mut temp_buffer, &mut result);
+
+    Ok(result)
+}
+
+/// Gaussian blur with its own kernel size and sigma per axis, e.g. wide but
+/// short kernels for a smooth lighting estimate at half the cost of a square
+/// one, or extra smoothing along a vertical motion smear only. A kernel size
+/// of 1 leaves that axis unblurred.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_x` - Odd horizontal kernel size
+/// * `sigma_x` - Horizontal sigma; 0 derives it from `kernel_x`
+/// * `kernel_y` - Odd vertical kernel size
+/// * `sigma_y` - Vertical sigma; 0 derives it from `kernel_y`
+/// * `border` - How pixels beyond the image border are read
+///
+/// # Returns
+/// Blurred image as Vec<u8>
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn blur_anisotropic(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    kernel_x: usize,
+    sigma_x: f32,
+    kernel_y: usize,
+    sigma_y: f32,
+    border: BorderMode,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(grayscale, width, height)?;
+    check_kernel_size(kernel_x)?;
+    check_kernel_size(kernel_y)?;
+
+    let pixel_count = width * height;
+    let mut temp_buffer = vec![0u32; pixel_count];
+    let mut result = vec![0u8; pixel_count];
+    let (horizontal, vertical) = ((kernel_x, sigma_x), (kernel_y, sigma_y));
+    blur_xy_into(grayscale, width, height, horizontal, vertical, border, &mut temp_buffer, &mut result);
+    Ok(result)
+}
+
+// Blur into caller-owned buffers so pipelines can reuse their scratch memory.
+// `temp_buffer` and `result` must both hold width * height elements.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn blur_into(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    sigma: f32,
+    border: BorderMode,
+    temp_buffer: &mut [u32],
+    result: &mut [u8],
+) {
+    let kernel = (kernel_size, sigma);
+    blur_xy_into(grayscale, width, height, kernel, kernel, border, temp_buffer, result);
+}
+
+// Fixed-point kernel for an odd `kernel_size`, with the sigma derived by
+// OpenCV's default formula if not provided.
+fn axis_kernel(kernel_size: usize, sigma: f32) -> Vec<FixedPoint> {
+    if kernel_size == 0 || kernel_size % 2 == 0 {
+        panic!("Kernel size must be odd and greater than 0");
+    }
+    let sigma = if sigma <= 0.0 { 0.3 * (((kernel_size - 1) as f32) * 0.5 - 1.0) + 0.8 } else { sigma };
+    create_gaussian_kernel_fixed(kernel_size, sigma)
+}
+
+// `blur_into` with a (kernel size, sigma) pair per axis.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn blur_xy_into(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    (kernel_x, sigma_x): (usize, f32),
+    (kernel_y, sigma_y): (usize, f32),
+    border: BorderMode,
+    temp_buffer: &mut [u32],
+    result: &mut [u8],
+) {
     // Validate inputs
     if grayscale.len() != width * height {
         panic!("Input array size doesn't match width * height");
     }
-    if kernel_size == 0 || kernel_size % 2 == 0 {
-        panic!("Kernel size must be odd and greater than 0");
-    }
+    let horizontal = axis_kernel(kernel_x, sigma_x);
+    let vertical = axis_kernel(kernel_y, sigma_y);
 
-    // Calculate sigma using OpenCV's default formula if not provided
-    if sigma <= 0.0 {
-        sigma = 0.3 * (((kernel_size - 1) as f32) * 0.5 - 1.0) + 0.8;
+    if width == 0 || height == 0 {
+        return;
     }
 
-    // Use fixed-point kernel for better performance
-    let kernel_fixed = create_gaussian_kernel_fixed(kernel_size, sigma);
-    
-    // Pre-allocate buffers with exact capacity
-    let pixel_count = width * height;
-    let mut temp_buffer = vec![0u32; pixel_count];
-    let mut result = vec![0u8; pixel_count];
+    // Execute optimized fixed-point blur. With the `threads` feature both passes
+    // are split into bands of rows; the vertical pass only starts once the whole
+    // horizontal pass is done, since each output row reads its neighbours.
+    #[cfg(target_family = "wasm")]
+    let simd = simd_enabled();
+    #[cfg(not(target_family = "wasm"))]
+    let simd = false;
+    #[cfg(feature = "threads")]
+    {
+        let band = width * ROWS_PER_TASK;
+        grayscale
+            .par_chunks(band)
+            .zip(temp_buffer.par_chunks_mut(band))
+            .for_each(|(src, dst)| horizontal_band(src, dst, width, &horizontal, border, simd));
+        let temp_buffer = &*temp_buffer;
+        result.par_chunks_mut(band).enumerate().for_each(|(i, dst)| {
+            vertical_band(temp_buffer, dst, width, height, i * ROWS_PER_TASK, &vertical, border, simd)
+        });
+    }
 
-    // Execute optimized fixed-point blur
-    unsafe {
-        horizontal_pass_fixed(grayscale, &mut temp_buffer, width, height, &kernel_fixed);
-        vertical_pass_fixed(&temp_buffer, &mut result, width, height, &kernel_fixed);
+    #[cfg(not(feature = "threads"))]
+    {
+        horizontal_band(grayscale, temp_buffer, width, &horizontal, border, simd);
+        vertical_band(temp_buffer, result, width, height, 0, &vertical, border, simd);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    result
+    #[test]
+    fn test_blur_anisotropic() {
+        let (width, height) = (23, 17);
+        let gray: Vec<u8> = (0..width * height).map(|i| ((i * 37) % 251) as u8).collect();
+        for border in [BorderMode::Replicate, BorderMode::Reflect101, BorderMode::Constant] {
+            let square = blur_with_border(&gray, width, height, 5, 1.2, border);
+            assert_eq!(blur_anisotropic(&gray, width, height, 5, 1.2, 5, 1.2, border), square);
+
+            // A size of 1 leaves its axis alone, so columns of a vertical-only
+            // blur match blurring each column on its own; the SIMD passes agree
+            // with the scalar ones for wide but short kernels
+            let tall = blur_anisotropic(&gray, width, height, 1, 0.0, 9, 0.0, border).unwrap();
+            let column: Vec<u8> = (0..height).map(|y| gray[y * width + 3]).collect();
+            let column_blur = blur_anisotropic(&column, 1, height, 1, 0.0, 9, 0.0, border).unwrap();
+            assert!((0..height).all(|y| tall[y * width + 3] == column_blur[y]));
+            let wide = || blur_anisotropic(&gray, width, height, 15, 3.0, 3, 0.0, border);
+            let (simd, scalar) = crate::dispatch::simd_and_scalar(wide);
+            assert_eq!(simd, scalar);
+        }
+        let even = blur_anisotropic(&gray, width, height, 3, 0.0, 4, 0.0, BorderMode::Replicate);
+        assert_eq!(even, Err(ScanError::InvalidKernelSize(4)));
+    }
 }
@@ -0,0 +1,219 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::ScanError;
+
+type Point = (f32, f32);
+
+#[inline]
+fn cross(o: Point, a: Point, b: Point) -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn to_points(points: &[f32]) -> Result<Vec<Point>, ScanError> {
+    if !points.len().is_multiple_of(2) {
+        return Err(ScanError::InvalidPoints("Points must be x, y pairs"));
+    }
+    Ok(points.chunks_exact(2).map(|p| (p[0], p[1])).collect())
+}
+
+fn to_flat(points: &[Point]) -> Vec<f32> {
+    points.iter().flat_map(|&(x, y)| [x, y]).collect()
+}
+
+// Andrew's monotone chain. Collinear points on the hull are dropped.
+pub(crate) fn hull_of(points: &[Point]) -> Vec<Point> {
+    let mut sorted: Vec<Point> = points.iter().copied().filter(|p| p.0.is_finite() && p.1.is_finite()).collect();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    // Lower hull left to right, then upper hull right to left; the last point of
+    // each chain is the first point of the other one
+    let mut hull: Vec<Point> = Vec::with_capacity(sorted.len() + 1);
+    for chain in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in chain {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+// Minimum-area enclosing rectangle of a convex hull (as returned by `hull_of`),
+// by rotating calipers: one rectangle side is collinear with a hull edge, and
+// while walking the edges in order the points touching the other three sides
+// only ever move forward, so the whole search is linear in the hull size.
+pub(crate) fn min_rect_of_hull(hull: &[Point]) -> [Point; 4] {
+    let n = hull.len();
+    if n == 0 {
+        return [(0.0, 0.0); 4];
+    }
+    if n == 1 {
+        return [hull[0]; 4];
+    }
+
+    let dot = |p: Point, (ux, uy): Point| p.0 * ux + p.1 * uy;
+    // Indices of the points furthest along the edge, furthest from it and
+    // furthest back along it
+    let (mut ahead, mut across, mut behind) = (0, 0, 0);
+    let mut best = (f32::INFINITY, [(0.0, 0.0); 4]);
+
+    for i in 0..n {
+        let (a, b) = (hull[i], hull[(i + 1) % n]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        if length == 0.0 {
+            continue;
+        }
+        let u = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        // The hull runs counter-clockwise in x-right/y-up terms, so its interior
+        // lies on the left normal of every edge
+        let v = (-u.1, u.0);
+
+        if i == 0 {
+            let arg = |key: &dyn Fn(Point) -> f32| (0..n).max_by(|&p, &q| key(hull[p]).total_cmp(&key(hull[q]))).unwrap();
+            ahead = arg(&|p| dot(p, u));
+            across = arg(&|p| dot(p, v));
+            behind = arg(&|p| -dot(p, u));
+        } else {
+            while dot(hull[(ahead + 1) % n], u) > dot(hull[ahead], u) {
+                ahead = (ahead + 1) % n;
+            }
+            while dot(hull[(across + 1) % n], v) > dot(hull[across], v) {
+                across = (across + 1) % n;
+            }
+            while dot(hull[(behind + 1) % n], u) < dot(hull[behind], u) {
+                behind = (behind + 1) % n;
+            }
+        }
+
+        let base_u = dot(a, u);
+        let base_v = dot(a, v);
+        let (min_u, max_u) = (dot(hull[behind], u) - base_u, dot(hull[ahead], u) - base_u);
+        let max_v = dot(hull[across], v) - base_v;
+        let area = (max_u - min_u) * max_v;
+        if area < best.0 {
+            let corner = |s: f32, t: f32| (a.0 + s * u.0 + t * v.0, a.1 + s * u.1 + t * v.1);
+            best = (area, [corner(min_u, 0.0), corner(max_u, 0.0), corner(max_u, max_v), corner(min_u, max_v)]);
+        }
+    }
+    best.1
+}
+
+/// Convex hull of a point set, e.g. a document contour whose border has small
+/// concavities from shadows or fingers.
+///
+/// # Arguments
+/// * `points` - Flat list of x, y pairs
+///
+/// # Returns
+/// Hull vertices as flat x, y pairs, starting at the leftmost point and running
+/// clockwise on screen (y pointing down); collinear points are dropped
+#[wasm_bindgen]
+pub fn convex_hull(points: &[f32]) -> Result<Vec<f32>, ScanError> {
+    Ok(to_flat(&hull_of(&to_points(points)?)))
+}
+
+/// Smallest-area rotated rectangle enclosing a point set.
+///
+/// # Arguments
+/// * `points` - Flat list of x, y pairs
+///
+/// # Returns
+/// The rectangle's 4 corners (8 values) in order around the rectangle, or an
+/// empty Vec for an empty point set
+#[wasm_bindgen]
+pub fn min_area_rect(points: &[f32]) -> Result<Vec<f32>, ScanError> {
+    let hull = hull_of(&to_points(points)?);
+    if hull.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(to_flat(&min_rect_of_hull(&hull)))
+}
+
+/// Fits the minimum-area rotated rectangle around a point set and describes it
+/// by centre, size and angle, e.g. to estimate a document's skew from its contour.
+///
+/// # Arguments
+/// * `points` - Flat list of x, y pairs
+///
+/// # Returns
+/// `[center_x, center_y, width, height, angle]`. `width` is the side closer to
+/// horizontal and `angle` its rotation in degrees in (-45, 45], positive when
+/// the rectangle is turned clockwise on screen. Empty for an empty point set.
+#[wasm_bindgen]
+pub fn fit_rotated_rect(points: &[f32]) -> Result<Vec<f32>, ScanError> {
+    let hull = hull_of(&to_points(points)?);
+    if hull.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rect = min_rect_of_hull(&hull);
+    let center_x = rect.iter().map(|p| p.0).sum::<f32>() / 4.0;
+    let center_y = rect.iter().map(|p| p.1).sum::<f32>() / 4.0;
+
+    let side = |a: Point, b: Point| ((b.0 - a.0).hypot(b.1 - a.1), (b.1 - a.1).atan2(b.0 - a.0).to_degrees());
+    let (first, first_angle) = side(rect[0], rect[1]);
+    let (second, second_angle) = side(rect[1], rect[2]);
+    // Fold a side direction into (-90, 90]
+    let fold = |angle: f32| {
+        let angle = angle % 180.0;
+        if angle > 90.0 {
+            angle - 180.0
+        } else if angle <= -90.0 {
+            angle + 180.0
+        } else {
+            angle
+        }
+    };
+    let (first_angle, second_angle) = (fold(first_angle), fold(second_angle));
+    let (width, height, angle) = if first_angle.abs() <= second_angle.abs() {
+        (first, second, first_angle)
+    } else {
+        (second, first, second_angle)
+    };
+    // Exactly diagonal rectangles report +45 rather than -45
+    let angle = if angle <= -45.0 { angle + 90.0 } else { angle };
+    Ok(vec![center_x, center_y, width, height, angle])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hull_and_min_area_rect() {
+        // Square with a shadow notch on the top edge, interior and collinear points
+        let contour = [
+            0.0, 0.0, 5.0, 0.0, 6.0, 2.0, 7.0, 0.0, 10.0, 0.0, 10.0, 5.0, 10.0, 10.0, 0.0, 10.0, 3.0, 3.0, 0.0, 10.0,
+        ];
+        assert_eq!(convex_hull(&contour).unwrap(), vec![0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0]);
+
+        // A 4x2 rectangle rotated by 30 degrees comes back with the same area
+        let (sin, cos) = (30f32.to_radians().sin(), 30f32.to_radians().cos());
+        let rotated: Vec<f32> = [(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0), (2.0, 1.0), (1.0, 0.0)]
+            .iter()
+            .flat_map(|&(x, y): &(f32, f32)| [x * cos - y * sin + 20.0, x * sin + y * cos + 10.0])
+            .collect();
+        let rect = min_area_rect(&rotated).unwrap();
+        let side = |i: usize, j: usize| (rect[2 * i] - rect[2 * j]).hypot(rect[2 * i + 1] - rect[2 * j + 1]);
+        assert!((side(0, 1) * side(1, 2) - 8.0).abs() < 1e-3);
+        assert!(min_area_rect(&[]).unwrap().is_empty());
+
+        // Centre of the rotated rectangle, its long side as width and the skew angle
+        let fit = fit_rotated_rect(&rotated).unwrap();
+        let (cx, cy) = (2.0 * cos - sin + 20.0, 2.0 * sin + cos + 10.0);
+        let expected = [cx, cy, 4.0, 2.0, 30.0];
+        assert!(fit.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-3));
+        // Rotated past 45 degrees, the other side becomes the near-horizontal one
+        let steep: Vec<f32> = rotated.chunks_exact(2).flat_map(|p| [-p[1], p[0]]).collect();
+        assert!((fit_rotated_rect(&steep).unwrap()[4] - 30.0).abs() < 1e-3);
+    }
+}
@@ -1,34 +1,109 @@
 use wasm_bindgen::prelude::*;
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::border::BorderMode;
+use crate::error::{check_gray, ScanError};
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+#[cfg(feature = "threads")]
+use crate::scheduler::ROWS_PER_TASK;
+
 #[wasm_bindgen]
-pub fn calculate_gradients(blurred: &[u8], width: usize, height: usize) -> Vec<i16> {
-    let size = width * height;
-    let mut result = vec![0i16; 2 * size];
-
-    // Full 3×3 Sobel operator (matches the JS calculateGradients implementation)
-    for y in 1..height - 1 {
-        let prev_row = (y - 1) * width;
-        let curr_row = y * width;
-        let next_row = (y + 1) * width;
-
-        for x in 1..width - 1 {
-            let p0 = blurred[prev_row + x - 1] as i16;
-            let p1 = blurred[prev_row + x]     as i16;
-            let p2 = blurred[prev_row + x + 1] as i16;
-            let p3 = blurred[curr_row + x - 1] as i16;
-            let p5 = blurred[curr_row + x + 1] as i16;
-            let p6 = blurred[next_row + x - 1] as i16;
-            let p7 = blurred[next_row + x]     as i16;
-            let p8 = blurred[next_row + x + 1] as i16;
-
-            let gx = (p2 - p0) + 2 * (p5 - p3) + (p8 - p6);
-            let gy = (p6 + 2 * p7 + p8) - (p0 + 2 * p1 + p2);
-
-            let idx = curr_row + x;
-            result[2 * idx] = gx;
-            result[2 * idx + 1] = gy;
-        }
+pub fn calculate_gradients(blurred: &[u8], width: usize, height: usize) -> Result<Vec<i16>, ScanError> {
+    check_gray(blurred, width, height)?;
+    let mut result = vec![0i16; 2 * width * height];
+    gradients_into(blurred, width, height, &mut result);
+    Ok(result)
+}
+
+/// Sobel gradients like `calculate_gradients`, with the outermost rows and
+/// columns computed too instead of left at 0, reading beyond the image
+/// according to `border`.
+///
+/// # Arguments
+/// * `blurred` - Blurred grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `border` - How pixels beyond the image border are read
+///
+/// # Returns
+/// Interleaved (gx, gy) gradients as Vec<i16>
+#[wasm_bindgen]
+pub fn calculate_gradients_with_border(
+    blurred: &[u8],
+    width: usize,
+    height: usize,
+    border: BorderMode,
+) -> Result<Vec<i16>, ScanError> {
+    let mut result = calculate_gradients(blurred, width, height)?;
+    border_gradients_into(blurred, width, height, border, &mut result);
+    Ok(result)
+}
+
+// Interleaved (gx, gy) gradients into a caller-owned buffer of 2 * width * height values.
+pub(crate) fn gradients_into(blurred: &[u8], width: usize, height: usize, result: &mut [i16]) {
+    if width == 0 || height == 0 {
+        return;
     }
+    // Rows are independent, so with the `threads` feature they are spread over the pool
+    #[cfg(feature = "threads")]
+    let rows = result.par_chunks_mut(2 * width).with_min_len(ROWS_PER_TASK);
+    #[cfg(not(feature = "threads"))]
+    let rows = result.chunks_mut(2 * width);
 
-    result
+    rows.enumerate()
+        .filter(|&(y, _)| y > 0 && y + 1 < height)
+        .for_each(|(y, row)| gradient_row(blurred, width, y, row));
+}
+
+// Full 3×3 Sobel operator (matches the JS calculateGradients implementation) for
+// one interior row, written as interleaved (gx, gy) pairs.
+fn gradient_row(blurred: &[u8], width: usize, y: usize, result: &mut [i16]) {
+    let prev_row = (y - 1) * width;
+    let curr_row = y * width;
+    let next_row = (y + 1) * width;
+
+    for x in 1..width - 1 {
+        let p0 = blurred[prev_row + x - 1] as i16;
+        let p1 = blurred[prev_row + x]     as i16;
+        let p2 = blurred[prev_row + x + 1] as i16;
+        let p3 = blurred[curr_row + x - 1] as i16;
+        let p5 = blurred[curr_row + x + 1] as i16;
+        let p6 = blurred[next_row + x - 1] as i16;
+        let p7 = blurred[next_row + x]     as i16;
+        let p8 = blurred[next_row + x + 1] as i16;
+
+        let gx = (p2 - p0) + 2 * (p5 - p3) + (p8 - p6);
+        let gy = (p6 + 2 * p7 + p8) - (p0 + 2 * p1 + p2);
+
+        result[2 * x] = gx;
+        result[2 * x + 1] = gy;
+    }
+}
+
+// Sobel gradients of the outermost rows and columns, which `gradients_into`
+// leaves at 0, reading beyond the image according to `border`.
+fn border_gradients_into(blurred: &[u8], width: usize, height: usize, border: BorderMode, result: &mut [i16]) {
+    let at = |x: isize, y: isize| match (border.index(x, width), border.index(y, height)) {
+        (Some(x), Some(y)) => blurred[y * width + x] as i16,
+        _ => 0,
+    };
+    for y in 0..height {
+        // Whole first and last rows, only the first and last column in between
+        let step = if y == 0 || y + 1 == height { 1 } else { width.saturating_sub(1).max(1) };
+        for x in (0..width).step_by(step) {
+            let (x, y) = (x as isize, y as isize);
+            let gx = (at(x + 1, y - 1) - at(x - 1, y - 1)) + 2 * (at(x + 1, y) - at(x - 1, y))
+                + (at(x + 1, y + 1) - at(x - 1, y + 1));
+            let gy = (at(x - 1, y + 1) + 2 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2 * at(x, y - 1) + at(x + 1, y - 1));
+            let i = 2 * (y as usize * width + x as usize);
+            result[i] = gx;
+            result[i + 1] = gy;
+        }
+    }
 }
@@ -0,0 +1,133 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+/// Luma weights used for the RGB to grayscale conversion.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrayscaleWeights {
+    /// ITU-R BT.601 (0.299, 0.587, 0.114), matches the JS `convertToGrayscale`
+    Bt601 = 0,
+    /// ITU-R BT.709 (0.2126, 0.7152, 0.0722)
+    Bt709 = 1,
+}
+
+// Q8 fixed-point weights, each triple sums to 256 so white stays 255.
+#[inline]
+pub(crate) fn fixed_weights(weights: GrayscaleWeights) -> (u16, u16, u16) {
+    match weights {
+        GrayscaleWeights::Bt601 => (77, 150, 29),
+        GrayscaleWeights::Bt709 => (54, 183, 19),
+    }
+}
+
+#[inline]
+pub(crate) fn luma_scalar(r: u8, g: u8, b: u8, (wr, wg, wb): (u16, u16, u16)) -> u8 {
+    ((r as u16 * wr + g as u16 * wg + b as u16 * wb + 128) >> 8) as u8
+}
+
+// Processes 16 pixels (64 bytes) per iteration: the four loaded vectors are
+// deinterleaved into planes of the first three channels with byte shuffles, then
+// weighted in u16. Channel order only matters through `weights`, so BGRA input
+// uses the same kernel with the R and B weights swapped.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn packed_to_grayscale_simd(rgba: &[u8], gray: &mut [u8], weights: (u16, u16, u16)) {
+    let chunks = gray.len() / 16;
+    let wr = u16x8_splat(weights.0);
+    let wg = u16x8_splat(weights.1);
+    let wb = u16x8_splat(weights.2);
+    let round = u16x8_splat(128);
+
+    for chunk in 0..chunks {
+        let src = rgba.as_ptr().add(chunk * 64);
+        let v0 = v128_load(src as *const v128);
+        let v1 = v128_load(src.add(16) as *const v128);
+        let v2 = v128_load(src.add(32) as *const v128);
+        let v3 = v128_load(src.add(48) as *const v128);
+
+        let r01 = i8x16_shuffle::<0, 4, 8, 12, 16, 20, 24, 28, 0, 0, 0, 0, 0, 0, 0, 0>(v0, v1);
+        let r23 = i8x16_shuffle::<0, 4, 8, 12, 16, 20, 24, 28, 0, 0, 0, 0, 0, 0, 0, 0>(v2, v3);
+        let g01 = i8x16_shuffle::<1, 5, 9, 13, 17, 21, 25, 29, 0, 0, 0, 0, 0, 0, 0, 0>(v0, v1);
+        let g23 = i8x16_shuffle::<1, 5, 9, 13, 17, 21, 25, 29, 0, 0, 0, 0, 0, 0, 0, 0>(v2, v3);
+        let b01 = i8x16_shuffle::<2, 6, 10, 14, 18, 22, 26, 30, 0, 0, 0, 0, 0, 0, 0, 0>(v0, v1);
+        let b23 = i8x16_shuffle::<2, 6, 10, 14, 18, 22, 26, 30, 0, 0, 0, 0, 0, 0, 0, 0>(v2, v3);
+
+        let r = i8x16_shuffle::<0, 1, 2, 3, 4, 5, 6, 7, 16, 17, 18, 19, 20, 21, 22, 23>(r01, r23);
+        let g = i8x16_shuffle::<0, 1, 2, 3, 4, 5, 6, 7, 16, 17, 18, 19, 20, 21, 22, 23>(g01, g23);
+        let b = i8x16_shuffle::<0, 1, 2, 3, 4, 5, 6, 7, 16, 17, 18, 19, 20, 21, 22, 23>(b01, b23);
+
+        let lo = u16x8_add(
+            u16x8_add(
+                u16x8_mul(u16x8_extend_low_u8x16(r), wr),
+                u16x8_mul(u16x8_extend_low_u8x16(g), wg),
+            ),
+            u16x8_add(u16x8_mul(u16x8_extend_low_u8x16(b), wb), round),
+        );
+        let hi = u16x8_add(
+            u16x8_add(
+                u16x8_mul(u16x8_extend_high_u8x16(r), wr),
+                u16x8_mul(u16x8_extend_high_u8x16(g), wg),
+            ),
+            u16x8_add(u16x8_mul(u16x8_extend_high_u8x16(b), wb), round),
+        );
+
+        let luma = u8x16_narrow_i16x8(u16x8_shr(lo, 8), u16x8_shr(hi, 8));
+        v128_store(gray.as_mut_ptr().add(chunk * 16) as *mut v128, luma);
+    }
+
+    // Handle remaining pixels
+    for (g, px) in gray[chunks * 16..].iter_mut().zip(rgba[chunks * 64..].chunks_exact(4)) {
+        *g = luma_scalar(px[0], px[1], px[2], weights);
+    }
+}
+
+// Grayscale from 4-byte pixels with Q8 `weights` for the first three bytes.
+pub(crate) fn packed_to_grayscale_into(src: &[u8], gray: &mut [u8], weights: (u16, u16, u16)) {
+    #[cfg(target_family = "wasm")]
+    if simd_enabled() {
+        unsafe {
+            packed_to_grayscale_simd(src, gray, weights);
+        }
+        return;
+    }
+
+    for (g, px) in gray.iter_mut().zip(src.chunks_exact(4)) {
+        *g = luma_scalar(px[0], px[1], px[2], weights);
+    }
+}
+
+pub(crate) fn rgba_to_grayscale_into(rgba: &[u8], gray: &mut [u8], weights: GrayscaleWeights) {
+    packed_to_grayscale_into(rgba, gray, fixed_weights(weights));
+}
+
+/// Converts an RGBA buffer (e.g. `ImageData.data`) to 8-bit grayscale.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels, 4 bytes per pixel
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `weights` - Luma weights (BT.601 or BT.709)
+///
+/// # Returns
+/// Grayscale image as Vec<u8>
+#[wasm_bindgen]
+pub fn rgba_to_grayscale(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    weights: GrayscaleWeights,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, weights);
+    Ok(gray)
+}
@@ -0,0 +1,192 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+use crate::grayscale::{fixed_weights, luma_scalar, GrayscaleWeights};
+
+// Number of histogram bins (one per grey level).
+pub(crate) const BINS: usize = 256;
+
+pub(crate) fn gray_histogram(gray: &[u8]) -> [u32; BINS] {
+    let mut histogram = [0u32; BINS];
+    for &v in gray {
+        histogram[v as usize] += 1;
+    }
+    histogram
+}
+
+// Histogram of the BT.601 luma of RGBA pixels.
+#[cfg_attr(not(feature = "enhance"), allow(dead_code))]
+pub(crate) fn luma_histogram(rgba: &[u8]) -> [u32; BINS] {
+    let weights = fixed_weights(GrayscaleWeights::Bt601);
+    let mut histogram = [0u32; BINS];
+    for px in rgba.chunks_exact(4) {
+        histogram[luma_scalar(px[0], px[1], px[2], weights) as usize] += 1;
+    }
+    histogram
+}
+
+// Levels below which `low_pct` percent and above which `high_pct` percent of the
+// pixels lie. Returns (0, 255) for an empty histogram.
+pub(crate) fn percentile_levels(histogram: &[u32; BINS], low_pct: f32, high_pct: f32) -> (u8, u8) {
+    let total: u64 = histogram.iter().map(|&c| c as u64).sum();
+    if total == 0 {
+        return (0, 255);
+    }
+    let low_count = (total as f32 * low_pct.clamp(0.0, 100.0) / 100.0) as u64;
+    let high_count = (total as f32 * high_pct.clamp(0.0, 100.0) / 100.0) as u64;
+
+    let mut seen = 0u64;
+    let mut low = 0;
+    for (level, &count) in histogram.iter().enumerate() {
+        seen += count as u64;
+        if seen > low_count {
+            low = level;
+            break;
+        }
+    }
+    seen = 0;
+    let mut high = BINS - 1;
+    for (level, &count) in histogram.iter().enumerate().rev() {
+        seen += count as u64;
+        if seen > high_count {
+            high = level;
+            break;
+        }
+    }
+    (low as u8, high.max(low) as u8)
+}
+
+// LUT mapping `low` to 0 and `high` to 255 linearly, clamping outside. Flat
+// images (`low == high`) map to the identity.
+pub(crate) fn stretch_lut(low: u8, high: u8) -> [u8; BINS] {
+    let mut lut = [0u8; BINS];
+    for (i, v) in lut.iter_mut().enumerate() {
+        *v = if high <= low {
+            i as u8
+        } else {
+            let scaled = (i as i32 - low as i32) * 255 * 2 + (high - low) as i32;
+            (scaled / (2 * (high - low) as i32)).clamp(0, 255) as u8
+        };
+    }
+    lut
+}
+
+// Histogram equalization LUT: each level maps to its cumulative share of the
+// pixels, with the darkest occupied level at 0 (as OpenCV's `equalizeHist`).
+// Single-level images map to the identity.
+pub(crate) fn equalize_lut(histogram: &[u32; BINS]) -> [u8; BINS] {
+    let total: u64 = histogram.iter().map(|&c| c as u64).sum();
+    let first = histogram.iter().find(|&&c| c > 0).map_or(0, |&c| c as u64);
+    let mut lut = [0u8; BINS];
+    if total == first {
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+        return lut;
+    }
+    let range = total - first;
+    let mut cumulative = 0u64;
+    for (v, &count) in lut.iter_mut().zip(histogram) {
+        cumulative += count as u64;
+        *v = ((cumulative.saturating_sub(first) * 255 + range / 2) / range) as u8;
+    }
+    lut
+}
+
+/// Counts the pixels of every grey level of a grayscale image.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// 256 counts, one per grey level
+#[wasm_bindgen]
+pub fn histogram(gray: &[u8], width: usize, height: usize) -> Result<Vec<u32>, ScanError> {
+    check_gray(gray, width, height)?;
+    Ok(gray_histogram(gray).to_vec())
+}
+
+/// Spreads the grey levels of an image so they are used about equally often,
+/// e.g. for a high-contrast preview of a dim capture.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Equalized grayscale image as Vec<u8>
+#[wasm_bindgen]
+pub fn equalize_hist(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let lut = equalize_lut(&gray_histogram(gray));
+    Ok(gray.iter().map(|&v| lut[v as usize]).collect())
+}
+
+/// Stretches the grey levels linearly so the range between the two percentiles
+/// spans 0..255; pixels beyond them clip.
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `percent_low` - Percentage of the darkest pixels allowed to clip to black
+/// * `percent_high` - Percentage of the brightest pixels allowed to clip to white
+///
+/// # Returns
+/// Stretched grayscale image as Vec<u8>
+#[wasm_bindgen]
+pub fn stretch_contrast(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    percent_low: f32,
+    percent_high: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let (low, high) = percentile_levels(&gray_histogram(gray), percent_low, percent_high);
+    let lut = stretch_lut(low, high);
+    Ok(gray.iter().map(|&v| lut[v as usize]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_stretch() {
+        // 100 pixels spread evenly over levels 50..150
+        let gray: Vec<u8> = (50..150).collect();
+        let counts = histogram(&gray, 10, 10).unwrap();
+        assert_eq!(counts.iter().sum::<u32>(), 100);
+        assert_eq!((counts[49], counts[50], counts[149], counts[150]), (0, 1, 1, 0));
+
+        let hist = gray_histogram(&gray);
+        assert_eq!(percentile_levels(&hist, 0.0, 0.0), (50, 149));
+        assert_eq!(percentile_levels(&hist, 5.0, 10.0), (55, 139));
+        assert_eq!(percentile_levels(&[0; BINS], 1.0, 1.0), (0, 255));
+
+        let lut = stretch_lut(50, 149);
+        assert_eq!((lut[0], lut[50], lut[100], lut[149], lut[255]), (0, 0, 129, 255, 255));
+        assert_eq!(stretch_lut(80, 80)[200], 200);
+    }
+
+    #[test]
+    fn test_equalize_and_stretch_contrast() {
+        // Three equally common levels crowded into the dark end
+        let gray = [10, 10, 20, 20, 30, 30];
+        assert_eq!(equalize_hist(&gray, 3, 2).unwrap(), vec![0, 0, 128, 128, 255, 255]);
+        assert_eq!(equalize_hist(&[77; 4], 2, 2).unwrap(), vec![77; 4]);
+
+        let ramp: Vec<u8> = (50..150).collect();
+        let stretched = stretch_contrast(&ramp, 10, 10, 0.0, 0.0).unwrap();
+        assert_eq!((stretched[0], stretched[50], stretched[99]), (0, 129, 255));
+        assert!(stretched.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(stretch_contrast(&ramp, 10, 10, 10.0, 10.0).unwrap()[5], 0);
+    }
+}
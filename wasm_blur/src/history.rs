@@ -0,0 +1,168 @@
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+// Detections kept when the capacity is not set explicitly, about a second of
+// preview at 30 fps.
+pub(crate) const DEFAULT_CAPACITY: usize = 30;
+
+#[derive(Clone, Copy)]
+struct Detection {
+    // None when no quad was found on the frame
+    corners: Option<[f32; 8]>,
+    confidence: f32,
+    timestamp_ms: f64,
+}
+
+// Ring buffer of the latest detection results. Ages count back from the newest
+// entry (age 0).
+pub(crate) struct DetectionHistory {
+    entries: Vec<Detection>,
+    // Slot the next entry goes to once the buffer is full
+    next: usize,
+    capacity: usize,
+}
+
+impl Default for DetectionHistory {
+    fn default() -> Self {
+        DetectionHistory { entries: Vec::new(), next: 0, capacity: DEFAULT_CAPACITY }
+    }
+}
+
+impl DetectionHistory {
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        // Keep the newest entries, oldest first
+        let keep = self.len().min(capacity);
+        let mut entries: Vec<Detection> = (0..keep).rev().map(|age| *self.get(age)).collect();
+        entries.shrink_to_fit();
+        self.entries = entries;
+        self.next = 0;
+        self.capacity = capacity;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.next = 0;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn push(&mut self, corners: Option<[f32; 8]>, confidence: f32, timestamp_ms: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        let detection = Detection { corners, confidence, timestamp_ms };
+        if self.entries.len() < self.capacity {
+            self.entries.push(detection);
+        } else {
+            self.entries[self.next] = detection;
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    fn get(&self, age: usize) -> &Detection {
+        if age >= self.entries.len() {
+            panic!("History index out of range");
+        }
+        let newest = if self.entries.len() < self.capacity { self.entries.len() } else { self.next };
+        let len = self.entries.len();
+        &self.entries[(newest + len - 1 - age) % len]
+    }
+
+    pub(crate) fn corners(&self, age: usize) -> Option<[f32; 8]> {
+        self.get(age).corners
+    }
+
+    pub(crate) fn confidence(&self, age: usize) -> f32 {
+        self.get(age).confidence
+    }
+
+    pub(crate) fn timestamp_ms(&self, age: usize) -> f64 {
+        self.get(age).timestamp_ms
+    }
+
+    // Quads among the newest `window` entries; 0 covers the whole history.
+    fn quads(&self, window: usize) -> Vec<[f32; 8]> {
+        let window = if window == 0 { self.len() } else { window.min(self.len()) };
+        (0..window).filter_map(|age| self.get(age).corners).collect()
+    }
+
+    // Per-coordinate median of the quads in the window.
+    pub(crate) fn median_quad(&self, window: usize) -> Option<[f32; 8]> {
+        let quads = self.quads(window);
+        if quads.is_empty() {
+            return None;
+        }
+        let mut median = [0f32; 8];
+        let mut values = Vec::with_capacity(quads.len());
+        for (k, m) in median.iter_mut().enumerate() {
+            values.clear();
+            values.extend(quads.iter().map(|q| q[k]));
+            values.sort_by(f32::total_cmp);
+            let mid = values.len() / 2;
+            *m = if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] };
+        }
+        Some(median)
+    }
+
+    // Mean over the 8 coordinates of their variance across the window, in px².
+    pub(crate) fn stability_variance(&self, window: usize) -> f32 {
+        let quads = self.quads(window);
+        if quads.len() < 2 {
+            return f32::INFINITY;
+        }
+        let n = quads.len() as f32;
+        let mut total = 0.0;
+        for k in 0..8 {
+            let mean = quads.iter().map(|q| q[k]).sum::<f32>() / n;
+            total += quads.iter().map(|q| (q[k] - mean) * (q[k] - mean)).sum::<f32>() / n;
+        }
+        total / 8.0
+    }
+
+    // Fraction of the entries in the window that found a quad.
+    pub(crate) fn detection_rate(&self, window: usize) -> f32 {
+        let window = if window == 0 { self.len() } else { window.min(self.len()) };
+        if window == 0 {
+            return 0.0;
+        }
+        self.quads(window).len() as f32 / window as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_history_ring_and_aggregates() {
+        let mut context = ScanContext::new(64, 48);
+        context.set_history_capacity(4);
+        let quad = |dx: f32| [10.0 + dx, 10.0, 50.0 + dx, 10.0, 50.0 + dx, 40.0, 10.0 + dx, 40.0];
+        for (i, dx) in [0.0, 100.0, 1.0, 2.0, 3.0].into_iter().enumerate() {
+            context.record_detection(&quad(dx), 0.9, i as f64 * 33.0).unwrap();
+        }
+        context.record_detection(&[], 0.0, 165.0).unwrap();
+
+        // Capacity 4 keeps the last four frames, newest first
+        assert_eq!(context.history_len(), 4);
+        assert!(context.history_corners(0).unwrap().is_empty());
+        assert_eq!(context.history_corners(1).unwrap(), quad(3.0).to_vec());
+        assert_eq!(context.history_timestamp(3).unwrap(), 66.0);
+        assert_eq!(context.detection_rate(0), 0.75);
+
+        // The outlier at dx = 100 has dropped out; the median ignores misses
+        assert_eq!(context.median_quad(0), quad(2.0).to_vec());
+        let variance = context.stability_variance(0);
+        // x coordinates vary by 2/3 px², y not at all
+        assert!((variance - 1.0 / 3.0).abs() < 1e-5);
+        assert_eq!(context.stability_variance(2), f32::INFINITY);
+
+        context.set_history_capacity(2);
+        assert_eq!(context.history_len(), 2);
+        assert_eq!(context.history_corners(1).unwrap(), quad(3.0).to_vec());
+        context.clear_history();
+        assert!(context.median_quad(0).is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::ScanError;
+
+type Point = (f64, f64);
+type Matrix = [f64; 9];
+
+// Confidence that RANSAC has drawn at least one all-inlier sample before it
+// stops early.
+const RANSAC_CONFIDENCE: f64 = 0.995;
+// Fixed seed, so the same correspondences always give the same result.
+const RANSAC_SEED: u32 = 0x9e37_79b9;
+// Jacobi sweeps before giving up on the eigen decomposition; 9x9 converges in
+// well under 10.
+const MAX_SWEEPS: usize = 50;
+
+fn to_points(points: &[f32]) -> Vec<Point> {
+    points.chunks_exact(2).map(|p| (p[0] as f64, p[1] as f64)).collect()
+}
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [0f64; 9];
+    for r in 0..3 {
+        for c in 0..3 {
+            out[r * 3 + c] = (0..3).map(|k| a[r * 3 + k] * b[k * 3 + c]).sum();
+        }
+    }
+    out
+}
+
+fn project(h: &Matrix, (x, y): Point) -> Option<Point> {
+    let w = h[6] * x + h[7] * y + h[8];
+    if w.abs() < 1e-12 {
+        return None;
+    }
+    Some(((h[0] * x + h[1] * y + h[2]) / w, (h[3] * x + h[4] * y + h[5]) / w))
+}
+
+// Hartley normalization: moves the centroid to the origin and scales the mean
+// distance to sqrt(2). Returns the normalizing transform and its inverse.
+fn normalization(points: &[Point]) -> (Matrix, Matrix) {
+    let n = points.len() as f64;
+    let cx = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let cy = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let mean = points.iter().map(|p| (p.0 - cx).hypot(p.1 - cy)).sum::<f64>() / n;
+    let s = if mean > 0.0 { core::f64::consts::SQRT_2 / mean } else { 1.0 };
+    (
+        [s, 0.0, -s * cx, 0.0, s, -s * cy, 0.0, 0.0, 1.0],
+        [1.0 / s, 0.0, cx, 0.0, 1.0 / s, cy, 0.0, 0.0, 1.0],
+    )
+}
+
+// Eigenvector of the smallest eigenvalue of a symmetric 9x9 matrix, by cyclic
+// Jacobi rotations.
+fn smallest_eigenvector(mut a: [[f64; 9]; 9]) -> [f64; 9] {
+    let mut v = [[0f64; 9]; 9];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..9).flat_map(|p| (p + 1..9).map(move |q| (p, q))).map(|(p, q)| a[p][q] * a[p][q]).sum();
+        if off < 1e-30 {
+            break;
+        }
+        for p in 0..9 {
+            for q in p + 1..9 {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                // Smaller root of t^2 + 2 theta t - 1 = 0 (signum(0) is 1)
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                a[p] = core::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+                a[q] = core::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let smallest = (0..9).min_by(|&i, &j| a[i][i].total_cmp(&a[j][j])).unwrap();
+    let mut out = [0f64; 9];
+    for (o, row) in out.iter_mut().zip(&v) {
+        *o = row[smallest];
+    }
+    out
+}
+
+// Normalized direct linear transform: the least-squares homography mapping
+// `src` onto `dst` (at least 4 pairs), scaled so h[8] = 1. None when degenerate.
+pub(crate) fn dlt(src: &[Point], dst: &[Point]) -> Option<Matrix> {
+    if src.len() < 4 || src.len() != dst.len() {
+        return None;
+    }
+    let (t_src, _) = normalization(src);
+    let (t_dst, t_dst_inv) = normalization(dst);
+
+    // Normal equations A^T A of the 2n x 9 DLT system
+    let mut ata = [[0f64; 9]; 9];
+    for (&s, &d) in src.iter().zip(dst) {
+        let (x, y) = project(&t_src, s)?;
+        let (u, v) = project(&t_dst, d)?;
+        let rows = [
+            [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, -u],
+            [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, -v],
+        ];
+        for row in &rows {
+            for i in 0..9 {
+                for j in 0..9 {
+                    ata[i][j] += row[i] * row[j];
+                }
+            }
+        }
+    }
+
+    let normalized = smallest_eigenvector(ata);
+    let h = mul(&mul(&t_dst_inv, &normalized), &t_src);
+    if h[8].abs() < 1e-12 || h.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+    Some(h.map(|v| v / h[8]))
+}
+
+// Marks the pairs `h` maps within `threshold` pixels of their destination.
+fn inlier_mask(h: &Matrix, src: &[Point], dst: &[Point], threshold: f64) -> Vec<bool> {
+    src.iter()
+        .zip(dst)
+        .map(|(&s, &d)| project(h, s).is_some_and(|(x, y)| (x - d.0).hypot(y - d.1) <= threshold))
+        .collect()
+}
+
+fn matching<'a>(points: &'a [Point], mask: &'a [bool]) -> impl Iterator<Item = Point> + 'a {
+    points.iter().zip(mask).filter(|(_, &m)| m).map(|(&p, _)| p)
+}
+
+// xorshift32; only used to draw RANSAC samples.
+fn next_random(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+// RANSAC over minimal 4-pair samples, followed by a DLT refit on the inliers of
+// the best sample. Stops early once `RANSAC_CONFIDENCE` is reached.
+pub(crate) fn ransac(
+    src: &[Point],
+    dst: &[Point],
+    threshold: f64,
+    max_iterations: usize,
+) -> Option<(Matrix, Vec<bool>)> {
+    let n = src.len();
+    if n < 4 {
+        return None;
+    }
+    let mut state = RANSAC_SEED;
+    let mut best: Option<(Matrix, Vec<bool>, usize)> = None;
+    let mut iterations = max_iterations;
+    let mut i = 0;
+    while i < iterations {
+        i += 1;
+        let mut sample = [0usize; 4];
+        for k in 0..4 {
+            sample[k] = loop {
+                let candidate = next_random(&mut state) as usize % n;
+                if !sample[..k].contains(&candidate) {
+                    break candidate;
+                }
+            };
+        }
+        let s: Vec<Point> = sample.iter().map(|&j| src[j]).collect();
+        let d: Vec<Point> = sample.iter().map(|&j| dst[j]).collect();
+        let Some(h) = dlt(&s, &d) else { continue };
+
+        let mask = inlier_mask(&h, src, dst, threshold);
+        let count = mask.iter().filter(|&&m| m).count();
+        if best.as_ref().is_none_or(|b| count > b.2) {
+            // Samples needed to draw 4 inliers at this inlier ratio with the target confidence
+            let ratio = count as f64 / n as f64;
+            let needed = (1.0 - RANSAC_CONFIDENCE).ln() / (1.0 - ratio.powi(4)).ln();
+            if needed.is_finite() {
+                iterations = iterations.min(needed.ceil().max(1.0) as usize);
+            } else if ratio >= 1.0 {
+                iterations = i;
+            }
+            best = Some((h, mask, count));
+        }
+    }
+
+    let (h, mask, _) = best?;
+    let s: Vec<Point> = matching(src, &mask).collect();
+    let d: Vec<Point> = matching(dst, &mask).collect();
+    let refined = dlt(&s, &d).unwrap_or(h);
+    let refined_mask = inlier_mask(&refined, src, dst, threshold);
+    Some((refined, refined_mask))
+}
+
+fn check_pairs(src_points: &[f32], dst_points: &[f32]) -> Result<(), ScanError> {
+    if src_points.len() != dst_points.len() || !src_points.len().is_multiple_of(2) {
+        return Err(ScanError::InvalidPoints("Source and destination points must be the same number of x, y pairs"));
+    }
+    Ok(())
+}
+
+/// A homography found by `find_homography`.
+#[wasm_bindgen]
+pub struct HomographyResult {
+    matrix: Option<Matrix>,
+    inliers: Vec<bool>,
+}
+
+#[wasm_bindgen]
+impl HomographyResult {
+    /// Row-major 3x3 matrix mapping source onto destination points, or an empty
+    /// Vec when no homography was found.
+    pub fn matrix(&self) -> Vec<f32> {
+        self.matrix.map(|h| h.iter().map(|&v| v as f32).collect()).unwrap_or_default()
+    }
+
+    /// One byte per point pair, 1 for inliers and 0 for outliers.
+    pub fn inliers(&self) -> Vec<u8> {
+        self.inliers.iter().map(|&m| m as u8).collect()
+    }
+
+    pub fn inlier_count(&self) -> usize {
+        self.inliers.iter().filter(|&&m| m).count()
+    }
+}
+
+/// Least-squares homography over all point pairs (normalized DLT), e.g. for
+/// more than 4 exact correspondences.
+///
+/// # Arguments
+/// * `src_points` - Source points as flat x, y pairs, at least 4
+/// * `dst_points` - Matching destination points
+///
+/// # Returns
+/// Row-major 3x3 matrix as Vec<f32>, or an empty Vec for fewer than 4 pairs or
+/// degenerate points
+#[wasm_bindgen]
+pub fn homography_dlt(src_points: &[f32], dst_points: &[f32]) -> Result<Vec<f32>, ScanError> {
+    check_pairs(src_points, dst_points)?;
+    Ok(match dlt(&to_points(src_points), &to_points(dst_points)) {
+        Some(h) => h.iter().map(|&v| v as f32).collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Robust homography from noisy correspondences with outliers, e.g. feature
+/// matches between frames for stabilization: RANSAC over 4-pair samples, then a
+/// normalized DLT refit on the inliers.
+///
+/// # Arguments
+/// * `src_points` - Source points as flat x, y pairs
+/// * `dst_points` - Matching destination points
+/// * `threshold` - Largest reprojection error in pixels for an inlier
+/// * `max_iterations` - Upper bound on RANSAC samples
+///
+/// # Returns
+/// The matrix and the inlier mask
+#[wasm_bindgen]
+pub fn find_homography(
+    src_points: &[f32],
+    dst_points: &[f32],
+    threshold: f32,
+    max_iterations: usize,
+) -> Result<HomographyResult, ScanError> {
+    check_pairs(src_points, dst_points)?;
+    let (src, dst) = (to_points(src_points), to_points(dst_points));
+    Ok(match ransac(&src, &dst, threshold as f64, max_iterations) {
+        Some((h, mask)) => HomographyResult { matrix: Some(h), inliers: mask },
+        None => HomographyResult { matrix: None, inliers: vec![false; src.len()] },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dlt_and_ransac() {
+        let h = [1.2, 0.1, 15.0, -0.05, 0.9, 8.0, 0.0004, -0.0002, 1.0];
+        let src: Vec<f32> = (0..30).flat_map(|i| [(i % 6) as f32 * 40.0 + 3.0, (i / 6) as f32 * 35.0 + (i % 4) as f32]).collect();
+        let mut dst: Vec<f32> = src
+            .chunks_exact(2)
+            .flat_map(|p| {
+                let (x, y) = project(&h, (p[0] as f64, p[1] as f64)).unwrap();
+                [x as f32, y as f32]
+            })
+            .collect();
+
+        let exact = homography_dlt(&src, &dst).unwrap();
+        assert!(exact.iter().zip(&h).all(|(a, b)| (*a as f64 - b).abs() < 1e-4));
+
+        // Every fifth pair is an outlier
+        for i in (0..30).step_by(5) {
+            dst[2 * i] += 50.0;
+        }
+        let result = find_homography(&src, &dst, 2.0, 500).unwrap();
+        assert_eq!(result.inlier_count(), 24);
+        assert!(result.inliers().iter().enumerate().all(|(i, &m)| m == (i % 5 != 0) as u8));
+        assert!(result.matrix().iter().zip(&h).all(|(a, b)| (*a as f64 - b).abs() < 1e-4));
+
+        assert!(find_homography(&src[..6], &dst[..6], 2.0, 10).unwrap().matrix().is_empty());
+    }
+}
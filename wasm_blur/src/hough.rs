@@ -0,0 +1,359 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+use core::f32::consts::PI;
+
+// Hough accumulator over (rho, theta). Rho covers [-diagonal, diagonal] so every
+// line through the image has a cell; theta covers [0, pi).
+struct Accumulator {
+    votes: Vec<u32>,
+    num_rho: usize,
+    num_theta: usize,
+    rho_res: f32,
+    rho_offset: f32,
+    theta_step: f32,
+    cos: Vec<f32>,
+    sin: Vec<f32>,
+}
+
+impl Accumulator {
+    fn new(width: usize, height: usize, rho_res: f32, theta_res: f32) -> Result<Accumulator, ScanError> {
+        if rho_res <= 0.0 || theta_res <= 0.0 {
+            return Err(ScanError::InvalidParameter("Rho and theta resolution must be greater than 0"));
+        }
+        let diagonal = ((width * width + height * height) as f32).sqrt();
+        let num_rho = (2.0 * diagonal / rho_res).ceil() as usize + 1;
+        // Whole number of theta steps over [0, pi)
+        let num_theta = ((PI / theta_res).round() as usize).max(1);
+        let theta_step = PI / num_theta as f32;
+        let (sin, cos) = (0..num_theta).map(|t| (t as f32 * theta_step).sin_cos()).unzip();
+        Ok(Accumulator {
+            votes: vec![0; num_rho * num_theta],
+            num_rho,
+            num_theta,
+            rho_res,
+            rho_offset: diagonal / rho_res,
+            theta_step,
+            cos,
+            sin,
+        })
+    }
+
+    #[inline]
+    fn rho_index(&self, x: usize, y: usize, t: usize) -> usize {
+        let rho = x as f32 * self.cos[t] + y as f32 * self.sin[t];
+        (rho / self.rho_res + self.rho_offset).round() as usize
+    }
+
+    // Adds `delta` (+1 or -1) for every theta; returns the best (votes, theta) after voting.
+    fn vote(&mut self, x: usize, y: usize, delta: i32) -> (u32, usize) {
+        let mut best = (0, 0);
+        for t in 0..self.num_theta {
+            let idx = self.rho_index(x, y, t) * self.num_theta + t;
+            let cell = &mut self.votes[idx];
+            *cell = cell.wrapping_add_signed(delta);
+            if *cell > best.0 {
+                best = (*cell, t);
+            }
+        }
+        best
+    }
+
+    fn rho(&self, r: usize) -> f32 {
+        (r as f32 - self.rho_offset) * self.rho_res
+    }
+}
+
+/// Standard Hough transform over an edge map (non-zero pixels are edges).
+///
+/// # Arguments
+/// * `edges` - Edge map, e.g. the Canny output
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `rho_res` - Distance resolution in pixels
+/// * `theta_res` - Angle resolution in radians
+/// * `threshold` - Minimum votes for a line
+/// * `max_lines` - Maximum number of lines returned (strongest first)
+///
+/// # Returns
+/// Flat `[rho, theta, votes]` triples; each line is x * cos(theta) + y * sin(theta) = rho
+#[wasm_bindgen]
+pub fn hough_lines(
+    edges: &[u8],
+    width: usize,
+    height: usize,
+    rho_res: f32,
+    theta_res: f32,
+    threshold: u32,
+    max_lines: usize,
+) -> Result<Vec<f32>, ScanError> {
+    check_gray(edges, width, height)?;
+    let mut acc = Accumulator::new(width, height, rho_res, theta_res)?;
+    for y in 0..height {
+        for x in 0..width {
+            if edges[y * width + x] != 0 {
+                acc.vote(x, y, 1);
+            }
+        }
+    }
+
+    // Peaks: cells at or above the threshold that are maxima of their 3x3 neighbourhood
+    let (num_rho, num_theta) = (acc.num_rho, acc.num_theta);
+    let mut peaks = Vec::new();
+    for r in 0..num_rho {
+        for t in 0..num_theta {
+            let v = acc.votes[r * num_theta + t];
+            if v < threshold.max(1) {
+                continue;
+            }
+            let mut is_max = true;
+            for nr in r.saturating_sub(1)..(r + 2).min(num_rho) {
+                for nt in t.saturating_sub(1)..(t + 2).min(num_theta) {
+                    let n = acc.votes[nr * num_theta + nt];
+                    // Ties go to the first cell so a flat plateau yields one line
+                    if n > v || (n == v && (nr, nt) < (r, t)) {
+                        is_max = false;
+                    }
+                }
+            }
+            if is_max {
+                peaks.push((v, r, t));
+            }
+        }
+    }
+    peaks.sort_by(|a, b| b.0.cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    Ok(peaks
+        .iter()
+        .take(max_lines)
+        .flat_map(|&(v, r, t)| [acc.rho(r), t as f32 * acc.theta_step, v as f32])
+        .collect())
+}
+
+// Pixel states for the probabilistic transform.
+const PENDING: u8 = 1;
+const VOTED: u8 = 2;
+
+// Walks from (x, y) along (dx, dy) while the gap between edge pixels stays within
+// `max_gap`, calling `visit` for every remaining edge pixel. Returns the last one.
+fn walk_line(
+    state: &[u8],
+    width: usize,
+    height: usize,
+    (x, y): (usize, usize),
+    (dx, dy): (f32, f32),
+    max_gap: usize,
+    mut visit: impl FnMut(usize),
+) -> (usize, usize) {
+    let mut end = (x, y);
+    let (mut px, mut py) = (x as f32, y as f32);
+    let mut gap = 0;
+    loop {
+        px += dx;
+        py += dy;
+        let (ix, iy) = (px.round(), py.round());
+        if ix < 0.0 || iy < 0.0 || ix >= width as f32 || iy >= height as f32 {
+            break;
+        }
+        let idx = iy as usize * width + ix as usize;
+        if state[idx] != 0 {
+            visit(idx);
+            end = (ix as usize, iy as usize);
+            gap = 0;
+        } else {
+            gap += 1;
+            if gap > max_gap {
+                break;
+            }
+        }
+    }
+    end
+}
+
+/// Probabilistic Hough transform: finds line segments instead of infinite lines.
+/// Edge pixels are processed in a fixed pseudo-random order, so the result is
+/// deterministic for a given input.
+///
+/// # Arguments
+/// * `edges` - Edge map, e.g. the Canny output
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `rho_res` - Distance resolution in pixels
+/// * `theta_res` - Angle resolution in radians
+/// * `threshold` - Minimum votes before a segment is traced
+/// * `min_line_length` - Shorter segments are discarded
+/// * `max_line_gap` - Largest gap (in pixels) bridged within one segment
+///
+/// # Returns
+/// Flat `[x1, y1, x2, y2]` segment endpoints
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn hough_lines_p(
+    edges: &[u8],
+    width: usize,
+    height: usize,
+    rho_res: f32,
+    theta_res: f32,
+    threshold: u32,
+    min_line_length: f32,
+    max_line_gap: usize,
+) -> Result<Vec<f32>, ScanError> {
+    check_gray(edges, width, height)?;
+    let mut acc = Accumulator::new(width, height, rho_res, theta_res)?;
+    let mut state: Vec<u8> = edges.iter().map(|&e| if e != 0 { PENDING } else { 0 }).collect();
+    let mut points: Vec<usize> = (0..state.len()).filter(|&i| state[i] != 0).collect();
+
+    // Fisher-Yates shuffle with a fixed LCG seed
+    let mut seed = 0x2545_f491u32;
+    for i in (1..points.len()).rev() {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        points.swap(i, (seed >> 8) as usize % (i + 1));
+    }
+
+    let mut segments = Vec::new();
+    let mut on_line = Vec::new();
+    for &p in &points {
+        if state[p] != PENDING {
+            continue;
+        }
+        let (x, y) = (p % width, p / width);
+        state[p] = VOTED;
+        let (votes, t) = acc.vote(x, y, 1);
+        if votes < threshold.max(1) {
+            continue;
+        }
+
+        // Trace the line through (x, y) in both directions, stepping one pixel
+        // along the dominant axis
+        let angle = t as f32 * acc.theta_step;
+        let (dx, dy) = (-angle.sin(), angle.cos());
+        let step = dx.abs().max(dy.abs());
+        let (dx, dy) = (dx / step, dy / step);
+        on_line.clear();
+        on_line.push(p);
+        let start = walk_line(&state, width, height, (x, y), (-dx, -dy), max_line_gap, |i| on_line.push(i));
+        let end = walk_line(&state, width, height, (x, y), (dx, dy), max_line_gap, |i| on_line.push(i));
+
+        let length = (end.0 as f32 - start.0 as f32).hypot(end.1 as f32 - start.1 as f32);
+        let good = length >= min_line_length;
+        // Pixels on the traced line are consumed either way; only a kept segment
+        // takes its votes back out of the accumulator
+        for &i in &on_line {
+            if good && state[i] == VOTED {
+                acc.vote(i % width, i / width, -1);
+            }
+            state[i] = 0;
+        }
+        if good {
+            segments.extend_from_slice(&[start.0 as f32, start.1 as f32, end.0 as f32, end.1 as f32]);
+        }
+    }
+    Ok(segments)
+}
+
+// Intersection of two lines in (rho, theta) form.
+fn intersect((r1, t1): (f32, f32), (r2, t2): (f32, f32)) -> Option<(f32, f32)> {
+    let (s1, c1) = t1.sin_cos();
+    let (s2, c2) = t2.sin_cos();
+    let det = c1 * s2 - s1 * c2;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    Some(((r1 * s2 - r2 * s1) / det, (c1 * r2 - c2 * r1) / det))
+}
+
+/// Builds a quadrilateral candidate from Hough lines: the two strongest
+/// near-horizontal and the two strongest near-vertical lines that are at least
+/// `min_separation` (fraction of the image size) apart are intersected. Works when
+/// part of the document border is occluded, since each side only needs to be
+/// visible somewhere along its length.
+///
+/// # Arguments
+/// * `lines` - `[rho, theta, votes]` triples, e.g. from `hough_lines`
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `min_separation` - Minimum distance between opposite sides, as a fraction of
+///   the image width (left/right) or height (top/bottom)
+///
+/// # Returns
+/// Corners as [TL.x, TL.y, TR.x, TR.y, BR.x, BR.y, BL.x, BL.y], or an empty vector
+/// when no quadrilateral could be formed
+#[wasm_bindgen]
+pub fn quad_from_lines(lines: &[f32], width: usize, height: usize, min_separation: f32) -> Vec<f32> {
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let mut lines: Vec<(f32, f32, f32)> = lines.chunks_exact(3).map(|l| (l[0], l[1], l[2])).collect();
+    lines.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    // Position of each line across the image: y at the centre column for
+    // near-horizontal lines (normal close to 90 degrees), x at the centre row otherwise
+    let mut horizontal: Vec<(f32, f32, f32)> = Vec::new();
+    let mut vertical: Vec<(f32, f32, f32)> = Vec::new();
+    for &(rho, theta, _) in &lines {
+        let (sin, cos) = theta.sin_cos();
+        let (group, position, min_gap) = if sin.abs() > cos.abs() {
+            (&mut horizontal, (rho - cx * cos) / sin, min_separation * height as f32)
+        } else {
+            (&mut vertical, (rho - cy * sin) / cos, min_separation * width as f32)
+        };
+        if group.len() < 2 && group.iter().all(|&(_, _, p)| (p - position).abs() >= min_gap) {
+            group.push((rho, theta, position));
+        }
+    }
+    if horizontal.len() < 2 || vertical.len() < 2 {
+        return Vec::new();
+    }
+
+    let by_position = |a: &(f32, f32, f32), b: &(f32, f32, f32)| a.2.total_cmp(&b.2);
+    horizontal.sort_by(by_position);
+    vertical.sort_by(by_position);
+    let (top, bottom) = ((horizontal[0].0, horizontal[0].1), (horizontal[1].0, horizontal[1].1));
+    let (left, right) = ((vertical[0].0, vertical[0].1), (vertical[1].0, vertical[1].1));
+
+    let mut quad = Vec::with_capacity(8);
+    for (a, b) in [(top, left), (top, right), (bottom, right), (bottom, left)] {
+        match intersect(a, b) {
+            Some((x, y)) => quad.extend_from_slice(&[x, y]),
+            None => return Vec::new(),
+        }
+    }
+    quad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hough_finds_rectangle_with_occluded_side() {
+        let (width, height) = (80, 60);
+        let mut edges = vec![0u8; width * height];
+        for x in 10..70 {
+            edges[10 * width + x] = 255;
+            edges[50 * width + x] = 255;
+        }
+        for y in 10..51 {
+            edges[y * width + 10] = 255;
+            // Right side partly hidden by a "finger"
+            if !(25..40).contains(&y) {
+                edges[y * width + 69] = 255;
+            }
+        }
+
+        let lines = hough_lines(&edges, width, height, 1.0, PI / 180.0, 20, 8).unwrap();
+        let quad = quad_from_lines(&lines, width, height, 0.2);
+        let expected = [10.0, 10.0, 69.0, 10.0, 69.0, 50.0, 10.0, 50.0];
+        assert_eq!(quad.len(), 8);
+        for (c, e) in quad.iter().zip(expected) {
+            assert!((c - e).abs() < 1.5, "{quad:?}");
+        }
+
+        // The two right-side pieces are shorter than min_line_length
+        let segments = hough_lines_p(&edges, width, height, 1.0, PI / 180.0, 15, 20.0, 3).unwrap();
+        assert_eq!(segments.len(), 3 * 4, "{segments:?}");
+        assert!(segments.chunks_exact(4).all(|s| (s[2] - s[0]).hypot(s[3] - s[1]) >= 38.0));
+    }
+}
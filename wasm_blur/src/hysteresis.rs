@@ -1,5 +1,10 @@
 use wasm_bindgen::prelude::*;
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+
 /// Applies double thresholding and hysteresis using a stack-based approach.
 /// Optimized version with SIMD for threshold comparisons and better memory access patterns.
 /// Follows OpenCV's logic more closely.
@@ -20,9 +25,13 @@ pub fn hysteresis_thresholding(
     height: usize,
     low_threshold: f32,
     high_threshold: f32,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, ScanError> {
+    check_len(suppressed.len(), image_len(width, height, 1)?, "width * height")?;
     // Map values: 0 = weak edge (potential), 1 = non-edge, 2 = strong edge
     let mut edge_map = vec![1u8; width * height]; // Initialize all as non-edge
+    if width < 3 || height < 3 {
+        return Ok(edge_map); // No inner pixels
+    }
     let mut stack = Vec::with_capacity(1024); // Pre-allocate with reasonable capacity
     
     // SIMD-optimized first pass: Identify strong edges and potential weak edges
@@ -108,7 +117,7 @@ pub fn hysteresis_thresholding(
         }
     }
     
-    edge_map
+    Ok(edge_map)
 }
 
 /// Creates a binary edge image from the hysteresis edge map
@@ -145,6 +154,62 @@ pub fn edge_map_to_binary(edge_map: &[u8]) -> Vec<u8> {
     binary
 }
 
+/// Creates an anti-aliased edge visualization from the hysteresis edge map
+/// Edge pixels are weighted by their gradient strength and spread with a 3x3 tent
+/// filter, so the overlay stays smooth when composited at non-integer scale factors
+/// 
+/// # Arguments
+/// * `edge_map` - Edge map from hysteresis thresholding (0, 1, 2 values)
+/// * `suppressed` - Suppressed magnitude values used for the edge strength
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `high_threshold` - High threshold value, edges at or above it render at full strength
+/// 
+/// # Returns
+/// Soft edge image as Vec<u8> (0 to 255)
+#[wasm_bindgen]
+pub fn edge_map_to_soft(
+    edge_map: &[u8],
+    suppressed: &[f32],
+    width: usize,
+    height: usize,
+    high_threshold: f32,
+) -> Result<Vec<u8>, ScanError> {
+    check_len(edge_map.len(), image_len(width, height, 1)?, "width * height")?;
+    check_len(suppressed.len(), image_len(width, height, 1)?, "width * height")?;
+    let size = width * height;
+    let mut core = vec![0u16; size];
+    let inv_high = if high_threshold > 0.0 { 1.0 / high_threshold } else { 1.0 };
+
+    // Weak edges promoted by hysteresis render dimmer than strong ones, but never
+    // below half intensity so connected contours don't visibly break up.
+    for i in 0..size {
+        if edge_map[i] == 2 {
+            let strength = (suppressed[i] * inv_high).clamp(0.5, 1.0);
+            core[i] = (strength * 255.0 + 0.5) as u16;
+        }
+    }
+
+    // 3x3 tent filter (1 2 1 / 2 4 2 / 1 2 1), keeping the core value on the edge itself
+    let mut soft = vec![0u8; size];
+    for y in 0..height {
+        let y0 = y.saturating_sub(1);
+        let y1 = (y + 1).min(height - 1);
+        for x in 0..width {
+            let x0 = x.saturating_sub(1);
+            let x1 = (x + 1).min(width - 1);
+            let row = |yy: usize| {
+                core[yy * width + x0] as u32 + 2 * core[yy * width + x] as u32 + core[yy * width + x1] as u32
+            };
+            let blurred = (row(y0) + 2 * row(y) + row(y1) + 8) >> 4;
+            let idx = y * width + x;
+            soft[idx] = blurred.max(core[idx] as u32).min(255) as u8;
+        }
+    }
+
+    Ok(soft)
+}
+
 /// Combined hysteresis thresholding and binary conversion
 /// This is a convenience function that combines both steps for efficiency
 /// Optimized to avoid intermediate allocations where possible
@@ -165,9 +230,13 @@ pub fn hysteresis_thresholding_binary(
     height: usize,
     low_threshold: f32,
     high_threshold: f32,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, ScanError> {
+    check_len(suppressed.len(), image_len(width, height, 1)?, "width * height")?;
     // Optimized version that directly produces binary output without intermediate edge map
     let mut binary = vec![0u8; width * height];
+    if width < 3 || height < 3 {
+        return Ok(binary); // No inner pixels
+    }
     let mut edge_map = vec![1u8; width * height]; // Temporary edge map for hysteresis
     let mut stack = Vec::with_capacity(1024);
     
@@ -221,7 +290,7 @@ pub fn hysteresis_thresholding_binary(
         }
     }
     
-    binary
+    Ok(binary)
 }
 
 #[cfg(test)]
@@ -245,7 +314,7 @@ mod tests {
         let low_threshold = 75.0;
         let high_threshold = 200.0;
         
-        let edge_map = hysteresis_thresholding(&suppressed, width, height, low_threshold, high_threshold);
+        let edge_map = hysteresis_thresholding(&suppressed, width, height, low_threshold, high_threshold).unwrap();
         
         // Center pixel should be strong edge (2)
         assert_eq!(edge_map[12], 2);
@@ -285,7 +354,7 @@ mod tests {
         let low_threshold = 75.0;
         let high_threshold = 200.0;
         
-        let binary = hysteresis_thresholding_binary(&suppressed, width, height, low_threshold, high_threshold);
+        let binary = hysteresis_thresholding_binary(&suppressed, width, height, low_threshold, high_threshold).unwrap();
         
         // Center and connected pixels should be 255
         assert_eq!(binary[12], 255); // center
@@ -313,7 +382,7 @@ mod tests {
         let low_threshold = 75.0;
         let high_threshold = 200.0;
         
-        let binary = hysteresis_thresholding_binary(&suppressed, width, height, low_threshold, high_threshold);
+        let binary = hysteresis_thresholding_binary(&suppressed, width, height, low_threshold, high_threshold).unwrap();
         
         // Should have some edges
         let edge_count = binary.iter().filter(|&&x| x == 255).count();
@@ -0,0 +1,157 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+use crate::histogram::{luma_histogram, percentile_levels};
+use crate::output::{apply_output_mode_into, OutputMode};
+
+/// When to invert a page before enhancement and binarization, for white text on
+/// a dark background (slides, chalkboards, dark-mode screens) and film
+/// negatives. Both assume dark ink on light paper and otherwise turn such pages
+/// into black sheets.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InversionMode {
+    /// Never invert (the default)
+    #[default]
+    Off = 0,
+    /// Invert pages `detect_inverted` flags
+    Auto = 1,
+    /// Invert every page, e.g. for a roll of negatives
+    Always = 2,
+}
+
+// Percentage of the darkest and brightest pixels ignored when measuring the
+// page's tonal range.
+const CLIP_PCT: f32 = 2.0;
+// A page is inverted when its median luma, the background, lies within this
+// share of the tonal range above the darkest level...
+const BACKGROUND_SHARE: f32 = 1.0 / 3.0;
+// ...and the range is wide enough to hold text at all.
+const MIN_CONTRAST: u8 = 32;
+
+// Whether an RGBA page shows light content on a dark background. Paper covers
+// most of a page, so the median luma is the background level: near the bright
+// end of the range for a regular page, near the dark end for an inverted one.
+// Dark paper with darker ink keeps its median well above the ink.
+pub(crate) fn page_is_inverted(rgba: &[u8]) -> bool {
+    let histogram = luma_histogram(rgba);
+    let (dark, light) = percentile_levels(&histogram, CLIP_PCT, CLIP_PCT);
+    let (median, _) = percentile_levels(&histogram, 50.0, 50.0);
+    let range = light.saturating_sub(dark);
+    range >= MIN_CONTRAST && ((median.saturating_sub(dark)) as f32) < range as f32 * BACKGROUND_SHARE
+}
+
+// Whether `mode` inverts this page.
+pub(crate) fn should_invert(rgba: &[u8], mode: InversionMode) -> bool {
+    match mode {
+        InversionMode::Off => false,
+        InversionMode::Auto => page_is_inverted(rgba),
+        InversionMode::Always => true,
+    }
+}
+
+// Inverts the colour channels in place; alpha is kept.
+pub(crate) fn invert_into(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        for v in &mut px[..3] {
+            *v = 255 - *v;
+        }
+    }
+}
+
+/// Detects inverted content: light text or lines on a dark background, or a
+/// film negative. The page's median luma (its background) has to lie in the
+/// darkest third of its tonal range.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels of the rectified page
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// True if the page is inverted
+#[wasm_bindgen]
+pub fn detect_inverted(rgba: &[u8], width: usize, height: usize) -> Result<bool, ScanError> {
+    check_rgba(rgba, width, height)?;
+    Ok(page_is_inverted(rgba))
+}
+
+/// `apply_output_mode` for pages that may be inverted. The page is inverted
+/// first, so the output mode sees dark ink on light paper, and optionally
+/// inverted back afterwards: a slide then keeps its white-on-black look with
+/// clean black-and-white output, while a negative stays positive.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels, typically the output of `warp_perspective`
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `mode` - Colour, grayscale or black and white
+/// * `inversion` - Whether to invert before the output mode
+/// * `restore_polarity` - Invert inverted pages back after the output mode
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn apply_output_mode_with_inversion(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    mode: OutputMode,
+    inversion: InversionMode,
+    restore_polarity: bool,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut out = rgba.to_vec();
+    let inverted = should_invert(&out, inversion);
+    if inverted {
+        invert_into(&mut out);
+    }
+    apply_output_mode_into(&mut out, width, height, mode);
+    if inverted && restore_polarity {
+        invert_into(&mut out);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverted_page_output() {
+        // Light text rows on a dark slide, and the same page printed normally
+        let (width, height) = (64usize, 48usize);
+        let text = |i: usize| (i / width) % 8 < 2 && i % 7 < 5;
+        let slide: Vec<u8> = (0..width * height)
+            .flat_map(|i| if text(i) { [220, 225, 230, 255] } else { [30, 35, 40, 255] })
+            .collect();
+        let mut page = slide.clone();
+        invert_into(&mut page);
+        assert!(detect_inverted(&slide, width, height).unwrap());
+        assert!(!detect_inverted(&page, width, height).unwrap());
+        // Dark paper with darker ink is not inverted, neither is a flat frame
+        let dark_paper: Vec<u8> =
+            (0..width * height).flat_map(|i| if text(i) { [5, 5, 5, 255] } else { [70, 70, 70, 255] }).collect();
+        assert!(!detect_inverted(&dark_paper, width, height).unwrap());
+        assert!(!detect_inverted(&[30u8; 64], 4, 4).unwrap());
+
+        let bw = |rgba: &[u8], inversion, restore| {
+            apply_output_mode_with_inversion(rgba, width, height, OutputMode::BlackAndWhite, inversion, restore).unwrap()
+        };
+        let binary = |out: &[u8]| -> Vec<bool> { out.chunks_exact(4).map(|p| p[0] == 255).collect() };
+        let paper: Vec<bool> = (0..width * height).map(|i| !text(i)).collect();
+        // Inverted, the slide binarizes like the printed page, and restoring the
+        // polarity gives white text on black
+        let expected = binary(&bw(&page, InversionMode::Off, false));
+        assert_eq!(expected, paper);
+        assert_eq!(binary(&bw(&slide, InversionMode::Auto, false)), expected);
+        let restored = binary(&bw(&slide, InversionMode::Auto, true));
+        assert!(restored.iter().zip(&expected).all(|(r, e)| r != e));
+        // Auto leaves the printed page alone
+        assert_eq!(bw(&page, InversionMode::Auto, true), bw(&page, InversionMode::Off, true));
+        assert_eq!(bw(&page, InversionMode::Always, false), bw(&slide, InversionMode::Off, false));
+    }
+}
@@ -0,0 +1,161 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_rgba, ScanError};
+
+use jpeg_encoder::{ChromaSubsamplingMethod, ColorType, Encoder, SamplingFactor};
+
+// Largest image side a baseline JPEG header can hold.
+const MAX_SIDE: usize = u16::MAX as usize;
+// Quality used for 0, which keeps text free of visible ringing.
+const DEFAULT_QUALITY: u8 = 85;
+
+/// Chroma resolution of an encoded JPEG. Lower resolutions save about a third
+/// of the size on photos, but smear coloured text and stamps.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// Full chroma resolution, for colour documents with fine coloured print
+    Yuv444 = 0,
+    /// Half horizontal chroma resolution
+    Yuv422 = 1,
+    /// Half horizontal and vertical chroma resolution, as cameras and canvas
+    /// `toBlob` use
+    Yuv420 = 2,
+}
+
+impl ChromaSubsampling {
+    fn factor(self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+fn encode(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    color: ColorType,
+    quality: u8,
+    subsampling: ChromaSubsampling,
+) -> Result<Vec<u8>, ScanError> {
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(ScanError::InvalidParameter("JPEG width and height must be 1 to 65535"));
+    }
+    if !(1..=100).contains(&quality) {
+        return Err(ScanError::InvalidParameter("JPEG quality must be 1 to 100"));
+    }
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, quality);
+    encoder.set_sampling_factor(subsampling.factor());
+    // Averaged chroma keeps thin coloured strokes from aliasing
+    encoder.set_chroma_subsampling_method(ChromaSubsamplingMethod::Average);
+    encoder
+        .encode(data, width as u16, height as u16, color)
+        .map_err(|_| ScanError::InvalidParameter("JPEG encoding failed"))?;
+    Ok(out)
+}
+
+/// Encodes an RGBA image as a baseline JPEG with 4:2:0 chroma subsampling.
+/// Encoding in the module avoids the extra generation loss and the unknown
+/// quality of re-encoding through a canvas or a PDF library. Alpha is
+/// dropped.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width, up to 65535
+/// * `height` - Image height, up to 65535
+/// * `quality` - 1 to 100; 0 picks 85
+///
+/// # Returns
+/// JPEG file bytes
+#[wasm_bindgen]
+pub fn encode_jpeg(rgba: &[u8], width: usize, height: usize, quality: u8) -> Result<Vec<u8>, ScanError> {
+    encode_jpeg_subsampled(rgba, width, height, quality, ChromaSubsampling::Yuv420)
+}
+
+/// Encodes an RGBA image as a baseline JPEG with the given chroma
+/// subsampling, see `encode_jpeg`.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width, up to 65535
+/// * `height` - Image height, up to 65535
+/// * `quality` - 1 to 100; 0 picks 85
+/// * `subsampling` - Chroma resolution
+///
+/// # Returns
+/// JPEG file bytes
+#[wasm_bindgen]
+pub fn encode_jpeg_subsampled(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    quality: u8,
+    subsampling: ChromaSubsampling,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let quality = if quality == 0 { DEFAULT_QUALITY } else { quality };
+    encode(rgba, width, height, ColorType::Rgba, quality, subsampling)
+}
+
+/// Encodes a grayscale image as a single-channel JPEG, smaller than the same
+/// page encoded as colour.
+///
+/// # Arguments
+/// * `gray` - Grayscale pixels
+/// * `width` - Image width, up to 65535
+/// * `height` - Image height, up to 65535
+/// * `quality` - 1 to 100; 0 picks 85
+///
+/// # Returns
+/// JPEG file bytes
+#[wasm_bindgen]
+pub fn encode_jpeg_gray(gray: &[u8], width: usize, height: usize, quality: u8) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let quality = if quality == 0 { DEFAULT_QUALITY } else { quality };
+    encode(gray, width, height, ColorType::Luma, quality, ChromaSubsampling::Yuv444)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Width, height and component sampling factors from the baseline frame header.
+    fn frame_components(jpeg: &[u8]) -> (usize, usize, Vec<u8>) {
+        let sof = jpeg.windows(2).position(|w| w == [0xff, 0xc0]).unwrap();
+        let header = &jpeg[sof + 4..];
+        let height = u16::from_be_bytes([header[1], header[2]]) as usize;
+        let width = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let samplings = (0..header[5] as usize).map(|c| header[6 + 3 * c + 1]).collect();
+        (width, height, samplings)
+    }
+
+    #[test]
+    fn test_encode_jpeg() {
+        let (width, height) = (50, 30);
+        let rgba: Vec<u8> =
+            (0..width * height).flat_map(|i| [(i % width * 5) as u8, (i / width * 8) as u8, 90, 255]).collect();
+
+        let jpeg = encode_jpeg(&rgba, width, height, 0).unwrap();
+        assert_eq!((&jpeg[..2], &jpeg[jpeg.len() - 2..]), (&[0xff, 0xd8][..], &[0xff, 0xd9][..]));
+        assert_eq!(frame_components(&jpeg), (50, 30, vec![0x22, 0x11, 0x11]));
+        let full = encode_jpeg_subsampled(&rgba, width, height, 85, ChromaSubsampling::Yuv444).unwrap();
+        assert_eq!(frame_components(&full).2, vec![0x11, 0x11, 0x11]);
+        assert!(full.len() > jpeg.len());
+        let low = encode_jpeg(&rgba, width, height, 20).unwrap();
+        assert!(low.len() < jpeg.len());
+
+        let gray: Vec<u8> = rgba.chunks_exact(4).map(|px| px[0]).collect();
+        assert_eq!(frame_components(&encode_jpeg_gray(&gray, width, height, 90).unwrap()), (50, 30, vec![0x11]));
+
+        assert!(encode_jpeg(&rgba, width, height, 101).is_err());
+        assert!(encode_jpeg(&[], 0, 0, 85).is_err());
+        assert!(encode_jpeg(&rgba[4..], width, height, 85).is_err());
+    }
+}
@@ -1,9 +1,162 @@
+// Without the `std` feature the algorithm core is `no_std + alloc` (embedded targets)
+#![cfg_attr(not(feature = "std"), no_std)]
+// memory64 builds (wasm64-unknown-unknown, nightly) need the unstable wasm64 SIMD module
+#![cfg_attr(target_arch = "wasm64", feature(simd_wasm64))]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
 pub mod non_maximum_suppression;
 pub mod dilation;
 pub mod gradient_calculation;
 pub mod canny;
 pub mod gaussian_blur;
+pub mod border;
 pub mod hysteresis;
+#[cfg(feature = "warp")]
+pub mod warp;
+#[cfg(feature = "tracker")]
+pub mod tracker;
+#[cfg(feature = "session")]
+pub mod session;
+pub mod grayscale;
+pub mod context;
+pub mod dispatch;
+pub mod scheduler;
+pub mod warnings;
+pub mod error;
+#[cfg(feature = "resize")]
+pub mod resize;
+#[cfg(feature = "pyramid")]
+pub mod pyramid;
+#[cfg(feature = "hough")]
+pub mod hough;
+#[cfg(feature = "formats")]
+pub mod pixel_format;
+#[cfg(feature = "quad_scoring")]
+pub mod quad_scoring;
+#[cfg(feature = "corners")]
+pub mod corners;
+#[cfg(feature = "channels")]
+pub mod channels;
+#[cfg(feature = "geometry")]
+pub mod geometry;
+#[cfg(feature = "enhance")]
+pub mod enhance;
+#[cfg(feature = "deskew")]
+pub mod deskew;
+#[cfg(feature = "histogram")]
+pub mod histogram;
+#[cfg(feature = "orientation")]
+pub mod orientation;
+#[cfg(feature = "roi")]
+pub mod roi;
+#[cfg(feature = "homography")]
+pub mod homography;
+#[cfg(feature = "dewarp")]
+pub mod dewarp;
+#[cfg(feature = "binarize")]
+pub mod binarize;
+#[cfg(feature = "preset")]
+pub mod preset;
+#[cfg(feature = "output")]
+pub mod output;
+#[cfg(feature = "fusion")]
+pub mod fusion;
+#[cfg(feature = "tone")]
+pub mod tone;
+#[cfg(feature = "history")]
+mod history;
+#[cfg(feature = "colorspace")]
+pub mod colorspace;
+#[cfg(feature = "analytics")]
+pub mod analytics;
+#[cfg(feature = "color_edges")]
+pub mod color_edges;
+#[cfg(feature = "shake")]
+pub mod shake;
+#[cfg(feature = "rolling_shutter")]
+pub mod rolling_shutter;
+#[cfg(feature = "low_light")]
+pub mod low_light;
+#[cfg(feature = "flash")]
+pub mod flash;
+#[cfg(feature = "pipeline_debug")]
+pub mod pipeline_debug;
+#[cfg(feature = "screen")]
+pub mod screen;
+#[cfg(feature = "detection")]
+pub mod detection;
+#[cfg(feature = "inversion")]
+pub mod inversion;
+#[cfg(feature = "readiness")]
+pub mod readiness;
+#[cfg(feature = "stamp")]
+pub mod stamp;
+#[cfg(feature = "sharpness")]
+pub mod sharpness;
+#[cfg(feature = "stitch")]
+pub mod stitch;
+#[cfg(feature = "cards")]
+pub mod cards;
+#[cfg(feature = "forms")]
+pub mod forms;
+#[cfg(feature = "lighting")]
+pub mod lighting;
+#[cfg(feature = "occlusion")]
+pub mod occlusion;
+#[cfg(feature = "tables")]
+pub mod tables;
+#[cfg(feature = "signature")]
+pub mod signature;
+#[cfg(feature = "photos")]
+pub mod photos;
+#[cfg(feature = "paper")]
+pub mod paper;
+#[cfg(feature = "dropout")]
+pub mod dropout;
+#[cfg(feature = "jpeg")]
+pub mod jpeg;
+#[cfg(feature = "banding")]
+pub mod banding;
+#[cfg(feature = "flicker")]
+pub mod flicker;
+#[cfg(feature = "png")]
+pub mod png;
+#[cfg(feature = "source")]
+pub mod source;
+#[cfg(feature = "webp")]
+pub mod webp;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+#[cfg(feature = "verify")]
+pub mod verify;
+#[cfg(feature = "ccitt")]
+pub mod ccitt;
+#[cfg(feature = "auto_dilation")]
+pub mod auto_dilation;
+#[cfg(feature = "tiff")]
+pub mod tiff;
+#[cfg(feature = "decode")]
+pub mod decode;
+#[cfg(any(feature = "preset", feature = "analytics"))]
+mod clock;
+mod arena;
+#[cfg(not(feature = "std"))]
+mod math;
+
+// What the std prelude would otherwise provide, for `no_std` builds.
+#[cfg(not(feature = "std"))]
+mod prelude {
+    pub(crate) use alloc::{boxed::Box, vec, vec::Vec};
+    pub(crate) use crate::math::Float;
+}
+
+// WebAssembly SIMD intrinsics; the same set is exposed for wasm32 and wasm64 (memory64).
+#[cfg(target_arch = "wasm32")]
+use core::arch::wasm32 as simd;
+#[cfg(target_arch = "wasm64")]
+use core::arch::wasm64 as simd;
 
 // Re-export the blur function from gaussian_blur module for backward compatibility
 pub use gaussian_blur::blur;
@@ -0,0 +1,165 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+use crate::histogram::{gray_histogram, percentile_levels};
+
+// Levels at or beyond which a pixel counts as clipped to black or white.
+const SHADOW_CLIP: u8 = 5;
+const HIGHLIGHT_CLIP: u8 = 250;
+// Percentiles spanning the dynamic range, ignoring a few specular highlights
+// and deep shadows.
+const RANGE_PERCENTILE: f32 = 1.0;
+// Mean luma range of a usable exposure (as for `capture_readiness`).
+const MIN_LUMA: f32 = 60.0;
+const MAX_LUMA: f32 = 220.0;
+// Largest difference between the brightest and darkest quadrant, in grey
+// levels, before the light counts as uneven.
+const MAX_QUADRANT_SPREAD: f32 = 60.0;
+
+/// Exposure and lighting measurements of a frame, from `lighting_report`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightingReport {
+    mean_luma: f32,
+    dynamic_range: u8,
+    shadow_clipped: f32,
+    highlight_clipped: f32,
+    quadrants: [f32; 4],
+}
+
+#[wasm_bindgen]
+impl LightingReport {
+    #[wasm_bindgen(getter)]
+    pub fn mean_luma(&self) -> f32 {
+        self.mean_luma
+    }
+
+    /// Spread between the 1st and 99th percentile grey level.
+    #[wasm_bindgen(getter)]
+    pub fn dynamic_range(&self) -> u8 {
+        self.dynamic_range
+    }
+
+    /// Share of pixels at luma 5 or below.
+    #[wasm_bindgen(getter)]
+    pub fn shadow_clipped(&self) -> f32 {
+        self.shadow_clipped
+    }
+
+    /// Share of pixels at luma 250 or above.
+    #[wasm_bindgen(getter)]
+    pub fn highlight_clipped(&self) -> f32 {
+        self.highlight_clipped
+    }
+
+    /// Mean luma of the top-left, top-right, bottom-left and bottom-right
+    /// quadrant.
+    pub fn quadrants(&self) -> Vec<f32> {
+        self.quadrants.to_vec()
+    }
+
+    /// Mean luma below 60: prompt for more light.
+    pub fn too_dark(&self) -> bool {
+        self.mean_luma < MIN_LUMA
+    }
+
+    /// Mean luma above 220: the frame is washed out.
+    pub fn too_bright(&self) -> bool {
+        self.mean_luma > MAX_LUMA
+    }
+
+    /// The brightest and darkest quadrant differ by more than 60 grey levels,
+    /// e.g. from a shadow cast over part of the page.
+    pub fn uneven(&self) -> bool {
+        let brightest = self.quadrants.iter().copied().fold(f32::MIN, f32::max);
+        let darkest = self.quadrants.iter().copied().fold(f32::MAX, f32::min);
+        brightest - darkest > MAX_QUADRANT_SPREAD
+    }
+}
+
+// Mean luma of each quadrant: TL, TR, BL, BR. Odd sizes give the extra row and
+// column to the bottom and right quadrants.
+fn quadrant_means(gray: &[u8], width: usize, height: usize) -> [f32; 4] {
+    let (half_w, half_h) = (width / 2, height / 2);
+    let mut sums = [0u64; 4];
+    for (y, row) in gray.chunks_exact(width.max(1)).enumerate() {
+        let bottom = 2 * (y >= half_h) as usize;
+        sums[bottom] += row[..half_w].iter().map(|&v| v as u64).sum::<u64>();
+        sums[bottom + 1] += row[half_w..].iter().map(|&v| v as u64).sum::<u64>();
+    }
+    let widths = [half_w, width - half_w];
+    let heights = [half_h, height - half_h];
+    let mut means = [0f32; 4];
+    for (q, mean) in means.iter_mut().enumerate() {
+        let pixels = widths[q % 2] * heights[q / 2];
+        *mean = if pixels > 0 { sums[q] as f32 / pixels as f32 } else { 0.0 };
+    }
+    means
+}
+
+/// Measures the exposure and lighting of a frame, so the UI can prompt "too
+/// dark" or "uneven lighting" before the capture.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// `LightingReport` with the mean luma, dynamic range, clipped fractions and
+/// per-quadrant brightness
+#[wasm_bindgen]
+pub fn lighting_report(gray: &[u8], width: usize, height: usize) -> Result<LightingReport, ScanError> {
+    check_gray(gray, width, height)?;
+    let histogram = gray_histogram(gray);
+    let pixels = gray.len().max(1) as f32;
+    let total: u64 = histogram.iter().enumerate().map(|(level, &count)| level as u64 * count as u64).sum();
+    let count = |levels: &[u32]| levels.iter().map(|&c| c as u64).sum::<u64>() as f32 / pixels;
+    let (low, high) = percentile_levels(&histogram, RANGE_PERCENTILE, RANGE_PERCENTILE);
+    Ok(LightingReport {
+        mean_luma: total as f32 / pixels,
+        dynamic_range: high - low,
+        shadow_clipped: count(&histogram[..=SHADOW_CLIP as usize]),
+        highlight_clipped: count(&histogram[HIGHLIGHT_CLIP as usize..]),
+        quadrants: quadrant_means(gray, width, height),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lighting_report() {
+        // A page lit from the left: bright on the left half, shadowed on the
+        // right, with black text and a blown-out glare spot
+        let (width, height) = (40, 30);
+        let gray: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                match (x, y) {
+                    (2..=5, 2..=3) => 255,
+                    _ if y % 6 == 0 && x % 4 != 0 => 0,
+                    _ if x < 20 => 200,
+                    _ => 110,
+                }
+            })
+            .collect();
+        let report = lighting_report(&gray, width, height).unwrap();
+        // The glare spot is under 1 % of the frame and left out of the range
+        assert_eq!(report.dynamic_range(), 200);
+        assert!((report.highlight_clipped() - 8.0 / 1200.0).abs() < 1e-6);
+        assert!((report.shadow_clipped() - 150.0 / 1200.0).abs() < 1e-6, "{report:?}");
+        let quadrants = report.quadrants();
+        assert!(quadrants[0] > quadrants[1] + 60.0 && quadrants[2] > quadrants[3] + 60.0, "{quadrants:?}");
+        assert!(report.uneven() && !report.too_dark() && !report.too_bright());
+
+        let dim = lighting_report(&[30; 16], 4, 4).unwrap();
+        assert_eq!((dim.mean_luma(), dim.dynamic_range()), (30.0, 0));
+        assert!(dim.too_dark() && !dim.uneven());
+        assert_eq!(lighting_report(&[0; 3], 3, 1).unwrap().quadrants(), vec![0.0; 4]);
+    }
+}
@@ -0,0 +1,196 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// When `ScanContext` runs its low-light pipeline variant: 2x2-binned luma
+/// averaged over recent frames, a stronger blur and relaxed Canny thresholds.
+/// The default settings find almost no document edges below about 10 lux,
+/// where sensor noise is as strong as the page contrast.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LowLightMode {
+    /// Always use the regular pipeline
+    Off = 0,
+    /// Switch by the mean luma of the frame (see `ScanContext::mean_luma`)
+    Auto = 1,
+    /// Always use the low-light pipeline
+    On = 2,
+}
+
+// Auto mode switches low light on below this mean luma and off again above the
+// exit level, so frames around the threshold don't flip between pipelines.
+const ENTER_MEAN_LUMA: f32 = 40.0;
+const EXIT_MEAN_LUMA: f32 = 56.0;
+// The accumulator keeps binned luma with this many fractional bits, and each new
+// frame enters the running average with weight 1 / 2^ACCUMULATION_SHIFT.
+const FRACTION_BITS: u32 = 4;
+const ACCUMULATION_SHIFT: u32 = 2;
+// Blur kernel on the binned frame, about 14 px at full resolution.
+pub(crate) const KERNEL_SIZE: usize = 7;
+// Canny thresholds are scaled by this in low light. Page contrast drops with the
+// light, and the binned, averaged frame is clean enough for low thresholds.
+pub(crate) const THRESHOLD_SCALE: f32 = 0.25;
+
+// Low-light state of a `ScanContext`: the mode, the latest exposure metric and
+// the temporal average of the binned frames.
+pub(crate) struct LowLight {
+    mode: LowLightMode,
+    active: bool,
+    mean_luma: f32,
+    // Running average of 2x2-binned luma, FRACTION_BITS fixed point; empty
+    // until low light runs and after a switch or size change
+    accumulator: Vec<u16>,
+}
+
+impl Default for LowLight {
+    fn default() -> Self {
+        LowLight { mode: LowLightMode::Off, active: false, mean_luma: 0.0, accumulator: Vec::new() }
+    }
+}
+
+impl LowLight {
+    pub(crate) fn set_mode(&mut self, mode: LowLightMode) {
+        self.mode = mode;
+    }
+
+    pub(crate) fn mode(&self) -> LowLightMode {
+        self.mode
+    }
+
+    pub(crate) fn active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn mean_luma(&self) -> f32 {
+        self.mean_luma
+    }
+
+    // Measures the frame's exposure and decides whether it takes the low-light
+    // pipeline.
+    pub(crate) fn update(&mut self, gray: &[u8]) -> bool {
+        self.mean_luma = mean_luma(gray);
+        let active = match self.mode {
+            LowLightMode::Off => false,
+            LowLightMode::On => true,
+            LowLightMode::Auto if self.active => self.mean_luma <= EXIT_MEAN_LUMA,
+            LowLightMode::Auto => self.mean_luma < ENTER_MEAN_LUMA,
+        };
+        if active != self.active {
+            // Don't average across frames the regular pipeline handled
+            self.accumulator.clear();
+        }
+        self.active = active;
+        active
+    }
+
+    // Bins `gray` 2x2 into `binned` ((width / 2) * (height / 2) bytes) and
+    // replaces it with the running average over the recent frames.
+    pub(crate) fn accumulate_into(&mut self, gray: &[u8], width: usize, height: usize, binned: &mut [u8]) {
+        bin_into(gray, width, height, binned);
+        if self.accumulator.len() != binned.len() {
+            self.accumulator.clear();
+            self.accumulator.extend(binned.iter().map(|&v| (v as u16) << FRACTION_BITS));
+            return;
+        }
+        let round = 1 << (FRACTION_BITS - 1);
+        for (average, value) in self.accumulator.iter_mut().zip(binned.iter_mut()) {
+            let current = (*value as i32) << FRACTION_BITS;
+            let updated = *average as i32 + ((current - *average as i32) >> ACCUMULATION_SHIFT);
+            *average = updated as u16;
+            *value = ((updated + round) >> FRACTION_BITS) as u8;
+        }
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.accumulator = Vec::new();
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.accumulator.capacity() * size_of::<u16>()
+    }
+}
+
+// Mean luma of a frame, sampled on every fourth pixel.
+fn mean_luma(gray: &[u8]) -> f32 {
+    let (sum, count) = gray.iter().step_by(4).fold((0u64, 0u64), |(sum, count), &v| (sum + v as u64, count + 1));
+    if count == 0 { 0.0 } else { sum as f32 / count as f32 }
+}
+
+// Averages 2x2 blocks; an odd last row or column is dropped.
+fn bin_into(gray: &[u8], width: usize, height: usize, binned: &mut [u8]) {
+    let half = width / 2;
+    for (y, row) in binned.chunks_exact_mut(half.max(1)).enumerate().take(height / 2) {
+        let top = &gray[2 * y * width..][..2 * half];
+        let bottom = &gray[(2 * y + 1) * width..][..2 * half];
+        for ((value, t), b) in row.iter_mut().zip(top.chunks_exact(2)).zip(bottom.chunks_exact(2)) {
+            *value = ((t[0] as u16 + t[1] as u16 + b[0] as u16 + b[1] as u16 + 2) / 4) as u8;
+        }
+    }
+}
+
+// Nearest-neighbour 2x upsampling of a binned map back to width x height; the
+// dropped odd row or column repeats its neighbour.
+pub(crate) fn upsample_into(small: &[u8], width: usize, height: usize, out: &mut [u8]) {
+    let (half_width, half_height) = (width / 2, height / 2);
+    if half_width == 0 || half_height == 0 {
+        out.fill(0);
+        return;
+    }
+    for (y, row) in out.chunks_exact_mut(width).enumerate() {
+        let src = &small[(y / 2).min(half_height - 1) * half_width..][..half_width];
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = src[(x / 2).min(half_width - 1)];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_low_light_finds_edges_in_noisy_frames() {
+        let (width, height) = (96, 72);
+        // A dim page (luma ~30) on a darker desk under strong sensor noise
+        let mut seed = 7u32;
+        let mut frame = || -> Vec<u8> {
+            (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                    let base = if (24..72).contains(&x) && (18..54).contains(&y) { 30 } else { 8 };
+                    (base + (seed >> 16) % 40) as u8
+                })
+                .collect()
+        };
+        // Fraction of the page's left border found, and edge pixels inside the page
+        let score = |edges: &[u8]| {
+            let border = (20..52).filter(|&y| edges[y * width + 22..y * width + 26].contains(&255)).count();
+            let stray = (0..width * height)
+                .filter(|&i| edges[i] == 255 && (i % width).abs_diff(48) < 12 && (i / width).abs_diff(36) < 8)
+                .count();
+            (border as f32 / 32.0, stray)
+        };
+
+        // The default settings miss the page entirely
+        let mut context = ScanContext::new(width, height);
+        context.set_dilation(0);
+        context.process_gray_frame(&frame()).unwrap();
+        assert!(!context.low_light_active());
+        assert_eq!(score(&context.edges()).0, 0.0);
+
+        context.set_low_light_mode(LowLightMode::Auto);
+        for _ in 0..6 {
+            context.process_gray_frame(&frame()).unwrap();
+        }
+        assert!(context.low_light_active() && context.mean_luma() < ENTER_MEAN_LUMA);
+        let (border, stray) = score(&context.edges());
+        assert!(border > 0.9 && stray == 0, "{border} {stray}");
+
+        // A well lit frame switches back to the regular pipeline
+        context.process_gray_frame(&vec![128u8; width * height]).unwrap();
+        assert!(!context.low_light_active());
+    }
+}
@@ -0,0 +1,120 @@
+// Float functions used by the algorithms. With `std` these are the inherent
+// f32/f64 methods; `no_std` builds get the same method names from libm through
+// this trait, so call sites stay unchanged. Some methods are only used by
+// optional stages.
+#[allow(dead_code)]
+pub(crate) trait Float: Sized {
+    fn sqrt(self) -> Self;
+    fn exp(self) -> Self;
+    fn round(self) -> Self;
+    fn ceil(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn sin_cos(self) -> (Self, Self);
+    fn hypot(self, other: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn acos(self) -> Self;
+    fn ln(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn cbrt(self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+}
+
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+    fn exp(self) -> f32 {
+        libm::expf(self)
+    }
+    fn round(self) -> f32 {
+        libm::roundf(self)
+    }
+    fn ceil(self) -> f32 {
+        libm::ceilf(self)
+    }
+    fn sin(self) -> f32 {
+        libm::sinf(self)
+    }
+    fn cos(self) -> f32 {
+        libm::cosf(self)
+    }
+    fn sin_cos(self) -> (f32, f32) {
+        libm::sincosf(self)
+    }
+    fn hypot(self, other: f32) -> f32 {
+        libm::hypotf(self, other)
+    }
+    fn powi(self, n: i32) -> f32 {
+        libm::powf(self, n as f32)
+    }
+    fn powf(self, n: f32) -> f32 {
+        libm::powf(self, n)
+    }
+    fn acos(self) -> f32 {
+        libm::acosf(self)
+    }
+    fn ln(self) -> f32 {
+        libm::logf(self)
+    }
+    fn atan2(self, other: f32) -> f32 {
+        libm::atan2f(self, other)
+    }
+    fn cbrt(self) -> f32 {
+        libm::cbrtf(self)
+    }
+    fn rem_euclid(self, rhs: f32) -> f32 {
+        let r = libm::fmodf(self, rhs);
+        if r < 0.0 { r + libm::fabsf(rhs) } else { r }
+    }
+}
+
+impl Float for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+    fn exp(self) -> f64 {
+        libm::exp(self)
+    }
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+    fn sin_cos(self) -> (f64, f64) {
+        libm::sincos(self)
+    }
+    fn hypot(self, other: f64) -> f64 {
+        libm::hypot(self, other)
+    }
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+    fn acos(self) -> f64 {
+        libm::acos(self)
+    }
+    fn ln(self) -> f64 {
+        libm::log(self)
+    }
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+    fn cbrt(self) -> f64 {
+        libm::cbrt(self)
+    }
+    fn rem_euclid(self, rhs: f64) -> f64 {
+        let r = libm::fmod(self, rhs);
+        if r < 0.0 { r + libm::fabs(rhs) } else { r }
+    }
+}
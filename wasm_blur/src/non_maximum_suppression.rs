@@ -1,10 +1,23 @@
 use wasm_bindgen::prelude::*;
 
-#[cfg(target_arch = "wasm32")]
-use std::arch::wasm32::*;
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+#[cfg(feature = "threads")]
+use crate::scheduler::ROWS_PER_TASK;
 
 // This is the new SIMD-optimized implementation.
-#[cfg(target_arch = "wasm32")]
+#[cfg(target_family = "wasm")]
 #[target_feature(enable = "simd128")]
 unsafe fn calculate_magnitude_simd(
     dx: &[i16],
@@ -60,78 +73,110 @@ pub fn non_maximum_suppression(
     width: usize,
     height: usize,
     l2_gradient: bool,
-) -> Vec<f32> {
+) -> Result<Vec<f32>, ScanError> {
+    check_len(dx.len(), image_len(width, height, 1)?, "width * height")?;
+    check_len(dy.len(), dx.len(), "width * height")?;
     let mut magnitude = vec![0.0f32; width * height];
     let mut suppressed = vec![0.0f32; width * height];
 
-    // Calculate magnitude for all pixels first
-    #[cfg(target_arch = "wasm32")]
-    unsafe {
-        calculate_magnitude_simd(dx, dy, &mut magnitude, l2_gradient);
+    // Calculate magnitude for all pixels first. With the `threads` feature both
+    // passes below are split into bands of rows across the rayon pool.
+    #[cfg(feature = "threads")]
+    {
+        let band = (width * ROWS_PER_TASK).max(1);
+        magnitude
+            .par_chunks_mut(band)
+            .zip(dx.par_chunks(band).zip(dy.par_chunks(band)))
+            .for_each(|(mag, (dx, dy))| calculate_magnitude(dx, dy, mag, l2_gradient));
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        for i in 0..dx.len() {
-            let gx = dx[i] as f32;
-            let gy = dy[i] as f32;
-            if l2_gradient {
-                magnitude[i] = (gx * gx + gy * gy).sqrt();
-            } else {
-                magnitude[i] = gx.abs() + gy.abs(); // L1 norm
-            }
+    #[cfg(not(feature = "threads"))]
+    calculate_magnitude(dx, dy, &mut magnitude, l2_gradient);
+
+    // Perform non-maximum suppression
+    if width < 3 || height < 3 {
+        return Ok(suppressed);
+    }
+
+    #[cfg(feature = "threads")]
+    let rows = suppressed.par_chunks_mut(width).with_min_len(ROWS_PER_TASK);
+    #[cfg(not(feature = "threads"))]
+    let rows = suppressed.chunks_mut(width);
+
+    let magnitude = &magnitude;
+    rows.enumerate()
+        .filter(|&(y, _)| y > 0 && y + 1 < height)
+        .for_each(|(y, row)| suppress_row(dx, dy, magnitude, width, y, row));
+
+    Ok(suppressed)
+}
+
+fn calculate_magnitude(dx: &[i16], dy: &[i16], magnitude: &mut [f32], l2_gradient: bool) {
+    #[cfg(target_family = "wasm")]
+    if simd_enabled() {
+        unsafe {
+            calculate_magnitude_simd(dx, dy, magnitude, l2_gradient);
         }
+        return;
     }
 
-    // Perform non-maximum suppression
-    for y in 1..height - 1 {
-        for x in 1..width - 1 {
-            let idx = y * width + x;
-            let mag = magnitude[idx];
-
-            if mag == 0.0 {
-                suppressed[idx] = 0.0;
-                continue;
-            }
+    for i in 0..dx.len() {
+        let gx = dx[i] as f32;
+        let gy = dy[i] as f32;
+        if l2_gradient {
+            magnitude[i] = (gx * gx + gy * gy).sqrt();
+        } else {
+            magnitude[i] = gx.abs() + gy.abs(); // L1 norm
+        }
+    }
+}
 
-            let gx = dx[idx] as f32;
-            let gy = dy[idx] as f32;
-
-            let neighbor1;
-            let neighbor2;
-
-            let abs_gx = gx.abs();
-            let abs_gy = gy.abs();
-
-            // The constant 2.4142 is tan(67.5 degrees), which is used to partition
-            // the gradient direction into 45-degree sectors. This is an approximation
-            // of the gradient angle.
-            if abs_gy > abs_gx * 2.4142 { // Vertical edge
-                neighbor1 = magnitude[idx - width]; // top
-                neighbor2 = magnitude[idx + width]; // bottom
-            } else if abs_gx > abs_gy * 2.4142 { // Horizontal edge
-                neighbor1 = magnitude[idx - 1]; // left
-                neighbor2 = magnitude[idx + 1]; // right
-            } else { // Diagonal edge
-                // Check for 45 or 135 degree angles based on signs of gx and gy
-                if (gx > 0.0 && gy > 0.0) || (gx < 0.0 && gy < 0.0) { // 45 degrees (top-right to bottom-left)
-                    neighbor1 = magnitude[idx - width + 1];
-                    neighbor2 = magnitude[idx + width - 1];
-                } else { // 135 degrees (top-left to bottom-right)
-                    neighbor1 = magnitude[idx - width - 1];
-                    neighbor2 = magnitude[idx + width + 1];
-                }
-            }
+// Non-maximum suppression for one interior row `y` into `suppressed` (that row only).
+fn suppress_row(dx: &[i16], dy: &[i16], magnitude: &[f32], width: usize, y: usize, suppressed: &mut [f32]) {
+    for (x, out) in suppressed.iter_mut().enumerate().take(width - 1).skip(1) {
+        let idx = y * width + x;
+        let mag = magnitude[idx];
+
+        if mag == 0.0 {
+            *out = 0.0;
+            continue;
+        }
 
-            // If the pixel's magnitude is greater than or equal to its neighbors
-            // along the gradient direction, keep it. Otherwise, suppress it.
-            if mag >= neighbor1 && mag >= neighbor2 {
-                suppressed[idx] = mag;
-            } else {
-                suppressed[idx] = 0.0;
+        let gx = dx[idx] as f32;
+        let gy = dy[idx] as f32;
+
+        let neighbor1;
+        let neighbor2;
+
+        let abs_gx = gx.abs();
+        let abs_gy = gy.abs();
+
+        // The constant 2.4142 is tan(67.5 degrees), which is used to partition
+        // the gradient direction into 45-degree sectors. This is an approximation
+        // of the gradient angle.
+        if abs_gy > abs_gx * 2.4142 { // Vertical edge
+            neighbor1 = magnitude[idx - width]; // top
+            neighbor2 = magnitude[idx + width]; // bottom
+        } else if abs_gx > abs_gy * 2.4142 { // Horizontal edge
+            neighbor1 = magnitude[idx - 1]; // left
+            neighbor2 = magnitude[idx + 1]; // right
+        } else { // Diagonal edge
+            // Check for 45 or 135 degree angles based on signs of gx and gy
+            if (gx > 0.0 && gy > 0.0) || (gx < 0.0 && gy < 0.0) { // 45 degrees (top-right to bottom-left)
+                neighbor1 = magnitude[idx - width + 1];
+                neighbor2 = magnitude[idx + width - 1];
+            } else { // 135 degrees (top-left to bottom-right)
+                neighbor1 = magnitude[idx - width - 1];
+                neighbor2 = magnitude[idx + width + 1];
             }
         }
-    }
 
-    suppressed
+        // If the pixel's magnitude is greater than or equal to its neighbors
+        // along the gradient direction, keep it. Otherwise, suppress it.
+        if mag >= neighbor1 && mag >= neighbor2 {
+            *out = mag;
+        } else {
+            *out = 0.0;
+        }
+    }
 }
@@ -0,0 +1,199 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_index, to_quad, ScanError};
+
+// Search radius (px) around a point on a side for an edge pixel backing it.
+const SUPPORT_RADIUS: isize = 2;
+// Shortest run of unsupported points reported as a gap: a share of the side,
+// but at least a few pixels so noise on small quads doesn't count.
+const MIN_GAP_FRACTION: f32 = 0.04;
+const MIN_GAP_PX: usize = 6;
+// Corners are matched loosely by the quad fit; this share of each side's ends
+// is left out.
+const CORNER_SKIP: f32 = 0.03;
+
+struct Gap {
+    side: usize,
+    start: f32,
+    end: f32,
+    points: [f32; 4],
+}
+
+/// Stretches of the quad's sides without edges behind them, from
+/// `border_gaps`. A thumb holding the page or an object lying across its border
+/// hides the page edge there.
+#[wasm_bindgen]
+pub struct BorderGaps {
+    coverage: [f32; 4],
+    gaps: Vec<Gap>,
+}
+
+#[wasm_bindgen]
+impl BorderGaps {
+    /// Whether any side has a gap: warn the user to move their fingers off
+    /// the page.
+    pub fn occluded(&self) -> bool {
+        !self.gaps.is_empty()
+    }
+
+    /// Share of each side backed by edges, 0..1: top, right, bottom, left.
+    pub fn coverage(&self) -> Vec<f32> {
+        self.coverage.to_vec()
+    }
+
+    pub fn count(&self) -> usize {
+        self.gaps.len()
+    }
+
+    /// Side of a gap: 0 top (TL to TR), 1 right (TR to BR), 2 bottom (BR to BL),
+    /// 3 left (BL to TL).
+    pub fn side(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.side)
+    }
+
+    /// Where a gap starts and ends along its side, 0..1 from the side's first
+    /// corner.
+    pub fn span(&self, index: usize) -> Result<Vec<f32>, ScanError> {
+        let gap = self.get(index)?;
+        Ok(vec![gap.start, gap.end])
+    }
+
+    /// The two end points of a gap in the frame (x0, y0, x1, y1), where the
+    /// page edge is visible again; interpolate between them to bridge it.
+    pub fn points(&self, index: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(index)?.points.to_vec())
+    }
+}
+
+impl BorderGaps {
+    fn get(&self, index: usize) -> Result<&Gap, ScanError> {
+        check_index(index, self.gaps.len())?;
+        Ok(&self.gaps[index])
+    }
+}
+
+// Edge support along each side of `quad`, one point per pixel of side length.
+fn find_gaps(edges: &[u8], width: usize, height: usize, quad: &[f32; 8]) -> BorderGaps {
+    let has_edge = |x: f32, y: f32| {
+        let (x, y) = (x.round() as isize, y.round() as isize);
+        (-SUPPORT_RADIUS..=SUPPORT_RADIUS).any(|dy| {
+            (-SUPPORT_RADIUS..=SUPPORT_RADIUS).any(|dx| {
+                let (nx, ny) = (x + dx, y + dy);
+                nx >= 0
+                    && ny >= 0
+                    && (nx as usize) < width
+                    && (ny as usize) < height
+                    && edges[ny as usize * width + nx as usize] != 0
+            })
+        })
+    };
+
+    let mut coverage = [0f32; 4];
+    let mut gaps = Vec::new();
+    for side in 0..4 {
+        let (ax, ay, bx, by) = (quad[2 * side], quad[2 * side + 1], quad[(2 * side + 2) % 8], quad[(2 * side + 3) % 8]);
+        let length = ((bx - ax).hypot(by - ay).round() as usize).max(1);
+        let skip = (CORNER_SKIP * length as f32) as usize;
+        let at = |s: usize| s as f32 / length as f32;
+        let along = |t: f32| (ax + (bx - ax) * t, ay + (by - ay) * t);
+        let supported: Vec<bool> = (skip..=length - skip)
+            .map(|s| {
+                let (x, y) = along(at(s));
+                has_edge(x, y)
+            })
+            .collect();
+        coverage[side] = supported.iter().filter(|&&s| s).count() as f32 / supported.len() as f32;
+
+        let min_gap = ((MIN_GAP_FRACTION * length as f32) as usize).max(MIN_GAP_PX);
+        let mut s = 0;
+        while s < supported.len() {
+            let run = supported[s..].iter().take_while(|&&v| !v).count();
+            if run >= min_gap {
+                // From the last supported point before the gap to the first after it
+                let (start, end) = (at((skip + s).saturating_sub(1)), at(skip + s + run).min(1.0));
+                let ((x0, y0), (x1, y1)) = (along(start), along(end));
+                gaps.push(Gap { side, start, end, points: [x0, y0, x1, y1] });
+            }
+            s += run.max(1);
+        }
+    }
+    BorderGaps { coverage, gaps }
+}
+
+/// Checks how well each side of a detected quad is backed by edges and reports
+/// the stretches without edges, typically where a thumb holds the page. The
+/// app can warn the user, or interpolate the border across the gap before
+/// dewarping.
+///
+/// # Arguments
+/// * `edges` - Edge map of the frame, e.g. from `canny_edge_detector`
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `corners` - Page quad (TL, TR, BR, BL as 8 values)
+///
+/// # Returns
+/// `BorderGaps` with the per-side coverage and each gap found
+#[wasm_bindgen]
+pub fn border_gaps(edges: &[u8], width: usize, height: usize, corners: &[f32]) -> Result<BorderGaps, ScanError> {
+    check_gray(edges, width, height)?;
+    Ok(find_gaps(edges, width, height, &to_quad(corners)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_border_gaps() {
+        // Outline of a page with a thumb covering part of the bottom edge
+        let (width, height) = (120, 90);
+        let mut edges = vec![0u8; width * height];
+        for x in 10..=110 {
+            edges[10 * width + x] = 255;
+            if !(40..60).contains(&x) {
+                edges[80 * width + x] = 255;
+            }
+        }
+        for y in 10..=80 {
+            edges[y * width + 10] = 255;
+            edges[y * width + 110] = 255;
+        }
+        let quad = [10.0, 10.0, 110.0, 10.0, 110.0, 80.0, 10.0, 80.0];
+
+        let gaps = border_gaps(&edges, width, height, &quad).unwrap();
+        assert!(gaps.occluded() && gaps.count() == 1);
+        let coverage = gaps.coverage();
+        assert!(coverage[0] == 1.0 && coverage[1] == 1.0 && coverage[3] == 1.0 && coverage[2] < 0.9, "{coverage:?}");
+        assert_eq!(gaps.side(0).unwrap(), 2);
+        // The bottom side runs from BR to BL; edges within 2 px still back it
+        let points = gaps.points(0).unwrap();
+        let expected = [58.0, 80.0, 41.0, 80.0];
+        assert!(points.iter().zip(expected).all(|(p, e)| (p - e).abs() < 0.01), "{points:?}");
+        assert_eq!(gaps.span(0).unwrap(), vec![0.52, 0.69]);
+
+        let edges_full: Vec<u8> = (0..width * height).map(|i| if i / width == 80 { 255 } else { edges[i] }).collect();
+        assert!(!border_gaps(&edges_full, width, height, &quad).unwrap().occluded());
+        assert!(border_gaps(&edges, width, height, &quad[..6]).is_err());
+
+        // ScanContext checks the edge map of its last frame
+        let mut context = crate::context::ScanContext::new(width, height);
+        assert!(context.border_gaps(&quad).is_err());
+        let page: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let thumb = (40..60).contains(&x) && y >= 70;
+                if (10..=110).contains(&x) && (10..=80).contains(&y) && !thumb {
+                    220
+                } else {
+                    60
+                }
+            })
+            .collect();
+        context.process_gray_frame(&page).unwrap();
+        let gaps = context.border_gaps(&quad).unwrap();
+        assert_eq!((gaps.count(), gaps.side(0).unwrap()), (1, 2));
+    }
+}
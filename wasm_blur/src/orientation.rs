@@ -0,0 +1,219 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_channels, check_len, image_len, ScanError};
+
+// Side of the square tiles the quarter turns are copied in, so reads and
+// writes both stay within a few cache lines per row.
+const TILE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Transform {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+// Applies `transform` to a `width` x `height` image of `T` pixels. `dst` has the
+// same length as `src`; quarter turns swap width and height.
+fn transform_into<T: Copy>(src: &[T], width: usize, height: usize, transform: Transform, dst: &mut [T]) {
+    match transform {
+        Transform::Rotate90 | Transform::Rotate270 => {
+            let clockwise = transform == Transform::Rotate90;
+            for ty in (0..height).step_by(TILE) {
+                for tx in (0..width).step_by(TILE) {
+                    for y in ty..(ty + TILE).min(height) {
+                        let row = &src[y * width..(y + 1) * width];
+                        for (x, &px) in row.iter().enumerate().take((tx + TILE).min(width)).skip(tx) {
+                            // The source row becomes a destination column of length `height`
+                            let i = if clockwise { x * height + (height - 1 - y) } else { (width - 1 - x) * height + y };
+                            dst[i] = px;
+                        }
+                    }
+                }
+            }
+        }
+        Transform::Rotate180 => {
+            for (d, &s) in dst.iter_mut().zip(src.iter().rev()) {
+                *d = s;
+            }
+        }
+        Transform::FlipHorizontal => {
+            for (d, s) in dst.chunks_exact_mut(width).zip(src.chunks_exact(width)) {
+                for (dp, &sp) in d.iter_mut().zip(s.iter().rev()) {
+                    *dp = sp;
+                }
+            }
+        }
+        Transform::FlipVertical => {
+            for (d, s) in dst.chunks_exact_mut(width).zip(src.chunks_exact(width).rev()) {
+                d.copy_from_slice(s);
+            }
+        }
+    }
+}
+
+fn transform(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    channels: usize,
+    transform: Transform,
+) -> Result<Vec<u8>, ScanError> {
+    check_channels(channels)?;
+    check_len(src.len(), image_len(width, height, channels)?, "width * height * channels")?;
+    let mut dst = vec![0u8; src.len()];
+    if width == 0 || height == 0 {
+        return Ok(dst);
+    }
+    if channels == 1 {
+        transform_into(src, width, height, transform, &mut dst);
+    } else {
+        // Whole RGBA pixels are moved as 4-byte arrays
+        let (pixels, _) = src.as_chunks::<4>();
+        let (out, _) = dst.as_chunks_mut::<4>();
+        transform_into(pixels, width, height, transform, out);
+    }
+    Ok(dst)
+}
+
+// Turns an RGBA image stored with EXIF orientation `orientation` upright and
+// returns it with its new width and height. Values other than 2 to 8 leave it
+// as it is.
+#[cfg_attr(not(feature = "decode"), allow(dead_code))]
+pub(crate) fn apply_exif_orientation(
+    rgba: Vec<u8>,
+    width: usize,
+    height: usize,
+    orientation: u16,
+) -> (Vec<u8>, usize, usize) {
+    let steps: &[Transform] = match orientation {
+        2 => &[Transform::FlipHorizontal],
+        3 => &[Transform::Rotate180],
+        4 => &[Transform::FlipVertical],
+        // Transpose and transverse: a quarter turn, then a flip
+        5 => &[Transform::Rotate90, Transform::FlipHorizontal],
+        6 => &[Transform::Rotate90],
+        7 => &[Transform::Rotate90, Transform::FlipVertical],
+        8 => &[Transform::Rotate270],
+        _ => &[],
+    };
+    let (mut rgba, mut width, mut height) = (rgba, width, height);
+    for &step in steps {
+        let mut out = vec![0u8; rgba.len()];
+        let (pixels, _) = rgba.as_chunks::<4>();
+        let (dst, _) = out.as_chunks_mut::<4>();
+        transform_into(pixels, width, height, step, dst);
+        if step == Transform::Rotate90 || step == Transform::Rotate270 {
+            (width, height) = (height, width);
+        }
+        rgba = out;
+    }
+    (rgba, width, height)
+}
+
+/// Rotates an image 90 degrees clockwise, e.g. to undo the camera orientation
+/// of portrait iOS captures before processing.
+///
+/// # Arguments
+/// * `src` - Grayscale (1 channel) or RGBA (4 channels) pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Bytes per pixel, 1 or 4
+///
+/// # Returns
+/// Rotated image as Vec<u8>, `height` pixels wide and `width` pixels high
+#[wasm_bindgen]
+pub fn rotate90(src: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ScanError> {
+    transform(src, width, height, channels, Transform::Rotate90)
+}
+
+/// Rotates an image by 180 degrees.
+///
+/// # Arguments
+/// * `src` - Grayscale (1 channel) or RGBA (4 channels) pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Bytes per pixel, 1 or 4
+///
+/// # Returns
+/// Rotated image as Vec<u8>, same size as the input
+#[wasm_bindgen]
+pub fn rotate180(src: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ScanError> {
+    transform(src, width, height, channels, Transform::Rotate180)
+}
+
+/// Rotates an image 90 degrees counter-clockwise.
+///
+/// # Arguments
+/// * `src` - Grayscale (1 channel) or RGBA (4 channels) pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Bytes per pixel, 1 or 4
+///
+/// # Returns
+/// Rotated image as Vec<u8>, `height` pixels wide and `width` pixels high
+#[wasm_bindgen]
+pub fn rotate270(src: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ScanError> {
+    transform(src, width, height, channels, Transform::Rotate270)
+}
+
+/// Mirrors an image left to right.
+///
+/// # Arguments
+/// * `src` - Grayscale (1 channel) or RGBA (4 channels) pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Bytes per pixel, 1 or 4
+///
+/// # Returns
+/// Mirrored image as Vec<u8>
+#[wasm_bindgen]
+pub fn flip_horizontal(src: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ScanError> {
+    transform(src, width, height, channels, Transform::FlipHorizontal)
+}
+
+/// Mirrors an image top to bottom.
+///
+/// # Arguments
+/// * `src` - Grayscale (1 channel) or RGBA (4 channels) pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `channels` - Bytes per pixel, 1 or 4
+///
+/// # Returns
+/// Mirrored image as Vec<u8>
+#[wasm_bindgen]
+pub fn flip_vertical(src: &[u8], width: usize, height: usize, channels: usize) -> Result<Vec<u8>, ScanError> {
+    transform(src, width, height, channels, Transform::FlipVertical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotations_and_flips() {
+        // 3x2:  1 2 3
+        //       4 5 6
+        let gray = [1, 2, 3, 4, 5, 6];
+        assert_eq!(rotate90(&gray, 3, 2, 1).unwrap(), vec![4, 1, 5, 2, 6, 3]);
+        assert_eq!(rotate180(&gray, 3, 2, 1).unwrap(), vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(rotate270(&gray, 3, 2, 1).unwrap(), vec![3, 6, 2, 5, 1, 4]);
+        assert_eq!(flip_horizontal(&gray, 3, 2, 1).unwrap(), vec![3, 2, 1, 6, 5, 4]);
+        assert_eq!(flip_vertical(&gray, 3, 2, 1).unwrap(), vec![4, 5, 6, 1, 2, 3]);
+
+        // RGBA pixels move as a whole; a larger image crosses tile borders
+        let (width, height) = (45, 70);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let turned = rotate90(&rgba, width, height, 4).unwrap();
+        assert_eq!(&turned[..4], &rgba[(height - 1) * width * 4..][..4]);
+        assert_eq!(rotate270(&turned, height, width, 4).unwrap(), rgba);
+        let twice = rotate90(&turned, height, width, 4).unwrap();
+        assert_eq!(twice, rotate180(&rgba, width, height, 4).unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::binarize::binarize_document_into;
+use crate::error::{check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::histogram::{gray_histogram, percentile_levels, stretch_lut, BINS};
+
+/// Colour mode of the final scan.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The rectified page as is.
+    Color = 0,
+    /// Contrast-normalized grey levels with slightly darkened mid-tones.
+    Grayscale = 1,
+    /// Pure black and white from the Sauvola binarizer; printed photos are
+    /// dithered instead of thresholded.
+    BlackAndWhite = 2,
+}
+
+// Percentage of the darkest and brightest pixels clipped by the grayscale
+// contrast stretch.
+const GRAY_CLIP_PCT: f32 = 0.5;
+// Gamma applied after the stretch; above 1 darkens mid-tones so faint text and
+// pencil stay readable.
+const GRAY_GAMMA: f32 = 1.2;
+// Sauvola window as a fraction of the shorter page side, within fixed bounds, so
+// it covers a few text lines whatever the scan resolution.
+const BW_WINDOW_DIVISOR: usize = 24;
+const BW_MIN_WINDOW: usize = 15;
+const BW_MAX_WINDOW: usize = 101;
+// Sauvola sensitivity for document pages.
+const BW_K: f32 = 0.3;
+
+// Stretch and gamma LUT for grayscale output.
+fn grayscale_lut(gray: &[u8]) -> [u8; BINS] {
+    let (low, high) = percentile_levels(&gray_histogram(gray), GRAY_CLIP_PCT, GRAY_CLIP_PCT);
+    let stretch = stretch_lut(low, high);
+    stretch.map(|v| ((v as f32 / 255.0).powf(GRAY_GAMMA) * 255.0).round() as u8)
+}
+
+// Applies `mode` to an RGBA page in place. Grayscale and black-and-white pages
+// stay RGBA with equal colour channels; alpha is kept.
+pub(crate) fn apply_output_mode_into(rgba: &mut [u8], width: usize, height: usize, mode: OutputMode) {
+    if mode == OutputMode::Color || width == 0 || height == 0 {
+        return;
+    }
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, GrayscaleWeights::Bt601);
+    let levels = match mode {
+        OutputMode::Grayscale => {
+            let lut = grayscale_lut(&gray);
+            gray.iter().map(|&v| lut[v as usize]).collect()
+        }
+        _ => {
+            let window = (width.min(height) / BW_WINDOW_DIVISOR).clamp(BW_MIN_WINDOW, BW_MAX_WINDOW);
+            let mut binary = vec![0u8; gray.len()];
+            binarize_document_into(&gray, width, height, window, BW_K, &mut binary);
+            binary
+        }
+    };
+    for (px, v) in rgba.chunks_exact_mut(4).zip(levels) {
+        px[..3].fill(v);
+    }
+}
+
+/// Converts a rectified RGBA page to its final colour mode, ready for encoding.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels, typically the output of `warp_perspective`
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `mode` - Colour, grayscale or black and white
+///
+/// # Returns
+/// RGBA image as Vec<u8>; grayscale and black-and-white pages have equal colour
+/// channels
+#[wasm_bindgen]
+pub fn apply_output_mode(rgba: &[u8], width: usize, height: usize, mode: OutputMode) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut out = rgba.to_vec();
+    apply_output_mode_into(&mut out, width, height, mode);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_modes() {
+        // Low-contrast grey page with dark text rows and a tinted background
+        let (width, height) = (64usize, 48usize);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| if (i / width) % 8 < 2 && i % 7 < 5 { [90, 80, 80, 255] } else { [190, 180, 170, 255] })
+            .collect();
+        assert_eq!(apply_output_mode(&rgba, width, height, OutputMode::Color).unwrap(), rgba);
+
+        let gray = apply_output_mode(&rgba, width, height, OutputMode::Grayscale).unwrap();
+        assert!(gray.chunks_exact(4).all(|p| p[0] == p[1] && p[1] == p[2] && p[3] == 255));
+        // The stretch maps ink to black and paper to white
+        assert_eq!((gray[0], gray[2 * width * 4]), (0, 255));
+
+        let bw = apply_output_mode(&rgba, width, height, OutputMode::BlackAndWhite).unwrap();
+        assert!(bw.chunks_exact(4).all(|p| (p[0] == 0 || p[0] == 255) && p[0] == p[2]));
+        assert_eq!((bw[0], bw[2 * width * 4]), (0, 255));
+    }
+}
@@ -0,0 +1,265 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, to_quad, ScanError};
+use crate::quad_scoring::perspective_aspect;
+use crate::warp::{quad_output_size, rect_to_quad_matrix, warp_with_matrix, OutOfBoundsPolicy};
+
+// Millimetres per inch.
+const MM_PER_INCH: f32 = 25.4;
+// Largest relative deviation from a paper's aspect ratio still matched to it.
+const ASPECT_TOLERANCE: f32 = 0.03;
+// Output resolution range accepted by `rectify_to_paper_size`.
+const MAX_DPI: f32 = 1200.0;
+
+/// Physical paper size of a rectified page. ISO A sizes share one aspect
+/// ratio, so `infer_paper_size` reports them as A4; pass A3 or A5 to
+/// `rectify_to_paper_size` explicitly.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaperSize {
+    /// Aspect ratio matches no known size
+    Unknown = 0,
+    /// 297 x 420 mm
+    A3 = 1,
+    /// 210 x 297 mm
+    A4 = 2,
+    /// 148 x 210 mm
+    A5 = 3,
+    /// US Letter, 8.5 x 11 in
+    Letter = 4,
+    /// US Legal, 8.5 x 14 in
+    Legal = 5,
+    /// ISO/IEC 7810 ID-1 (ID and bank cards), 85.6 x 53.98 mm
+    IdCard = 6,
+}
+
+impl PaperSize {
+    // Portrait size in millimetres.
+    pub(crate) fn dimensions_mm(self) -> Option<(f32, f32)> {
+        match self {
+            PaperSize::Unknown => None,
+            PaperSize::A3 => Some((297.0, 420.0)),
+            PaperSize::A4 => Some((210.0, 297.0)),
+            PaperSize::A5 => Some((148.0, 210.0)),
+            PaperSize::Letter => Some((215.9, 279.4)),
+            PaperSize::Legal => Some((215.9, 355.6)),
+            PaperSize::IdCard => Some((53.98, 85.6)),
+        }
+    }
+}
+
+// Sizes `infer_paper_size` chooses between, one per aspect ratio.
+const CANDIDATES: [PaperSize; 4] = [PaperSize::A4, PaperSize::Letter, PaperSize::Legal, PaperSize::IdCard];
+
+/// Portrait width and height of a paper size in millimetres, empty for
+/// `Unknown`.
+#[wasm_bindgen]
+pub fn paper_dimensions(paper: PaperSize) -> Vec<f32> {
+    paper.dimensions_mm().map_or_else(Vec::new, |(w, h)| vec![w, h])
+}
+
+// Paper size closest to the perspective-corrected aspect ratio of a quad.
+fn infer(quad: &[f32; 8], width: usize, height: usize) -> PaperSize {
+    let points = [(quad[0], quad[1]), (quad[2], quad[3]), (quad[4], quad[5]), (quad[6], quad[7])];
+    let Some(ratio) = perspective_aspect(&points, (width as f32 / 2.0, height as f32 / 2.0)) else {
+        return PaperSize::Unknown;
+    };
+    CANDIDATES
+        .into_iter()
+        .filter_map(|paper| {
+            let (w, h) = paper.dimensions_mm()?;
+            let deviation = (ratio / (h / w)).ln().abs();
+            (deviation <= (1.0 + ASPECT_TOLERANCE).ln()).then_some((deviation, paper))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map_or(PaperSize::Unknown, |(_, paper)| paper)
+}
+
+/// Infers the paper size of a document from its quad in the frame. The aspect
+/// ratio is measured under perspective (see `QuadScorer::set_expected_aspect`)
+/// and matched to A4, US Letter, US Legal or an ID-1 card within 3 %.
+///
+/// # Arguments
+/// * `corners` - Page quad (TL, TR, BR, BL as 8 values)
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// The closest paper size, `Unknown` if none is close
+#[wasm_bindgen]
+pub fn infer_paper_size(corners: &[f32], width: usize, height: usize) -> Result<PaperSize, ScanError> {
+    Ok(infer(&to_quad(corners)?, width, height))
+}
+
+/// A page rectified to the pixel size of its paper at a given resolution, from
+/// `rectify_to_paper`.
+#[wasm_bindgen]
+pub struct PaperPage {
+    paper: PaperSize,
+    dpi: f32,
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl PaperPage {
+    #[wasm_bindgen(getter)]
+    pub fn paper(&self) -> PaperSize {
+        self.paper
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dpi(&self) -> f32 {
+        self.dpi
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the page is wider than tall.
+    pub fn landscape(&self) -> bool {
+        self.width > self.height
+    }
+
+    /// Physical width in millimetres at `dpi`, e.g. for the PDF page size.
+    #[wasm_bindgen(getter)]
+    pub fn width_mm(&self) -> f32 {
+        self.width as f32 / self.dpi * MM_PER_INCH
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height_mm(&self) -> f32 {
+        self.height as f32 / self.dpi * MM_PER_INCH
+    }
+
+    /// Rectified RGBA pixels.
+    pub fn image(&self) -> Vec<u8> {
+        self.rgba.clone()
+    }
+}
+
+/// Rectifies a page straight to the pixel size of a given paper at `dpi`, e.g.
+/// 2480 x 3508 for A4 at 300 DPI, in one resampling step. The orientation
+/// follows the quad. With `Unknown` the page keeps the size of the quad in the
+/// frame, and its physical size then only holds if the frame was captured at
+/// `dpi`.
+///
+/// # Arguments
+/// * `rgba` - RGBA frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `corners` - Page quad (TL, TR, BR, BL as 8 values)
+/// * `paper` - Paper size of the page
+/// * `dpi` - Output resolution, up to 1200
+///
+/// # Returns
+/// `PaperPage` with the rectified image, its size and the paper
+#[wasm_bindgen]
+pub fn rectify_to_paper_size(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    corners: &[f32],
+    paper: PaperSize,
+    dpi: f32,
+) -> Result<PaperPage, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let quad = to_quad(corners)?;
+    if !(dpi > 0.0 && dpi <= MAX_DPI) {
+        return Err(ScanError::InvalidParameter("DPI must be in (0, 1200]"));
+    }
+    let (quad_width, quad_height) = quad_output_size(&quad);
+    let (page_width, page_height) = match paper.dimensions_mm() {
+        Some((w, h)) => {
+            let pixels = |mm: f32| ((mm / MM_PER_INCH * dpi).round() as usize).max(1);
+            if quad_width > quad_height {
+                (pixels(h), pixels(w))
+            } else {
+                (pixels(w), pixels(h))
+            }
+        }
+        None => (quad_width, quad_height),
+    };
+    let matrix = rect_to_quad_matrix(&quad, page_width, page_height)
+        .ok_or(ScanError::InvalidPoints("Page quad is degenerate"))?;
+    let policy = OutOfBoundsPolicy::Replicate;
+    let rgba = warp_with_matrix(rgba, width, height, &matrix, page_width, page_height, policy, 0);
+    Ok(PaperPage { paper, dpi, width: page_width, height: page_height, rgba })
+}
+
+/// Infers the paper size with `infer_paper_size` and rectifies the page to it
+/// at `dpi`, so exported PDFs get the right physical page size.
+///
+/// # Arguments
+/// * `rgba` - RGBA frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `corners` - Page quad (TL, TR, BR, BL as 8 values)
+/// * `dpi` - Output resolution, e.g. 300
+///
+/// # Returns
+/// `PaperPage` with the rectified image and the inferred paper
+#[wasm_bindgen]
+pub fn rectify_to_paper(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    corners: &[f32],
+    dpi: f32,
+) -> Result<PaperPage, ScanError> {
+    let paper = infer(&to_quad(corners)?, width, height);
+    rectify_to_paper_size(rgba, width, height, corners, paper, dpi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paper_size_and_dpi() {
+        // An A4 page turned and tilted away from the camera, so its quad in the
+        // frame is far from 1.414
+        let (width, height) = (320usize, 240usize);
+        let (f, turn, tilt) = (400.0f32, 25f32.to_radians(), 40f32.to_radians());
+        let project = |x: f32, y: f32| {
+            let (x3, z3) = (x * turn.cos(), -x * turn.sin());
+            let (y3, z3) = (y * tilt.cos() - z3 * tilt.sin(), 700.0 + y * tilt.sin() + z3 * tilt.cos());
+            [f * x3 / z3 + 160.0, f * y3 / z3 + 120.0]
+        };
+        let a4: Vec<f32> = [(-105.0, -148.5), (105.0, -148.5), (105.0, 148.5), (-105.0, 148.5)]
+            .iter()
+            .flat_map(|&(x, y)| project(x, y))
+            .collect();
+        let (w, h) = quad_output_size(&a4);
+        assert!(((h as f32 / w as f32) - 1.414).abs() > 0.1, "{w}x{h}");
+        assert_eq!(infer_paper_size(&a4, width, height).unwrap(), PaperSize::A4);
+
+        let letter = [50.0, 20.0, 220.0, 20.0, 220.0, 240.0, 50.0, 240.0];
+        assert_eq!(infer_paper_size(&letter, 400, 300).unwrap(), PaperSize::Letter);
+        let square = [50.0, 50.0, 150.0, 50.0, 150.0, 150.0, 50.0, 150.0];
+        assert_eq!(infer_paper_size(&square, 400, 300).unwrap(), PaperSize::Unknown);
+
+        // A4 at 20 DPI is 165 x 234 px; a landscape ID card at 300 DPI 1011 x 638
+        let rgba = vec![200u8; width * height * 4];
+        let page = rectify_to_paper(&rgba, width, height, &a4, 20.0).unwrap();
+        assert_eq!((page.paper(), page.width(), page.height()), (PaperSize::A4, 165, 234));
+        assert!((page.width_mm() - 209.55).abs() < 0.01 && !page.landscape());
+        assert_eq!(page.image().len(), 165 * 234 * 4);
+        let card = [10.0, 10.0, 110.0, 10.0, 110.0, 73.0, 10.0, 73.0];
+        let page = rectify_to_paper_size(&rgba, width, height, &card, PaperSize::IdCard, 300.0).unwrap();
+        assert_eq!((page.width(), page.height(), page.landscape()), (1011, 638, true));
+        assert_eq!(paper_dimensions(PaperSize::A5), vec![148.0, 210.0]);
+        assert!(rectify_to_paper(&rgba, width, height, &a4, 0.0).is_err());
+    }
+}
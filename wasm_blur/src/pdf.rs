@@ -0,0 +1,299 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String};
+
+use crate::error::{check_index, ScanError};
+use crate::paper::PaperSize;
+
+// PDF units (points) per inch and millimetres per inch.
+const POINTS_PER_INCH: f32 = 72.0;
+const MM_PER_INCH: f32 = 25.4;
+// Resolution assumed for pages of unknown paper size.
+const DEFAULT_DPI: f32 = 300.0;
+// Header, with a binary comment line so transfers treat the file as binary.
+const HEADER: &[u8] = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n";
+// Objects before the first page: catalog and page tree.
+const FIRST_PAGE_OBJECT: usize = 3;
+
+// Compressed image data and the dictionary entries that describe it.
+struct Page {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+    // Filter, colour space, bit depth and decode parameters
+    image: Vec<u8>,
+    // Page size in points
+    size: (f32, f32),
+}
+
+impl Page {
+    fn new(data: &[u8], width: usize, height: usize, image: String, paper: PaperSize) -> Page {
+        let size = match paper.dimensions_mm() {
+            Some((w, h)) => {
+                let (w, h) = (w / MM_PER_INCH * POINTS_PER_INCH, h / MM_PER_INCH * POINTS_PER_INCH);
+                // The paper is turned to the orientation of the image
+                if width > height {
+                    (h, w)
+                } else {
+                    (w, h)
+                }
+            }
+            None => (width as f32 / DEFAULT_DPI * POINTS_PER_INCH, height as f32 / DEFAULT_DPI * POINTS_PER_INCH),
+        };
+        Page { data: data.to_vec(), width, height, image: image.into_bytes(), size }
+    }
+
+    // Drawing commands that scale the image to fit the page, centred.
+    fn contents(&self) -> String {
+        let (page_width, page_height) = self.size;
+        let scale = (page_width / self.width as f32).min(page_height / self.height as f32);
+        let (width, height) = (self.width as f32 * scale, self.height as f32 * scale);
+        let (x, y) = ((page_width - width) / 2.0, (page_height - height) / 2.0);
+        format!("q {width:.2} 0 0 {height:.2} {x:.2} {y:.2} cm /Im0 Do Q")
+    }
+}
+
+// Width, height and number of components from the frame header of a JPEG.
+fn jpeg_frame(jpeg: &[u8]) -> Result<(usize, usize, u8), ScanError> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return Err(ScanError::InvalidData("Not a JPEG file"));
+    }
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xff {
+            return Err(ScanError::InvalidData("Malformed JPEG marker"));
+        }
+        let marker = jpeg[pos + 1];
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        // Start of frame, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            let header = jpeg.get(pos + 4..pos + 10).ok_or(ScanError::InvalidData("Truncated JPEG frame header"))?;
+            let height = u16::from_be_bytes([header[1], header[2]]) as usize;
+            let width = u16::from_be_bytes([header[3], header[4]]) as usize;
+            return Ok((width, height, header[5]));
+        }
+        if marker == 0xda {
+            break;
+        }
+        pos += 2 + len;
+    }
+    Err(ScanError::InvalidData("JPEG has no frame header"))
+}
+
+/// A PDF document of scanned pages. Each page embeds an already encoded
+/// image as it is, so JPEG pages keep the quality they were encoded with
+/// instead of being decoded and recompressed by a JS PDF library. Pages are
+/// sized to their paper (see `infer_paper_size`), turned to the orientation
+/// of the image, with the image fitted and centred.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct PdfDocument {
+    pages: Vec<Page>,
+}
+
+#[wasm_bindgen]
+impl PdfDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PdfDocument {
+        PdfDocument::default()
+    }
+
+    /// Adds a page showing a baseline or progressive JPEG (gray or RGB, e.g.
+    /// from `encode_jpeg`) and returns its index.
+    ///
+    /// # Arguments
+    /// * `jpeg` - JPEG file bytes
+    /// * `paper` - Paper size of the page; `Unknown` sizes the page to the
+    ///   image at 300 dpi
+    pub fn add_jpeg_page(&mut self, jpeg: &[u8], paper: PaperSize) -> Result<usize, ScanError> {
+        let (width, height, components) = jpeg_frame(jpeg)?;
+        let color_space = match components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            _ => return Err(ScanError::InvalidData("JPEG must have 1 or 3 components")),
+        };
+        if width == 0 || height == 0 {
+            return Err(ScanError::InvalidData("JPEG has no size"));
+        }
+        let image = format!("/Filter /DCTDecode /ColorSpace /{color_space} /BitsPerComponent 8");
+        self.pages.push(Page::new(jpeg, width, height, image, paper));
+        Ok(self.pages.len() - 1)
+    }
+
+    /// Adds a page showing a bilevel image coded as CCITT Group 4 (T.6), e.g.
+    /// by `encode_ccitt_g4`, and returns its index.
+    ///
+    /// # Arguments
+    /// * `data` - Group 4 coded image without a header
+    /// * `width` - Image width
+    /// * `height` - Image height
+    /// * `paper` - Paper size of the page; `Unknown` sizes the page to the
+    ///   image at 300 dpi
+    pub fn add_ccitt_page(
+        &mut self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        paper: PaperSize,
+    ) -> Result<usize, ScanError> {
+        if width == 0 || height == 0 {
+            return Err(ScanError::InvalidParameter("Image width and height must be at least 1"));
+        }
+        let image = format!(
+            "/Filter /CCITTFaxDecode /DecodeParms << /K -1 /Columns {width} /Rows {height} /BlackIs1 false >> \
+             /ColorSpace /DeviceGray /BitsPerComponent 1"
+        );
+        self.pages.push(Page::new(data, width, height, image, paper));
+        Ok(self.pages.len() - 1)
+    }
+
+    pub fn remove_page(&mut self, index: usize) -> Result<(), ScanError> {
+        check_index(index, self.pages.len())?;
+        self.pages.remove(index);
+        Ok(())
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The document as PDF file bytes; an error while there are no pages.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ScanError> {
+        if self.pages.is_empty() {
+            return Err(ScanError::InvalidParameter("A PDF needs at least one page"));
+        }
+        let data_len: usize = self.pages.iter().map(|page| page.data.len()).sum();
+        let mut out = Vec::with_capacity(data_len + 512 * self.pages.len() + 256);
+        out.extend_from_slice(HEADER);
+        let mut offsets = Vec::with_capacity(FIRST_PAGE_OBJECT + 3 * self.pages.len());
+        let mut object = |out: &mut Vec<u8>, body: &[u8]| {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        };
+
+        object(&mut out, b"<< /Type /Catalog /Pages 2 0 R >>");
+        // Page, contents and image objects of page i start at 3 + 3i
+        let kids: Vec<String> = (0..self.pages.len()).map(|i| format!("{} 0 R", FIRST_PAGE_OBJECT + 3 * i)).collect();
+        let tree = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.pages.len());
+        object(&mut out, tree.as_bytes());
+        for (i, page) in self.pages.iter().enumerate() {
+            let id = FIRST_PAGE_OBJECT + 3 * i;
+            let (width, height) = page.size;
+            let dictionary = format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width:.2} {height:.2}] /Contents {} 0 R \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> >>",
+                id + 1,
+                id + 2
+            );
+            object(&mut out, dictionary.as_bytes());
+            let contents = page.contents();
+            object(&mut out, format!("<< /Length {} >>\nstream\n{contents}\nendstream", contents.len()).as_bytes());
+            let mut image = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /Length {} ",
+                page.width,
+                page.height,
+                page.data.len()
+            )
+            .into_bytes();
+            image.extend_from_slice(&page.image);
+            image.extend_from_slice(b" >>\nstream\n");
+            image.extend_from_slice(&page.data);
+            image.extend_from_slice(b"\nendstream");
+            object(&mut out, &image);
+        }
+
+        // Cross-reference table of fixed 20-byte entries
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+        for offset in &offsets {
+            out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        let trailer = format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n", offsets.len() + 1);
+        out.extend_from_slice(trailer.as_bytes());
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "ccitt")]
+#[wasm_bindgen]
+impl PdfDocument {
+    /// Adds a page showing a black-and-white image, coded as CCITT Group 4,
+    /// and returns its index. The smallest pages for binarized text.
+    ///
+    /// # Arguments
+    /// * `image` - Grayscale or RGBA pixels, black below 128
+    /// * `width` - Image width
+    /// * `height` - Image height
+    /// * `paper` - Paper size of the page; `Unknown` sizes the page to the
+    ///   image at 300 dpi
+    pub fn add_bilevel_page(
+        &mut self,
+        image: &[u8],
+        width: usize,
+        height: usize,
+        paper: PaperSize,
+    ) -> Result<usize, ScanError> {
+        let data = crate::ccitt::encode_ccitt_g4(image, width, height)?;
+        self.add_ccitt_page(&data, width, height, paper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Byte offset of `needle` in `haystack` from `from` on.
+    fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+        haystack[from..].windows(needle.len()).position(|w| w == needle).map(|i| from + i)
+    }
+
+    #[test]
+    fn test_pdf_document() {
+        // A minimal JPEG header: SOI, an APP0 segment and a 3-component frame
+        // of 400 x 300
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0];
+        jpeg.extend_from_slice(&[0xff, 0xc0, 0, 17, 8, 1, 44, 1, 144, 3]);
+        jpeg.extend_from_slice(&[0; 9]);
+        jpeg.extend_from_slice(&[0xff, 0xd9]);
+        assert_eq!(jpeg_frame(&jpeg), Ok((400, 300, 3)));
+
+        let mut document = PdfDocument::new();
+        assert!(document.to_bytes().is_err());
+        assert_eq!(document.add_jpeg_page(&jpeg, PaperSize::A4), Ok(0));
+        assert_eq!(document.add_ccitt_page(&[0x26, 0xa0], 2480, 3508, PaperSize::Unknown), Ok(1));
+        assert_eq!(document.page_count(), 2);
+        let pdf = document.to_bytes().unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4\n") && pdf.ends_with(b"%%EOF\n"));
+
+        // Every cross-reference entry points at its object
+        let text = String::from_utf8_lossy(&pdf);
+        let start: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = core::str::from_utf8(&pdf[start..]).unwrap();
+        assert!(xref.starts_with("xref\n0 9\n"));
+        for (i, entry) in xref.lines().skip(3).take(8).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()), "object {}", i + 1);
+        }
+
+        // The landscape image turns the A4 page; the CCITT page is its pixel
+        // size at 300 dpi, which is A4 again
+        assert!(text.contains("/MediaBox [0 0 841.89 595.28]"));
+        assert!(text.contains("/MediaBox [0 0 595.20 841.92]"));
+        assert!(text.contains("/Kids [3 0 R 6 0 R] /Count 2"));
+        assert!(text.contains("/Columns 2480 /Rows 3508"));
+        let stream = find(&pdf, b"/Length 29 /Filter /DCTDecode", 0).unwrap();
+        assert!(find(&pdf, b"stream\n", stream).is_some_and(|s| pdf[s + 7..].starts_with(&jpeg)));
+
+        document.remove_page(0).unwrap();
+        assert_eq!(document.page_count(), 1);
+        #[cfg(feature = "ccitt")]
+        assert_eq!(document.add_bilevel_page(&[0, 255, 255, 0], 2, 2, PaperSize::Unknown), Ok(1));
+        assert!(document.add_jpeg_page(&jpeg[2..], PaperSize::A4).is_err());
+        assert!(document.add_ccitt_page(&[], 0, 10, PaperSize::A4).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::binarize::{halftone_blocks, BLOCK};
+use crate::error::{check_index, check_rgba, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::warp::{quad_output_size, rect_to_quad_matrix, warp_with_matrix, OutOfBoundsPolicy};
+
+// Smallest photo in halftone blocks; fewer are a stray textured patch.
+const MIN_BLOCKS: usize = 4;
+// Pixels this far from the paper level belong to a photo.
+const PAPER_MARGIN: u8 = 32;
+// Outer rows and columns of the search area with less photo than this share
+// are trimmed, so captions next to a photo don't stretch its crop.
+const MIN_LINE_COVERAGE: f32 = 0.5;
+
+struct Photo {
+    corners: [f32; 8],
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+/// Photos found by `extract_photos`, in the order of their top-left block, each
+/// with its quad on the page and the rectified crop.
+#[wasm_bindgen]
+pub struct PhotoRegions {
+    photos: Vec<Photo>,
+}
+
+#[wasm_bindgen]
+impl PhotoRegions {
+    pub fn count(&self) -> usize {
+        self.photos.len()
+    }
+
+    /// Corners of one photo on the page (TL, TR, BR, BL as 8 values).
+    pub fn corners(&self, index: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(index)?.corners.to_vec())
+    }
+
+    pub fn width(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.width)
+    }
+
+    pub fn height(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.height)
+    }
+
+    /// Rectified RGBA pixels of one photo.
+    pub fn image(&self, index: usize) -> Result<Vec<u8>, ScanError> {
+        Ok(self.get(index)?.rgba.clone())
+    }
+}
+
+impl PhotoRegions {
+    fn get(&self, index: usize) -> Result<&Photo, ScanError> {
+        check_index(index, self.photos.len())?;
+        Ok(&self.photos[index])
+    }
+}
+
+// 8-connected groups of photo blocks, as block indices.
+fn block_groups(blocks: &[bool], bw: usize, bh: usize) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; blocks.len()];
+    let mut groups = Vec::new();
+    for start in 0..blocks.len() {
+        if !blocks[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut group = vec![start];
+        let mut next = 0;
+        while next < group.len() {
+            let (bx, by) = (group[next] % bw, group[next] / bw);
+            next += 1;
+            for ny in by.saturating_sub(1)..(by + 2).min(bh) {
+                for nx in bx.saturating_sub(1)..(bx + 2).min(bw) {
+                    let n = ny * bw + nx;
+                    if blocks[n] && !seen[n] {
+                        seen[n] = true;
+                        group.push(n);
+                    }
+                }
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+// Median luma of the pixels outside photo blocks, 255 on an all-photo page.
+fn paper_level(gray: &[u8], width: usize, blocks: &[bool], bw: usize) -> u8 {
+    let mut histogram = [0usize; 256];
+    for (i, &v) in gray.iter().enumerate() {
+        if !blocks[(i / width / BLOCK) * bw + (i % width) / BLOCK] {
+            histogram[v as usize] += 1;
+        }
+    }
+    let total: usize = histogram.iter().sum();
+    let mut seen = 0;
+    histogram
+        .iter()
+        .position(|&n| {
+            seen += n;
+            total > 0 && 2 * seen > total
+        })
+        .map_or(255, |level| level as u8)
+}
+
+// Quad of the photo covering one block group: its pixels off the paper level
+// in the group's blocks plus a block of margin, with sparse outer rows and
+// columns of the margin trimmed, fitted by the extremes along the diagonals.
+fn photo_corners(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    group: &[usize],
+    (blocks, bw): (&[bool], usize),
+    paper: u8,
+) -> Option<[f32; 8]> {
+    let (mut bx0, mut by0, mut bx1, mut by1) = (usize::MAX, usize::MAX, 0, 0);
+    for &b in group {
+        (bx0, by0, bx1, by1) = (bx0.min(b % bw), by0.min(b / bw), bx1.max(b % bw), by1.max(b / bw));
+    }
+    // The group's block box in pixels, and the search area one block around it
+    let (inner_x0, inner_y0) = (bx0 * BLOCK, by0 * BLOCK);
+    let (inner_x1, inner_y1) = (((bx1 + 1) * BLOCK).min(width), ((by1 + 1) * BLOCK).min(height));
+    let (mut x0, mut y0) = (inner_x0.saturating_sub(BLOCK), inner_y0.saturating_sub(BLOCK));
+    let (mut x1, mut y1) = ((inner_x1 + BLOCK).min(width), (inner_y1 + BLOCK).min(height));
+
+    // Other photos' blocks are off limits
+    let own = |x: usize, y: usize| {
+        let b = (y / BLOCK) * bw + x / BLOCK;
+        !blocks[b] || group.contains(&b)
+    };
+    let is_photo = |x: usize, y: usize| own(x, y) && gray[y * width + x].abs_diff(paper) > PAPER_MARGIN;
+    let row_coverage = |y: usize, x0: usize, x1: usize| {
+        (x0..x1).filter(|&x| is_photo(x, y)).count() as f32 / (x1 - x0).max(1) as f32
+    };
+    let column_coverage = |x: usize, y0: usize, y1: usize| {
+        (y0..y1).filter(|&y| is_photo(x, y)).count() as f32 / (y1 - y0).max(1) as f32
+    };
+    while y0 < inner_y0 && row_coverage(y0, x0, x1) < MIN_LINE_COVERAGE {
+        y0 += 1;
+    }
+    while y1 > inner_y1 && row_coverage(y1 - 1, x0, x1) < MIN_LINE_COVERAGE {
+        y1 -= 1;
+    }
+    while x0 < inner_x0 && column_coverage(x0, y0, y1) < MIN_LINE_COVERAGE {
+        x0 += 1;
+    }
+    while x1 > inner_x1 && column_coverage(x1 - 1, y0, y1) < MIN_LINE_COVERAGE {
+        x1 -= 1;
+    }
+
+    // Extremes of x + y and x - y: TL, TR, BR, BL
+    let mut extremes = [(f32::INFINITY, (0f32, 0f32)); 4];
+    for y in y0..y1 {
+        for x in (x0..x1).filter(|&x| is_photo(x, y)) {
+            let (fx, fy) = (x as f32, y as f32);
+            for (extreme, key) in extremes.iter_mut().zip([fx + fy, fy - fx, -(fx + fy), fx - fy]) {
+                if key < extreme.0 {
+                    *extreme = (key, (fx, fy));
+                }
+            }
+        }
+    }
+    if extremes[0].0.is_infinite() {
+        return None;
+    }
+    let mut corners = [0f32; 8];
+    for (corner, (_, (x, y))) in corners.chunks_exact_mut(2).zip(extremes) {
+        corner.copy_from_slice(&[x, y]);
+    }
+    Some(corners)
+}
+
+/// Finds the photographs on a page with text, e.g. the portrait on an ID page
+/// or the prints on an album page, and returns each one as a separate
+/// rectified crop. Photos are the continuous-tone and halftone regions
+/// `halftone_mask` finds; their outline is fitted to the pixels that stand out
+/// from the paper, so slightly tilted prints come out straight.
+///
+/// # Arguments
+/// * `rgba` - RGBA page, ideally already rectified
+/// * `width` - Page width
+/// * `height` - Page height
+///
+/// # Returns
+/// `PhotoRegions` with the corners and rectified image of each photo
+#[wasm_bindgen]
+pub fn extract_photos(rgba: &[u8], width: usize, height: usize) -> Result<PhotoRegions, ScanError> {
+    check_rgba(rgba, width, height)?;
+    let mut gray = vec![0u8; width * height];
+    rgba_to_grayscale_into(rgba, &mut gray, GrayscaleWeights::Bt601);
+    let (blocks, bw, bh) = halftone_blocks(&gray, width, height);
+    let paper = paper_level(&gray, width, &blocks, bw);
+
+    let photos = block_groups(&blocks, bw, bh)
+        .into_iter()
+        .filter(|group| group.len() >= MIN_BLOCKS)
+        .filter_map(|group| {
+            let corners = photo_corners(&gray, width, height, &group, (&blocks, bw), paper)?;
+            let (photo_width, photo_height) = quad_output_size(&corners);
+            let matrix = rect_to_quad_matrix(&corners, photo_width, photo_height)?;
+            let policy = OutOfBoundsPolicy::Replicate;
+            let rgba = warp_with_matrix(rgba, width, height, &matrix, photo_width, photo_height, policy, 0);
+            Some(Photo { corners, width: photo_width, height: photo_height, rgba })
+        })
+        .collect();
+    Ok(PhotoRegions { photos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_photos() {
+        // An ID page: lines of text, a portrait photo with a caption right
+        // under it, and a small textured stamp that is no photo
+        let (width, height) = (192, 144);
+        let mut gray = vec![235u8; width * height];
+        for (i, v) in gray.iter_mut().enumerate() {
+            let (x, y) = (i % width, i / width);
+            if (40..104).contains(&x) && (32..96).contains(&y) {
+                *v = (60 + (x - 40) + (y - 32)) as u8;
+            } else if (100..103).contains(&y) && (40..104).contains(&x) && x % 6 < 2 {
+                *v = 20;
+            } else if x >= 120 && y % 10 < 2 && x % 9 < 6 {
+                *v = 30;
+            } else if (150..166).contains(&x) && (120..136).contains(&y) {
+                *v = 128;
+            }
+        }
+        let rgba: Vec<u8> = gray.iter().flat_map(|&v| [v, v, v, 255]).collect();
+
+        let photos = extract_photos(&rgba, width, height).unwrap();
+        assert_eq!(photos.count(), 1);
+        let corners = photos.corners(0).unwrap();
+        let expected = [40.0, 32.0, 103.0, 32.0, 103.0, 95.0, 40.0, 95.0];
+        assert!(corners.iter().zip(expected).all(|(c, e)| (c - e).abs() <= 1.0), "{corners:?}");
+        let (w, h) = (photos.width(0).unwrap(), photos.height(0).unwrap());
+        assert!(w.abs_diff(64) <= 2 && h.abs_diff(64) <= 2, "{w}x{h}");
+        // The crop starts dark at the top-left and brightens to the bottom-right
+        let image = photos.image(0).unwrap();
+        assert!(image[0] < 70 && image[(w * h - 1) * 4] > 170);
+        assert!(photos.image(1).is_err());
+    }
+}
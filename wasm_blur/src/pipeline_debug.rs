@@ -0,0 +1,140 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::canny::CannyOptions;
+use crate::error::{check_gray, check_kernel_size, ScanError};
+use crate::gaussian_blur::blur_with_border;
+use crate::gradient_calculation::calculate_gradients;
+use crate::hysteresis::{edge_map_to_binary, hysteresis_thresholding};
+use crate::non_maximum_suppression::non_maximum_suppression;
+
+/// Every intermediate stage of one Canny run, from `canny_edge_detector_debug`,
+/// for devtools that show why detection failed on a frame. Each plane is
+/// width * height values; the getters copy them out as typed arrays. Contours
+/// are traced in JS, which adds them to the same debug object.
+#[wasm_bindgen]
+pub struct PipelineDebug {
+    blurred: Vec<u8>,
+    magnitude: Vec<f32>,
+    suppressed: Vec<f32>,
+    edge_map: Vec<u8>,
+    canny_edges: Vec<u8>,
+    edges: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl PipelineDebug {
+    /// Blurred grayscale frame
+    pub fn blurred(&self) -> Vec<u8> {
+        self.blurred.clone()
+    }
+
+    /// Sobel gradient magnitude (L1 or L2, as configured)
+    pub fn magnitude(&self) -> Vec<f32> {
+        self.magnitude.clone()
+    }
+
+    /// Magnitude left after non-maximum suppression, 0 elsewhere
+    pub fn suppressed(&self) -> Vec<f32> {
+        self.suppressed.clone()
+    }
+
+    /// Hysteresis classes: 0 weak edge that wasn't connected, 1 no edge,
+    /// 2 edge
+    pub fn edge_map(&self) -> Vec<u8> {
+        self.edge_map.clone()
+    }
+
+    /// Binary edges (0 or 255) before dilation
+    pub fn canny_edges(&self) -> Vec<u8> {
+        self.canny_edges.clone()
+    }
+
+    /// Binary edges (0 or 255) after dilation, as `canny_edge_detector` returns
+    pub fn edges(&self) -> Vec<u8> {
+        self.edges.clone()
+    }
+}
+
+/// `canny_edge_detector` run stage by stage, keeping each intermediate plane.
+/// The final edges are identical; the extra full-frame planes make it slower,
+/// so use it only while debugging.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale pixels
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `options` - Thresholds, blur, gradient and dilation settings
+///
+/// # Returns
+/// `PipelineDebug` with the intermediate stages and final edges
+#[wasm_bindgen]
+pub fn canny_edge_detector_debug(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    options: &CannyOptions,
+) -> Result<PipelineDebug, ScanError> {
+    check_gray(grayscale, width, height)?;
+    let options = options.for_size(width, height);
+    check_kernel_size(options.kernel_size())?;
+    let l2_gradient = options.l2_gradient();
+    let (kernel_size, sigma, border) = (options.kernel_size(), options.sigma(), options.border_mode());
+    let blurred = blur_with_border(grayscale, width, height, kernel_size, sigma, border)?;
+
+    let gradients = calculate_gradients(&blurred, width, height)?;
+    let dx: Vec<i16> = gradients.iter().step_by(2).copied().collect();
+    let dy: Vec<i16> = gradients.iter().skip(1).step_by(2).copied().collect();
+    let magnitude = dx
+        .iter()
+        .zip(&dy)
+        .map(|(&gx, &gy)| {
+            let (fx, fy) = (gx as f32, gy as f32);
+            if l2_gradient { (fx * fx + fy * fy).sqrt() } else { fx.abs() + fy.abs() }
+        })
+        .collect();
+    let suppressed = non_maximum_suppression(&dx, &dy, width, height, l2_gradient)?;
+
+    // Same threshold scaling as the fused pipeline
+    let (low, high) = (options.low_threshold(), options.high_threshold());
+    let (low, high) = if l2_gradient { (low * low, high * high) } else { (low, high) };
+    let edge_map = hysteresis_thresholding(&suppressed, width, height, low, high)?;
+    let canny_edges = edge_map_to_binary(&edge_map);
+    let edges = match options.dilation_kernel_size() {
+        0 => canny_edges.clone(),
+        kernel_size => crate::dilation::dilate(&canny_edges, width, height, kernel_size)?,
+    };
+
+    Ok(PipelineDebug { blurred, magnitude, suppressed, edge_map, canny_edges, edges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canny::canny_edge_detector;
+
+    #[test]
+    fn test_debug_stages_match_canny() {
+        let (width, height) = (48, 40);
+        let gray: Vec<u8> = (0..width * height)
+            .map(|i| if (10..38).contains(&(i % width)) && (8..30).contains(&(i / width)) { 210 } else { 50 })
+            .collect();
+        let options = CannyOptions::new();
+        let debug = canny_edge_detector_debug(&gray, width, height, &options).unwrap();
+        assert_eq!(debug.edges(), canny_edge_detector(&gray, width, height, &options).unwrap());
+
+        // Every edge survived suppression above the low threshold, and the
+        // stages stay consistent with each other
+        let (edge_map, suppressed, magnitude) = (debug.edge_map(), debug.suppressed(), debug.magnitude());
+        for (i, &edge) in debug.canny_edges().iter().enumerate() {
+            assert_eq!(edge == 255, edge_map[i] == 2);
+            if edge == 255 {
+                assert!(suppressed[i] >= 75.0 && suppressed[i] <= magnitude[i]);
+            }
+        }
+        assert!(debug.canny_edges().contains(&255) && debug.blurred()[0] == 50);
+        assert!(canny_edge_detector_debug(&gray[1..], width, height, &options).is_err());
+    }
+}
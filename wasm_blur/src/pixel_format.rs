@@ -0,0 +1,440 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+use crate::grayscale::{self, GrayscaleWeights};
+
+/// Layout of an input frame. Planar YUV formats use 4:2:0 chroma with odd sizes
+/// rounded up, i.e. (width + 1) / 2 x (height + 1) / 2 chroma samples.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// R, G, B, A bytes per pixel (`ImageData`, WebGL readback)
+    Rgba8 = 0,
+    /// B, G, R, A bytes per pixel (Android `ImageReader`, many desktop capture APIs)
+    Bgra8 = 1,
+    /// R, G, B bytes per pixel without alpha
+    Rgb8 = 2,
+    /// One luma byte per pixel
+    Gray8 = 3,
+    /// Y plane followed by one interleaved U/V plane (`VideoFrame` "NV12")
+    Nv12 = 4,
+    /// Y plane followed by separate U and V planes (`VideoFrame` "I420")
+    I420 = 5,
+    /// Little-endian 16-bit 5-6-5 RGB
+    Rgb565 = 6,
+}
+
+/// Bytes one `width` x `height` frame occupies in `format`.
+pub(crate) fn frame_len(format: PixelFormat, width: usize, height: usize) -> usize {
+    let chroma = width.div_ceil(2) * height.div_ceil(2);
+    match format {
+        PixelFormat::Rgba8 | PixelFormat::Bgra8 => width * height * 4,
+        PixelFormat::Rgb8 => width * height * 3,
+        PixelFormat::Gray8 => width * height,
+        PixelFormat::Rgb565 => width * height * 2,
+        PixelFormat::Nv12 | PixelFormat::I420 => width * height + 2 * chroma,
+    }
+}
+
+// Video-range luma (16..235) to full-range gray, Q7 (149 / 128 = 255 / 219). Same
+// result as converting a neutral YUV pixel to RGB with `yuv_to_rgb`.
+#[inline]
+fn expand_luma_scalar(y: u8) -> u8 {
+    ((y.saturating_sub(16) as u16 * 149 + 64) >> 7).min(255) as u8
+}
+
+// BT.601 video-range YUV to RGB, Q8.
+#[inline]
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = 298 * (y as i32 - 16);
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |x: i32| ((x + 128) >> 8).clamp(0, 255) as u8;
+    [clamp(c + 409 * e), clamp(c - 100 * d - 208 * e), clamp(c + 516 * d)]
+}
+
+// Bit replication keeps 0 at 0 and the channel maximum at 255.
+#[inline]
+fn rgb565_to_rgb(lo: u8, hi: u8) -> [u8; 3] {
+    let p = u16::from_le_bytes([lo, hi]);
+    let r = (p >> 11) as u8;
+    let g = ((p >> 5) & 0x3f) as u8;
+    let b = (p & 0x1f) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+// Swaps the R and B bytes of every 4-byte pixel (BGRA <-> RGBA).
+fn swap_red_blue(src: &[u8], dst: &mut [u8]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { swap_red_blue_simd(src, dst) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (out, px) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)).skip(done) {
+        out.copy_from_slice(&[px[2], px[1], px[0], px[3]]);
+    }
+}
+
+// 4 pixels per iteration; returns how many pixels were done.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn swap_red_blue_simd(src: &[u8], dst: &mut [u8]) -> usize {
+    let pixels = dst.len() / 4;
+    let mut x = 0;
+    while x + 4 <= pixels {
+        let v = v128_load(src.as_ptr().add(x * 4) as *const v128);
+        let v = i8x16_shuffle::<2, 1, 0, 3, 6, 5, 4, 7, 10, 9, 8, 11, 14, 13, 12, 15>(v, v);
+        v128_store(dst.as_mut_ptr().add(x * 4) as *mut v128, v);
+        x += 4;
+    }
+    x
+}
+
+fn rgb_to_rgba(src: &[u8], dst: &mut [u8]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { rgb_to_rgba_simd(src, dst) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (out, px) in dst.chunks_exact_mut(4).zip(src.chunks_exact(3)).skip(done) {
+        out.copy_from_slice(&[px[0], px[1], px[2], 255]);
+    }
+}
+
+// 16 pixels (48 source bytes) per iteration. Each output vector gathers four
+// 3-byte pixels, possibly straddling two loads; alpha is OR-ed in afterwards.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn rgb_to_rgba_simd(src: &[u8], dst: &mut [u8]) -> usize {
+    let pixels = dst.len() / 4;
+    let alpha = u32x4_splat(0xff00_0000);
+    let mut x = 0;
+    while x + 16 <= pixels {
+        let s = src.as_ptr().add(x * 3);
+        let v0 = v128_load(s as *const v128);
+        let v1 = v128_load(s.add(16) as *const v128);
+        let v2 = v128_load(s.add(32) as *const v128);
+
+        let p0 = i8x16_shuffle::<0, 1, 2, 0, 3, 4, 5, 0, 6, 7, 8, 0, 9, 10, 11, 0>(v0, v0);
+        let p1 = i8x16_shuffle::<12, 13, 14, 0, 15, 16, 17, 0, 18, 19, 20, 0, 21, 22, 23, 0>(v0, v1);
+        let p2 = i8x16_shuffle::<8, 9, 10, 0, 11, 12, 13, 0, 14, 15, 16, 0, 17, 18, 19, 0>(v1, v2);
+        let p3 = i8x16_shuffle::<4, 5, 6, 0, 7, 8, 9, 0, 10, 11, 12, 0, 13, 14, 15, 0>(v2, v2);
+
+        let d = dst.as_mut_ptr().add(x * 4);
+        v128_store(d as *mut v128, v128_or(p0, alpha));
+        v128_store(d.add(16) as *mut v128, v128_or(p1, alpha));
+        v128_store(d.add(32) as *mut v128, v128_or(p2, alpha));
+        v128_store(d.add(48) as *mut v128, v128_or(p3, alpha));
+        x += 16;
+    }
+    x
+}
+
+fn gray_to_rgba(src: &[u8], dst: &mut [u8]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { gray_to_rgba_simd(src, dst) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (out, &v) in dst.chunks_exact_mut(4).zip(src).skip(done) {
+        out.copy_from_slice(&[v, v, v, 255]);
+    }
+}
+
+// 16 pixels per iteration, each luma byte broadcast to R, G and B.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn gray_to_rgba_simd(src: &[u8], dst: &mut [u8]) -> usize {
+    let alpha = u32x4_splat(0xff00_0000);
+    let mut x = 0;
+    while x + 16 <= src.len() {
+        let v = v128_load(src.as_ptr().add(x) as *const v128);
+        let p0 = i8x16_shuffle::<0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3>(v, v);
+        let p1 = i8x16_shuffle::<4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7>(v, v);
+        let p2 = i8x16_shuffle::<8, 8, 8, 8, 9, 9, 9, 9, 10, 10, 10, 10, 11, 11, 11, 11>(v, v);
+        let p3 = i8x16_shuffle::<12, 12, 12, 12, 13, 13, 13, 13, 14, 14, 14, 14, 15, 15, 15, 15>(v, v);
+
+        let d = dst.as_mut_ptr().add(x * 4);
+        v128_store(d as *mut v128, v128_or(p0, alpha));
+        v128_store(d.add(16) as *mut v128, v128_or(p1, alpha));
+        v128_store(d.add(32) as *mut v128, v128_or(p2, alpha));
+        v128_store(d.add(48) as *mut v128, v128_or(p3, alpha));
+        x += 16;
+    }
+    x
+}
+
+fn rgb565_to_rgba(src: &[u8], dst: &mut [u8]) {
+    for (out, px) in dst.chunks_exact_mut(4).zip(src.chunks_exact(2)) {
+        let [r, g, b] = rgb565_to_rgb(px[0], px[1]);
+        out.copy_from_slice(&[r, g, b, 255]);
+    }
+}
+
+fn rgb565_to_gray(src: &[u8], gray: &mut [u8], weights: (u16, u16, u16)) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { rgb565_to_gray_simd(src, gray, weights) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (out, px) in gray.iter_mut().zip(src.chunks_exact(2)).skip(done) {
+        let [r, g, b] = rgb565_to_rgb(px[0], px[1]);
+        *out = grayscale::luma_scalar(r, g, b, weights);
+    }
+}
+
+// 16 pixels per iteration: each u16 lane holds one pixel, so the channels are
+// extracted with shifts and masks and widened by bit replication in place.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn rgb565_to_gray_simd(src: &[u8], gray: &mut [u8], weights: (u16, u16, u16)) -> usize {
+    let wr = u16x8_splat(weights.0);
+    let wg = u16x8_splat(weights.1);
+    let wb = u16x8_splat(weights.2);
+    let round = u16x8_splat(128);
+    let mask5 = u16x8_splat(0x1f);
+    let mask6 = u16x8_splat(0x3f);
+
+    let luma = |p: v128| {
+        let r = u16x8_shr(p, 11);
+        let g = v128_and(u16x8_shr(p, 5), mask6);
+        let b = v128_and(p, mask5);
+        let r = v128_or(u16x8_shl(r, 3), u16x8_shr(r, 2));
+        let g = v128_or(u16x8_shl(g, 2), u16x8_shr(g, 4));
+        let b = v128_or(u16x8_shl(b, 3), u16x8_shr(b, 2));
+        let sum = u16x8_add(u16x8_add(u16x8_mul(r, wr), u16x8_mul(g, wg)), u16x8_add(u16x8_mul(b, wb), round));
+        u16x8_shr(sum, 8)
+    };
+
+    let mut x = 0;
+    while x + 16 <= gray.len() {
+        let s = src.as_ptr().add(x * 2);
+        let lo = luma(v128_load(s as *const v128));
+        let hi = luma(v128_load(s.add(16) as *const v128));
+        v128_store(gray.as_mut_ptr().add(x) as *mut v128, u8x16_narrow_i16x8(lo, hi));
+        x += 16;
+    }
+    x
+}
+
+fn expand_luma(y: &[u8], gray: &mut [u8]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { expand_luma_simd(y, gray) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (out, &v) in gray.iter_mut().zip(y).skip(done) {
+        *out = expand_luma_scalar(v);
+    }
+}
+
+// 16 pixels per iteration in u16; the saturating narrow clamps to 255.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn expand_luma_simd(y: &[u8], gray: &mut [u8]) -> usize {
+    let black = u8x16_splat(16);
+    let scale = u16x8_splat(149);
+    let round = u16x8_splat(64);
+    let mut x = 0;
+    while x + 16 <= gray.len() {
+        let v = u8x16_sub_sat(v128_load(y.as_ptr().add(x) as *const v128), black);
+        let lo = u16x8_shr(u16x8_add(u16x8_mul(u16x8_extend_low_u8x16(v), scale), round), 7);
+        let hi = u16x8_shr(u16x8_add(u16x8_mul(u16x8_extend_high_u8x16(v), scale), round), 7);
+        v128_store(gray.as_mut_ptr().add(x) as *mut v128, u8x16_narrow_i16x8(lo, hi));
+        x += 16;
+    }
+    x
+}
+
+fn yuv420_to_rgba(src: &[u8], format: PixelFormat, width: usize, height: usize, dst: &mut [u8]) {
+    let chroma_width = width.div_ceil(2);
+    let chroma_len = chroma_width * height.div_ceil(2);
+    let (luma, chroma) = src.split_at(width * height);
+
+    for (y, (out_row, y_row)) in dst.chunks_exact_mut(width * 4).zip(luma.chunks_exact(width)).enumerate() {
+        let chroma_row = (y / 2) * chroma_width;
+        for (x, (out, &luma)) in out_row.chunks_exact_mut(4).zip(y_row).enumerate() {
+            let (u, v) = match format {
+                PixelFormat::Nv12 => {
+                    let i = (chroma_row + x / 2) * 2;
+                    (chroma[i], chroma[i + 1])
+                }
+                _ => {
+                    let i = chroma_row + x / 2;
+                    (chroma[i], chroma[chroma_len + i])
+                }
+            };
+            let [r, g, b] = yuv_to_rgb(luma, u, v);
+            out.copy_from_slice(&[r, g, b, 255]);
+        }
+    }
+}
+
+// Converts a frame into a caller-owned RGBA buffer of width * height * 4 bytes.
+pub(crate) fn to_rgba_into(src: &[u8], format: PixelFormat, width: usize, height: usize, rgba: &mut [u8]) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    match format {
+        PixelFormat::Rgba8 => rgba.copy_from_slice(src),
+        PixelFormat::Bgra8 => swap_red_blue(src, rgba),
+        PixelFormat::Rgb8 => rgb_to_rgba(src, rgba),
+        PixelFormat::Gray8 => gray_to_rgba(src, rgba),
+        PixelFormat::Rgb565 => rgb565_to_rgba(src, rgba),
+        PixelFormat::Nv12 | PixelFormat::I420 => yuv420_to_rgba(src, format, width, height, rgba),
+    }
+}
+
+// Converts a frame into a caller-owned grayscale buffer of width * height bytes.
+// YUV frames use their luma plane directly (`weights` does not apply). BGRA and
+// RGB565, the common Android camera formats, have direct kernels; RGB goes
+// through RGBA one row at a time.
+pub(crate) fn to_gray_into(
+    src: &[u8],
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    weights: GrayscaleWeights,
+    gray: &mut [u8],
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    match format {
+        PixelFormat::Rgba8 => grayscale::rgba_to_grayscale_into(src, gray, weights),
+        PixelFormat::Bgra8 => {
+            let (wr, wg, wb) = grayscale::fixed_weights(weights);
+            grayscale::packed_to_grayscale_into(src, gray, (wb, wg, wr));
+        }
+        PixelFormat::Rgb565 => rgb565_to_gray(src, gray, grayscale::fixed_weights(weights)),
+        PixelFormat::Gray8 => gray.copy_from_slice(src),
+        PixelFormat::Nv12 | PixelFormat::I420 => expand_luma(&src[..width * height], gray),
+        _ => {
+            let src_row_len = frame_len(format, width, 1);
+            let mut rgba_row = vec![0u8; width * 4];
+            for (src_row, gray_row) in src.chunks_exact(src_row_len).zip(gray.chunks_exact_mut(width)) {
+                to_rgba_into(src_row, format, width, 1, &mut rgba_row);
+                grayscale::rgba_to_grayscale_into(&rgba_row, gray_row, weights);
+            }
+        }
+    }
+}
+
+// `frame_len` for dimensions from the caller, which may be too large to address.
+pub(crate) fn checked_frame_len(format: PixelFormat, width: usize, height: usize) -> Result<usize, ScanError> {
+    // No format takes more than 4 bytes per pixel
+    image_len(width, height, 4)?;
+    Ok(frame_len(format, width, height))
+}
+
+/// Size in bytes of a `width` x `height` frame in `format`, e.g. to allocate the
+/// buffer passed to `ScanContext::alloc_frame_buffer`; an error for dimensions
+/// too large to address.
+#[wasm_bindgen]
+pub fn pixel_format_frame_len(format: PixelFormat, width: usize, height: usize) -> Result<usize, ScanError> {
+    checked_frame_len(format, width, height)
+}
+
+/// Converts a frame in any supported pixel format to RGBA.
+///
+/// # Arguments
+/// * `src` - Frame bytes laid out as `format`
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `format` - Layout of `src`
+///
+/// # Returns
+/// RGBA image as Vec<u8>
+#[wasm_bindgen]
+pub fn convert_to_rgba(src: &[u8], width: usize, height: usize, format: PixelFormat) -> Result<Vec<u8>, ScanError> {
+    check_len(src.len(), checked_frame_len(format, width, height)?, "the frame size for this pixel format")?;
+    let mut rgba = vec![0u8; width * height * 4];
+    to_rgba_into(src, format, width, height, &mut rgba);
+    Ok(rgba)
+}
+
+/// Converts a frame in any supported pixel format to 8-bit grayscale.
+///
+/// # Arguments
+/// * `src` - Frame bytes laid out as `format`
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `format` - Layout of `src`
+/// * `weights` - Luma weights for RGB formats; YUV formats use their Y plane
+///
+/// # Returns
+/// Grayscale image as Vec<u8>
+#[wasm_bindgen]
+pub fn convert_to_grayscale(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    weights: GrayscaleWeights,
+) -> Result<Vec<u8>, ScanError> {
+    check_len(src.len(), checked_frame_len(format, width, height)?, "the frame size for this pixel format")?;
+    let mut gray = vec![0u8; width * height];
+    to_gray_into(src, format, width, height, weights, &mut gray);
+    Ok(gray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_agree() {
+        let (width, height) = (37, 5);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| [(i * 7) as u8, (i * 13 + 50) as u8, (i * 3 + 100) as u8, 255])
+            .collect();
+        let gray = grayscale::rgba_to_grayscale(&rgba, width, height, GrayscaleWeights::Bt601).unwrap();
+
+        // Lossless packed layouts of the same image convert back to identical RGBA and gray
+        let bgra: Vec<u8> = rgba.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+        let rgb: Vec<u8> = rgba.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+        for (src, format) in [(&bgra, PixelFormat::Bgra8), (&rgb, PixelFormat::Rgb8)] {
+            assert_eq!(convert_to_rgba(src, width, height, format).unwrap(), rgba);
+            assert_eq!(convert_to_grayscale(src, width, height, format, GrayscaleWeights::Bt601).unwrap(), gray);
+        }
+        let expanded = convert_to_rgba(&gray, width, height, PixelFormat::Gray8).unwrap();
+        assert!(expanded.chunks_exact(4).zip(&gray).all(|(p, &v)| p == [v, v, v, 255]));
+
+        // RGB565 white/black and channel maxima survive bit replication
+        let rgb565 = [0xff, 0xff, 0x00, 0x00, 0x00, 0xf8, 0xe0, 0x07];
+        assert_eq!(
+            convert_to_rgba(&rgb565, 4, 1, PixelFormat::Rgb565).unwrap(),
+            vec![255, 255, 255, 255, 0, 0, 0, 255, 255, 0, 0, 255, 0, 255, 0, 255]
+        );
+        // The direct RGB565 gray kernel matches converting through RGBA
+        let rgb565: Vec<u8> = rgba[..width * height * 2].to_vec();
+        let via_rgba = convert_to_rgba(&rgb565, width, height, PixelFormat::Rgb565).unwrap();
+        assert_eq!(
+            convert_to_grayscale(&rgb565, width, height, PixelFormat::Rgb565, GrayscaleWeights::Bt709).unwrap(),
+            grayscale::rgba_to_grayscale(&via_rgba, width, height, GrayscaleWeights::Bt709).unwrap()
+        );
+
+        // NV12 and I420 with neutral chroma: gray and RGB agree, video range is expanded
+        let (w, h) = (5usize, 3usize);
+        let luma: Vec<u8> = (0..w * h).map(|i| (i * 17) as u8).collect();
+        let chroma_len = w.div_ceil(2) * h.div_ceil(2);
+        let mut nv12 = luma.clone();
+        nv12.resize(frame_len(PixelFormat::Nv12, w, h), 128);
+        let mut i420 = luma.clone();
+        i420.resize(luma.len() + 2 * chroma_len, 128);
+        let nv12_rgba = convert_to_rgba(&nv12, w, h, PixelFormat::Nv12).unwrap();
+        assert_eq!(nv12_rgba, convert_to_rgba(&i420, w, h, PixelFormat::I420).unwrap());
+        let nv12_gray = convert_to_grayscale(&nv12, w, h, PixelFormat::Nv12, GrayscaleWeights::Bt601).unwrap();
+        assert!(nv12_rgba.chunks_exact(4).zip(&nv12_gray).all(|(p, &v)| p == [v, v, v, 255]));
+        assert_eq!((nv12_gray[0], nv12_gray[1], nv12_gray[14]), (0, 1, 255));
+    }
+}
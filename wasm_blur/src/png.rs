@@ -0,0 +1,250 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+// zlib level, the usual balance of speed and size.
+const COMPRESSION_LEVEL: u8 = 6;
+// Levels at or above this are white in bilevel output.
+const BILEVEL_THRESHOLD: u8 = 128;
+// Largest image side a PNG header can hold.
+const MAX_SIDE: usize = i32::MAX as usize;
+
+/// Pixel format of an encoded PNG.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngMode {
+    /// 8-bit grayscale
+    Gray8 = 0,
+    /// 1 bit per pixel, 8 pixels per byte: black below 128, white from 128.
+    /// A binarized page comes out several times smaller than as 8-bit gray
+    Bilevel = 1,
+}
+
+// CRC-32 (ISO 3309) lookup table, as used by PNG chunks.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8))
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// Appends one 8-bit row with the filter that gives the smallest sum of
+// absolute residuals (the libpng heuristic), preceded by its filter type.
+fn push_filtered_row(out: &mut Vec<u8>, row: &[u8], above: Option<&[u8]>, scratch: &mut [Vec<u8>; 2]) {
+    let [candidate, best] = scratch;
+    let up = |x: usize| above.map_or(0, |above| above[x]);
+    let left = |x: usize| if x > 0 { row[x - 1] } else { 0 };
+    let up_left = |x: usize| if x > 0 { up(x - 1) } else { 0 };
+    let (mut best_cost, mut best_filter) = (u64::MAX, 0u8);
+    for filter in 0..5u8 {
+        let mut cost = 0u64;
+        for (x, (r, &v)) in candidate.iter_mut().zip(row).enumerate() {
+            *r = match filter {
+                0 => v,
+                1 => v.wrapping_sub(left(x)),
+                2 => v.wrapping_sub(up(x)),
+                3 => v.wrapping_sub(((left(x) as u16 + up(x) as u16) / 2) as u8),
+                _ => v.wrapping_sub(paeth(left(x), up(x), up_left(x))),
+            };
+            cost += (*r as i8).unsigned_abs() as u64;
+        }
+        if cost < best_cost {
+            (best_cost, best_filter) = (cost, filter);
+            core::mem::swap(candidate, best);
+        }
+    }
+    out.push(best_filter);
+    out.extend_from_slice(best);
+}
+
+// Filtered scanlines of the image, ready for compression.
+fn scanlines(gray: &[u8], width: usize, mode: PngMode) -> Vec<u8> {
+    let mut out = Vec::new();
+    match mode {
+        PngMode::Gray8 => {
+            let mut scratch = [vec![0u8; width], vec![0u8; width]];
+            let mut above = None;
+            for row in gray.chunks_exact(width) {
+                push_filtered_row(&mut out, row, above, &mut scratch);
+                above = Some(row);
+            }
+        }
+        // Packed rows go unfiltered, as libpng does below 8 bits per pixel
+        PngMode::Bilevel => {
+            for row in gray.chunks_exact(width) {
+                out.push(0);
+                out.extend(row.chunks(8).map(|bits| {
+                    bits.iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (i, &v)| byte | (u8::from(v >= BILEVEL_THRESHOLD) << (7 - i)))
+                }));
+            }
+        }
+    }
+    out
+}
+
+/// Encodes a grayscale page as PNG, lossless and without the colour
+/// management a canvas applies. Binarized pages (see `OutputMode`) compress
+/// far better as `Bilevel` than as the 32-bit PNG a canvas produces.
+///
+/// # Arguments
+/// * `image` - Grayscale or RGBA pixels; the layout is taken from the length,
+///   and RGBA is reduced to its luma
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `mode` - 8-bit gray or 1-bit bilevel
+///
+/// # Returns
+/// PNG file bytes
+#[wasm_bindgen]
+pub fn encode_png(image: &[u8], width: usize, height: usize, mode: PngMode) -> Result<Vec<u8>, ScanError> {
+    let pixels = image_len(width, height, 1)?;
+    let mut converted = Vec::new();
+    let gray = if image.len() == pixels {
+        image
+    } else {
+        check_len(image.len(), image_len(width, height, 4)?, "width * height or width * height * 4")?;
+        converted.resize(pixels, 0);
+        rgba_to_grayscale_into(image, &mut converted, GrayscaleWeights::Bt601);
+        &converted
+    };
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(ScanError::InvalidParameter("PNG width and height must be 1 to 2^31 - 1"));
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    let bit_depth = match mode {
+        PngMode::Gray8 => 8,
+        PngMode::Bilevel => 1,
+    };
+    // Greyscale, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[bit_depth, 0, 0, 0, 0]);
+
+    let data = compress_to_vec_zlib(&scanlines(gray, width, mode), COMPRESSION_LEVEL);
+    let mut out = Vec::with_capacity(data.len() + 64);
+    out.extend_from_slice(&SIGNATURE);
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"IDAT", &data);
+    push_chunk(&mut out, b"IEND", &[]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+    // Checks the chunk CRCs and returns the IHDR fields and the inflated,
+    // unfiltered rows.
+    fn decode(png: &[u8]) -> ([u8; 13], Vec<Vec<u8>>) {
+        assert_eq!(png[..8], SIGNATURE);
+        let (mut header, mut data, mut pos) = ([0u8; 13], Vec::new(), 8);
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let body = &png[pos + 4..pos + 8 + len];
+            assert_eq!(crc32(body).to_be_bytes(), png[pos + 8 + len..pos + 12 + len]);
+            match &body[..4] {
+                b"IHDR" => header.copy_from_slice(&body[4..]),
+                b"IDAT" => data.extend_from_slice(&body[4..]),
+                _ => {}
+            }
+            pos += 12 + len;
+        }
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let stride = if header[8] == 8 { width } else { width.div_ceil(8) };
+        let raw = decompress_to_vec_zlib(&data).unwrap();
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        for line in raw.chunks_exact(stride + 1) {
+            let mut row = line[1..].to_vec();
+            for x in 0..stride {
+                let (left, up) = (if x > 0 { row[x - 1] } else { 0 }, rows.last().map_or(0, |r| r[x]));
+                let up_left = if x > 0 { rows.last().map_or(0, |r| r[x - 1]) } else { 0 };
+                row[x] = row[x].wrapping_add(match line[0] {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    _ => paeth(left, up, up_left),
+                });
+            }
+            rows.push(row);
+        }
+        (header, rows)
+    }
+
+    #[test]
+    fn test_encode_png() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+
+        // A binarized page with text-like blocks, 13 px wide so rows end in a
+        // partial byte, next to a gradient that needs the row filters
+        let (width, height) = (13, 9);
+        let page: Vec<u8> =
+            (0..width * height).map(|i| if (i % width + i / width) % 5 < 2 { 0 } else { 255 }).collect();
+        let (header, rows) = decode(&encode_png(&page, width, height, PngMode::Bilevel).unwrap());
+        assert_eq!(header, [0, 0, 0, 13, 0, 0, 0, 9, 1, 0, 0, 0, 0]);
+        for (y, row) in rows.iter().enumerate() {
+            let bits: Vec<u8> = (0..width).map(|x| if row[x / 8] >> (7 - x % 8) & 1 == 1 { 255 } else { 0 }).collect();
+            assert_eq!(bits, page[y * width..][..width]);
+        }
+
+        let gradient: Vec<u8> = (0..64 * 48).map(|i| ((i % 64) * 3 + (i / 64) * 2 + (i * 7) % 5) as u8).collect();
+        let png = encode_png(&gradient, 64, 48, PngMode::Gray8).unwrap();
+        let (header, rows) = decode(&png);
+        assert_eq!(header[8], 8);
+        assert_eq!(rows.concat(), gradient);
+        assert!(png.len() < gradient.len() / 2);
+
+        // RGBA input is reduced to gray; bilevel is smaller than 8-bit gray
+        let rgba: Vec<u8> = page.iter().flat_map(|&v| [v, v, v, 255]).collect();
+        let bilevel = encode_png(&rgba, width, height, PngMode::Bilevel).unwrap();
+        assert_eq!(bilevel, encode_png(&page, width, height, PngMode::Bilevel).unwrap());
+        assert!(bilevel.len() < encode_png(&rgba, width, height, PngMode::Gray8).unwrap().len());
+        assert!(encode_png(&page[1..], width, height, PngMode::Gray8).is_err());
+        assert!(encode_png(&[], 0, 0, PngMode::Gray8).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::clock::now_ms;
+use crate::context::ScanContext;
+use crate::resize::Interpolation;
+
+// Time `ScanContext::init_profile` spends benchmarking.
+const PROFILE_BUDGET_MS: f64 = 50.0;
+// Benchmark frame size; small enough that even slow devices finish a few runs
+// within the budget.
+const PROFILE_WIDTH: usize = 256;
+const PROFILE_HEIGHT: usize = 192;
+// Edge pipeline throughput, in pixels per millisecond, from which the Balanced
+// and Best presets are selected.
+const BALANCED_THROUGHPUT: f64 = 4_000.0;
+const BEST_THROUGHPUT: f64 = 20_000.0;
+// Share of a 60 fps frame the edge pipeline may take at the recommended size.
+const FRAME_BUDGET_MS: f64 = 8.0;
+// Range and step of the recommended processing size (longer side, pixels).
+const MIN_DIMENSION: usize = 320;
+const MAX_DIMENSION: usize = 1920;
+const DIMENSION_STEP: usize = 32;
+// Used when no clock is available (no_std builds outside the browser); matches
+// the JS `maxProcessingDimension` default.
+const DEFAULT_DIMENSION: usize = 800;
+
+/// Bundled speed/quality trade-off for the whole pipeline, so integrators pick
+/// one value instead of tuning every stage separately.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityPreset {
+    /// Smallest working resolution and cheapest operators, for low-end devices
+    /// and high frame rate previews.
+    Fast = 0,
+    /// Half resolution with the `ScanContext` defaults (5x5 blur, L1 gradient).
+    Balanced = 1,
+    /// Full resolution, L2 gradient and area resampling, for single captures.
+    Best = 2,
+}
+
+/// Settings selected by a `QualityPreset`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresetSettings {
+    decimation: usize,
+    kernel_size: usize,
+    dilation_kernel_size: usize,
+    l2_gradient: bool,
+    interpolation: Interpolation,
+    enhancement_strength: f32,
+}
+
+impl QualityPreset {
+    pub(crate) fn settings(self) -> PresetSettings {
+        match self {
+            QualityPreset::Fast => PresetSettings {
+                decimation: 4,
+                kernel_size: 3,
+                dilation_kernel_size: 3,
+                l2_gradient: false,
+                interpolation: Interpolation::Bilinear,
+                enhancement_strength: 0.5,
+            },
+            QualityPreset::Balanced => PresetSettings {
+                decimation: 2,
+                kernel_size: 5,
+                dilation_kernel_size: 5,
+                l2_gradient: false,
+                interpolation: Interpolation::Area,
+                enhancement_strength: 0.75,
+            },
+            QualityPreset::Best => PresetSettings {
+                decimation: 1,
+                kernel_size: 5,
+                dilation_kernel_size: 5,
+                l2_gradient: true,
+                interpolation: Interpolation::Area,
+                enhancement_strength: 1.0,
+            },
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl PresetSettings {
+    /// Integer factor frames are downscaled by before edge detection.
+    #[wasm_bindgen(getter)]
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    /// Gaussian blur kernel size for the Canny stage.
+    #[wasm_bindgen(getter)]
+    pub fn kernel_size(&self) -> usize {
+        self.kernel_size
+    }
+
+    /// Dilation kernel size applied to the edge map.
+    #[wasm_bindgen(getter)]
+    pub fn dilation_kernel_size(&self) -> usize {
+        self.dilation_kernel_size
+    }
+
+    /// Whether the gradient magnitude is L2 (exact) rather than L1 (faster).
+    #[wasm_bindgen(getter)]
+    pub fn l2_gradient(&self) -> bool {
+        self.l2_gradient
+    }
+
+    /// Sampling for the downscale by `decimation` and other resizes.
+    #[wasm_bindgen(getter)]
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Scale for enhancement amounts such as `adjust_shadows_highlights`, 0..1.
+    #[wasm_bindgen(getter)]
+    pub fn enhancement_strength(&self) -> f32 {
+        self.enhancement_strength
+    }
+
+    /// Size of a `width` x `height` frame after decimation, never below 1x1.
+    pub fn processing_size(&self, width: usize, height: usize) -> Vec<usize> {
+        vec![width.div_ceil(self.decimation).max(1), height.div_ceil(self.decimation).max(1)]
+    }
+}
+
+/// Result of the startup benchmark in `ScanContext::init_profile`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceProfile {
+    throughput: f64,
+    preset: QualityPreset,
+    max_dimension: usize,
+}
+
+impl DeviceProfile {
+    // Profile for an edge pipeline throughput in pixels per millisecond.
+    pub(crate) fn from_throughput(throughput: f64) -> DeviceProfile {
+        let preset = if throughput >= BEST_THROUGHPUT {
+            QualityPreset::Best
+        } else if throughput >= BALANCED_THROUGHPUT {
+            QualityPreset::Balanced
+        } else {
+            QualityPreset::Fast
+        };
+        // Largest square-ish frame the pipeline handles within the frame budget
+        let side = (throughput * FRAME_BUDGET_MS).sqrt() as usize / DIMENSION_STEP * DIMENSION_STEP;
+        DeviceProfile { throughput, preset, max_dimension: side.clamp(MIN_DIMENSION, MAX_DIMENSION) }
+    }
+
+    // Profile used when the device can't be timed.
+    fn fallback() -> DeviceProfile {
+        DeviceProfile { throughput: 0.0, preset: QualityPreset::Balanced, max_dimension: DEFAULT_DIMENSION }
+    }
+}
+
+#[wasm_bindgen]
+impl DeviceProfile {
+    /// Measured edge pipeline throughput in pixels per millisecond; 0 when no
+    /// clock was available.
+    #[wasm_bindgen(getter)]
+    pub fn throughput(&self) -> f64 {
+        self.throughput
+    }
+
+    /// Preset selected for the device.
+    #[wasm_bindgen(getter)]
+    pub fn preset(&self) -> QualityPreset {
+        self.preset
+    }
+
+    /// Recommended longer side of processed frames, e.g. for the JS
+    /// `maxProcessingDimension` option.
+    #[wasm_bindgen(getter)]
+    pub fn max_dimension(&self) -> usize {
+        self.max_dimension
+    }
+}
+
+// Runs the edge pipeline on a synthetic page for about `PROFILE_BUDGET_MS`.
+pub(crate) fn benchmark() -> DeviceProfile {
+    let Some(start) = now_ms() else {
+        return DeviceProfile::fallback();
+    };
+    // A bright page on a darker, textured background
+    let mut frame = vec![0u8; PROFILE_WIDTH * PROFILE_HEIGHT];
+    for (i, px) in frame.iter_mut().enumerate() {
+        let (x, y) = (i % PROFILE_WIDTH, i / PROFILE_WIDTH);
+        let page = (40..216).contains(&x) && (24..168).contains(&y);
+        *px = if page { 220 } else { (60 + (x * 7 + y * 13) % 40) as u8 };
+    }
+    let mut context = ScanContext::new(PROFILE_WIDTH, PROFILE_HEIGHT);
+    context.set_quality_preset(QualityPreset::Balanced);
+
+    let mut runs = 0usize;
+    let elapsed = loop {
+        context.process_gray_frame(&frame).expect("frame matches the context size");
+        runs += 1;
+        let elapsed = now_ms().unwrap_or(start) - start;
+        if elapsed >= PROFILE_BUDGET_MS {
+            break elapsed;
+        }
+    };
+    DeviceProfile::from_throughput((runs * PROFILE_WIDTH * PROFILE_HEIGHT) as f64 / elapsed)
+}
+
+/// Settings a preset selects for each pipeline stage.
+///
+/// # Arguments
+/// * `preset` - Speed/quality trade-off
+///
+/// # Returns
+/// The preset's settings; `ScanContext::set_quality_preset` applies the edge
+/// detection ones directly.
+#[wasm_bindgen]
+pub fn preset_settings(preset: QualityPreset) -> PresetSettings {
+    preset.settings()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_trade_speed_for_quality() {
+        let [fast, balanced, best] = [QualityPreset::Fast, QualityPreset::Balanced, QualityPreset::Best].map(preset_settings);
+        assert!(fast.decimation() > balanced.decimation() && balanced.decimation() > best.decimation());
+        assert!(fast.kernel_size() <= balanced.kernel_size() && !fast.l2_gradient() && best.l2_gradient());
+        assert!(fast.enhancement_strength() < best.enhancement_strength());
+        assert_eq!(fast.processing_size(1001, 3), vec![251, 1]);
+        assert_eq!(best.processing_size(640, 480), vec![640, 480]);
+
+        // Slow devices get the Fast preset and small frames
+        let slow = DeviceProfile::from_throughput(1_000.0);
+        assert_eq!((slow.preset(), slow.max_dimension()), (QualityPreset::Fast, MIN_DIMENSION));
+        let fast = DeviceProfile::from_throughput(100_000.0);
+        assert_eq!((fast.preset(), fast.max_dimension()), (QualityPreset::Best, 864));
+
+        let mut context = ScanContext::new(64, 48);
+        assert!(context.profile().is_none());
+        let profile = context.init_profile();
+        assert!(profile.throughput() > 0.0);
+        assert_eq!(context.profile(), Some(profile));
+        assert_eq!(context.retained_bytes(), 0);
+    }
+}
@@ -0,0 +1,216 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// 5-tap binomial kernel [1 4 6 4 1] / 16 per axis (OpenCV pyrDown/pyrUp).
+const KERNEL: [u32; 5] = [1, 4, 6, 4, 1];
+
+// Reflect-101 border (…2 1 | 0 1 2 … n-2 n-1 | n-2 …), the OpenCV default.
+#[inline]
+fn reflect_101(i: isize, len: usize) -> usize {
+    if len == 1 {
+        return 0;
+    }
+    let last = len as isize - 1;
+    let i = if i < 0 { -i } else { i };
+    (if i > last { 2 * last - i } else { i }) as usize
+}
+
+/// Output size of one pyrDown step: half the input, rounded up.
+pub(crate) fn down_size(width: usize, height: usize) -> (usize, usize) {
+    (width.div_ceil(2), height.div_ceil(2))
+}
+
+// Gaussian blur and 2x decimation into a caller-owned (w+1)/2 x (h+1)/2 buffer.
+pub(crate) fn pyr_down_into(src: &[u8], width: usize, height: usize, dst: &mut [u8]) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (dst_width, _) = down_size(width, height);
+    let mut column_sums = vec![0u32; width];
+
+    for (y, dst_row) in dst.chunks_exact_mut(dst_width).enumerate() {
+        // Vertical pass over the five source rows around 2y
+        column_sums.fill(0);
+        for (k, &weight) in KERNEL.iter().enumerate() {
+            let sy = reflect_101(2 * y as isize + k as isize - 2, height);
+            let src_row = &src[sy * width..(sy + 1) * width];
+            for (sum, &px) in column_sums.iter_mut().zip(src_row) {
+                *sum += weight * px as u32;
+            }
+        }
+
+        // Horizontal pass at even columns; total weight is 256
+        for (x, out) in dst_row.iter_mut().enumerate() {
+            let mut sum = 0;
+            for (k, &weight) in KERNEL.iter().enumerate() {
+                sum += weight * column_sums[reflect_101(2 * x as isize + k as isize - 2, width)];
+            }
+            *out = ((sum + 128) >> 8) as u8;
+        }
+    }
+}
+
+// 2x upsampling with Gaussian interpolation into a caller-owned 2w x 2h buffer.
+// Even output samples weight their source pixel 6:1:1 against its neighbours,
+// odd samples sit halfway between two source pixels (4:4).
+pub(crate) fn pyr_up_into(src: &[u8], width: usize, height: usize, dst: &mut [u8]) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let dst_width = width * 2;
+    // Horizontally upsampled source rows, weights summing to 8
+    let mut rows = vec![0u32; dst_width * height];
+    for (y, row) in rows.chunks_exact_mut(dst_width).enumerate() {
+        let src_row = &src[y * width..(y + 1) * width];
+        for x in 0..width {
+            let left = src_row[x.saturating_sub(1)] as u32;
+            let center = src_row[x] as u32;
+            let right = src_row[(x + 1).min(width - 1)] as u32;
+            row[2 * x] = left + 6 * center + right;
+            row[2 * x + 1] = 4 * (center + right);
+        }
+    }
+
+    for (y, dst_row) in dst.chunks_exact_mut(dst_width).enumerate() {
+        let sy = y / 2;
+        let above = &rows[sy.saturating_sub(1) * dst_width..][..dst_width];
+        let center = &rows[sy * dst_width..][..dst_width];
+        let below = &rows[(sy + 1).min(height - 1) * dst_width..][..dst_width];
+        for (x, out) in dst_row.iter_mut().enumerate() {
+            let sum = if y % 2 == 0 {
+                above[x] + 6 * center[x] + below[x]
+            } else {
+                4 * (center[x] + below[x])
+            };
+            *out = ((sum + 32) >> 6) as u8;
+        }
+    }
+}
+
+/// Blurs with a 5x5 Gaussian and halves the image (OpenCV `pyrDown`).
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Image of (width + 1) / 2 x (height + 1) / 2 pixels
+#[wasm_bindgen]
+pub fn pyr_down(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let (dst_width, dst_height) = down_size(width, height);
+    let mut dst = vec![0u8; dst_width * dst_height];
+    pyr_down_into(gray, width, height, &mut dst);
+    Ok(dst)
+}
+
+/// Doubles the image size with Gaussian interpolation (OpenCV `pyrUp`).
+///
+/// # Arguments
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// Image of width * 2 x height * 2 pixels
+#[wasm_bindgen]
+pub fn pyr_up(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let mut dst = vec![0u8; width * height * 4];
+    pyr_up_into(gray, width, height, &mut dst);
+    Ok(dst)
+}
+
+struct Level {
+    gray: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+/// Gaussian pyramid of a grayscale frame for coarse-to-fine detection: detect on a
+/// small level, then map the result back with `scale_x`/`scale_y` and refine at
+/// full resolution. Level 0 is the input frame.
+#[wasm_bindgen]
+pub struct ImagePyramid {
+    levels: Vec<Level>,
+}
+
+#[wasm_bindgen]
+impl ImagePyramid {
+    /// Builds up to `max_levels` levels (including the input), stopping early once
+    /// a level would be smaller than `min_size` pixels on its shorter side.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        gray: &[u8],
+        width: usize,
+        height: usize,
+        max_levels: usize,
+        min_size: usize,
+    ) -> Result<ImagePyramid, ScanError> {
+        check_gray(gray, width, height)?;
+
+        let mut levels = vec![Level { gray: gray.to_vec(), width, height }];
+        while levels.len() < max_levels {
+            let prev = &levels[levels.len() - 1];
+            let (w, h) = down_size(prev.width, prev.height);
+            if w.min(h) < min_size.max(1) || (w, h) == (prev.width, prev.height) {
+                break;
+            }
+            let mut next = vec![0u8; w * h];
+            pyr_down_into(&prev.gray, prev.width, prev.height, &mut next);
+            levels.push(Level { gray: next, width: w, height: h });
+        }
+
+        Ok(ImagePyramid { levels })
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn width(&self, level: usize) -> usize {
+        self.levels[level].width
+    }
+
+    pub fn height(&self, level: usize) -> usize {
+        self.levels[level].height
+    }
+
+    /// Grayscale pixels of one level.
+    pub fn level(&self, level: usize) -> Vec<u8> {
+        self.levels[level].gray.clone()
+    }
+
+    /// Factor mapping x coordinates on `level` back to level 0.
+    pub fn scale_x(&self, level: usize) -> f32 {
+        self.levels[0].width as f32 / self.levels[level].width as f32
+    }
+
+    /// Factor mapping y coordinates on `level` back to level 0.
+    pub fn scale_y(&self, level: usize) -> f32 {
+        self.levels[0].height as f32 / self.levels[level].height as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyramid_sizes_and_flat_levels() {
+        let flat = vec![90u8; 37 * 20];
+        let pyramid = ImagePyramid::new(&flat, 37, 20, 10, 4).unwrap();
+        let sizes: Vec<_> = (0..pyramid.level_count()).map(|i| (pyramid.width(i), pyramid.height(i))).collect();
+        assert_eq!(sizes, vec![(37, 20), (19, 10), (10, 5)]);
+        assert!((0..pyramid.level_count()).all(|i| pyramid.level(i).iter().all(|&v| v == 90)));
+
+        let up = pyr_up(&pyramid.level(2), 10, 5).unwrap();
+        assert_eq!(up.len(), 20 * 10);
+        assert!(up.iter().all(|&v| v == 90));
+    }
+}
@@ -0,0 +1,523 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_index, ScanError};
+
+// Area fraction of the image at which the area criterion saturates; documents
+// smaller than this are increasingly likely to be something else on the desk.
+const FULL_AREA_FRACTION: f32 = 0.3;
+// Aspect ratios (long side / short side) of common documents: square labels,
+// US Letter, ISO A-series and ID-1 cards.
+const DEFAULT_ASPECT_RATIOS: [f32; 4] = [1.0, 1.294, 1.414, 1.586];
+// Aspect ratios this far off (as a factor) from the closest expected one score 0.
+const ASPECT_TOLERANCE: f32 = 2.0;
+// Below this |n2.z * n3.z| the quad's opposite sides are parallel in the image
+// and the focal length can't be recovered (see `perspective_aspect`).
+const AFFINE_EPSILON: f64 = 1e-9;
+// Number of criteria reported per quad by `RankedQuads::criteria`.
+const CRITERIA: usize = 5;
+// Values passed to a custom scoring function: the built-in score, the criteria
+// and the quad centre relative to the image size.
+const FEATURES: usize = CRITERIA + 3;
+
+type ScoreFn = Box<dyn Fn(&[f32; FEATURES]) -> f32>;
+
+#[wasm_bindgen]
+extern "C" {
+    /// JS function `(features: Float32Array) => number` used by
+    /// `QuadScorer::set_score_callback`.
+    #[wasm_bindgen(typescript_type = "(features: Float32Array) => number")]
+    pub type QuadScoreCallback;
+
+    #[wasm_bindgen(method, js_name = call)]
+    fn call_with(this: &QuadScoreCallback, context: &JsValue, features: &[f32]) -> f64;
+}
+
+type Point = (f32, f32);
+
+#[inline]
+fn cross(o: Point, a: Point, b: Point) -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+// Absolute shoelace area of the polygon visiting `points` in order.
+fn polygon_area(points: &[Point]) -> f32 {
+    let mut sum = 0.0;
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        sum += x0 * y1 - x1 * y0;
+    }
+    sum.abs() / 2.0
+}
+
+// Area of the quad over the area of the convex hull of its corners: 1 for convex
+// quads, lower for concave or self-intersecting ones. The hull of four points is
+// either one of their three cyclic orderings or one of the four triangles.
+fn convexity(quad: &[Point; 4]) -> f32 {
+    let [a, b, c, d] = *quad;
+    let hull = [
+        polygon_area(&[a, b, c, d]),
+        polygon_area(&[a, c, b, d]),
+        polygon_area(&[a, b, d, c]),
+        polygon_area(&[a, b, c]),
+        polygon_area(&[a, b, d]),
+        polygon_area(&[a, c, d]),
+        polygon_area(&[b, c, d]),
+    ]
+    .into_iter()
+    .fold(0.0, f32::max);
+
+    if hull <= 0.0 {
+        return 0.0;
+    }
+    // Quads that turn both ways (concave or self-intersecting) are capped at 0.5
+    let signs = (0..4).filter(|&i| cross(quad[i], quad[(i + 1) % 4], quad[(i + 2) % 4]) > 0.0).count();
+    let area = polygon_area(quad);
+    if signs == 0 || signs == 4 {
+        area / hull
+    } else {
+        (area / hull).min(0.5)
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+// Long over short side, from the mean lengths of opposite sides.
+fn side_ratio(quad: &[Point; 4]) -> Option<f32> {
+    let width = (distance(quad[0], quad[1]) + distance(quad[3], quad[2])) / 2.0;
+    let height = (distance(quad[0], quad[3]) + distance(quad[1], quad[2])) / 2.0;
+    (width > 0.0 && height > 0.0).then(|| width.max(height) / width.min(height))
+}
+
+// Aspect ratio (long over short side) of the rectangle the quad is the image
+// of, undoing the perspective foreshortening. Zhang & He, "Whiteboard scanning
+// and image enhancement": with the principal point at `centre`, the corners fix
+// the focal length and the rectangle's true side ratio. Falls back to the side
+// ratio when the image is (nearly) affine or the focal length comes out
+// imaginary.
+pub(crate) fn perspective_aspect(quad: &[Point; 4], centre: Point) -> Option<f32> {
+    type Vec3 = [f64; 3];
+    let cross = |a: Vec3, b: Vec3| [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+    let dot = |a: Vec3, b: Vec3| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let point = |p: Point| [(p.0 - centre.0) as f64, (p.1 - centre.1) as f64, 1.0];
+    // m1..m4: TL, TR, BL, BR
+    let (m1, m2, m3, m4) = (point(quad[0]), point(quad[1]), point(quad[3]), point(quad[2]));
+
+    let (d2, d3) = (dot(cross(m2, m4), m3), dot(cross(m3, m4), m2));
+    if d2 == 0.0 || d3 == 0.0 {
+        return None;
+    }
+    let k2 = dot(cross(m1, m4), m3) / d2;
+    let k3 = dot(cross(m1, m4), m2) / d3;
+    let n2: Vec3 = [k2 * m2[0] - m1[0], k2 * m2[1] - m1[1], k2 * m2[2] - m1[2]];
+    let n3: Vec3 = [k3 * m3[0] - m1[0], k3 * m3[1] - m1[1], k3 * m3[2] - m1[2]];
+
+    let zz = n2[2] * n3[2];
+    let f2 = -(n2[0] * n3[0] + n2[1] * n3[1]) / zz;
+    if zz.abs() < AFFINE_EPSILON || !f2.is_finite() || f2 <= 0.0 {
+        return side_ratio(quad);
+    }
+    let length = |n: Vec3| (n[0] * n[0] + n[1] * n[1]) / f2 + n[2] * n[2];
+    let ratio = (length(n2) / length(n3)).sqrt() as f32;
+    (ratio.is_finite() && ratio > 0.0).then(|| ratio.max(1.0 / ratio))
+}
+
+// 1 at the expected aspect ratio, falling linearly (in log space) to 0 at the
+// relative `tolerance` off it.
+fn expected_aspect_score(ratio: Option<f32>, expected: f32, tolerance: f32) -> f32 {
+    ratio.map_or(0.0, |ratio| (1.0 - (ratio / expected).ln().abs() / (1.0 + tolerance).ln()).max(0.0))
+}
+
+// 1 at an expected aspect ratio, falling linearly (in log space) to 0 at
+// `ASPECT_TOLERANCE` times or a fraction of the closest one.
+fn aspect_score(quad: &[Point; 4], ratios: &[f32]) -> f32 {
+    let Some(ratio) = side_ratio(quad) else {
+        return 0.0;
+    };
+    ratios
+        .iter()
+        .map(|&expected| 1.0 - (ratio / expected).ln().abs() / ASPECT_TOLERANCE.ln())
+        .fold(0.0, f32::max)
+}
+
+// 1 for right angles everywhere, 0 when the mean deviation reaches 90 degrees.
+fn angle_score(quad: &[Point; 4]) -> f32 {
+    let mut deviation = 0.0;
+    for i in 0..4 {
+        let prev = quad[(i + 3) % 4];
+        let corner = quad[i];
+        let next = quad[(i + 1) % 4];
+        let (ax, ay) = (prev.0 - corner.0, prev.1 - corner.1);
+        let (bx, by) = (next.0 - corner.0, next.1 - corner.1);
+        let norm = ax.hypot(ay) * bx.hypot(by);
+        if norm <= 0.0 {
+            return 0.0;
+        }
+        let cos = ((ax * bx + ay * by) / norm).clamp(-1.0, 1.0);
+        deviation += (cos.acos() - core::f32::consts::FRAC_PI_2).abs();
+    }
+    (1.0 - deviation / 4.0 / core::f32::consts::FRAC_PI_2).clamp(0.0, 1.0)
+}
+
+// Mean over the four sides of the fraction of points along the side that have an
+// edge pixel within one pixel. Every side counts equally, so one strong border
+// cannot make up for a missing one.
+fn edge_support(quad: &[Point; 4], edges: &[u8], width: usize, height: usize) -> f32 {
+    let has_edge = |x: f32, y: f32| {
+        let (cx, cy) = (x.round() as isize, y.round() as isize);
+        (-1..=1).any(|dy| {
+            (-1..=1).any(|dx| {
+                let (nx, ny) = (cx + dx, cy + dy);
+                let inside = nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height;
+                inside && edges[ny as usize * width + nx as usize] != 0
+            })
+        })
+    };
+
+    let mut total = 0.0;
+    for i in 0..4 {
+        let (a, b) = (quad[i], quad[(i + 1) % 4]);
+        let steps = distance(a, b).ceil().max(1.0) as usize;
+        let hits = (0..=steps)
+            .filter(|&s| {
+                let t = s as f32 / steps as f32;
+                has_edge(a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+            })
+            .count();
+        total += hits as f32 / (steps + 1) as f32;
+    }
+    total / 4.0
+}
+
+struct Ranked {
+    index: usize,
+    corners: [f32; 8],
+    score: f32,
+    criteria: [f32; CRITERIA],
+}
+
+/// Candidates ranked by `QuadScorer::rank`, best first.
+#[wasm_bindgen]
+pub struct RankedQuads {
+    quads: Vec<Ranked>,
+}
+
+#[wasm_bindgen]
+impl RankedQuads {
+    pub fn count(&self) -> usize {
+        self.quads.len()
+    }
+
+    /// Corners (8 values) of the quad at `rank`.
+    pub fn corners(&self, rank: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(rank)?.corners.to_vec())
+    }
+
+    /// Combined confidence score in 0..1, or the custom score when a scoring
+    /// callback is set.
+    pub fn score(&self, rank: usize) -> Result<f32, ScanError> {
+        Ok(self.get(rank)?.score)
+    }
+
+    /// Position of the quad in the candidate list passed to `rank`.
+    pub fn candidate_index(&self, rank: usize) -> Result<usize, ScanError> {
+        Ok(self.get(rank)?.index)
+    }
+
+    /// Per-criterion scores in 0..1: area, convexity, aspect ratio, edge support
+    /// and angle regularity.
+    pub fn criteria(&self, rank: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(rank)?.criteria.to_vec())
+    }
+}
+
+impl RankedQuads {
+    fn get(&self, rank: usize) -> Result<&Ranked, ScanError> {
+        check_index(rank, self.quads.len())?;
+        Ok(&self.quads[rank])
+    }
+}
+
+/// Scores and ranks document quad candidates, e.g. from contours or from
+/// `quad_from_lines`, so applications can pick the best one and offer the others
+/// as alternates.
+#[wasm_bindgen]
+pub struct QuadScorer {
+    // Area, convexity, aspect ratio, edge support, angle regularity
+    weights: [f32; CRITERIA],
+    aspect_ratios: Vec<f32>,
+    // Known document shape: ratio and relative tolerance
+    expected_aspect: Option<(f32, f32)>,
+    // Application-specific scoring on top of the built-in criteria
+    custom: Option<ScoreFn>,
+}
+
+impl Default for QuadScorer {
+    fn default() -> Self {
+        QuadScorer {
+            weights: [1.0, 1.0, 0.5, 2.0, 0.5],
+            aspect_ratios: DEFAULT_ASPECT_RATIOS.to_vec(),
+            expected_aspect: None,
+            custom: None,
+        }
+    }
+}
+
+impl QuadScorer {
+    pub(crate) fn set_score_fn(&mut self, score: impl Fn(&[f32; FEATURES]) -> f32 + 'static) {
+        self.custom = Some(Box::new(score));
+    }
+}
+
+#[wasm_bindgen]
+impl QuadScorer {
+    /// Creates a scorer with default weights; edge support counts most, since it
+    /// is the only criterion that checks the quad against the image.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> QuadScorer {
+        QuadScorer::default()
+    }
+
+    /// Relative weight of each criterion; 0 ignores it.
+    pub fn set_weights(&mut self, area: f32, convexity: f32, aspect: f32, edge_support: f32, angle: f32) {
+        self.weights = [area, convexity, aspect, edge_support, angle].map(|w| w.max(0.0));
+    }
+
+    /// Expected aspect ratios (long side / short side), e.g. `[1.586]` for ID cards.
+    pub fn set_aspect_ratios(&mut self, ratios: &[f32]) -> Result<(), ScanError> {
+        if ratios.is_empty() || ratios.iter().any(|r| r.is_nan() || *r < 1.0) {
+            return Err(ScanError::InvalidParameter("Aspect ratios must be at least 1"));
+        }
+        self.aspect_ratios = ratios.to_vec();
+        Ok(())
+    }
+
+    /// Constrains ranking to documents of one known shape, e.g. 1.586 for ISO
+    /// ID-1 cards. The aspect ratio of each candidate is measured under
+    /// perspective, assuming the camera looks through the image centre, so a
+    /// card tilted away from the camera still matches. The aspect criterion
+    /// then falls to 0 at `tolerance` off the ratio and gates the whole score:
+    /// candidates of any other shape score 0.
+    ///
+    /// # Arguments
+    /// * `ratio` - Long side over short side of the document
+    /// * `tolerance` - Relative deviation still accepted, e.g. 0.08 for 8 %
+    pub fn set_expected_aspect(&mut self, ratio: f32, tolerance: f32) -> Result<(), ScanError> {
+        if !(ratio >= 1.0 && ratio.is_finite()) {
+            return Err(ScanError::InvalidParameter("Aspect ratio must be at least 1"));
+        }
+        if !(tolerance > 0.0 && tolerance.is_finite()) {
+            return Err(ScanError::InvalidParameter("Aspect tolerance must be positive"));
+        }
+        self.expected_aspect = Some((ratio, tolerance));
+        Ok(())
+    }
+
+    /// Drops the shape constraint of `set_expected_aspect`, scoring the aspect
+    /// ratio against `set_aspect_ratios` again.
+    pub fn clear_expected_aspect(&mut self) {
+        self.expected_aspect = None;
+    }
+
+    /// Lets the application score candidates itself, e.g. to prefer centred
+    /// quads on a kiosk with a fixed document position. The callback gets 8
+    /// features per candidate: the built-in score, the five criteria (see
+    /// `RankedQuads::criteria`) and the quad centre as a fraction of the image
+    /// width and height. Its result replaces the built-in score; non-finite
+    /// results count as 0.
+    pub fn set_score_callback(&mut self, callback: QuadScoreCallback) {
+        self.set_score_fn(move |features| callback.call_with(&JsValue::NULL, features) as f32);
+    }
+
+    /// Removes the scoring callback, restoring the built-in score.
+    pub fn clear_score_callback(&mut self) {
+        self.custom = None;
+    }
+
+    /// Ranks quad candidates.
+    ///
+    /// # Arguments
+    /// * `candidates` - Flat list of quads, 8 values each (4 corners in perimeter order)
+    /// * `edges` - Edge map the candidates were found on (non-zero pixels are edges)
+    /// * `width` - Image width
+    /// * `height` - Image height
+    /// * `max_results` - Maximum number of quads returned
+    ///
+    /// # Returns
+    /// The best `max_results` candidates with scores, best first. Degenerate quads
+    /// (zero area or non-finite corners) are dropped.
+    pub fn rank(
+        &self,
+        candidates: &[f32],
+        edges: &[u8],
+        width: usize,
+        height: usize,
+        max_results: usize,
+    ) -> Result<RankedQuads, ScanError> {
+        if !candidates.len().is_multiple_of(8) {
+            return Err(ScanError::InvalidPoints("Candidates must have 8 coordinates (4 corners) per quad"));
+        }
+        check_gray(edges, width, height)?;
+
+        let total_weight: f32 = self.weights.iter().sum();
+        let image_area = (width * height) as f32;
+        let centre = (width as f32 / 2.0, height as f32 / 2.0);
+        let mut quads: Vec<Ranked> = candidates
+            .chunks_exact(8)
+            .enumerate()
+            .filter(|(_, c)| c.iter().all(|v| v.is_finite()))
+            .filter_map(|(index, c)| {
+                let quad = [(c[0], c[1]), (c[2], c[3]), (c[4], c[5]), (c[6], c[7])];
+                let area = polygon_area(&quad);
+                if area <= 0.0 {
+                    return None;
+                }
+                let criteria = [
+                    (area / image_area / FULL_AREA_FRACTION).min(1.0),
+                    convexity(&quad),
+                    match self.expected_aspect {
+                        Some((ratio, tolerance)) => {
+                            expected_aspect_score(perspective_aspect(&quad, centre), ratio, tolerance)
+                        }
+                        None => aspect_score(&quad, &self.aspect_ratios),
+                    },
+                    edge_support(&quad, edges, width, height),
+                    angle_score(&quad),
+                ];
+                let weighted: f32 = criteria.iter().zip(&self.weights).map(|(s, w)| s * w).sum();
+                let mut score = if total_weight > 0.0 { weighted / total_weight } else { 0.0 };
+                if self.expected_aspect.is_some() {
+                    score *= criteria[2];
+                }
+                if let Some(custom) = &self.custom {
+                    let mut features = [0f32; FEATURES];
+                    features[0] = score;
+                    features[1..=CRITERIA].copy_from_slice(&criteria);
+                    features[CRITERIA + 1] = quad.iter().map(|p| p.0).sum::<f32>() / 4.0 / width as f32;
+                    features[CRITERIA + 2] = quad.iter().map(|p| p.1).sum::<f32>() / 4.0 / height as f32;
+                    score = Some(custom(&features)).filter(|s| s.is_finite()).unwrap_or(0.0);
+                }
+                let mut corners = [0f32; 8];
+                corners.copy_from_slice(c);
+                Some(Ranked { index, corners, score, criteria })
+            })
+            .collect();
+
+        // Stable sort keeps candidate order among equal scores
+        quads.sort_by(|a, b| b.score.total_cmp(&a.score));
+        quads.truncate(max_results);
+        Ok(RankedQuads { quads })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_prefers_supported_regular_quad() {
+        // Edge map with the outline of an A4-like rectangle
+        let (width, height) = (120usize, 160usize);
+        let mut edges = vec![0u8; width * height];
+        for x in 20..=99 {
+            edges[20 * width + x] = 255;
+            edges[133 * width + x] = 255;
+        }
+        for y in 20..=133 {
+            edges[y * width + 20] = 255;
+            edges[y * width + 99] = 255;
+        }
+
+        let page = [20.0, 20.0, 99.0, 20.0, 99.0, 133.0, 20.0, 133.0];
+        let skewed = [25.0, 30.0, 90.0, 20.0, 110.0, 150.0, 10.0, 120.0];
+        let bow_tie = [20.0, 20.0, 99.0, 133.0, 99.0, 20.0, 20.0, 100.0];
+        let degenerate = [5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0];
+        let candidates: Vec<f32> = [skewed, bow_tie, degenerate, page].concat();
+
+        let ranked = QuadScorer::new().rank(&candidates, &edges, width, height, 10).unwrap();
+        assert_eq!(ranked.count(), 3);
+        assert_eq!(ranked.candidate_index(0).unwrap(), 3);
+        assert_eq!(ranked.corners(0).unwrap(), page.to_vec());
+        let criteria = ranked.criteria(0).unwrap();
+        assert!(criteria[1] > 0.99 && criteria[3] > 0.99 && criteria[4] > 0.99);
+        assert!(ranked.criteria(2).unwrap()[1] <= 0.5);
+        let scores: Vec<f32> = (0..3).map(|rank| ranked.score(rank).unwrap()).collect();
+        assert!(scores[0] > scores[1] && scores[1] > scores[2]);
+
+        assert_eq!(QuadScorer::new().rank(&candidates, &edges, width, height, 1).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_custom_score_prefers_centred_quad() {
+        // Two equally good outlines, one in the corner and one in the centre
+        let (width, height) = (200usize, 200usize);
+        let mut edges = vec![0u8; width * height];
+        for (x0, y0) in [(10usize, 10usize), (70, 70)] {
+            for i in 0..=60 {
+                edges[y0 * width + x0 + i] = 255;
+                edges[(y0 + 60) * width + x0 + i] = 255;
+                edges[(y0 + i) * width + x0] = 255;
+                edges[(y0 + i) * width + x0 + 60] = 255;
+            }
+        }
+        let corner = [10.0, 10.0, 70.0, 10.0, 70.0, 70.0, 10.0, 70.0];
+        let centre = [70.0, 70.0, 130.0, 70.0, 130.0, 130.0, 70.0, 130.0];
+        let candidates = [corner, centre].concat();
+
+        let mut scorer = QuadScorer::new();
+        assert_eq!(scorer.rank(&candidates, &edges, width, height, 2).unwrap().candidate_index(0).unwrap(), 0);
+        scorer.set_score_fn(|f| f[0] * (1.0 - (f[6] - 0.5).hypot(f[7] - 0.5)));
+        let ranked = scorer.rank(&candidates, &edges, width, height, 2).unwrap();
+        assert_eq!(ranked.candidate_index(0).unwrap(), 1);
+        assert!(ranked.score(1).unwrap() < ranked.score(0).unwrap() * 0.7);
+
+        scorer.set_score_fn(|_| f32::NAN);
+        assert_eq!(scorer.rank(&candidates, &edges, width, height, 2).unwrap().score(0).unwrap(), 0.0);
+        scorer.clear_score_callback();
+        assert_eq!(scorer.rank(&candidates, &edges, width, height, 2).unwrap().candidate_index(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_expected_aspect_under_perspective() {
+        // An ID-1 card (85.6 x 54 mm) turned 30° and tilted 45° away from a
+        // camera with a 400 px focal length centred on the image
+        let (width, height) = (320usize, 240usize);
+        let (f, turn, tilt) = (400.0f32, 30f32.to_radians(), 45f32.to_radians());
+        let project = |x: f32, y: f32| {
+            let (x3, z3) = (x * turn.cos(), -x * turn.sin());
+            let (y3, z3) = (y * tilt.cos() - z3 * tilt.sin(), 200.0 + y * tilt.sin() + z3 * tilt.cos());
+            (f * x3 / z3 + 160.0, f * y3 / z3 + 120.0)
+        };
+        let card: Vec<f32> = [(-42.8, -27.0), (42.8, -27.0), (42.8, 27.0), (-42.8, 27.0)]
+            .iter()
+            .flat_map(|&(x, y)| {
+                let (u, v) = project(x, y);
+                [u, v]
+            })
+            .collect();
+        let quad = [(card[0], card[1]), (card[2], card[3]), (card[4], card[5]), (card[6], card[7])];
+        let foreshortened = side_ratio(&quad).unwrap();
+        let recovered = perspective_aspect(&quad, (160.0, 120.0)).unwrap();
+        assert!((foreshortened - 1.586).abs() > 0.2 && (recovered - 1.586).abs() < 0.01, "{foreshortened} {recovered}");
+
+        // Next to an A4-shaped sheet facing the camera; without edges to tell
+        // them apart the sheet wins on area, the ID-1 constraint picks the card
+        let sheet = [40.0, 20.0, 140.0, 20.0, 140.0, 161.4, 40.0, 161.4];
+        let candidates = [&sheet[..], &card].concat();
+        let edges = vec![0u8; width * height];
+        let mut scorer = QuadScorer::new();
+        scorer.set_weights(1.0, 1.0, 1.0, 0.0, 0.0);
+        assert_eq!(scorer.rank(&candidates, &edges, width, height, 2).unwrap().candidate_index(0).unwrap(), 0);
+        scorer.set_expected_aspect(1.586, 0.05).unwrap();
+        let ranked = scorer.rank(&candidates, &edges, width, height, 2).unwrap();
+        assert_eq!(ranked.candidate_index(0).unwrap(), 1);
+        assert!(ranked.criteria(0).unwrap()[2] > 0.8 && ranked.score(1).unwrap() == 0.0);
+        assert!(scorer.set_expected_aspect(0.5, 0.05).is_err() && scorer.set_expected_aspect(1.5, 0.0).is_err());
+        scorer.clear_expected_aspect();
+        assert_eq!(scorer.rank(&candidates, &edges, width, height, 2).unwrap().candidate_index(0).unwrap(), 0);
+    }
+}
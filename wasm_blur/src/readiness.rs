@@ -0,0 +1,166 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+use crate::sharpness::laplacian_variance;
+
+// Largest mean corner variance (px²) over the recent detections at which the
+// quad counts as steady.
+const MAX_STABILITY_VARIANCE: f32 = 4.0;
+// Smallest variance of the Laplacian at which a frame counts as in focus.
+// Motion blur and defocus both flatten the second derivative.
+const MIN_SHARPNESS: f32 = 100.0;
+// Mean luma range of a usable exposure.
+const MIN_LUMA: f32 = 60.0;
+const MAX_LUMA: f32 = 220.0;
+// Clipped pixels (glare, blown-out paper) allowed as a share of the frame.
+const CLIPPED_LUMA: u8 = 250;
+const MAX_CLIPPED_FRACTION: f32 = 0.05;
+// Detections considered for stability by `ScanContext::capture_readiness`.
+pub(crate) const STABILITY_WINDOW: usize = 10;
+
+/// Whether a frame is good enough for an automatic capture, from
+/// `capture_readiness`: each criterion as a flag, the measurements behind them,
+/// and one combined score.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaptureReadiness {
+    stability_variance: f32,
+    sharpness: f32,
+    mean_luma: f32,
+    clipped_fraction: f32,
+}
+
+#[wasm_bindgen]
+impl CaptureReadiness {
+    /// All criteria met: trigger the capture.
+    pub fn ready(&self) -> bool {
+        self.stable() && self.sharp() && self.well_lit()
+    }
+
+    /// Readiness 0..1: the product of one 0..1 score per criterion, each 1 once
+    /// its flag is set. Drives a progress ring towards the capture.
+    pub fn score(&self) -> f32 {
+        let stable = (MAX_STABILITY_VARIANCE / self.stability_variance.max(f32::MIN_POSITIVE)).min(1.0);
+        let sharp = (self.sharpness / MIN_SHARPNESS).min(1.0);
+        let exposure = (self.mean_luma / MIN_LUMA).min(1.0) * ((255.0 - self.mean_luma) / (255.0 - MAX_LUMA)).min(1.0);
+        let clipping = (2.0 - self.clipped_fraction / MAX_CLIPPED_FRACTION).clamp(0.0, 1.0);
+        stable * sharp * exposure * clipping
+    }
+
+    /// The quad held still over the recent detections (corner variance up to
+    /// 4 px²).
+    pub fn stable(&self) -> bool {
+        self.stability_variance <= MAX_STABILITY_VARIANCE
+    }
+
+    /// The frame is in focus and free of motion blur (Laplacian variance of at
+    /// least 100).
+    pub fn sharp(&self) -> bool {
+        self.sharpness >= MIN_SHARPNESS
+    }
+
+    /// Mean luma between 60 and 220 with at most 5 % of the pixels clipped.
+    pub fn well_lit(&self) -> bool {
+        (MIN_LUMA..=MAX_LUMA).contains(&self.mean_luma) && self.clipped_fraction <= MAX_CLIPPED_FRACTION
+    }
+
+    /// Mean variance of the corner coordinates in px², infinite without enough
+    /// detections.
+    #[wasm_bindgen(getter)]
+    pub fn stability_variance(&self) -> f32 {
+        self.stability_variance
+    }
+
+    /// Variance of the Laplacian of the frame (see `sharpness_score`).
+    #[wasm_bindgen(getter)]
+    pub fn sharpness(&self) -> f32 {
+        self.sharpness
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn mean_luma(&self) -> f32 {
+        self.mean_luma
+    }
+
+    /// Share of pixels at luma 250 or above.
+    #[wasm_bindgen(getter)]
+    pub fn clipped_fraction(&self) -> f32 {
+        self.clipped_fraction
+    }
+}
+
+// Measures `gray` for `CaptureReadiness`.
+pub(crate) fn measure(gray: &[u8], width: usize, height: usize, stability_variance: f32) -> CaptureReadiness {
+    let total: u64 = gray.iter().map(|&v| v as u64).sum();
+    let clipped = gray.iter().filter(|&&v| v >= CLIPPED_LUMA).count();
+    let pixels = gray.len().max(1) as f32;
+    CaptureReadiness {
+        stability_variance,
+        sharpness: laplacian_variance(gray, width, height),
+        mean_luma: total as f32 / pixels,
+        clipped_fraction: clipped as f32 / pixels,
+    }
+}
+
+/// Checks whether a frame is ready for an automatic capture: the document held
+/// still, the frame is sharp, and the exposure is usable. `ScanContext` has the
+/// same check on its last frame and detection history.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `stability_variance` - Corner variance of the recent detections in px²,
+///   e.g. from `ScanContext::stability_variance`
+///
+/// # Returns
+/// `CaptureReadiness` with the score, per-criterion flags and measurements
+#[wasm_bindgen]
+pub fn capture_readiness(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    stability_variance: f32,
+) -> Result<CaptureReadiness, ScanError> {
+    check_gray(gray, width, height)?;
+    Ok(measure(gray, width, height, stability_variance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_capture_readiness() {
+        // A page with fine text on a mid-grey desk, and the same frame blurred
+        let (width, height) = (64, 48);
+        let page: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let inside = (8..56).contains(&x) && (6..42).contains(&y);
+                if !inside { 90 } else if y % 4 == 0 && x % 3 != 0 { 40 } else { 200 }
+            })
+            .collect();
+        let blurred = crate::blur(&page, width, height, 9, 0.0).unwrap();
+
+        let ready = capture_readiness(&page, width, height, 1.0).unwrap();
+        assert!(ready.ready() && ready.score() == 1.0, "{ready:?}");
+        let soft = capture_readiness(&blurred, width, height, 1.0).unwrap();
+        assert!(!soft.sharp() && soft.stable() && soft.well_lit() && soft.score() < 0.5, "{soft:?}");
+        let moving = capture_readiness(&page, width, height, 16.0).unwrap();
+        assert!(!moving.ready() && (moving.score() - 0.25).abs() < 1e-6);
+        let dark: Vec<u8> = page.iter().map(|&v| v / 5).collect();
+        assert!(!capture_readiness(&dark, width, height, 1.0).unwrap().well_lit());
+
+        // The context uses its last frame and the recorded detections
+        let mut context = ScanContext::new(width, height);
+        context.process_gray_frame(&page).unwrap();
+        assert!(!context.capture_readiness().stable());
+        let quad = [8.0, 6.0, 56.0, 6.0, 56.0, 42.0, 8.0, 42.0];
+        for t in 0..5 {
+            context.record_detection(&quad, 1.0, t as f64 * 33.0).unwrap();
+        }
+        assert!(context.capture_readiness().ready());
+    }
+}
@@ -0,0 +1,420 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_channels, check_len, image_len, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+/// Sampling used by `resize`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Nearest source pixel; exact copies, fastest.
+    Nearest = 0,
+    /// Linear blend of the two nearest pixels per axis (pixel-centre aligned).
+    Bilinear = 1,
+    /// Average over the covered source area. Best for downscaling, since every
+    /// source pixel contributes and fine detail does not alias.
+    Area = 2,
+}
+
+// Filter weights are Q12 and sum to exactly 1 << WEIGHT_BITS per output sample.
+const WEIGHT_BITS: u32 = 12;
+const WEIGHT_ONE: u32 = 1 << WEIGHT_BITS;
+// The horizontal pass keeps 4 fractional bits (Q4) in its u16 intermediate, so
+// the vertical pass ends up in Q16: Q12 weights times Q4 samples.
+const H_SHIFT: u32 = WEIGHT_BITS - 4;
+const V_SHIFT: u32 = WEIGHT_BITS + 4;
+
+// Separable filter along one axis: output `i` reads the source samples
+// `starts[i]..starts[i] + taps` with weights `weights[i * taps..(i + 1) * taps]`.
+struct AxisFilter {
+    taps: usize,
+    starts: Vec<usize>,
+    weights: Vec<u16>,
+}
+
+impl AxisFilter {
+    fn new(src_len: usize, dst_len: usize, interpolation: Interpolation) -> AxisFilter {
+        let scale = src_len as f64 / dst_len as f64;
+        let last = src_len - 1;
+
+        // (source index, weight) pairs for each output sample, in ascending index order
+        let contributions: Vec<Vec<(usize, f64)>> = (0..dst_len)
+            .map(|i| match interpolation {
+                Interpolation::Nearest => {
+                    let sx = ((i as f64 + 0.5) * scale) as usize;
+                    vec![(sx.min(last), 1.0)]
+                }
+                Interpolation::Bilinear => {
+                    let sx = ((i as f64 + 0.5) * scale - 0.5).clamp(0.0, last as f64);
+                    let x0 = sx as usize;
+                    let fx = sx - x0 as f64;
+                    if x0 == last || fx == 0.0 {
+                        vec![(x0, 1.0)]
+                    } else {
+                        vec![(x0, 1.0 - fx), (x0 + 1, fx)]
+                    }
+                }
+                Interpolation::Area => {
+                    let lo = i as f64 * scale;
+                    let hi = ((i + 1) as f64 * scale).min(src_len as f64);
+                    let first = lo as usize;
+                    let end = (hi.ceil() as usize).clamp(first + 1, src_len);
+                    (first..end)
+                        .map(|j| {
+                            let overlap = hi.min((j + 1) as f64) - lo.max(j as f64);
+                            (j, overlap.max(0.0) / scale)
+                        })
+                        .collect()
+                }
+            })
+            .collect();
+
+        let taps = contributions
+            .iter()
+            .map(|c| c[c.len() - 1].0 - c[0].0 + 1)
+            .max()
+            .unwrap_or(1);
+
+        let mut starts = Vec::with_capacity(dst_len);
+        let mut weights = vec![0u16; dst_len * taps];
+        for (i, contribution) in contributions.iter().enumerate() {
+            // Keep the whole window inside the source; the extra taps get zero weight
+            let start = contribution[0].0.min(src_len - taps);
+            let total: f64 = contribution.iter().map(|&(_, w)| w).sum();
+            let window = &mut weights[i * taps..(i + 1) * taps];
+
+            let mut sum = 0u32;
+            for &(j, w) in contribution {
+                let q = (w / total * WEIGHT_ONE as f64).round() as u32;
+                window[j - start] = q as u16;
+                sum += q;
+            }
+            // Put the rounding error on the largest tap so flat areas stay exact
+            let (largest, _) = window.iter().enumerate().max_by_key(|&(_, &w)| w).unwrap();
+            window[largest] = (window[largest] as i32 + WEIGHT_ONE as i32 - sum as i32) as u16;
+            starts.push(start);
+        }
+
+        AxisFilter { taps, starts, weights }
+    }
+
+    #[inline]
+    fn weights(&self, i: usize) -> &[u16] {
+        &self.weights[i * self.taps..(i + 1) * self.taps]
+    }
+}
+
+// Horizontal pass for one source row into a Q4 row of dst_width * channels samples.
+fn horizontal_row(src_row: &[u8], dst_row: &mut [u16], channels: usize, filter: &AxisFilter) {
+    #[cfg(target_family = "wasm")]
+    {
+        if channels == 4 && simd_enabled() {
+            unsafe {
+                horizontal_row_rgba_simd(src_row, dst_row, filter);
+            }
+            return;
+        }
+    }
+
+    for (x, out) in dst_row.chunks_exact_mut(channels).enumerate() {
+        let start = filter.starts[x];
+        for (c, value) in out.iter_mut().enumerate() {
+            let mut sum = 0u32;
+            for (t, &w) in filter.weights(x).iter().enumerate() {
+                sum += src_row[(start + t) * channels + c] as u32 * w as u32;
+            }
+            *value = ((sum + (1 << (H_SHIFT - 1))) >> H_SHIFT) as u16;
+        }
+    }
+}
+
+// One RGBA pixel per iteration: all four channels share the tap weights, so they
+// are accumulated together in a u32x4.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn horizontal_row_rgba_simd(src_row: &[u8], dst_row: &mut [u16], filter: &AxisFilter) {
+    let round = u32x4_splat(1 << (H_SHIFT - 1));
+    for x in 0..dst_row.len() / 4 {
+        let start = filter.starts[x];
+        let mut sum = u32x4_splat(0);
+        for (t, &w) in filter.weights(x).iter().enumerate() {
+            let px = v128_load32_zero(src_row.as_ptr().add((start + t) * 4) as *const u32);
+            let px = u32x4_extend_low_u16x8(u16x8_extend_low_u8x16(px));
+            sum = u32x4_add(sum, u32x4_mul(px, u32x4_splat(w as u32)));
+        }
+        let value = u32x4_shr(u32x4_add(sum, round), H_SHIFT);
+        let packed = u16x8_narrow_i32x4(value, value);
+        v128_store64_lane::<0>(packed, dst_row.as_mut_ptr().add(x * 4) as *mut u64);
+    }
+}
+
+// Vertical pass for one output row from the Q4 rows its taps reference.
+fn vertical_row(rows: &[u16], row_len: usize, start: usize, weights: &[u16], dst_row: &mut [u8]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { vertical_row_simd(rows, row_len, start, weights, dst_row) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for (x, out) in dst_row.iter_mut().enumerate().skip(done) {
+        let mut sum = 0u32;
+        for (t, &w) in weights.iter().enumerate() {
+            sum += rows[(start + t) * row_len + x] as u32 * w as u32;
+        }
+        *out = ((sum + (1 << (V_SHIFT - 1))) >> V_SHIFT).min(255) as u8;
+    }
+}
+
+// Processes 8 samples per iteration; returns how many were done.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn vertical_row_simd(rows: &[u16], row_len: usize, start: usize, weights: &[u16], dst_row: &mut [u8]) -> usize {
+    let round = u32x4_splat(1 << (V_SHIFT - 1));
+    let mut x = 0;
+    while x + 8 <= row_len {
+        let mut lo = round;
+        let mut hi = round;
+        for (t, &w) in weights.iter().enumerate() {
+            if w == 0 {
+                continue;
+            }
+            let samples = v128_load(rows.as_ptr().add((start + t) * row_len + x) as *const v128);
+            let weight = u16x8_splat(w);
+            lo = u32x4_add(lo, u32x4_extmul_low_u16x8(samples, weight));
+            hi = u32x4_add(hi, u32x4_extmul_high_u16x8(samples, weight));
+        }
+        let words = u16x8_narrow_i32x4(u32x4_shr(lo, V_SHIFT), u32x4_shr(hi, V_SHIFT));
+        let bytes = u8x16_narrow_i16x8(words, words);
+        v128_store64_lane::<0>(bytes, dst_row.as_mut_ptr().add(x) as *mut u64);
+        x += 8;
+    }
+    x
+}
+
+// Resize into a caller-owned buffer of dst_width * dst_height * channels bytes.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn resize_into(
+    src: &[u8],
+    src_width: usize,
+    src_height: usize,
+    channels: usize,
+    dst_width: usize,
+    dst_height: usize,
+    interpolation: Interpolation,
+    dst: &mut [u8],
+) {
+    if dst_width == 0 || dst_height == 0 {
+        return;
+    }
+    if src_width == 0 || src_height == 0 {
+        panic!("Source image is empty");
+    }
+
+    let h_filter = AxisFilter::new(src_width, dst_width, interpolation);
+    let v_filter = AxisFilter::new(src_height, dst_height, interpolation);
+
+    // Only source rows some output row actually reads go through the horizontal
+    // pass (bilinear and nearest downscaling skip most of them)
+    let mut needed = vec![false; src_height];
+    for (y, &start) in v_filter.starts.iter().enumerate() {
+        for (t, &w) in v_filter.weights(y).iter().enumerate() {
+            needed[start + t] |= w > 0;
+        }
+    }
+
+    let src_row_len = src_width * channels;
+    let row_len = dst_width * channels;
+    let mut rows = vec![0u16; row_len * src_height];
+    for (y, row) in rows.chunks_exact_mut(row_len).enumerate() {
+        if needed[y] {
+            horizontal_row(&src[y * src_row_len..(y + 1) * src_row_len], row, channels, &h_filter);
+        }
+    }
+
+    for (y, dst_row) in dst.chunks_exact_mut(row_len).enumerate() {
+        vertical_row(&rows, row_len, v_filter.starts[y], v_filter.weights(y), dst_row);
+    }
+}
+
+/// Resizes a grayscale or RGBA image.
+///
+/// # Arguments
+/// * `src` - Source pixels, `channels` bytes per pixel
+/// * `src_width` - Source width
+/// * `src_height` - Source height
+/// * `channels` - 1 for grayscale, 4 for RGBA
+/// * `dst_width` - Output width
+/// * `dst_height` - Output height
+/// * `interpolation` - Nearest, bilinear or area averaging
+///
+/// # Returns
+/// Resized image as Vec<u8> with the same channel layout as the input
+#[wasm_bindgen]
+pub fn resize(
+    src: &[u8],
+    src_width: usize,
+    src_height: usize,
+    channels: usize,
+    dst_width: usize,
+    dst_height: usize,
+    interpolation: Interpolation,
+) -> Result<Vec<u8>, ScanError> {
+    check_channels(channels)?;
+    check_len(src.len(), image_len(src_width, src_height, channels)?, "width * height * channels")?;
+    let dst_len = image_len(dst_width, dst_height, channels)?;
+    if src.is_empty() && dst_len > 0 {
+        return Err(ScanError::InvalidParameter("Source image is empty"));
+    }
+    let mut dst = vec![0u8; dst_len];
+    resize_into(src, src_width, src_height, channels, dst_width, dst_height, interpolation, &mut dst);
+    Ok(dst)
+}
+
+/// Coordinate contract between a capture and the reduced frame detection runs on.
+///
+/// * Size: frames whose longer side exceeds `max_dimension` are scaled so that
+///   side is exactly `max_dimension`; the shorter side is rounded to the nearest
+///   pixel (halves away from zero, like JS `Math.round` for positive values) and
+///   is at least 1. Smaller frames are used as is.
+/// * Coordinates: an integer coordinate is the centre of a pixel, as in the
+///   edge map. Each axis scales by its own exact ratio of sizes, so rounding the
+///   reduced size never skews the quad: `full = (reduced + 0.5) * scale - 0.5`.
+/// * Precision: coordinates are mapped in f64 and never rounded, so sub-pixel
+///   positions survive the round trip.
+///
+/// This matches `resize`, which samples at pixel centres in every mode.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScaleMapping {
+    full_width: usize,
+    full_height: usize,
+    width: usize,
+    height: usize,
+}
+
+#[wasm_bindgen]
+impl ScaleMapping {
+    /// Mapping for a `full_width` x `full_height` capture processed at no more
+    /// than `max_dimension` pixels on its longer side; 0 disables downscaling.
+    #[wasm_bindgen(constructor)]
+    pub fn new(full_width: usize, full_height: usize, max_dimension: usize) -> ScaleMapping {
+        let longer = full_width.max(full_height);
+        let (width, height) = if max_dimension == 0 || longer <= max_dimension {
+            (full_width, full_height)
+        } else {
+            let scale = max_dimension as f64 / longer as f64;
+            let side = |len: usize| ((len as f64 * scale).round() as usize).clamp(1, max_dimension);
+            (side(full_width), side(full_height))
+        };
+        ScaleMapping { full_width, full_height, width, height }
+    }
+
+    /// Width of the reduced frame.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the reduced frame.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Factor from reduced to full-resolution x distances.
+    #[wasm_bindgen(getter)]
+    pub fn scale_x(&self) -> f64 {
+        self.full_width as f64 / self.width.max(1) as f64
+    }
+
+    /// Factor from reduced to full-resolution y distances.
+    #[wasm_bindgen(getter)]
+    pub fn scale_y(&self) -> f64 {
+        self.full_height as f64 / self.height.max(1) as f64
+    }
+
+    /// Downscales a grayscale or RGBA capture to the reduced size with area
+    /// averaging, the sampling the coordinate mapping assumes.
+    pub fn downscale(&self, src: &[u8], channels: usize) -> Result<Vec<u8>, ScanError> {
+        resize(src, self.full_width, self.full_height, channels, self.width, self.height, Interpolation::Area)
+    }
+
+    /// Maps x, y pairs found on the reduced frame (e.g. quad corners) to
+    /// full-resolution coordinates.
+    pub fn to_full(&self, points: &[f32]) -> Result<Vec<f32>, ScanError> {
+        self.map(points, self.scale_x(), self.scale_y())
+    }
+
+    /// Maps full-resolution x, y pairs to the reduced frame.
+    pub fn to_reduced(&self, points: &[f32]) -> Result<Vec<f32>, ScanError> {
+        self.map(points, 1.0 / self.scale_x(), 1.0 / self.scale_y())
+    }
+}
+
+impl ScaleMapping {
+    fn map(&self, points: &[f32], sx: f64, sy: f64) -> Result<Vec<f32>, ScanError> {
+        if !points.len().is_multiple_of(2) {
+            return Err(ScanError::InvalidPoints("Points must be x, y pairs"));
+        }
+        let axis = |v: f32, scale: f64| ((v as f64 + 0.5) * scale - 0.5) as f32;
+        Ok(points.chunks_exact(2).flat_map(|p| [axis(p[0], sx), axis(p[1], sy)]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_modes() {
+        // Flat images stay flat in every mode and at every scale
+        let flat = vec![137u8; 13 * 7 * 4];
+        for &mode in &[Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Area] {
+            for &(w, h) in &[(5, 3), (13, 7), (40, 19)] {
+                assert!(resize(&flat, 13, 7, 4, w, h, mode).unwrap().iter().all(|&v| v == 137));
+            }
+        }
+
+        // Area 2x downscale averages 2x2 blocks; nearest upscale repeats pixels
+        let gray = vec![0u8, 100, 200, 50, 0, 100, 200, 50];
+        assert_eq!(resize(&gray, 4, 2, 1, 2, 1, Interpolation::Area).unwrap(), vec![50, 125]);
+        assert_eq!(resize(&gray[..2], 2, 1, 1, 4, 1, Interpolation::Nearest).unwrap(), vec![0, 0, 100, 100]);
+
+        // Bilinear upscale interpolates between pixel centres
+        assert_eq!(resize(&[0, 200], 2, 1, 1, 4, 1, Interpolation::Bilinear).unwrap(), vec![0, 50, 150, 200]);
+    }
+
+    #[test]
+    fn test_scale_mapping() {
+        // 4032x3024 at 800 gives exactly 800x600; 1001x333 at 100 rounds 33.27 to 33
+        let mapping = ScaleMapping::new(4032, 3024, 800);
+        assert_eq!((mapping.width(), mapping.height()), (800, 600));
+        assert_eq!((ScaleMapping::new(1001, 333, 100).width(), ScaleMapping::new(1001, 333, 100).height()), (100, 33));
+        assert_eq!(ScaleMapping::new(640, 480, 800).to_full(&[12.25, 7.5]).unwrap(), vec![12.25, 7.5]);
+        assert_eq!(ScaleMapping::new(640, 480, 0).width(), 640);
+
+        // Pixel centres map to pixel centres: the first reduced pixel covers full
+        // pixels 0..5, centred on 2.0
+        let mapping = ScaleMapping::new(40, 20, 8);
+        assert_eq!((mapping.width(), mapping.height()), (8, 4));
+        assert_eq!(mapping.to_full(&[0.0, 0.0, 7.0, 3.0]).unwrap(), vec![2.0, 2.0, 37.0, 17.0]);
+        let quad = [1.3, 0.6, 6.7, 0.4, 6.9, 3.2, 1.1, 3.4];
+        let round_trip = mapping.to_reduced(&mapping.to_full(&quad).unwrap()).unwrap();
+        assert!(round_trip.iter().zip(quad).all(|(a, b)| (a - b).abs() < 1e-5));
+
+        // A bright column at full pixels 10..15 lands on reduced pixel 2
+        let mut gray = vec![0u8; 40 * 20];
+        for row in gray.chunks_exact_mut(40) {
+            row[10..15].fill(255);
+        }
+        let small = mapping.downscale(&gray, 1).unwrap();
+        assert_eq!(&small[..8], &[0, 0, 255, 0, 0, 0, 0, 0]);
+        assert_eq!(mapping.to_full(&[2.0, 0.0]).unwrap()[0], 12.0);
+    }
+}
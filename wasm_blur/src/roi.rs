@@ -0,0 +1,276 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_len, image_len, ScanError};
+
+/// Rectangular region of interest, in pixels.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Roi {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+#[wasm_bindgen]
+impl Roi {
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Roi {
+        Roi { x, y, width, height }
+    }
+}
+
+impl Roi {
+    // The part of the region inside a `width` x `height` image.
+    fn clip(self, width: usize, height: usize) -> Roi {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Roi {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+
+    // The region grown by `margin` on every side, clipped to the image.
+    fn padded(self, margin: usize, width: usize, height: usize) -> Roi {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        Roi {
+            x,
+            y,
+            width: (self.x + self.width + margin).min(width) - x,
+            height: (self.y + self.height + margin).min(height) - y,
+        }
+    }
+}
+
+// Copies `roi` out of an image with `channels` bytes per pixel.
+fn crop_into(src: &[u8], width: usize, channels: usize, roi: Roi, dst: &mut [u8]) {
+    let row_bytes = roi.width * channels;
+    if row_bytes == 0 {
+        return;
+    }
+    for (r, out) in dst.chunks_exact_mut(row_bytes).enumerate() {
+        let start = ((roi.y + r) * width + roi.x) * channels;
+        out.copy_from_slice(&src[start..start + row_bytes]);
+    }
+}
+
+// Runs `stage` on the ROI of a grayscale image only. The stage sees the ROI
+// padded by `margin` pixels of real image content, so its border handling only
+// affects pixels outside the ROI. Outside the ROI the result keeps the input
+// (`keep_outside`) or is 0. Without a ROI the stage runs on the whole image.
+fn run_in_roi(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    roi: Option<Roi>,
+    margin: usize,
+    keep_outside: bool,
+    stage: impl FnOnce(&[u8], usize, usize) -> Result<Vec<u8>, ScanError>,
+) -> Result<Vec<u8>, ScanError> {
+    check_gray(src, width, height)?;
+    let Some(roi) = roi else {
+        return stage(src, width, height);
+    };
+
+    let roi = roi.clip(width, height);
+    let mut out = if keep_outside { src.to_vec() } else { vec![0u8; src.len()] };
+    if roi.width == 0 || roi.height == 0 {
+        return Ok(out);
+    }
+    let padded = roi.padded(margin, width, height);
+    let mut region = vec![0u8; padded.width * padded.height];
+    crop_into(src, width, 1, padded, &mut region);
+    let processed = stage(&region, padded.width, padded.height)?;
+
+    let (dx, dy) = (roi.x - padded.x, roi.y - padded.y);
+    for r in 0..roi.height {
+        let from = (dy + r) * padded.width + dx;
+        let to = (roi.y + r) * width + roi.x;
+        out[to..to + roi.width].copy_from_slice(&processed[from..from + roi.width]);
+    }
+    Ok(out)
+}
+
+/// Copies a rectangle out of a grayscale or RGBA image.
+///
+/// # Arguments
+/// * `src` - Grayscale or RGBA pixels; the layout is taken from the length
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `x` - Left edge of the rectangle
+/// * `y` - Top edge of the rectangle
+/// * `w` - Rectangle width
+/// * `h` - Rectangle height
+///
+/// # Returns
+/// The rectangle's pixels as Vec<u8>, in the input layout. Parts of the rectangle
+/// outside the image are cut off.
+#[wasm_bindgen]
+pub fn crop(
+    src: &[u8],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> Result<Vec<u8>, ScanError> {
+    let pixels = image_len(width, height, 1)?;
+    let channels = if src.len() == pixels { 1 } else { 4 };
+    check_len(src.len(), image_len(width, height, channels)?, "width * height or width * height * 4")?;
+    let roi = Roi::new(x, y, w, h).clip(width, height);
+    let mut out = vec![0u8; roi.width * roi.height * channels];
+    crop_into(src, width, channels, roi, &mut out);
+    Ok(out)
+}
+
+/// `blur` restricted to a region of interest; pixels outside it are returned
+/// unchanged.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Odd Gaussian kernel size
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+/// * `roi` - Region to process; None processes the whole image
+///
+/// # Returns
+/// Blurred image as Vec<u8>, same size as the input
+#[wasm_bindgen]
+pub fn blur_roi(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    sigma: f32,
+    roi: Option<Roi>,
+) -> Result<Vec<u8>, ScanError> {
+    run_in_roi(grayscale, width, height, roi, kernel_size / 2, true, |src, w, h| {
+        crate::blur(src, w, h, kernel_size, sigma)
+    })
+}
+
+/// `canny_edge_detector_full` restricted to a region of interest, e.g. a
+/// user-drawn area the document is known to be in. No edges are reported
+/// outside the region.
+///
+/// # Arguments
+/// * `grayscale` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `low_threshold` - Hysteresis low threshold
+/// * `high_threshold` - Hysteresis high threshold
+/// * `kernel_size` - Odd Gaussian kernel size
+/// * `sigma` - Gaussian sigma; 0 derives it from the kernel size
+/// * `l2_gradient` - Use the L2 gradient magnitude
+/// * `apply_dilation` - Dilate the edge map
+/// * `dilation_kernel_size` - Dilation kernel size
+/// * `roi` - Region to process; None processes the whole image
+///
+/// # Returns
+/// Binary edge map as Vec<u8>, same size as the input
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn canny_roi(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    low_threshold: f32,
+    high_threshold: f32,
+    kernel_size: usize,
+    sigma: f32,
+    l2_gradient: bool,
+    apply_dilation: bool,
+    dilation_kernel_size: usize,
+    roi: Option<Roi>,
+) -> Result<Vec<u8>, ScanError> {
+    // Blur, the 3x3 gradient and dilation each reach a little past the ROI
+    let margin = kernel_size / 2 + 1 + if apply_dilation { dilation_kernel_size / 2 } else { 0 };
+    run_in_roi(grayscale, width, height, roi, margin, false, |src, w, h| {
+        crate::canny::canny_edge_detector_full(
+            src,
+            w,
+            h,
+            low_threshold,
+            high_threshold,
+            kernel_size,
+            sigma,
+            l2_gradient,
+            apply_dilation,
+            dilation_kernel_size,
+        )
+    })
+}
+
+/// `dilate` restricted to a region of interest; pixels outside it are returned
+/// unchanged.
+///
+/// # Arguments
+/// * `edges` - Edge map
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Dilation kernel size
+/// * `roi` - Region to process; None processes the whole image
+///
+/// # Returns
+/// Dilated edge map as Vec<u8>, same size as the input
+#[wasm_bindgen]
+pub fn dilate_roi(
+    edges: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    roi: Option<Roi>,
+) -> Result<Vec<u8>, ScanError> {
+    run_in_roi(edges, width, height, roi, kernel_size / 2, true, |src, w, h| {
+        crate::dilation::dilate(src, w, h, kernel_size)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crop_and_roi_processing() {
+        let (width, height) = (16usize, 12usize);
+        let gray: Vec<u8> = (0..width * height).map(|i| (i * 37 % 256) as u8).collect();
+        assert_eq!(crop(&gray, width, height, 2, 3, 2, 2).unwrap(), vec![gray[50], gray[51], gray[66], gray[67]]);
+        let rgba: Vec<u8> = gray.iter().flat_map(|&v| [v, v, v, 255]).collect();
+        assert_eq!(crop(&rgba, width, height, 14, 10, 5, 5).unwrap().len(), 2 * 2 * 4);
+
+        // Inside the ROI the result matches processing the whole image; the
+        // padding keeps the ROI border free of edge effects
+        let roi = Roi::new(4, 3, 6, 5);
+        let full = crate::blur(&gray, width, height, 5, 0.0).unwrap();
+        let partial = blur_roi(&gray, width, height, 5, 0.0, Some(roi)).unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let inside = (4..10).contains(&x) && (3..8).contains(&y);
+                assert_eq!(partial[i], if inside { full[i] } else { gray[i] });
+            }
+        }
+        assert_eq!(blur_roi(&gray, width, height, 5, 0.0, None).unwrap(), full);
+
+        // Edges only appear inside the ROI
+        let mut square = vec![0u8; width * height];
+        for y in 2..10 {
+            square[y * width + 2..y * width + 14].fill(255);
+        }
+        let roi = Some(Roi::new(0, 0, 8, 12));
+        let edges = canny_roi(&square, width, height, 50.0, 100.0, 3, 0.0, false, false, 3, roi).unwrap();
+        assert!(edges.iter().enumerate().any(|(i, &e)| e != 0 && i % width < 8));
+        assert!(edges.iter().enumerate().all(|(i, &e)| e == 0 || i % width < 8));
+        let dilated = dilate_roi(&edges, width, height, 3, Some(Roi::new(0, 0, width, height))).unwrap();
+        assert_eq!(dilated, crate::dilation::dilate(&edges, width, height, 3).unwrap());
+    }
+}
@@ -0,0 +1,189 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::border::BorderMode;
+use crate::error::{check_gray, check_rgba, ScanError};
+use crate::gaussian_blur::blur_into;
+use crate::gradient_calculation::gradients_into;
+use crate::warp::{warp_with_matrix, OutOfBoundsPolicy};
+
+// Blur applied before the gradients so staircase edges give stable slopes.
+const KERNEL_SIZE: usize = 5;
+// Gradients weaker than this (|gx| + |gy|, Sobel units) are noise, not edges.
+const MIN_MAGNITUDE: i32 = 96;
+// Histogram bin width in slope units (about 0.25 degrees).
+const BIN_SLOPE: f32 = 0.004;
+// Bins either side of the peak averaged for the refined slope.
+const REFINE_BINS: usize = 2;
+
+// Magnitude-weighted histograms of edge slopes within `max_slope`: dy/dx of
+// near-horizontal edges and dx/dy of near-vertical ones.
+struct SlopeHistograms {
+    horizontal: Vec<f32>,
+    vertical: Vec<f32>,
+    max_slope: f32,
+}
+
+impl SlopeHistograms {
+    fn new(max_slope: f32) -> SlopeHistograms {
+        let bins = (2.0 * max_slope / BIN_SLOPE) as usize + 1;
+        SlopeHistograms { horizontal: vec![0.0; bins], vertical: vec![0.0; bins], max_slope }
+    }
+
+    fn add(&mut self, gx: i32, gy: i32) {
+        let magnitude = gx.abs() + gy.abs();
+        if magnitude < MIN_MAGNITUDE {
+            return;
+        }
+        // The edge runs perpendicular to the gradient, along (-gy, gx)
+        let (slope, bins) = if gy.abs() > gx.abs() {
+            (-gx as f32 / gy as f32, &mut self.horizontal)
+        } else {
+            (-gy as f32 / gx as f32, &mut self.vertical)
+        };
+        if slope.abs() <= self.max_slope {
+            bins[((slope + self.max_slope) / BIN_SLOPE) as usize] += magnitude as f32;
+        }
+    }
+}
+
+// Weighted mean slope around the strongest bin, None without any edges.
+fn dominant_slope(bins: &[f32], max_slope: f32) -> Option<f32> {
+    let (peak, _) = bins.iter().enumerate().filter(|(_, &w)| w > 0.0).max_by(|a, b| a.1.total_cmp(b.1))?;
+    let (from, to) = (peak.saturating_sub(REFINE_BINS), (peak + REFINE_BINS + 1).min(bins.len()));
+    let (mut sum, mut weight) = (0f32, 0f32);
+    for (i, &w) in (from..).zip(&bins[from..to]) {
+        sum += w * ((i as f32 + 0.5) * BIN_SLOPE - max_slope);
+        weight += w;
+    }
+    Some(sum / weight)
+}
+
+/// Estimates the rolling-shutter skew of a video frame. Cameras read the sensor
+/// row by row, so panning sideways shifts each row a little further than the one
+/// above and straight vertical edges lean over while horizontal ones stay level.
+/// The shear is found from edge orientation statistics as the lean of the
+/// dominant near-vertical edges that a rotation of the near-horizontal ones does
+/// not explain; a frame that is merely rotated gives 0.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `max_angle` - Largest edge tilt considered, in degrees either way
+///
+/// # Returns
+/// Horizontal shift per row (dx/dy), positive when lower rows are shifted right;
+/// 0 for frames without both horizontal and vertical edges
+#[wasm_bindgen]
+pub fn estimate_rolling_shutter(gray: &[u8], width: usize, height: usize, max_angle: f32) -> Result<f32, ScanError> {
+    check_gray(gray, width, height)?;
+    if width < 3 || height < 3 {
+        return Ok(0.0);
+    }
+    let (sin, cos) = max_angle.clamp(0.0, 30.0).to_radians().sin_cos();
+    let mut histograms = SlopeHistograms::new(sin / cos);
+
+    let mut temp = vec![0u32; width * height];
+    let mut blurred = vec![0u8; width * height];
+    blur_into(gray, width, height, KERNEL_SIZE, 0.0, BorderMode::Replicate, &mut temp, &mut blurred);
+    let mut gradients = vec![0i16; 2 * width * height];
+    gradients_into(&blurred, width, height, &mut gradients);
+    for row in gradients.chunks_exact(2 * width).skip(1).take(height - 2) {
+        for g in row[2..2 * width - 2].chunks_exact(2) {
+            histograms.add(g[0] as i32, g[1] as i32);
+        }
+    }
+
+    let horizontal = dominant_slope(&histograms.horizontal, histograms.max_slope);
+    let vertical = dominant_slope(&histograms.vertical, histograms.max_slope);
+    Ok(match (horizontal, vertical) {
+        // A rotation by a small angle tilts horizontal edges by +slope and
+        // vertical ones by -slope, so only the shear survives the sum
+        (Some(h), Some(v)) => h + v,
+        _ => 0.0,
+    })
+}
+
+// Destination-to-source matrix undoing a shear of `shear` px per row, keeping
+// the middle row in place.
+pub(crate) fn shear_matrix(shear: f32, height: usize) -> [f64; 9] {
+    let shear = shear as f64;
+    let cy = (height.max(1) - 1) as f64 / 2.0;
+    [1.0, shear, -shear * cy, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+}
+
+/// Removes rolling-shutter skew from an RGBA frame by warping it with the
+/// opposite shear (see `estimate_rolling_shutter`). The middle row stays in
+/// place and pixels sheared in from outside replicate the frame border.
+///
+/// Rectifying a page from corners detected on the same frame already absorbs a
+/// uniform shear; compensate full-frame captures, or frames before detection.
+///
+/// # Arguments
+/// * `rgba` - RGBA frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `shear` - Horizontal shift per row, as returned by `estimate_rolling_shutter`
+///
+/// # Returns
+/// Compensated RGBA frame as Vec<u8>, same size
+#[wasm_bindgen]
+pub fn compensate_rolling_shutter(rgba: &[u8], width: usize, height: usize, shear: f32) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    if !shear.is_finite() {
+        return Err(ScanError::InvalidParameter("Shear must be finite"));
+    }
+    if shear == 0.0 {
+        return Ok(rgba.to_vec());
+    }
+    let m = shear_matrix(shear, height);
+    Ok(warp_with_matrix(rgba, width, height, &m, width, height, OutOfBoundsPolicy::Replicate, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bright page on a dark desk, rotated by `angle` degrees and sheared by
+    // `shear` px per row about the frame centre, rendered with 4x4 supersampling.
+    fn frame(width: usize, height: usize, angle: f32, shear: f32) -> Vec<u8> {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let mut gray = vec![0u8; width * height];
+        for (i, value) in gray.iter_mut().enumerate() {
+            let mut covered = 0u32;
+            for s in 0..16 {
+                let x = (i % width) as f32 + (s % 4) as f32 / 4.0 - cx;
+                let y = (i / width) as f32 + (s / 4) as f32 / 4.0 - cy;
+                let x = x - shear * y;
+                let (u, v) = (x * cos + y * sin, y * cos - x * sin);
+                covered += (u.abs() < 0.35 * width as f32 && v.abs() < 0.35 * height as f32) as u32;
+            }
+            *value = (40 + covered * 200 / 16) as u8;
+        }
+        gray
+    }
+
+    #[test]
+    fn test_estimate_and_compensate_rolling_shutter() {
+        let (width, height) = (200, 160);
+        let skewed = frame(width, height, 0.0, 0.08);
+        let shear = estimate_rolling_shutter(&skewed, width, height, 15.0).unwrap();
+        assert!((shear - 0.08).abs() < 0.01, "shear {shear}");
+
+        // Rotation alone is not mistaken for rolling shutter
+        let rotated = estimate_rolling_shutter(&frame(width, height, 4.0, 0.0), width, height, 15.0).unwrap();
+        assert!(rotated.abs() < 0.01, "rotated {rotated}");
+        assert_eq!(estimate_rolling_shutter(&[128u8; 64], 8, 8, 15.0).unwrap(), 0.0);
+
+        // Undoing the estimated shear straightens the page edges
+        let rgba: Vec<u8> = skewed.iter().flat_map(|&v| [v, v, v, 255]).collect();
+        let straight = compensate_rolling_shutter(&rgba, width, height, shear).unwrap();
+        let gray: Vec<u8> = straight.chunks_exact(4).map(|p| p[0]).collect();
+        assert!(estimate_rolling_shutter(&gray, width, height, 15.0).unwrap().abs() < 0.01);
+        assert!(compensate_rolling_shutter(&rgba, width, height, f32::NAN).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+// Cooperative priority between live preview frames and background work (export,
+// encoding) sharing the same thread pool. Preview work marks itself active with a
+// `PreviewGuard`; long background loops call `yield_point()` at row/page
+// boundaries and back off while a preview frame is in flight.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+static ACTIVE_PREVIEWS: AtomicUsize = AtomicUsize::new(0);
+static BACKGROUND_YIELDS: AtomicUsize = AtomicUsize::new(0);
+
+// Upper bound on how long one yield point waits, so back-to-back preview frames
+// can slow background work down but never starve it completely.
+#[cfg(feature = "threads")]
+const MAX_YIELD_SPINS: usize = 1 << 16;
+
+// Background loops only check for preview work every this many rows. Only the
+// optional warp/export stages run such loops.
+#[cfg_attr(not(feature = "warp"), allow(dead_code))]
+pub(crate) const YIELD_INTERVAL_ROWS: usize = 64;
+
+// Rows per rayon task in the row-parallel image passes. Small enough to balance a
+// 4K frame across the pool, large enough to keep task overhead negligible.
+pub(crate) const ROWS_PER_TASK: usize = 16;
+
+/// Marks preview work as running for as long as the guard is alive.
+pub(crate) struct PreviewGuard(());
+
+impl Drop for PreviewGuard {
+    fn drop(&mut self) {
+        ACTIVE_PREVIEWS.fetch_sub(1, Ordering::Release);
+    }
+}
+
+pub(crate) fn preview_scope() -> PreviewGuard {
+    ACTIVE_PREVIEWS.fetch_add(1, Ordering::Acquire);
+    PreviewGuard(())
+}
+
+/// Explicit yield point for background work. Without the `threads` feature
+/// everything runs on the caller's thread and this is a no-op.
+#[cfg_attr(not(feature = "warp"), allow(dead_code))]
+pub(crate) fn yield_point() {
+    #[cfg(feature = "threads")]
+    {
+        if ACTIVE_PREVIEWS.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        BACKGROUND_YIELDS.fetch_add(1, Ordering::Relaxed);
+
+        let mut spins = 0;
+        while ACTIVE_PREVIEWS.load(Ordering::Acquire) > 0 && spins < MAX_YIELD_SPINS {
+            // Help with pending pool jobs (typically the preview frame's own row
+            // tasks) before spinning.
+            match rayon::yield_now() {
+                Some(rayon::Yield::Executed) => {}
+                _ => core::hint::spin_loop(),
+            }
+            spins += 1;
+        }
+    }
+}
+
+/// True while a preview frame is being processed.
+#[wasm_bindgen]
+pub fn preview_in_flight() -> bool {
+    ACTIVE_PREVIEWS.load(Ordering::Acquire) > 0
+}
+
+/// Number of times background work has backed off for a preview frame.
+#[wasm_bindgen]
+pub fn background_yield_count() -> usize {
+    BACKGROUND_YIELDS.load(Ordering::Relaxed)
+}
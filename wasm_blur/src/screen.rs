@@ -0,0 +1,240 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// Blur kernel in screen-capture mode, wide enough to wash out the moiré between
+// the display's pixel grid and the camera sensor.
+pub(crate) const KERNEL_SIZE: usize = 9;
+// Steps in log brightness between neighbouring rows are binned this finely,
+// within ±MAX_STEP.
+const STEP_BIN: f32 = 1.0 / 128.0;
+const MAX_STEP: f32 = 0.5;
+// Rows on either side averaged for the band-free trend: a share of the height,
+// at least MIN_TREND_RADIUS.
+const TREND_FRACTION: usize = 8;
+const MIN_TREND_RADIUS: usize = 8;
+// Largest correction applied to a row; refresh bands are rarely stronger, and
+// page edges that shift the row means are only ever dimmed this much.
+const MAX_ROW_GAIN: f32 = 1.25;
+// Bezel pixels are at most this bright, and edges within BEZEL_MARGIN px of the
+// bezel are dropped.
+const BEZEL_LUMA: u8 = 48;
+const BEZEL_MARGIN: usize = 6;
+
+// Mean over a window of `radius` entries either side, clamped at the ends.
+fn box_mean(values: &[f32], radius: usize) -> Vec<f32> {
+    let mut prefix = vec![0f32; values.len() + 1];
+    for (i, &v) in values.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + v;
+    }
+    (0..values.len())
+        .map(|i| {
+            let (from, to) = (i.saturating_sub(radius), (i + radius + 1).min(values.len()));
+            (prefix[to] - prefix[from]) / (to - from) as f32
+        })
+        .collect()
+}
+
+// Median step in log brightness from row `above` to `row`, over the pixels
+// bright enough to be screen; 0 without any.
+fn median_step(above: &[u8], row: &[u8], log: &[f32], histogram: &mut [u32]) -> f32 {
+    histogram.fill(0);
+    let mut count = 0;
+    for (&a, &b) in above.iter().zip(row) {
+        if a > BEZEL_LUMA && b > BEZEL_LUMA {
+            let step = (log[b as usize] - log[a as usize]).clamp(-MAX_STEP, MAX_STEP);
+            histogram[((step + MAX_STEP) / STEP_BIN + 0.5) as usize] += 1;
+            count += 1;
+        }
+    }
+    let mut seen = 0;
+    for (bin, &n) in histogram.iter().enumerate() {
+        seen += n;
+        if count > 0 && 2 * seen >= count {
+            return bin as f32 * STEP_BIN - MAX_STEP;
+        }
+    }
+    0.0
+}
+
+// Scales each row of `gray` into `flattened` to remove the bright and dark
+// bands a display's refresh leaves in rolling-shutter frames. Bands scale whole
+// rows while page content changes only some of a row's pixels, so the banding
+// profile is accumulated from the median brightness step between neighbouring
+// rows, and each row is corrected towards the profile's slow trend.
+pub(crate) fn remove_banding_into(gray: &[u8], width: usize, height: usize, flattened: &mut [u8]) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let log: Vec<f32> = (0..256u32).map(|v| (v.max(1) as f32).ln()).collect();
+    let mut histogram = vec![0u32; (2.0 * MAX_STEP / STEP_BIN) as usize + 1];
+    let mut profile = vec![0f32; height];
+    for (y, rows) in (1..).zip(gray.chunks_exact(width).collect::<Vec<_>>().windows(2)) {
+        profile[y] = profile[y - 1] + median_step(rows[0], rows[1], &log, &mut histogram);
+    }
+    let trend = box_mean(&profile, (height / TREND_FRACTION).max(MIN_TREND_RADIUS));
+    for (y, (src, dst)) in gray.chunks_exact(width).zip(flattened.chunks_exact_mut(width)).enumerate() {
+        let gain = (trend[y] - profile[y]).exp().clamp(1.0 / MAX_ROW_GAIN, MAX_ROW_GAIN);
+        for (d, &s) in dst.iter_mut().zip(src) {
+            *d = (s as f32 * gain + 0.5).min(255.0) as u8;
+        }
+    }
+}
+
+// Clears the edges along the monitor bezel: dark pixels connected to the frame
+// border are flood-filled into `mask`, which is grown by BEZEL_MARGIN into
+// `grown` and removed from `edges`. `stack` holds width * height entries and
+// `mask`, `grown` and `temp` width * height bytes each.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn suppress_bezel_into(
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    stack: &mut [usize],
+    mask: &mut [u8],
+    grown: &mut [u8],
+    temp: &mut [u8],
+    edges: &mut [u8],
+) {
+    mask.fill(0);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let mut len = 0;
+    let rows = (0..width).flat_map(|x| [x, (height - 1) * width + x]);
+    let columns = (0..height).flat_map(|y| [y * width, y * width + width - 1]);
+    for i in rows.chain(columns) {
+        if gray[i] <= BEZEL_LUMA && mask[i] == 0 {
+            mask[i] = 255;
+            stack[len] = i;
+            len += 1;
+        }
+    }
+    while len > 0 {
+        len -= 1;
+        let i = stack[len];
+        let (x, y) = (i % width, i / width);
+        let neighbours = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+        for n in neighbours.into_iter().flatten() {
+            if gray[n] <= BEZEL_LUMA && mask[n] == 0 {
+                mask[n] = 255;
+                stack[len] = n;
+                len += 1;
+            }
+        }
+    }
+
+    // Grow the bezel over the blurred transition into the screen, where Canny
+    // puts the bezel edge
+    crate::dilation::dilate_into(mask, width, height, 2 * BEZEL_MARGIN + 1, temp, grown);
+    for (edge, &bezel) in edges.iter_mut().zip(grown.iter()) {
+        if bezel != 0 {
+            *edge = 0;
+        }
+    }
+}
+
+/// Flattens the horizontal bright and dark bands that a display's refresh
+/// leaves in rolling-shutter frames, when scanning a document shown on another
+/// screen. Each row is scaled (by at most 25 %) so its mean follows the slow
+/// trend over the frame.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// Grayscale frame without refresh banding as Vec<u8>
+#[wasm_bindgen]
+pub fn remove_refresh_banding(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    let mut flattened = vec![0u8; width * height];
+    remove_banding_into(gray, width, height, &mut flattened);
+    Ok(flattened)
+}
+
+/// Removes the edges of a monitor's dark bezel from an edge map, so a document
+/// shown on another screen is detected instead of the screen itself. The bezel
+/// is the dark region (luma up to 48) connected to the frame border; edges
+/// within 6 px of it are cleared. A page that fills the whole screen shares its
+/// border with the bezel and is cleared as well.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame the edges were detected on
+/// * `edges` - Edge map of the frame
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// Edge map without the bezel edges as Vec<u8>
+#[wasm_bindgen]
+pub fn suppress_bezel_edges(gray: &[u8], edges: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    check_gray(gray, width, height)?;
+    check_gray(edges, width, height)?;
+    let size = width * height;
+    let mut stack = vec![0usize; size];
+    let (mut mask, mut grown, mut temp) = (vec![0u8; size], vec![0u8; size], vec![0u8; size]);
+    let mut result = edges.to_vec();
+    suppress_bezel_into(gray, width, height, &mut stack, &mut mask, &mut grown, &mut temp, &mut result);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_screen_capture_mode() {
+        let (width, height) = (160, 120);
+        // A monitor filling the frame: dark bezel, grey viewer background with a
+        // white page, and refresh bands every 15 rows
+        let frame: Vec<u8> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let bezel = !(12..148).contains(&x) || !(10..110).contains(&y);
+                let page = (50..110).contains(&x) && (35..85).contains(&y);
+                let band = if (y / 15) % 2 == 0 { 1.2 } else { 0.85 };
+                if bezel { 10 } else { (if page { 210.0 } else { 110.0 } * band) as u8 }
+            })
+            .collect();
+
+        // Row means of a strip of viewer background hardly vary any more
+        let strip = |gray: &[u8], y: usize| gray[y * width + 20..y * width + 40].iter().map(|&v| v as f32).sum::<f32>();
+        let ripple = |gray: &[u8]| {
+            let means: Vec<f32> = (12..108).map(|y| strip(gray, y) / 20.0).collect();
+            let mean = means.iter().sum::<f32>() / means.len() as f32;
+            (means.iter().map(|m| (m - mean) * (m - mean)).sum::<f32>() / means.len() as f32).sqrt()
+        };
+        let flattened = remove_refresh_banding(&frame, width, height).unwrap();
+        assert!(ripple(&flattened) < 0.2 * ripple(&frame), "{} {}", ripple(&flattened), ripple(&frame));
+
+        // The regular pipeline finds the screen border and the band edges;
+        // screen capture drops both and keeps the page
+        let edge_near = |edges: &[u8], x: usize| {
+            (40..80).filter(|&y| edges[y * width + x - 3..][..6].contains(&255)).count()
+        };
+        let band_edges = |edges: &[u8]| (12..108).filter(|&y| edges[y * width + 20..][..20].contains(&255)).count();
+        let mut context = ScanContext::new(width, height);
+        context.set_thresholds(40.0, 100.0);
+        context.set_dilation(0);
+        context.process_gray_frame(&frame).unwrap();
+        assert!(edge_near(&context.edges(), 12) > 30 && band_edges(&context.edges()) > 0);
+        context.set_screen_capture(true);
+        context.process_gray_frame(&frame).unwrap();
+        assert_eq!((edge_near(&context.edges(), 12), band_edges(&context.edges())), (0, 0));
+        assert!(edge_near(&context.edges(), 50) > 30);
+
+        let cleared = suppress_bezel_edges(&frame, &[255u8; 160 * 120], width, height).unwrap();
+        assert_eq!((cleared[60 * width + 16], cleared[60 * width + 22]), (0, 255));
+    }
+}
@@ -0,0 +1,765 @@
+use std::sync::{Arc, Mutex};
+
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+#[cfg(feature = "enhance")]
+use crate::enhance::WhiteBalanceMethod;
+#[cfg(feature = "inversion")]
+use crate::inversion::InversionMode;
+#[cfg(feature = "output")]
+use crate::output::OutputMode;
+#[cfg(all(feature = "pdf", feature = "jpeg"))]
+use crate::paper::PaperSize;
+#[cfg(all(feature = "pdf", feature = "jpeg"))]
+use crate::pdf::PdfDocument;
+#[cfg(feature = "tiff")]
+use crate::tiff::{TiffCompression, TiffDocument};
+#[cfg(feature = "stamp")]
+use crate::stamp::BatesNumbering;
+use crate::error::{check_index, check_rgba, output_len, to_quad, ScanError};
+#[cfg(feature = "tiff")]
+use crate::grayscale::{rgba_to_grayscale_into, GrayscaleWeights};
+use crate::warnings::{ScanWarning, Warnings};
+use crate::warp::{self, OutOfBoundsPolicy};
+
+// A captured page as handed in by the integrator (full-resolution RGBA), or an
+// exported one with the adjustments made while exporting it.
+struct Page {
+    rgba: Vec<u8>,
+    width: usize,
+    height: usize,
+    warnings: Warnings,
+    // Set on export when the page was inverted before enhancement
+    #[cfg(feature = "inversion")]
+    inverted: bool,
+}
+
+/// Per-page export settings for `ScanSession::export_all`.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PageSettings {
+    corners: Option<[f32; 8]>,
+    output_width: usize,
+    output_height: usize,
+    policy: OutOfBoundsPolicy,
+    fill_color: u32,
+    #[cfg(feature = "enhance")]
+    white_balance: Option<WhiteBalanceMethod>,
+    #[cfg(feature = "enhance")]
+    normalize_levels: bool,
+    #[cfg(feature = "output")]
+    output_mode: OutputMode,
+    #[cfg(feature = "inversion")]
+    inversion: InversionMode,
+    #[cfg(feature = "inversion")]
+    restore_polarity: bool,
+}
+
+impl Default for PageSettings {
+    fn default() -> Self {
+        PageSettings {
+            corners: None,
+            output_width: 0,
+            output_height: 0,
+            policy: OutOfBoundsPolicy::Replicate,
+            fill_color: 0xffff_ffff,
+            #[cfg(feature = "enhance")]
+            white_balance: None,
+            #[cfg(feature = "enhance")]
+            normalize_levels: false,
+            #[cfg(feature = "output")]
+            output_mode: OutputMode::Color,
+            #[cfg(feature = "inversion")]
+            inversion: InversionMode::Off,
+            #[cfg(feature = "inversion")]
+            restore_polarity: false,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl PageSettings {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PageSettings {
+        PageSettings::default()
+    }
+
+    /// Document corners (TL, TR, BR, BL as 8 values) used to rectify the page.
+    /// Pages without corners are exported as captured. Fails if the rectified
+    /// page would be too large to allocate.
+    pub fn set_corners(&mut self, corners: &[f32]) -> Result<(), ScanError> {
+        let settings = PageSettings { corners: Some(to_quad(corners)?), ..self.clone() };
+        settings.rectified_size()?;
+        *self = settings;
+        Ok(())
+    }
+
+    /// Fixed output size; 0 derives it from the corner distances. Fails if the
+    /// rectified page would be too large to allocate.
+    pub fn set_output_size(&mut self, width: usize, height: usize) -> Result<(), ScanError> {
+        let settings = PageSettings { output_width: width, output_height: height, ..self.clone() };
+        settings.rectified_size()?;
+        *self = settings;
+        Ok(())
+    }
+
+    pub fn set_out_of_bounds(&mut self, policy: OutOfBoundsPolicy, fill_color: u32) {
+        self.policy = policy;
+        self.fill_color = fill_color;
+    }
+}
+
+#[cfg(feature = "enhance")]
+#[wasm_bindgen]
+impl PageSettings {
+    /// Removes the colour cast of the rectified page with `method` (see
+    /// `white_balance`) before any other enhancement; `undefined` disables it.
+    pub fn set_white_balance(&mut self, method: Option<WhiteBalanceMethod>) {
+        self.white_balance = method;
+    }
+
+    /// Maps the page's estimated ink and paper levels to black and white after
+    /// rectifying (see `estimate_paper_levels`), so all pages of the document
+    /// share the same background brightness. Coloured paper keeps its colour and
+    /// dark paper is left as is (see `classify_paper`).
+    pub fn set_normalize_levels(&mut self, enabled: bool) {
+        self.normalize_levels = enabled;
+    }
+}
+
+#[cfg(feature = "output")]
+#[wasm_bindgen]
+impl PageSettings {
+    /// Colour mode of the exported page (see `apply_output_mode`). It is the last
+    /// export stage and runs after page harmonization.
+    pub fn set_output_mode(&mut self, mode: OutputMode) {
+        self.output_mode = mode;
+    }
+}
+
+#[cfg(feature = "inversion")]
+#[wasm_bindgen]
+impl PageSettings {
+    /// Inverts light-on-dark pages right after rectifying (see `InversionMode`),
+    /// so white balance, level normalization, harmonization and the output mode
+    /// all see dark ink on light paper. With `restore_polarity` the page is
+    /// inverted back after the output mode.
+    pub fn set_inversion(&mut self, mode: InversionMode, restore_polarity: bool) {
+        self.inversion = mode;
+        self.restore_polarity = restore_polarity;
+    }
+}
+
+/// Pages produced by `ScanSession::export_all`, in session order.
+#[wasm_bindgen]
+pub struct ExportedPages {
+    pages: Vec<Page>,
+}
+
+#[wasm_bindgen]
+impl ExportedPages {
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn width(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.width)
+    }
+
+    pub fn height(&self, index: usize) -> Result<usize, ScanError> {
+        Ok(self.get(index)?.height)
+    }
+
+    /// RGBA pixels of one exported page.
+    pub fn page(&self, index: usize) -> Result<Vec<u8>, ScanError> {
+        Ok(self.get(index)?.rgba.clone())
+    }
+
+    /// Adjustments made while exporting one page (see `ScanWarning`).
+    pub fn warnings(&self, index: usize) -> Result<Vec<ScanWarning>, ScanError> {
+        Ok(self.get(index)?.warnings.to_vec())
+    }
+
+    /// Whether one page was inverted on export (see `PageSettings::set_inversion`).
+    #[cfg(feature = "inversion")]
+    pub fn inverted(&self, index: usize) -> Result<bool, ScanError> {
+        Ok(self.get(index)?.inverted)
+    }
+}
+
+impl ExportedPages {
+    fn get(&self, index: usize) -> Result<&Page, ScanError> {
+        check_index(index, self.pages.len())?;
+        Ok(&self.pages[index])
+    }
+}
+
+#[cfg(all(feature = "pdf", feature = "jpeg"))]
+#[wasm_bindgen]
+impl ExportedPages {
+    /// The pages as one PDF, in export order. Black-and-white pages are coded
+    /// as CCITT Group 4 (with the `ccitt` feature), grey pages as grayscale
+    /// JPEG and the others as colour JPEG.
+    ///
+    /// # Arguments
+    /// * `paper` - Paper size of every page; `Unknown` sizes each page to its
+    ///   image at 300 dpi
+    /// * `quality` - JPEG quality, 1 to 100; 0 picks 85
+    ///
+    /// # Returns
+    /// PDF file bytes; an error while there are no pages
+    pub fn to_pdf(&self, paper: PaperSize, quality: u8) -> Result<Vec<u8>, ScanError> {
+        let mut document = PdfDocument::new();
+        for page in &self.pages {
+            let (width, height) = (page.width, page.height);
+            let jpeg = match page_kind(&page.rgba) {
+                #[cfg(feature = "ccitt")]
+                PageKind::Bilevel => {
+                    document.add_bilevel_page(&page.rgba, width, height, paper)?;
+                    continue;
+                }
+                PageKind::Color => crate::jpeg::encode_jpeg(&page.rgba, width, height, quality)?,
+                _ => crate::jpeg::encode_jpeg_gray(&red_channel(&page.rgba), width, height, quality)?,
+            };
+            document.add_jpeg_page(&jpeg, paper)?;
+        }
+        document.to_bytes()
+    }
+}
+
+#[cfg(feature = "tiff")]
+#[wasm_bindgen]
+impl ExportedPages {
+    /// The pages as one multi-page TIFF, in export order. Black-and-white
+    /// pages are stored as bilevel images with `compression`, all others as
+    /// 8-bit grayscale with LZW; TIFF pages carry no colour here, so colour
+    /// pages are converted to luma.
+    ///
+    /// # Arguments
+    /// * `compression` - Compression of the black-and-white pages
+    /// * `dpi` - Resolution recorded for every page
+    ///
+    /// # Returns
+    /// TIFF file bytes; an error while there are no pages
+    pub fn to_tiff(&self, compression: TiffCompression, dpi: u32) -> Result<Vec<u8>, ScanError> {
+        let mut document = TiffDocument::new();
+        document.set_dpi(dpi)?;
+        for page in &self.pages {
+            let (width, height) = (page.width, page.height);
+            match page_kind(&page.rgba) {
+                PageKind::Color => {
+                    let mut gray = vec![0u8; width * height];
+                    rgba_to_grayscale_into(&page.rgba, &mut gray, GrayscaleWeights::Bt601);
+                    document.add_gray_page(&gray, width, height)?
+                }
+                PageKind::Gray => document.add_gray_page(&red_channel(&page.rgba), width, height)?,
+                PageKind::Bilevel => document.add_bilevel_page(&red_channel(&page.rgba), width, height, compression)?,
+            };
+        }
+        document.to_bytes()
+    }
+}
+
+// How a page is stored in a document: pure black-and-white pages as bilevel
+// images, which are far smaller, and grey pages without colour channels.
+#[cfg(any(all(feature = "pdf", feature = "jpeg"), feature = "tiff"))]
+#[derive(Clone, Copy, PartialEq)]
+enum PageKind {
+    Bilevel,
+    Gray,
+    Color,
+}
+
+#[cfg(any(all(feature = "pdf", feature = "jpeg"), feature = "tiff"))]
+fn page_kind(rgba: &[u8]) -> PageKind {
+    let mut kind = PageKind::Bilevel;
+    for px in rgba.chunks_exact(4) {
+        if px[0] != px[1] || px[0] != px[2] {
+            return PageKind::Color;
+        }
+        if px[0] != 0 && px[0] != 255 {
+            kind = PageKind::Gray;
+        }
+    }
+    kind
+}
+
+// Grey levels of a page whose channels are all equal.
+#[cfg(any(all(feature = "pdf", feature = "jpeg"), feature = "tiff"))]
+fn red_channel(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|px| px[0]).collect()
+}
+
+// Session-wide export options, copied into background exports.
+#[derive(Clone, Default)]
+struct ExportOptions {
+    #[cfg(feature = "enhance")]
+    harmonize_pages: bool,
+    duplex: bool,
+    #[cfg(feature = "stamp")]
+    bates: Option<BatesNumbering>,
+}
+
+/// A multi-page capture session. Pages are kept inside WASM memory so the whole
+/// document can be exported in one call instead of one boundary crossing per step.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct ScanSession {
+    // Shared so a background export can keep reading pages while new ones are captured
+    pages: Vec<Arc<Page>>,
+    options: ExportOptions,
+}
+
+#[wasm_bindgen]
+impl ScanSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ScanSession {
+        ScanSession::default()
+    }
+
+    /// Adds a captured RGBA page and returns its index.
+    pub fn add_page(&mut self, rgba: &[u8], width: usize, height: usize) -> Result<usize, ScanError> {
+        check_rgba(rgba, width, height)?;
+        self.pages.push(Arc::new(Page {
+            rgba: rgba.to_vec(),
+            width,
+            height,
+            warnings: Warnings::default(),
+            #[cfg(feature = "inversion")]
+            inverted: false,
+        }));
+        Ok(self.pages.len() - 1)
+    }
+
+    pub fn remove_page(&mut self, index: usize) -> Result<(), ScanError> {
+        check_index(index, self.pages.len())?;
+        self.pages.remove(index);
+        Ok(())
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+
+    /// Gives the page list's spare capacity back to the allocator, e.g. after
+    /// `clear` at the end of a scanning session. Page pixels themselves are freed
+    /// as soon as a page is removed and no running export still holds it.
+    pub fn compact(&mut self) {
+        self.pages.shrink_to_fit();
+    }
+
+    /// Exports the pages of a double-sided batch in reading order: the fronts
+    /// were captured first (pages 1, 3, 5, ...), then the stack was turned over
+    /// and the backs captured last to first (6, 4, 2). See `duplex_order`.
+    /// `PageSettings` still follow the capture order.
+    pub fn set_duplex(&mut self, enabled: bool) {
+        self.options.duplex = enabled;
+    }
+
+    /// Applies per-page settings to every page in one call. `settings[i]` applies
+    /// to page `i`; pages beyond the end of `settings` use the defaults.
+    /// With the `threads` feature pages are processed in parallel.
+    pub fn export_all(&self, settings: Vec<PageSettings>) -> ExportedPages {
+        export_pages(&self.pages, &settings, self.options.clone())
+    }
+
+    /// Exports every page like `export_all` and assembles them into one PDF
+    /// in the same call, see `ExportedPages::to_pdf`.
+    #[cfg(all(feature = "pdf", feature = "jpeg"))]
+    pub fn export_pdf(&self, settings: Vec<PageSettings>, paper: PaperSize, quality: u8) -> Result<Vec<u8>, ScanError> {
+        self.export_all(settings).to_pdf(paper, quality)
+    }
+
+    /// Exports every page like `export_all` and assembles them into one
+    /// multi-page TIFF in the same call, see `ExportedPages::to_tiff`.
+    #[cfg(feature = "tiff")]
+    pub fn export_tiff(
+        &self,
+        settings: Vec<PageSettings>,
+        compression: TiffCompression,
+        dpi: u32,
+    ) -> Result<Vec<u8>, ScanError> {
+        self.export_all(settings).to_tiff(compression, dpi)
+    }
+
+    /// Starts `export_all` in the background and returns immediately. With the
+    /// `threads` feature the export runs on the rayon pool so preview frames can
+    /// keep being processed; without it the export completes before returning.
+    pub fn start_export(&self, settings: Vec<PageSettings>) -> ExportJob {
+        let job = ExportJob { result: Arc::new(Mutex::new(None)) };
+        let pages = self.pages.clone();
+        let slot = Arc::clone(&job.result);
+        let options = self.options.clone();
+        let run = move || {
+            let exported = export_pages(&pages, &settings, options);
+            *slot.lock().unwrap() = Some(exported);
+        };
+
+        #[cfg(feature = "threads")]
+        rayon::spawn(run);
+
+        #[cfg(not(feature = "threads"))]
+        run();
+
+        job
+    }
+}
+
+#[cfg(feature = "stamp")]
+#[wasm_bindgen]
+impl ScanSession {
+    /// Stamps sequential identifiers onto the exported pages (see
+    /// `BatesNumbering`), in the final page order and after the output mode, so
+    /// black-and-white pages keep a crisp label; `undefined` disables it.
+    pub fn set_bates_numbering(&mut self, numbering: Option<BatesNumbering>) {
+        self.options.bates = numbering;
+    }
+}
+
+#[cfg(feature = "enhance")]
+#[wasm_bindgen]
+impl ScanSession {
+    /// After exporting, scales the colour channels of every white-paper page so
+    /// their paper colours match: the same neutral white at the session's average
+    /// paper brightness. Evens out pages captured under changing light; coloured
+    /// and dark pages (see `classify_paper`) keep their background.
+    pub fn set_harmonize_pages(&mut self, enabled: bool) {
+        self.options.harmonize_pages = enabled;
+    }
+}
+
+/// Handle to an export started with `ScanSession::start_export`.
+#[wasm_bindgen]
+pub struct ExportJob {
+    result: Arc<Mutex<Option<ExportedPages>>>,
+}
+
+#[wasm_bindgen]
+impl ExportJob {
+    /// True once the exported pages are ready to be taken.
+    pub fn is_done(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+
+    /// Takes the exported pages, or returns None while the export is running.
+    pub fn take(&self) -> Option<ExportedPages> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+#[cfg_attr(not(feature = "enhance"), allow(unused_variables))]
+fn export_pages(pages: &[Arc<Page>], settings: &[PageSettings], options: ExportOptions) -> ExportedPages {
+    let defaults = PageSettings::default();
+    let settings_for = |i: usize| settings.get(i).unwrap_or(&defaults);
+
+    #[cfg(feature = "threads")]
+    let pages = pages
+        .par_iter()
+        .enumerate()
+        .map(|(i, page)| export_page(page, settings_for(i)))
+        .collect();
+
+    #[cfg(not(feature = "threads"))]
+    let pages = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| export_page(page, settings_for(i)))
+        .collect();
+
+    #[cfg(feature = "enhance")]
+    let pages = if options.harmonize_pages { harmonize_pages(pages) } else { pages };
+
+    #[cfg(feature = "output")]
+    let pages = apply_output_modes(pages, settings_for);
+
+    let pages = if options.duplex { duplex_pages(pages) } else { pages };
+
+    #[cfg(feature = "stamp")]
+    let pages = match &options.bates {
+        Some(bates) => stamp_pages(pages, bates),
+        None => pages,
+    };
+
+    ExportedPages { pages }
+}
+
+// Stamps the Bates number of each page, counting in export order.
+#[cfg(feature = "stamp")]
+fn stamp_pages(mut pages: Vec<Page>, bates: &BatesNumbering) -> Vec<Page> {
+    for (i, page) in pages.iter_mut().enumerate() {
+        bates.stamp_into(&mut page.rgba, page.width, page.height, i);
+    }
+    pages
+}
+
+// Puts the pages of a double-sided batch into reading order.
+fn duplex_pages(pages: Vec<Page>) -> Vec<Page> {
+    let order = duplex_order(pages.len());
+    let mut captured: Vec<Option<Page>> = pages.into_iter().map(Some).collect();
+    order.into_iter().map(|i| captured[i].take().unwrap()).collect()
+}
+
+/// Reading order of a double-sided batch captured as all fronts followed by
+/// all backs in reverse, as after turning the stack over: entry `i` is the
+/// capture index of page `i`. Six captures give `[0, 5, 1, 4, 2, 3]`; with an
+/// odd count the last back is missing.
+///
+/// # Arguments
+/// * `page_count` - Number of captured pages
+///
+/// # Returns
+/// Capture indices in reading order as Vec<usize>
+#[wasm_bindgen]
+pub fn duplex_order(page_count: usize) -> Vec<usize> {
+    // The fronts are the first half of the captures (rounded up), the backs the
+    // rest in reverse
+    (0..page_count).map(|i| if i % 2 == 0 { i / 2 } else { page_count - 1 - i / 2 }).collect()
+}
+
+// Converts every page to its output mode, after harmonization so black-and-white
+// pages don't have their paper rescaled. Inverted pages get their polarity back
+// here if requested.
+#[cfg(feature = "output")]
+fn apply_output_modes<'a>(mut pages: Vec<Page>, settings_for: impl Fn(usize) -> &'a PageSettings + Sync) -> Vec<Page> {
+    let apply = |(i, page): (usize, &mut Page)| {
+        crate::scheduler::yield_point();
+        crate::output::apply_output_mode_into(&mut page.rgba, page.width, page.height, settings_for(i).output_mode);
+        #[cfg(feature = "inversion")]
+        if page.inverted && settings_for(i).restore_polarity {
+            crate::inversion::invert_into(&mut page.rgba);
+        }
+    };
+
+    #[cfg(feature = "threads")]
+    pages.par_iter_mut().enumerate().for_each(apply);
+
+    #[cfg(not(feature = "threads"))]
+    pages.iter_mut().enumerate().for_each(apply);
+
+    pages
+}
+
+// Gives every white-paper page the same neutral paper colour, at the mean paper
+// brightness of those pages. Coloured and dark pages keep their background.
+#[cfg(feature = "enhance")]
+fn harmonize_pages(mut pages: Vec<Page>) -> Vec<Page> {
+    use crate::enhance::{classify_paper_color, paper_color, scale_channels_into, PaperClass};
+
+    let colors: Vec<Option<[f32; 3]>> = pages
+        .iter()
+        .map(|page| Some(paper_color(&page.rgba)).filter(|&c| classify_paper_color(c) == PaperClass::White))
+        .collect();
+    let white: Vec<[f32; 3]> = colors.iter().flatten().copied().collect();
+    if white.is_empty() {
+        return pages;
+    }
+    let target = white.iter().map(|c| (c[0] + c[1] + c[2]) / 3.0).sum::<f32>() / white.len() as f32;
+    for (page, color) in pages.iter_mut().zip(&colors) {
+        if let Some(color) = color {
+            let gains = color.map(|c| if c > 0.0 { target / c } else { 1.0 });
+            scale_channels_into(&mut page.rgba, gains);
+        }
+    }
+    pages
+}
+
+fn export_page(page: &Page, settings: &PageSettings) -> Page {
+    crate::scheduler::yield_point();
+    let exported = rectify_page(page, settings);
+
+    #[cfg(feature = "inversion")]
+    let exported = invert_page(exported, settings.inversion);
+
+    #[cfg(feature = "enhance")]
+    let exported = match settings.white_balance {
+        Some(method) => white_balance_page(exported, method),
+        None => exported,
+    };
+
+    #[cfg(feature = "enhance")]
+    let exported = if settings.normalize_levels { normalize_page(exported) } else { exported };
+
+    exported
+}
+
+#[cfg(feature = "inversion")]
+fn invert_page(mut page: Page, mode: InversionMode) -> Page {
+    page.inverted = crate::inversion::should_invert(&page.rgba, mode);
+    if page.inverted {
+        crate::inversion::invert_into(&mut page.rgba);
+    }
+    page
+}
+
+#[cfg(feature = "enhance")]
+fn white_balance_page(mut page: Page, method: WhiteBalanceMethod) -> Page {
+    crate::enhance::white_balance_into(&mut page.rgba, method);
+    page
+}
+
+#[cfg(feature = "enhance")]
+fn normalize_page(mut page: Page) -> Page {
+    crate::enhance::normalize_paper_into(&mut page.rgba);
+    page
+}
+
+impl PageSettings {
+    // Size of the rectified page, or `None` without corners. The setters check
+    // it, so an export never allocates a page past `output_len`.
+    fn rectified_size(&self) -> Result<Option<(usize, usize)>, ScanError> {
+        let Some(corners) = self.corners else {
+            return Ok(None);
+        };
+        let (auto_width, auto_height) = warp::quad_output_size(&corners);
+        let width = if self.output_width > 0 { self.output_width } else { auto_width };
+        let height = if self.output_height > 0 { self.output_height } else { auto_height };
+        output_len(width, height, 4)?;
+        Ok(Some((width, height)))
+    }
+}
+
+fn rectify_page(page: &Page, settings: &PageSettings) -> Page {
+    let as_captured = |warnings| Page {
+        rgba: page.rgba.clone(),
+        width: page.width,
+        height: page.height,
+        warnings,
+        #[cfg(feature = "inversion")]
+        inverted: false,
+    };
+    let (Some(corners), Ok(Some((width, height)))) = (settings.corners, settings.rectified_size()) else {
+        return as_captured(Warnings::default());
+    };
+
+    match warp::rect_to_quad_matrix(&corners, width, height) {
+        Some(matrix) => Page {
+            rgba: warp::warp_with_matrix(
+                &page.rgba,
+                page.width,
+                page.height,
+                &matrix,
+                width,
+                height,
+                settings.policy,
+                settings.fill_color,
+            ),
+            width,
+            height,
+            warnings: Warnings::default(),
+            #[cfg(feature = "inversion")]
+            inverted: false,
+        },
+        // Degenerate quad: fall back to the page as captured
+        None => {
+            let mut warnings = Warnings::default();
+            warnings.set(ScanWarning::DegenerateQuad, true);
+            as_captured(warnings)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "enhance")]
+    #[test]
+    fn test_harmonize_pages() {
+        // A bluish and a dim yellowish page, each with a dark text pixel
+        let page = |paper: [u8; 3]| -> Vec<u8> {
+            let mut rgba: Vec<u8> = (0..16).flat_map(|_| [paper[0], paper[1], paper[2], 255]).collect();
+            rgba[..4].copy_from_slice(&[20, 20, 20, 255]);
+            rgba
+        };
+        let mut session = ScanSession::new();
+        session.add_page(&page([200, 210, 240]), 4, 4).unwrap();
+        session.add_page(&page([190, 180, 150]), 4, 4).unwrap();
+        session.set_harmonize_pages(true);
+
+        let exported = session.export_all(Vec::new());
+        // Mean paper brightness is 195, now neutral on both pages
+        for i in 0..2 {
+            assert_eq!(&exported.page(i).unwrap()[4..8], &[195, 195, 195, 255]);
+        }
+        assert_ne!(exported.page(0).unwrap()[..3], exported.page(1).unwrap()[..3]);
+    }
+
+    #[test]
+    fn test_duplex_export_order() {
+        assert_eq!(duplex_order(6), [0, 5, 1, 4, 2, 3]);
+        assert_eq!(duplex_order(5), [0, 4, 1, 3, 2]);
+        assert!(duplex_order(0).is_empty());
+
+        // Fronts 1, 3, 5 then backs 6, 4, 2, each page 1x1 with its number as red;
+        // settings follow the capture order
+        let mut session = ScanSession::new();
+        for number in [1, 3, 5, 6, 4, 2] {
+            session.add_page(&[number, 0, 0, 255], 1, 1).unwrap();
+        }
+        session.set_duplex(true);
+        let mut settings = vec![PageSettings::new(); 4];
+        settings[3].set_corners(&[0.0; 8]).unwrap();
+        let exported = session.export_all(settings);
+        let numbers: Vec<u8> = (0..6).map(|i| exported.page(i).unwrap()[0]).collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(exported.warnings(5).unwrap(), [ScanWarning::DegenerateQuad]);
+    }
+
+    #[test]
+    fn test_oversized_output_is_rejected() {
+        // Corners far apart, or a fixed size, that would need a page past the cap
+        let mut settings = PageSettings::new();
+        let huge = [0.0, 0.0, 1e6, 0.0, 1e6, 1e6, 0.0, 1e6];
+        assert!(settings.set_corners(&huge).is_err());
+        assert_eq!(settings.rectified_size(), Ok(None));
+
+        settings.set_corners(&[0.0, 0.0, 3.0, 0.0, 3.0, 2.0, 0.0, 2.0]).unwrap();
+        assert!(settings.set_output_size(usize::MAX, 2).is_err());
+        assert!(settings.set_output_size(0, 1 << 28).is_err());
+        assert_eq!(settings.rectified_size(), Ok(Some((3, 2))));
+        settings.set_output_size(8, 0).unwrap();
+        assert_eq!(settings.rectified_size(), Ok(Some((8, 2))));
+    }
+
+    #[cfg(all(feature = "pdf", feature = "jpeg", feature = "tiff"))]
+    #[test]
+    fn test_document_export() {
+        // A colour, a grey and a black-and-white page
+        let (width, height) = (16, 16);
+        let mut session = ScanSession::new();
+        let colour: Vec<u8> = (0..width * height).flat_map(|i| [200, (i % 256) as u8, 40, 255]).collect();
+        let grey: Vec<u8> = (0..width * height).flat_map(|i| [(i % 256) as u8; 3].into_iter().chain([255])).collect();
+        let text: Vec<u8> =
+            (0..width * height).flat_map(|i| if i % 5 == 0 { [0, 0, 0, 255] } else { [255; 4] }).collect();
+        for page in [&colour, &grey, &text] {
+            session.add_page(page, width, height).unwrap();
+        }
+        let kinds: Vec<PageKind> = [&colour, &grey, &text].iter().map(|page| page_kind(page)).collect();
+        assert!(kinds == [PageKind::Color, PageKind::Gray, PageKind::Bilevel]);
+
+        let pdf = session.export_pdf(Vec::new(), PaperSize::A4, 0).unwrap();
+        let count = |needle: &[u8]| pdf.windows(needle.len()).filter(|w| w == &needle).count();
+        assert!(pdf.starts_with(b"%PDF-1.4") && pdf.ends_with(b"%%EOF\n"));
+        assert_eq!((count(b"/Type /Page "), count(b"/DCTDecode"), count(b"/CCITTFaxDecode")), (3, 2, 1));
+        assert_eq!(count(b"/DeviceRGB"), 1);
+
+        let tiff = session.export_tiff(Vec::new(), TiffCompression::Group4, 200).unwrap();
+        let mut expected = TiffDocument::new();
+        expected.set_dpi(200).unwrap();
+        let mut gray = vec![0u8; width * height];
+        rgba_to_grayscale_into(&colour, &mut gray, GrayscaleWeights::Bt601);
+        expected.add_gray_page(&gray, width, height).unwrap();
+        expected.add_gray_page(&red_channel(&grey), width, height).unwrap();
+        expected.add_bilevel_page(&red_channel(&text), width, height, TiffCompression::Group4).unwrap();
+        assert_eq!(tiff, expected.to_bytes().unwrap());
+
+        assert!(ScanSession::new().export_pdf(Vec::new(), PaperSize::A4, 0).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+
+// Longest side of the thumbnail the gradients are compared on.
+const THUMBNAIL_SIZE: usize = 64;
+
+// Block-averages `gray` down to at most THUMBNAIL_SIZE px per side and stores the
+// central-difference gradients (gx, gy interleaved) of the thumbnail's interior
+// in `gradients`. Returns the thumbnail size.
+fn thumbnail_gradients(gray: &[u8], width: usize, height: usize, gradients: &mut Vec<f32>) -> (usize, usize) {
+    let block = width.max(height).div_ceil(THUMBNAIL_SIZE).max(1);
+    let (tw, th) = (width / block, height / block);
+    let mut thumbnail = vec![0u32; tw * th];
+    for y in 0..th * block {
+        let row = &gray[y * width..y * width + tw * block];
+        let sums = &mut thumbnail[(y / block) * tw..][..tw];
+        for (sum, cells) in sums.iter_mut().zip(row.chunks_exact(block)) {
+            *sum += cells.iter().map(|&v| v as u32).sum::<u32>();
+        }
+    }
+
+    gradients.clear();
+    let at = |x: usize, y: usize| thumbnail[y * tw + x] as f32;
+    for y in 1..th.saturating_sub(1) {
+        for x in 1..tw.saturating_sub(1) {
+            gradients.push(at(x + 1, y) - at(x - 1, y));
+            gradients.push(at(x, y + 1) - at(x, y - 1));
+        }
+    }
+    (tw, th)
+}
+
+// 1 - normalized correlation of two gradient fields, clamped to 0..=1. Two flat
+// frames count as still, a flat and a structured one as fully different.
+fn gradient_shake(previous: &[f32], current: &[f32]) -> f32 {
+    let (mut dot, mut prev_energy, mut curr_energy) = (0f64, 0f64, 0f64);
+    for (&p, &c) in previous.iter().zip(current) {
+        dot += (p * c) as f64;
+        prev_energy += (p * p) as f64;
+        curr_energy += (c * c) as f64;
+    }
+    if prev_energy == 0.0 && curr_energy == 0.0 {
+        return 0.0;
+    }
+    let norm = (prev_energy * curr_energy).sqrt();
+    if norm == 0.0 {
+        return 1.0;
+    }
+    (1.0 - dot / norm).clamp(0.0, 1.0) as f32
+}
+
+// Shake between consecutive frames of a `ScanContext`. Keeps the previous
+// thumbnail's gradients so each frame is only reduced once.
+#[derive(Default)]
+pub(crate) struct ShakeEstimator {
+    previous: Vec<f32>,
+    current: Vec<f32>,
+    // Thumbnail size of `previous`, None before the first frame
+    previous_size: Option<(usize, usize)>,
+    score: Option<f32>,
+}
+
+impl ShakeEstimator {
+    pub(crate) fn push_frame(&mut self, gray: &[u8], width: usize, height: usize) {
+        let size = thumbnail_gradients(gray, width, height, &mut self.current);
+        // A resized stream starts over instead of comparing different layouts
+        self.score = (self.previous_size == Some(size)).then(|| gradient_shake(&self.previous, &self.current));
+        core::mem::swap(&mut self.previous, &mut self.current);
+        self.previous_size = Some(size);
+    }
+
+    pub(crate) fn score(&self) -> Option<f32> {
+        self.score
+    }
+
+    pub(crate) fn release(&mut self) {
+        *self = ShakeEstimator::default();
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        (self.previous.capacity() + self.current.capacity()) * size_of::<f32>()
+    }
+}
+
+/// Camera shake between two consecutive grayscale frames, from the normalized
+/// correlation of their gradients on 64 px thumbnails: 0 when the image stands
+/// still, towards 1 while the phone moves (shifted or motion-blurred edges).
+/// Two flat frames without any structure count as still.
+///
+/// # Arguments
+/// * `previous` - Earlier grayscale frame
+/// * `current` - Later grayscale frame of the same size
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// Shake score in 0..=1
+#[wasm_bindgen]
+pub fn shake_score(previous: &[u8], current: &[u8], width: usize, height: usize) -> Result<f32, ScanError> {
+    check_gray(previous, width, height)?;
+    check_gray(current, width, height)?;
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    thumbnail_gradients(previous, width, height, &mut a);
+    thumbnail_gradients(current, width, height, &mut b);
+    Ok(gradient_shake(&a, &b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_shake_score() {
+        let (width, height) = (256, 192);
+        // A page with text-like stripes, optionally shifted by a few pixels
+        let frame = |shift: usize| -> Vec<u8> {
+            (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width + shift, i / width + shift);
+                    let on_page = (40..216).contains(&x) && (30..162).contains(&y);
+                    if on_page && (y / 8) % 2 == 0 && (x / 12) % 3 != 0 { 40 } else if on_page { 230 } else { 90 }
+                })
+                .collect()
+        };
+        let still = frame(0);
+        assert_eq!(shake_score(&still, &still, width, height).unwrap(), 0.0);
+        let moved = shake_score(&still, &frame(12), width, height).unwrap();
+        assert!(moved > 0.3, "{moved}");
+        assert_eq!(shake_score(&still, &vec![128; width * height], width, height).unwrap(), 1.0);
+
+        let mut context = ScanContext::new(width, height);
+        context.process_gray_frame(&still).unwrap();
+        assert_eq!(context.shake_score(), None);
+        context.process_gray_frame(&still).unwrap();
+        assert_eq!(context.shake_score(), Some(0.0));
+        context.process_gray_frame(&frame(12)).unwrap();
+        assert_eq!(context.shake_score(), Some(moved));
+    }
+}
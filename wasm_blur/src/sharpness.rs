@@ -0,0 +1,124 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_len, image_len, ScanError};
+use crate::gradient_calculation::gradients_into;
+
+/// Focus measure used by `sharpness_score`. Both drop sharply with defocus and
+/// motion blur; compare scores of frames of the same scene and size rather than
+/// against a fixed threshold, since they also grow with the amount of texture.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SharpnessMethod {
+    /// Variance of the 4-neighbour Laplacian (the default). Around 100 and
+    /// above is usually sharp for a text page.
+    #[default]
+    LaplacianVariance = 0,
+    /// Mean squared Sobel gradient magnitude, less sensitive to noise
+    Tenengrad = 1,
+}
+
+// Variance of the 4-neighbour Laplacian over the interior pixels.
+pub(crate) fn laplacian_variance(gray: &[u8], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let (mut sum, mut sum_sq) = (0f64, 0f64);
+    for y in 1..height - 1 {
+        let row = &gray[y * width..(y + 1) * width];
+        let (above, below) = (&gray[(y - 1) * width..y * width], &gray[(y + 1) * width..(y + 2) * width]);
+        for x in 1..width - 1 {
+            let l = above[x] as i32 + below[x] as i32 + row[x - 1] as i32 + row[x + 1] as i32 - 4 * row[x] as i32;
+            sum += l as f64;
+            sum_sq += (l * l) as f64;
+        }
+    }
+    let n = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean) as f32
+}
+
+// Mean of gx² + gy² over the interior pixels of an interleaved gradient buffer;
+// the border is 0 in `calculate_gradients` output and left out.
+pub(crate) fn tenengrad(gradients: &[i16], width: usize, height: usize) -> f32 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let sum: f64 = gradients
+        .chunks_exact(2)
+        .map(|g| {
+            let (gx, gy) = (g[0] as f64, g[1] as f64);
+            gx * gx + gy * gy
+        })
+        .sum();
+    (sum / ((width - 2) * (height - 2)) as f64) as f32
+}
+
+/// Estimates how sharp a frame is, to reject blurry captures before running the
+/// full pipeline or to ask the user to hold still. Higher is sharper.
+///
+/// # Arguments
+/// * `gray` - Grayscale frame, unblurred
+/// * `width` - Frame width
+/// * `height` - Frame height
+/// * `method` - Laplacian variance or Tenengrad
+///
+/// # Returns
+/// Sharpness score, 0 for a flat frame
+#[wasm_bindgen]
+pub fn sharpness_score(gray: &[u8], width: usize, height: usize, method: SharpnessMethod) -> Result<f32, ScanError> {
+    check_gray(gray, width, height)?;
+    Ok(match method {
+        SharpnessMethod::LaplacianVariance => laplacian_variance(gray, width, height),
+        SharpnessMethod::Tenengrad => {
+            let mut gradients = vec![0i16; 2 * width * height];
+            gradients_into(gray, width, height, &mut gradients);
+            tenengrad(&gradients, width, height)
+        }
+    })
+}
+
+/// Tenengrad sharpness of a gradient buffer already computed with
+/// `calculate_gradients`, without a second Sobel pass. Gradients of the
+/// blurred frame score lower than `sharpness_score` on the raw frame, but still
+/// rank frames of the same scene.
+///
+/// # Arguments
+/// * `gradients` - Interleaved (gx, gy) gradients
+/// * `width` - Frame width
+/// * `height` - Frame height
+///
+/// # Returns
+/// Mean squared gradient magnitude over the interior pixels
+#[wasm_bindgen]
+pub fn gradient_sharpness(gradients: &[i16], width: usize, height: usize) -> Result<f32, ScanError> {
+    check_len(gradients.len(), image_len(width, height, 2)?, "width * height * 2")?;
+    Ok(tenengrad(gradients, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient_calculation::calculate_gradients;
+
+    #[test]
+    fn test_sharpness_ranks_blur() {
+        // Text-like strokes, then the same frame blurred more and more
+        let (width, height) = (64, 48);
+        let page: Vec<u8> = (0..width * height).map(|i| if (i / width) % 6 < 2 && i % 5 < 3 { 30 } else { 210 }).collect();
+        let soft = crate::blur(&page, width, height, 5, 0.0).unwrap();
+        let softer = crate::blur(&page, width, height, 11, 0.0).unwrap();
+        for method in [SharpnessMethod::LaplacianVariance, SharpnessMethod::Tenengrad] {
+            let score = |gray: &[u8]| sharpness_score(gray, width, height, method).unwrap();
+            assert!(score(&page) > 2.0 * score(&soft) && score(&soft) > score(&softer), "{method:?}");
+            assert_eq!(score(&[128; 64 * 48]), 0.0);
+        }
+
+        let gradients = calculate_gradients(&page, width, height).unwrap();
+        let tenengrad = sharpness_score(&page, width, height, SharpnessMethod::Tenengrad).unwrap();
+        assert_eq!(gradient_sharpness(&gradients, width, height).unwrap(), tenengrad);
+        assert!(gradient_sharpness(&gradients[2..], width, height).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, ScanError};
+use crate::forms::ink_components;
+
+use core::f32::consts::PI;
+
+// Binary pixels at or below this are ink.
+const INK: u8 = 127;
+// Ink runs at least this share of the region width are the printed signature
+// line, not part of the signature.
+const RULE_FRACTION: f32 = 0.5;
+// Ink share of a signed region: less is dust, more a stamp, a shadow or a
+// dark region.
+const MIN_INK: f32 = 0.005;
+const MAX_INK: f32 = 0.35;
+// Least normalized entropy of the stroke orientations. Handwriting turns
+// through every direction; rules, boxes and single strokes only run along a
+// few.
+const MIN_CURVATURE: f32 = 0.75;
+// Least extent of the largest stroke as a share of the region side, so
+// specks and short marks don't count.
+const MIN_EXTENT: f32 = 0.2;
+// Orientation bins over 0..180°.
+const ORIENTATION_BINS: usize = 8;
+
+/// Stroke statistics of a signature field, from `detect_signature`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignatureCheck {
+    ink_fraction: f32,
+    curvature: f32,
+    extent: f32,
+}
+
+#[wasm_bindgen]
+impl SignatureCheck {
+    /// Whether the region holds handwriting-like strokes: 0.5-35 % ink, a
+    /// curvature of at least 0.75 and a stroke reaching over a fifth of the
+    /// region.
+    pub fn signed(&self) -> bool {
+        (MIN_INK..=MAX_INK).contains(&self.ink_fraction) && self.curvature >= MIN_CURVATURE && self.extent >= MIN_EXTENT
+    }
+
+    /// Share of ink in the region, without the signature line.
+    #[wasm_bindgen(getter)]
+    pub fn ink_fraction(&self) -> f32 {
+        self.ink_fraction
+    }
+
+    /// Normalized entropy of the stroke orientations, 0..1: 0 for straight
+    /// strokes in one direction, 1 for strokes turning evenly through all.
+    #[wasm_bindgen(getter)]
+    pub fn curvature(&self) -> f32 {
+        self.curvature
+    }
+
+    /// Width or height of the largest connected stroke as a share of the
+    /// region's, whichever is larger.
+    #[wasm_bindgen(getter)]
+    pub fn extent(&self) -> f32 {
+        self.extent
+    }
+}
+
+// Orientation entropy of the stroke outlines in a binary patch, from the Sobel
+// gradient of the ink mask.
+fn orientation_entropy(ink: &[bool], width: usize, height: usize) -> f32 {
+    let mut bins = [0u32; ORIENTATION_BINS];
+    let at = |x: usize, y: usize| ink[y * width + x] as i32;
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width - 1 {
+            let gx = at(x + 1, y - 1) + 2 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            if gx == 0 && gy == 0 {
+                continue;
+            }
+            // Orientation folded into 0..π
+            let angle = (gy as f32).atan2(gx as f32).rem_euclid(PI);
+            bins[((angle / PI * ORIENTATION_BINS as f32) as usize).min(ORIENTATION_BINS - 1)] += 1;
+        }
+    }
+    let total: u32 = bins.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let entropy: f32 = bins
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f32 / total as f32;
+            -p * p.ln()
+        })
+        .sum();
+    entropy / (ORIENTATION_BINS as f32).ln()
+}
+
+/// Checks whether a signature field on a binarized, rectified page has been
+/// signed, from the density, curvature and extent of the strokes in it. A
+/// printed signature line running across the field is ignored. Lets a capture
+/// workflow reject unsigned documents right away.
+///
+/// # Arguments
+/// * `binary` - Binary page, 0 for ink and 255 for paper
+/// * `width` - Page width
+/// * `height` - Page height
+/// * `x`, `y`, `region_width`, `region_height` - Signature field on the page
+///
+/// # Returns
+/// `SignatureCheck` with the verdict and stroke statistics
+#[wasm_bindgen]
+pub fn detect_signature(
+    binary: &[u8],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    region_width: usize,
+    region_height: usize,
+) -> Result<SignatureCheck, ScanError> {
+    check_gray(binary, width, height)?;
+    if region_width < 3 || region_height < 3 || x + region_width > width || y + region_height > height {
+        return Err(ScanError::InvalidParameter("Signature region must lie inside the page, at least 3x3"));
+    }
+
+    // Crop the region and clear the signature line
+    let mut ink: Vec<bool> = (0..region_width * region_height)
+        .map(|i| binary[(y + i / region_width) * width + x + i % region_width] <= INK)
+        .collect();
+    let min_rule = (RULE_FRACTION * region_width as f32) as usize;
+    for row in ink.chunks_exact_mut(region_width) {
+        let mut start = 0;
+        while start < region_width {
+            let run = row[start..].iter().take_while(|&&v| v).count();
+            if run >= min_rule.max(1) {
+                row[start..start + run].fill(false);
+            }
+            start += run.max(1);
+        }
+    }
+
+    let ink_pixels = ink.iter().filter(|&&v| v).count();
+    let mask: Vec<u8> = ink.iter().map(|&v| if v { 0 } else { 255 }).collect();
+    let extent = ink_components(&mask, region_width, region_height)
+        .into_iter()
+        .map(|[x0, y0, x1, y1]| {
+            ((x1 - x0 + 1) as f32 / region_width as f32).max((y1 - y0 + 1) as f32 / region_height as f32)
+        })
+        .fold(0.0, f32::max);
+    Ok(SignatureCheck {
+        ink_fraction: ink_pixels as f32 / ink.len() as f32,
+        curvature: orientation_entropy(&ink, region_width, region_height),
+        extent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_presence() {
+        // Two signature fields above printed lines; the left one is signed
+        // with a looping scrawl, the right one only has a stray straight mark
+        let (width, height) = (200, 80);
+        let mut page = vec![255u8; width * height];
+        for x in (10..90).chain(110..190) {
+            page[60 * width + x] = 0;
+            page[61 * width + x] = 0;
+        }
+        for step in 0..600 {
+            let t = step as f32 / 10.0;
+            let (px, py) = (15.0 + t + 6.0 * t.cos(), 38.0 + 14.0 * (t / 1.3).sin());
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                page[(py as usize + dy) * width + px as usize + dx] = 0;
+            }
+        }
+        for x in 130..150 {
+            page[40 * width + x] = 0;
+        }
+
+        let signed = detect_signature(&page, width, height, 5, 10, 90, 60).unwrap();
+        assert!(signed.signed(), "{signed:?}");
+        let unsigned = detect_signature(&page, width, height, 105, 10, 90, 60).unwrap();
+        assert!(!unsigned.signed() && unsigned.curvature() < 0.5, "{unsigned:?}");
+        let blank = detect_signature(&page, width, height, 105, 62, 90, 15).unwrap();
+        assert_eq!((blank.ink_fraction(), blank.signed()), (0.0, false));
+        assert!(detect_signature(&page, width, height, 150, 10, 90, 60).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::ScanError;
+
+// Strips larger than this are rejected rather than allocated, so a corrupt
+// byte count can't exhaust memory.
+const MAX_STRIP_BYTES: u64 = 256 << 20;
+
+/// Random-access bytes of a file too large to load at once, e.g. a
+/// multi-hundred-MB TIFF on disk. Readers like `TiffStrips` fetch only the
+/// parts they decode. Implemented for byte slices, for `Read + Seek` readers
+/// (`ReaderSource`, with `std`) and for closures (`FnSource`).
+pub trait ByteSource {
+    /// Total length in bytes.
+    fn len(&self) -> u64;
+
+    /// Fills `buf` with the bytes starting at `offset`, failing with
+    /// `ScanError::ReadFailed` if the range is not available.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), ScanError>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ByteSource for &[u8] {
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), ScanError> {
+        let failed = ScanError::ReadFailed { offset, len: buf.len() };
+        let start = usize::try_from(offset).map_err(|_| failed)?;
+        let bytes = self.get(start..start.checked_add(buf.len()).ok_or(failed)?).ok_or(failed)?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// `ByteSource` over a seekable reader such as a `std::fs::File`.
+#[cfg(feature = "std")]
+pub struct ReaderSource<R> {
+    reader: R,
+    len: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> ReaderSource<R> {
+    pub fn new(mut reader: R) -> std::io::Result<ReaderSource<R>> {
+        let len = reader.seek(std::io::SeekFrom::End(0))?;
+        Ok(ReaderSource { reader, len })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> ByteSource for ReaderSource<R> {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), ScanError> {
+        let failed = ScanError::ReadFailed { offset, len: buf.len() };
+        self.reader.seek(std::io::SeekFrom::Start(offset)).map_err(|_| failed)?;
+        self.reader.read_exact(buf).map_err(|_| failed)
+    }
+}
+
+/// `ByteSource` calling `read(offset, buf)` for every range, e.g. to read from
+/// a memory-mapped file or a platform file API. The callback returns false if
+/// it can't fill `buf`.
+pub struct FnSource<F> {
+    len: u64,
+    read: F,
+}
+
+impl<F: FnMut(u64, &mut [u8]) -> bool> FnSource<F> {
+    pub fn new(len: u64, read: F) -> FnSource<F> {
+        FnSource { len, read }
+    }
+}
+
+impl<F: FnMut(u64, &mut [u8]) -> bool> ByteSource for FnSource<F> {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<(), ScanError> {
+        if offset.saturating_add(buf.len() as u64) > self.len || !(self.read)(offset, buf) {
+            return Err(ScanError::ReadFailed { offset, len: buf.len() });
+        }
+        Ok(())
+    }
+}
+
+// Field types of the TIFF tags read: BYTE, SHORT and LONG.
+fn field_size(kind: u16) -> Option<u64> {
+    match kind {
+        1 => Some(1),
+        3 => Some(2),
+        4 => Some(4),
+        _ => None,
+    }
+}
+
+// Expands PackBits runs (TIFF compression 32773) into `out`.
+fn unpack_bits(packed: &[u8], out: &mut Vec<u8>) -> Result<(), ScanError> {
+    let mut i = 0;
+    while i < packed.len() {
+        let n = packed[i] as i8;
+        i += 1;
+        match n {
+            0.. => {
+                let literal =
+                    packed.get(i..i + n as usize + 1).ok_or(ScanError::InvalidData("Truncated PackBits run"))?;
+                out.extend_from_slice(literal);
+                i += literal.len();
+            }
+            -127..=-1 => {
+                let &byte = packed.get(i).ok_or(ScanError::InvalidData("Truncated PackBits run"))?;
+                out.extend(core::iter::repeat_n(byte, 1 - n as isize as usize));
+                i += 1;
+            }
+            // -128 is a no-op
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Strip-by-strip reader of a baseline TIFF from a `ByteSource`: the header is
+/// parsed on `open`, and each strip is only read and decoded when asked for,
+/// so memory stays at one strip however large the file is. Handles 1-bit
+/// bilevel, 8-bit grayscale, RGB and RGBA images, uncompressed or PackBits,
+/// in strips (not tiles); strips come out as RGBA rows.
+pub struct TiffStrips<S> {
+    source: S,
+    little_endian: bool,
+    width: usize,
+    height: usize,
+    bits: usize,
+    samples: usize,
+    // Bilevel and grayscale images with 0 as white (photometric 0)
+    min_is_white: bool,
+    packbits: bool,
+    rows_per_strip: usize,
+    offsets: Vec<u64>,
+    byte_counts: Vec<u64>,
+    // Encoded strip, reused across strips
+    encoded: Vec<u8>,
+}
+
+impl<S: ByteSource> TiffStrips<S> {
+    /// Parses the header and first image directory of a TIFF.
+    pub fn open(mut source: S) -> Result<TiffStrips<S>, ScanError> {
+        let mut header = [0u8; 8];
+        source.read_at(0, &mut header)?;
+        let little_endian = match &header[..4] {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            [b'I', b'I', 43, 0] | [b'M', b'M', 0, 43] => {
+                return Err(ScanError::InvalidData("BigTIFF is not supported"))
+            }
+            _ => return Err(ScanError::InvalidData("Not a TIFF file")),
+        };
+        let mut tiff = TiffStrips {
+            source,
+            little_endian,
+            width: 0,
+            height: 0,
+            bits: 1,
+            samples: 1,
+            min_is_white: false,
+            packbits: false,
+            rows_per_strip: usize::MAX,
+            offsets: Vec::new(),
+            byte_counts: Vec::new(),
+            encoded: Vec::new(),
+        };
+        let directory = tiff.number(&header[4..8]);
+        let mut count = [0u8; 2];
+        tiff.source.read_at(directory, &mut count)?;
+        let entries = tiff.number(&count);
+
+        let mut photometric = None;
+        for e in 0..entries {
+            let mut entry = [0u8; 12];
+            tiff.source.read_at(directory + 2 + 12 * e, &mut entry)?;
+            let tag = tiff.number(&entry[..2]);
+            let values = tiff.values(&entry)?;
+            let first = values.first().copied().unwrap_or(0);
+            match tag {
+                256 => tiff.width = first as usize,
+                257 => tiff.height = first as usize,
+                258 => tiff.bits = first as usize,
+                259 if first == 32773 => tiff.packbits = true,
+                259 if first != 1 => {
+                    return Err(ScanError::InvalidData("Only uncompressed and PackBits TIFFs are supported"))
+                }
+                262 => photometric = Some(first),
+                273 => tiff.offsets = values,
+                277 => tiff.samples = first as usize,
+                278 => tiff.rows_per_strip = first as usize,
+                279 => tiff.byte_counts = values,
+                284 if first != 1 => return Err(ScanError::InvalidData("Planar TIFFs are not supported")),
+                317 if first != 1 => return Err(ScanError::InvalidData("TIFF predictors are not supported")),
+                322 => return Err(ScanError::InvalidData("Tiled TIFFs are not supported")),
+                _ => {}
+            }
+        }
+
+        let supported = matches!(
+            (tiff.bits, tiff.samples, photometric),
+            (1 | 8, 1, Some(0 | 1)) | (8, 3, Some(2)) | (8, 4, Some(2))
+        );
+        if !supported {
+            return Err(ScanError::InvalidData("Unsupported TIFF pixel format"));
+        }
+        tiff.min_is_white = photometric == Some(0);
+        tiff.rows_per_strip = tiff.rows_per_strip.clamp(1, tiff.height.max(1));
+        let strips = tiff.height.div_ceil(tiff.rows_per_strip);
+        if tiff.width == 0 || tiff.offsets.len() != strips || tiff.byte_counts.len() != strips {
+            return Err(ScanError::InvalidData("TIFF strip layout doesn't match the image size"));
+        }
+        Ok(tiff)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn strip_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Rows per strip; the last strip may have fewer.
+    pub fn rows_per_strip(&self) -> usize {
+        self.rows_per_strip
+    }
+
+    /// Reads and decodes one strip into `rgba` (resized to its rows) and
+    /// returns the index of its first row.
+    pub fn read_strip(&mut self, index: usize, rgba: &mut Vec<u8>) -> Result<usize, ScanError> {
+        crate::error::check_index(index, self.offsets.len())?;
+        let first_row = index * self.rows_per_strip;
+        let rows = self.rows_per_strip.min(self.height - first_row);
+        let row_bytes = (self.width * self.samples * self.bits).div_ceil(8);
+        let byte_count = self.byte_counts[index];
+        if byte_count > MAX_STRIP_BYTES {
+            return Err(ScanError::InvalidData("TIFF strip too large"));
+        }
+        self.encoded.resize(byte_count as usize, 0);
+        self.source.read_at(self.offsets[index], &mut self.encoded)?;
+        let mut unpacked = Vec::new();
+        let raw = if self.packbits {
+            unpack_bits(&self.encoded, &mut unpacked)?;
+            &unpacked
+        } else {
+            &self.encoded
+        };
+        if raw.len() < rows * row_bytes {
+            return Err(ScanError::InvalidData("TIFF strip is shorter than its rows"));
+        }
+
+        rgba.clear();
+        rgba.reserve(rows * self.width * 4);
+        for row in raw.chunks_exact(row_bytes).take(rows) {
+            match (self.bits, self.samples) {
+                (1, _) => rgba.extend((0..self.width).flat_map(|x| {
+                    let bit = row[x / 8] >> (7 - x % 8) & 1 == 1;
+                    let v = if bit != self.min_is_white { 255 } else { 0 };
+                    [v, v, v, 255]
+                })),
+                (_, 1) => rgba.extend(row.iter().flat_map(|&v| {
+                    let v = if self.min_is_white { 255 - v } else { v };
+                    [v, v, v, 255]
+                })),
+                (_, 3) => rgba.extend(row.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255])),
+                _ => rgba.extend_from_slice(row),
+            }
+        }
+        Ok(first_row)
+    }
+
+    /// Decodes the image strip by strip, calling `process(first_row, rows,
+    /// rgba)` for each; only one strip is held in memory at a time.
+    pub fn for_each_strip(&mut self, mut process: impl FnMut(usize, usize, &[u8])) -> Result<(), ScanError> {
+        let mut rgba = Vec::new();
+        for index in 0..self.strip_count() {
+            let first_row = self.read_strip(index, &mut rgba)?;
+            process(first_row, rgba.len() / (self.width * 4), &rgba);
+        }
+        Ok(())
+    }
+
+    // Unsigned integer of 2 or 4 bytes in the file's byte order.
+    fn number(&self, bytes: &[u8]) -> u64 {
+        let fold = |n: u64, &b: &u8| n << 8 | b as u64;
+        if self.little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        }
+    }
+
+    // Values of a directory entry, stored inline or at an offset.
+    fn values(&mut self, entry: &[u8; 12]) -> Result<Vec<u64>, ScanError> {
+        let Some(size) = field_size(self.number(&entry[2..4]) as u16) else {
+            return Ok(Vec::new());
+        };
+        let count = self.number(&entry[4..8]);
+        if count * size > MAX_STRIP_BYTES {
+            return Err(ScanError::InvalidData("TIFF directory entry too large"));
+        }
+        let len = (count * size) as usize;
+        let mut bytes = vec![0u8; len];
+        if len <= 4 {
+            bytes.copy_from_slice(&entry[8..8 + len]);
+        } else {
+            let offset = self.number(&entry[8..12]);
+            self.source.read_at(offset, &mut bytes)?;
+        }
+        Ok(bytes.chunks_exact(size as usize).map(|value| self.number(value)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A TIFF with the given directory entries (tag, type, values) plus strip
+    // offsets and byte counts, in either byte order: header, directory,
+    // out-of-line values, then the strips.
+    fn tiff(little_endian: bool, entries: &[(u16, u16, Vec<u32>)], strips: &[Vec<u8>]) -> Vec<u8> {
+        let u16b = |v: u16| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32b = |v: u32| if little_endian { v.to_le_bytes() } else { v.to_be_bytes() };
+        let mut entries = entries.to_vec();
+        entries.push((273, 4, vec![0; strips.len()]));
+        entries.push((279, 4, strips.iter().map(|s| s.len() as u32).collect()));
+        entries.sort_by_key(|e| e.0);
+        let size = |kind: u16, values: &[u32]| values.len() * if kind == 3 { 2 } else { 4 };
+        let extra_start = 8 + 2 + 12 * entries.len() + 4;
+        let extra_len: usize = entries.iter().map(|e| size(e.1, &e.2)).filter(|&n| n > 4).sum();
+        let mut strip_start = (extra_start + extra_len) as u32;
+        for (offset, strip) in entries.iter_mut().find(|e| e.0 == 273).unwrap().2.iter_mut().zip(strips) {
+            *offset = strip_start;
+            strip_start += strip.len() as u32;
+        }
+
+        let mut file = if little_endian { b"II".to_vec() } else { b"MM".to_vec() };
+        file.extend(u16b(42));
+        file.extend(u32b(8));
+        file.extend(u16b(entries.len() as u16));
+        let mut extra = Vec::new();
+        for (tag, kind, values) in &entries {
+            file.extend(u16b(*tag));
+            file.extend(u16b(*kind));
+            file.extend(u32b(values.len() as u32));
+            let bytes: Vec<u8> = values
+                .iter()
+                .flat_map(|&v| if *kind == 3 { u16b(v as u16).to_vec() } else { u32b(v).to_vec() })
+                .collect();
+            if bytes.len() <= 4 {
+                file.extend(&bytes);
+                file.resize(file.len() + 4 - bytes.len(), 0);
+            } else {
+                file.extend(u32b((extra_start + extra.len()) as u32));
+                extra.extend(bytes);
+            }
+        }
+        file.extend(u32b(0));
+        file.extend(extra);
+        strips.iter().for_each(|s| file.extend(s));
+        file
+    }
+
+    #[test]
+    fn test_tiff_strips() {
+        // A 5x5 grayscale ramp in 2-row PackBits strips, min-is-white
+        let (width, height) = (5, 5);
+        let gray: Vec<u8> = (0..25).map(|i| (i * 10) as u8).collect();
+        let strips: Vec<Vec<u8>> = gray.chunks(10).map(|rows| [&[rows.len() as u8 - 1][..], rows].concat()).collect();
+        let entries =
+            [(256, 3, vec![5]), (257, 3, vec![5]), (258, 3, vec![8]), (259, 3, vec![32773]), (262, 3, vec![0])];
+        let file = tiff(true, &[&entries[..], &[(278, 3, vec![2])]].concat(), &strips);
+
+        // Read through a callback that records the largest request
+        let mut largest = 0;
+        let source = FnSource::new(file.len() as u64, |offset, buf: &mut [u8]| {
+            largest = largest.max(buf.len());
+            buf.copy_from_slice(&file[offset as usize..][..buf.len()]);
+            true
+        });
+        let mut reader = TiffStrips::open(source).unwrap();
+        assert_eq!((reader.width(), reader.height(), reader.strip_count()), (width, height, 3));
+        let mut decoded = Vec::new();
+        reader
+            .for_each_strip(|first_row, rows, rgba| {
+                assert_eq!((first_row, rows), (decoded.len() / width, rows.min(height - first_row)));
+                decoded.extend(rgba.chunks_exact(4).map(|px| 255 - px[0]));
+            })
+            .unwrap();
+        assert_eq!(decoded, gray);
+        assert!(largest <= 12, "{largest}");
+
+        // Big-endian RGB in one strip, from a file on disk and from a slice
+        let rgb: Vec<u8> = (0..2 * 3 * 3).map(|i| i as u8 * 9).collect();
+        let entries =
+            [(256, 4, vec![2]), (257, 4, vec![3]), (258, 3, vec![8, 8, 8]), (262, 3, vec![2]), (277, 3, vec![3])];
+        let file = tiff(false, &entries, core::slice::from_ref(&rgb));
+        let path = std::env::temp_dir().join(format!("scanic-source-{}.tif", std::process::id()));
+        std::fs::write(&path, &file).unwrap();
+        let mut reader = TiffStrips::open(ReaderSource::new(std::fs::File::open(&path).unwrap()).unwrap()).unwrap();
+        let mut rgba = Vec::new();
+        assert_eq!(reader.read_strip(0, &mut rgba).unwrap(), 0);
+        assert_eq!(rgba.chunks_exact(4).flat_map(|px| px[..3].to_vec()).collect::<Vec<u8>>(), rgb);
+        assert!(reader.read_strip(1, &mut rgba).is_err());
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+        let mut truncated = TiffStrips::open(&file[..file.len() - 1]).unwrap();
+        assert_eq!(
+            truncated.read_strip(0, &mut rgba),
+            Err(ScanError::ReadFailed { offset: file.len() as u64 - 18, len: 18 })
+        );
+        assert!(TiffStrips::open(&b"GIF89a.."[..]).is_err());
+    }
+}
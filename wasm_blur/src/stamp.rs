@@ -0,0 +1,249 @@
+use wasm_bindgen::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+
+// Glyphs are 5x7 cells, one byte per row with the leftmost cell in bit 4,
+// drawn with a blank column between characters.
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const DIGITS: [[u8; GLYPH_HEIGHT]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+const LETTERS: [[u8; GLYPH_HEIGHT]; 26] = [
+    [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+    [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+];
+// Cell size in px is the page's shorter side over this, rounded down to whole
+// cells, so labels keep their size relative to the page at any resolution.
+const CELL_DIVISOR: usize = 560;
+// Distance of the label box from the page edges, in cells.
+const MARGIN_CELLS: usize = 4;
+// Default zero padding of the number, as in "ABC000001".
+const DEFAULT_PADDING: usize = 6;
+
+/// Page corner or edge a stamp is placed at.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StampPosition {
+    TopLeft = 0,
+    TopRight = 1,
+    BottomLeft = 2,
+    /// The usual place for Bates numbers (the default)
+    #[default]
+    BottomRight = 3,
+    BottomCenter = 4,
+}
+
+// Rows of the glyph for `c`; letters are drawn upper case.
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    match c.to_ascii_uppercase() {
+        d @ '0'..='9' => Some(DIGITS[d as usize - '0' as usize]),
+        l @ 'A'..='Z' => Some(LETTERS[l as usize - 'A' as usize]),
+        ' ' => Some([0; GLYPH_HEIGHT]),
+        '-' => Some([0, 0, 0, 0x1f, 0, 0, 0]),
+        '.' => Some([0, 0, 0, 0, 0, 0x0c, 0x0c]),
+        '_' => Some([0, 0, 0, 0, 0, 0, 0x1f]),
+        '/' => Some([0, 0x01, 0x02, 0x04, 0x08, 0x10, 0]),
+        ':' => Some([0, 0x0c, 0x0c, 0, 0x0c, 0x0c, 0]),
+        '#' => Some([0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a]),
+        _ => None,
+    }
+}
+
+fn check_text(text: &str) -> Result<(), ScanError> {
+    if !text.chars().all(|c| glyph(c).is_some()) {
+        return Err(ScanError::InvalidParameter("Stamp text may only contain letters, digits, space and - . _ / : #"));
+    }
+    Ok(())
+}
+
+// Draws `text` as black glyphs on a white box at `position` of an RGBA page;
+// characters without a glyph are left blank. The box is clipped to the page.
+pub(crate) fn stamp_into(rgba: &mut [u8], width: usize, height: usize, text: &str, position: StampPosition) {
+    let cell = (width.min(height) / CELL_DIVISOR).max(1);
+    let chars = text.chars().count();
+    // One blank cell around the text, and one between characters
+    let box_width = (chars * (GLYPH_WIDTH + 1) + 1) * cell;
+    let box_height = (GLYPH_HEIGHT + 2) * cell;
+    let margin = MARGIN_CELLS * cell;
+    let left = match position {
+        StampPosition::TopLeft | StampPosition::BottomLeft => margin,
+        StampPosition::TopRight | StampPosition::BottomRight => width.saturating_sub(box_width + margin),
+        StampPosition::BottomCenter => width.saturating_sub(box_width) / 2,
+    };
+    let top = match position {
+        StampPosition::TopLeft | StampPosition::TopRight => margin,
+        _ => height.saturating_sub(box_height + margin),
+    };
+
+    let mut fill = |x0: usize, y0: usize, w: usize, h: usize, value: u8| {
+        for y in y0.min(height)..(y0 + h).min(height) {
+            for x in x0.min(width)..(x0 + w).min(width) {
+                let i = (y * width + x) * 4;
+                rgba[i..i + 3].fill(value);
+                rgba[i + 3] = 255;
+            }
+        }
+    };
+    fill(left, top, box_width, box_height, 255);
+    for (k, c) in text.chars().enumerate() {
+        let rows = glyph(c).unwrap_or_default();
+        let x0 = left + (k * (GLYPH_WIDTH + 1) + 1) * cell;
+        for (r, bits) in rows.iter().enumerate() {
+            for col in (0..GLYPH_WIDTH).filter(|col| bits & (0x10 >> col) != 0) {
+                fill(x0 + col * cell, top + (r + 1) * cell, cell, cell, 0);
+            }
+        }
+    }
+}
+
+/// Sequential page identifiers stamped onto exported pages, e.g. Bates numbers
+/// for legal productions: a prefix and a zero-padded number counting up from
+/// the first page (see `ScanSession::set_bates_numbering`).
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatesNumbering {
+    prefix: String,
+    start: u32,
+    padding: usize,
+    position: StampPosition,
+}
+
+#[wasm_bindgen]
+impl BatesNumbering {
+    /// Numbering from 1 with 6 digits at the bottom right. The prefix may hold
+    /// letters, digits, space and `- . _ / : #`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(prefix: &str) -> Result<BatesNumbering, ScanError> {
+        check_text(prefix)?;
+        Ok(BatesNumbering {
+            prefix: prefix.to_string(),
+            start: 1,
+            padding: DEFAULT_PADDING,
+            position: StampPosition::default(),
+        })
+    }
+
+    /// Number of the first exported page, e.g. to continue a production.
+    pub fn set_start(&mut self, start: u32) {
+        self.start = start;
+    }
+
+    /// Minimum number of digits; shorter numbers get leading zeros.
+    pub fn set_padding(&mut self, digits: usize) {
+        self.padding = digits;
+    }
+
+    pub fn set_position(&mut self, position: StampPosition) {
+        self.position = position;
+    }
+
+    /// Label of the page at `index` in export order.
+    pub fn label(&self, index: usize) -> String {
+        format!("{}{:0width$}", self.prefix, self.start as u64 + index as u64, width = self.padding)
+    }
+}
+
+impl BatesNumbering {
+    pub(crate) fn stamp_into(&self, rgba: &mut [u8], width: usize, height: usize, index: usize) {
+        stamp_into(rgba, width, height, &self.label(index), self.position);
+    }
+}
+
+/// Stamps a line of text onto an RGBA page in black on a white box, sized
+/// relative to the page with the crate's built-in 5x7 pixel font.
+///
+/// # Arguments
+/// * `rgba` - RGBA page
+/// * `width` - Page width
+/// * `height` - Page height
+/// * `text` - Letters, digits, space and `- . _ / : #`; letters are drawn upper case
+/// * `position` - Corner or edge of the page
+///
+/// # Returns
+/// Stamped RGBA page as Vec<u8>
+#[wasm_bindgen]
+pub fn stamp_text(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    text: &str,
+    position: StampPosition,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    check_text(text)?;
+    let mut out = rgba.to_vec();
+    stamp_into(&mut out, width, height, text, position);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::ScanSession;
+
+    #[test]
+    fn test_bates_numbering() {
+        let mut numbering = BatesNumbering::new("ABC-").unwrap();
+        numbering.set_start(41);
+        numbering.set_padding(4);
+        assert_eq!((numbering.label(0), numbering.label(1)), ("ABC-0041".to_string(), "ABC-0042".to_string()));
+        assert!(BatesNumbering::new("Akte §1").is_err());
+
+        // Grey pages get a white box with black glyphs in the bottom-right corner
+        let (width, height) = (120, 80);
+        let mut session = ScanSession::new();
+        for _ in 0..2 {
+            session.add_page(&[128u8; 120 * 80 * 4], width, height).unwrap();
+        }
+        session.set_bates_numbering(Some(numbering));
+        let exported = session.export_all(Vec::new());
+        let pages: Vec<Vec<u8>> = (0..2).map(|i| exported.page(i).unwrap()).collect();
+        let at = |page: &[u8], x: usize, y: usize| page[(y * width + x) * 4];
+        // 8 characters, 6 cells each plus one: 49 px wide, 9 px high, 4 px margin
+        let changed: Vec<(usize, usize)> =
+            (0..width * height).map(|i| (i % width, i / width)).filter(|&(x, y)| at(&pages[0], x, y) != 128).collect();
+        assert!(changed.iter().all(|&(x, y)| (67..116).contains(&x) && (67..76).contains(&y)));
+        assert_eq!(changed.len(), 49 * 9);
+        assert!(changed.iter().any(|&(x, y)| at(&pages[0], x, y) == 0));
+        // Only the last digit differs between the pages
+        let differs = |&&(x, y): &&(usize, usize)| at(&pages[0], x, y) != at(&pages[1], x, y);
+        let diff: Vec<usize> = changed.iter().filter(differs).map(|&(x, _)| x).collect();
+        assert!(!diff.is_empty() && diff.iter().all(|&x| x >= 67 + 43));
+
+        assert_eq!(stamp_text(&pages[0], width, height, "ABC-0041", StampPosition::BottomRight).unwrap(), pages[0]);
+    }
+}
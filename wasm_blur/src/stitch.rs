@@ -0,0 +1,314 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+use crate::grayscale::{fixed_weights, luma_scalar, GrayscaleWeights};
+
+// Overlap between consecutive segments, as a share of the new segment's height.
+const MIN_OVERLAP_FRACTION: f32 = 0.1;
+const MAX_OVERLAP_FRACTION: f32 = 0.9;
+// Largest sideways drift (px) between segments that registration searches.
+const MAX_SHIFT: isize = 16;
+// The offset is searched on COARSE x COARSE block means first, then refined
+// within one block at full resolution.
+const COARSE: usize = 4;
+// Mean absolute luma difference over the overlap above which the segments
+// don't match.
+const MAX_MATCH_ERROR: f32 = 12.0;
+// Overlap rows above the seam over which the new segment is faded in.
+const FEATHER_ROWS: usize = 16;
+
+// Luma plane used for registration.
+struct Plane {
+    luma: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl Plane {
+    fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Plane {
+        let weights = fixed_weights(GrayscaleWeights::Bt601);
+        let luma = rgba.chunks_exact(4).map(|p| luma_scalar(p[0], p[1], p[2], weights)).collect();
+        Plane { luma, width, height }
+    }
+
+    // Block means over COARSE x COARSE pixels; the remainder is dropped.
+    fn coarse(&self) -> Plane {
+        let (width, height) = (self.width / COARSE, self.height / COARSE);
+        let mut luma = vec![0u8; width * height];
+        for (i, value) in luma.iter_mut().enumerate() {
+            let (bx, by) = (i % width * COARSE, i / width * COARSE);
+            let sum: u32 = (by..by + COARSE)
+                .flat_map(|y| self.luma[y * self.width + bx..][..COARSE].iter().map(|&v| v as u32))
+                .sum();
+            *value = (sum / (COARSE * COARSE) as u32) as u8;
+        }
+        Plane { luma, width, height }
+    }
+}
+
+// Mean absolute difference when the top `overlap` rows of `segment`, shifted
+// right by `shift`, lie over the bottom rows of `canvas`. None if the shifted
+// segment doesn't overlap the canvas at all.
+fn match_error(canvas: &Plane, segment: &Plane, overlap: usize, shift: isize) -> Option<f32> {
+    let top = canvas.height - overlap;
+    let from = shift.max(0) as usize;
+    let to = (segment.width as isize + shift).min(canvas.width as isize);
+    if to <= from as isize {
+        return None;
+    }
+    let to = to as usize;
+    let mut sum = 0u64;
+    for y in 0..overlap {
+        let canvas_row = &canvas.luma[(top + y) * canvas.width..][from..to];
+        let segment_row = &segment.luma[y * segment.width + (from as isize - shift) as usize..][..to - from];
+        sum += canvas_row.iter().zip(segment_row).map(|(&a, &b)| a.abs_diff(b) as u64).sum::<u64>();
+    }
+    Some(sum as f32 / (overlap * (to - from)) as f32)
+}
+
+// Best (error, overlap, shift) among the candidates.
+fn best_match(
+    canvas: &Plane,
+    segment: &Plane,
+    overlaps: impl Iterator<Item = usize> + Clone,
+    shifts: impl Iterator<Item = isize> + Clone,
+) -> Option<(f32, usize, isize)> {
+    let mut best: Option<(f32, usize, isize)> = None;
+    for overlap in overlaps {
+        for shift in shifts.clone() {
+            if let Some(error) = match_error(canvas, segment, overlap, shift) {
+                if best.is_none_or(|(e, _, _)| error < e) {
+                    best = Some((error, overlap, shift));
+                }
+            }
+        }
+    }
+    best
+}
+
+// Overlap (rows) and sideways shift (px) of `segment` below `canvas`, with the
+// match error. `canvas` holds only the bottom rows of the stitched image.
+fn register(canvas: &Plane, segment: &Plane) -> Option<(f32, usize, isize)> {
+    let max_overlap = ((segment.height as f32 * MAX_OVERLAP_FRACTION) as usize).min(canvas.height);
+    let min_overlap = ((segment.height as f32 * MIN_OVERLAP_FRACTION).ceil() as usize).max(1);
+    if max_overlap < min_overlap {
+        return None;
+    }
+    let (coarse_canvas, coarse_segment) = (canvas.coarse(), segment.coarse());
+    let coarse_max = (max_overlap / COARSE).min(coarse_canvas.height);
+    let coarse_min = (min_overlap / COARSE).max(1);
+    let coarse_shift = MAX_SHIFT / COARSE as isize;
+    let (_, overlap, shift) = if coarse_min <= coarse_max {
+        best_match(&coarse_canvas, &coarse_segment, coarse_min..=coarse_max, -coarse_shift..=coarse_shift)?
+    } else {
+        (0.0, 0, 0)
+    };
+
+    // Refine within one block of the coarse estimate
+    let (overlap, shift) = (overlap * COARSE, shift * COARSE as isize);
+    let overlaps = overlap.saturating_sub(COARSE).max(min_overlap)..=(overlap + COARSE).min(max_overlap);
+    let reach = COARSE as isize;
+    let shifts = (shift - reach).max(-MAX_SHIFT)..=(shift + reach).min(MAX_SHIFT);
+    best_match(canvas, segment, overlaps, shifts)
+}
+
+/// Guided capture of a long receipt in overlapping vertical segments, stitched
+/// into one tall image. Feed the rectified segments top to bottom with
+/// `add_segment`; each one has to repeat 10-90 % of the previous one's bottom
+/// and may drift sideways by up to 16 px. Segments are registered on their luma
+/// in the overlap and faded in over the 16 rows above the seam.
+#[wasm_bindgen]
+pub struct ReceiptStitcher {
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+    segments: usize,
+    last_overlap: usize,
+    last_shift: isize,
+    last_error: f32,
+}
+
+#[wasm_bindgen]
+impl ReceiptStitcher {
+    /// Creates a stitcher for segments `width` pixels wide.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize) -> ReceiptStitcher {
+        ReceiptStitcher {
+            width,
+            height: 0,
+            rgba: Vec::new(),
+            segments: 0,
+            last_overlap: 0,
+            last_shift: 0,
+            last_error: 0.0,
+        }
+    }
+
+    /// Appends the next segment below the stitched image. Returns false and
+    /// leaves the image unchanged if no overlap with the previous segment was
+    /// found, e.g. because the user moved too far: ask for a retake from
+    /// further up the receipt.
+    pub fn add_segment(&mut self, rgba: &[u8], width: usize, height: usize) -> Result<bool, ScanError> {
+        check_rgba(rgba, width, height)?;
+        if width != self.width {
+            return Err(ScanError::InvalidParameter("Segment width must match the stitcher width"));
+        }
+        if height == 0 {
+            return Ok(false);
+        }
+        if self.segments == 0 {
+            self.rgba = rgba.to_vec();
+            self.height = height;
+            self.segments = 1;
+            return Ok(true);
+        }
+
+        // Whole blocks, so the coarse canvas ends at the bottom row
+        let tail = self.height.min(height) / COARSE * COARSE;
+        let canvas = Plane::from_rgba(&self.rgba[(self.height - tail) * width * 4..], width, tail);
+        let segment = Plane::from_rgba(rgba, width, height);
+        let Some((error, overlap, shift)) = register(&canvas, &segment) else {
+            return Ok(false);
+        };
+        (self.last_overlap, self.last_shift, self.last_error) = (overlap, shift, error);
+        if error > MAX_MATCH_ERROR {
+            return Ok(false);
+        }
+
+        // Segment pixel for stitched column x, clamped at the segment's sides
+        let source = |x: usize, y: usize| {
+            let sx = (x as isize - shift).clamp(0, width as isize - 1) as usize;
+            (y * width + sx) * 4
+        };
+        let top = self.height - overlap;
+        let feather = FEATHER_ROWS.min(overlap);
+        for y in overlap - feather..overlap {
+            let weight = (y + feather + 1 - overlap) as f32 / (feather + 1) as f32;
+            for x in 0..width {
+                let (dst, src) = ((top + y) * width * 4 + x * 4, source(x, y));
+                for c in 0..4 {
+                    let blended = self.rgba[dst + c] as f32 * (1.0 - weight) + rgba[src + c] as f32 * weight;
+                    self.rgba[dst + c] = (blended + 0.5) as u8;
+                }
+            }
+        }
+        self.rgba.reserve((height - overlap) * width * 4);
+        for y in overlap..height {
+            for x in 0..width {
+                let src = source(x, y);
+                self.rgba.extend_from_slice(&rgba[src..src + 4]);
+            }
+        }
+        self.height += height - overlap;
+        self.segments += 1;
+        Ok(true)
+    }
+
+    /// Starts a new receipt.
+    pub fn reset(&mut self) {
+        self.rgba = Vec::new();
+        self.height = 0;
+        self.segments = 0;
+        (self.last_overlap, self.last_shift, self.last_error) = (0, 0, 0.0);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the stitched image so far.
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn segment_count(&self) -> usize {
+        self.segments
+    }
+
+    /// Rows shared with the previous segment in the last registration, also
+    /// when it was rejected. Guides the user towards about a third of the frame.
+    #[wasm_bindgen(getter)]
+    pub fn last_overlap(&self) -> usize {
+        self.last_overlap
+    }
+
+    /// Sideways offset (px, positive to the right) at which the last segment
+    /// was placed to line up with the previous one.
+    #[wasm_bindgen(getter)]
+    pub fn last_shift(&self) -> i32 {
+        self.last_shift as i32
+    }
+
+    /// Mean absolute luma difference over the last overlap; segments above 12
+    /// are rejected.
+    #[wasm_bindgen(getter)]
+    pub fn last_match_error(&self) -> f32 {
+        self.last_error
+    }
+
+    /// RGBA pixels of the stitched receipt, `width` x `height`.
+    pub fn stitched(&self) -> Vec<u8> {
+        self.rgba.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_stitching() {
+        // A long receipt of random text blocks, captured in three segments with
+        // different overlaps, the middle one taken 3 px further right
+        let (width, height) = (96, 400);
+        let mut seed = 7u32;
+        let mut receipt = vec![0u8; width * height * 4];
+        for line in 0..height / 8 {
+            for word in 0..width / 12 {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                let ink = if (seed >> 16).is_multiple_of(3) { 40 } else { 235 };
+                for y in line * 8..line * 8 + 5 {
+                    for x in word * 12..word * 12 + 9 {
+                        receipt[(y * width + x) * 4..][..4].copy_from_slice(&[ink, ink, ink, 255]);
+                    }
+                }
+            }
+        }
+        for px in receipt.chunks_exact_mut(4).filter(|p| p[3] == 0) {
+            px.copy_from_slice(&[235, 235, 235, 255]);
+        }
+        let segment = |top: usize, rows: usize, shift: isize| -> Vec<u8> {
+            (0..rows * width)
+                .flat_map(|i| {
+                    let x = (i % width) as isize + shift;
+                    let x = x.clamp(0, width as isize - 1) as usize;
+                    receipt[((top + i / width) * width + x) * 4..][..4].to_vec()
+                })
+                .collect()
+        };
+
+        let mut stitcher = ReceiptStitcher::new(width);
+        assert!(stitcher.add_segment(&segment(0, 180, 0), width, 180).unwrap());
+        assert!(stitcher.add_segment(&segment(120, 160, 3), width, 160).unwrap());
+        assert_eq!((stitcher.last_overlap(), stitcher.last_shift()), (60, 3));
+        assert!(stitcher.add_segment(&segment(230, 170, 0), width, 170).unwrap());
+        assert_eq!((stitcher.last_overlap(), stitcher.last_shift(), stitcher.last_match_error()), (50, 0, 0.0));
+        assert_eq!((stitcher.height(), stitcher.segment_count()), (height, 3));
+        // Exact away from the columns the drift pulled in from the side
+        let stitched = stitcher.stitched();
+        let inner = |rgba: &[u8]| rgba.chunks_exact(4 * width).flat_map(|r| r[16..320].to_vec()).collect::<Vec<u8>>();
+        assert_eq!(inner(&stitched), inner(&receipt));
+
+        // An unrelated frame doesn't register and leaves the receipt as it is
+        let dots: Vec<u8> = (0..width * 100).flat_map(|i| if i % 7 == 0 { [0, 0, 0, 255] } else { [255; 4] }).collect();
+        assert!(!stitcher.add_segment(&dots, width, 100).unwrap());
+        assert_eq!(stitcher.height(), height);
+        assert!(stitcher.add_segment(&vec![255; (width + 1) * 10 * 4], width + 1, 10).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_gray, check_index, ScanError};
+
+// Binary pixels at or below this are ink.
+const INK: u8 = 127;
+// Shortest ruling line as a share of the page side it runs along, and in px.
+// Text strokes are far shorter; underlines that don't meet two crossing
+// lines are dropped later.
+const MIN_LINE_FRACTION: f32 = 1.0 / 30.0;
+const MIN_LINE_PX: usize = 12;
+// Paper pixels bridged inside a run, for lines broken up by binarization.
+const MAX_RUN_GAP: usize = 2;
+// Tolerance (px) when merging runs into lines, grouping lines into grid
+// positions and testing whether two lines cross.
+const LINE_TOL: usize = 3;
+// Share of a cell side a ruling has to cover to separate two cells; cells
+// whose shared side is not ruled are merged.
+const MIN_SEPARATOR_COVERAGE: f32 = 0.5;
+
+// A ruling line: mean position across it and its extent along it.
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    position: f32,
+    start: usize,
+    end: usize,
+    last: usize,
+    weight: usize,
+}
+
+// Grid position shared by one or more collinear line segments.
+struct Ruling {
+    position: f32,
+    segments: Vec<(usize, usize)>,
+}
+
+impl Ruling {
+    // Whether the segments cover enough of `from..to` along the line.
+    fn separates(&self, from: f32, to: f32) -> bool {
+        let covered: f32 =
+            self.segments.iter().map(|&(start, end)| (to.min(end as f32) - from.max(start as f32)).max(0.0)).sum();
+        covered >= MIN_SEPARATOR_COVERAGE * (to - from)
+    }
+}
+
+struct Cell {
+    grid: [usize; 4],
+    bounds: [f32; 4],
+}
+
+/// Table found by `detect_table`: the ruling positions and the cells between
+/// them, with cells spanning several rows or columns where rulings are
+/// missing.
+#[wasm_bindgen]
+pub struct TableGrid {
+    rows: Vec<f32>,
+    columns: Vec<f32>,
+    cells: Vec<Cell>,
+}
+
+#[wasm_bindgen]
+impl TableGrid {
+    /// Y positions of the horizontal rulings, top to bottom.
+    pub fn rows(&self) -> Vec<f32> {
+        self.rows.clone()
+    }
+
+    /// X positions of the vertical rulings, left to right.
+    pub fn columns(&self) -> Vec<f32> {
+        self.columns.clone()
+    }
+
+    /// Number of cells, in reading order of their top-left grid position.
+    pub fn count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Grid position of a cell as `[row, column, row_span, column_span]`.
+    pub fn cell(&self, index: usize) -> Result<Vec<usize>, ScanError> {
+        Ok(self.get(index)?.grid.to_vec())
+    }
+
+    /// Cell rectangle between the ruling centres as `[x, y, width, height]`.
+    pub fn bounds(&self, index: usize) -> Result<Vec<f32>, ScanError> {
+        Ok(self.get(index)?.bounds.to_vec())
+    }
+}
+
+impl TableGrid {
+    fn get(&self, index: usize) -> Result<&Cell, ScanError> {
+        check_index(index, self.cells.len())?;
+        Ok(&self.cells[index])
+    }
+}
+
+// Long ink runs along one axis, merged with the runs in adjacent rows (or
+// columns) they overlap. `ink(along, across)` reads the page in that axis.
+fn extract_lines(along_len: usize, across_len: usize, ink: impl Fn(usize, usize) -> bool) -> Vec<Line> {
+    let min_len = ((MIN_LINE_FRACTION * along_len as f32) as usize).max(MIN_LINE_PX);
+    let mut done = Vec::new();
+    let mut open: Vec<Line> = Vec::new();
+    for across in 0..across_len {
+        // Lines not continued within the tolerance are finished
+        let (stale, active): (Vec<Line>, Vec<Line>) = open.into_iter().partition(|l| l.last + LINE_TOL < across);
+        done.extend(stale);
+        open = active;
+
+        let mut along = 0;
+        while along < along_len {
+            if !ink(along, across) {
+                along += 1;
+                continue;
+            }
+            let start = along;
+            let mut end = along;
+            while along < along_len && along - end <= MAX_RUN_GAP {
+                if ink(along, across) {
+                    end = along;
+                }
+                along += 1;
+            }
+            if end + 1 - start < min_len {
+                continue;
+            }
+            match open.iter_mut().find(|l| l.last < across && start <= l.end && end >= l.start) {
+                Some(line) => {
+                    line.position += (across as f32 - line.position) / (line.weight + 1) as f32;
+                    line.weight += 1;
+                    line.last = across;
+                    line.start = line.start.min(start);
+                    line.end = line.end.max(end);
+                }
+                None => open.push(Line { position: across as f32, start, end, last: across, weight: 1 }),
+            }
+        }
+    }
+    done.extend(open);
+    done
+}
+
+// Whether a line crosses (or meets) a line of the other axis.
+fn crosses(line: &Line, other: &Line) -> bool {
+    let tol = LINE_TOL as f32;
+    other.position >= line.start as f32 - tol
+        && other.position <= line.end as f32 + tol
+        && line.position >= other.start as f32 - tol
+        && line.position <= other.end as f32 + tol
+}
+
+// Lines meeting at least two lines of the other axis, grouped into rulings by
+// position.
+fn rulings(lines: &[Line], others: &[Line]) -> Vec<Ruling> {
+    let mut kept: Vec<Line> =
+        lines.iter().copied().filter(|l| others.iter().filter(|o| crosses(l, o)).count() >= 2).collect();
+    kept.sort_by(|a, b| a.position.total_cmp(&b.position));
+    let mut rulings: Vec<Ruling> = Vec::new();
+    for line in kept {
+        match rulings.last_mut() {
+            Some(ruling) if line.position - ruling.position <= LINE_TOL as f32 => {
+                ruling.segments.push((line.start, line.end))
+            }
+            _ => rulings.push(Ruling { position: line.position, segments: vec![(line.start, line.end)] }),
+        }
+    }
+    rulings
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+// Cells of the grid spanned by the rulings; neighbouring grid cells without a
+// ruling between them form one cell.
+fn segment_cells(rows: &[Ruling], columns: &[Ruling]) -> Vec<Cell> {
+    if rows.len() < 2 || columns.len() < 2 {
+        return Vec::new();
+    }
+    let (grid_rows, grid_columns) = (rows.len() - 1, columns.len() - 1);
+    let mut parent: Vec<usize> = (0..grid_rows * grid_columns).collect();
+    for r in 0..grid_rows {
+        for c in 0..grid_columns {
+            let i = r * grid_columns + c;
+            if c + 1 < grid_columns && !columns[c + 1].separates(rows[r].position, rows[r + 1].position) {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, i + 1));
+                parent[b.max(a)] = a.min(b);
+            }
+            if r + 1 < grid_rows && !rows[r + 1].separates(columns[c].position, columns[c + 1].position) {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, i + grid_columns));
+                parent[b.max(a)] = a.min(b);
+            }
+        }
+    }
+
+    // Roots are the smallest index of their group, so cells come out in
+    // reading order of their top-left grid cell
+    let mut cells = Vec::new();
+    for root in 0..parent.len() {
+        if find(&mut parent, root) != root {
+            continue;
+        }
+        let members: Vec<usize> = (root..parent.len()).filter(|&i| find(&mut parent, i) == root).collect();
+        let (r0, c0) = (root / grid_columns, root % grid_columns);
+        let r1 = members.iter().map(|&i| i / grid_columns).max().unwrap_or(r0);
+        let c1 = members.iter().map(|&i| i % grid_columns).max().unwrap_or(c0);
+        let (x, y) = (columns[c0].position, rows[r0].position);
+        let bounds = [x, y, columns[c1 + 1].position - x, rows[r1 + 1].position - y];
+        cells.push(Cell { grid: [r0, c0, r1 - r0 + 1, c1 - c0 + 1], bounds });
+    }
+    cells
+}
+
+/// Finds a ruled table on a binarized, rectified page: long horizontal and
+/// vertical ink lines are extracted and merged, and the grid they form is cut
+/// into cells. Cells whose shared side is not ruled (merged cells) come out as
+/// one cell spanning several rows or columns. Lines that don't meet at least
+/// two lines of the other direction, like underlines, are ignored.
+///
+/// # Arguments
+/// * `binary` - Binary page, 0 for ink and 255 for paper
+/// * `width` - Page width
+/// * `height` - Page height
+///
+/// # Returns
+/// `TableGrid` with the rulings and cells; no cells if there is no table
+#[wasm_bindgen]
+pub fn detect_table(binary: &[u8], width: usize, height: usize) -> Result<TableGrid, ScanError> {
+    check_gray(binary, width, height)?;
+    let ink = |x: usize, y: usize| binary[y * width + x] <= INK;
+    let horizontal = extract_lines(width, height, ink);
+    let vertical = extract_lines(height, width, |y, x| ink(x, y));
+    let rows = rulings(&horizontal, &vertical);
+    let columns = rulings(&vertical, &horizontal);
+    let cells = segment_cells(&rows, &columns);
+    if cells.is_empty() {
+        return Ok(TableGrid { rows: Vec::new(), columns: Vec::new(), cells });
+    }
+    Ok(TableGrid {
+        rows: rows.iter().map(|r| r.position).collect(),
+        columns: columns.iter().map(|c| c.position).collect(),
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_cells() {
+        // A 3x3 table with 2 px rulings, a header cell spanning the two right
+        // columns, a ruling broken by binarization, text in the cells and an
+        // underlined heading above the table
+        let (width, height) = (160, 120);
+        let mut page = vec![255u8; width * height];
+        let mut fill = |x0: usize, x1: usize, y0: usize, y1: usize| {
+            for y in y0..y1 {
+                page[y * width + x0..y * width + x1].fill(0);
+            }
+        };
+        for y in [30, 60, 80, 100] {
+            fill(10, 152, y, y + 2);
+        }
+        for x in [10, 60, 150] {
+            fill(x, x + 2, 30, 102);
+        }
+        fill(110, 112, 60, 102);
+        fill(20, 80, 10, 12);
+        fill(20, 26, 12, 20);
+        fill(70, 90, 45, 48);
+        fill(120, 130, 88, 92);
+        page[80 * width + 100] = 255;
+        page[81 * width + 100] = 255;
+        page[90 * width + 60] = 255;
+
+        let table = detect_table(&page, width, height).unwrap();
+        assert_eq!(table.rows(), vec![30.5, 60.5, 80.5, 100.5]);
+        assert_eq!(table.columns(), vec![10.5, 60.5, 110.5, 150.5]);
+        assert_eq!(table.count(), 8);
+        assert_eq!(table.cell(0).unwrap(), vec![0, 0, 1, 1]);
+        assert_eq!(table.cell(1).unwrap(), vec![0, 1, 1, 2]);
+        assert_eq!(table.bounds(1).unwrap(), vec![60.5, 30.5, 90.0, 30.0]);
+        assert_eq!(table.cell(7).unwrap(), vec![2, 2, 1, 1]);
+        assert!(table.cell(8).is_err());
+
+        let blank = detect_table(&[255; 64 * 64], 64, 64).unwrap();
+        assert!(blank.count() == 0 && blank.rows().is_empty());
+    }
+}
@@ -0,0 +1,410 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::ccitt::{encode_ccitt_g4, BitWriter, BILEVEL_THRESHOLD};
+use crate::error::{check_gray, check_index, ScanError};
+
+// Resolution written when none is set.
+const DEFAULT_DPI: u32 = 300;
+// Little-endian header; the first directory offset follows.
+const HEADER: &[u8; 4] = b"II*\0";
+// Field types: SHORT, LONG and RATIONAL.
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+// Compression tag values.
+const LZW: u16 = 5;
+const GROUP4: u16 = 4;
+// Photometric interpretations: 0 is white for bilevel pages, black for gray.
+const WHITE_IS_ZERO: u16 = 0;
+const BLACK_IS_ZERO: u16 = 1;
+// LZW control codes and the first code of the string table, which holds up to
+// 12-bit codes and is cleared one code before it fills up.
+const CLEAR: u16 = 256;
+const EOI: u16 = 257;
+const FIRST_CODE: u16 = 258;
+const LAST_CODE: u16 = 4094;
+// Open-addressing table of (prefix, byte) -> code, twice the table size.
+const HASH_SIZE: usize = 8192;
+
+/// Compression of a bilevel TIFF page.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TiffCompression {
+    /// LZW, as for grayscale pages
+    Lzw = 0,
+    /// CCITT Group 4, several times smaller for text
+    Group4 = 1,
+}
+
+// String table of the LZW coder, keyed by prefix code and next byte.
+struct LzwTable {
+    keys: Vec<u32>,
+    codes: Vec<u16>,
+}
+
+impl LzwTable {
+    // Slot of `key`, or of the empty entry it would go in.
+    fn slot(&self, key: u32) -> usize {
+        let mut slot = (key.wrapping_mul(2654435761) >> 19) as usize;
+        while self.keys[slot] != 0 && self.keys[slot] != key {
+            slot = (slot + 1) % HASH_SIZE;
+        }
+        slot
+    }
+}
+
+// TIFF flavour of LZW: MSB-first codes that start at 9 bits and widen as the
+// table grows, one code earlier than plain LZW, up to 12 bits.
+fn encode_lzw(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::with_capacity(data.len() / 2 + 4);
+    let mut table = LzwTable { keys: vec![0; HASH_SIZE], codes: vec![0; HASH_SIZE] };
+    let (mut next, mut bits) = (FIRST_CODE, 9);
+    w.put((CLEAR, bits));
+    let Some((&first, rest)) = data.split_first() else {
+        w.put((EOI, bits));
+        return w.finish();
+    };
+    let mut prefix = first as u16;
+    for &byte in rest {
+        // Keys are offset by one so that 0 marks an empty slot
+        let key = ((prefix as u32) << 8 | byte as u32) + 1;
+        let slot = table.slot(key);
+        if table.keys[slot] != 0 {
+            prefix = table.codes[slot];
+            continue;
+        }
+        w.put((prefix, bits));
+        table.keys[slot] = key;
+        table.codes[slot] = next;
+        next += 1;
+        if next == LAST_CODE {
+            w.put((CLEAR, bits));
+            table.keys.fill(0);
+            (next, bits) = (FIRST_CODE, 9);
+        } else if next > (1 << bits) - 1 {
+            bits += 1;
+        }
+        prefix = byte as u16;
+    }
+    // The decoder adds an entry for the last code too before reading the end
+    w.put((prefix, bits));
+    if next + 1 == LAST_CODE {
+        w.put((CLEAR, bits));
+        bits = 9;
+    } else if next + 1 > (1 << bits) - 1 {
+        bits += 1;
+    }
+    w.put((EOI, bits));
+    w.finish()
+}
+
+// Compressed strip of a page and the tags that describe it.
+struct Page {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+    bits: u16,
+    compression: u16,
+    photometric: u16,
+}
+
+/// A multi-page TIFF of scanned pages, for document management systems that
+/// only accept TIFF: grayscale pages compressed with LZW and bilevel pages
+/// with LZW or CCITT Group 4. Each page is one strip.
+#[wasm_bindgen]
+pub struct TiffDocument {
+    pages: Vec<Page>,
+    dpi: u32,
+}
+
+impl Default for TiffDocument {
+    fn default() -> Self {
+        TiffDocument { pages: Vec::new(), dpi: DEFAULT_DPI }
+    }
+}
+
+#[wasm_bindgen]
+impl TiffDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TiffDocument {
+        TiffDocument::default()
+    }
+
+    /// Resolution recorded for every page, in dots per inch (300 by default).
+    pub fn set_dpi(&mut self, dpi: u32) -> Result<(), ScanError> {
+        if dpi == 0 {
+            return Err(ScanError::InvalidParameter("Resolution must be at least 1 dpi"));
+        }
+        self.dpi = dpi;
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dpi(&self) -> u32 {
+        self.dpi
+    }
+
+    /// Adds an 8-bit grayscale page, compressed with LZW, and returns its
+    /// index.
+    ///
+    /// # Arguments
+    /// * `gray` - Grayscale pixels
+    /// * `width` - Image width
+    /// * `height` - Image height
+    pub fn add_gray_page(&mut self, gray: &[u8], width: usize, height: usize) -> Result<usize, ScanError> {
+        check_page(gray, width, height)?;
+        let data = encode_lzw(gray);
+        self.pages.push(Page { data, width, height, bits: 8, compression: LZW, photometric: BLACK_IS_ZERO });
+        Ok(self.pages.len() - 1)
+    }
+
+    /// Adds a black-and-white page and returns its index.
+    ///
+    /// # Arguments
+    /// * `gray` - Grayscale pixels, black below 128
+    /// * `width` - Image width
+    /// * `height` - Image height
+    /// * `compression` - LZW or CCITT Group 4
+    pub fn add_bilevel_page(
+        &mut self,
+        gray: &[u8],
+        width: usize,
+        height: usize,
+        compression: TiffCompression,
+    ) -> Result<usize, ScanError> {
+        check_page(gray, width, height)?;
+        let (data, compression) = match compression {
+            TiffCompression::Lzw => {
+                // One bit per pixel, 1 for black, rows padded to whole bytes
+                let mut packed = vec![0u8; width.div_ceil(8) * height];
+                for (line, out) in gray.chunks_exact(width).zip(packed.chunks_exact_mut(width.div_ceil(8))) {
+                    for (x, _) in line.iter().enumerate().filter(|&(_, &v)| v < BILEVEL_THRESHOLD) {
+                        out[x / 8] |= 0x80 >> (x % 8);
+                    }
+                }
+                (encode_lzw(&packed), LZW)
+            }
+            TiffCompression::Group4 => (encode_ccitt_g4(gray, width, height)?, GROUP4),
+        };
+        self.pages.push(Page { data, width, height, bits: 1, compression, photometric: WHITE_IS_ZERO });
+        Ok(self.pages.len() - 1)
+    }
+
+    pub fn remove_page(&mut self, index: usize) -> Result<(), ScanError> {
+        check_index(index, self.pages.len())?;
+        self.pages.remove(index);
+        Ok(())
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The document as TIFF file bytes; an error while there are no pages or
+    /// past the 4 GB a TIFF can address.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ScanError> {
+        if self.pages.is_empty() {
+            return Err(ScanError::InvalidParameter("A TIFF needs at least one page"));
+        }
+        let data_len: usize = self.pages.iter().map(|page| page.data.len()).sum();
+        let mut out = Vec::with_capacity(data_len + 256 * self.pages.len() + 8);
+        out.extend_from_slice(HEADER);
+        // Offset of the field pointing at the next directory
+        let mut next_directory = out.len();
+        out.extend_from_slice(&[0; 4]);
+
+        for (i, page) in self.pages.iter().enumerate() {
+            let strip = out.len();
+            out.extend_from_slice(&page.data);
+            // Directories start on a word boundary, followed by the resolution
+            out.resize(out.len() + out.len() % 2, 0);
+            let directory = out.len();
+            let mut entries: Vec<(u16, u16, u32, u32)> = vec![
+                // A page of a multi-page document
+                (254, LONG, 1, if self.pages.len() > 1 { 2 } else { 0 }),
+                (256, LONG, 1, page.width as u32),
+                (257, LONG, 1, page.height as u32),
+                (258, SHORT, 1, page.bits as u32),
+                (259, SHORT, 1, page.compression as u32),
+                (262, SHORT, 1, page.photometric as u32),
+                (273, LONG, 1, strip as u32),
+                (277, SHORT, 1, 1),
+                (278, LONG, 1, page.height as u32),
+                (279, LONG, 1, page.data.len() as u32),
+                // Both resolutions point at the rational after the directory
+                (282, RATIONAL, 1, 0),
+                (283, RATIONAL, 1, 0),
+            ];
+            if page.compression == GROUP4 {
+                // T6Options: no uncompressed mode
+                entries.push((293, LONG, 1, 0));
+            }
+            // Resolution in inches, and the page number out of the total
+            entries.extend([(296, SHORT, 1, 2), (297, SHORT, 2, i as u32 | (self.pages.len() as u32) << 16)]);
+            let resolution = (directory + 2 + 12 * entries.len() + 4) as u32;
+            for entry in entries.iter_mut().filter(|entry| entry.1 == RATIONAL) {
+                entry.3 = resolution;
+            }
+
+            let link = u32::try_from(directory).map_err(|_| ScanError::InvalidData("TIFF larger than 4 GB"))?;
+            out[next_directory..next_directory + 4].copy_from_slice(&link.to_le_bytes());
+            out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for (tag, kind, count, value) in entries {
+                out.extend_from_slice(&tag.to_le_bytes());
+                out.extend_from_slice(&kind.to_le_bytes());
+                out.extend_from_slice(&count.to_le_bytes());
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            next_directory = out.len();
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&self.dpi.to_le_bytes());
+            out.extend_from_slice(&1u32.to_le_bytes());
+        }
+        if u32::try_from(out.len()).is_err() {
+            return Err(ScanError::InvalidData("TIFF larger than 4 GB"));
+        }
+        Ok(out)
+    }
+}
+
+fn check_page(gray: &[u8], width: usize, height: usize) -> Result<(), ScanError> {
+    check_gray(gray, width, height)?;
+    if width == 0 || height == 0 {
+        return Err(ScanError::InvalidParameter("Image width and height must be at least 1"));
+    }
+    Ok(())
+}
+
+/// Encodes a single grayscale page as an LZW-compressed TIFF at 300 dpi; see
+/// `TiffDocument` for multi-page and bilevel files.
+///
+/// # Arguments
+/// * `gray` - Grayscale pixels
+/// * `width` - Image width
+/// * `height` - Image height
+///
+/// # Returns
+/// TIFF file bytes
+#[wasm_bindgen]
+pub fn encode_tiff(gray: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ScanError> {
+    let mut document = TiffDocument::new();
+    document.add_gray_page(gray, width, height)?;
+    document.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plain TIFF LZW decoder: the table widens the code once it holds
+    // 2^bits - 1 entries.
+    fn decode_lzw(data: &[u8]) -> Vec<u8> {
+        let (mut table, mut previous, mut out): (Vec<Vec<u8>>, Option<Vec<u8>>, Vec<u8>) = (vec![], None, vec![]);
+        let (mut pos, mut bits) = (0, 9);
+        loop {
+            let mut code = 0;
+            for _ in 0..bits {
+                code = code << 1 | (data[pos / 8] >> (7 - pos % 8) & 1) as usize;
+                pos += 1;
+            }
+            match code {
+                256 => {
+                    table = (0..258).map(|b| vec![b as u8]).collect();
+                    (previous, bits) = (None, 9);
+                    continue;
+                }
+                257 => return out,
+                _ => {}
+            }
+            let entry = table.get(code).cloned().unwrap_or_else(|| {
+                let mut entry = previous.clone().unwrap();
+                entry.push(entry[0]);
+                entry
+            });
+            if let Some(mut added) = previous.take() {
+                added.push(entry[0]);
+                table.push(added);
+            }
+            out.extend_from_slice(&entry);
+            previous = Some(entry);
+            if table.len() >= (1 << bits) - 1 && bits < 12 {
+                bits += 1;
+            }
+        }
+    }
+
+    // Tags of every directory, as tag -> (count, value or offset).
+    fn directories(tiff: &[u8]) -> Vec<Vec<(u16, (u32, u32))>> {
+        let u16_at = |at: usize| u16::from_le_bytes([tiff[at], tiff[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(tiff[at..at + 4].try_into().unwrap());
+        let (mut directories, mut at) = (vec![], u32_at(4) as usize);
+        while at != 0 {
+            assert_eq!(at % 2, 0);
+            let count = u16_at(at) as usize;
+            directories.push(
+                (0..count).map(|e| at + 2 + 12 * e).map(|e| (u16_at(e), (u32_at(e + 4), u32_at(e + 8)))).collect(),
+            );
+            at = u32_at(at + 2 + 12 * count) as usize;
+        }
+        directories
+    }
+
+    fn tag(directory: &[(u16, (u32, u32))], tag: u16) -> u32 {
+        directory.iter().find(|entry| entry.0 == tag).unwrap().1 .1
+    }
+
+    #[test]
+    fn test_tiff_document() {
+        let (width, height) = (37, 23);
+        let gray: Vec<u8> = (0..width * height).map(|i| ((i % width) * 6 + (i * 7919) % 13) as u8).collect();
+        // Enough distinct strings to clear the LZW table several times
+        let noise: Vec<u8> = (0..300 * 200u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let page: Vec<u8> =
+            (0..width * height).map(|i| if (i % width) / 4 % 2 == (i / width) / 5 % 2 { 0 } else { 255 }).collect();
+
+        let mut document = TiffDocument::new();
+        assert!(document.to_bytes().is_err());
+        assert!(document.set_dpi(0).is_err());
+        assert_eq!(document.set_dpi(200), Ok(()));
+        assert_eq!(document.add_gray_page(&gray, width, height), Ok(0));
+        assert_eq!(document.add_gray_page(&noise, 300, 200), Ok(1));
+        assert_eq!(document.add_bilevel_page(&page, width, height, TiffCompression::Lzw), Ok(2));
+        assert_eq!(document.add_bilevel_page(&page, width, height, TiffCompression::Group4), Ok(3));
+        assert!(document.add_gray_page(&gray, width, height + 1).is_err());
+        assert!(document.add_bilevel_page(&[], 0, 0, TiffCompression::Lzw).is_err());
+        let tiff = document.to_bytes().unwrap();
+        assert_eq!(&tiff[..4], HEADER);
+
+        let pages = directories(&tiff);
+        assert_eq!(pages.len(), 4);
+        let strip = |d: &[(u16, (u32, u32))]| &tiff[tag(d, 273) as usize..][..tag(d, 279) as usize];
+        let mut packed = vec![0u8; width.div_ceil(8) * height];
+        for (i, _) in page.iter().enumerate().filter(|&(_, &v)| v == 0) {
+            packed[i / width * width.div_ceil(8) + i % width / 8] |= 0x80 >> (i % width % 8);
+        }
+        assert_eq!(decode_lzw(strip(&pages[0])), gray);
+        assert_eq!(decode_lzw(strip(&pages[1])), noise);
+        assert_eq!(decode_lzw(strip(&pages[2])), packed);
+        assert_eq!(strip(&pages[3]), encode_ccitt_g4(&page, width, height).unwrap());
+        for (i, directory) in pages.iter().enumerate() {
+            assert!(directory.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert_eq!((tag(directory, 254), tag(directory, 297)), (2, i as u32 | 4 << 16));
+            let resolution = tag(directory, 282) as usize;
+            assert_eq!(
+                (tag(directory, 283) as usize, &tiff[resolution..resolution + 8]),
+                (resolution, &[200, 0, 0, 0, 1, 0, 0, 0][..])
+            );
+        }
+        let formats: Vec<_> = pages.iter().map(|d| (tag(d, 258), tag(d, 259), tag(d, 262))).collect();
+        assert_eq!(formats, [(8, 5, 1), (8, 5, 1), (1, 5, 0), (1, 4, 0)]);
+
+        assert!(document.remove_page(4).is_err());
+        assert_eq!((document.remove_page(1), document.page_count()), (Ok(()), 3));
+        let single = encode_tiff(&gray, width, height).unwrap();
+        assert_eq!(tag(&directories(&single)[0], 254), 0);
+        assert_eq!(decode_lzw(&encode_lzw(&[])), []);
+    }
+}
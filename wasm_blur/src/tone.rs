@@ -0,0 +1,207 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_len, image_len, ScanError};
+
+#[cfg(target_family = "wasm")]
+use crate::dispatch::simd_enabled;
+#[cfg(target_family = "wasm")]
+use crate::simd::*;
+
+// Maps the red, green and blue channels of every pixel through their LUT,
+// leaving alpha alone.
+pub(crate) fn apply_luts(rgba: &mut [u8], luts: [&[u8]; 3]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { apply_luts_simd(rgba, luts) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for px in rgba.chunks_exact_mut(4).skip(done) {
+        for (c, lut) in px[..3].iter_mut().zip(luts) {
+            *c = lut[*c as usize];
+        }
+    }
+}
+
+// Maps every byte of a grayscale image through `lut`.
+fn apply_lut_gray(gray: &mut [u8], lut: &[u8; 256]) {
+    #[cfg(target_family = "wasm")]
+    let done = if simd_enabled() { unsafe { apply_lut_gray_simd(gray, lut) } } else { 0 };
+    #[cfg(not(target_family = "wasm"))]
+    let done = 0;
+
+    for v in gray.iter_mut().skip(done) {
+        *v = lut[*v as usize];
+    }
+}
+
+// Looks up all 16 bytes of `index` in a 256-entry table held as 16 vectors.
+// Each swizzle covers one 16-entry slice; indices outside it wrap to 16 or more
+// after the subtraction, which the swizzle turns into 0, so OR-ing the slices
+// gives the lookup without a gather.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn lookup(table: &[v128; 16], index: v128) -> v128 {
+    let mut out = u8x16_splat(0);
+    for (k, slice) in table.iter().enumerate() {
+        let local = u8x16_sub(index, u8x16_splat(16 * k as u8));
+        out = v128_or(out, i8x16_swizzle(*slice, local));
+    }
+    out
+}
+
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn load_table(lut: &[u8]) -> [v128; 16] {
+    let mut table = [u8x16_splat(0); 16];
+    for (k, slice) in table.iter_mut().enumerate() {
+        *slice = v128_load(lut.as_ptr().add(16 * k) as *const v128);
+    }
+    table
+}
+
+// 4 pixels per iteration. A single curve for all channels needs one lookup per
+// vector; separate curves are looked up one by one and blended per channel.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn apply_luts_simd(rgba: &mut [u8], luts: [&[u8]; 3]) -> usize {
+    let pixels = rgba.len() / 4;
+    let shared = luts[1].as_ptr() == luts[0].as_ptr() && luts[2].as_ptr() == luts[0].as_ptr();
+    let tables = [load_table(luts[0]), load_table(luts[1]), load_table(luts[2])];
+    let rgb = u32x4_splat(0x00ff_ffff);
+    let channel = [u32x4_splat(0xff), u32x4_splat(0xff00), u32x4_splat(0xff_0000)];
+    let mut x = 0;
+    while x + 4 <= pixels {
+        let ptr = rgba.as_mut_ptr().add(x * 4) as *mut v128;
+        let v = v128_load(ptr);
+        let mapped = if shared {
+            v128_bitselect(lookup(&tables[0], v), v, rgb)
+        } else {
+            let mut out = v;
+            for (table, mask) in tables.iter().zip(channel) {
+                out = v128_bitselect(lookup(table, v), out, mask);
+            }
+            out
+        };
+        v128_store(ptr, mapped);
+        x += 4;
+    }
+    x
+}
+
+// 16 pixels per iteration.
+#[cfg(target_family = "wasm")]
+#[target_feature(enable = "simd128")]
+unsafe fn apply_lut_gray_simd(gray: &mut [u8], lut: &[u8; 256]) -> usize {
+    let table = load_table(lut);
+    let mut x = 0;
+    while x + 16 <= gray.len() {
+        let ptr = gray.as_mut_ptr().add(x) as *mut v128;
+        v128_store(ptr, lookup(&table, v128_load(ptr)));
+        x += 16;
+    }
+    x
+}
+
+// Gamma LUT: `out = in^(1 / gamma)` on the 0..1 range, so gamma above 1
+// brightens mid-tones and below 1 darkens them. Black and white stay fixed.
+fn gamma_lut(gamma: f32) -> [u8; 256] {
+    let exponent = 1.0 / gamma;
+    let mut lut = [0u8; 256];
+    for (i, v) in lut.iter_mut().enumerate() {
+        *v = ((i as f32 / 255.0).powf(exponent) * 255.0).round() as u8;
+    }
+    lut
+}
+
+// Brightness/contrast LUT: contrast scales the distance to mid-grey, then
+// brightness shifts by a fraction of the full range. Results clip to 0..255.
+fn brightness_contrast_lut(brightness: f32, contrast: f32) -> [u8; 256] {
+    let mut lut = [0u8; 256];
+    for (i, v) in lut.iter_mut().enumerate() {
+        let adjusted = (i as f32 - 127.5) * contrast + 127.5 + brightness * 255.0;
+        *v = adjusted.round().clamp(0.0, 255.0) as u8;
+    }
+    lut
+}
+
+// Applies one LUT to a grayscale or RGBA image, taking the layout from the length.
+fn apply_to_image(image: &[u8], width: usize, height: usize, lut: &[u8; 256]) -> Result<Vec<u8>, ScanError> {
+    let pixels = image_len(width, height, 1)?;
+    let mut out = image.to_vec();
+    if image.len() == pixels {
+        apply_lut_gray(&mut out, lut);
+    } else {
+        check_len(image.len(), image_len(width, height, 4)?, "width * height or width * height * 4")?;
+        apply_luts(&mut out, [lut, lut, lut]);
+    }
+    Ok(out)
+}
+
+/// Gamma-corrects a grayscale or RGBA image.
+///
+/// # Arguments
+/// * `image` - Grayscale or RGBA pixels; the layout is taken from the length
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `gamma` - Above 1 brightens mid-tones, below 1 darkens them; 1 keeps them
+///
+/// # Returns
+/// Adjusted image as Vec<u8>, in the input layout. Alpha is kept.
+#[wasm_bindgen]
+pub fn adjust_gamma(image: &[u8], width: usize, height: usize, gamma: f32) -> Result<Vec<u8>, ScanError> {
+    if gamma.is_nan() || gamma <= 0.0 {
+        return Err(ScanError::InvalidParameter("Gamma must be greater than 0"));
+    }
+    apply_to_image(image, width, height, &gamma_lut(gamma))
+}
+
+/// Adjusts brightness and contrast of a grayscale or RGBA image, like the CSS
+/// `brightness`/`contrast` filters but without a canvas.
+///
+/// # Arguments
+/// * `image` - Grayscale or RGBA pixels; the layout is taken from the length
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `brightness` - Shift as a fraction of the full range, -1..1; 0 keeps it
+/// * `contrast` - Multiplier of the distance to mid-grey; 1 keeps it, 0 gives
+///   flat grey
+///
+/// # Returns
+/// Adjusted image as Vec<u8>, in the input layout. Alpha is kept.
+#[wasm_bindgen]
+pub fn adjust_brightness_contrast(
+    image: &[u8],
+    width: usize,
+    height: usize,
+    brightness: f32,
+    contrast: f32,
+) -> Result<Vec<u8>, ScanError> {
+    let lut = brightness_contrast_lut(brightness.clamp(-1.0, 1.0), contrast.max(0.0));
+    apply_to_image(image, width, height, &lut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma_brightness_contrast() {
+        let gray: Vec<u8> = (0..=255).collect();
+        assert_eq!(adjust_gamma(&gray, 16, 16, 1.0).unwrap(), gray);
+        let bright = adjust_gamma(&gray, 16, 16, 2.2).unwrap();
+        assert_eq!((bright[0], bright[64], bright[255]), (0, 136, 255));
+
+        assert_eq!(adjust_brightness_contrast(&gray, 16, 16, 0.0, 1.0).unwrap(), gray);
+        let adjusted = adjust_brightness_contrast(&gray, 16, 16, 0.1, 2.0).unwrap();
+        assert_eq!((adjusted[0], adjusted[100], adjusted[128], adjusted[200]), (0, 98, 154, 255));
+        assert!(adjust_brightness_contrast(&gray, 16, 16, 0.0, 0.0).unwrap().iter().all(|&v| v == 128));
+
+        // RGBA gets the same curve on every colour channel; alpha is kept
+        let rgba: Vec<u8> = gray.iter().flat_map(|&v| [v, v, v, 77]).collect();
+        let out = adjust_gamma(&rgba, 16, 16, 2.2).unwrap();
+        assert!(out.chunks_exact(4).zip(&bright).all(|(p, &v)| p == [v, v, v, 77]));
+    }
+}
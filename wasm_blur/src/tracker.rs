@@ -0,0 +1,292 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{to_quad, ScanError};
+
+// Residual (in pixels) at which a corner's observation confidence drops to 1/e.
+const RESIDUAL_SCALE: f32 = 12.0;
+// Per-frame confidence decay applied when the detector finds no quad.
+const MISSING_DECAY: f32 = 0.6;
+// Smallest frame interval used for velocity, guards against duplicate timestamps.
+const MIN_DT_MS: f64 = 1.0;
+// Default mean corner jump (px) from the prediction beyond which a detection is
+// an outlier, e.g. a table edge picked up for a single frame.
+const DEFAULT_OUTLIER_PX: f32 = 50.0;
+// Consecutive outliers after which the document is taken to have really moved
+// and the tracker restarts from the new detection.
+const MAX_REJECTED_FRAMES: u32 = 3;
+// Default largest corner movement (px) between frames that still counts as
+// holding still, and the number of such frames that make the quad stable.
+const DEFAULT_STABLE_PX: f32 = 4.0;
+const DEFAULT_STABLE_FRAMES: u32 = 10;
+
+#[derive(Clone, Copy, Default)]
+struct CornerState {
+    x: f32,
+    y: f32,
+    vx: f32, // px per second
+    vy: f32,
+    confidence: f32,
+}
+
+/// Stateful tracker for the four corners of a detected document quad.
+/// Smooths positions across frames and exposes per-corner velocity and confidence
+/// so UIs can ease corner handles toward new positions and fade uncertain ones.
+#[wasm_bindgen]
+pub struct QuadTracker {
+    corners: [CornerState; 4],
+    initialized: bool,
+    last_timestamp: f64,
+    smoothing: f32,
+    outlier_px: f32,
+    rejected_frames: u32,
+    stable_px: f32,
+    required_stable_frames: u32,
+    stable_frames: u32,
+}
+
+#[wasm_bindgen]
+impl QuadTracker {
+    /// Creates a tracker. `smoothing` is the weight (0..1) kept from the previous
+    /// estimate; 0 follows detections exactly, values near 1 move slowly.
+    #[wasm_bindgen(constructor)]
+    pub fn new(smoothing: f32) -> QuadTracker {
+        QuadTracker {
+            corners: [CornerState::default(); 4],
+            initialized: false,
+            last_timestamp: 0.0,
+            smoothing: smoothing.clamp(0.0, 0.99),
+            outlier_px: DEFAULT_OUTLIER_PX,
+            rejected_frames: 0,
+            stable_px: DEFAULT_STABLE_PX,
+            required_stable_frames: DEFAULT_STABLE_FRAMES,
+            stable_frames: 0,
+        }
+    }
+
+    /// Mean corner distance (px) from the predicted quad beyond which a
+    /// detection is rejected as an outlier and handled like a missed frame
+    /// (default 50). After 3 rejections in a row the tracker accepts the new
+    /// position and restarts from it. 0 disables the rejection.
+    pub fn set_outlier_threshold(&mut self, pixels: f32) {
+        self.outlier_px = pixels.max(0.0);
+    }
+
+    /// The quad is stable once `frames` detections in a row moved no corner by
+    /// more than `tolerance_px` (defaults 10 frames, 4 px), e.g. to trigger
+    /// auto-capture.
+    pub fn set_stability(&mut self, tolerance_px: f32, frames: u32) {
+        self.stable_px = tolerance_px.max(0.0);
+        self.required_stable_frames = frames.max(1);
+    }
+
+    /// Feeds a new detection. `corners` holds 8 values: x,y for top-left,
+    /// top-right, bottom-right and bottom-left; `timestamp_ms` is the frame time.
+    pub fn update(&mut self, corners: &[f32], timestamp_ms: f64) -> Result<(), ScanError> {
+        let corners = to_quad(corners)?;
+
+        if self.initialized && self.is_outlier(&corners, timestamp_ms) {
+            if self.rejected_frames < MAX_REJECTED_FRAMES {
+                self.update_missing(timestamp_ms);
+                self.rejected_frames += 1;
+                return Ok(());
+            }
+            self.initialized = false;
+        }
+        self.rejected_frames = 0;
+
+        if !self.initialized {
+            for (i, state) in self.corners.iter_mut().enumerate() {
+                *state = CornerState {
+                    x: corners[2 * i],
+                    y: corners[2 * i + 1],
+                    vx: 0.0,
+                    vy: 0.0,
+                    confidence: 1.0,
+                };
+            }
+            self.initialized = true;
+            self.last_timestamp = timestamp_ms;
+            self.stable_frames = 1;
+            return Ok(());
+        }
+
+        let still = self
+            .corners
+            .iter()
+            .enumerate()
+            .all(|(i, state)| (corners[2 * i] - state.x).hypot(corners[2 * i + 1] - state.y) <= self.stable_px);
+        self.stable_frames = if still { self.stable_frames.saturating_add(1) } else { 1 };
+
+        let dt_s = ((timestamp_ms - self.last_timestamp).max(MIN_DT_MS) / 1000.0) as f32;
+        let keep = self.smoothing;
+        let take = 1.0 - keep;
+
+        for (i, state) in self.corners.iter_mut().enumerate() {
+            let ox = corners[2 * i];
+            let oy = corners[2 * i + 1];
+
+            // Confidence drops when the observation disagrees with the prediction
+            let px = state.x + state.vx * dt_s;
+            let py = state.y + state.vy * dt_s;
+            let residual = ((ox - px).powi(2) + (oy - py).powi(2)).sqrt();
+            let observed = (-residual / RESIDUAL_SCALE).exp();
+            state.confidence = keep * state.confidence + take * observed;
+
+            let nx = keep * state.x + take * ox;
+            let ny = keep * state.y + take * oy;
+            state.vx = keep * state.vx + take * (nx - state.x) / dt_s;
+            state.vy = keep * state.vy + take * (ny - state.y) / dt_s;
+            state.x = nx;
+            state.y = ny;
+        }
+
+        self.last_timestamp = timestamp_ms;
+        Ok(())
+    }
+
+    /// Records a frame where no quad was detected: corners keep their position,
+    /// velocities settle and confidences fade.
+    pub fn update_missing(&mut self, timestamp_ms: f64) {
+        self.stable_frames = 0;
+        if !self.initialized {
+            return;
+        }
+        for state in self.corners.iter_mut() {
+            state.confidence *= MISSING_DECAY;
+            state.vx *= MISSING_DECAY;
+            state.vy *= MISSING_DECAY;
+        }
+        self.last_timestamp = timestamp_ms;
+    }
+
+    /// Clears all tracked state.
+    pub fn reset(&mut self) {
+        self.corners = [CornerState::default(); 4];
+        self.initialized = false;
+        self.rejected_frames = 0;
+        self.stable_frames = 0;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Detections rejected as outliers in a row; non-zero when the last one was.
+    #[wasm_bindgen(getter)]
+    pub fn rejected_frames(&self) -> u32 {
+        self.rejected_frames
+    }
+
+    /// Detections in a row that held still (see `set_stability`).
+    #[wasm_bindgen(getter)]
+    pub fn stable_frames(&self) -> u32 {
+        self.stable_frames
+    }
+
+    /// True once the quad has held still for the configured number of frames.
+    pub fn is_stable(&self) -> bool {
+        self.stable_frames >= self.required_stable_frames
+    }
+
+    /// Smoothed corner positions as 8 values (x,y per corner).
+    pub fn corners(&self) -> Vec<f32> {
+        self.corners.iter().flat_map(|c| [c.x, c.y]).collect()
+    }
+
+    /// Smoothed corner velocities in px/s as 8 values (vx,vy per corner).
+    pub fn velocities(&self) -> Vec<f32> {
+        self.corners.iter().flat_map(|c| [c.vx, c.vy]).collect()
+    }
+
+    /// Per-corner confidence (0..1) as 4 values.
+    pub fn confidences(&self) -> Vec<f32> {
+        self.corners.iter().map(|c| c.confidence).collect()
+    }
+
+    /// Everything a UI needs to animate the handles in one call:
+    /// 5 values per corner (x, y, vx, vy, confidence).
+    pub fn animation_data(&self) -> Vec<f32> {
+        self.corners
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.vx, c.vy, c.confidence])
+            .collect()
+    }
+}
+
+impl QuadTracker {
+    // Whether a detection lies too far from the quad predicted for its frame.
+    fn is_outlier(&self, corners: &[f32; 8], timestamp_ms: f64) -> bool {
+        if self.outlier_px <= 0.0 {
+            return false;
+        }
+        let dt_s = ((timestamp_ms - self.last_timestamp).max(MIN_DT_MS) / 1000.0) as f32;
+        let jump: f32 = self
+            .corners
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (corners[2 * i] - s.x - s.vx * dt_s).hypot(corners[2 * i + 1] - s.y - s.vy * dt_s))
+            .sum();
+        jump / 4.0 > self.outlier_px
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: [f32; 8] = [10.0, 10.0, 110.0, 10.0, 110.0, 150.0, 10.0, 150.0];
+
+    #[test]
+    fn test_tracker_velocity_and_confidence() {
+        let mut tracker = QuadTracker::new(0.5);
+        tracker.update(&QUAD, 0.0).unwrap();
+        assert_eq!(tracker.corners(), QUAD.to_vec());
+
+        // Move everything 10px right over 100ms
+        let moved: Vec<f32> = QUAD.iter().enumerate().map(|(i, &v)| if i % 2 == 0 { v + 10.0 } else { v }).collect();
+        tracker.update(&moved, 100.0).unwrap();
+        let velocities = tracker.velocities();
+        assert!(velocities[0] > 0.0);
+        assert_eq!(velocities[1], 0.0);
+
+        let before = tracker.confidences()[0];
+        tracker.update_missing(133.0);
+        assert!(tracker.confidences()[0] < before);
+        assert_eq!(tracker.animation_data().len(), 20);
+    }
+
+    #[test]
+    fn test_tracker_outliers_and_stability() {
+        let shifted =
+            |dx: f32| -> Vec<f32> { QUAD.iter().enumerate().map(|(i, &v)| if i % 2 == 0 { v + dx } else { v }).collect() };
+        let mut tracker = QuadTracker::new(0.5);
+        tracker.set_stability(2.0, 5);
+        for frame in 0..5 {
+            assert!(!tracker.is_stable());
+            // Sub-pixel jitter holds still
+            tracker.update(&shifted(if frame % 2 == 0 { 0.5 } else { -0.5 }), frame as f64 * 33.0).unwrap();
+        }
+        assert!(tracker.is_stable() && tracker.stable_frames() == 5);
+
+        // A one-frame jump to a wrong quad is ignored, but breaks the stability
+        let before = tracker.corners();
+        tracker.update(&shifted(200.0), 165.0).unwrap();
+        assert_eq!((tracker.rejected_frames(), tracker.corners()), (1, before));
+        assert!(!tracker.is_stable());
+        tracker.update(&shifted(0.0), 198.0).unwrap();
+        assert_eq!((tracker.rejected_frames(), tracker.stable_frames()), (0, 1));
+
+        // A document that really moved is accepted after three rejections
+        for frame in 0..4 {
+            tracker.update(&shifted(200.0), 231.0 + frame as f64 * 33.0).unwrap();
+        }
+        assert_eq!((tracker.rejected_frames(), tracker.corners()), (0, shifted(200.0)));
+        tracker.set_outlier_threshold(0.0);
+        tracker.update(&shifted(0.0), 400.0).unwrap();
+        assert_eq!(tracker.corners()[0], 110.0);
+    }
+}
@@ -0,0 +1,224 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::dilation::dilate;
+use crate::error::{check_gray, check_kernel_size, ScanError};
+use crate::gaussian_blur::blur;
+use crate::gradient_calculation::calculate_gradients;
+use crate::non_maximum_suppression::non_maximum_suppression;
+
+// Largest change of the image mean `blur` may make, in gray levels, as the
+// fixed-point passes truncate.
+const BLUR_MEAN_TOLERANCE: f32 = 1.5;
+// Generated test images: up to 70 × 40, wider than a few 16-pixel SIMD
+// vectors so both the vector loops and their remainders run.
+const MAX_WIDTH: u32 = 70;
+const MAX_HEIGHT: u32 = 40;
+// Largest generated blur and dilation kernel.
+const MAX_KERNEL_SIZE: u32 = 15;
+
+/// Properties the edge pipeline stages keep on every input, whatever SIMD or
+/// threaded path computes them.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Invariant {
+    /// `blur` keeps the image mean within 1.5 gray levels. This needs a flat
+    /// border at least half the kernel wide: replicated edge pixels weigh
+    /// more than the others
+    BlurMean = 0,
+    /// `dilate` never lowers a pixel
+    DilationExtensive = 1,
+    /// `dilate` with size 1 returns its input, so applying it again changes
+    /// nothing
+    DilationIdentity = 2,
+    /// `non_maximum_suppression` keeps every pixel between 0 and its gradient
+    /// magnitude
+    NmsBounded = 3,
+}
+
+const INVARIANTS: [Invariant; 4] =
+    [Invariant::BlurMean, Invariant::DilationExtensive, Invariant::DilationIdentity, Invariant::NmsBounded];
+
+fn mean(values: &[u8]) -> f32 {
+    values.iter().map(|&v| v as u64).sum::<u64>() as f32 / values.len().max(1) as f32
+}
+
+// Checks the invariant through the exported functions, so it covers exactly
+// what callers get on this build.
+fn holds(invariant: Invariant, gray: &[u8], width: usize, height: usize, kernel_size: usize) -> bool {
+    match invariant {
+        Invariant::BlurMean => blur(gray, width, height, kernel_size, 0.0)
+            .is_ok_and(|blurred| (mean(&blurred) - mean(gray)).abs() <= BLUR_MEAN_TOLERANCE),
+        Invariant::DilationExtensive => dilate(gray, width, height, kernel_size)
+            .is_ok_and(|dilated| dilated.iter().zip(gray).all(|(&d, &v)| d >= v)),
+        Invariant::DilationIdentity => dilate(gray, width, height, 1).is_ok_and(|dilated| {
+            dilated == gray && dilate(&dilated, width, height, 1).is_ok_and(|twice| twice == dilated)
+        }),
+        Invariant::NmsBounded => {
+            let Ok(gradients) = calculate_gradients(gray, width, height) else {
+                return false;
+            };
+            let (dx, dy): (Vec<i16>, Vec<i16>) = gradients.chunks_exact(2).map(|g| (g[0], g[1])).unzip();
+            [false, true].into_iter().all(|l2_gradient| {
+                non_maximum_suppression(&dx, &dy, width, height, l2_gradient).is_ok_and(|suppressed| {
+                    suppressed.iter().zip(dx.iter().zip(&dy)).all(|(&s, (&gx, &gy))| {
+                        let (gx, gy) = (gx as f32, gy as f32);
+                        let magnitude = if l2_gradient { (gx * gx + gy * gy).sqrt() } else { gx.abs() + gy.abs() };
+                        (0.0..=magnitude).contains(&s)
+                    })
+                })
+            })
+        }
+    }
+}
+
+/// Checks one invariant on one image, e.g. a camera frame that showed a
+/// problem with a SIMD or threaded build.
+///
+/// # Arguments
+/// * `invariant` - Property to check
+/// * `gray` - Grayscale image
+/// * `width` - Image width
+/// * `height` - Image height
+/// * `kernel_size` - Odd blur or dilation kernel size; unused by `NmsBounded`
+///   and `DilationIdentity`
+///
+/// # Returns
+/// Whether the invariant holds
+#[wasm_bindgen]
+pub fn check_invariant(
+    invariant: Invariant,
+    gray: &[u8],
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+) -> Result<bool, ScanError> {
+    check_gray(gray, width, height)?;
+    if invariant == Invariant::BlurMean || invariant == Invariant::DilationExtensive {
+        check_kernel_size(kernel_size)?;
+    }
+    Ok(holds(invariant, gray, width, height, kernel_size))
+}
+
+// A generated test case: a random size and kernel, and an image of flat
+// rectangles (edges for NMS) over noise of a random strength, inside a flat
+// border half the kernel wide (see `Invariant::BlurMean`).
+struct Case {
+    width: usize,
+    height: usize,
+    kernel_size: usize,
+    gray: Vec<u8>,
+}
+
+impl Case {
+    fn generate(seed: u32) -> Case {
+        let mut state = seed;
+        let mut next = |range: u32| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) % range
+        };
+        let (width, height) = (1 + next(MAX_WIDTH) as usize, 1 + next(MAX_HEIGHT) as usize);
+        let kernel_size = 1 + 2 * next(MAX_KERNEL_SIZE / 2 + 1) as usize;
+        let noise = next(64) as i32;
+        let rects: Vec<[u32; 5]> =
+            (0..next(5)).map(|_| [next(width as u32), next(height as u32), next(40), next(30), next(256)]).collect();
+        let border = (kernel_size / 2) as u32;
+        let gray = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as u32, (i / width) as u32);
+                if x < border || y < border || x + border >= width as u32 || y + border >= height as u32 {
+                    return 128;
+                }
+                let base = rects
+                    .iter()
+                    .rev()
+                    .find(|r| (r[0]..r[0] + r[2]).contains(&x) && (r[1]..r[1] + r[3]).contains(&y))
+                    .map_or(128, |r| r[4] as i32);
+                (base + next(2 * noise as u32 + 1) as i32 - noise).clamp(0, 255) as u8
+            })
+            .collect();
+        Case { width, height, kernel_size, gray }
+    }
+}
+
+/// Outcome of `verify_invariants`: the number of cases run and the first
+/// case that broke an invariant, with its image so it can be kept as a
+/// regression test.
+#[wasm_bindgen]
+pub struct VerifyReport {
+    cases: usize,
+    failure: Option<(Invariant, Case)>,
+}
+
+#[wasm_bindgen]
+impl VerifyReport {
+    #[wasm_bindgen(getter)]
+    pub fn cases(&self) -> usize {
+        self.cases
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// The broken invariant, if any.
+    pub fn failed_invariant(&self) -> Option<Invariant> {
+        self.failure.as_ref().map(|(invariant, _)| *invariant)
+    }
+
+    /// Width, height and kernel size of the failing case; empty if all passed.
+    pub fn failed_params(&self) -> Vec<usize> {
+        self.failure.as_ref().map_or_else(Vec::new, |(_, case)| vec![case.width, case.height, case.kernel_size])
+    }
+
+    /// Grayscale image of the failing case; empty if all passed.
+    pub fn failed_image(&self) -> Vec<u8> {
+        self.failure.as_ref().map_or_else(Vec::new, |(_, case)| case.gray.clone())
+    }
+}
+
+/// Checks every `Invariant` on pseudo-random images of random sizes and
+/// kernels, stopping at the first violation. The same seed gives the same
+/// cases on every build, so a SIMD or threaded change can be compared with a
+/// scalar build case by case.
+///
+/// # Arguments
+/// * `seed` - Seed of the generated cases
+/// * `cases` - Number of images to check
+///
+/// # Returns
+/// A report with the first failing case, if any
+#[wasm_bindgen]
+pub fn verify_invariants(seed: u32, cases: usize) -> VerifyReport {
+    for n in 0..cases {
+        let case = Case::generate(seed.wrapping_add(n as u32).wrapping_mul(2654435761));
+        if let Some(invariant) = INVARIANTS
+            .into_iter()
+            .find(|&invariant| !holds(invariant, &case.gray, case.width, case.height, case.kernel_size))
+        {
+            return VerifyReport { cases: n + 1, failure: Some((invariant, case)) };
+        }
+    }
+    VerifyReport { cases, failure: None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_invariants() {
+        let report = verify_invariants(7, 200);
+        assert!(report.passed(), "{:?} {:?}", report.failed_invariant(), report.failed_params());
+        assert_eq!((report.cases(), report.failed_image().len()), (200, 0));
+
+        let gray = [10, 200, 30, 40, 250, 60];
+        for invariant in INVARIANTS {
+            assert_eq!(check_invariant(invariant, &gray, 3, 2, 3), Ok(true), "{invariant:?}");
+        }
+        assert!(check_invariant(Invariant::BlurMean, &gray, 3, 2, 4).is_err());
+        assert!(check_invariant(Invariant::NmsBounded, &gray, 4, 2, 3).is_err());
+    }
+}
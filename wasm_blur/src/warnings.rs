@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+
+/// Adjustments the crate made to a request instead of failing. They are reported
+/// next to the results (`ScanContext::warnings`, `ExportedPages::warnings`) so
+/// integrators notice them during development.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanWarning {
+    /// An even (or zero) blur kernel size was rounded up to the next odd size
+    KernelSizeRoundedUp = 0,
+    /// Canny thresholds were given as low > high and have been swapped
+    ThresholdsSwapped = 1,
+    /// The page corners don't form a valid quad, so the page was exported as
+    /// captured
+    DegenerateQuad = 2,
+}
+
+const ALL: [ScanWarning; 3] =
+    [ScanWarning::KernelSizeRoundedUp, ScanWarning::ThresholdsSwapped, ScanWarning::DegenerateQuad];
+
+impl ScanWarning {
+    fn message(self) -> &'static str {
+        match self {
+            ScanWarning::KernelSizeRoundedUp => "kernel size rounded up to the next odd size",
+            ScanWarning::ThresholdsSwapped => "low threshold above high threshold; thresholds swapped",
+            ScanWarning::DegenerateQuad => "corners don't form a valid quad; page exported as captured",
+        }
+    }
+}
+
+/// Human-readable description of `warning`, e.g. for `console.warn`.
+#[wasm_bindgen]
+pub fn scan_warning_message(warning: ScanWarning) -> String {
+    warning.message().to_string()
+}
+
+// Set of warnings attached to a result, one bit per `ScanWarning`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Warnings(u32);
+
+impl Warnings {
+    pub(crate) fn set(&mut self, warning: ScanWarning, raised: bool) {
+        if raised {
+            self.0 |= 1 << warning as u32;
+        } else {
+            self.0 &= !(1 << warning as u32);
+        }
+    }
+
+    pub(crate) fn contains(self, warning: ScanWarning) -> bool {
+        self.0 & (1 << warning as u32) != 0
+    }
+
+    pub(crate) fn to_vec(self) -> Vec<ScanWarning> {
+        ALL.into_iter().filter(|&w| self.contains(w)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ScanContext;
+
+    #[test]
+    fn test_adjustments_are_reported() {
+        let mut context = ScanContext::new(16, 16);
+        assert!(context.warnings().is_empty());
+        context.set_blur(4, 0.0);
+        context.set_thresholds(200.0, 75.0);
+        assert_eq!(context.warnings(), [ScanWarning::KernelSizeRoundedUp, ScanWarning::ThresholdsSwapped]);
+        // Runs with the adjusted settings instead of panicking on the even kernel
+        context.process_gray_frame(&vec![0u8; 16 * 16]).unwrap();
+        // Valid settings clear the warnings again
+        context.set_blur(5, 0.0);
+        context.set_thresholds(75.0, 200.0);
+        assert!(context.warnings().is_empty());
+
+        #[cfg(feature = "session")]
+        {
+            use crate::session::{PageSettings, ScanSession};
+            let mut session = ScanSession::new();
+            session.add_page(&[255u8; 8 * 8 * 4], 8, 8).unwrap();
+            session.add_page(&[255u8; 8 * 8 * 4], 8, 8).unwrap();
+            let mut collinear = PageSettings::new();
+            collinear.set_corners(&[0.0, 0.0, 2.0, 2.0, 4.0, 4.0, 6.0, 6.0]).unwrap();
+            let exported = session.export_all(vec![PageSettings::new(), collinear]);
+            assert!(exported.warnings(0).unwrap().is_empty());
+            assert_eq!(exported.warnings(1).unwrap(), [ScanWarning::DegenerateQuad]);
+            assert_eq!(exported.width(1).unwrap(), 8);
+        }
+        assert!(scan_warning_message(ScanWarning::DegenerateQuad).contains("quad"));
+    }
+}
@@ -0,0 +1,379 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, output_len, ScanError};
+
+/// How destination pixels that map outside the source image are filled.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutOfBoundsPolicy {
+    /// Fill with the caller-supplied `fill_color`.
+    Constant = 0,
+    /// Clamp to the nearest source edge pixel (what the JS warp does today).
+    Replicate = 1,
+    /// Fill with the paper colour estimated from the warped page itself.
+    PaperFill = 2,
+    /// Replicate the colour but set alpha to 0 so the integrator can composite.
+    MarkInAlpha = 3,
+}
+
+// Samples within half a pixel of the border still count as inside, otherwise
+// the outermost row/column of an exact full-frame warp would be filled.
+const EDGE_SLACK: f64 = 0.5;
+
+// Fraction of the brightest in-bounds pixels averaged for the paper colour.
+const PAPER_FRACTION: f64 = 0.1;
+
+#[inline]
+fn unpack_rgba(color: u32) -> [u8; 4] {
+    [(color >> 24) as u8, (color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+#[inline]
+pub(crate) fn sample_bilinear(src: &[u8], width: usize, height: usize, sx: f64, sy: f64) -> [u8; 4] {
+    let max_x = (width - 1) as f64;
+    let max_y = (height - 1) as f64;
+    let cx = sx.clamp(0.0, max_x);
+    let cy = sy.clamp(0.0, max_y);
+
+    let x0 = cx as usize;
+    let y0 = cy as usize;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = cx - x0 as f64;
+    let fy = cy - y0 as f64;
+
+    let w00 = (1.0 - fx) * (1.0 - fy);
+    let w10 = fx * (1.0 - fy);
+    let w01 = (1.0 - fx) * fy;
+    let w11 = fx * fy;
+
+    let i00 = (y0 * width + x0) * 4;
+    let i10 = (y0 * width + x1) * 4;
+    let i01 = (y1 * width + x0) * 4;
+    let i11 = (y1 * width + x1) * 4;
+
+    let mut out = [0u8; 4];
+    for (c, value) in out.iter_mut().enumerate() {
+        let v = src[i00 + c] as f64 * w00
+            + src[i10 + c] as f64 * w10
+            + src[i01 + c] as f64 * w01
+            + src[i11 + c] as f64 * w11;
+        *value = (v + 0.5) as u8;
+    }
+    out
+}
+
+// Average colour of the brightest in-bounds pixels. Falls back to white when
+// the whole destination maps outside the source.
+fn estimate_paper_color(dst: &[u8], outside: &[bool]) -> [u8; 4] {
+    let mut lumas: Vec<(u32, usize)> = outside
+        .iter()
+        .enumerate()
+        .filter(|(_, &out)| !out)
+        .map(|(i, _)| {
+            let p = i * 4;
+            let luma = 299 * dst[p] as u32 + 587 * dst[p + 1] as u32 + 114 * dst[p + 2] as u32;
+            (luma, p)
+        })
+        .collect();
+
+    if lumas.is_empty() {
+        return [255, 255, 255, 255];
+    }
+
+    let take = ((lumas.len() as f64 * PAPER_FRACTION).ceil() as usize).max(1);
+    let split = lumas.len() - take;
+    lumas.select_nth_unstable_by_key(split, |&(luma, _)| luma);
+
+    let mut sums = [0u64; 3];
+    for &(_, p) in &lumas[split..] {
+        for c in 0..3 {
+            sums[c] += dst[p + c] as u64;
+        }
+    }
+    let n = take as u64;
+    [
+        ((sums[0] + n / 2) / n) as u8,
+        ((sums[1] + n / 2) / n) as u8,
+        ((sums[2] + n / 2) / n) as u8,
+        255,
+    ]
+}
+
+// Solves the 8x8 system for the homography mapping `src` points onto `dst`
+// points (4 x,y pairs each). Returns None for degenerate configurations.
+pub(crate) fn solve_perspective(src: &[f32], dst: &[f32]) -> Option<[f64; 9]> {
+    let mut a = [[0f64; 9]; 8];
+    for i in 0..4 {
+        let (x, y) = (src[2 * i] as f64, src[2 * i + 1] as f64);
+        let (u, v) = (dst[2 * i] as f64, dst[2 * i + 1] as f64);
+        a[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        a[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..8 {
+        let pivot = (col..8).max_by(|&r1, &r2| a[r1][col].abs().total_cmp(&a[r2][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col];
+        for (row, values) in a.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (v, &p) in values[col..].iter_mut().zip(&pivot_row[col..]) {
+                    *v -= factor * p;
+                }
+            }
+        }
+    }
+
+    let mut h = [0f64; 9];
+    for i in 0..8 {
+        h[i] = a[i][8] / a[i][i];
+    }
+    h[8] = 1.0;
+    Some(h)
+}
+
+// Output size for rectifying a quad (TL, TR, BR, BL), matching the JS unwarpImage.
+#[cfg_attr(not(any(feature = "session", feature = "cards", feature = "photos", feature = "paper")), allow(dead_code))]
+pub(crate) fn quad_output_size(corners: &[f32]) -> (usize, usize) {
+    let dist = |a: usize, b: usize| {
+        (corners[2 * a] - corners[2 * b]).hypot(corners[2 * a + 1] - corners[2 * b + 1])
+    };
+    let width = dist(2, 3).max(dist(1, 0)).round() as usize;
+    let height = dist(1, 2).max(dist(0, 3)).round() as usize;
+    (width.max(1), height.max(1))
+}
+
+// Matrix mapping an output rectangle of the given size back onto the quad.
+#[cfg_attr(not(any(feature = "session", feature = "cards", feature = "photos", feature = "paper")), allow(dead_code))]
+pub(crate) fn rect_to_quad_matrix(corners: &[f32], width: usize, height: usize) -> Option<[f64; 9]> {
+    let w = (width.max(1) - 1) as f32;
+    let h = (height.max(1) - 1) as f32;
+    let rect = [0.0, 0.0, w, 0.0, w, h, 0.0, h];
+    solve_perspective(&rect, corners)
+}
+
+/// Computes the 3x3 perspective matrix mapping four source points onto four
+/// destination points (8 values each, x,y per point).
+///
+/// # Returns
+/// Row-major 3x3 matrix as Vec<f32>; degenerate (e.g. collinear) points are an
+/// error
+#[wasm_bindgen]
+pub fn perspective_matrix(src_points: &[f32], dst_points: &[f32]) -> Result<Vec<f32>, ScanError> {
+    if src_points.len() != 8 || dst_points.len() != 8 {
+        return Err(ScanError::InvalidPoints("Perspective points must have 8 coordinates (4 points)"));
+    }
+    let h = solve_perspective(src_points, dst_points)
+        .ok_or(ScanError::InvalidParameter("Perspective points are degenerate (collinear or coincident)"))?;
+    Ok(h.iter().map(|&v| v as f32).collect())
+}
+
+/// Warps an RGBA image with a perspective transform using inverse mapping and
+/// bilinear sampling.
+///
+/// # Arguments
+/// * `src` - Source RGBA pixels
+/// * `src_width` / `src_height` - Source dimensions
+/// * `matrix` - Row-major 3x3 matrix mapping destination to source coordinates
+/// * `dst_width` / `dst_height` - Output dimensions
+/// * `policy` - How to fill destination pixels that map outside the source
+/// * `fill_color` - Packed `0xRRGGBBAA` colour used by `OutOfBoundsPolicy::Constant`
+///
+/// # Returns
+/// Warped RGBA image as Vec<u8>
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn warp_perspective(
+    src: &[u8],
+    src_width: usize,
+    src_height: usize,
+    matrix: &[f32],
+    dst_width: usize,
+    dst_height: usize,
+    policy: OutOfBoundsPolicy,
+    fill_color: u32,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(src, src_width, src_height)?;
+    if matrix.len() != 9 {
+        return Err(ScanError::InvalidParameter("Perspective matrix must have 9 elements"));
+    }
+    output_len(dst_width, dst_height, 4)?;
+
+    let mut m = [0f64; 9];
+    for (d, &v) in m.iter_mut().zip(matrix) {
+        *d = v as f64;
+    }
+    Ok(warp_with_matrix(src, src_width, src_height, &m, dst_width, dst_height, policy, fill_color))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn warp_with_matrix(
+    src: &[u8],
+    src_width: usize,
+    src_height: usize,
+    m: &[f64; 9],
+    dst_width: usize,
+    dst_height: usize,
+    policy: OutOfBoundsPolicy,
+    fill_color: u32,
+) -> Vec<u8> {
+    let mut dst = vec![0u8; dst_width * dst_height * 4];
+    if src_width == 0 || src_height == 0 {
+        return dst;
+    }
+
+    // Only the paper fill needs to revisit outside pixels after sampling.
+    let mut outside = if policy == OutOfBoundsPolicy::PaperFill {
+        vec![false; dst_width * dst_height]
+    } else {
+        Vec::new()
+    };
+    let constant = unpack_rgba(fill_color);
+    let min_x = -EDGE_SLACK;
+    let min_y = -EDGE_SLACK;
+    let max_x = (src_width - 1) as f64 + EDGE_SLACK;
+    let max_y = (src_height - 1) as f64 + EDGE_SLACK;
+
+    for oy in 0..dst_height {
+        if oy % crate::scheduler::YIELD_INTERVAL_ROWS == 0 {
+            crate::scheduler::yield_point();
+        }
+
+        // Precompute the y-dependent part of the inverse transform
+        let fy = oy as f64;
+        let iy1 = m[1] * fy + m[2];
+        let iy2 = m[4] * fy + m[5];
+        let iy3 = m[7] * fy + m[8];
+
+        for ox in 0..dst_width {
+            let fx = ox as f64;
+            let w = m[6] * fx + iy3;
+            let inv_w = 1.0 / w;
+            let sx = (m[0] * fx + iy1) * inv_w;
+            let sy = (m[3] * fx + iy2) * inv_w;
+
+            let inside = w.is_finite()
+                && w != 0.0
+                && sx >= min_x
+                && sx <= max_x
+                && sy >= min_y
+                && sy <= max_y;
+
+            let di = (oy * dst_width + ox) * 4;
+            let pixel = if inside {
+                sample_bilinear(src, src_width, src_height, sx, sy)
+            } else {
+                match policy {
+                    OutOfBoundsPolicy::Constant => constant,
+                    OutOfBoundsPolicy::Replicate | OutOfBoundsPolicy::PaperFill => {
+                        if sx.is_finite() && sy.is_finite() {
+                            sample_bilinear(src, src_width, src_height, sx, sy)
+                        } else {
+                            constant
+                        }
+                    }
+                    OutOfBoundsPolicy::MarkInAlpha => {
+                        let mut p = if sx.is_finite() && sy.is_finite() {
+                            sample_bilinear(src, src_width, src_height, sx, sy)
+                        } else {
+                            constant
+                        };
+                        p[3] = 0;
+                        p
+                    }
+                }
+            };
+
+            dst[di..di + 4].copy_from_slice(&pixel);
+            if !inside && policy == OutOfBoundsPolicy::PaperFill {
+                outside[oy * dst_width + ox] = true;
+            }
+        }
+    }
+
+    if policy == OutOfBoundsPolicy::PaperFill {
+        let paper = estimate_paper_color(&dst, &outside);
+        for (i, _) in outside.iter().enumerate().filter(|(_, &out)| out) {
+            dst[i * 4..i * 4 + 4].copy_from_slice(&paper);
+        }
+    }
+
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    // Shifts the destination 2 pixels to the right of the source.
+    const SHIFT: [f32; 9] = [1.0, 0.0, -2.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+    fn gradient_image(width: usize, height: usize) -> Vec<u8> {
+        let mut img = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                img.extend_from_slice(&[(x * 40) as u8, (y * 40) as u8, 200, 255]);
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn test_identity_warp_is_lossless() {
+        let src = gradient_image(4, 4);
+        let dst = warp_perspective(&src, 4, 4, &IDENTITY, 4, 4, OutOfBoundsPolicy::Constant, 0).unwrap();
+        assert_eq!(dst, src);
+
+        // Output sizes that overflow or pass the cap fail before allocating
+        let too_large = Err(ScanError::InvalidParameter("Output dimensions are too large"));
+        let warp = |w, h| warp_perspective(&src, 4, 4, &IDENTITY, w, h, OutOfBoundsPolicy::Constant, 0);
+        assert_eq!(warp(1 << 15, 1 << 14), too_large);
+        assert!(warp(usize::MAX / 2, 3).is_err());
+    }
+
+    #[test]
+    fn test_perspective_matrix_maps_points() {
+        let src = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0];
+        let dst = [2.0, 1.0, 14.0, 3.0, 12.0, 13.0, 1.0, 11.0];
+        let h = perspective_matrix(&src, &dst).unwrap();
+        for i in 0..4 {
+            let (x, y) = (src[2 * i], src[2 * i + 1]);
+            let w = h[6] * x + h[7] * y + h[8];
+            assert!(((h[0] * x + h[1] * y + h[2]) / w - dst[2 * i]).abs() < 1e-3);
+            assert!(((h[3] * x + h[4] * y + h[5]) / w - dst[2 * i + 1]).abs() < 1e-3);
+        }
+
+        // Three collinear corners have no perspective solution
+        let collinear = [0.0, 0.0, 5.0, 0.0, 10.0, 0.0, 0.0, 10.0];
+        assert!(matches!(perspective_matrix(&collinear, &dst), Err(ScanError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_out_of_bounds_policies() {
+        let src = gradient_image(4, 4);
+
+        let constant = warp_perspective(&src, 4, 4, &SHIFT, 4, 4, OutOfBoundsPolicy::Constant, 0xff00_00ff).unwrap();
+        assert_eq!(&constant[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&constant[8..12], &src[0..4]);
+
+        let replicate = warp_perspective(&src, 4, 4, &SHIFT, 4, 4, OutOfBoundsPolicy::Replicate, 0).unwrap();
+        assert_eq!(&replicate[0..4], &src[0..4]);
+
+        let alpha = warp_perspective(&src, 4, 4, &SHIFT, 4, 4, OutOfBoundsPolicy::MarkInAlpha, 0).unwrap();
+        assert_eq!(alpha[3], 0);
+        assert_eq!(alpha[11], 255);
+
+        let paper = warp_perspective(&src, 4, 4, &SHIFT, 4, 4, OutOfBoundsPolicy::PaperFill, 0).unwrap();
+        assert_eq!(paper[3], 255);
+        assert!(paper[1] >= 80, "paper fill should come from the brightest rows");
+    }
+}
@@ -0,0 +1,1301 @@
+use wasm_bindgen::prelude::*;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+use crate::error::{check_rgba, ScanError};
+
+use core::f32::consts::LN_2;
+use image_webp::{ColorType, WebPEncoder};
+
+// Largest image side a VP8 frame header can hold (14 bits).
+const MAX_SIDE: usize = 16383;
+// Quality used for 0.
+const DEFAULT_QUALITY: u8 = 80;
+// Rounding of quantized AC coefficients, in eighths of a step. Below a half
+// step, small coefficients round to zero, which saves many tokens for
+// little visible loss.
+const AC_ROUNDING: i32 = 3;
+// Weight of one bit against the squared error in mode decisions, per squared
+// AC quantizer step.
+const LAMBDA: f32 = 0.05;
+// Largest quantized coefficient a token can carry.
+const MAX_LEVEL: i32 = 2048;
+// Token block types: luma AC after a Y2 block, Y2 (the luma DCs), chroma,
+// and luma with its DC.
+const LUMA_AC: usize = 0;
+const Y2: usize = 1;
+const CHROMA: usize = 2;
+const LUMA: usize = 3;
+// Coefficient scan order and the probability band of each scan position.
+const ZIGZAG: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+const BANDS: [usize; 16] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7];
+// Smallest magnitude and extra-bit probabilities of the large-coefficient
+// token categories.
+const CATEGORY_BASE: [i32; 6] = [5, 7, 11, 19, 35, 67];
+const CATEGORY_PROBS: [&[u8]; 6] = [
+    &[159],
+    &[165, 145],
+    &[173, 148, 140],
+    &[176, 155, 140, 135],
+    &[180, 157, 141, 134, 130],
+    &[254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129],
+];
+// Key frame mode trees: pairs of branches, where -v is the mode v and a
+// positive entry the index of the next pair, with the probability of each
+// pair. Subblock modes (B_PRED = 4) follow `SubMode`.
+const B_PRED: i8 = 4;
+const LUMA_MODE_TREE: [i8; 8] = [-B_PRED, 2, 4, 6, 0, -1, -2, -3];
+const LUMA_MODE_PROBS: [u8; 4] = [145, 156, 163, 128];
+const CHROMA_MODE_TREE: [i8; 6] = [0, 2, -1, 4, -2, -3];
+const CHROMA_MODE_PROBS: [u8; 3] = [142, 114, 183];
+const SUBBLOCK_MODE_TREE: [i8; 18] = [0, 2, -1, 4, -2, 6, 8, 12, -3, 10, -5, -6, -4, 14, -7, 16, -8, -9];
+// Inverse DCT multipliers √2·cos(π/8) - 1 and √2·sin(π/8) in 16-bit fixed
+// point.
+const COS_MINUS_ONE: i64 = 20091;
+const SIN: i64 = 35468;
+// Row length of a luma macroblock with its border: the left column, 16
+// pixels and 4 above-right.
+const BORDERED: usize = 21;
+
+// Destination of boolean-coded bits: the stream, or a rate estimate.
+trait BitSink {
+    fn put(&mut self, bit: bool, prob: u8);
+}
+
+// Boolean entropy coder of VP8 (RFC 6386 section 7), after libvpx.
+struct BoolWriter {
+    out: Vec<u8>,
+    range: u32,
+    low: u32,
+    // Bits shifted into `low` since the last output byte, minus 24
+    count: i32,
+}
+
+impl BoolWriter {
+    fn new() -> BoolWriter {
+        BoolWriter { out: Vec::new(), range: 255, low: 0, count: -24 }
+    }
+
+    fn put_literal(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            self.put(value >> bit & 1 == 1, 128);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for _ in 0..32 {
+            self.put(false, 128);
+        }
+        self.out
+    }
+}
+
+impl BitSink for BoolWriter {
+    fn put(&mut self, bit: bool, prob: u8) {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        if bit {
+            self.low += split;
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+        let mut shift = self.range.leading_zeros() as i32 - 24;
+        self.range <<= shift;
+        self.count += shift;
+        if self.count >= 0 {
+            let offset = shift - self.count;
+            if (self.low << (offset - 1)) & 0x8000_0000 != 0 {
+                // Carry into the bytes already written
+                for byte in self.out.iter_mut().rev() {
+                    if *byte == 0xff {
+                        *byte = 0;
+                    } else {
+                        *byte += 1;
+                        break;
+                    }
+                }
+            }
+            self.out.push((self.low >> (24 - offset)) as u8);
+            self.low = (self.low << offset) & 0xff_ffff;
+            shift = self.count;
+            self.count -= 8;
+        }
+        self.low <<= shift;
+    }
+}
+
+// Sums the cost of bits instead of writing them, in 1/256 bit.
+struct Rate<'a> {
+    costs: &'a [u32; 256],
+    total: u32,
+}
+
+impl BitSink for Rate<'_> {
+    fn put(&mut self, bit: bool, prob: u8) {
+        self.total += self.costs[if bit { 256 - prob as usize } else { prob as usize }];
+    }
+}
+
+// Cost in 1/256 bit of coding a bit whose probability is p/256.
+fn bit_costs() -> [u32; 256] {
+    core::array::from_fn(|p| (-(p.max(1) as f32 / 256.0).ln() / LN_2 * 256.0 + 0.5) as u32)
+}
+
+// Writes the leaf `-value` of a mode tree.
+fn put_tree(w: &mut impl BitSink, tree: &[i8], probs: &[u8], value: i8) {
+    // Walk up from the leaf to the root, then write the branches top down
+    let (mut path, mut len, mut node) = ([(0, false); 8], 0, -value);
+    loop {
+        let i = tree.iter().position(|&t| t == node).unwrap_or(0);
+        path[len] = (i / 2, i % 2 == 1);
+        len += 1;
+        if i < 2 {
+            break;
+        }
+        node = (i - i % 2) as i8;
+    }
+    for &(pair, bit) in path[..len].iter().rev() {
+        w.put(bit, probs[pair]);
+    }
+}
+
+// 16×16 luma and 8×8 chroma prediction modes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Prediction {
+    Dc = 0,
+    Vertical = 1,
+    Horizontal = 2,
+    TrueMotion = 3,
+}
+
+impl Prediction {
+    // Modes that only use neighbours inside the frame; the decoder would
+    // substitute 127 and 129 for missing ones, which predicts badly.
+    fn available(x: usize, y: usize) -> impl Iterator<Item = Prediction> {
+        [
+            (Prediction::Dc, true),
+            (Prediction::Vertical, y > 0),
+            (Prediction::Horizontal, x > 0),
+            (Prediction::TrueMotion, x > 0 && y > 0),
+        ]
+        .into_iter()
+        .filter_map(|(mode, inside)| inside.then_some(mode))
+    }
+
+    // Subblock mode the neighbours of a 16×16 predicted block see.
+    fn subblock_mode(self) -> SubMode {
+        match self {
+            Prediction::Dc => SubMode::Dc,
+            Prediction::Vertical => SubMode::Vertical,
+            Prediction::Horizontal => SubMode::Horizontal,
+            Prediction::TrueMotion => SubMode::TrueMotion,
+        }
+    }
+}
+
+// 4×4 luma subblock prediction modes (B_PRED), in the decoder's numbering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SubMode {
+    Dc,
+    TrueMotion,
+    Vertical,
+    Horizontal,
+    DownLeft,
+    DownRight,
+    VerticalRight,
+    VerticalLeft,
+    HorizontalDown,
+    HorizontalUp,
+}
+
+const SUBMODES: [SubMode; 10] = [
+    SubMode::Dc,
+    SubMode::TrueMotion,
+    SubMode::Vertical,
+    SubMode::Horizontal,
+    SubMode::DownLeft,
+    SubMode::DownRight,
+    SubMode::VerticalRight,
+    SubMode::VerticalLeft,
+    SubMode::HorizontalDown,
+    SubMode::HorizontalUp,
+];
+
+// Predicts the n×n block at (x, y) of a plane from its reconstructed
+// neighbours, as the decoder does.
+fn predict(plane: &[u8], stride: usize, x: usize, y: usize, n: usize, mode: Prediction, out: &mut [u8]) {
+    let above = |i: usize| plane[(y - 1) * stride + x + i] as i32;
+    let left = |j: usize| plane[(y + j) * stride + x - 1] as i32;
+    if mode == Prediction::Dc {
+        let shift = n.trailing_zeros();
+        let dc = match (y > 0, x > 0) {
+            (true, true) => ((0..n).map(|k| above(k) + left(k)).sum::<i32>() + n as i32) >> (shift + 1),
+            (true, false) => ((0..n).map(above).sum::<i32>() + n as i32 / 2) >> shift,
+            (false, true) => ((0..n).map(left).sum::<i32>() + n as i32 / 2) >> shift,
+            (false, false) => 128,
+        };
+        out[..n * n].fill(dc as u8);
+        return;
+    }
+    for (j, row) in out.chunks_exact_mut(n).take(n).enumerate() {
+        for (i, v) in row.iter_mut().enumerate() {
+            *v = match mode {
+                Prediction::Vertical => above(i),
+                Prediction::Horizontal => left(j),
+                _ => (left(j) + above(i) - plane[(y - 1) * stride + x - 1] as i32).clamp(0, 255),
+            } as u8;
+        }
+    }
+}
+
+// Predicts a 4×4 subblock (RFC 6386 section 12.3) from its neighbours
+// [L3, L3, L2, L1, L0, P, A0..A7, A7]: the left column bottom-up, the corner,
+// and the row above including 4 above-right, with the ends repeated.
+fn predict_subblock(e: &[i32; 15], mode: SubMode) -> [u8; 16] {
+    let a2 = |k: usize| ((e[k] + e[k + 1] + 1) >> 1) as u8;
+    let a3 = |k: usize| ((e[k] + 2 * e[k + 1] + e[k + 2] + 2) >> 2) as u8;
+    match mode {
+        SubMode::Dc => [((e[1..5].iter().sum::<i32>() + e[6..10].iter().sum::<i32>() + 4) >> 3) as u8; 16],
+        SubMode::TrueMotion => core::array::from_fn(|k| (e[4 - k / 4] + e[6 + k % 4] - e[5]).clamp(0, 255) as u8),
+        SubMode::Vertical => core::array::from_fn(|k| a3(5 + k % 4)),
+        SubMode::Horizontal => core::array::from_fn(|k| a3(3 - k / 4)),
+        SubMode::DownLeft => core::array::from_fn(|k| a3(6 + k / 4 + k % 4)),
+        SubMode::DownRight => core::array::from_fn(|k| a3(4 + k % 4 - k / 4)),
+        #[rustfmt::skip]
+        SubMode::VerticalRight => [
+            a2(5), a2(6), a2(7), a2(8),
+            a3(4), a3(5), a3(6), a3(7),
+            a3(3), a2(5), a2(6), a2(7),
+            a3(2), a3(4), a3(5), a3(6),
+        ],
+        #[rustfmt::skip]
+        SubMode::VerticalLeft => [
+            a2(6), a2(7), a2(8), a2(9),
+            a3(6), a3(7), a3(8), a3(9),
+            a2(7), a2(8), a2(9), a3(10),
+            a3(7), a3(8), a3(9), a3(11),
+        ],
+        #[rustfmt::skip]
+        SubMode::HorizontalDown => [
+            a2(4), a3(4), a3(5), a3(6),
+            a2(3), a3(3), a2(4), a3(4),
+            a2(2), a3(2), a2(3), a3(3),
+            a2(1), a3(1), a2(2), a3(2),
+        ],
+        #[rustfmt::skip]
+        SubMode::HorizontalUp => {
+            let l3 = e[1] as u8;
+            [
+                a2(3), a3(2), a2(2), a3(1),
+                a2(2), a3(1), a2(1), a3(0),
+                a2(1), a3(0), l3, l3,
+                l3, l3, l3, l3,
+            ]
+        }
+    }
+}
+
+// Forward DCT of a 4×4 residual block, the libvpx integer transform.
+fn fdct(block: &mut [i32; 16]) {
+    for row in block.chunks_exact_mut(4) {
+        let (a, b) = ((row[0] + row[3]) * 8, (row[1] + row[2]) * 8);
+        let (c, d) = ((row[1] - row[2]) * 8, (row[0] - row[3]) * 8);
+        row[0] = a + b;
+        row[2] = a - b;
+        row[1] = (c * 2217 + d * 5352 + 14500) >> 12;
+        row[3] = (d * 2217 - c * 5352 + 7500) >> 12;
+    }
+    for i in 0..4 {
+        let (a, b) = (block[i] + block[12 + i], block[4 + i] + block[8 + i]);
+        let (c, d) = (block[4 + i] - block[8 + i], block[i] - block[12 + i]);
+        block[i] = (a + b + 7) >> 4;
+        block[8 + i] = (a - b + 7) >> 4;
+        block[4 + i] = ((c * 2217 + d * 5352 + 12000) >> 16) + i32::from(d != 0);
+        block[12 + i] = (d * 2217 - c * 5352 + 51000) >> 16;
+    }
+}
+
+// Inverse DCT exactly as the decoder computes it (RFC 6386 section 14.3).
+fn idct(block: &mut [i32; 16]) {
+    let mul_cos = |v: i64| v + ((v * COS_MINUS_ONE) >> 16);
+    let mul_sin = |v: i64| (v * SIN) >> 16;
+    let pass = |v: [i64; 4]| {
+        let (a, b) = (v[0] + v[2], v[0] - v[2]);
+        let (c, d) = (mul_sin(v[1]) - mul_cos(v[3]), mul_cos(v[1]) + mul_sin(v[3]));
+        [a + d, b + c, b - c, a - d]
+    };
+    for i in 0..4 {
+        let column = pass([0, 4, 8, 12].map(|k| block[i + k] as i64));
+        for (k, v) in column.into_iter().enumerate() {
+            block[i + 4 * k] = v as i32;
+        }
+    }
+    for row in block.chunks_exact_mut(4) {
+        let out = pass([row[0], row[1], row[2], row[3]].map(|v| v as i64));
+        for (r, v) in row.iter_mut().zip(out) {
+            *r = ((v + 4) >> 3) as i32;
+        }
+    }
+}
+
+// Forward Walsh-Hadamard transform of the 16 luma DCs of a macroblock.
+fn fwht(block: &mut [i32; 16]) {
+    for row in block.chunks_exact_mut(4) {
+        let (a, d) = ((row[0] + row[2]) * 4, (row[1] + row[3]) * 4);
+        let (c, b) = ((row[1] - row[3]) * 4, (row[0] - row[2]) * 4);
+        row[0] = a + d + i32::from(a != 0);
+        row[1] = b + c;
+        row[2] = b - c;
+        row[3] = a - d;
+    }
+    for i in 0..4 {
+        let (a, d) = (block[i] + block[8 + i], block[4 + i] + block[12 + i]);
+        let (c, b) = (block[4 + i] - block[12 + i], block[i] - block[8 + i]);
+        for (k, v) in [a + d, b + c, b - c, a - d].into_iter().enumerate() {
+            block[i + 4 * k] = (v + i32::from(v < 0) + 3) >> 3;
+        }
+    }
+}
+
+// Inverse Walsh-Hadamard transform as the decoder computes it.
+fn iwht(block: &mut [i32; 16]) {
+    for i in 0..4 {
+        let (a, b) = (block[i] + block[12 + i], block[4 + i] + block[8 + i]);
+        let (c, d) = (block[4 + i] - block[8 + i], block[i] - block[12 + i]);
+        block[i] = a + b;
+        block[4 + i] = c + d;
+        block[8 + i] = a - b;
+        block[12 + i] = d - c;
+    }
+    for row in block.chunks_exact_mut(4) {
+        let (a, b) = (row[0] + row[3], row[1] + row[2]);
+        let (c, d) = (row[1] - row[2], row[0] - row[3]);
+        for (r, v) in row.iter_mut().zip([a + b, c + d, a - b, d - c]) {
+            *r = (v + 3) >> 3;
+        }
+    }
+}
+
+// Transformed residual of the 4×4 block at (x, y) of n-wide source and
+// prediction blocks.
+fn residual(source: &[u8], predicted: &[u8], n: usize, x: usize, y: usize) -> [i32; 16] {
+    let mut block = core::array::from_fn(|k| {
+        let i = (y + k / 4) * n + x + k % 4;
+        source[i] as i32 - predicted[i] as i32
+    });
+    fdct(&mut block);
+    block
+}
+
+// Adds decoded coefficients to the prediction of the 4×4 block at (x, y), as
+// the decoder does.
+fn reconstruct(predicted: &[u8], mut coefficients: [i32; 16], n: usize, x: usize, y: usize, recon: &mut [u8]) {
+    idct(&mut coefficients);
+    for (k, c) in coefficients.into_iter().enumerate() {
+        let i = (y + k / 4) * n + x + k % 4;
+        recon[i] = (predicted[i] as i32 + c).clamp(0, 255) as u8;
+    }
+}
+
+// Quantizes coefficients from `first` on with (DC, AC) steps.
+fn quantize(coefficients: &[i32; 16], steps: [i32; 2], first: usize) -> [i32; 16] {
+    core::array::from_fn(|i| {
+        let (step, rounding) = if i == 0 { (steps[0], steps[0] / 2) } else { (steps[1], steps[1] * AC_ROUNDING / 8) };
+        let c = coefficients[i];
+        if i < first {
+            0
+        } else {
+            ((c.abs() + rounding) / step).min(MAX_LEVEL) * c.signum()
+        }
+    })
+}
+
+fn dequantize(levels: &[i32; 16], steps: [i32; 2]) -> [i32; 16] {
+    core::array::from_fn(|i| levels[i] * steps[usize::from(i > 0)])
+}
+
+fn squared_error(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(&a, &b)| (a.abs_diff(b) as u32).pow(2)).sum()
+}
+
+// Codes the quantized coefficients of one block as tokens (RFC 6386 section
+// 13.2) and returns whether any is non-zero, the context of the next blocks.
+fn put_tokens(w: &mut impl BitSink, kind: usize, levels: &[i32; 16], first: usize, context: usize) -> bool {
+    let last = (first..16).rev().find(|&i| levels[ZIGZAG[i]] != 0);
+    let (mut context, mut after_zero) = (context, false);
+    for i in first..16 {
+        let p = &COEFF_PROBS[kind][BANDS[i]][context];
+        if last.is_none_or(|last| i > last) {
+            w.put(false, p[0]);
+            break;
+        }
+        if !after_zero {
+            w.put(true, p[0]);
+        }
+        let level = levels[ZIGZAG[i]];
+        let magnitude = level.abs();
+        w.put(magnitude > 0, p[1]);
+        after_zero = magnitude == 0;
+        if after_zero {
+            context = 0;
+            continue;
+        }
+        w.put(magnitude > 1, p[2]);
+        if magnitude > 1 {
+            put_large(w, p, magnitude);
+        }
+        w.put(level < 0, 128);
+        context = if magnitude > 1 { 2 } else { 1 };
+    }
+    last.is_some()
+}
+
+// Token of a magnitude of 2 or more, with its extra bits.
+fn put_large(w: &mut impl BitSink, p: &[u8; 11], magnitude: i32) {
+    w.put(magnitude > 4, p[3]);
+    if magnitude <= 4 {
+        w.put(magnitude > 2, p[4]);
+        if magnitude > 2 {
+            w.put(magnitude == 4, p[5]);
+        }
+        return;
+    }
+    let category = CATEGORY_BASE.iter().rposition(|&base| magnitude >= base).unwrap_or(0);
+    w.put(category > 1, p[6]);
+    if category <= 1 {
+        w.put(category == 1, p[7]);
+    } else {
+        w.put(category > 3, p[8]);
+        w.put(category % 2 == 1, p[9 + usize::from(category > 3)]);
+    }
+    let (extra, probs) = (magnitude - CATEGORY_BASE[category], CATEGORY_PROBS[category]);
+    for (k, &prob) in probs.iter().enumerate() {
+        w.put(extra >> (probs.len() - 1 - k) & 1 == 1, prob);
+    }
+}
+
+// Coding context along one macroblock edge: whether the neighbouring Y2, 4
+// luma, 2 U and 2 V blocks had non-zero coefficients, and the modes of the
+// neighbouring luma subblocks.
+#[derive(Clone, Copy)]
+struct Edge {
+    nonzero: [bool; 9],
+    modes: [SubMode; 4],
+}
+
+impl Default for Edge {
+    fn default() -> Edge {
+        Edge { nonzero: [false; 9], modes: [SubMode::Dc; 4] }
+    }
+}
+
+fn token_context(above: bool, left: bool) -> usize {
+    usize::from(above) + usize::from(left)
+}
+
+// A coded luma macroblock.
+struct Luma {
+    // 16×16 mode, or None for per-subblock modes
+    mode: Option<Prediction>,
+    subblock_modes: [SubMode; 16],
+    y2: [i32; 16],
+    levels: [[i32; 16]; 16],
+    recon: [u8; 256],
+}
+
+// Writes the modes and tokens of a luma macroblock, updating the contexts.
+fn put_luma(luma: &Luma, modes: &mut impl BitSink, tokens: &mut impl BitSink, above: &mut Edge, left: &mut Edge) {
+    let (kind, first) = match luma.mode {
+        Some(mode) => {
+            put_tree(modes, &LUMA_MODE_TREE, &LUMA_MODE_PROBS, mode as i8);
+            let context = token_context(above.nonzero[0], left.nonzero[0]);
+            let nonzero = put_tokens(tokens, Y2, &luma.y2, 0, context);
+            (above.nonzero[0], left.nonzero[0]) = (nonzero, nonzero);
+            (above.modes, left.modes) = ([mode.subblock_mode(); 4], [mode.subblock_mode(); 4]);
+            (LUMA_AC, 1)
+        }
+        None => {
+            put_tree(modes, &LUMA_MODE_TREE, &LUMA_MODE_PROBS, B_PRED);
+            for (b, &mode) in luma.subblock_modes.iter().enumerate() {
+                let (x, y) = (b % 4, b / 4);
+                let probs = &SUBBLOCK_MODE_PROBS[above.modes[x] as usize][left.modes[y] as usize];
+                put_tree(modes, &SUBBLOCK_MODE_TREE, probs, mode as i8);
+                (above.modes[x], left.modes[y]) = (mode, mode);
+            }
+            (LUMA, 0)
+        }
+    };
+    for (b, levels) in luma.levels.iter().enumerate() {
+        let (x, y) = (1 + b % 4, 1 + b / 4);
+        let nonzero = put_tokens(tokens, kind, levels, first, token_context(above.nonzero[x], left.nonzero[y]));
+        (above.nonzero[x], left.nonzero[y]) = (nonzero, nonzero);
+    }
+}
+
+// A macroblock-padded plane: the source and the decoder's reconstruction.
+struct Plane {
+    stride: usize,
+    source: Vec<u8>,
+    recon: Vec<u8>,
+}
+
+impl Plane {
+    // Source pixels of the n×n block at (x, y).
+    fn source_block(&self, x: usize, y: usize, n: usize) -> [u8; 256] {
+        let mut block = [0; 256];
+        for (j, row) in block.chunks_exact_mut(n).take(n).enumerate() {
+            row.copy_from_slice(&self.source[(y + j) * self.stride + x..][..n]);
+        }
+        block
+    }
+
+    fn store(&mut self, x: usize, y: usize, n: usize, recon: &[u8]) {
+        for (j, row) in recon.chunks_exact(n).take(n).enumerate() {
+            self.recon[(y + j) * self.stride + x..][..n].copy_from_slice(row);
+        }
+    }
+}
+
+// Lossy VP8 key frame encoder. Each luma macroblock is predicted as a whole
+// (with the DCs of its 4×4 transform blocks through a second, Y2 transform)
+// or per 4×4 subblock, whichever costs less in squared error plus weighted
+// bits; chroma is predicted per 8×8 block.
+struct Vp8 {
+    mb_width: usize,
+    mb_height: usize,
+    planes: [Plane; 3],
+    // (DC, AC) quantizer steps of luma, Y2 and chroma blocks
+    steps: [[i32; 2]; 3],
+    lambda: f32,
+    costs: [u32; 256],
+    modes: BoolWriter,
+    tokens: BoolWriter,
+    // Contexts below each macroblock column and right of the last macroblock
+    above: Vec<Edge>,
+    left: Edge,
+}
+
+impl Vp8 {
+    fn new(rgba: &[u8], width: usize, height: usize, index: usize) -> Vp8 {
+        let (mb_width, mb_height) = (width.div_ceil(16), height.div_ceil(16));
+        // Limited-range BT.601, as VP8 decoders convert back
+        let pixel = |x: usize, y: usize| {
+            let px = &rgba[(y.min(height - 1) * width + x.min(width - 1)) * 4..][..3];
+            [px[0] as f32, px[1] as f32, px[2] as f32]
+        };
+        let luma = |[r, g, b]: [f32; 3]| 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+        let cb = |[r, g, b]: [f32; 3]| 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+        let cr = |[r, g, b]: [f32; 3]| 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+        let plane = |scale: usize, convert: &dyn Fn([f32; 3]) -> f32| {
+            let (w, h) = (mb_width * 16 / scale, mb_height * 16 / scale);
+            let source = (0..w * h)
+                .map(|i| {
+                    let (x, y) = (i % w * scale, i / w * scale);
+                    let sum: f32 = (0..scale * scale).map(|k| convert(pixel(x + k % scale, y + k / scale))).sum();
+                    (sum / (scale * scale) as f32 + 0.5) as u8
+                })
+                .collect();
+            Plane { stride: w, source, recon: vec![0; w * h] }
+        };
+        let steps = [
+            [DC_QUANT[index], AC_QUANT[index]],
+            [DC_QUANT[index] * 2, (AC_QUANT[index] * 155 / 100).max(8)],
+            [DC_QUANT[index].min(132), AC_QUANT[index]],
+        ];
+        Vp8 {
+            mb_width,
+            mb_height,
+            planes: [plane(1, &luma), plane(2, &cb), plane(2, &cr)],
+            steps,
+            lambda: LAMBDA * (AC_QUANT[index] * AC_QUANT[index]) as f32,
+            costs: bit_costs(),
+            modes: BoolWriter::new(),
+            tokens: BoolWriter::new(),
+            above: vec![Edge::default(); mb_width],
+            left: Edge::default(),
+        }
+    }
+
+    // Squared error plus weighted bits of coding `luma` in macroblock column mx.
+    fn cost(&self, luma: &Luma, mx: usize, source: &[u8; 256]) -> f32 {
+        let (mut modes, mut tokens) = (Rate { costs: &self.costs, total: 0 }, Rate { costs: &self.costs, total: 0 });
+        put_luma(luma, &mut modes, &mut tokens, &mut self.above[mx].clone(), &mut self.left.clone());
+        squared_error(source, &luma.recon) as f32 + self.lambda * (modes.total + tokens.total) as f32 / 256.0
+    }
+
+    // Codes the luma macroblock as a whole with `mode`.
+    fn luma_16(&self, mode: Prediction, (x, y): (usize, usize), source: &[u8; 256]) -> Luma {
+        let plane = &self.planes[0];
+        let mut predicted = [0u8; 256];
+        predict(&plane.recon, plane.stride, x, y, 16, mode, &mut predicted);
+        let blocks: [[i32; 16]; 16] = core::array::from_fn(|b| residual(source, &predicted, 16, b % 4 * 4, b / 4 * 4));
+        let mut dcs = blocks.map(|block| block[0]);
+        fwht(&mut dcs);
+        let y2 = quantize(&dcs, self.steps[1], 0);
+        let mut dcs = dequantize(&y2, self.steps[1]);
+        iwht(&mut dcs);
+        let levels = blocks.map(|block| quantize(&block, self.steps[0], 1));
+        let mut recon = [0u8; 256];
+        for (b, levels) in levels.iter().enumerate() {
+            let mut coefficients = dequantize(levels, self.steps[0]);
+            coefficients[0] = dcs[b];
+            reconstruct(&predicted, coefficients, 16, b % 4 * 4, b / 4 * 4, &mut recon);
+        }
+        Luma { mode: Some(mode), subblock_modes: [mode.subblock_mode(); 16], y2, levels, recon }
+    }
+
+    // Codes the luma macroblock per subblock, picking each subblock's mode by
+    // squared error plus weighted bits.
+    fn luma_4(&self, mx: usize, my: usize, source: &[u8; 256]) -> Luma {
+        let mut border = self.luma_border(mx, my);
+        let (mut above, mut left) = (self.above[mx], self.left);
+        let mut luma =
+            Luma { mode: None, subblock_modes: [SubMode::Dc; 16], y2: [0; 16], levels: [[0; 16]; 16], recon: [0; 256] };
+        for b in 0..16 {
+            let (bx, by) = (b % 4, b / 4);
+            // Neighbours from the bordered block, see `predict_subblock`
+            let at = |i: usize, j: usize| border[(by * 4 + j) * BORDERED + bx * 4 + i] as i32;
+            let mut edge = [0; 15];
+            edge[..5].copy_from_slice(&[at(0, 4), at(0, 4), at(0, 3), at(0, 2), at(0, 1)]);
+            edge[5] = at(0, 0);
+            for (c, e) in edge[6..14].iter_mut().enumerate() {
+                *e = at(1 + c, 0);
+            }
+            edge[14] = edge[13];
+
+            let probs = &SUBBLOCK_MODE_PROBS[above.modes[bx] as usize][left.modes[by] as usize];
+            let context = token_context(above.nonzero[1 + bx], left.nonzero[1 + by]);
+            let mut best = (f32::MAX, SubMode::Dc, [0; 16], [0u8; 16], false);
+            for mode in SUBMODES {
+                let predicted = predict_subblock(&edge, mode);
+                let source_block: [u8; 16] = core::array::from_fn(|k| source[(by * 4 + k / 4) * 16 + bx * 4 + k % 4]);
+                let levels = quantize(&residual(&source_block, &predicted, 4, 0, 0), self.steps[0], 0);
+                let mut rate = Rate { costs: &self.costs, total: 0 };
+                put_tree(&mut rate, &SUBBLOCK_MODE_TREE, probs, mode as i8);
+                let nonzero = put_tokens(&mut rate, LUMA, &levels, 0, context);
+                let mut recon = [0u8; 16];
+                reconstruct(&predicted, dequantize(&levels, self.steps[0]), 4, 0, 0, &mut recon);
+                let cost = squared_error(&source_block, &recon) as f32 + self.lambda * rate.total as f32 / 256.0;
+                if cost < best.0 {
+                    best = (cost, mode, levels, recon, nonzero);
+                }
+            }
+
+            let (_, mode, levels, recon, nonzero) = best;
+            (luma.subblock_modes[b], luma.levels[b]) = (mode, levels);
+            (above.modes[bx], left.modes[by]) = (mode, mode);
+            (above.nonzero[1 + bx], left.nonzero[1 + by]) = (nonzero, nonzero);
+            for (j, row) in recon.chunks_exact(4).enumerate() {
+                luma.recon[(by * 4 + j) * 16 + bx * 4..][..4].copy_from_slice(row);
+                border[(by * 4 + j + 1) * BORDERED + bx * 4 + 1..][..4].copy_from_slice(row);
+            }
+        }
+        luma
+    }
+
+    // The luma macroblock's reconstructed neighbours as the decoder lays them
+    // out for subblock prediction: 127 above and 129 left of the frame, the
+    // above-right pixels repeated down the right edge, and the last pixel
+    // above repeated in the last column.
+    fn luma_border(&self, mx: usize, my: usize) -> [u8; 17 * BORDERED] {
+        let plane = &self.planes[0];
+        let (x, y) = (mx * 16, my * 16);
+        let mut border = [0u8; 17 * BORDERED];
+        for (i, v) in border[1..BORDERED].iter_mut().enumerate() {
+            *v = match (my, i) {
+                (0, _) => 127,
+                _ if mx + 1 == self.mb_width && i >= 16 => plane.recon[(y - 1) * plane.stride + x + 15],
+                _ => plane.recon[(y - 1) * plane.stride + x + i],
+            };
+        }
+        for j in 0..17 {
+            border[j * BORDERED] = match (mx, my, j) {
+                (_, 0, 0) => 127,
+                (0, _, _) => 129,
+                _ => plane.recon[(y + j - 1) * plane.stride + x - 1],
+            };
+        }
+        for j in [4, 8, 12] {
+            border.copy_within(17..BORDERED, j * BORDERED + 17);
+        }
+        border
+    }
+
+    fn encode_macroblock(&mut self, mx: usize, my: usize) {
+        let origin = (mx * 16, my * 16);
+        let source = self.planes[0].source_block(origin.0, origin.1, 16);
+        let candidates = Prediction::available(origin.0, origin.1)
+            .map(|mode| self.luma_16(mode, origin, &source))
+            .chain([self.luma_4(mx, my, &source)]);
+        let luma = candidates
+            .map(|luma| (self.cost(&luma, mx, &source), luma))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, luma)| luma)
+            .unwrap_or_else(|| self.luma_16(Prediction::Dc, origin, &source));
+        put_luma(&luma, &mut self.modes, &mut self.tokens, &mut self.above[mx], &mut self.left);
+        self.planes[0].store(origin.0, origin.1, 16, &luma.recon);
+
+        // Chroma: one mode for both planes, by the prediction error
+        let (x, y) = (mx * 8, my * 8);
+        let sources = [1, 2].map(|p| self.planes[p].source_block(x, y, 8));
+        let mode = Prediction::available(x, y)
+            .min_by_key(|&mode| {
+                let mut predicted = [0u8; 64];
+                (1..3)
+                    .map(|p| {
+                        predict(&self.planes[p].recon, self.planes[p].stride, x, y, 8, mode, &mut predicted);
+                        squared_error(&sources[p - 1][..64], &predicted)
+                    })
+                    .sum::<u32>()
+            })
+            .unwrap_or(Prediction::Dc);
+        put_tree(&mut self.modes, &CHROMA_MODE_TREE, &CHROMA_MODE_PROBS, mode as i8);
+        for (p, source) in [(1, sources[0]), (2, sources[1])] {
+            let (plane, offset) = (&self.planes[p], 2 * p + 3);
+            let mut predicted = [0u8; 64];
+            predict(&plane.recon, plane.stride, x, y, 8, mode, &mut predicted);
+            let mut recon = [0u8; 64];
+            for b in 0..4 {
+                let (bx, by) = (b % 2 * 4, b / 2 * 4);
+                let levels = quantize(&residual(&source, &predicted, 8, bx, by), self.steps[2], 0);
+                let (above, left) = (offset + b % 2, offset + b / 2);
+                let context = token_context(self.above[mx].nonzero[above], self.left.nonzero[left]);
+                let nonzero = put_tokens(&mut self.tokens, CHROMA, &levels, 0, context);
+                (self.above[mx].nonzero[above], self.left.nonzero[left]) = (nonzero, nonzero);
+                reconstruct(&predicted, dequantize(&levels, self.steps[2]), 8, bx, by, &mut recon);
+            }
+            self.planes[p].store(x, y, 8, &recon);
+        }
+    }
+
+    // The VP8 frame: frame tag, key frame header, first partition with the
+    // frame header and modes, and the token partition.
+    fn encode(mut self, width: usize, height: usize, index: usize) -> Vec<u8> {
+        let filter_level = (AC_QUANT[index] / 4).min(63) as u32;
+        let w = &mut self.modes;
+        // Colour space, clamping, no segmentation, normal loop filter
+        w.put_literal(0, 4);
+        w.put_literal(filter_level, 6);
+        // Sharpness, no filter deltas, one token partition
+        w.put_literal(0, 6);
+        w.put_literal(index as u32, 7);
+        // No quantizer deltas, no entropy refresh
+        w.put_literal(0, 6);
+        for &prob in COEFF_UPDATE_PROBS.iter().flatten().flatten().flatten() {
+            w.put(false, prob);
+        }
+        // No skipped macroblocks
+        w.put_literal(0, 1);
+        for my in 0..self.mb_height {
+            self.left = Edge::default();
+            for mx in 0..self.mb_width {
+                self.encode_macroblock(mx, my);
+            }
+        }
+
+        let (modes, tokens) = (self.modes.finish(), self.tokens.finish());
+        // Key frame, version 0, shown
+        let tag = (modes.len() as u32) << 5 | 1 << 4;
+        let mut frame = Vec::with_capacity(10 + modes.len() + tokens.len());
+        frame.extend_from_slice(&tag.to_le_bytes()[..3]);
+        frame.extend_from_slice(&[0x9d, 0x01, 0x2a]);
+        frame.extend_from_slice(&(width as u16).to_le_bytes());
+        frame.extend_from_slice(&(height as u16).to_le_bytes());
+        frame.extend_from_slice(&modes);
+        frame.extend_from_slice(&tokens);
+        frame
+    }
+}
+
+// Wraps a frame in a simple-format WebP container.
+fn riff(chunk: &[u8; 4], frame: &[u8]) -> Vec<u8> {
+    let padded = frame.len() + frame.len() % 2;
+    let mut out = Vec::with_capacity(20 + padded);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((12 + padded) as u32).to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(chunk);
+    out.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    out.extend_from_slice(frame);
+    out.resize(20 + padded, 0);
+    out
+}
+
+/// Encodes an RGBA image as WebP, which at the same visible quality is
+/// usually smaller than JPEG, so uploads of scans shrink without another JS
+/// library. Lossy output is a VP8 image with 4:2:0 chroma and drops alpha;
+/// lossless output (VP8L) keeps every pixel, alpha included.
+///
+/// # Arguments
+/// * `rgba` - RGBA pixels
+/// * `width` - Image width, up to 16383
+/// * `height` - Image height, up to 16383
+/// * `quality` - 1 to 100 for lossy output; 0 picks 80. Ignored when lossless
+/// * `lossless` - Whether to encode losslessly
+///
+/// # Returns
+/// WebP file bytes
+#[wasm_bindgen]
+pub fn encode_webp(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    quality: u8,
+    lossless: bool,
+) -> Result<Vec<u8>, ScanError> {
+    check_rgba(rgba, width, height)?;
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(ScanError::InvalidParameter("WebP width and height must be 1 to 16383"));
+    }
+    if quality > 100 {
+        return Err(ScanError::InvalidParameter("WebP quality must be 1 to 100"));
+    }
+    if lossless {
+        let mut out = Vec::new();
+        WebPEncoder::new(&mut out)
+            .encode(rgba, width as u32, height as u32, ColorType::Rgba8)
+            .map_err(|_| ScanError::InvalidParameter("WebP encoding failed"))?;
+        return Ok(out);
+    }
+    let quality = if quality == 0 { DEFAULT_QUALITY } else { quality } as usize;
+    // Quality 100 is the finest quantizer (index 0), 1 the coarsest (127)
+    let index = (100 - quality) * 127 / 99;
+    let frame = Vp8::new(rgba, width, height, index).encode(width, height, index);
+    Ok(riff(b"VP8 ", &frame))
+}
+
+// Quantizer step of DC coefficients by quantizer index (RFC 6386 section 14.1).
+#[rustfmt::skip]
+const DC_QUANT: [i32; 128] = [
+      4,   5,   6,   7,   8,   9,  10,  10,  11,  12,  13,  14,  15,  16,  17,  17,
+     18,  19,  20,  20,  21,  21,  22,  22,  23,  23,  24,  25,  25,  26,  27,  28,
+     29,  30,  31,  32,  33,  34,  35,  36,  37,  37,  38,  39,  40,  41,  42,  43,
+     44,  45,  46,  46,  47,  48,  49,  50,  51,  52,  53,  54,  55,  56,  57,  58,
+     59,  60,  61,  62,  63,  64,  65,  66,  67,  68,  69,  70,  71,  72,  73,  74,
+     75,  76,  76,  77,  78,  79,  80,  81,  82,  83,  84,  85,  86,  87,  88,  89,
+     91,  93,  95,  96,  98, 100, 101, 102, 104, 106, 108, 110, 112, 114, 116, 118,
+    122, 124, 126, 128, 130, 132, 134, 136, 138, 140, 143, 145, 148, 151, 154, 157,
+];
+// Quantizer step of AC coefficients by quantizer index.
+#[rustfmt::skip]
+const AC_QUANT: [i32; 128] = [
+      4,   5,   6,   7,   8,   9,  10,  11,  12,  13,  14,  15,  16,  17,  18,  19,
+     20,  21,  22,  23,  24,  25,  26,  27,  28,  29,  30,  31,  32,  33,  34,  35,
+     36,  37,  38,  39,  40,  41,  42,  43,  44,  45,  46,  47,  48,  49,  50,  51,
+     52,  53,  54,  55,  56,  57,  58,  60,  62,  64,  66,  68,  70,  72,  74,  76,
+     78,  80,  82,  84,  86,  88,  90,  92,  94,  96,  98, 100, 102, 104, 106, 108,
+    110, 112, 114, 116, 119, 122, 125, 128, 131, 134, 137, 140, 143, 146, 149, 152,
+    155, 158, 161, 164, 167, 170, 173, 177, 181, 185, 189, 193, 197, 201, 205, 209,
+    213, 217, 221, 225, 229, 234, 239, 245, 249, 254, 259, 264, 269, 274, 279, 284,
+];
+
+// Default token probabilities by block type, band, context and tree node
+// (RFC 6386 section 13.5).
+#[rustfmt::skip]
+const COEFF_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [[128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+         [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+         [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128]],
+        [[253, 136, 254, 255, 228, 219, 128, 128, 128, 128, 128],
+         [189, 129, 242, 255, 227, 213, 255, 219, 128, 128, 128],
+         [106, 126, 227, 252, 214, 209, 255, 255, 128, 128, 128]],
+        [[  1,  98, 248, 255, 236, 226, 255, 255, 128, 128, 128],
+         [181, 133, 238, 254, 221, 234, 255, 154, 128, 128, 128],
+         [ 78, 134, 202, 247, 198, 180, 255, 219, 128, 128, 128]],
+        [[  1, 185, 249, 255, 243, 255, 128, 128, 128, 128, 128],
+         [184, 150, 247, 255, 236, 224, 128, 128, 128, 128, 128],
+         [ 77, 110, 216, 255, 236, 230, 128, 128, 128, 128, 128]],
+        [[  1, 101, 251, 255, 241, 255, 128, 128, 128, 128, 128],
+         [170, 139, 241, 252, 236, 209, 255, 255, 128, 128, 128],
+         [ 37, 116, 196, 243, 228, 255, 255, 255, 128, 128, 128]],
+        [[  1, 204, 254, 255, 245, 255, 128, 128, 128, 128, 128],
+         [207, 160, 250, 255, 238, 128, 128, 128, 128, 128, 128],
+         [102, 103, 231, 255, 211, 171, 128, 128, 128, 128, 128]],
+        [[  1, 152, 252, 255, 240, 255, 128, 128, 128, 128, 128],
+         [177, 135, 243, 255, 234, 225, 128, 128, 128, 128, 128],
+         [ 80, 129, 211, 255, 194, 224, 128, 128, 128, 128, 128]],
+        [[  1,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [246,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [255, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128]],
+    ],
+    [
+        [[198,  35, 237, 223, 193, 187, 162, 160, 145, 155,  62],
+         [131,  45, 198, 221, 172, 176, 220, 157, 252, 221,   1],
+         [ 68,  47, 146, 208, 149, 167, 221, 162, 255, 223, 128]],
+        [[  1, 149, 241, 255, 221, 224, 255, 255, 128, 128, 128],
+         [184, 141, 234, 253, 222, 220, 255, 199, 128, 128, 128],
+         [ 81,  99, 181, 242, 176, 190, 249, 202, 255, 255, 128]],
+        [[  1, 129, 232, 253, 214, 197, 242, 196, 255, 255, 128],
+         [ 99, 121, 210, 250, 201, 198, 255, 202, 128, 128, 128],
+         [ 23,  91, 163, 242, 170, 187, 247, 210, 255, 255, 128]],
+        [[  1, 200, 246, 255, 234, 255, 128, 128, 128, 128, 128],
+         [109, 178, 241, 255, 231, 245, 255, 255, 128, 128, 128],
+         [ 44, 130, 201, 253, 205, 192, 255, 255, 128, 128, 128]],
+        [[  1, 132, 239, 251, 219, 209, 255, 165, 128, 128, 128],
+         [ 94, 136, 225, 251, 218, 190, 255, 255, 128, 128, 128],
+         [ 22, 100, 174, 245, 186, 161, 255, 199, 128, 128, 128]],
+        [[  1, 182, 249, 255, 232, 235, 128, 128, 128, 128, 128],
+         [124, 143, 241, 255, 227, 234, 128, 128, 128, 128, 128],
+         [ 35,  77, 181, 251, 193, 211, 255, 205, 128, 128, 128]],
+        [[  1, 157, 247, 255, 236, 231, 255, 255, 128, 128, 128],
+         [121, 141, 235, 255, 225, 227, 255, 255, 128, 128, 128],
+         [ 45,  99, 188, 251, 195, 217, 255, 224, 128, 128, 128]],
+        [[  1,   1, 251, 255, 213, 255, 128, 128, 128, 128, 128],
+         [203,   1, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+         [137,   1, 177, 255, 224, 255, 128, 128, 128, 128, 128]],
+    ],
+    [
+        [[253,   9, 248, 251, 207, 208, 255, 192, 128, 128, 128],
+         [175,  13, 224, 243, 193, 185, 249, 198, 255, 255, 128],
+         [ 73,  17, 171, 221, 161, 179, 236, 167, 255, 234, 128]],
+        [[  1,  95, 247, 253, 212, 183, 255, 255, 128, 128, 128],
+         [239,  90, 244, 250, 211, 209, 255, 255, 128, 128, 128],
+         [155,  77, 195, 248, 188, 195, 255, 255, 128, 128, 128]],
+        [[  1,  24, 239, 251, 218, 219, 255, 205, 128, 128, 128],
+         [201,  51, 219, 255, 196, 186, 128, 128, 128, 128, 128],
+         [ 69,  46, 190, 239, 201, 218, 255, 228, 128, 128, 128]],
+        [[  1, 191, 251, 255, 255, 128, 128, 128, 128, 128, 128],
+         [223, 165, 249, 255, 213, 255, 128, 128, 128, 128, 128],
+         [141, 124, 248, 255, 255, 128, 128, 128, 128, 128, 128]],
+        [[  1,  16, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+         [190,  36, 230, 255, 236, 255, 128, 128, 128, 128, 128],
+         [149,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128]],
+        [[  1, 226, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [247, 192, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [240, 128, 255, 128, 128, 128, 128, 128, 128, 128, 128]],
+        [[  1, 134, 252, 255, 255, 128, 128, 128, 128, 128, 128],
+         [213,  62, 250, 255, 255, 128, 128, 128, 128, 128, 128],
+         [ 55,  93, 255, 128, 128, 128, 128, 128, 128, 128, 128]],
+        [[128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+         [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+         [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128]],
+    ],
+    [
+        [[202,  24, 213, 235, 186, 191, 220, 160, 240, 175, 255],
+         [126,  38, 182, 232, 169, 184, 228, 174, 255, 187, 128],
+         [ 61,  46, 138, 219, 151, 178, 240, 170, 255, 216, 128]],
+        [[  1, 112, 230, 250, 199, 191, 247, 159, 255, 255, 128],
+         [166, 109, 228, 252, 211, 215, 255, 174, 128, 128, 128],
+         [ 39,  77, 162, 232, 172, 180, 245, 178, 255, 255, 128]],
+        [[  1,  52, 220, 246, 198, 199, 249, 220, 255, 255, 128],
+         [124,  74, 191, 243, 183, 193, 250, 221, 255, 255, 128],
+         [ 24,  71, 130, 219, 154, 170, 243, 182, 255, 255, 128]],
+        [[  1, 182, 225, 249, 219, 240, 255, 224, 128, 128, 128],
+         [149, 150, 226, 252, 216, 205, 255, 171, 128, 128, 128],
+         [ 28, 108, 170, 242, 183, 194, 254, 223, 255, 255, 128]],
+        [[  1,  81, 230, 252, 204, 203, 255, 192, 128, 128, 128],
+         [123, 102, 209, 247, 188, 196, 255, 233, 128, 128, 128],
+         [ 20,  95, 153, 243, 164, 173, 255, 203, 128, 128, 128]],
+        [[  1, 222, 248, 255, 216, 213, 128, 128, 128, 128, 128],
+         [168, 175, 246, 252, 235, 205, 255, 255, 128, 128, 128],
+         [ 47, 116, 215, 255, 211, 212, 255, 255, 128, 128, 128]],
+        [[  1, 121, 236, 253, 212, 214, 255, 255, 128, 128, 128],
+         [141,  84, 213, 252, 201, 202, 255, 219, 128, 128, 128],
+         [ 42,  80, 160, 240, 162, 185, 255, 205, 128, 128, 128]],
+        [[  1,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [244,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+         [238,   1, 255, 128, 128, 128, 128, 128, 128, 128, 128]],
+    ],
+];
+
+// Probabilities of the per-frame flags that would replace `COEFF_PROBS`
+// (RFC 6386 section 13.4); every flag is coded as "keep".
+#[rustfmt::skip]
+const COEFF_UPDATE_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[176, 246, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [223, 241, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+         [249, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 244, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+         [234, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 246, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [239, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [251, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [251, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 253, 255, 254, 255, 255, 255, 255, 255, 255],
+         [250, 255, 254, 255, 254, 255, 255, 255, 255, 255, 255],
+         [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+    ],
+    [
+        [[217, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [225, 252, 241, 253, 255, 255, 254, 255, 255, 255, 255],
+         [234, 250, 241, 250, 253, 255, 253, 254, 255, 255, 255]],
+        [[255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [223, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [238, 253, 254, 254, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [249, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 253, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [247, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [252, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+    ],
+    [
+        [[186, 251, 250, 255, 255, 255, 255, 255, 255, 255, 255],
+         [234, 251, 244, 254, 255, 255, 255, 255, 255, 255, 255],
+         [251, 251, 243, 253, 254, 255, 254, 255, 255, 255, 255]],
+        [[255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [236, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [251, 253, 253, 254, 254, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+    ],
+    [
+        [[248, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [250, 254, 252, 254, 255, 255, 255, 255, 255, 255, 255],
+         [248, 254, 249, 253, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [246, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [252, 254, 251, 254, 254, 255, 255, 255, 255, 255, 255]],
+        [[255, 254, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+         [248, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [253, 255, 254, 254, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [245, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [253, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 251, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [252, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 252, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [249, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+         [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+        [[255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+         [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255]],
+    ],
+];
+// Probabilities of the subblock mode tree by the modes of the subblocks above
+// and to the left (RFC 6386 section 11.5).
+#[rustfmt::skip]
+const SUBBLOCK_MODE_PROBS: [[[u8; 9]; 10]; 10] = [
+    [
+        [231, 120,  48,  89, 115, 113, 120, 152, 112],
+        [152, 179,  64, 126, 170, 118,  46,  70,  95],
+        [175,  69, 143,  80,  85,  82,  72, 155, 103],
+        [ 56,  58,  10, 171, 218, 189,  17,  13, 152],
+        [144,  71,  10,  38, 171, 213, 144,  34,  26],
+        [114,  26,  17, 163,  44, 195,  21,  10, 173],
+        [121,  24,  80, 195,  26,  62,  44,  64,  85],
+        [170,  46,  55,  19, 136, 160,  33, 206,  71],
+        [ 63,  20,   8, 114, 114, 208,  12,   9, 226],
+        [ 81,  40,  11,  96, 182,  84,  29,  16,  36],
+    ],
+    [
+        [134, 183,  89, 137,  98, 101, 106, 165, 148],
+        [ 72, 187, 100, 130, 157, 111,  32,  75,  80],
+        [ 66, 102, 167,  99,  74,  62,  40, 234, 128],
+        [ 41,  53,   9, 178, 241, 141,  26,   8, 107],
+        [104,  79,  12,  27, 217, 255,  87,  17,   7],
+        [ 74,  43,  26, 146,  73, 166,  49,  23, 157],
+        [ 65,  38, 105, 160,  51,  52,  31, 115, 128],
+        [ 87,  68,  71,  44, 114,  51,  15, 186,  23],
+        [ 47,  41,  14, 110, 182, 183,  21,  17, 194],
+        [ 66,  45,  25, 102, 197, 189,  23,  18,  22],
+    ],
+    [
+        [ 88,  88, 147, 150,  42,  46,  45, 196, 205],
+        [ 43,  97, 183, 117,  85,  38,  35, 179,  61],
+        [ 39,  53, 200,  87,  26,  21,  43, 232, 171],
+        [ 56,  34,  51, 104, 114, 102,  29,  93,  77],
+        [107,  54,  32,  26,  51,   1,  81,  43,  31],
+        [ 39,  28,  85, 171,  58, 165,  90,  98,  64],
+        [ 34,  22, 116, 206,  23,  34,  43, 166,  73],
+        [ 68,  25, 106,  22,  64, 171,  36, 225, 114],
+        [ 34,  19,  21, 102, 132, 188,  16,  76, 124],
+        [ 62,  18,  78,  95,  85,  57,  50,  48,  51],
+    ],
+    [
+        [193, 101,  35, 159, 215, 111,  89,  46, 111],
+        [ 60, 148,  31, 172, 219, 228,  21,  18, 111],
+        [112, 113,  77,  85, 179, 255,  38, 120, 114],
+        [ 40,  42,   1, 196, 245, 209,  10,  25, 109],
+        [100,  80,   8,  43, 154,   1,  51,  26,  71],
+        [ 88,  43,  29, 140, 166, 213,  37,  43, 154],
+        [ 61,  63,  30, 155,  67,  45,  68,   1, 209],
+        [142,  78,  78,  16, 255, 128,  34, 197, 171],
+        [ 41,  40,   5, 102, 211, 183,   4,   1, 221],
+        [ 51,  50,  17, 168, 209, 192,  23,  25,  82],
+    ],
+    [
+        [125,  98,  42,  88, 104,  85, 117, 175,  82],
+        [ 95,  84,  53,  89, 128, 100, 113, 101,  45],
+        [ 75,  79, 123,  47,  51, 128,  81, 171,   1],
+        [ 57,  17,   5,  71, 102,  57,  53,  41,  49],
+        [115,  21,   2,  10, 102, 255, 166,  23,   6],
+        [ 38,  33,  13, 121,  57,  73,  26,   1,  85],
+        [ 41,  10,  67, 138,  77, 110,  90,  47, 114],
+        [101,  29,  16,  10,  85, 128, 101, 196,  26],
+        [ 57,  18,  10, 102, 102, 213,  34,  20,  43],
+        [117,  20,  15,  36, 163, 128,  68,   1,  26],
+    ],
+    [
+        [138,  31,  36, 171,  27, 166,  38,  44, 229],
+        [ 67,  87,  58, 169,  82, 115,  26,  59, 179],
+        [ 63,  59,  90, 180,  59, 166,  93,  73, 154],
+        [ 40,  40,  21, 116, 143, 209,  34,  39, 175],
+        [ 57,  46,  22,  24, 128,   1,  54,  17,  37],
+        [ 47,  15,  16, 183,  34, 223,  49,  45, 183],
+        [ 46,  17,  33, 183,   6,  98,  15,  32, 183],
+        [ 65,  32,  73, 115,  28, 128,  23, 128, 205],
+        [ 40,   3,   9, 115,  51, 192,  18,   6, 223],
+        [ 87,  37,   9, 115,  59,  77,  64,  21,  47],
+    ],
+    [
+        [104,  55,  44, 218,   9,  54,  53, 130, 226],
+        [ 64,  90,  70, 205,  40,  41,  23,  26,  57],
+        [ 54,  57, 112, 184,   5,  41,  38, 166, 213],
+        [ 30,  34,  26, 133, 152, 116,  10,  32, 134],
+        [ 75,  32,  12,  51, 192, 255, 160,  43,  51],
+        [ 39,  19,  53, 221,  26, 114,  32,  73, 255],
+        [ 31,   9,  65, 234,   2,  15,   1, 118,  73],
+        [ 88,  31,  35,  67, 102,  85,  55, 186,  85],
+        [ 56,  21,  23, 111,  59, 205,  45,  37, 192],
+        [ 55,  38,  70, 124,  73, 102,   1,  34,  98],
+    ],
+    [
+        [102,  61,  71,  37,  34,  53,  31, 243, 192],
+        [ 69,  60,  71,  38,  73, 119,  28, 222,  37],
+        [ 68,  45, 128,  34,   1,  47,  11, 245, 171],
+        [ 62,  17,  19,  70, 146,  85,  55,  62,  70],
+        [ 75,  15,   9,   9,  64, 255, 184, 119,  16],
+        [ 37,  43,  37, 154, 100, 163,  85, 160,   1],
+        [ 63,   9,  92, 136,  28,  64,  32, 201,  85],
+        [ 86,   6,  28,   5,  64, 255,  25, 248,   1],
+        [ 56,   8,  17, 132, 137, 255,  55, 116, 128],
+        [ 58,  15,  20,  82, 135,  57,  26, 121,  40],
+    ],
+    [
+        [164,  50,  31, 137, 154, 133,  25,  35, 218],
+        [ 51, 103,  44, 131, 131, 123,  31,   6, 158],
+        [ 86,  40,  64, 135, 148, 224,  45, 183, 128],
+        [ 22,  26,  17, 131, 240, 154,  14,   1, 209],
+        [ 83,  12,  13,  54, 192, 255,  68,  47,  28],
+        [ 45,  16,  21,  91,  64, 222,   7,   1, 197],
+        [ 56,  21,  39, 155,  60, 138,  23, 102, 213],
+        [ 85,  26,  85,  85, 128, 128,  32, 146, 171],
+        [ 18,  11,   7,  63, 144, 171,   4,   4, 246],
+        [ 35,  27,  10, 146, 174, 171,  12,  26, 128],
+    ],
+    [
+        [190,  80,  35,  99, 180,  80, 126,  54,  45],
+        [ 85, 126,  47,  87, 176,  51,  41,  20,  32],
+        [101,  75, 128, 139, 118, 146, 116, 128,  85],
+        [ 56,  41,  15, 176, 236,  85,  37,   9,  62],
+        [146,  36,  19,  30, 171, 255,  97,  27,  20],
+        [ 71,  30,  17, 119, 118, 255,  17,  18, 138],
+        [101,  38,  60, 138,  55,  70,  43,  26, 142],
+        [138,  45,  61,  62, 219,   1,  81, 188,  64],
+        [ 32,  41,  20, 117, 151, 142,  20,  21, 163],
+        [112,  19,  12,  61, 195, 128,  48,   4,  24],
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image_webp::WebPDecoder;
+
+    fn decode(webp: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut decoder = WebPDecoder::new(std::io::Cursor::new(webp)).unwrap();
+        let (width, height) = decoder.dimensions();
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        (width, height, pixels)
+    }
+
+    // Mean absolute difference of the colour channels of RGBA and RGB pixels.
+    fn mean_error(rgba: &[u8], rgb: &[u8]) -> f32 {
+        let diff: u32 = rgba
+            .chunks_exact(4)
+            .zip(rgb.chunks_exact(3))
+            .flat_map(|(a, b)| (0..3).map(|c| a[c].abs_diff(b[c]) as u32))
+            .sum();
+        diff as f32 / rgb.len() as f32
+    }
+
+    #[test]
+    fn test_encode_webp() {
+        // A page with a shading gradient, lines of text and a red stamp,
+        // sized so the last macroblocks are partly outside the image
+        let (width, height) = (75, 50);
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                let paper = (200 + x / 3) as u8;
+                if y % 12 < 3 && (5..60).contains(&x) && x % 7 != 0 {
+                    [30, 30, 40, 255]
+                } else if (x as i32 - 60).pow(2) + (y as i32 - 35).pow(2) < 80 {
+                    [200, 40, 40, 255]
+                } else {
+                    [paper, paper, paper - 10, 255]
+                }
+            })
+            .collect();
+
+        let webp = encode_webp(&rgba, width, height, 0, false).unwrap();
+        assert_eq!((&webp[..4], &webp[8..16]), (&b"RIFF"[..], &b"WEBPVP8 "[..]));
+        let (w, h, rgb) = decode(&webp);
+        assert_eq!((w, h), (75, 50));
+        let error = mean_error(&rgba, &rgb);
+        assert!(error < 6.0, "{error}");
+
+        // Higher quality: larger and closer to the original
+        let fine = encode_webp(&rgba, width, height, 98, false).unwrap();
+        let coarse = encode_webp(&rgba, width, height, 20, false).unwrap();
+        assert!(coarse.len() < webp.len() && webp.len() < fine.len());
+        assert!(mean_error(&rgba, &decode(&fine).2) < error);
+
+        let lossless = encode_webp(&rgba, width, height, 0, true).unwrap();
+        assert_eq!(&lossless[12..16], b"VP8L");
+        assert_eq!(decode(&lossless).2, rgba);
+
+        assert!(encode_webp(&rgba, width, height, 101, false).is_err());
+        assert!(encode_webp(&rgba[4..], width, height, 80, false).is_err());
+        assert!(encode_webp(&[], 0, 0, 80, false).is_err());
+    }
+}